@@ -0,0 +1,82 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use space_api_rs::services::bench_service::{
+    bench_prefix, decode, encode, resize, sample_image, BENCH_FORMATS, BENCH_SIZES,
+};
+use space_api_rs::utils::cache;
+use std::hint::black_box;
+
+fn bench_encode_decode(c: &mut Criterion) {
+    let mut encode_group = c.benchmark_group("encode");
+    for &size in BENCH_SIZES {
+        let img = sample_image(size);
+        encode_group.throughput(Throughput::Elements((size * size) as u64));
+        for &format in BENCH_FORMATS {
+            let ext = format.extensions_str()[0];
+            encode_group.bench_with_input(BenchmarkId::new(ext, size), &img, |b, img| {
+                b.iter(|| encode(black_box(img), format).unwrap())
+            });
+        }
+    }
+    encode_group.finish();
+
+    let mut decode_group = c.benchmark_group("decode");
+    for &size in BENCH_SIZES {
+        let img = sample_image(size);
+        decode_group.throughput(Throughput::Elements((size * size) as u64));
+        for &format in BENCH_FORMATS.iter().filter(|f| f.reading_enabled()) {
+            let ext = format.extensions_str()[0];
+            let bytes = encode(&img, format).unwrap();
+            decode_group.bench_with_input(BenchmarkId::new(ext, size), &bytes, |b, bytes| {
+                b.iter(|| decode(black_box(bytes)).unwrap())
+            });
+        }
+    }
+    decode_group.finish();
+}
+
+fn bench_resize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resize");
+    for &size in BENCH_SIZES {
+        let img = sample_image(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &img, |b, img| {
+            b.iter(|| resize(black_box(img)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_cache_ops(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let backend = cache::backend();
+    let prefix = bench_prefix();
+    let value = vec![0u8; 4096];
+
+    let mut group = c.benchmark_group("cache");
+    group.throughput(Throughput::Elements(1));
+
+    let mut i = 0u64;
+    group.bench_function("put_4k", |b| {
+        b.iter(|| {
+            i += 1;
+            rt.block_on(backend.put(&format!("{}{}", prefix, i % 10_000), value.clone(), None));
+        })
+    });
+
+    rt.block_on(async {
+        for i in 0..10_000u64 {
+            backend.put(&format!("{}{}", prefix, i), value.clone(), None).await;
+        }
+    });
+    let mut j = 0u64;
+    group.bench_function("get_4k", |b| {
+        b.iter(|| {
+            j += 1;
+            black_box(rt.block_on(backend.get(&format!("{}{}", prefix, j % 10_000))))
+        })
+    });
+    group.finish();
+    rt.block_on(backend.remove_prefix(&prefix));
+}
+
+criterion_group!(benches, bench_encode_decode, bench_resize, bench_cache_ops);
+criterion_main!(benches);
@@ -1,5 +1,6 @@
 use config::{Config as ConfigLoader, Environment, File};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,6 +10,72 @@ pub struct Config {
     pub oauth: OAuthConfig,
     #[serde(default)]
     pub memory: MemoryConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    #[serde(default)]
+    pub abuse: AbuseConfig,
+    #[serde(default)]
+    pub robots: RobotsConfig,
+    #[serde(default)]
+    pub service_worker: ServiceWorkerConfig,
+    #[serde(default)]
+    pub static_files: StaticFilesConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub mock_upstreams: MockUpstreamsConfig,
+    #[serde(default)]
+    pub signed_urls: SignedUrlConfig,
+    #[serde(default)]
+    pub og_image: OgImageConfig,
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    #[serde(default)]
+    pub spam: SpamConfig,
+    #[serde(default)]
+    pub rpc: RpcConfig,
+    #[serde(default)]
+    pub outbox: OutboxConfig,
+    #[serde(default)]
+    pub cdn: CdnConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub github_links: GithubLinksConfig,
+    #[serde(default)]
+    pub inbound_email: InboundEmailConfig,
+    #[serde(default)]
+    pub upstreams: UpstreamsConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub upstream_fixtures: UpstreamFixturesConfig,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    pub wallpaper_pregen: WallpaperPregenConfig,
+    #[serde(default)]
+    pub cache_warmup: CacheWarmupConfig,
+    #[serde(default)]
+    pub transcode: TranscodeConfig,
+    #[serde(default)]
+    pub wallpapers: WallpapersConfig,
+    #[serde(default)]
+    pub avatar: AvatarConfig,
+    #[serde(default)]
+    pub requests: RequestsConfig,
+    #[serde(default)]
+    pub features: FeaturesConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub image_proxy: ImageProxyConfig,
+    #[serde(default)]
+    pub weather: WeatherConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,20 +130,1455 @@ impl Default for MemoryConfig {
     }
 }
 
-fn default_memory_threshold() -> u64 {
-    500
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// 敏感字段加密密钥（第一个为当前写入密钥，其余仅用于解密旧数据，实现密钥轮换）
+    #[serde(default)]
+    pub encryption_keys: Vec<EncryptionKeyConfig>,
+    /// 验证码 / 临时代码哈希使用的 pepper（HMAC-SHA256 密钥）
+    #[serde(default)]
+    pub code_pepper: Option<String>,
 }
 
-fn default_check_interval() -> u64 {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionKeyConfig {
+    /// 密钥 ID（写入密文，用于轮换时定位解密密钥）
+    pub id: String,
+    /// Base64 编码的 32 字节 AES-256 密钥
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// 管理接口访问令牌（通过 Authorization: Bearer <token> 传递，未配置则禁用所有管理接口）
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxConfig {
+    /// 后台投递任务的轮询间隔（秒）
+    #[serde(default = "default_outbox_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// 最大投递次数，超过后标记为 failed 不再重试
+    #[serde(default = "default_outbox_max_attempts")]
+    pub max_attempts: u32,
+    /// 接收事件通知邮件的地址（为空则不发送）
+    #[serde(default)]
+    pub notify_email: Option<String>,
+    /// 需要发送通知邮件的事件
+    #[serde(default)]
+    pub notify_events: Vec<String>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// 订阅的事件，为空表示全部
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// 签名密钥（可选），设置后请求带 X-Space-Signature: sha256=<HMAC-SHA256(body)>
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_outbox_poll_interval(),
+            max_attempts: default_outbox_max_attempts(),
+            notify_email: None,
+            notify_events: Vec::new(),
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+fn default_outbox_poll_interval() -> u64 {
+    10
+}
+
+fn default_outbox_max_attempts() -> u32 {
+    8
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdnConfig {
+    /// CDN 服务商：none / cloudflare / edgeone（腾讯云 EdgeOne）
+    #[serde(default = "default_cdn_provider")]
+    pub provider: String,
+    /// 对外访问的站点地址（如 https://api.example.com），站内路径按此拼接为完整 URL
+    #[serde(default)]
+    pub public_base_url: String,
+    /// 站点（Zone）ID
+    #[serde(default)]
+    pub zone_id: String,
+    /// Cloudflare API 令牌（需要 Cache Purge 权限）
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// 腾讯云 API 密钥
+    #[serde(default)]
+    pub secret_id: Option<String>,
+    #[serde(default)]
+    pub secret_key: Option<String>,
+    /// 启动时刷新的路径（sw.js 等内容随部署变化的文件）
+    #[serde(default = "default_cdn_purge_on_startup")]
+    pub purge_on_startup: Vec<String>,
+}
+
+impl Default for CdnConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_cdn_provider(),
+            public_base_url: String::new(),
+            zone_id: String::new(),
+            api_token: None,
+            secret_id: None,
+            secret_key: None,
+            purge_on_startup: default_cdn_purge_on_startup(),
+        }
+    }
+}
+
+fn default_cdn_provider() -> String {
+    "none".to_string()
+}
+
+fn default_cdn_purge_on_startup() -> Vec<String> {
+    vec!["/sw.js".to_string()]
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// 外部事件来源，按 name 对应 POST /hooks/<name>
+    #[serde(default)]
+    pub sources: Vec<HookSourceConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubLinksConfig {
+    /// 接收友链申请的仓库（owner/name），为空表示不启用
+    #[serde(default)]
+    pub repo: String,
+    /// 友链申请 issue / PR 的标签
+    #[serde(default = "default_github_links_label")]
+    pub label: String,
+    /// GitHub 令牌（可选），用于提高 API 限额以及回复、关闭已处理的 issue
+    #[serde(default)]
+    pub token: Option<String>,
+    /// 轮询间隔（秒），0 表示只通过 webhook 接收
+    #[serde(default = "default_github_links_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// 处理后是否关闭 issue（需要 token）
+    #[serde(default = "default_github_links_close_issues")]
+    pub close_issues: bool,
+}
+
+impl Default for GithubLinksConfig {
+    fn default() -> Self {
+        Self {
+            repo: String::new(),
+            label: default_github_links_label(),
+            token: None,
+            poll_interval_secs: default_github_links_poll_interval(),
+            close_issues: true,
+        }
+    }
+}
+
+fn default_github_links_label() -> String {
+    "friend-link".to_string()
+}
+
+fn default_github_links_poll_interval() -> u64 {
+    600
+}
+
+fn default_github_links_close_issues() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundEmailConfig {
+    /// IMAP 服务器（TLS），为空表示不启用
+    #[serde(default)]
+    pub server: String,
+    #[serde(default = "default_inbound_email_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_inbound_email_mailbox")]
+    pub mailbox: String,
+    /// 轮询间隔（秒）
+    #[serde(default = "default_inbound_email_poll_interval")]
+    pub poll_interval_secs: u64,
+    /// 友链申请邮件的主题前缀
+    #[serde(default = "default_inbound_email_subject_prefix")]
+    pub subject_prefix: String,
+}
+
+impl Default for InboundEmailConfig {
+    fn default() -> Self {
+        Self {
+            server: String::new(),
+            port: default_inbound_email_port(),
+            username: String::new(),
+            password: String::new(),
+            mailbox: default_inbound_email_mailbox(),
+            poll_interval_secs: default_inbound_email_poll_interval(),
+            subject_prefix: default_inbound_email_subject_prefix(),
+        }
+    }
+}
+
+fn default_inbound_email_port() -> u16 {
+    993
+}
+
+fn default_inbound_email_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+fn default_inbound_email_poll_interval() -> u64 {
+    300
+}
+
+fn default_inbound_email_subject_prefix() -> String {
+    "[友链]".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamsConfig {
+    /// 固定解析的上游域名
+    #[serde(default)]
+    pub hosts: Vec<UpstreamHostConfig>,
+    /// 健康探测间隔（秒）
+    #[serde(default = "default_upstream_probe_interval")]
+    pub probe_interval_secs: u64,
+    /// 单次探测的连接超时（毫秒）
+    #[serde(default = "default_upstream_probe_timeout")]
+    pub probe_timeout_ms: u64,
+    /// 出站代理，按顺序匹配
+    #[serde(default)]
+    pub proxies: Vec<UpstreamProxyConfig>,
+    /// 出站连接的地址族：auto、prefer_ipv4、prefer_ipv6、ipv4（仅 IPv4）、ipv6（仅 IPv6）
+    #[serde(default = "default_upstream_ip_family")]
+    pub ip_family: String,
+    /// 上游请求的默认总超时（秒），各服务可按请求单独设置更短的超时
+    #[serde(default = "default_upstream_timeout")]
+    pub timeout_secs: u64,
+    /// 建立连接的超时（毫秒）
+    #[serde(default = "default_upstream_connect_timeout")]
+    pub connect_timeout_ms: u64,
+    /// 上游请求的默认 User-Agent（个别上游会单独设置）
+    #[serde(default = "default_upstream_user_agent")]
+    pub user_agent: String,
+}
+
+impl Default for UpstreamsConfig {
+    fn default() -> Self {
+        Self {
+            hosts: Vec::new(),
+            probe_interval_secs: default_upstream_probe_interval(),
+            probe_timeout_ms: default_upstream_probe_timeout(),
+            proxies: Vec::new(),
+            ip_family: default_upstream_ip_family(),
+            timeout_secs: default_upstream_timeout(),
+            connect_timeout_ms: default_upstream_connect_timeout(),
+            user_agent: default_upstream_user_agent(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamHostConfig {
+    pub host: String,
+    /// 固定的 IP 地址，按顺序优先使用健康的地址（为空时使用系统解析）
+    #[serde(default)]
+    pub ips: Vec<String>,
+    /// 健康探测连接的端口
+    #[serde(default = "default_upstream_probe_port")]
+    pub probe_port: u16,
+    /// 覆盖该域名的地址族（取值同 upstreams.ip_family）
+    #[serde(default)]
+    pub ip_family: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamProxyConfig {
+    /// 代理地址：http://、https://、socks5://、socks5h://（由代理解析域名）
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 经此代理访问的域名（含子域名），为空表示全部
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// 不经此代理的域名（含子域名）
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+fn default_upstream_probe_interval() -> u64 {
     30
 }
 
-fn default_gc_cooldown() -> u64 {
+fn default_upstream_probe_timeout() -> u64 {
+    2000
+}
+
+fn default_upstream_probe_port() -> u16 {
+    443
+}
+
+fn default_upstream_ip_family() -> String {
+    "auto".to_string()
+}
+
+fn default_upstream_timeout() -> u64 {
+    30
+}
+
+fn default_upstream_connect_timeout() -> u64 {
+    5000
+}
+
+fn default_upstream_user_agent() -> String {
+    concat!("space-api/", env!("CARGO_PKG_VERSION")).to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// 字节值缓存后端：memory（进程内）或 redis（多实例共享）
+    #[serde(default = "default_cache_backend")]
+    pub backend: String,
+    /// Redis 地址：redis://[:密码@]主机[:端口][/库编号]
+    #[serde(default)]
+    pub redis_url: String,
+    /// Redis 键前缀（多个服务共用一个 Redis 时区分）
+    #[serde(default = "default_cache_key_prefix")]
+    pub key_prefix: String,
+    /// 内存和 Redis 缓存项的默认过期时间（秒），命名空间未配置 ttl_secs 时使用
+    #[serde(default = "default_cache_ttl")]
+    pub ttl_secs: u64,
+    /// 硬盘缓存的默认过期时间（秒）
+    #[serde(default = "default_cache_disk_ttl")]
+    pub disk_ttl_secs: u64,
+    /// 硬盘缓存总大小上限（MB），超出时由清理任务删除最旧的文件
+    #[serde(default)]
+    pub disk_max_size_mb: Option<u64>,
+    /// 硬盘缓存文件数上限
+    #[serde(default)]
+    pub disk_max_files: Option<u64>,
+    /// 不小于该大小（KB）的硬盘缓存项使用 zstd 压缩存储，未设置则不压缩
+    #[serde(default)]
+    pub disk_compress_min_kb: Option<u64>,
+    /// 硬盘缓存的 zstd 压缩等级（1-19，越高压缩率越高、越慢）
+    #[serde(default = "default_disk_compress_level")]
+    pub disk_compress_level: i32,
+    /// 壁纸（按格式编码后的图片和拼图）
+    #[serde(default)]
+    pub wallpapers: CacheNamespaceConfig,
+    /// 头像（原图和转码结果）
+    #[serde(default)]
+    pub avatars: CacheNamespaceConfig,
+    /// sw.js 及其预压缩版本
+    #[serde(default)]
+    pub sw_js: CacheNamespaceConfig,
+    /// 网易云音乐播放状态
+    #[serde(default)]
+    pub ncm_status: CacheNamespaceConfig,
+    /// 徽章 SVG、codetime 统计等小片段
+    #[serde(default)]
+    pub snippets: CacheNamespaceConfig,
+    /// 友链头像元数据等
+    #[serde(default)]
+    pub metadata: CacheNamespaceConfig,
+}
+
+/// 单个缓存命名空间的过期时间和容量，未配置的项使用全局默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheNamespaceConfig {
+    /// 过期时间（秒），同时用于内存、硬盘和 Redis 缓存
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// 容量上限（MB），内存和硬盘分别计算；Redis 不限制
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_cache_backend(),
+            redis_url: String::new(),
+            key_prefix: default_cache_key_prefix(),
+            ttl_secs: default_cache_ttl(),
+            disk_ttl_secs: default_cache_disk_ttl(),
+            disk_max_size_mb: None,
+            disk_max_files: None,
+            disk_compress_min_kb: None,
+            disk_compress_level: default_disk_compress_level(),
+            wallpapers: CacheNamespaceConfig::default(),
+            avatars: CacheNamespaceConfig::default(),
+            sw_js: CacheNamespaceConfig::default(),
+            ncm_status: CacheNamespaceConfig::default(),
+            snippets: CacheNamespaceConfig::default(),
+            metadata: CacheNamespaceConfig::default(),
+        }
+    }
+}
+
+fn default_cache_backend() -> String {
+    "memory".to_string()
+}
+
+fn default_cache_key_prefix() -> String {
+    "space-api:".to_string()
+}
+
+fn default_cache_ttl() -> u64 {
+    12 * 60 * 60
+}
+
+fn default_cache_disk_ttl() -> u64 {
     30
 }
 
+fn default_disk_compress_level() -> i32 {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookSourceConfig {
+    /// 来源名称（URL 路径的一部分）
+    pub name: String,
+    /// 签名格式：github（X-Hub-Signature-256）/ mix-space（X-Webhook-Signature256）
+    pub kind: String,
+    /// 签名密钥
+    pub secret: String,
+    /// 处理的事件（GitHub 的 X-GitHub-Event，Mix Space 的事件类型），为空表示全部
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// 收到事件后执行的动作：purge_sw（刷新 sw.js 缓存）/ purge_cdn（刷新全站 CDN 缓存）/ notify（发送 hook.received 通知）/ ingest_link（导入 GitHub 友链申请）
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RpcConfig {
+    /// 是否启用 /rpc（JSON-RPC 2.0，供博客后端、机器人等内部服务调用）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 访问令牌（通过 Authorization: Bearer <token> 传递），未配置时不校验，仅适合内网部署
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IpFilterConfig {
+    /// 是否启用 IP 过滤
+    #[serde(default)]
+    pub enabled: bool,
+    /// 是否信任代理头（CF-Connecting-IP / X-Forwarded-For / X-Real-IP）获取客户端 IP
+    /// 仅在服务部署于可信反向代理之后时开启，否则客户端可伪造来源 IP
+    #[serde(default)]
+    pub trust_proxy_headers: bool,
+    /// 全局白名单（CIDR 或单个 IP，为空则不限制）
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// 全局黑名单（CIDR 或单个 IP）
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// 按路由分组的规则
+    #[serde(default)]
+    pub groups: Vec<IpFilterGroupConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpFilterGroupConfig {
+    /// 分组名称（用于日志）
+    pub name: String,
+    /// 匹配的路径前缀，如 "/api/admin"
+    pub path_prefixes: Vec<String>,
+    /// 分组白名单（为空则不限制）
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// 分组黑名单
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbuseConfig {
+    /// 是否启用滥用检测与自动临时封禁
+    #[serde(default)]
+    pub enabled: bool,
+    /// 图片缓存未命中阈值（单个 IP 在窗口内超过此值触发封禁）
+    #[serde(default = "default_image_miss_threshold")]
+    pub image_miss_threshold: u32,
+    /// 图片缓存未命中统计窗口（秒）
+    #[serde(default = "default_image_miss_window")]
+    pub image_miss_window_secs: u64,
+    /// 验证码校验失败阈值
+    #[serde(default = "default_verify_failure_threshold")]
+    pub verify_failure_threshold: u32,
+    /// 验证码校验失败统计窗口（秒）
+    #[serde(default = "default_verify_failure_window")]
+    pub verify_failure_window_secs: u64,
+    /// 首次封禁时长（秒），之后每次违规翻倍
+    #[serde(default = "default_base_ban")]
+    pub base_ban_secs: u64,
+    /// 最长封禁时长（秒）
+    #[serde(default = "default_max_ban")]
+    pub max_ban_secs: u64,
+    /// 违规记录保留时长（秒），超过后封禁时长重新从 base_ban_secs 开始计算
+    #[serde(default = "default_offense_memory")]
+    pub offense_memory_secs: u64,
+}
+
+impl Default for AbuseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            image_miss_threshold: default_image_miss_threshold(),
+            image_miss_window_secs: default_image_miss_window(),
+            verify_failure_threshold: default_verify_failure_threshold(),
+            verify_failure_window_secs: default_verify_failure_window(),
+            base_ban_secs: default_base_ban(),
+            max_ban_secs: default_max_ban(),
+            offense_memory_secs: default_offense_memory(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotsConfig {
+    /// robots.txt 规则分组
+    #[serde(default = "default_robots_rules")]
+    pub rules: Vec<RobotsRuleConfig>,
+    /// 可选的 Sitemap 地址
+    #[serde(default)]
+    pub sitemap: Option<String>,
+    /// X-Robots-Tag 头的值（为空则不添加）
+    #[serde(default = "default_x_robots_tag")]
+    pub x_robots_tag: String,
+    /// 添加 X-Robots-Tag 的路径前缀（"/" 表示所有路径）
+    #[serde(default = "default_noindex_paths")]
+    pub noindex_paths: Vec<String>,
+}
+
+impl Default for RobotsConfig {
+    fn default() -> Self {
+        Self {
+            rules: default_robots_rules(),
+            sitemap: None,
+            x_robots_tag: default_x_robots_tag(),
+            noindex_paths: default_noindex_paths(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RobotsRuleConfig {
+    #[serde(default = "default_robots_user_agent")]
+    pub user_agent: String,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub disallow: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceWorkerConfig {
+    /// 安装阶段预缓存的 URL 列表
+    #[serde(default)]
+    pub precache: Vec<String>,
+    /// 运行时缓存规则（按顺序匹配，第一条命中的规则生效）
+    #[serde(default = "default_sw_runtime_rules")]
+    pub runtime_rules: Vec<ServiceWorkerRuleConfig>,
+}
+
+impl Default for ServiceWorkerConfig {
+    fn default() -> Self {
+        Self {
+            precache: Vec::new(),
+            runtime_rules: default_sw_runtime_rules(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceWorkerRuleConfig {
+    /// 匹配请求 URL 的正则表达式（JavaScript RegExp 语法）
+    pub pattern: String,
+    /// 缓存策略：cache-first / network-first / stale-while-revalidate / network-only
+    pub strategy: String,
+    /// 缓存名称（实际名称会追加版本号）
+    pub cache_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticFilesConfig {
+    /// 静态文件目录（仪表盘 JS/CSS、默认图片等）
+    #[serde(default = "default_static_dir")]
+    pub dir: String,
+    /// 未带内容哈希的文件的缓存时长（秒），带哈希的文件始终为一年 + immutable
+    #[serde(default = "default_static_max_age")]
+    pub max_age_secs: u64,
+    /// 按模板预加载的资源（模板名 -> 资源列表），渲染页面时以 Link 响应头发出
+    #[serde(default = "default_static_preload")]
+    pub preload: HashMap<String, Vec<String>>,
+}
+
+impl Default for StaticFilesConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_static_dir(),
+            max_age_secs: default_static_max_age(),
+            preload: default_static_preload(),
+        }
+    }
+}
+
+fn default_static_dir() -> String {
+    "src/static".to_string()
+}
+
+fn default_static_max_age() -> u64 {
+    300
+}
+
+fn default_static_preload() -> HashMap<String, Vec<String>> {
+    HashMap::from([(
+        "index".to_string(),
+        vec!["dashboard.css".to_string(), "dashboard.js".to_string()],
+    )])
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// 全局日志级别（RUST_LOG 环境变量优先）
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// 按模块设置的日志级别，例如 sysinfo = "warn"
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
+    #[serde(default)]
+    pub file: LogFileConfig,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            modules: HashMap::new(),
+            file: LogFileConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFileConfig {
+    /// 是否同时输出到文件（标准输出始终保留）
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_log_dir")]
+    pub dir: String,
+    /// 文件名前缀
+    #[serde(default = "default_log_prefix")]
+    pub prefix: String,
+    /// 轮转方式：daily / hourly / size / never
+    #[serde(default = "default_log_rotation")]
+    pub rotation: String,
+    /// rotation = "size" 时单个文件的最大大小（MB）
+    #[serde(default = "default_log_max_size")]
+    pub max_size_mb: u64,
+    /// 保留的日志文件数量
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_log_dir(),
+            prefix: default_log_prefix(),
+            rotation: default_log_rotation(),
+            max_size_mb: default_log_max_size(),
+            max_files: default_log_max_files(),
+        }
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_dir() -> String {
+    "logs".to_string()
+}
+
+fn default_log_prefix() -> String {
+    "space-api".to_string()
+}
+
+fn default_log_rotation() -> String {
+    "daily".to_string()
+}
+
+fn default_log_max_size() -> u64 {
+    50
+}
+
+fn default_log_max_files() -> usize {
+    7
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamFixturesConfig {
+    /// off；record：将 NCM、codetime、QQ 用户信息的响应脱敏后写入 dir；replay：从 dir 读取，不访问上游
+    #[serde(default = "default_fixtures_mode")]
+    pub mode: String,
+    /// 快照目录
+    #[serde(default = "default_fixtures_dir")]
+    pub dir: String,
+}
+
+impl Default for UpstreamFixturesConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_fixtures_mode(),
+            dir: default_fixtures_dir(),
+        }
+    }
+}
+
+fn default_fixtures_mode() -> String {
+    "off".to_string()
+}
+
+fn default_fixtures_dir() -> String {
+    "tests/fixtures".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsConfig {
+    /// 耗时超过该值（毫秒）的请求记录上游调用耗时、缓存判定和内存压力，0 表示关闭
+    #[serde(default = "default_slow_request_ms")]
+    pub slow_request_ms: u64,
+    /// 保留的慢请求记录数（超出后淘汰最旧的）
+    #[serde(default = "default_max_slow_requests")]
+    pub max_slow_requests: usize,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            slow_request_ms: default_slow_request_ms(),
+            max_slow_requests: default_max_slow_requests(),
+        }
+    }
+}
+
+fn default_slow_request_ms() -> u64 {
+    2000
+}
+
+fn default_max_slow_requests() -> usize {
+    100
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WallpaperPregenConfig {
+    /// 启动后在后台为所有壁纸预先生成各格式的编码结果并写入硬盘缓存
+    #[serde(default)]
+    pub enabled: bool,
+    /// 预生成的格式（按顺序处理）：webp / avif / jpeg
+    #[serde(default = "default_pregen_formats")]
+    pub formats: Vec<String>,
+    /// 重新检查并补齐缺失（或已过期）变体的间隔（秒），0 表示只在启动时执行一次
+    #[serde(default = "default_pregen_interval")]
+    pub interval_secs: u64,
+}
+
+impl Default for WallpaperPregenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            formats: default_pregen_formats(),
+            interval_secs: default_pregen_interval(),
+        }
+    }
+}
+
+fn default_pregen_formats() -> Vec<String> {
+    vec!["webp".to_string(), "avif".to_string()]
+}
+
+fn default_pregen_interval() -> u64 {
+    6 * 60 * 60
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheWarmupConfig {
+    /// 启动时预先下载并缓存的头像原图地址
+    #[serde(default)]
+    pub avatars: Vec<String>,
+    /// 启动时预先编码并缓存的壁纸原图地址
+    #[serde(default)]
+    pub wallpapers: Vec<String>,
+    /// 壁纸和头像预热的格式（与按 Accept 协商出的格式对应）：avif / webp / jpeg
+    #[serde(default = "default_warmup_formats")]
+    pub formats: Vec<String>,
+}
+
+impl Default for CacheWarmupConfig {
+    fn default() -> Self {
+        Self {
+            avatars: Vec::new(),
+            wallpapers: Vec::new(),
+            formats: default_warmup_formats(),
+        }
+    }
+}
+
+fn default_warmup_formats() -> Vec<String> {
+    vec!["avif".to_string(), "webp".to_string()]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscodeConfig {
+    /// 同时进行的图片解码 / 缩放 / 编码数（默认为 CPU 核心数）
+    #[serde(default = "default_transcode_concurrency")]
+    pub max_concurrent: usize,
+    /// 请求排队等待的最长时间（毫秒），超时返回 503
+    #[serde(default = "default_transcode_queue_timeout")]
+    pub queue_timeout_ms: u64,
+    /// 503 响应的 Retry-After（秒）
+    #[serde(default = "default_transcode_retry_after")]
+    pub retry_after_secs: u64,
+    /// 动图（GIF / 动态 WebP）的处理方式：passthrough（原样返回）/ reencode（逐帧缩放后编码为 GIF）
+    #[serde(default = "default_transcode_animated")]
+    pub animated: String,
+    /// 转码时去除 EXIF / XMP / ICC 元数据（请求可用 keep_metadata=true 保留）
+    #[serde(default = "default_transcode_strip_metadata")]
+    pub strip_metadata: bool,
+}
+
+impl Default for TranscodeConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_transcode_concurrency(),
+            queue_timeout_ms: default_transcode_queue_timeout(),
+            retry_after_secs: default_transcode_retry_after(),
+            animated: default_transcode_animated(),
+            strip_metadata: default_transcode_strip_metadata(),
+        }
+    }
+}
+
+fn default_transcode_concurrency() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2)
+}
+
+fn default_transcode_queue_timeout() -> u64 {
+    3000
+}
+
+fn default_transcode_retry_after() -> u64 {
+    5
+}
+
+fn default_transcode_animated() -> String {
+    "passthrough".to_string()
+}
+
+fn default_transcode_strip_metadata() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestsConfig {
+    /// 请求的处理时限（毫秒，从收到请求开始计算），超过后不再开始下载 / 转码 / 查询，返回 504；0 表示不限制
+    #[serde(default = "default_request_deadline")]
+    pub deadline_ms: u64,
+}
+
+impl Default for RequestsConfig {
+    fn default() -> Self {
+        Self {
+            deadline_ms: default_request_deadline(),
+        }
+    }
+}
+
+fn default_request_deadline() -> u64 {
+    30000
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeaturesConfig {
+    /// 默认关闭的功能（名称见 GET /api/admin/features），管理接口的设置优先
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageProxyConfig {
+    /// /images/proxy、/images/blurhash、/images/palette 允许获取的域名（同时匹配子域名）；其他域名的图片只接受签名链接
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceConfig {
+    /// 启动时是否处于维护模式（运行时通过 PUT /api/admin/maintenance 切换，不持久化）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 维护期间返回的提示信息
+    #[serde(default = "default_maintenance_message")]
+    pub message: String,
+    /// 503 响应的 Retry-After（秒）
+    #[serde(default = "default_maintenance_retry_after")]
+    pub retry_after_secs: u64,
+    /// 维护期间仍可访问的路径前缀（管理后台、仪表盘和运行状态接口）
+    #[serde(default = "default_maintenance_allow_paths")]
+    pub allow_paths: Vec<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: default_maintenance_message(),
+            retry_after_secs: default_maintenance_retry_after(),
+            allow_paths: default_maintenance_allow_paths(),
+        }
+    }
+}
+
+fn default_maintenance_message() -> String {
+    "服务维护中，请稍后再试".to_string()
+}
+
+fn default_maintenance_retry_after() -> u64 {
+    600
+}
+
+fn default_maintenance_allow_paths() -> Vec<String> {
+    [
+        "/",
+        "/static",
+        "/admin",
+        "/api/admin",
+        "/api/dashboard",
+        "/api/metrics",
+        "/api/memory",
+        "/api/boot-report",
+        "/api/logs",
+        "/api/stats",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherConfig {
+    /// 首页天气组件的位置（纬度、经度），未配置时组件返回错误
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    /// 显示的地点名称
+    #[serde(default)]
+    pub location: String,
+    /// Open-Meteo 兼容的天气接口
+    #[serde(default = "default_weather_endpoint")]
+    pub endpoint: String,
+    /// 天气数据缓存时间（秒）
+    #[serde(default = "default_weather_max_age")]
+    pub max_age_secs: u64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            latitude: None,
+            longitude: None,
+            location: String::new(),
+            endpoint: default_weather_endpoint(),
+            max_age_secs: default_weather_max_age(),
+        }
+    }
+}
+
+fn default_weather_endpoint() -> String {
+    "https://api.open-meteo.com/v1/forecast".to_string()
+}
+
+fn default_weather_max_age() -> u64 {
+    600
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WallpapersConfig {
+    /// 管理接口上传的壁纸原图保存目录
+    #[serde(default = "default_wallpaper_upload_dir")]
+    pub upload_dir: String,
+    /// 单张壁纸的大小上限（MB），还受 Rocket.toml 中 limits.file 限制
+    #[serde(default = "default_wallpaper_max_upload")]
+    pub max_upload_mb: u64,
+}
+
+impl Default for WallpapersConfig {
+    fn default() -> Self {
+        Self {
+            upload_dir: default_wallpaper_upload_dir(),
+            max_upload_mb: default_wallpaper_max_upload(),
+        }
+    }
+}
+
+fn default_wallpaper_upload_dir() -> String {
+    "data/wallpapers".to_string()
+}
+
+fn default_wallpaper_max_upload() -> u64 {
+    20
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvatarConfig {
+    /// 未指定来源或来源不存在时使用的来源
+    #[serde(default = "default_avatar_source")]
+    pub default_source: String,
+    /// 头像来源（/avatar?s=<名称>），启动时校验
+    #[serde(default = "default_avatar_sources")]
+    pub sources: BTreeMap<String, AvatarSourceConfig>,
+    /// PUT /avatar/self 上传的头像及其转码结果的保存目录
+    #[serde(default = "default_avatar_upload_dir")]
+    pub upload_dir: String,
+    /// 上传头像的大小上限（MB）
+    #[serde(default = "default_avatar_max_upload")]
+    pub max_upload_mb: u64,
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        Self {
+            default_source: default_avatar_source(),
+            sources: default_avatar_sources(),
+            upload_dir: default_avatar_upload_dir(),
+            max_upload_mb: default_avatar_max_upload(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvatarSourceConfig {
+    /// 原图地址模板，{id} 替换为请求的 id 参数（未指定时使用 default_id）
+    pub url: String,
+    #[serde(default)]
+    pub default_id: Option<String>,
+    /// 来源的其他名称（如 gh）
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// 响应的缓存时间（秒），同时作为 Redis 缓存后端中转码结果的过期时间
+    #[serde(default = "default_avatar_ttl")]
+    pub ttl_secs: u64,
+    /// 获取失败时改用的来源
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// 是否在启动时预热（只预热使用 default_id 的地址）
+    #[serde(default = "default_avatar_warm")]
+    pub warm: bool,
+    /// 是否接受 email 参数：{id} 替换为邮箱的 SHA-256（Gravatar / Libravatar）
+    #[serde(default)]
+    pub email: bool,
+    /// 是否按邮箱域名的 _avatars-sec._tcp SRV 记录改用该域名自建的 Libravatar 服务器（需开启 email）
+    #[serde(default)]
+    pub federated: bool,
+}
+
+fn default_avatar_source() -> String {
+    "default".to_string()
+}
+
+fn default_avatar_sources() -> BTreeMap<String, AvatarSourceConfig> {
+    let source = |url: &str, default_id: Option<&str>, aliases: &[&str], fallback: Option<&str>| AvatarSourceConfig {
+        url: url.to_string(),
+        default_id: default_id.map(str::to_string),
+        aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        ttl_secs: default_avatar_ttl(),
+        fallback: fallback.map(str::to_string),
+        warm: true,
+        email: false,
+        federated: false,
+    };
+    BTreeMap::from([
+        (
+            "default".to_string(),
+            source("https://cdn.tnxg.top/images/avatar/main/Texas.png", None, &[], None),
+        ),
+        (
+            "qq".to_string(),
+            source("https://q1.qlogo.cn/g?b=qq&nk={id}&s=640", Some("2271225249"), &[], Some("default")),
+        ),
+        (
+            "github".to_string(),
+            source("https://avatars.githubusercontent.com/u/{id}", Some("69001561"), &["gh"], Some("default")),
+        ),
+        (
+            "gravatar".to_string(),
+            AvatarSourceConfig {
+                email: true,
+                ..source("https://gravatar.com/avatar/{id}?s=640&d=404", None, &[], Some("libravatar"))
+            },
+        ),
+        (
+            "libravatar".to_string(),
+            AvatarSourceConfig {
+                email: true,
+                federated: true,
+                ..source("https://seccdn.libravatar.org/avatar/{id}?s=640&d=404", None, &[], Some("default"))
+            },
+        ),
+    ])
+}
+
+fn default_avatar_ttl() -> u64 {
+    259200
+}
+
+fn default_avatar_warm() -> bool {
+    true
+}
+
+fn default_avatar_upload_dir() -> String {
+    "data/avatar".to_string()
+}
+
+fn default_avatar_max_upload() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockUpstreamsConfig {
+    /// 开启后 NCM、codetime、CDN、QQ OAuth 请求由内置假响应返回（仅用于压测和 CI）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 模拟的上游延迟（毫秒）
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// 延迟随机抖动范围（± 毫秒）
+    #[serde(default)]
+    pub jitter_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedUrlConfig {
+    /// 签名密钥（HMAC-SHA256），也可通过环境变量 SPACE_API_URL_SIGNING_KEY 注入
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 签名链接允许的最长有效期（秒）
+    #[serde(default = "default_signed_url_max_ttl")]
+    pub max_ttl_secs: u64,
+    /// 是否要求友链头像代理请求携带签名
+    #[serde(default)]
+    pub protect_friend_avatar: bool,
+}
+
+impl Default for SignedUrlConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            max_ttl_secs: default_signed_url_max_ttl(),
+            protect_friend_avatar: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OgImageConfig {
+    /// 字体文件路径（TTF/OTF），按顺序作为回退链：某个字符在前面的字体中缺失时使用后面的字体
+    #[serde(default = "default_og_fonts")]
+    pub fonts: Vec<String>,
+    /// 主题：dark / light
+    #[serde(default = "default_og_theme")]
+    pub theme: String,
+    /// 强调色（十六进制）
+    #[serde(default = "default_og_accent_color")]
+    pub accent_color: String,
+    /// 卡片底部显示的站点名称
+    #[serde(default)]
+    pub site_name: String,
+}
+
+impl Default for OgImageConfig {
+    fn default() -> Self {
+        Self {
+            fonts: default_og_fonts(),
+            theme: default_og_theme(),
+            accent_color: default_og_accent_color(),
+            site_name: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarConfig {
+    /// 日历名称（订阅后显示在日历应用中）
+    #[serde(default = "default_calendar_name")]
+    pub name: String,
+    /// 客户端缓存时间（秒）
+    #[serde(default = "default_calendar_max_age")]
+    pub max_age_secs: u64,
+    /// 固定事件（与数据库 calendar_events 集合中的事件合并）
+    #[serde(default)]
+    pub events: Vec<CalendarEvent>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            name: default_calendar_name(),
+            max_age_secs: default_calendar_max_age(),
+            events: Vec::new(),
+        }
+    }
+}
+
+/// 日历事件（纪念日、维护窗口等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    /// 唯一 ID，为空时根据标题和开始时间生成
+    #[serde(default)]
+    pub uid: String,
+    pub summary: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 开始时间：YYYY-MM-DD 表示全天事件，RFC 3339 时间表示定时事件
+    pub start: String,
+    /// 定时事件的持续时长（分钟），全天事件忽略
+    #[serde(default = "default_event_duration")]
+    pub duration_minutes: u32,
+    /// 重复规则：none / daily / weekly / monthly / yearly
+    #[serde(default = "default_event_recurrence")]
+    pub recurrence: String,
+}
+
+fn default_event_duration() -> u32 {
+    60
+}
+
+fn default_event_recurrence() -> String {
+    "none".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpamConfig {
+    /// 关键词规则（不区分大小写的子串匹配），每命中一条累加对应权重
+    #[serde(default)]
+    pub rules: Vec<SpamRuleConfig>,
+    /// 内容中允许的链接数量，超出部分每个累加 link_weight
+    #[serde(default = "default_spam_max_links")]
+    pub max_links: usize,
+    #[serde(default = "default_spam_link_weight")]
+    pub link_weight: f32,
+    /// 得分达到该值时标记为待审核
+    #[serde(default = "default_spam_threshold")]
+    pub threshold: f32,
+    /// Akismet API key（可选），也可通过环境变量 SPACE_API_AKISMET_KEY 注入
+    #[serde(default)]
+    pub akismet_key: Option<String>,
+    /// Akismet 校验使用的站点地址
+    #[serde(default)]
+    pub akismet_site: String,
+    /// Akismet 判定为垃圾内容时累加的权重
+    #[serde(default = "default_spam_threshold")]
+    pub akismet_weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpamRuleConfig {
+    pub keyword: String,
+    #[serde(default = "default_spam_rule_weight")]
+    pub weight: f32,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_links: default_spam_max_links(),
+            link_weight: default_spam_link_weight(),
+            threshold: default_spam_threshold(),
+            akismet_key: None,
+            akismet_site: String::new(),
+            akismet_weight: default_spam_threshold(),
+        }
+    }
+}
+
+fn default_spam_max_links() -> usize {
+    2
+}
+
+fn default_spam_link_weight() -> f32 {
+    0.3
+}
+
+fn default_spam_threshold() -> f32 {
+    1.0
+}
+
+fn default_spam_rule_weight() -> f32 {
+    0.5
+}
+
+fn default_calendar_name() -> String {
+    "Space API".to_string()
+}
+
+fn default_calendar_max_age() -> u64 {
+    3600
+}
+
+fn default_og_fonts() -> Vec<String> {
+    vec![
+        // Alpine (font-dejavu)
+        "/usr/share/fonts/dejavu/DejaVuSans-Bold.ttf".to_string(),
+        // Debian / Ubuntu (fonts-dejavu-core)
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf".to_string(),
+    ]
+}
+
+fn default_og_theme() -> String {
+    "dark".to_string()
+}
+
+fn default_og_accent_color() -> String {
+    "#7c5cff".to_string()
+}
+
+fn default_signed_url_max_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_sw_runtime_rules() -> Vec<ServiceWorkerRuleConfig> {
+    vec![
+        ServiceWorkerRuleConfig {
+            pattern: r"\.(?:png|jpe?g|webp|avif|gif|svg)(?:\?.*)?$".to_string(),
+            strategy: "cache-first".to_string(),
+            cache_name: "images".to_string(),
+        },
+        ServiceWorkerRuleConfig {
+            pattern: r"\.(?:js|css|woff2?)(?:\?.*)?$".to_string(),
+            strategy: "stale-while-revalidate".to_string(),
+            cache_name: "static".to_string(),
+        },
+    ]
+}
+
+fn default_robots_rules() -> Vec<RobotsRuleConfig> {
+    vec![RobotsRuleConfig {
+        user_agent: default_robots_user_agent(),
+        allow: Vec::new(),
+        disallow: vec!["/".to_string()],
+    }]
+}
+
+fn default_robots_user_agent() -> String {
+    "*".to_string()
+}
+
+fn default_x_robots_tag() -> String {
+    "noindex, nofollow".to_string()
+}
+
+fn default_noindex_paths() -> Vec<String> {
+    vec!["/".to_string()]
+}
+
+fn default_image_miss_threshold() -> u32 {
+    100
+}
+
+fn default_image_miss_window() -> u64 {
+    60
+}
+
+fn default_verify_failure_threshold() -> u32 {
+    10
+}
+
+fn default_verify_failure_window() -> u64 {
+    600
+}
+
+fn default_base_ban() -> u64 {
+    300
+}
+
+fn default_max_ban() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_offense_memory() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_memory_threshold() -> u64 {
+    500
+}
+
+fn default_check_interval() -> u64 {
+    30
+}
+
+fn default_gc_cooldown() -> u64 {
+    30
+}
+
+/// 配置文件路径（环境变量 CONFIG_PATH，默认 config.toml）
+fn config_path() -> String {
+    env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string())
+}
+
+/// 本次启动使用的配置来源（只记录环境变量名，不含值）
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSources {
+    pub file: String,
+    /// 配置文件不存在时只使用默认值和环境变量
+    pub file_found: bool,
+    /// 覆盖配置的 SPACE_API_* 环境变量名（按名称排序）
+    pub env_overrides: Vec<String>,
+}
+
+pub fn config_sources() -> ConfigSources {
+    let file = config_path();
+    let mut env_overrides: Vec<String> = env::vars()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with("SPACE_API_") && name.contains("__"))
+        .collect();
+    env_overrides.sort();
+    ConfigSources {
+        file_found: std::path::Path::new(&file).is_file(),
+        file,
+        env_overrides,
+    }
+}
+
 pub fn load_config() -> Config {
-    let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string());
+    let config_path = config_path();
 
     let s = ConfigLoader::builder()
         // 1. 设置默认值 (可选，这里略过，依靠 Result 处理或 Serde default)
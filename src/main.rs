@@ -1,15 +1,49 @@
 use dotenv::dotenv;
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use rocket_dyn_templates::Template;
 use space_api_rs::config;
+use space_api_rs::config::settings::Config;
 use space_api_rs::routes;
 use space_api_rs::routes::index::MetricsHistory;
+use space_api_rs::services::abuse_service::AbuseService;
+use space_api_rs::services::avatar_service;
+use space_api_rs::services::blurhash_service::BlurhashService;
+use space_api_rs::services::boot_report;
+use space_api_rs::services::cdn_service;
 use space_api_rs::services::db_service;
+use space_api_rs::services::event_bus;
+use space_api_rs::services::feature_service;
 use space_api_rs::services::friend_avatar_service::FriendAvatarService;
-use space_api_rs::services::image_service::ImageService;
+use space_api_rs::services::github_link_service;
+use space_api_rs::services::graphql_service;
+use space_api_rs::services::image_service::{self, ImageService};
+use space_api_rs::services::inbound_email_service;
+use space_api_rs::services::ip_filter_service::IpFilterService;
+use space_api_rs::services::link_service::LinkService;
+use space_api_rs::services::maintenance_service;
 use space_api_rs::services::memory_service::MemoryManager;
+use space_api_rs::services::mock_upstream;
+use space_api_rs::services::og_service::OgService;
+use space_api_rs::services::outbox_service;
+use space_api_rs::services::spam_service;
+use space_api_rs::services::upstream_fixtures;
+use space_api_rs::services::upstream_service;
+use space_api_rs::services::wallpaper_service;
+use space_api_rs::services::weather_service;
 use space_api_rs::utils::cache;
 use space_api_rs::utils::charset::Utf8CharsetFairing;
+use space_api_rs::utils::crypto;
+use space_api_rs::utils::errors;
+use space_api_rs::utils::idempotency::IdempotencyFairing;
+use space_api_rs::utils::ip_filter::{self, AbuseFairing, IpFilterFairing};
+use space_api_rs::utils::logging;
+use space_api_rs::utils::maintenance::{self, MaintenanceFairing};
+use space_api_rs::utils::request_context;
+use space_api_rs::utils::request_counter::RequestCounterFairing;
+use space_api_rs::utils::robots_tag::RobotsTagFairing;
+use space_api_rs::utils::signed_url;
+use space_api_rs::utils::slow_requests::{self, SlowRequestFairing};
+use space_api_rs::utils::transcode_limiter;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -33,12 +67,32 @@ narenas:4\
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
 
-    // 初始化日志系统
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-        .format_timestamp_millis()
-        .init();
-
+    // 先安装引导日志，加载配置期间的日志输出到标准错误
+    logging::bootstrap();
     let config = config::settings::load_config();
+
+    // 按配置初始化日志系统（替换引导日志；guard 需持有到进程退出，保证文件日志写完）
+    let _log_guard = logging::init(&config.log);
+
+    // 模拟上游模式（压测 / CI）
+    mock_upstream::init(&config.mock_upstreams);
+    // 上游响应快照录制 / 回放（开发）
+    upstream_fixtures::init(&config.upstream_fixtures);
+    // 慢请求采样
+    slow_requests::init(&config.diagnostics);
+    // 请求处理时限
+    request_context::init(&config.requests);
+    // 维护模式（不依赖 MongoDB，需早于数据库初始化）
+    maintenance_service::init(&config.maintenance);
+    transcode_limiter::init(&config.transcode);
+    image_service::init(&config.transcode);
+    wallpaper_service::init(&config.wallpapers);
+    // 字节值缓存后端（进程内 / Redis）
+    cache::init_backend(&config.cache);
+    // 上游地址覆盖（需早于各服务创建 HTTP 客户端）
+    upstream_service::init(&config.upstreams);
+    upstream_service::start_prober(config.upstreams.clone());
+
     let mongo_client = match db_service::initialize_db(&config.mongo).await {
         Ok(c) => c,
         Err(e) => {
@@ -47,6 +101,93 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    let indexes_ensured = match db_service::ensure_indexes().await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("创建数据库索引失败: {}", e);
+            false
+        }
+    };
+
+    // 初始化敏感字段加密
+    if let Err(e) = crypto::init(&config.security) {
+        error!("字段加密初始化失败: {}", e);
+        return Err(e.into());
+    }
+
+    // 校验头像来源配置
+    if let Err(e) = avatar_service::init(&config.avatar) {
+        error!("头像来源配置无效: {}", e);
+        return Err(e.into());
+    }
+
+    // 初始化签名链接密钥
+    signed_url::init(&config.signed_urls);
+
+    // 初始化垃圾内容评分
+    spam_service::init(&config.spam);
+
+    // 初始化首页天气组件
+    weather_service::init(&config.weather);
+
+    // 初始化事件投递（webhook / 通知邮件），后台持续投递 outbox 中的待发送事件
+    outbox_service::init(&config.outbox);
+    outbox_service::start_dispatcher(config.email.clone());
+
+    // 初始化 CDN 缓存刷新，并刷新 sw.js 等随部署变化的文件
+    cdn_service::init(&config.cdn);
+    cdn_service::purge_on_startup();
+
+    // 定时导入 GitHub issue 中的友链申请
+    github_link_service::start_poller(config.github_links.clone());
+    // 定时收取邮件中的友链申请
+    inbound_email_service::start_poller(config.inbound_email.clone(), config.email.clone());
+
+    // 友链变更时清除公开列表缓存
+    LinkService::watch_changes();
+
+    // 事件总线：以 debug 级别记录所有内部事件，便于排查订阅者行为
+    event_bus::subscribe("event_log", &[], |event| async move {
+        debug!("[event] {}", serde_json::to_string(&event).unwrap_or_else(|_| event.name().to_string()));
+    });
+
+    // 存在旧密钥时，后台使用当前密钥重新加密历史数据
+    if crypto::cipher().is_some_and(|c| c.has_retired_keys()) {
+        tokio::spawn(async {
+            match db_service::reseal_collection("users").await {
+                Ok(report) => {
+                    if report.updated > 0 {
+                        info!("已使用当前密钥重新加密 users 中 {} 条记录", report.updated);
+                    }
+                    if report.failed > 0 {
+                        warn!("users 中 {} 条记录重新加密失败，已跳过", report.failed);
+                    }
+                }
+                Err(e) => warn!("重新加密 users 失败: {}", e),
+            }
+        });
+    }
+
+    // 初始化 IP 过滤并加载持久化的动态封禁
+    let ip_filter_service = Arc::new(IpFilterService::new(&config.ip_filter));
+    match ip_filter_service.load_persisted().await {
+        Ok(n) if n > 0 => info!("已加载 {} 条 IP 封禁记录", n),
+        Ok(_) => {}
+        Err(e) => warn!("加载 IP 封禁记录失败: {}", e),
+    }
+    let abuse_service = Arc::new(AbuseService::new(config.abuse.clone(), ip_filter_service.clone()));
+
+    // 功能开关：配置中的默认值和管理接口保存的设置
+    feature_service::init(&config.features);
+    match feature_service::load_persisted().await {
+        Ok(n) if n > 0 => info!("已加载 {} 个功能开关", n),
+        Ok(_) => {}
+        Err(e) => warn!("加载功能开关失败: {}", e),
+    }
+
+    // 共享的上游 HTTP 客户端（在 upstream_service::init 中按 [upstreams] 配置创建）
+    let http = upstream_service::http().clone();
+
     // 初始化内存管理器
     let memory_manager = Arc::new(MemoryManager::new(config.memory.clone()));
 
@@ -71,6 +212,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
+    // 壁纸预生成（后台逐张转码，写入硬盘缓存）
+    if config.wallpaper_pregen.enabled && config.cache.wallpapers.ttl_secs.is_none() {
+        warn!(
+            "Wallpaper pre-generation is enabled but cache.wallpapers.ttl_secs is not set, \
+             generated variants expire with the default disk TTL"
+        );
+    }
+    image_service::start_wallpaper_pregen(config.wallpaper_pregen.clone(), routes::images::wallpaper_urls());
+    // 头像和壁纸缓存预热
+    image_service::start_cache_warmup(config.cache_warmup.clone());
+
     // 输出初始内存状态
     if let Ok(status) = memory_manager.get_memory_status().await {
         info!(
@@ -83,23 +235,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 使用 custom(figment) 替代 build()
     let rocket = rocket::custom(figment)
+        .attach(IpFilterFairing::new(ip_filter_service.clone()))
+        .attach(AbuseFairing::new(
+            abuse_service.clone(),
+            config.ip_filter.trust_proxy_headers,
+        ))
+        .attach(MaintenanceFairing)
+        .attach(RequestCounterFairing)
+        .attach(SlowRequestFairing)
+        .attach(IdempotencyFairing)
         .attach(Utf8CharsetFairing)
+        .attach(RobotsTagFairing::new(&config.robots))
         .attach(Template::fairing())
+        .register("/", errors::catchers())
         .mount("/", routes::index::routes())
+        .mount("/", ip_filter::routes())
+        .mount("/", maintenance::routes())
+        .mount("/admin", routes::admin_ui::routes())
+        .mount("/api/admin", routes::admin::routes())
+        .mount("/api/bench", routes::bench::routes())
+        .mount("/api/cache", routes::cache::routes())
+        .mount("/api/dashboard", routes::dashboard::routes())
+        .mount("/api/diagnostics", routes::diagnostics::routes())
+        .mount("/api/errors", routes::errors::routes())
+        .mount("/api/examples", routes::examples::routes())
         .mount("/avatar", routes::avatar::routes())
+        .mount("/badge", routes::badge::routes())
         .mount("/email", routes::email::routes())
         .mount("/friend-avatar", routes::friend_avatar::routes())
+        .mount("/graphql", routes::graphql::routes())
+        .mount("/hooks", routes::hooks::routes())
         .mount("/images", routes::images::routes())
+        .mount("/links", routes::links::routes())
+        .mount("/api/logs", routes::logs::routes())
         .mount("/oauth", routes::oauth::routes())
+        .mount("/api/render", routes::render::routes())
+        .mount("/rpc", routes::rpc::routes())
+        .mount("/api/stats", routes::stats::routes())
         .mount("/status", routes::status::routes())
+        .mount("/", routes::calendar::routes())
+        .mount("/", routes::robots::routes())
+        .mount("/", routes::search::routes())
+        .mount("/", routes::static_files::routes())
         .mount("/", routes::sw::routes())
         .mount("/user", routes::user::routes())
+        .mount("/api/widgets", routes::widgets::routes())
+        .manage(OgService::new(&config.og_image))
+        .manage(graphql_service::build_schema())
         .manage(config)
         .manage(mongo_client)
         .manage(MetricsHistory::new())
         .manage(routes::index::SystemState::new())
-        .manage(ImageService::new())
-        .manage(FriendAvatarService::new())
+        .manage(ImageService::new(&http))
+        .manage(FriendAvatarService::new(&http))
+        .manage(BlurhashService::new(&http))
+        .manage(http)
+        .manage(ip_filter_service)
+        .manage(abuse_service)
         .manage(memory_manager);
 
     // 从Cargo.toml获取版本号
@@ -127,6 +319,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ╚═══════════════════════════════════════════════════════════════╝\n",
         version
     );
+    // 启动报告（以 JSON 记录，GET /api/boot-report 可查询）
+    if let Some(config) = rocket.state::<Config>() {
+        boot_report::record(boot_report::build(config, rocket.routes().count(), indexes_ensured));
+    }
     rocket.launch().await?;
 
     Ok(())
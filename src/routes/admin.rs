@@ -0,0 +1,448 @@
+use crate::services::abuse_service::AbuseService;
+use crate::services::audit_service::AuditService;
+use crate::services::calendar_service::{CalendarEvent, CalendarService};
+use crate::services::cdn_service::{self, PurgeOutcome, PurgeTarget, MAX_PURGE_URLS};
+use crate::services::command_service::{CommandOutcome, CommandService, CommandSpec, COMMANDS};
+use crate::services::feature_service::{self, FeatureState};
+use crate::services::ip_filter_service::{IpBlock, IpFilterService};
+use crate::services::maintenance_service::{self, MaintenanceState};
+use crate::services::memory_service::MemoryManager;
+use crate::services::upstream_service::{self, UpstreamDiagnostics};
+use crate::utils::auth::AdminGuard;
+use crate::utils::idempotency::Idempotency;
+use crate::utils::response::ApiResponse;
+use crate::utils::signed_url;
+use crate::utils::validation::{self, Valid, Validate, Validator};
+use crate::{Error, Result};
+use rocket::serde::{json::Json, Deserialize};
+use rocket::{delete, get, post, put, routes, Route, State};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+pub struct BlockIpRequest {
+    /// 单个 IP 或 CIDR
+    cidr: String,
+    reason: Option<String>,
+    /// 封禁时长（秒），为空表示永久
+    ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignUrlRequest {
+    /// 站内路径（可带查询参数），如 /friend-avatar?url=...
+    path: String,
+    /// 有效期（秒），默认 1 小时
+    ttl_secs: Option<u64>,
+    /// 是否为一次性链接
+    #[serde(default)]
+    once: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommandRequest {
+    /// 命令名称，见 GET /api/admin/commands
+    command: String,
+    #[serde(default)]
+    args: serde_json::Value,
+    /// 幂等键：24 小时内相同键的请求只执行一次，重复请求返回首次的结果
+    idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CdnPurgeRequest {
+    /// 站内路径（按 cdn.public_base_url 拼接）或完整 URL
+    #[serde(default)]
+    urls: Vec<String>,
+    /// 刷新整个站点（忽略 urls）
+    #[serde(default)]
+    everything: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeatureToggleRequest {
+    enabled: bool,
+    /// 切换原因（记录在审计日志和功能状态中）
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    enabled: bool,
+    /// 维护提示信息，为空则保持不变
+    message: Option<String>,
+    /// 503 响应的 Retry-After（秒），为空则保持不变
+    retry_after_secs: Option<u64>,
+}
+
+impl Validate for BlockIpRequest {
+    fn check(&self, v: &mut Validator) {
+        v.length("cidr", self.cidr.trim(), 1, 64);
+        if let Some(reason) = &self.reason {
+            v.length("reason", reason, 0, 200);
+        }
+        if let Some(ttl) = self.ttl_secs {
+            v.check("ttl_secs", ttl > 0, "must be greater than 0");
+        }
+    }
+}
+
+impl Validate for SignUrlRequest {
+    fn check(&self, v: &mut Validator) {
+        v.length("path", &self.path, 1, 2048)
+            .check("path", self.path.starts_with('/'), "must start with '/'");
+    }
+}
+
+impl Validate for CdnPurgeRequest {
+    fn check(&self, v: &mut Validator) {
+        if !self.everything {
+            v.check("urls", !self.urls.is_empty(), "is required unless everything is true")
+                .count("urls", &self.urls, MAX_PURGE_URLS);
+            for url in &self.urls {
+                v.check(
+                    "urls",
+                    url.starts_with('/') || validation::is_http_url(url),
+                    "must be site paths or http(s) URLs",
+                );
+            }
+        }
+    }
+}
+
+impl Validate for FeatureToggleRequest {
+    fn check(&self, v: &mut Validator) {
+        if let Some(reason) = &self.reason {
+            v.length("reason", reason, 0, 200);
+        }
+    }
+}
+
+impl Validate for MaintenanceRequest {
+    fn check(&self, v: &mut Validator) {
+        if let Some(message) = &self.message {
+            v.length("message", message, 0, 500);
+        }
+        if let Some(secs) = self.retry_after_secs {
+            v.range("retry_after_secs", secs, 1, 86400);
+        }
+    }
+}
+
+impl Validate for CommandRequest {
+    fn check(&self, v: &mut Validator) {
+        v.required("command", &self.command).check(
+            "args",
+            self.args.is_null() || self.args.is_object(),
+            "must be an object",
+        );
+        if let Some(key) = &self.idempotency_key {
+            v.length("idempotency_key", key.trim(), 1, 128);
+        }
+    }
+}
+
+// 列出动态 IP 封禁
+#[get("/ip-blocks")]
+async fn list_ip_blocks(
+    _admin: AdminGuard,
+    ip_filter: &State<Arc<IpFilterService>>,
+) -> Json<ApiResponse<Vec<IpBlock>>> {
+    ApiResponse::success(ip_filter.list_blocks().await, "IP blocks")
+}
+
+// 添加动态 IP 封禁
+#[post("/ip-blocks", data = "<data>")]
+async fn add_ip_block(
+    admin: AdminGuard,
+    data: Valid<BlockIpRequest>,
+    ip_filter: &State<Arc<IpFilterService>>,
+) -> Result<Json<ApiResponse<IpBlock>>> {
+    let reason = data.reason.clone().unwrap_or_else(|| "manual".to_string());
+    let ttl = data.ttl_secs.map(Duration::from_secs);
+    let block = ip_filter.block(&data.cidr, &reason, ttl).await?;
+
+    AuditService::record(
+        "ip_filter.block",
+        &admin.actor,
+        &block.cidr,
+        serde_json::json!({ "reason": reason, "ttl_secs": data.ttl_secs }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(block, "IP blocked"))
+}
+
+// 解除动态 IP 封禁
+#[delete("/ip-blocks?<cidr>")]
+async fn remove_ip_block(
+    admin: AdminGuard,
+    cidr: &str,
+    ip_filter: &State<Arc<IpFilterService>>,
+) -> Result<Json<ApiResponse<bool>>> {
+    let removed = ip_filter.unblock(cidr).await?;
+
+    AuditService::record(
+        "ip_filter.unblock",
+        &admin.actor,
+        cidr,
+        serde_json::json!({ "existed": removed }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(removed, "IP unblocked"))
+}
+
+// 列出当前生效的自动封禁（附带违规次数）
+#[get("/bans")]
+async fn list_bans(
+    _admin: AdminGuard,
+    abuse: &State<Arc<AbuseService>>,
+) -> Json<ApiResponse<Vec<serde_json::Value>>> {
+    let mut bans = Vec::new();
+    for block in abuse.list_bans().await {
+        let offenses = match block.cidr.split('/').next().and_then(|ip| ip.parse().ok()) {
+            Some(ip) => abuse.offense_count(&ip).await,
+            None => 0,
+        };
+        bans.push(serde_json::json!({
+            "ip": block.cidr,
+            "reason": block.reason,
+            "created_at": block.created_at,
+            "expires_at": block.expires_at,
+            "offenses": offenses,
+        }));
+    }
+    ApiResponse::success(bans, "Active automatic bans")
+}
+
+// 解除自动封禁（保留违规次数，再次违规时封禁时长继续递增）
+#[delete("/bans?<ip>")]
+async fn lift_ban(
+    admin: AdminGuard,
+    ip: &str,
+    abuse: &State<Arc<AbuseService>>,
+) -> Result<Json<ApiResponse<bool>>> {
+    let removed = abuse.lift_ban(ip).await?;
+    if !removed {
+        return Err(Error::NotFound(format!("No active automatic ban for {}", ip)));
+    }
+
+    AuditService::record("abuse.lift_ban", &admin.actor, ip, serde_json::json!({})).await;
+
+    Ok(ApiResponse::success(true, "Ban lifted"))
+}
+
+// 签发临时访问链接
+#[post("/signed-urls", data = "<data>")]
+async fn sign_url(
+    admin: AdminGuard,
+    data: Valid<SignUrlRequest>,
+) -> Result<Json<ApiResponse<serde_json::Value>>> {
+    let ttl_secs = data.ttl_secs.unwrap_or(3600);
+    let path = if data.once {
+        let sep = if data.path.contains('?') { '&' } else { '?' };
+        format!("{}{}once=1", data.path, sep)
+    } else {
+        data.path.clone()
+    };
+    let url = signed_url::sign(&path, ttl_secs)?;
+    let expires_at = (chrono::Utc::now() + chrono::Duration::seconds(ttl_secs as i64)).to_rfc3339();
+
+    AuditService::record(
+        "signed_url.issue",
+        &admin.actor,
+        &data.path,
+        serde_json::json!({ "ttl_secs": ttl_secs, "once": data.once }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(
+        serde_json::json!({ "url": url, "expires_at": expires_at }),
+        "Signed URL issued",
+    ))
+}
+
+// 刷新 CDN 边缘节点缓存
+#[post("/cdn/purge", data = "<data>")]
+async fn purge_cdn(admin: AdminGuard, data: Valid<CdnPurgeRequest>) -> Result<Json<ApiResponse<PurgeOutcome>>> {
+    let cdn = cdn_service::get().ok_or_else(|| Error::BadRequest("CDN purge is not configured".into()))?;
+    let data = data.into_inner();
+    let target = if data.everything {
+        PurgeTarget::Everything
+    } else {
+        PurgeTarget::Urls(data.urls)
+    };
+    let outcome = cdn.purge(target).await?;
+
+    AuditService::record(
+        "cdn.purge",
+        &admin.actor,
+        if outcome.everything { "*" } else { &outcome.provider },
+        serde_json::json!({ "urls": outcome.urls, "everything": outcome.everything, "job_id": outcome.job_id }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(outcome, "CDN cache purged"))
+}
+
+// 列出数据库中的日历事件（不含配置文件中的事件）
+#[get("/calendar/events")]
+async fn list_calendar_events(_admin: AdminGuard) -> Result<Json<ApiResponse<Vec<CalendarEvent>>>> {
+    Ok(ApiResponse::success(CalendarService::stored_events().await?, "Calendar events"))
+}
+
+// 新增或替换日历事件（按 uid）
+#[post("/calendar/events", data = "<data>")]
+async fn save_calendar_event(
+    admin: AdminGuard,
+    data: Valid<CalendarEvent>,
+    _idempotency: Idempotency,
+) -> Result<Json<ApiResponse<CalendarEvent>>> {
+    let event = CalendarService::save_event(data.into_inner()).await?;
+
+    AuditService::record(
+        "calendar.save_event",
+        &admin.actor,
+        &event.uid,
+        serde_json::json!({ "summary": event.summary, "start": event.start }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(event, "Calendar event saved"))
+}
+
+// 删除日历事件
+#[delete("/calendar/events?<uid>")]
+async fn delete_calendar_event(admin: AdminGuard, uid: &str) -> Result<Json<ApiResponse<bool>>> {
+    if !CalendarService::delete_event(uid).await? {
+        return Err(Error::NotFound(format!("No calendar event {}", uid)));
+    }
+
+    AuditService::record("calendar.delete_event", &admin.actor, uid, serde_json::json!({})).await;
+
+    Ok(ApiResponse::success(true, "Calendar event deleted"))
+}
+
+// 列出可执行的管理命令
+#[get("/commands")]
+fn list_commands(_admin: AdminGuard) -> Json<ApiResponse<&'static [CommandSpec]>> {
+    ApiResponse::success(COMMANDS, "Commands")
+}
+
+// 执行管理命令（供 chat-ops 机器人等自动化工具调用）
+// 幂等只由请求体中的 idempotency_key 处理，不使用 Idempotency 守卫，避免两套机制对重复请求返回不同的状态码
+#[post("/commands", data = "<data>")]
+async fn run_command(
+    admin: AdminGuard,
+    data: Valid<CommandRequest>,
+    memory_manager: &State<Arc<MemoryManager>>,
+) -> Result<Json<ApiResponse<CommandOutcome>>> {
+    let data = data.into_inner();
+    let outcome = CommandService::execute(
+        &data.command,
+        data.args,
+        data.idempotency_key.as_deref(),
+        &admin.actor,
+        memory_manager,
+    )
+    .await?;
+    Ok(ApiResponse::success(outcome, "Command executed"))
+}
+
+// 列出功能开关及当前状态
+#[get("/features")]
+fn list_features(_admin: AdminGuard) -> Json<ApiResponse<Vec<FeatureState>>> {
+    ApiResponse::success(feature_service::list(), "Features")
+}
+
+// 开启或关闭功能（立即生效，保存后重启仍然有效）
+#[put("/features/<name>", data = "<data>")]
+async fn toggle_feature(
+    admin: AdminGuard,
+    name: &str,
+    data: Valid<FeatureToggleRequest>,
+) -> Result<Json<ApiResponse<FeatureState>>> {
+    let data = data.into_inner();
+    let feature = feature_service::set(name, data.enabled, data.reason.clone(), &admin.actor).await?;
+
+    AuditService::record(
+        if data.enabled { "feature.enable" } else { "feature.disable" },
+        &admin.actor,
+        feature.name,
+        serde_json::json!({ "reason": data.reason }),
+    )
+    .await;
+
+    let message = if feature.enabled { "Feature enabled" } else { "Feature disabled" };
+    Ok(ApiResponse::success(feature, message))
+}
+
+// 删除功能开关的设置，恢复为配置中的默认值
+#[delete("/features/<name>")]
+async fn reset_feature(admin: AdminGuard, name: &str) -> Result<Json<ApiResponse<FeatureState>>> {
+    let (feature, existed) = feature_service::reset(name).await?;
+
+    AuditService::record(
+        "feature.reset",
+        &admin.actor,
+        feature.name,
+        serde_json::json!({ "existed": existed, "enabled": feature.enabled }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(feature, "Feature reset to default"))
+}
+
+// 维护模式的当前状态
+#[get("/maintenance")]
+fn maintenance_status(_admin: AdminGuard) -> Json<ApiResponse<MaintenanceState>> {
+    ApiResponse::success(maintenance_service::current(), "Maintenance status")
+}
+
+// 开启或关闭维护模式（立即生效，只保存在内存中，重启后恢复为配置）
+#[put("/maintenance", data = "<data>")]
+fn set_maintenance(admin: AdminGuard, data: Valid<MaintenanceRequest>) -> Json<ApiResponse<MaintenanceState>> {
+    let data = data.into_inner();
+    let state = maintenance_service::set(data.enabled, data.message, data.retry_after_secs, &admin.actor);
+
+    // 维护期间 MongoDB 可能不可用，审计日志在后台写入，不阻塞切换
+    AuditService::record_detached(
+        if state.enabled { "maintenance.enable" } else { "maintenance.disable" },
+        &admin.actor,
+        "maintenance",
+        serde_json::json!({ "message": state.message, "retry_after_secs": state.retry_after_secs }),
+    );
+
+    let message = if state.enabled { "Maintenance mode enabled" } else { "Maintenance mode disabled" };
+    ApiResponse::success(state, message)
+}
+
+// 上游连接诊断：地址族策略、固定 IP 健康状态和各域名最近的解析结果
+#[get("/upstreams")]
+fn upstreams(_admin: AdminGuard) -> Json<ApiResponse<UpstreamDiagnostics>> {
+    ApiResponse::success(upstream_service::diagnostics(), "Upstream diagnostics")
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        list_ip_blocks,
+        add_ip_block,
+        remove_ip_block,
+        list_bans,
+        lift_ban,
+        sign_url,
+        purge_cdn,
+        list_calendar_events,
+        save_calendar_event,
+        delete_calendar_event,
+        list_commands,
+        run_command,
+        list_features,
+        toggle_feature,
+        reset_feature,
+        maintenance_status,
+        set_maintenance,
+        upstreams
+    ]
+}
@@ -0,0 +1,350 @@
+use crate::config::settings::Config;
+use crate::routes::static_files::{asset_url, preload_links, Preloaded};
+use crate::services::abuse_service::{AbuseService, AbuseSignal};
+use crate::services::audit_service::AuditService;
+use crate::services::command_service::{CommandService, CACHE_NAMESPACES, JOBS};
+use crate::services::db_service;
+use crate::services::link_service::LinkService;
+use crate::services::memory_service::{MemoryManager, MemoryPressure};
+use crate::services::stats_service::StatsService;
+use crate::services::user_service::UserService;
+use crate::utils::auth::{admin_session_owner, secure_eq, AdminSession, ADMIN_SESSION_COOKIE, DEFAULT_ADMIN_ID};
+use crate::utils::error_tracker::ERROR_TRACKER;
+use crate::utils::ip_filter::ClientAddr;
+use mongodb::bson::doc;
+use rocket::form::{Form, FromForm};
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::{get, post, routes, uri, Route, State};
+use rocket_dyn_templates::{context, Template};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// 管理后台登录会话有效期（小时）
+const SESSION_TTL_HOURS: i64 = 12;
+
+/// 登录会话 Cookie：路径为 /，仪表盘首页和 /api/dashboard 也要靠它识别管理员
+fn session_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((ADMIN_SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .max_age(rocket::time::Duration::hours(SESSION_TTL_HOURS))
+        .build()
+}
+
+#[derive(FromForm)]
+pub struct LoginForm {
+    token: String,
+}
+
+#[derive(FromForm)]
+pub struct CommandForm {
+    command: String,
+    namespace: Option<String>,
+    job: Option<String>,
+}
+
+/// 待审核友链（表格中的一行）
+#[derive(Serialize)]
+struct PendingLink {
+    id: String,
+    name: String,
+    url: String,
+    avatar: String,
+    state: String,
+    needs_review: bool,
+}
+
+/// 告警（内存压力、投递失败、服务端错误）
+#[derive(Serialize)]
+struct Alert {
+    level: &'static str,
+    title: String,
+    detail: String,
+}
+
+fn flash_context(flash: Option<FlashMessage<'_>>) -> Option<serde_json::Value> {
+    flash.map(|f| json!({ "kind": f.kind(), "message": f.message() }))
+}
+
+// 登录页
+#[get("/login")]
+async fn login_page(flash: Option<FlashMessage<'_>>, config: &State<Config>) -> Preloaded<Template> {
+    let template = Template::render(
+        "admin/login",
+        context! {
+            admin_css_url: asset_url(&config.static_files, "admin.css").await,
+            flash: flash_context(flash),
+        },
+    );
+    Preloaded::new(template, preload_links(&config.static_files, "admin/login", &[]).await)
+}
+
+// 使用管理员令牌登录，签发会话 Cookie（SameSite=Strict，后台表单无需额外的 CSRF 令牌）
+#[post("/login", data = "<form>")]
+async fn login(
+    form: Form<LoginForm>,
+    cookies: &CookieJar<'_>,
+    client: ClientAddr,
+    config: &State<Config>,
+    abuse: &State<Arc<AbuseService>>,
+) -> Flash<Redirect> {
+    let expected = config.admin.token.as_deref().unwrap_or_default();
+    if expected.is_empty() {
+        return Flash::error(Redirect::to(uri!("/admin", login_page)), "未配置管理员令牌（admin.token）");
+    }
+    if !secure_eq(form.token.trim(), expected) {
+        abuse.record(client.0, AbuseSignal::VerificationFailure).await;
+        return Flash::error(Redirect::to(uri!("/admin", login_page)), "令牌错误");
+    }
+
+    let owner = admin_session_owner(DEFAULT_ADMIN_ID);
+    match UserService::issue_session(&owner, chrono::Duration::hours(SESSION_TTL_HOURS)).await {
+        Ok((token, _)) => {
+            cookies.add(session_cookie(token));
+            let actor = format!("admin@{}", client.0.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".into()));
+            AuditService::record("admin.login", &actor, DEFAULT_ADMIN_ID, json!({})).await;
+            Flash::success(Redirect::to(uri!("/admin", console)), "已登录")
+        }
+        Err(e) => {
+            log::error!("Failed to create admin session: {}", e);
+            Flash::error(Redirect::to(uri!("/admin", login_page)), "登录失败，请稍后重试")
+        }
+    }
+}
+
+// 退出登录
+#[post("/logout")]
+async fn logout(session: Option<AdminSession>, cookies: &CookieJar<'_>) -> Redirect {
+    if let Some(session) = session {
+        if let Err(e) = UserService::revoke_session(&session.token).await {
+            log::warn!("Failed to revoke admin session: {}", e);
+        }
+    }
+    cookies.remove(Cookie::build(ADMIN_SESSION_COOKIE).path("/"));
+    Redirect::to(uri!("/admin", login_page))
+}
+
+// 运维控制台：待审核友链、缓存统计、任务执行、告警
+#[get("/")]
+async fn console(
+    session: Option<AdminSession>,
+    flash: Option<FlashMessage<'_>>,
+    config: &State<Config>,
+    memory_manager: &State<Arc<MemoryManager>>,
+) -> Result<Preloaded<Template>, Redirect> {
+    if session.is_none() {
+        return Err(Redirect::to(uri!("/admin", login_page)));
+    }
+
+    let pending: Vec<PendingLink> = LinkService::pending_links()
+        .await
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load pending links: {}", e);
+            Vec::new()
+        })
+        .iter()
+        .filter_map(|link| {
+            Some(PendingLink {
+                id: link.get_object_id("_id").ok()?.to_hex(),
+                name: link.get_str("name").unwrap_or_default().to_string(),
+                url: link.get_str("url").unwrap_or_default().to_string(),
+                avatar: link.get_str("avatar").unwrap_or_default().to_string(),
+                state: link.get_str("moderation_state").unwrap_or("approved").to_string(),
+                needs_review: link.get_bool("needs_review").unwrap_or(false),
+            })
+        })
+        .collect();
+
+    let stats = StatsService::site_stats().await.ok();
+    let memory = memory_manager.get_memory_status().await.ok();
+
+    let template = Template::render(
+        "admin/console",
+        context! {
+            admin_css_url: asset_url(&config.static_files, "admin.css").await,
+            flash: flash_context(flash),
+            pending,
+            stats,
+            memory: &memory,
+            alerts: alerts(memory.as_ref().map(|m| &m.pressure)).await,
+            jobs: JOBS,
+            namespaces: CACHE_NAMESPACES,
+        },
+    );
+    Ok(Preloaded::new(
+        template,
+        preload_links(&config.static_files, "admin/console", &[]).await,
+    ))
+}
+
+async fn alerts(pressure: Option<&MemoryPressure>) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    if let Some(pressure @ (MemoryPressure::High | MemoryPressure::Critical)) = pressure {
+        alerts.push(Alert {
+            level: if *pressure == MemoryPressure::Critical { "error" } else { "warning" },
+            title: "内存压力".to_string(),
+            detail: format!("当前内存压力等级：{:?}", pressure),
+        });
+    }
+
+    let failed = db_service::aggregate(
+        "outbox",
+        vec![doc! { "$match": { "state": "failed" } }, doc! { "$count": "count" }],
+    )
+    .await
+    .ok()
+    .and_then(|docs| docs.first().and_then(|d| d.get_i32("count").ok()))
+    .unwrap_or(0);
+    if failed > 0 {
+        alerts.push(Alert {
+            level: "warning",
+            title: "通知投递失败".to_string(),
+            detail: format!("{} 条 webhook / 邮件通知已放弃重试", failed),
+        });
+    }
+
+    for record in ERROR_TRACKER.recent(50).into_iter().filter(|r| r.status >= 500).take(10) {
+        alerts.push(Alert {
+            level: "error",
+            title: format!("{} {}", record.status, record.route),
+            detail: format!(
+                "{} 次，最近一次 {}（请求 {}）：{}",
+                record.count, record.last_seen, record.last_request_id, record.last_message
+            ),
+        });
+    }
+    alerts
+}
+
+// 审核友链（通过 / 拒绝）
+#[post("/links/<id>/<action>")]
+async fn moderate_link(
+    session: Option<AdminSession>,
+    id: &str,
+    action: &str,
+    memory_manager: &State<Arc<MemoryManager>>,
+) -> Result<Flash<Redirect>, Redirect> {
+    let session = session.ok_or_else(|| Redirect::to(uri!("/admin", login_page)))?;
+    let command = match action {
+        "approve" => "link.approve",
+        "reject" => "link.reject",
+        _ => return Ok(Flash::error(Redirect::to(uri!("/admin", console)), "未知操作")),
+    };
+    Ok(run(&session, command, json!({ "id": id }), memory_manager).await)
+}
+
+// 执行管理命令（清除缓存、内存释放、后台任务）
+#[post("/commands", data = "<form>")]
+async fn run_command(
+    session: Option<AdminSession>,
+    form: Form<CommandForm>,
+    memory_manager: &State<Arc<MemoryManager>>,
+) -> Result<Flash<Redirect>, Redirect> {
+    let session = session.ok_or_else(|| Redirect::to(uri!("/admin", login_page)))?;
+    let mut args = serde_json::Map::new();
+    if let Some(namespace) = form.namespace.as_deref().filter(|s| !s.is_empty()) {
+        args.insert("namespace".into(), json!(namespace));
+    }
+    if let Some(job) = form.job.as_deref().filter(|s| !s.is_empty()) {
+        args.insert("job".into(), json!(job));
+    }
+    Ok(run(&session, &form.command, serde_json::Value::Object(args), memory_manager).await)
+}
+
+async fn run(session: &AdminSession, command: &str, args: serde_json::Value, memory_manager: &MemoryManager) -> Flash<Redirect> {
+    let back = Redirect::to(uri!("/admin", console));
+    match CommandService::execute(command, args, None, &session.admin.actor, memory_manager).await {
+        Ok(outcome) => Flash::success(back, format!("{} 已执行：{}", outcome.command, outcome.output)),
+        Err(e) => Flash::error(back, format!("{} 执行失败：{}", command, e)),
+    }
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![login_page, login, logout, console, moderate_link, run_command]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+
+    #[get("/login")]
+    fn set_session(cookies: &CookieJar<'_>) {
+        cookies.add(session_cookie("token".into()));
+    }
+
+    #[get("/")]
+    fn read_session(cookies: &CookieJar<'_>) -> String {
+        cookies.get(ADMIN_SESSION_COOKIE).map(|c| c.value().to_string()).unwrap_or_default()
+    }
+
+    #[rocket::async_test]
+    async fn test_session_cookie_reaches_dashboard() {
+        let rocket = rocket::build().mount("/admin", routes![set_session]).mount("/", routes![read_session]);
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let response = client.get("/admin/login").dispatch().await;
+        let cookie = response.cookies().get(ADMIN_SESSION_COOKIE).unwrap().clone();
+        assert!(cookie.http_only().unwrap_or(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+
+        // 浏览器只在请求路径匹配 Cookie 路径时携带它：仪表盘首页和偏好设置接口都要带上会话
+        for path in ["/", "/api/dashboard/preferences"] {
+            assert!(path.starts_with(cookie.path().unwrap()), "{} not covered", path);
+        }
+        let body = client
+            .get("/")
+            .cookie((ADMIN_SESSION_COOKIE, cookie.value().to_string()))
+            .dispatch()
+            .await
+            .into_string()
+            .await;
+        assert_eq!(body.as_deref(), Some("token"));
+    }
+
+    #[rocket::async_test]
+    async fn test_render_templates() {
+        let figment = rocket::Config::figment().merge(("template_dir", "src/templates"));
+        let client = Client::untracked(rocket::custom(figment).attach(Template::fairing())).await.unwrap();
+        let rocket = client.rocket();
+
+        let login = Template::show(rocket, "admin/login", context! { admin_css_url: "/static/admin.css" }).unwrap();
+        assert!(login.contains("action=\"/admin/login\""));
+
+        let console = Template::show(
+            rocket,
+            "admin/console",
+            context! {
+                admin_css_url: "/static/admin.css",
+                flash: json!({ "kind": "success", "message": "done" }),
+                pending: vec![PendingLink {
+                    id: "66aa".into(),
+                    name: "<b>Site</b>".into(),
+                    url: "https://example.com".into(),
+                    avatar: String::new(),
+                    state: "pending".into(),
+                    needs_review: false,
+                }],
+                stats: json!({
+                    "cache": { "memory_entries": 1, "memory_bytes": 2048, "disk_files": 0, "disk_bytes": 0 },
+                    "links": { "total": 1, "by_state": { "pending": 1 } },
+                    "users": 0,
+                    "total_requests": 5,
+                    "generated_at": "2026-01-01T00:00:00Z",
+                }),
+                memory: None::<()>,
+                alerts: vec![Alert { level: "error", title: "500 /x".into(), detail: "boom".into() }],
+                jobs: JOBS,
+                namespaces: CACHE_NAMESPACES,
+            },
+        )
+        .unwrap();
+        assert!(console.contains("/admin/links/66aa/approve"));
+        assert!(console.contains("&lt;b&gt;Site&lt;&#x2F;b&gt;"));
+        assert!(console.contains("1 项 / 2 kB"));
+    }
+}
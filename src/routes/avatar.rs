@@ -1,89 +1,308 @@
-use crate::services::image_service::ImageService;
-use crate::utils::cache::{self, CACHE_BUCKET};
+use crate::config::settings::Config;
+use crate::services::audit_service::AuditService;
+use crate::services::avatar_service::{self, AvatarEmail, AvatarSource, SelfAvatar};
+use crate::services::image_service::{ImageService, ImageTransform, Shape};
+use crate::utils::auth::AdminGuard;
+use crate::utils::cache;
+use crate::utils::client_hints::{self, ClientHints};
 use crate::utils::custom_response::CustomResponse;
+use crate::utils::negotiate;
+use crate::utils::request_context::RequestContext;
+use crate::utils::single_flight::SingleFlight;
+use crate::utils::response::ApiResponse;
+use crate::utils::transcode_limiter;
+use crate::utils::upload;
 use crate::{Error, Result};
 use image::ImageFormat;
+use log::warn;
+use once_cell::sync::Lazy;
+use rocket::form::Form;
+use rocket::fs::TempFile;
 use rocket::http::{Accept, ContentType, Status};
-use rocket::{get, routes, Route, State};
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, routes, FromForm, Route, State};
+use serde_json::json;
+use std::time::Duration;
 
-// 简单的 Accept 协商：按优先级 avif > webp > png > jpeg
+// 进行中的头像转码（按缓存 key）
+static TRANSCODES: Lazy<SingleFlight<(Vec<u8>, bool)>> = Lazy::new(SingleFlight::new);
+
+// Accept 协商：按质量值选择，相同时按 avif > webp > png 的优先级，都不接受时为 jpeg
 fn negotiate_format(accept: &str) -> (&'static str, ImageFormat, ContentType) {
-    let a = accept.to_ascii_lowercase();
-    if a.contains("image/avif") {
-        ("avif", ImageFormat::Avif, ContentType::new("image", "avif"))
-    } else if a.contains("image/webp") {
-        ("webp", ImageFormat::WebP, ContentType::new("image", "webp"))
-    } else if a.contains("image/png") {
-        ("png", ImageFormat::Png, ContentType::PNG)
-    } else {
-        ("jpeg", ImageFormat::Jpeg, ContentType::JPEG)
+    match negotiate::preferred(accept, &["image/avif", "image/webp", "image/png"]) {
+        Some("image/avif") => ("avif", ImageFormat::Avif, ContentType::new("image", "avif")),
+        Some("image/webp") => ("webp", ImageFormat::WebP, ContentType::new("image", "webp")),
+        Some(_) => ("png", ImageFormat::Png, ContentType::PNG),
+        None => ("jpeg", ImageFormat::Jpeg, ContentType::JPEG),
+    }
+}
+
+// 下载原始头像并按尺寸、形状和格式转码，结果写入缓存；返回转码结果和原始抓取是否命中缓存
+async fn transcode(
+    ctx: &RequestContext,
+    image_service: &ImageService,
+    origin_url: &str,
+    transform: ImageTransform,
+    img_format: ImageFormat,
+    cache_key: &str,
+    ttl: Duration,
+) -> Result<(Vec<u8>, bool)> {
+    // 下载原始头像图像（复用托管的 ImageService，避免每次请求创建新 reqwest::Client）
+    let (raw_bytes, origin_cache_hit) = image_service.fetch_avatar(ctx, origin_url).await?;
+    if !matches!(img_format, ImageFormat::Avif | ImageFormat::WebP | ImageFormat::Png | ImageFormat::Jpeg) {
+        return Err(Error::Internal("Unsupported target image format".into()));
     }
+    ctx.check("avatar transcode")?;
+
+    // 解码、缩放和编码在阻塞线程中进行，同时进行的处理数受限，繁忙时返回 503
+    let out = transcode_limiter::run(move || avatar_service::process(raw_bytes, transform, img_format)).await??;
+
+    // 写入缓存（过期时间按来源配置，进程内缓存使用 avatars 命名空间的过期时间）
+    cache::backend().put(cache_key, out.clone(), Some(ttl)).await;
+    Ok((out, origin_cache_hit))
+}
+
+// 上传的头像按形状裁剪：从同尺寸的 PNG 变体（无损）处理，结果写入缓存
+async fn shape_uploaded(
+    ctx: &RequestContext,
+    transform: ImageTransform,
+    img_format: ImageFormat,
+    cache_key: &str,
+    ttl: Duration,
+) -> Result<Vec<u8>> {
+    let raw_bytes = avatar_service::read_variant(transform.width, ImageFormat::Png).await?;
+    ctx.check("avatar transcode")?;
+    let out = transcode_limiter::run(move || avatar_service::process(raw_bytes, transform, img_format)).await??;
+    cache::backend().put(cache_key, out.clone(), Some(ttl)).await;
+    Ok(out)
+}
+
+/// 头像查询参数
+#[derive(Debug, Default, FromForm)]
+struct AvatarQuery {
+    s: Option<String>,
+    source: Option<String>,
+    /// 替换来源地址模板中的 {id}，未指定时使用来源的 default_id
+    id: Option<String>,
+    /// id 的别名（QQ 号等数字 ID 习惯用 uid，用户名习惯用 user）
+    uid: Option<String>,
+    user: Option<String>,
+    /// Gravatar / Libravatar 等接受邮箱的来源：服务端计算哈希作为 id，原始邮箱不进入缓存 key 和原图地址
+    email: Option<String>,
+    /// 边长（像素，取 64 / 128 / 256 / 512 中不小于它的最小值，超过时为原图），优先于客户端提示和 w
+    size: Option<u32>,
+    /// circle / rounded：裁剪为圆形或圆角正方形（形状以外透明，客户端只接受 JPEG 时输出 PNG）
+    shape: Option<String>,
+    w: Option<u32>,
+    dpr: Option<f32>,
+    /// 为 true 时保留原图的 ICC 色彩配置和 EXIF（默认去除，见 transcode.strip_metadata）
+    keep_metadata: Option<bool>,
 }
 
-// 根据来源选择默认头像 URL
-fn pick_source(source: &str) -> &str {
-    match source.to_ascii_lowercase().as_str() {
-        "qq" => "https://q1.qlogo.cn/g?b=qq&nk=2271225249&s=640",
-        "github" | "gh" => "https://avatars.githubusercontent.com/u/69001561",
-        _ => "https://cdn.tnxg.top/images/avatar/main/Texas.png",
+impl AvatarQuery {
+    /// 请求的 id（依次取 id、uid、user，空值视为未指定）
+    fn requested_id(&self) -> Option<&str> {
+        [&self.id, &self.uid, &self.user]
+            .into_iter()
+            .filter_map(|v| v.as_deref())
+            .find(|v| !v.is_empty())
     }
 }
 
-#[get("/?<s>&<source>")]
+fn avatar_response(content_type: ContentType, data: Vec<u8>, source: &AvatarSource, cache_hit: bool) -> CustomResponse {
+    // 动图不转换为协商的格式
+    let content_type = match ImageService::animated_format(&data) {
+        Some(ImageFormat::Gif) => ContentType::GIF,
+        Some(ImageFormat::WebP) => ContentType::new("image", "webp"),
+        _ => content_type,
+    };
+    let ttl = source.ttl.as_secs();
+    CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("Cache-Control", format!("public, max-age={}, s-maxage={}", ttl, ttl * 2 / 3))
+        .with_header("Accept-CH", client_hints::ACCEPT_CH)
+        .with_vary(negotiate::VARY_ACCEPT)
+        .with_vary(client_hints::VARY)
+        .with_header("X-Avatar-Source", source.name.clone())
+        .with_etag()
+        .with_digest()
+        .with_cache(cache_hit)
+}
+
+// 来源见配置 [avatar.sources]，获取失败时依次尝试来源的 fallback（响应头 X-Avatar-Source 为实际使用的来源）
+// 尺寸优先使用查询参数 size（像素），其次按客户端提示选择，没有提示时使用查询参数 w（CSS 像素）和 dpr；都没有时返回原图
+// shape=circle|rounded 时缩放后裁剪为圆形或圆角正方形，各尺寸、形状和格式分别缓存
+// 转码结果默认去除元数据，keep_metadata=true 时保留 ICC 色彩配置和 EXIF（上传的头像变体始终不含元数据）
+// id 也可写作 uid 或 user（如 /avatar?source=qq&uid=12345），不同 id 分别缓存，未指定时使用来源的 default_id
+// 接受邮箱的来源使用 email 参数（如 /avatar?source=gravatar&email=...），Libravatar 按邮箱域名的 SRV 记录联邦查找服务器
+#[get("/?<query..>")]
 async fn get_avatar(
-    s: Option<&str>,
-    source: Option<&str>,
+    query: AvatarQuery,
+    hints: ClientHints,
     accept: &Accept,
+    ctx: RequestContext,
     image_service: &State<ImageService>,
 ) -> Result<CustomResponse> {
-    let src = s.or(source).unwrap_or("default");
-    let accept_str = accept.to_string();
-
-    if src.is_empty() {
+    let requested = query.s.as_deref().or(query.source.as_deref());
+    if requested == Some("") {
         return Err(Error::BadRequest(
             "Missing required parameter: s or source".into(),
         ));
     }
 
-    // Accept 头（如果通过查询参数未提供，则不用于协商）
-    let (fmt_key, img_format, content_type) = negotiate_format(&accept_str);
+    let shape = match query.shape.as_deref() {
+        Some(name) => Some(Shape::parse(name).ok_or_else(|| Error::BadRequest("shape must be circle or rounded".into()))?),
+        None => None,
+    };
 
-    let origin_url = pick_source(src);
-    let cache_key = format!("avatar:{}:{}", src, fmt_key);
+    // Accept 头（如果通过查询参数未提供，则不用于协商）；JPEG 不支持透明，有形状时改为 PNG
+    let (fmt_key, img_format, content_type) = match negotiate_format(&accept.to_string()) {
+        ("jpeg", ..) if shape.is_some() => ("png", ImageFormat::Png, ContentType::PNG),
+        negotiated => negotiated,
+    };
+    let requested_size = query.size.or_else(|| hints.requested_width(query.w, query.dpr));
+    let size = client_hints::pick_size(requested_size, client_hints::AVATAR_SIZES);
+    let transform = ImageTransform {
+        width: size,
+        height: size,
+        keep_metadata: query.keep_metadata.unwrap_or(false),
+        shape,
+        ..ImageTransform::default()
+    };
 
-    // 尝试缓存
-    if let Some(cached) = cache::get(&CACHE_BUCKET, &cache_key).await {
-        return Ok(CustomResponse::new(content_type, cached, Status::Ok)
-            .with_header("Cache-Control", "public, max-age=259200, s-maxage=172800")
-            .with_cache(true));
+    let email = query
+        .email
+        .as_deref()
+        .filter(|email| !email.is_empty())
+        .map(AvatarEmail::parse)
+        .transpose()?;
+
+    let registry = avatar_service::registry();
+    let chain = registry.chain(registry.get(requested));
+    if email.is_some() && !chain[0].email {
+        return Err(Error::BadRequest(format!("Avatar source {} does not accept email", chain[0].name)));
     }
+    let mut last_error = None;
+    for (i, source) in chain.iter().enumerate() {
+        // 邮箱哈希用于链中所有接受邮箱的来源；请求的 id 只用于请求的来源，其他 fallback 来源使用各自的 default_id
+        let requested_id = match &email {
+            Some(email) if source.email => Some(email.hash.as_str()),
+            _ if i == 0 => query.requested_id(),
+            _ => None,
+        };
+        let (mut origin_url, id) = source.resolve(requested_id)?;
+        if let Some(email) = email.as_ref().filter(|_| source.federated) {
+            origin_url = avatar_service::federated_url(&origin_url, email).await;
+        }
+        // 默认来源有上传的头像时使用预先转码的变体（按形状裁剪的结果按上传的头像分别缓存）
+        let uploaded = if registry.is_default(source) {
+            avatar_service::self_avatar().await
+        } else {
+            None
+        };
+        let cache_key = avatar_service::variant_key(source, id, &transform, uploaded.as_ref(), fmt_key);
+        if uploaded.is_some() && shape.is_none() {
+            match avatar_service::read_variant(size, img_format).await {
+                Ok(data) => return Ok(avatar_response(content_type, data, source, true)),
+                Err(e) => warn!("Uploaded avatar unavailable, using {}: {}", origin_url, e),
+            }
+        }
 
-    // 下载原始头像图像（复用托管的 ImageService，避免每次请求创建新 reqwest::Client）
-    let (raw_bytes, origin_cache_hit) = image_service.fetch_avatar(origin_url).await?;
-    let img = image::load_from_memory(&raw_bytes)
-        .map_err(|e| Error::Internal(format!("Failed to decode avatar: {}", e)))?;
-
-    let mut out: Vec<u8> = Vec::new();
-    match img_format {
-        ImageFormat::Avif | ImageFormat::WebP | ImageFormat::Jpeg => {
-            img.write_to(&mut std::io::Cursor::new(&mut out), img_format)
-                .map_err(|e| {
-                    Error::Internal(format!("Failed to encode {:?}: {}", img_format, e))
-                })?;
+        // 尝试缓存
+        if let Some(cached) = cache::backend().get(&cache_key).await {
+            return Ok(avatar_response(content_type, cached, source, true));
+        }
+
+        if uploaded.is_some() && shape.is_some() {
+            match shape_uploaded(&ctx, transform, img_format, &cache_key, source.ttl).await {
+                Ok(out) => return Ok(avatar_response(content_type, out, source, false)),
+                Err(e @ (Error::Unavailable(..) | Error::Timeout(_))) => return Err(e),
+                Err(e) => warn!("Uploaded avatar unavailable, using {}: {}", origin_url, e),
+            }
+        }
+
+        // 相同缓存 key 的并发请求共享一次下载和转码
+        let transcoded = TRANSCODES
+            .run(&cache_key, || {
+                transcode(&ctx, image_service, &origin_url, transform, img_format, &cache_key, source.ttl)
+            })
+            .await;
+        match transcoded {
+            // 这里表示底层原始抓取是否命中
+            Ok((out, origin_cache_hit)) => return Ok(avatar_response(content_type, out, source, origin_cache_hit)),
+            // 图片处理繁忙或超过请求时限时不再尝试其他来源
+            Err(e @ (Error::Unavailable(..) | Error::Timeout(_))) => return Err(e),
+            Err(e) => {
+                if i + 1 < chain.len() {
+                    warn!("Avatar source {} failed, falling back: {}", source.name, e);
+                }
+                last_error = Some(e);
+            }
         }
-        _ => return Err(Error::Internal("Unsupported target image format".into())),
     }
+    Err(last_error.unwrap_or_else(|| Error::NotFound("No avatar source available".into())))
+}
 
-    // 写入缓存
-    cache::put(&CACHE_BUCKET, cache_key.clone(), out.clone()).await;
+/// 上传头像的表单（multipart/form-data）
+#[derive(FromForm)]
+struct SelfAvatarUpload<'r> {
+    file: TempFile<'r>,
+}
 
-    Ok(
-        CustomResponse::new(content_type, out, Status::Ok)
-            .with_header("Cache-Control", "public, max-age=259200, s-maxage=172800")
-            .with_cache(origin_cache_hit), // 这里表示底层原始抓取是否命中
+/// 上传头像（JPEG / PNG / WebP），转码为全部尺寸和格式后替代默认来源
+#[put("/self", data = "<form>")]
+async fn upload_self(
+    admin: AdminGuard,
+    form: Form<SelfAvatarUpload<'_>>,
+    config: &State<Config>,
+) -> Result<Json<ApiResponse<SelfAvatar>>> {
+    let bytes = upload::read_temp_file(&form.file, config.avatar.max_upload_mb).await?;
+    let avatar = avatar_service::upload_self(bytes).await?;
+    AuditService::record(
+        "avatar.upload",
+        &admin.actor,
+        "self",
+        json!({ "sha256": &avatar.sha256, "width": avatar.width, "height": avatar.height, "size": avatar.size }),
     )
+    .await;
+    Ok(ApiResponse::success(avatar, "Avatar uploaded"))
+}
+
+/// 当前上传的头像（未上传时为 null）
+#[get("/self")]
+async fn get_self(_admin: AdminGuard) -> Json<ApiResponse<Option<SelfAvatar>>> {
+    ApiResponse::success(avatar_service::self_avatar().await, "Uploaded avatar")
+}
+
+/// 删除上传的头像，默认来源恢复使用配置的原图地址
+#[delete("/self")]
+async fn delete_self(admin: AdminGuard) -> Result<Json<ApiResponse<SelfAvatar>>> {
+    let avatar = avatar_service::delete_self()
+        .await?
+        .ok_or_else(|| Error::NotFound("No uploaded avatar".into()))?;
+    AuditService::record("avatar.delete", &admin.actor, "self", json!({ "sha256": &avatar.sha256 })).await;
+    Ok(ApiResponse::success(avatar, "Avatar deleted"))
 }
 
 pub fn routes() -> Vec<Route> {
-    routes![get_avatar]
+    routes![get_avatar, upload_self, get_self, delete_self]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_id() {
+        let query = |id: Option<&str>, uid: Option<&str>, user: Option<&str>| AvatarQuery {
+            id: id.map(str::to_string),
+            uid: uid.map(str::to_string),
+            user: user.map(str::to_string),
+            ..AvatarQuery::default()
+        };
+        assert_eq!(query(None, None, None).requested_id(), None);
+        assert_eq!(query(None, Some("12345"), None).requested_id(), Some("12345"));
+        assert_eq!(query(None, None, Some("foo")).requested_id(), Some("foo"));
+        assert_eq!(query(Some("1"), Some("2"), Some("3")).requested_id(), Some("1"));
+        assert_eq!(query(Some(""), None, Some("foo")).requested_id(), Some("foo"));
+    }
 }
@@ -0,0 +1,46 @@
+use crate::utils::badge;
+use crate::utils::custom_response::CustomResponse;
+use crate::{Error, Result};
+use rocket::http::{ContentType, Status};
+use rocket::{get, routes, Route};
+
+/// 标签 / 数值的最大长度（字符数）
+const MAX_TEXT_LEN: usize = 64;
+
+/// 自定义徽章
+///
+/// 查询参数：
+/// - label: 左侧标签文本（必需）
+/// - value: 右侧数值文本（必需）
+/// - color: 右侧背景色，预设名称（brightgreen、red、blue 等）或十六进制（默认 blue）
+/// - label_color: 左侧背景色（默认 grey）
+///
+/// 示例：/badge/custom?label=uptime&value=99.9%25&color=brightgreen
+#[get("/custom?<label>&<value>&<color>&<label_color>")]
+async fn custom_badge(
+    label: &str,
+    value: &str,
+    color: Option<&str>,
+    label_color: Option<&str>,
+) -> Result<CustomResponse> {
+    if label.chars().count() > MAX_TEXT_LEN || value.chars().count() > MAX_TEXT_LEN {
+        return Err(Error::BadRequest(format!(
+            "label and value must be at most {} characters",
+            MAX_TEXT_LEN
+        )));
+    }
+    let color = badge::parse_color(color.unwrap_or("blue"))
+        .ok_or_else(|| Error::BadRequest("Invalid color".into()))?;
+    let label_color = badge::parse_color(label_color.unwrap_or("grey"))
+        .ok_or_else(|| Error::BadRequest("Invalid label_color".into()))?;
+
+    // 参数完全由客户端决定，不写入共享缓存（否则任意请求都能挤掉真实的缓存项）；渲染开销很小，每次直接生成
+    let svg = badge::render(label, value, &label_color, &color).into_bytes();
+
+    Ok(CustomResponse::new(ContentType::SVG, svg, Status::Ok)
+        .with_header("Cache-Control", "public, max-age=300, s-maxage=300"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![custom_badge]
+}
@@ -0,0 +1,39 @@
+use crate::services::audit_service::AuditService;
+use crate::services::bench_service::{self, BenchResult};
+use crate::utils::auth::AdminGuard;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use rocket::serde::json::Json;
+use rocket::{post, routes, Route};
+use tokio::sync::Mutex;
+
+// 同一时间只允许运行一组基准测试，避免拖垮线上服务
+static BENCH_LOCK: Mutex<()> = Mutex::const_new(());
+
+// 手动触发图片管线与缓存基准测试（CPU 密集，建议在低峰期调用）
+#[post("/?<iterations>")]
+async fn run_bench(admin: AdminGuard, iterations: Option<u32>) -> Result<Json<ApiResponse<Vec<BenchResult>>>> {
+    let iterations = iterations.unwrap_or(5).clamp(1, 50);
+    let _guard = BENCH_LOCK
+        .try_lock()
+        .map_err(|_| Error::Conflict("A benchmark is already running".to_string()))?;
+
+    let mut results = tokio::task::spawn_blocking(move || bench_service::run_image_benchmarks(iterations))
+        .await
+        .map_err(|e| Error::Internal(format!("Benchmark task failed: {}", e)))??;
+    results.extend(bench_service::run_cache_benchmarks(iterations).await);
+
+    AuditService::record(
+        "bench.run",
+        &admin.actor,
+        "image_pipeline",
+        serde_json::json!({ "iterations": iterations }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(results, "Benchmark finished"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![run_bench]
+}
@@ -0,0 +1,193 @@
+use crate::services::audit_service::AuditService;
+use crate::services::cdn_service;
+use crate::services::command_service::{CommandService, CACHE_NAMESPACES};
+use crate::services::memory_service::MemoryManager;
+use crate::utils::auth::AdminGuard;
+use crate::utils::cache;
+use crate::utils::request_counter;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, routes, Route, State};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// 列出键时的默认数量和上限
+const DEFAULT_KEY_LIMIT: usize = 100;
+const MAX_KEY_LIMIT: usize = 1000;
+
+/// 缓存命名空间及其用量
+#[derive(Debug, Serialize)]
+pub struct NamespaceInfo {
+    name: &'static str,
+    /// 存储后端（memory 命名空间为 memory / redis）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backend: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    /// 是否可以按前缀列出和删除键
+    keyed: bool,
+    /// 是否已启用（cdn 未配置时为 false）
+    enabled: bool,
+    /// 进程内缓存按命名空间划分的各个缓存及其占用（仅 memory 后端）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    buckets: Option<Vec<cache::BucketUsage>>,
+}
+
+// 列出缓存命名空间及条目数、占用大小（memory 命名空间附带各进程内缓存的占用）
+#[get("/")]
+async fn namespaces(_admin: AdminGuard) -> Result<Json<ApiResponse<Vec<NamespaceInfo>>>> {
+    let backend = cache::backend();
+    let usage = backend.usage().await;
+    let (disk_files, disk_bytes) = tokio::task::spawn_blocking(cache::disk_usage)
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?;
+
+    let info = CACHE_NAMESPACES
+        .iter()
+        .map(|&name| {
+            let mut info = NamespaceInfo {
+                name,
+                backend: None,
+                entries: None,
+                bytes: None,
+                keyed: false,
+                enabled: true,
+                buckets: None,
+            };
+            match name {
+                "memory" => {
+                    info.backend = Some(backend.name());
+                    info.entries = usage.map(|(entries, _)| entries);
+                    info.bytes = usage.map(|(_, bytes)| bytes);
+                    info.keyed = true;
+                    // Redis 后端不使用进程内缓存
+                    if !backend.shared() {
+                        info.buckets = Some(cache::bucket_usage());
+                    }
+                }
+                "disk" => {
+                    info.entries = Some(disk_files);
+                    info.bytes = Some(disk_bytes);
+                }
+                "cdn" => info.enabled = cdn_service::get().is_some(),
+                _ => {}
+            }
+            info
+        })
+        .collect();
+    Ok(ApiResponse::success(info, "Cache namespaces"))
+}
+
+// 硬盘缓存的占用、容量限制、最近一次清理的结果和发现的损坏文件数
+#[get("/disk")]
+async fn disk(_admin: AdminGuard) -> Result<Json<ApiResponse<Value>>> {
+    let (files, bytes) = tokio::task::spawn_blocking(cache::disk_usage)
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?;
+    Ok(ApiResponse::success(
+        json!({
+            "files": files,
+            "bytes": bytes,
+            "limits": cache::disk_limits(),
+            "last_cleanup": cache::last_cleanup(),
+            "corrupt_entries": cache::corrupt_entries(),
+        }),
+        "Disk cache",
+    ))
+}
+
+// 各命名空间的命中、未命中、写入和淘汰计数（进程启动以来），以及当前占用
+#[get("/stats")]
+async fn stats(_admin: AdminGuard) -> Result<Json<ApiResponse<Value>>> {
+    cache::run_pending_tasks().await;
+    let (memory_entries, memory_bytes) = cache::memory_usage();
+    let (disk_files, disk_bytes) = tokio::task::spawn_blocking(cache::disk_usage)
+        .await
+        .map_err(|e| Error::Internal(e.to_string()))?;
+    let (memory, disk) = cache::total_stats();
+    Ok(ApiResponse::success(
+        json!({
+            "uptime_secs": request_counter::uptime_secs(),
+            "backend": cache::backend().name(),
+            "schema": { "version": cache::SCHEMA_VERSION, "epoch": cache::epoch() },
+            "totals": { "memory": memory, "disk": disk },
+            "namespaces": cache::stats(),
+            "usage": {
+                "memory_entries": memory_entries,
+                "memory_bytes": memory_bytes,
+                "disk_files": disk_files,
+                "disk_bytes": disk_bytes,
+            },
+        }),
+        "Cache statistics",
+    ))
+}
+
+// 按前缀列出缓存键（硬盘缓存按键的哈希存储，无法列出）
+#[get("/keys?<prefix>&<limit>")]
+async fn list_keys(_admin: AdminGuard, prefix: Option<&str>, limit: Option<usize>) -> Result<Json<ApiResponse<Value>>> {
+    let prefix = prefix.unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_KEY_LIMIT).clamp(1, MAX_KEY_LIMIT);
+    let backend = cache::backend();
+    let keys = backend.keys(prefix, limit).await;
+    Ok(ApiResponse::success(
+        json!({ "backend": backend.name(), "prefix": prefix, "keys": keys }),
+        "Cache keys",
+    ))
+}
+
+// 按键（所有缓存层，含硬盘缓存和预压缩变体）或前缀（仅缓存后端）删除缓存
+#[delete("/keys?<key>&<prefix>")]
+async fn invalidate(admin: AdminGuard, key: Option<&str>, prefix: Option<&str>) -> Result<Json<ApiResponse<Value>>> {
+    let (by, target, removed) = match (key.filter(|k| !k.is_empty()), prefix.filter(|p| !p.is_empty())) {
+        (Some(key), None) => ("key", key, cache::invalidate(key).await),
+        (None, Some(prefix)) => ("prefix", prefix, cache::backend().remove_prefix(prefix).await),
+        _ => return Err(Error::BadRequest("Specify exactly one of key or prefix".into())),
+    };
+
+    AuditService::record(
+        "cache.invalidate",
+        &admin.actor,
+        target,
+        json!({ "by": by, "removed": removed }),
+    )
+    .await;
+
+    Ok(ApiResponse::success(
+        json!({ "by": by, "target": target, "removed": removed }),
+        "Cache entries invalidated",
+    ))
+}
+
+// 递增缓存纪元，使派生缓存失效（与 cache.bump_epoch 命令相同）
+#[post("/epoch")]
+async fn bump_epoch(admin: AdminGuard, memory_manager: &State<Arc<MemoryManager>>) -> Result<Json<ApiResponse<Value>>> {
+    let outcome = CommandService::execute("cache.bump_epoch", json!({}), None, &admin.actor, memory_manager).await?;
+    Ok(ApiResponse::success(outcome.output, "Cache epoch bumped"))
+}
+
+// 清空整个命名空间（与 cache.purge 命令相同）
+#[delete("/<namespace>")]
+async fn purge(
+    admin: AdminGuard,
+    namespace: &str,
+    memory_manager: &State<Arc<MemoryManager>>,
+) -> Result<Json<ApiResponse<Value>>> {
+    let outcome = CommandService::execute(
+        "cache.purge",
+        json!({ "namespace": namespace }),
+        None,
+        &admin.actor,
+        memory_manager,
+    )
+    .await?;
+    Ok(ApiResponse::success(outcome.output, "Cache namespace purged"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![namespaces, disk, stats, list_keys, invalidate, bump_epoch, purge]
+}
@@ -0,0 +1,21 @@
+use crate::config::settings::Config;
+use crate::services::calendar_service::CalendarService;
+use crate::utils::custom_response::CustomResponse;
+use crate::Result;
+use rocket::http::{ContentType, Status};
+use rocket::{get, routes, Route, State};
+
+/// iCalendar 订阅：配置中的事件 + 数据库中的事件（纪念日、维护窗口等）
+#[get("/calendar.ics")]
+async fn calendar_ics(config: &State<Config>) -> Result<CustomResponse> {
+    let ics = CalendarService::ics(&config.calendar).await?;
+    let cache_control = format!("public, max-age={}", config.calendar.max_age_secs);
+
+    Ok(CustomResponse::new(ContentType::Calendar, ics.into_bytes(), Status::Ok)
+        .with_header("Cache-Control", cache_control)
+        .with_header("Content-Disposition", "inline; filename=\"calendar.ics\""))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![calendar_ics]
+}
@@ -0,0 +1,39 @@
+use crate::services::audit_service::AuditService;
+use crate::services::dashboard_service::{DashboardPreferences, DashboardService};
+use crate::utils::auth::AdminAuth;
+use crate::utils::response::ApiResponse;
+use crate::Result;
+use rocket::serde::json::Json;
+use rocket::{get, put, routes, Route};
+
+// 获取当前管理员的仪表盘设置（管理员令牌或管理后台登录会话）
+#[get("/preferences")]
+async fn get_preferences(auth: AdminAuth) -> Result<Json<ApiResponse<DashboardPreferences>>> {
+    let admin = auth.0;
+    let prefs = DashboardService::get_preferences(&admin.id).await?;
+    Ok(ApiResponse::success(prefs, "Dashboard preferences"))
+}
+
+// 保存当前管理员的仪表盘设置
+#[put("/preferences", data = "<data>")]
+async fn put_preferences(
+    auth: AdminAuth,
+    data: Json<DashboardPreferences>,
+) -> Result<Json<ApiResponse<DashboardPreferences>>> {
+    let admin = auth.0;
+    let prefs = DashboardService::save_preferences(&admin.id, data.into_inner()).await?;
+
+    AuditService::record(
+        "dashboard.preferences",
+        &admin.actor,
+        &admin.id,
+        serde_json::to_value(&prefs).unwrap_or_default(),
+    )
+    .await;
+
+    Ok(ApiResponse::success(prefs, "Dashboard preferences saved"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![get_preferences, put_preferences]
+}
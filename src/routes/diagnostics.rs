@@ -0,0 +1,37 @@
+use crate::utils::auth::AdminGuard;
+use crate::utils::response::ApiResponse;
+use crate::utils::slow_requests::{self, SlowRequest};
+use crate::utils::transcode_limiter::{self, LimiterStatus};
+use rocket::serde::json::Json;
+use rocket::serde::Serialize;
+use rocket::{get, routes, Route};
+
+#[derive(Debug, Serialize)]
+pub struct SlowRequests {
+    /// 慢请求阈值（毫秒，未开启时为 None）
+    threshold_ms: Option<u64>,
+    requests: Vec<SlowRequest>,
+}
+
+// 最近的慢请求及其上游调用耗时、缓存判定和内存压力（新的在前）
+#[get("/slow?<limit>")]
+fn slow(_admin: AdminGuard, limit: Option<usize>) -> Json<ApiResponse<SlowRequests>> {
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    ApiResponse::success(
+        SlowRequests {
+            threshold_ms: slow_requests::threshold().map(|t| t.as_millis() as u64),
+            requests: slow_requests::recent(limit),
+        },
+        "Slow requests",
+    )
+}
+
+// 图片处理的并发占用和被拒绝的请求数
+#[get("/transcode")]
+fn transcode(_admin: AdminGuard) -> Json<ApiResponse<LimiterStatus>> {
+    ApiResponse::success(transcode_limiter::status(), "Transcode limiter")
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![slow, transcode]
+}
@@ -1,10 +1,17 @@
 use rocket::{Route, post, routes, State};
-use rocket::serde::{json::Json, Deserialize};
+use rocket::serde::{json::Json, Deserialize, Serialize};
 use crate::config::settings::Config;
+use crate::services::abuse_service::{AbuseService, AbuseSignal};
 use crate::services::email_service::EmailService;
+use crate::services::feature_service::EmailVerification;
 use crate::services::verify_service::VerificationService;
+use crate::utils::feature_gate::FeatureGate;
+use crate::utils::idempotency::Idempotency;
+use crate::utils::ip_filter::ClientAddr;
 use crate::utils::response::ApiResponse;
-use crate::{Result, Error};
+use crate::utils::validation::{Valid, Validate, Validator};
+use crate::Result;
+use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 pub struct SendEmailRequest {
@@ -17,52 +24,73 @@ pub struct VerifyEmailRequest {
     code: String,
 }
 
-// 发送邮件路由
-#[post("/send", data = "<data>")]
-async fn send_email(data: Json<SendEmailRequest>, config: &State<Config>) -> Result<Json<ApiResponse<String>>> {
-    // 验证邮箱格式（基础 RFC 5321 检查）
-    let email = data.email.trim();
-    let is_valid_email = {
-        let parts: Vec<&str> = email.splitn(2, '@').collect();
-        parts.len() == 2
-            && !parts[0].is_empty()
-            && parts[0].len() <= 64
-            && !parts[1].is_empty()
-            && parts[1].contains('.')
-            && parts[1].len() <= 255
-            && !parts[1].starts_with('.')
-            && !parts[1].ends_with('.')
-            && !parts[0].contains(' ')
-            && !parts[1].contains(' ')
-    };
-    if !is_valid_email {
-        return Err(Error::BadRequest("Invalid email format".to_string()));
+/// 发送结果：有效期内重复请求会重发同一验证码
+#[derive(Debug, Serialize)]
+pub struct SendEmailResponse {
+    /// 验证码剩余有效秒数
+    pub expires_in_secs: u64,
+    pub resent: bool,
+}
+
+impl Validate for SendEmailRequest {
+    fn check(&self, v: &mut Validator) {
+        v.email("email", &self.email);
     }
-    
-    // 生成验证码
-    let verification_code = VerificationService::generate_verification_code();
-    
-    // 存储验证码
-    VerificationService::store_verification_code(&data.email, &verification_code).await?;
+}
+
+impl Validate for VerifyEmailRequest {
+    fn check(&self, v: &mut Validator) {
+        v.email("email", &self.email).length("code", &self.code, 1, 32);
+    }
+}
+
+// 发送邮件路由（有效期内重复请求重发同一验证码，不延长有效期）
+#[post("/send", data = "<data>")]
+async fn send_email(
+    _feature: FeatureGate<EmailVerification>,
+    data: Valid<SendEmailRequest>,
+    config: &State<Config>,
+    _idempotency: Idempotency,
+) -> Result<Json<ApiResponse<SendEmailResponse>>> {
+    // 签发验证码
+    let issued = VerificationService::issue_code(&data.email).await?;
     
     // 创建邮件服务
     let email_service = EmailService::new(config.email.clone())?;
     
     // 发送验证邮件
-    email_service.send_verification_email(&data.email, &verification_code).await?;
+    email_service.send_verification_email(&data.email, &issued.code).await?;
     
-    Ok(ApiResponse::success("Verification email sent successfully".to_string(), "验证邮件已发送"))
+    let message = if issued.resent { "验证邮件已重新发送" } else { "验证邮件已发送" };
+    Ok(ApiResponse::success(
+        SendEmailResponse {
+            expires_in_secs: issued.expires_in_secs,
+            resent: issued.resent,
+        },
+        message,
+    ))
 }
 
 // 验证邮箱路由
 #[post("/verify", data = "<data>")]
-async fn verify_email(data: Json<VerifyEmailRequest>) -> Result<Json<ApiResponse<bool>>> {
-    // 验证验证码
-    let verified = VerificationService::verify_code(&data.email, &data.code).await?;
+async fn verify_email(
+    data: Valid<VerifyEmailRequest>,
+    client: ClientAddr,
+    abuse: &State<Arc<AbuseService>>,
+) -> Result<Json<ApiResponse<bool>>> {
+    // 验证验证码（失败计入滥用检测）
+    let verified = match VerificationService::verify_code(&data.email, &data.code).await {
+        Ok(v) => v,
+        Err(e) => {
+            abuse.record(client.0, AbuseSignal::VerificationFailure).await;
+            return Err(e);
+        }
+    };
     
     if verified {
         Ok(ApiResponse::success(true, "Email verified successfully"))
     } else {
+        abuse.record(client.0, AbuseSignal::VerificationFailure).await;
         Ok(ApiResponse::success(false, "Verification code is invalid or expired"))
     }
 }
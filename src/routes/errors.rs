@@ -0,0 +1,29 @@
+use crate::utils::auth::AdminGuard;
+use crate::utils::error_tracker::{ErrorRecord, ErrorSummary, ERROR_TRACKER};
+use crate::utils::response::ApiResponse;
+use rocket::serde::json::Json;
+use rocket::serde::Serialize;
+use rocket::{get, routes, Route};
+
+#[derive(Debug, Serialize)]
+pub struct RecentErrors {
+    summary: ErrorSummary,
+    errors: Vec<ErrorRecord>,
+}
+
+// 最近的错误（类型、路由、次数、最后出现时间、请求 ID）及错误率
+#[get("/recent?<limit>")]
+fn recent_errors(_admin: AdminGuard, limit: Option<usize>) -> Json<ApiResponse<RecentErrors>> {
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    ApiResponse::success(
+        RecentErrors {
+            summary: ERROR_TRACKER.summary(),
+            errors: ERROR_TRACKER.recent(limit),
+        },
+        "Recent errors",
+    )
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![recent_errors]
+}
@@ -0,0 +1,429 @@
+use crate::config::settings::Config;
+use crate::routes::email::SendEmailResponse;
+use crate::routes::render::MarkdownResponse;
+use crate::services::blurhash_service::Blurhash;
+use crate::services::feature_service;
+use crate::services::link_service::{CountEntry, LinkStats};
+use crate::services::maintenance_service;
+use crate::services::palette_service::Palette;
+use crate::services::search_service::{SearchGroup, SearchHit, SearchResults};
+use crate::utils::auth::AdminGuard;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use rocket::http::uri::fmt::Path;
+use rocket::http::uri::Segments;
+use rocket::http::Method;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route, State};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+
+/// 未配置 cdn.public_base_url 时示例使用的站点地址
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:8000";
+
+/// 需要管理员令牌的路径前缀（示例中附带 Authorization 头）
+const ADMIN_PREFIXES: &[&str] = &[
+    "/api/admin",
+    "/api/bench",
+    "/api/boot-report",
+    "/api/cache",
+    "/api/dashboard",
+    "/api/diagnostics",
+    "/api/errors",
+    "/api/examples",
+    "/api/logs",
+    "/avatar/self",
+];
+
+/// 路由的调用示例
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteExample {
+    pub method: String,
+    /// 路由模板（含查询参数），如 /images/palette?<url>
+    pub route: String,
+    pub curl: String,
+    /// 请求体示例（JSON）
+    pub request: Option<Value>,
+    /// 响应类型
+    pub content_type: &'static str,
+    /// 响应示例（由响应类型的示例值序列化得到），未登记示例或非 JSON 响应时为空
+    pub response: Option<Value>,
+}
+
+/// 登记的示例数据
+#[derive(Default)]
+struct Sample {
+    /// 路径参数的示例值（未列出的参数使用 example）
+    params: &'static [(&'static str, &'static str)],
+    /// 查询字符串（未指定时按路由模板中的参数生成）
+    query: Option<&'static str>,
+    body: Option<Value>,
+    /// 图片等二进制响应
+    binary: Option<&'static str>,
+    response: Option<Value>,
+}
+
+fn api_response<T: Serialize>(data: T, message: &str) -> Option<Value> {
+    serde_json::to_value(ApiResponse::success(data, message).into_inner()).ok()
+}
+
+fn image(query: &'static str) -> Sample {
+    Sample {
+        query: Some(query),
+        binary: Some("image/*"),
+        ..Sample::default()
+    }
+}
+
+/// 按方法和路由路径登记的示例（响应由对应的响应类型构造，字段变化时示例随之变化）
+fn sample(method: Method, path: &str) -> Option<Sample> {
+    let avatar_url = "https://cdn.tnxg.top/images/avatar/main.jpg";
+    let sample = match (method, path) {
+        (Method::Get, "/images/wallpaper" | "/images/wallpaper_height") => image("w=1280"),
+        (Method::Get, "/images/proxy") => image("url=https://cdn.tnxg.top/images/wallpaper/1.jpg&w=640&format=webp"),
+        (Method::Get, "/images/og") => image("title=Hello&subtitle=World"),
+        (Method::Get, "/images/wallpaper/<id>") => Sample {
+            params: &[("id", "1")],
+            binary: Some("image/*"),
+            ..Sample::default()
+        },
+        (Method::Get, "/avatar") => image("size=128&shape=circle"),
+        (Method::Get, "/status/ncm/widget.svg") => Sample {
+            query: Some("q=515522946"),
+            binary: Some("image/svg+xml"),
+            ..Sample::default()
+        },
+        (Method::Get, "/friend-avatar") => image("url=https://cdn.tnxg.top/images/avatar/main.jpg"),
+        (Method::Get, "/images/palette") => Sample {
+            query: Some("url=https://cdn.tnxg.top/images/avatar/main.jpg"),
+            response: api_response(
+                Palette {
+                    url: avatar_url.to_string(),
+                    dominant: "#1e90ff".to_string(),
+                    palette: vec!["#1e90ff".to_string(), "#f5f5f7".to_string(), "#2d2d2d".to_string()],
+                },
+                "Palette computed",
+            ),
+            ..Sample::default()
+        },
+        (Method::Get, "/images/blurhash") => Sample {
+            query: Some("url=https://cdn.tnxg.top/images/avatar/main.jpg"),
+            response: api_response(
+                Blurhash {
+                    url: avatar_url.to_string(),
+                    blurhash: "LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_string(),
+                    width: 640,
+                    height: 640,
+                },
+                "Blurhash computed",
+            ),
+            ..Sample::default()
+        },
+        (Method::Get, "/search") => Sample {
+            query: Some("q=rust&page=1&per_page=20"),
+            response: api_response(
+                SearchResults {
+                    query: "rust".to_string(),
+                    page: 1,
+                    per_page: 20,
+                    links: SearchGroup {
+                        total: 1,
+                        items: vec![SearchHit {
+                            id: "65f0c0ffee0000000000beef".to_string(),
+                            title: "Rust 博客".to_string(),
+                            url: Some("https://blog.example.com".to_string()),
+                            snippet: Some("关于 Rust 的笔记".to_string()),
+                            score: 1.5,
+                        }],
+                    },
+                },
+                "Search results",
+            ),
+            ..Sample::default()
+        },
+        (Method::Get, "/links/<id>/stats") => Sample {
+            params: &[("id", "65f0c0ffee0000000000beef")],
+            query: Some("days=7"),
+            response: api_response(
+                LinkStats {
+                    link_id: "65f0c0ffee0000000000beef".to_string(),
+                    days: 7,
+                    total: 12,
+                    daily: vec![CountEntry {
+                        key: "2026-01-01".to_string(),
+                        clicks: 12,
+                    }],
+                    referrers: vec![CountEntry {
+                        key: "blog.example.com".to_string(),
+                        clicks: 12,
+                    }],
+                    countries: vec![CountEntry {
+                        key: "CN".to_string(),
+                        clicks: 12,
+                    }],
+                },
+                "Link stats",
+            ),
+            ..Sample::default()
+        },
+        (Method::Post, "/api/render/markdown") => Sample {
+            body: Some(json!({ "markdown": "# Hello\n\n**world**" })),
+            response: api_response(
+                MarkdownResponse {
+                    html: "<h1>Hello</h1>\n<p><strong>world</strong></p>\n".to_string(),
+                },
+                "Rendered",
+            ),
+            ..Sample::default()
+        },
+        (Method::Post, "/email/send") => Sample {
+            body: Some(json!({ "email": "user@example.com" })),
+            response: api_response(
+                SendEmailResponse {
+                    expires_in_secs: 600,
+                    resent: false,
+                },
+                "验证邮件已发送",
+            ),
+            ..Sample::default()
+        },
+        (Method::Post, "/email/verify") => Sample {
+            body: Some(json!({ "email": "user@example.com", "code": "123456" })),
+            response: api_response(true, "Email verified successfully"),
+            ..Sample::default()
+        },
+        (Method::Get, "/api/admin/features") => Sample {
+            response: api_response(feature_service::list(), "Features"),
+            ..Sample::default()
+        },
+        (Method::Put, "/api/admin/features/<name>") => Sample {
+            params: &[("name", "og_image")],
+            body: Some(json!({ "enabled": false, "reason": "字体更新中" })),
+            response: api_response(
+                feature_service::list().into_iter().find(|f| f.name == "og_image"),
+                "Feature disabled",
+            ),
+            ..Sample::default()
+        },
+        (Method::Get, "/api/admin/maintenance") => Sample {
+            response: api_response(maintenance_service::current(), "Maintenance status"),
+            ..Sample::default()
+        },
+        (Method::Put, "/api/admin/maintenance") => Sample {
+            body: Some(json!({ "enabled": true, "message": "数据库迁移中", "retry_after_secs": 600 })),
+            response: api_response(maintenance_service::current(), "Maintenance mode enabled"),
+            ..Sample::default()
+        },
+        _ => return None,
+    };
+    Some(sample)
+}
+
+/// 路由模板中的参数名（去掉尖括号和 ..）
+fn param_name(segment: &str) -> Option<&str> {
+    segment
+        .strip_prefix('<')
+        .and_then(|s| s.strip_suffix('>'))
+        .map(|s| s.trim_end_matches(".."))
+}
+
+/// 生成路由的调用示例
+fn example(method: Method, path: &str, query: Option<&str>, base_url: &str) -> RouteExample {
+    let sample = sample(method, path).unwrap_or_default();
+
+    let filled_path: Vec<String> = path
+        .split('/')
+        .map(|segment| match param_name(segment) {
+            Some(name) => sample
+                .params
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or("example", |(_, v)| v)
+                .to_string(),
+            None => segment.to_string(),
+        })
+        .collect();
+    let mut url = format!("{}{}", base_url.trim_end_matches('/'), filled_path.join("/"));
+    let query_string = match sample.query {
+        Some(q) => q.to_string(),
+        // 未登记示例时按模板中的单个参数生成（<query..> 等结构化参数省略）
+        None => query
+            .unwrap_or("")
+            .split('&')
+            .filter(|segment| !segment.ends_with("..>"))
+            .filter_map(param_name)
+            .map(|name| format!("{}=example", name))
+            .collect::<Vec<_>>()
+            .join("&"),
+    };
+    if !query_string.is_empty() {
+        url.push('?');
+        url.push_str(&query_string);
+    }
+
+    let mut curl = String::from("curl");
+    if method != Method::Get {
+        curl.push_str(&format!(" -X {}", method.as_str()));
+    }
+    curl.push_str(&format!(" '{}'", url));
+    if ADMIN_PREFIXES.iter().any(|p| path == *p || path.starts_with(&format!("{}/", p))) {
+        curl.push_str(" -H \"Authorization: Bearer $ADMIN_TOKEN\"");
+    }
+    if let Some(body) = &sample.body {
+        let body = body.to_string().replace('\'', "'\\''");
+        curl.push_str(&format!(" -H 'Content-Type: application/json' -d '{}'", body));
+    }
+    if sample.binary.is_some() {
+        curl.push_str(" -o output");
+    }
+
+    RouteExample {
+        method: method.as_str().to_string(),
+        route: match query {
+            Some(q) => format!("{}?{}", path, q),
+            None => path.to_string(),
+        },
+        curl,
+        request: sample.body,
+        content_type: sample.binary.unwrap_or("application/json"),
+        response: sample.response,
+    }
+}
+
+/// 路由模板是否匹配请求的路径（模板中的参数匹配任意段，<x..> 匹配剩余全部段）
+fn path_matches(template: &str, path: &str) -> bool {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    for (i, segment) in template.iter().enumerate() {
+        if segment.starts_with('<') && segment.ends_with("..>") {
+            return true;
+        }
+        match path.get(i) {
+            Some(p) if p == segment || param_name(segment).is_some() => {}
+            _ => return false,
+        }
+    }
+    template.len() == path.len()
+}
+
+/// 已挂载的路由（方法、路径模板、查询模板），不含 /__ 开头的内部路由
+struct MountedRoutes(Vec<(Method, String, Option<String>)>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MountedRoutes {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let mut routes: Vec<_> = req
+            .rocket()
+            .routes()
+            .filter(|r| !r.uri.path().starts_with("/__"))
+            .map(|r| (r.method, r.uri.path().to_string(), r.uri.query().map(str::to_string)))
+            .collect();
+        routes.sort_by(|a, b| (&a.1, a.0.as_str()).cmp(&(&b.1, b.0.as_str())));
+        routes.dedup();
+        Outcome::Success(MountedRoutes(routes))
+    }
+}
+
+/// 开发构建中任何人可访问，发布构建中只对管理员开放
+fn ensure_allowed(admin: Option<AdminGuard>) -> Result<()> {
+    if cfg!(debug_assertions) || admin.is_some() {
+        return Ok(());
+    }
+    Err(Error::Unauthorized("Admin token required".to_string()))
+}
+
+fn base_url(config: &Config) -> &str {
+    match config.cdn.public_base_url.as_str() {
+        "" => DEFAULT_BASE_URL,
+        url => url,
+    }
+}
+
+// 全部路由的调用示例
+#[get("/")]
+fn list(admin: Option<AdminGuard>, mounted: MountedRoutes, config: &State<Config>) -> Result<Json<ApiResponse<Vec<RouteExample>>>> {
+    ensure_allowed(admin)?;
+    let examples = mounted
+        .0
+        .iter()
+        .map(|(method, path, query)| example(*method, path, query.as_deref(), base_url(config)))
+        .collect();
+    Ok(ApiResponse::success(examples, "Route examples"))
+}
+
+// 指定路由的调用示例：路径可以是路由模板（/api/examples/links/<id>/stats）或实际路径（/api/examples/links/abc/stats），
+// method 未指定时返回该路径下全部方法的示例
+#[get("/<route..>?<method>", rank = 2)]
+fn route_example(
+    admin: Option<AdminGuard>,
+    route: Segments<'_, Path>,
+    method: Option<&str>,
+    mounted: MountedRoutes,
+    config: &State<Config>,
+) -> Result<Json<ApiResponse<Vec<RouteExample>>>> {
+    ensure_allowed(admin)?;
+    let path = format!("/{}", route.collect::<Vec<_>>().join("/"));
+    let examples: Vec<RouteExample> = mounted
+        .0
+        .iter()
+        .filter(|(m, template, _)| path_matches(template, &path) && method.is_none_or(|name| m.as_str().eq_ignore_ascii_case(name)))
+        .map(|(m, template, query)| example(*m, template, query.as_deref(), base_url(config)))
+        .collect();
+    if examples.is_empty() {
+        return Err(Error::NotFound(format!("No route matches {}", path)));
+    }
+    Ok(ApiResponse::success(examples, "Route examples"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![list, route_example]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::admin::{FeatureToggleRequest, MaintenanceRequest};
+    use crate::routes::email::{SendEmailRequest, VerifyEmailRequest};
+    use crate::routes::render::MarkdownRequest;
+    use serde::de::DeserializeOwned;
+
+    fn parses<T: DeserializeOwned>(method: Method, path: &str) {
+        let body = sample(method, path).and_then(|s| s.body).unwrap();
+        assert!(serde_json::from_value::<T>(body).is_ok(), "{} {}", method, path);
+    }
+
+    #[test]
+    fn test_examples() {
+        // 请求体示例与请求类型一致
+        parses::<MarkdownRequest>(Method::Post, "/api/render/markdown");
+        parses::<SendEmailRequest>(Method::Post, "/email/send");
+        parses::<VerifyEmailRequest>(Method::Post, "/email/verify");
+        parses::<FeatureToggleRequest>(Method::Put, "/api/admin/features/<name>");
+        parses::<MaintenanceRequest>(Method::Put, "/api/admin/maintenance");
+
+        let e = example(Method::Get, "/links/<id>/stats", Some("<days>"), "https://api.example.com/");
+        assert_eq!(e.curl, "curl 'https://api.example.com/links/65f0c0ffee0000000000beef/stats?days=7'");
+        assert_eq!(e.response.unwrap()["data"]["total"], 12);
+
+        let e = example(Method::Put, "/api/admin/maintenance", None, DEFAULT_BASE_URL);
+        assert!(e.curl.starts_with("curl -X PUT 'http://127.0.0.1:8000/api/admin/maintenance' -H \"Authorization"));
+        assert!(e.curl.contains("-d '{"));
+
+        // 未登记的路由按模板生成
+        let e = example(Method::Get, "/status/now", Some("<q>"), DEFAULT_BASE_URL);
+        assert_eq!(e.curl, "curl 'http://127.0.0.1:8000/status/now?q=example'");
+        assert!(e.response.is_none());
+        let e = example(Method::Get, "/badge/<name>", Some("<query..>"), DEFAULT_BASE_URL);
+        assert_eq!(e.curl, "curl 'http://127.0.0.1:8000/badge/example'");
+
+        assert!(path_matches("/links/<id>/stats", "/links/abc/stats"));
+        assert!(path_matches("/links/<id>/stats", "/links/<id>/stats"));
+        assert!(path_matches("/static/<path..>", "/static/a/b.css"));
+        assert!(path_matches("/links", "/links/"));
+        assert!(!path_matches("/links/<id>/stats", "/links/abc"));
+    }
+}
@@ -1,36 +1,69 @@
+use crate::config::settings::Config;
+use crate::services::cdn_service;
+use crate::services::feature_service::FriendAvatar;
 use crate::services::friend_avatar_service::FriendAvatarService;
 use crate::utils::custom_response::CustomResponse;
-use crate::Result;
+use crate::utils::feature_gate::FeatureGate;
+use crate::utils::negotiate;
+use crate::utils::request_context::RequestContext;
+use crate::utils::signed_url::SignedOrAdmin;
+use crate::{Error, Result};
 use rocket::http::{Accept, ContentType, Status};
-use rocket::{get, routes, Route, State};
+use rocket::{get, routes, FromForm, Route, State};
+
+/// 友链头像查询参数
+#[derive(Debug, FromForm)]
+struct FriendAvatarQuery {
+    url: String,
+    force: Option<String>,
+}
 
 /// 友链头像路由
 /// 
 /// 查询参数：
 /// - url: 友链头像的原始 URL (必需)
-/// - force: 强制刷新缓存 (可选，值为 "true" 时生效)
+/// - force: 强制刷新缓存 (可选，值为 "true" 时生效，需要管理员令牌或签名链接)
+/// - exp / sig: 签名参数（开启 signed_urls.protect_friend_avatar 时必需，管理员令牌可代替）
 /// 
 /// 示例：
 /// - /friend-avatar?url=https://example.com/avatar.jpg
 /// - /friend-avatar?url=https://example.com/avatar.jpg&force=true
-#[get("/?<url>&<force>")]
+#[get("/?<query..>")]
 async fn get_friend_avatar(
-    url: &str,
-    force: Option<&str>,
+    _feature: FeatureGate<FriendAvatar>,
+    query: FriendAvatarQuery,
     accept: &Accept,
+    ctx: RequestContext,
+    access: Option<SignedOrAdmin>,
+    config: &State<Config>,
     service: &State<FriendAvatarService>,
 ) -> Result<CustomResponse> {
-    let force_refresh = force.map(|f| f == "true").unwrap_or(false);
+    if config.signed_urls.protect_friend_avatar && access.is_none() {
+        return Err(Error::Forbidden("A valid signed URL is required".into()));
+    }
+
+    let url = query.url.as_str();
+    let force_refresh = query.force.as_deref() == Some("true");
+    // 强制刷新会绕过缓存回源并刷新 CDN，只允许管理员或签名链接触发
+    if force_refresh && access.is_none() {
+        return Err(Error::Forbidden("force=true requires an admin token or a signed URL".into()));
+    }
     let accept_str = accept.to_string();
 
     let (image_data, content_type, cache_status) = service
-        .fetch_friend_avatar(url, &accept_str, force_refresh)
+        .fetch_friend_avatar(&ctx, url, &accept_str, force_refresh)
         .await?;
 
+    // 强制刷新后同步刷新 CDN 上不带 force 参数的缓存
+    if force_refresh {
+        cdn_service::purge_detached(vec![format!("/friend-avatar?url={}", urlencoding::encode(url))]);
+    }
+
     let content_type = match content_type.as_str() {
         "avif" => ContentType::new("image", "avif"),
         "webp" => ContentType::new("image", "webp"),
         "png" => ContentType::PNG,
+        "gif" => ContentType::GIF,
         _ => ContentType::JPEG,
     };
 
@@ -54,6 +87,9 @@ async fn get_friend_avatar(
     Ok(CustomResponse::new(content_type, image_data, Status::Ok)
         .with_header("Cache-Control", cache_control)
         .with_header("X-Cache-Message", status_message)
+        .with_vary(negotiate::VARY_ACCEPT)
+        .with_etag()
+        .with_digest()
         .with_cache(cache_hit))
 }
 
@@ -0,0 +1,20 @@
+use crate::services::graphql_service::{self, ApiSchema, Viewer};
+use crate::utils::auth::AdminAuth;
+use rocket::serde::json::Json;
+use rocket::{post, routes, Route, State};
+
+// GraphQL 查询接口（只读），同一请求内的友链和用户查询通过 DataLoader 合并；
+// 待审核的友链和提交者只对管理员可见
+#[post("/", data = "<request>")]
+async fn graphql(
+    schema: &State<ApiSchema>,
+    admin: Option<AdminAuth>,
+    request: Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let viewer = Viewer { admin: admin.is_some() };
+    Json(schema.execute(graphql_service::with_loaders(request.into_inner(), viewer)).await)
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![graphql]
+}
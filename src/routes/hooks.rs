@@ -0,0 +1,354 @@
+use crate::config::settings::{Config, HookSourceConfig};
+use crate::routes::sw;
+use crate::services::abuse_service::{AbuseService, AbuseSignal};
+use crate::services::cdn_service::{self, PurgeTarget};
+use crate::services::event_bus::Event;
+use crate::services::github_link_service;
+use crate::services::outbox_service;
+use crate::utils::ip_filter::ClientAddr;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use hmac::{Hmac, Mac};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use rocket::data::{Data, ToByteUnit};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use rocket::{post, routes, Route, State};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 请求体大小上限
+const MAX_BODY_MB: u32 = 5;
+
+// 已处理的投递 ID（来源重试时不重复执行动作），保留 24 小时
+static DELIVERIES: Lazy<Cache<String, ()>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+// 投递中已成功的动作（`<投递>#<动作>`）：部分动作失败后来源重新投递时，只重试失败的动作
+static COMPLETED_ACTIONS: Lazy<Cache<String, ()>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(50_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+/// 外部事件请求头（GitHub 与 Mix Space 的签名、事件名和投递 ID）
+pub struct HookHeaders {
+    github_event: Option<String>,
+    github_signature: Option<String>,
+    github_delivery: Option<String>,
+    mix_event: Option<String>,
+    mix_signature: Option<String>,
+    mix_delivery: Option<String>,
+    mix_timestamp: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for HookHeaders {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = |name: &str| req.headers().get_one(name).map(|v| v.trim().to_string());
+        Outcome::Success(HookHeaders {
+            github_event: header("X-GitHub-Event"),
+            github_signature: header("X-Hub-Signature-256"),
+            github_delivery: header("X-GitHub-Delivery"),
+            mix_event: header("X-Webhook-Event"),
+            mix_signature: header("X-Webhook-Signature256"),
+            mix_delivery: header("X-Webhook-Id"),
+            mix_timestamp: header("X-Webhook-Timestamp"),
+        })
+    }
+}
+
+/// 校验通过的外部事件
+struct HookEvent {
+    event: String,
+    delivery: Option<String>,
+    summary: String,
+    payload: Value,
+}
+
+// 接收外部事件：校验签名后按配置执行动作
+#[post("/<source>", data = "<data>")]
+async fn receive(
+    source: &str,
+    headers: HookHeaders,
+    data: Data<'_>,
+    client: ClientAddr,
+    config: &State<Config>,
+    abuse: &State<Arc<AbuseService>>,
+) -> Result<Json<ApiResponse<Value>>> {
+    let hook = config
+        .hooks
+        .sources
+        .iter()
+        .find(|s| s.name == source)
+        .ok_or_else(|| Error::NotFound(format!("Unknown hook source: {}", source)))?;
+
+    let body = data
+        .open(MAX_BODY_MB.mebibytes())
+        .into_bytes()
+        .await
+        .map_err(|e| Error::BadRequest(format!("Failed to read body: {}", e)))?;
+    if !body.is_complete() {
+        return Err(Error::BadRequest("Payload too large".into()));
+    }
+
+    let event = match parse(hook, &headers, &body) {
+        Ok(event) => event,
+        Err(e) => {
+            if matches!(e, Error::Unauthorized(_)) {
+                abuse.record(client.0, AbuseSignal::VerificationFailure).await;
+            }
+            return Err(e);
+        }
+    };
+
+    let mut result = json!({ "source": hook.name, "event": event.event, "actions": [] });
+    if !hook.events.is_empty() && !hook.events.contains(&event.event) {
+        result["ignored"] = json!(true);
+        return Ok(ApiResponse::success(result, "Event ignored"));
+    }
+    // 先占用投递 ID，避免并发的重试重复执行；有动作失败时释放，允许来源重新投递
+    let delivery_key = event.delivery.as_ref().map(|d| format!("{}:{}", hook.name, d));
+    if let Some(key) = &delivery_key {
+        if !DELIVERIES.entry(key.clone()).or_insert(()).await.is_fresh() {
+            result["duplicate"] = json!(true);
+            return Ok(ApiResponse::success(result, "Delivery already processed"));
+        }
+    }
+
+    let mut executed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
+    for action in &hook.actions {
+        let action_key = delivery_key.as_ref().map(|key| format!("{}#{}", key, action));
+        if let Some(key) = &action_key {
+            if COMPLETED_ACTIONS.contains_key(key) {
+                skipped.push(action.clone());
+                continue;
+            }
+        }
+        match action.as_str() {
+            "purge_sw" => sw::invalidate(&config.service_worker).await,
+            "purge_cdn" => match cdn_service::get() {
+                Some(cdn) => {
+                    if let Err(e) = cdn.purge(PurgeTarget::Everything).await {
+                        log::warn!("Hook {} failed to purge CDN: {}", hook.name, e);
+                        failed.push(action.clone());
+                        continue;
+                    }
+                }
+                None => continue,
+            },
+            "notify" => {
+                let emitted = outbox_service::emit(Event::HookReceived {
+                    source: hook.name.clone(),
+                    kind: event.event.clone(),
+                    summary: event.summary.clone(),
+                })
+                .await;
+                if emitted.is_err() {
+                    failed.push(action.clone());
+                    continue;
+                }
+            }
+            "ingest_link" => {
+                // issues / pull_request 事件中的 issue 或 PR
+                let issue = match event.event.as_str() {
+                    "issues" => &event.payload["issue"],
+                    "pull_request" => &event.payload["pull_request"],
+                    _ => continue,
+                };
+                match github_link_service::ingest_issue(&config.github_links, issue).await {
+                    Ok(Some(outcome)) => result["link"] = json!(outcome),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::warn!("Hook {} failed to ingest link: {}", hook.name, e);
+                        failed.push(action.clone());
+                        continue;
+                    }
+                }
+            }
+            other => {
+                log::warn!("Unknown action for hook {}: {}", hook.name, other);
+                continue;
+            }
+        }
+        if let Some(key) = action_key {
+            COMPLETED_ACTIONS.insert(key, ()).await;
+        }
+        executed.push(action.clone());
+    }
+    log::info!("Hook {} ({}): {} -> {:?}", hook.name, event.event, event.summary, executed);
+    if !failed.is_empty() {
+        if let Some(key) = &delivery_key {
+            DELIVERIES.invalidate(key).await;
+        }
+        result["failed"] = json!(failed);
+    }
+    if !skipped.is_empty() {
+        result["skipped"] = json!(skipped);
+    }
+
+    result["actions"] = json!(executed);
+    Ok(ApiResponse::success(result, "Event processed"))
+}
+
+/// 按来源类型校验签名并解析事件名、投递 ID 和摘要
+fn parse(hook: &HookSourceConfig, headers: &HookHeaders, body: &[u8]) -> Result<HookEvent> {
+    let (signature, event, delivery) = match hook.kind.as_str() {
+        "github" => (&headers.github_signature, headers.github_event.clone(), &headers.github_delivery),
+        "mix-space" => (&headers.mix_signature, headers.mix_event.clone(), &headers.mix_delivery),
+        other => return Err(Error::Internal(format!("Unsupported hook kind: {}", other))),
+    };
+    let signature = signature
+        .as_deref()
+        .ok_or_else(|| Error::Unauthorized("Missing signature".into()))?;
+    if hook.secret.is_empty() || !verify_signature(&hook.secret, body, signature) {
+        return Err(Error::Unauthorized("Invalid signature".into()));
+    }
+
+    let payload: Value = serde_json::from_slice(body).unwrap_or_default();
+    let event = event
+        .or_else(|| payload["type"].as_str().map(str::to_string))
+        .ok_or_else(|| Error::BadRequest("Missing event type".into()))?;
+    let summary = match hook.kind.as_str() {
+        "github" => github_summary(&event, &payload),
+        _ => ["title", "slug", "id"]
+            .iter()
+            .find_map(|k| payload["payload"][k].as_str().or_else(|| payload["data"][k].as_str()))
+            .unwrap_or_default()
+            .to_string(),
+    };
+    // Mix Space 未带投递 ID 时按请求体和时间戳去重（重试时二者不变）
+    let delivery = match hook.kind.as_str() {
+        "mix-space" if delivery.is_none() => {
+            let mut hasher = Sha256::new();
+            hasher.update(headers.mix_timestamp.as_deref().unwrap_or_default().as_bytes());
+            hasher.update([0u8]);
+            hasher.update(body);
+            Some(format!("sha256:{}", hex::encode(hasher.finalize())))
+        }
+        _ => delivery.clone(),
+    };
+    Ok(HookEvent {
+        event,
+        delivery,
+        summary,
+        payload,
+    })
+}
+
+fn github_summary(event: &str, payload: &Value) -> String {
+    let repo = payload["repository"]["full_name"].as_str().unwrap_or_default();
+    match event {
+        "push" => format!(
+            "{} {}: {} commit(s), {}",
+            repo,
+            payload["ref"].as_str().unwrap_or_default(),
+            payload["commits"].as_array().map(Vec::len).unwrap_or(0),
+            payload["head_commit"]["message"]
+                .as_str()
+                .and_then(|m| m.lines().next())
+                .unwrap_or_default()
+        ),
+        _ => repo.to_string(),
+    }
+}
+
+/// HMAC-SHA256 签名校验（十六进制，可带 sha256= 前缀），常量时间比较
+fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let hex_sig = signature.strip_prefix("sha256=").unwrap_or(signature);
+    let Ok(expected) = hex::decode(hex_sig) else {
+        return false;
+    };
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![receive]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn source(kind: &str) -> HookSourceConfig {
+        HookSourceConfig {
+            name: kind.to_string(),
+            kind: kind.to_string(),
+            secret: "s3cret".to_string(),
+            events: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    fn headers() -> HookHeaders {
+        HookHeaders {
+            github_event: None,
+            github_signature: None,
+            github_delivery: None,
+            mix_event: None,
+            mix_signature: None,
+            mix_delivery: None,
+            mix_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_github_push() {
+        let body = br#"{"ref":"refs/heads/main","repository":{"full_name":"TNXG/space-api"},"commits":[{}],"head_commit":{"message":"Fix sw\n\nbody"}}"#;
+        let mut h = headers();
+        h.github_event = Some("push".into());
+        h.github_delivery = Some("d-1".into());
+        h.github_signature = Some(format!("sha256={}", sign("s3cret", body)));
+
+        let event = parse(&source("github"), &h, body).unwrap();
+        assert_eq!(event.event, "push");
+        assert_eq!(event.delivery.as_deref(), Some("d-1"));
+        assert_eq!(event.summary, "TNXG/space-api refs/heads/main: 1 commit(s), Fix sw");
+
+        h.github_signature = Some(format!("sha256={}", sign("wrong", body)));
+        assert!(matches!(parse(&source("github"), &h, body), Err(Error::Unauthorized(_))));
+        h.github_signature = None;
+        assert!(matches!(parse(&source("github"), &h, body), Err(Error::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_mix_space_post() {
+        let body = br#"{"type":"POST_CREATE","payload":{"title":"Hello"}}"#;
+        let mut h = headers();
+        h.mix_signature = Some(sign("s3cret", body));
+
+        let event = parse(&source("mix-space"), &h, body).unwrap();
+        assert_eq!(event.event, "POST_CREATE");
+        assert_eq!(event.summary, "Hello");
+        // 没有投递 ID 时按请求体和时间戳生成，重试得到相同的 ID
+        let delivery = event.delivery.unwrap();
+        assert_eq!(parse(&source("mix-space"), &h, body).unwrap().delivery.as_deref(), Some(delivery.as_str()));
+        h.mix_timestamp = Some("1700000000".into());
+        assert_ne!(parse(&source("mix-space"), &h, body).unwrap().delivery.unwrap(), delivery);
+        h.mix_delivery = Some("m-1".into());
+        assert_eq!(parse(&source("mix-space"), &h, body).unwrap().delivery.as_deref(), Some("m-1"));
+        // GitHub 的签名头不适用于 Mix Space 来源
+        h.github_signature = h.mix_signature.take();
+        assert!(parse(&source("mix-space"), &h, body).is_err());
+    }
+}
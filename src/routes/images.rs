@@ -1,12 +1,38 @@
-use crate::services::image_service::ImageService;
+use crate::config::settings::Config;
+use crate::services::audit_service::AuditService;
+use crate::services::blurhash_service::{Blurhash, BlurhashService};
+use crate::services::feature_service::{BlurhashApi, ImageProxy, OgImage, PaletteApi};
+use crate::services::friend_avatar_service::FriendAvatarService;
+use crate::services::cdn_service;
+use crate::services::image_service::{self, Fit, ImageService, ImageTransform};
+use crate::services::wallpaper_service::{self, NewWallpaper, Orientation, ServeStats, Wallpaper, WallpaperService};
+use crate::services::og_service::{OgCard, OgService};
+use crate::services::palette_service::{self, Palette};
+use crate::utils::auth::AdminGuard;
+use crate::utils::cache::{self, Namespace};
+use crate::utils::compression::AcceptEncoding;
+use crate::utils::client_hints::{self, ClientHints};
 use crate::utils::custom_response::CustomResponse;
-use crate::Result;
+use crate::utils::etag::Conditional;
+use crate::utils::feature_gate::FeatureGate;
+use crate::utils::negotiate;
+use crate::utils::request_context::RequestContext;
+use crate::utils::response::ApiResponse;
+use crate::utils::rng;
+use crate::utils::signed_url::SignedRequest;
+use crate::utils::upload;
+use crate::{Error, Result};
 use image::ImageFormat;
-use log::error;
+use chrono::Utc;
+use log::{error, warn};
 use once_cell::sync::Lazy;
 use rocket::http::{Accept, ContentType, Status};
-use rocket::{get, routes, Route, State}; // 导入 State
-use serde::Deserialize;
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, routes, FromForm, Route, State}; // 导入 State
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use serde_json::json;
 use std::collections::HashMap;
 
@@ -30,47 +56,230 @@ static MAX_WEIGHT_NUM: Lazy<u32> = Lazy::new(|| get_max_id(&BLURHASH.weight));
 static MAX_HEIGHT_NUM: Lazy<u32> = Lazy::new(|| get_max_id(&BLURHASH.height));
 
 fn get_max_id(map: &HashMap<String, String>) -> u32 {
-    map.keys()
+    wallpaper_ids(map).last().copied().unwrap_or(1)
+}
+
+/// 按编号排序的全部壁纸 ID
+fn wallpaper_ids(map: &HashMap<String, String>) -> Vec<u32> {
+    let mut ids: Vec<u32> = map
+        .keys()
         .filter_map(|k| k.split('.').next().and_then(|n| n.parse::<u32>().ok()))
-        .max()
-        .unwrap_or(1)
+        .collect();
+    ids.sort_unstable();
+    ids
 }
 
-async fn serve_wallpaper(
+/// 全部壁纸（横屏和竖屏）的原图地址，按编号排序
+pub fn wallpaper_urls() -> Vec<String> {
+    let mut ids = wallpaper_ids(&BLURHASH.weight);
+    ids.extend(wallpaper_ids(&BLURHASH.height));
+    ids.sort_unstable();
+    ids.dedup();
+    ids.into_iter()
+        .map(|id| format!("https://cdn.tnxg.top/images/wallpaper/{}.jpg", id))
+        .collect()
+}
+
+/// 壁纸的返回方式、尺寸和质量查询参数
+#[derive(Debug, Default, FromForm)]
+struct WallpaperQuery {
     t: Option<String>,
-    r#type: Option<String>,
+    /// 返回方式（cdn / json，默认返回图片），旧参数名为 t
+    #[field(name = "type")]
+    kind: Option<String>,
+    w: Option<u32>,
+    h: Option<u32>,
+    dpr: Option<f32>,
+    q: Option<u8>,
+    fit: Option<String>,
+    keep_metadata: Option<bool>,
+}
+
+/// t 的取值：偏向较久未返回的壁纸
+const WEIGHTED: &str = "weighted";
+
+impl WallpaperQuery {
+    fn req_type(&self) -> Option<&str> {
+        self.kind.as_deref().or(self.t.as_deref().filter(|t| *t != WEIGHTED))
+    }
+
+    fn weighted(&self) -> bool {
+        self.t.as_deref() == Some(WEIGHTED)
+    }
+
+    /// 指定 h 时按 w × h（CSS 像素，按 dpr 换算）和 fit 精确缩放，否则按客户端提示或 w 选择预设宽度
+    ///
+    /// 精确尺寸向上取整到 DIMENSION_STEP 的倍数，质量向上取整到 QUALITY_STEP 的倍数，限制缓存变体的数量
+    fn transform(&self, hints: ClientHints) -> Result<ImageTransform> {
+        let fit = match self.fit.as_deref() {
+            Some(name) => Fit::parse(name).ok_or_else(|| Error::BadRequest("fit must be cover or contain".into()))?,
+            None => Fit::default(),
+        };
+        let exact = |css: Option<u32>| {
+            client_hints::physical_pixels(css, self.dpr).map(|px| client_hints::snap_up(px, client_hints::DIMENSION_STEP))
+        };
+        let (width, height) = match self.h {
+            Some(h) => (exact(self.w), exact(Some(h))),
+            None => (
+                client_hints::pick_size(hints.requested_width(self.w, self.dpr), client_hints::WALLPAPER_WIDTHS),
+                None,
+            ),
+        };
+        Ok(ImageTransform {
+            width,
+            height,
+            quality: self
+                .q
+                .map(|q| client_hints::snap_up(q.into(), client_hints::QUALITY_STEP.into()).min(100) as u8),
+            fit,
+            keep_metadata: self.keep_metadata.unwrap_or(false),
+            shape: None,
+        })
+    }
+}
+
+/// 随机壁纸池：内置壁纸（编号 1..=max_id，原图在 CDN）和管理接口上传的同方向壁纸
+struct WallpaperPool<'a> {
+    builtin: &'a HashMap<String, String>,
+    max_id: u32,
+    uploaded: Vec<Wallpaper>,
+    public_base_url: &'a str,
+}
+
+/// 选中的壁纸
+struct PickedWallpaper {
+    id: u32,
+    /// 图片管线读取原图的地址
+    source: String,
+    /// 返回给客户端的原图地址
+    public_url: String,
+    blurhash: String,
+    /// 上传的壁纸在上传时已计算 SHA-256 和大小
+    digest: Option<(String, u64)>,
+}
+
+impl<'a> WallpaperPool<'a> {
+    async fn load(orientation: Orientation, config: &'a Config) -> WallpaperPool<'a> {
+        let (builtin, max_id) = match orientation {
+            Orientation::Landscape => (&BLURHASH.weight, *MAX_WEIGHT_NUM),
+            Orientation::Portrait => (&BLURHASH.height, *MAX_HEIGHT_NUM),
+        };
+        WallpaperPool {
+            builtin,
+            max_id,
+            uploaded: WallpaperService::pool(orientation).await,
+            public_base_url: &config.cdn.public_base_url,
+        }
+    }
+
+    /// 第 index 张（从 1 开始，内置壁纸在前）的壁纸编号
+    fn id_at(&self, index: u32) -> u32 {
+        index
+            .checked_sub(self.max_id + 1)
+            .and_then(|i| self.uploaded.get(i as usize))
+            .map_or(index, |w| w.wallpaper_id)
+    }
+
+    /// 随机选择一张：未提供 last_served 时均匀随机，否则按距上次返回的时间加权，偏向较久未返回的壁纸
+    fn pick(self, last_served: Option<&HashMap<u32, i64>>) -> PickedWallpaper {
+        let total = self.max_id + self.uploaded.len() as u32;
+        let index = match last_served {
+            Some(last_served) => {
+                let now = Utc::now().timestamp();
+                let weights: Vec<u64> = (1..=total)
+                    .map(|i| wallpaper_service::selection_weight(last_served.get(&self.id_at(i)).copied(), now))
+                    .collect();
+                rng::weighted_index(&weights).map_or(1, |i| i as u32 + 1)
+            }
+            None => rng::random_range(1..=total),
+        };
+        let id = self.id_at(index);
+        let uploaded = index
+            .checked_sub(self.max_id + 1)
+            .and_then(|i| self.uploaded.into_iter().nth(i as usize));
+        match uploaded {
+            Some(uploaded) => PickedWallpaper {
+                id,
+                source: uploaded.source_url(),
+                public_url: uploaded_url(self.public_base_url, uploaded.wallpaper_id),
+                blurhash: uploaded.blurhash,
+                digest: Some((uploaded.sha256, uploaded.size)),
+            },
+            None => {
+                let filename = format!("{}.jpg", index);
+                let cdn_url = format!("https://cdn.tnxg.top/images/wallpaper/{}", filename);
+                PickedWallpaper {
+                    id,
+                    source: cdn_url.clone(),
+                    public_url: cdn_url,
+                    blurhash: self.builtin.get(&filename).cloned().unwrap_or_default(),
+                    digest: None,
+                }
+            }
+        }
+    }
+}
+
+/// 上传壁纸的原图地址（按 cdn.public_base_url 拼接，未配置时为站内路径）
+fn uploaded_url(public_base_url: &str, wallpaper_id: u32) -> String {
+    format!("{}{}", public_base_url.trim_end_matches('/'), uploaded_path(wallpaper_id))
+}
+
+/// 上传壁纸原图的路径（CDN 刷新使用）
+fn uploaded_path(wallpaper_id: u32) -> String {
+    format!("/images/wallpaper/{}", wallpaper_id)
+}
+
+/// 上传的壁纸编号从内置壁纸的最大编号之后开始
+fn first_upload_id() -> u32 {
+    (*MAX_WEIGHT_NUM).max(*MAX_HEIGHT_NUM) + 1
+}
+
+async fn serve_wallpaper(
+    ctx: &RequestContext,
+    query: &WallpaperQuery,
     accept: &Accept,
+    transform: ImageTransform,
     service: &State<ImageService>,
-    map: &HashMap<String, String>,
-    max_num: u32,
-    url_prefix: &str,
+    pool: WallpaperPool<'_>,
 ) -> Result<CustomResponse> {
-    let req_type = r#type.or(t);
+    let last_served = if query.weighted() {
+        Some(WallpaperService::last_served().await)
+    } else {
+        None
+    };
+    let picked = pool.pick(last_served.as_ref());
 
-    let image_id = rand::random_range(1..=max_num);
-    let image_id_str = image_id.to_string();
-    let filename = format!("{}.jpg", image_id_str);
-
-    let cdn_url = format!("{}/{}", url_prefix, filename);
+    // 返回记录失败不影响响应
+    let wallpaper_id = picked.id;
+    tokio::spawn(async move {
+        if let Err(e) = WallpaperService::record_serve(wallpaper_id).await {
+            warn!("Failed to record serve of wallpaper #{}: {}", wallpaper_id, e);
+        }
+    });
 
-    match req_type.as_deref() {
+    match query.req_type() {
         Some("cdn") => {
             // 302 跳转
             let resp = CustomResponse::new(ContentType::Plain, Vec::new(), Status::Found)
-                .with_header("Location", cdn_url)
+                .with_header("Location", picked.public_url)
                 .with_header("Cache-Control", "no-cache");
             Ok(resp)
         }
         Some("json") => {
-            // JSON 返回
-            let blurhash = map.get(&filename).cloned().unwrap_or_default();
+            // JSON 返回（附带原图的 SHA-256 和大小，供客户端校验缓存内容）
+            // 只使用上传时保存或下载原图时记录的摘要，没有时为 null（不为此下载原图）
+            let digest = picked.digest.or_else(|| ImageService::cached_digest(&picked.source));
+            let (sha256, size) = digest.unzip();
 
             let payload = json!({
                 "code": "200",
                 "status": "success",
                 "data": {
-                    "image": cdn_url,
-                    "blurhash": blurhash,
+                    "image": picked.public_url,
+                    "blurhash": picked.blurhash,
+                    "sha256": sha256,
+                    "size": size,
                 }
             });
 
@@ -84,8 +293,8 @@ async fn serve_wallpaper(
             // 默认：代理图片，按格式缓存编码后的结果
             let accept_str = accept.to_string();
 
-            match service.fetch_wallpaper(&cdn_url, &accept_str).await {
-                Ok((encoded_data, format)) => {
+            match service.fetch_wallpaper(ctx, &picked.source, &accept_str, transform).await {
+                Ok((encoded_data, format, cache_hit)) => {
                     let content_type = match format {
                         ImageFormat::Avif => ContentType::new("image", "avif"),
                         ImageFormat::WebP => ContentType::new("image", "webp"),
@@ -95,11 +304,19 @@ async fn serve_wallpaper(
 
                     // 缓存 30s
                     let resp = CustomResponse::new(content_type, encoded_data, Status::Ok)
-                        .with_header("Cache-Control", "public, max-age=30");
+                        .with_header("Cache-Control", "public, max-age=30")
+                        .with_header("Accept-CH", client_hints::ACCEPT_CH)
+                        .with_vary(negotiate::VARY_ACCEPT)
+                        .with_vary(client_hints::VARY)
+                        .with_etag()
+                        .with_digest()
+                        .with_cache(cache_hit);
                     Ok(resp)
                 }
+                // 图片处理繁忙：返回 503 和 Retry-After，客户端稍后重试；超过请求时限：返回 504
+                Err(e @ (Error::Unavailable(..) | Error::Timeout(_))) => Err(e),
                 Err(e) => {
-                    error!("Error fetching wallpaper [{}]: {}", cdn_url, e);
+                    error!("Error fetching wallpaper [{}]: {}", picked.source, e);
                     let payload = json!({
                         "code": "500",
                         "message": "Error fetching wallpaper source",
@@ -115,44 +332,512 @@ async fn serve_wallpaper(
     }
 }
 
-#[get("/wallpaper?<t>&<type>")]
+/// 随机横屏壁纸
+///
+/// 尺寸优先按客户端提示（Sec-CH-Width / Sec-CH-Viewport-Width + Sec-CH-DPR）选择，
+/// 没有提示时使用查询参数 w（CSS 像素）和 dpr；都没有时返回原图
+///
+/// 其他查询参数：
+/// - h: 高度（CSS 像素），指定后按 w × h 缩放，不再按预设宽度选择；宽高最大 4096 像素，不放大
+/// - fit: 同时指定 w 和 h 时的缩放方式，contain（默认，完整显示）或 cover（居中裁剪填满）
+/// - q: 编码质量（1-100，仅 JPEG 输出使用）
+/// - keep_metadata: 为 true 时保留原图的 ICC 色彩配置和 EXIF（默认去除，见 transcode.strip_metadata）
+/// - t: weighted 时偏向较久未返回的壁纸（其他取值同 type）
+#[get("/wallpaper?<query..>")]
 async fn wallpaper(
-    t: Option<String>,
-    r#type: Option<String>,
+    query: WallpaperQuery,
+    hints: ClientHints,
+    ctx: RequestContext,
     accept: &Accept,
     service: &State<ImageService>,
+    config: &State<Config>,
 ) -> Result<CustomResponse> {
-    serve_wallpaper(
-        t,
-        r#type,
-        accept,
-        service,
-        &BLURHASH.weight,
-        *MAX_WEIGHT_NUM,
-        "https://cdn.tnxg.top/images/wallpaper",
-    )
-    .await
+    let transform = query.transform(hints)?;
+    let pool = WallpaperPool::load(Orientation::Landscape, config).await;
+    serve_wallpaper(&ctx, &query, accept, transform, service, pool).await
 }
 
-#[get("/wallpaper_height?<t>&<type>")]
+/// 随机竖屏壁纸（尺寸选择和处理参数同 /wallpaper）
+#[get("/wallpaper_height?<query..>")]
 async fn wallpaper_height(
-    t: Option<String>,
-    r#type: Option<String>,
+    query: WallpaperQuery,
+    hints: ClientHints,
+    ctx: RequestContext,
     accept: &Accept,
     service: &State<ImageService>,
+    config: &State<Config>,
 ) -> Result<CustomResponse> {
-    serve_wallpaper(
-        t,
-        r#type,
-        accept,
-        service,
-        &BLURHASH.height,                        // 使用 height 数据
-        *MAX_HEIGHT_NUM,                         // 使用 height 最大值
-        "https://cdn.tnxg.top/images/wallpaper", // 如果竖屏图在不同目录，请修改这里
+    let transform = query.transform(hints)?;
+    let pool = WallpaperPool::load(Orientation::Portrait, config).await;
+    serve_wallpaper(&ctx, &query, accept, transform, service, pool).await
+}
+
+/// 上传壁纸的表单（multipart/form-data）
+#[derive(FromForm)]
+struct WallpaperUpload<'r> {
+    file: TempFile<'r>,
+    /// 标签，可重复或用逗号分隔
+    #[field(default = Vec::new())]
+    tags: Vec<String>,
+    /// landscape / portrait，未指定时按宽高判断
+    orientation: Option<String>,
+}
+
+/// 上传壁纸（JPEG / PNG / WebP），按方向加入 /wallpaper 或 /wallpaper_height 的随机池
+#[post("/wallpaper", data = "<form>")]
+async fn upload_wallpaper(
+    admin: AdminGuard,
+    form: Form<WallpaperUpload<'_>>,
+    config: &State<Config>,
+) -> Result<Json<ApiResponse<Wallpaper>>> {
+    let form = form.into_inner();
+    let orientation = match form.orientation.as_deref().map(str::trim).filter(|o| !o.is_empty()) {
+        Some(name) => Some(
+            Orientation::parse(name)
+                .ok_or_else(|| Error::BadRequest("orientation must be landscape or portrait".into()))?,
+        ),
+        None => None,
+    };
+    let bytes = upload::read_temp_file(&form.file, config.wallpapers.max_upload_mb).await?;
+
+    let upload = NewWallpaper {
+        bytes,
+        orientation,
+        tags: form.tags,
+    };
+    let wallpaper = WallpaperService::upload(upload, first_upload_id()).await?;
+    // 编号可能属于已删除的壁纸，刷新 CDN 上的旧图；新壁纸加入预生成
+    cdn_service::purge_detached(vec![uploaded_path(wallpaper.wallpaper_id)]);
+    image_service::queue_wallpaper_pregen(wallpaper.source_url());
+    AuditService::record(
+        "wallpaper.upload",
+        &admin.actor,
+        &wallpaper.wallpaper_id.to_string(),
+        json!({
+            "orientation": wallpaper.orientation,
+            "width": wallpaper.width,
+            "height": wallpaper.height,
+            "size": wallpaper.size,
+            "tags": &wallpaper.tags,
+        }),
+    )
+    .await;
+    Ok(ApiResponse::success(wallpaper, "Wallpaper uploaded"))
+}
+
+/// 删除上传的壁纸（内置壁纸不可删除）
+#[delete("/wallpaper/<id>")]
+async fn delete_wallpaper(admin: AdminGuard, id: u32) -> Result<Json<ApiResponse<Wallpaper>>> {
+    if id < first_upload_id() {
+        return Err(Error::BadRequest("Built-in wallpapers cannot be deleted".into()));
+    }
+    let wallpaper = WallpaperService::delete(id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Wallpaper #{} not found", id)))?;
+    cdn_service::purge_detached(vec![uploaded_path(id)]);
+    AuditService::record(
+        "wallpaper.delete",
+        &admin.actor,
+        &id.to_string(),
+        json!({ "file": &wallpaper.file, "sha256": &wallpaper.sha256 }),
     )
-    .await
+    .await;
+    Ok(ApiResponse::success(wallpaper, "Wallpaper deleted"))
+}
+
+/// 壁纸列表中的一项
+#[derive(Debug, Serialize)]
+struct WallpaperItem {
+    id: u32,
+    url: String,
+    orientation: Orientation,
+    width: u32,
+    height: u32,
+    blurhash: String,
+    size: u64,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WallpaperList {
+    page: u64,
+    limit: u64,
+    total: u64,
+    items: Vec<WallpaperItem>,
+}
+
+/// 上传的壁纸列表（按编号排序）
+///
+/// 序列化结果按内容摘要写入缓存并生成预压缩版本，客户端支持时直接返回 brotli / gzip
+///
+/// 查询参数：
+/// - page: 页码（从 1 开始，默认 1）
+/// - limit: 每页数量（1-100，默认 20）
+/// - tag: 只返回带此标签的壁纸
+/// - orientation: landscape / portrait
+#[get("/wallpapers?<page>&<limit>&<tag>&<orientation>")]
+async fn list_wallpapers(
+    page: Option<u64>,
+    limit: Option<u64>,
+    tag: Option<&str>,
+    orientation: Option<&str>,
+    accept: AcceptEncoding,
+    config: &State<Config>,
+) -> Result<Conditional> {
+    let page = page.unwrap_or(1).max(1);
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    let orientation = match orientation.filter(|o| !o.is_empty()) {
+        Some(name) => Some(
+            Orientation::parse(name)
+                .ok_or_else(|| Error::BadRequest("orientation must be landscape or portrait".into()))?,
+        ),
+        None => None,
+    };
+
+    let (total, wallpapers) = WallpaperService::page(tag, orientation, page, limit).await?;
+    let items = wallpapers
+        .into_iter()
+        .map(|w| WallpaperItem {
+            id: w.wallpaper_id,
+            url: uploaded_url(&config.cdn.public_base_url, w.wallpaper_id),
+            orientation: w.orientation,
+            width: w.width,
+            height: w.height,
+            blurhash: w.blurhash,
+            size: w.size,
+            tags: w.tags,
+        })
+        .collect();
+    let list = ApiResponse::success(
+        WallpaperList {
+            page,
+            limit,
+            total,
+            items,
+        },
+        "Wallpapers",
+    );
+    let body = serde_json::to_vec(&list.into_inner())
+        .map_err(|e| Error::Internal(format!("Failed to serialize response: {}", e)))?;
+
+    // 缓存键由内容决定，壁纸增删后自然换成新的键
+    let key = Namespace::Wallpapers.derived_key(format_args!("manifest:{}", hex::encode(Sha256::digest(&body))));
+    let variant = match cache::get_variant(&key, &accept).await {
+        Some(found) => found,
+        None => {
+            cache::put_with_variants(key.clone(), body.clone()).await;
+            cache::get_variant(&key, &accept).await.unwrap_or((Vec::new(), None))
+        }
+    };
+    let mut response = Conditional::bytes(ContentType::JSON, body);
+    if let (compressed, Some(encoding)) = variant {
+        response = response.precompressed(encoding, compressed);
+    }
+    Ok(response)
+}
+
+/// 随机壁纸的返回次数（每张壁纸的累计次数和最近返回时间，按次数从多到少）
+#[get("/wallpaper/stats")]
+async fn wallpaper_stats() -> Result<Json<ApiResponse<ServeStats>>> {
+    Ok(ApiResponse::success(WallpaperService::serve_stats().await?, "Wallpaper serve stats"))
+}
+
+/// 上传壁纸的原图（随机壁纸的 cdn / json 返回的地址）
+#[get("/wallpaper/<id>")]
+async fn uploaded_wallpaper(id: u32) -> Result<CustomResponse> {
+    let wallpaper = WallpaperService::get(id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Wallpaper #{} not found", id)))?;
+    let data = wallpaper_service::read_upload(&wallpaper.file).await?;
+    let content_type = match ImageService::detect_format(&data) {
+        Some(ImageFormat::WebP) => ContentType::new("image", "webp"),
+        Some(ImageFormat::Png) => ContentType::PNG,
+        _ => ContentType::JPEG,
+    };
+    // 内容按编号固定（删除后编号不复用）
+    Ok(CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("Cache-Control", "public, max-age=86400")
+        .with_etag())
+}
+
+/// 按图片内容计算图片的 blurhash（结果持久化，同一地址只计算一次）
+///
+/// 与图片代理相同，只接受 image_proxy.allowed_domains 中的域名，其他地址需要有效的签名链接
+#[get("/blurhash?<url>")]
+async fn blurhash(
+    _feature: FeatureGate<BlurhashApi>,
+    url: &str,
+    signed: Option<SignedRequest>,
+    ctx: RequestContext,
+    service: &State<BlurhashService>,
+    config: &State<Config>,
+) -> Result<Json<ApiResponse<Blurhash>>> {
+    check_remote_url(url, signed.is_some(), config)?;
+    let (blurhash, cached) = service.get(&ctx, url).await?;
+    let message = if cached { "Blurhash" } else { "Blurhash computed" };
+    Ok(ApiResponse::success(blurhash, message))
+}
+
+/// 检查由服务端下载并计算的远程图片地址：白名单域名（含子域名）或有效签名链接
+fn check_remote_url(url: &str, signed: bool, config: &Config) -> Result<()> {
+    if url.is_empty() {
+        return Err(Error::BadRequest("Missing required parameter: url".into()));
+    }
+    FriendAvatarService::validate_url(url)?;
+    if !signed && !ImageService::proxy_allowed(url, &config.image_proxy.allowed_domains) {
+        return Err(Error::Forbidden("Domain is not allowed, a valid signed URL is required".into()));
+    }
+    Ok(())
+}
+
+/// 图片代理查询参数
+#[derive(Debug, FromForm)]
+struct ProxyQuery {
+    url: String,
+    w: Option<u32>,
+    format: Option<String>,
+}
+
+/// 通用图片代理：下载远程图片，缩放并转码后返回，结果与壁纸共用硬盘缓存
+///
+/// 只代理 image_proxy.allowed_domains 中的域名（含子域名），其他地址需要有效的签名链接
+///
+/// 查询参数：
+/// - url: 原图地址（必需）
+/// - w: 宽度（像素），取不小于 w 的预设宽度，超过最大预设宽度时返回原图尺寸；不放大
+/// - format: 输出格式（avif / webp / jpeg），未指定时按 Accept 协商
+/// - exp / sig: 签名参数（非白名单域名时必需）
+#[get("/proxy?<query..>")]
+async fn proxy(
+    _feature: FeatureGate<ImageProxy>,
+    query: ProxyQuery,
+    signed: Option<SignedRequest>,
+    ctx: RequestContext,
+    accept: &Accept,
+    service: &State<ImageService>,
+    config: &State<Config>,
+) -> Result<CustomResponse> {
+    let url = query.url.as_str();
+    check_remote_url(url, signed.is_some(), config)?;
+
+    // 指定格式时按该格式编码，否则按 Accept 协商（响应需按 Accept 区分缓存）
+    let (accept_str, vary) = match query.format.as_deref() {
+        Some(name) => {
+            let format = ImageService::parse_format(name)
+                .ok_or_else(|| Error::BadRequest("format must be avif, webp or jpeg".into()))?;
+            (format!("image/{}", ImageService::format_extension(format)), None)
+        }
+        None => (accept.to_string(), Some(negotiate::VARY_ACCEPT)),
+    };
+    let transform = ImageTransform::width(client_hints::pick_size(query.w, client_hints::PROXY_WIDTHS));
+
+    let (data, format, cache_hit) = service.fetch_wallpaper(&ctx, url, &accept_str, transform).await?;
+    let content_type = match format {
+        ImageFormat::Avif => ContentType::new("image", "avif"),
+        ImageFormat::WebP => ContentType::new("image", "webp"),
+        _ => ContentType::JPEG,
+    };
+    let mut resp = CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("Cache-Control", "public, max-age=86400, s-maxage=86400");
+    if let Some(vary) = vary {
+        resp = resp.with_vary(vary);
+    }
+    Ok(resp.with_etag().with_digest().with_cache(cache_hit))
+}
+
+/// 图片的主色和调色板（十六进制颜色，按占比从高到低），供前端按友链头像为卡片配色
+///
+/// 原图通过头像的原图缓存获取，计算结果缓存在 metadata 命名空间；
+/// 地址限制与 /images/blurhash 相同
+#[get("/palette?<url>")]
+async fn palette(
+    _feature: FeatureGate<PaletteApi>,
+    url: &str,
+    signed: Option<SignedRequest>,
+    ctx: RequestContext,
+    service: &State<ImageService>,
+    config: &State<Config>,
+) -> Result<Json<ApiResponse<Palette>>> {
+    check_remote_url(url, signed.is_some(), config)?;
+    let (palette, cached) = palette_service::get(&ctx, service, url).await?;
+    let message = if cached { "Palette" } else { "Palette computed" };
+    Ok(ApiResponse::success(palette, message))
+}
+
+/// 拼图可选的每行数量和缩略图宽度（每种组合都要下载并拼接全部壁纸，只提供少量预设）
+const SPRITE_COLS: &[u32] = &[4, 8];
+const SPRITE_SIZES: &[u32] = &[96, 160, 240];
+
+/// 取不小于请求值的最小预设，超过时取最大预设
+fn sprite_preset(requested: Option<u32>, presets: &[u32], default: u32) -> u32 {
+    let last = presets[presets.len() - 1];
+    requested.map_or(default, |v| client_hints::pick_size(Some(v), presets).unwrap_or(last))
+}
+
+/// 壁纸拼图（图库选择器使用）
+///
+/// 查询参数：
+/// - cols: 每行数量（4 或 8，默认 8）
+/// - size: 单张缩略图宽度（96 / 160 / 240，默认 160），高度按 16:9 计算
+///
+/// 其他值取不小于它的最小预设；第 i 张（按壁纸编号排序）位于第 i / cols 行、第 i % cols 列，布局通过 X-Sprite-* 响应头返回
+/// 有壁纸下载失败时对应格子留空，响应不缓存（X-Sprite-Missing 为留空的格子数）
+#[get("/wallpaper/sprite?<cols>&<size>")]
+async fn wallpaper_sprite(
+    cols: Option<u32>,
+    size: Option<u32>,
+    accept: &Accept,
+    service: &State<ImageService>,
+) -> Result<CustomResponse> {
+    let cols = sprite_preset(cols, SPRITE_COLS, 8);
+    let size = sprite_preset(size, SPRITE_SIZES, 160);
+    let urls: Vec<String> = wallpaper_ids(&BLURHASH.weight)
+        .into_iter()
+        .map(|id| format!("https://cdn.tnxg.top/images/wallpaper/{}.jpg", id))
+        .collect();
+
+    let (data, format, layout, cache_hit) = service
+        .fetch_wallpaper_sprite(&urls, cols, size, &accept.to_string())
+        .await?;
+    let content_type = match format {
+        ImageFormat::Avif => ContentType::new("image", "avif"),
+        ImageFormat::WebP => ContentType::new("image", "webp"),
+        ImageFormat::Png => ContentType::PNG,
+        _ => ContentType::JPEG,
+    };
+
+    let cache_control = if layout.missing == 0 { "public, max-age=3600" } else { "no-store" };
+    Ok(CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("Cache-Control", cache_control)
+        .with_header("X-Sprite-Missing", layout.missing.to_string())
+        .with_header("X-Sprite-Columns", layout.cols.to_string())
+        .with_header("X-Sprite-Cell", format!("{}x{}", layout.cell_width, layout.cell_height))
+        .with_header("X-Sprite-Count", layout.count.to_string())
+        .with_etag()
+        .with_digest()
+        .with_cache(cache_hit))
+}
+
+/// 分享卡片查询参数
+#[derive(Debug, FromForm)]
+struct OgQuery {
+    title: String,
+    subtitle: Option<String>,
+    avatar: Option<String>,
+}
+
+/// Open Graph 分享卡片（1200x630）
+///
+/// 查询参数：
+/// - title: 标题（必需，最多 120 字符）
+/// - subtitle: 副标题（可选，最多 200 字符）
+/// - avatar: 头像地址（可选），裁剪为圆形显示在右侧；获取失败时不显示
+///
+/// 背景按标题从壁纸池中固定选取一张，同一组参数始终得到相同的图片
+#[get("/og?<query..>")]
+async fn og_image(
+    _feature: FeatureGate<OgImage>,
+    query: OgQuery,
+    accept: &Accept,
+    ctx: RequestContext,
+    og: &State<OgService>,
+    service: &State<ImageService>,
+) -> Result<CustomResponse> {
+    let title = query.title.trim();
+    let subtitle = query.subtitle.as_deref().unwrap_or("").trim();
+    let avatar_url = query.avatar.as_deref().unwrap_or("").trim();
+    if title.is_empty() || title.chars().count() > 120 {
+        return Err(Error::BadRequest("title must be 1-120 characters".into()));
+    }
+    if subtitle.chars().count() > 200 {
+        return Err(Error::BadRequest("subtitle must be at most 200 characters".into()));
+    }
+    if !avatar_url.is_empty() {
+        FriendAvatarService::validate_url(avatar_url)?;
+    }
+
+    // 分享卡片主要给社交平台爬虫使用，只在明确支持时返回 WebP
+    let format = if negotiate::preferred(&accept.to_string(), &["image/webp"]).is_some() {
+        ImageFormat::WebP
+    } else {
+        ImageFormat::Png
+    };
+
+    let ids = wallpaper_ids(&BLURHASH.weight);
+    let background_url = if ids.is_empty() {
+        String::new()
+    } else {
+        let digest = Sha256::digest(title.as_bytes());
+        let index = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) as usize % ids.len();
+        format!("https://cdn.tnxg.top/images/wallpaper/{}.jpg", ids[index])
+    };
+
+    let cache_key = og.cache_key(title, subtitle, &background_url, avatar_url, format);
+    let (data, cache_hit) = match cache::get_disk(&cache_key) {
+        Some(cached) => (cached, true),
+        None => {
+            // 背景下载失败时退回纯色背景
+            let background = if background_url.is_empty() {
+                None
+            } else {
+                ctx.check("OG background download")?;
+                match service.download_image(&background_url).await {
+                    Ok(bytes) => Some(bytes),
+                    Err(e) => {
+                        error!("Failed to fetch OG background [{}]: {}", background_url, e);
+                        None
+                    }
+                }
+            };
+            // 头像使用头像的原图缓存，获取失败时不显示头像
+            let avatar = if avatar_url.is_empty() {
+                None
+            } else {
+                match service.fetch_avatar(&ctx, avatar_url).await {
+                    Ok((bytes, _)) => Some(bytes),
+                    Err(e @ Error::Timeout(_)) => return Err(e),
+                    Err(e) => {
+                        error!("Failed to fetch OG avatar [{}]: {}", avatar_url, e);
+                        None
+                    }
+                }
+            };
+            let card = OgCard {
+                title: title.to_string(),
+                subtitle: subtitle.to_string(),
+                background,
+                avatar,
+            };
+            let data = og.render_and_cache(&ctx, cache_key, card, format).await?;
+            (data, false)
+        }
+    };
+
+    let content_type = match format {
+        ImageFormat::WebP => ContentType::new("image", "webp"),
+        _ => ContentType::PNG,
+    };
+    Ok(CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("Cache-Control", "public, max-age=86400")
+        .with_vary(negotiate::VARY_ACCEPT)
+        .with_etag()
+        .with_digest()
+        .with_cache(cache_hit))
 }
 
 pub fn routes() -> Vec<Route> {
-    routes![wallpaper, wallpaper_height]
+    routes![
+        wallpaper,
+        wallpaper_height,
+        wallpaper_sprite,
+        wallpaper_stats,
+        upload_wallpaper,
+        delete_wallpaper,
+        list_wallpapers,
+        uploaded_wallpaper,
+        blurhash,
+        palette,
+        proxy,
+        og_image
+    ]
 }
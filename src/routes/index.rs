@@ -9,8 +9,16 @@ use std::process;
 use std::sync::{Arc, Mutex};
 use sysinfo::{Pid, ProcessesToUpdate, System};
 use rocket::response::stream::{Event, EventStream};
-use rocket::tokio::time::{interval, Duration};
-use crate::services::memory_service::MemoryManager;
+use rocket::tokio::time::{interval as tokio_interval, interval_at, Duration, Instant};
+use crate::config::settings::Config;
+use crate::routes::static_files::{asset_url, preload_links, Preloaded};
+use crate::services::boot_report::{self, BootReport};
+use crate::services::dashboard_service::{DashboardPreferences, DashboardService};
+use crate::services::memory_service::{MemoryManager, MemoryPressure};
+use crate::utils::auth::{AdminAuth, AdminGuard};
+use crate::utils::error_tracker::ERROR_TRACKER;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
 
 
 // 存储历史数据的结构
@@ -161,7 +169,9 @@ pub async fn index(
     metrics: &State<MetricsHistory>,
     sys_state: &State<SystemState>,
     memory_manager: &State<Arc<MemoryManager>>,
-) -> Template {
+    config: &State<Config>,
+    admin: Option<AdminAuth>,
+) -> Preloaded<Template> {
     let now = Local::now();
 
     // Scope the lock so it drops before async calls
@@ -255,7 +265,28 @@ pub async fn index(
         Err(_) => "Disconnected",
     };
 
-    Template::render(
+    // 管理员（已登录管理后台）访问时使用其保存的仪表盘设置，其余访问者使用默认设置
+    let preferences = match &admin {
+        Some(AdminAuth(admin)) => DashboardService::get_preferences(&admin.id)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to load dashboard preferences: {}", e);
+                DashboardPreferences::default()
+            }),
+        None => DashboardPreferences::default(),
+    };
+
+    let dashboard_css_url = asset_url(&config.static_files, "dashboard.css").await;
+    let dashboard_js_url = asset_url(&config.static_files, "dashboard.js").await;
+    // 仪表盘脚本按设置的刷新间隔连接 SSE，预加载的地址需与之一致
+    let preload = preload_links(
+        &config.static_files,
+        "index",
+        &[("refresh", preferences.refresh_interval_secs.to_string())],
+    )
+    .await;
+
+    let template = Template::render(
         "index",
         context! {
             version: concat!("v", env!("CARGO_PKG_VERSION")),
@@ -290,8 +321,21 @@ pub async fn index(
             timestamps_json: serde_json::to_string(&timestamps).unwrap_or_default(),
 
             mongo_status: mongo_status,
+
+            // 静态资源（带内容哈希）
+            dashboard_css_url: dashboard_css_url,
+            dashboard_js_url: dashboard_js_url,
+
+            // 仪表盘设置
+            theme: &preferences.theme,
+            preferences_json: serde_json::to_string(&preferences).unwrap_or_default(),
+            personalized: admin.is_some(),
+
+            // 错误率摘要
+            error_summary_json: serde_json::to_string(&ERROR_TRACKER.summary()).unwrap_or_default(),
         },
-    )
+    );
+    Preloaded::new(template, preload)
 }
 
 // API 端点用于实时更新数据
@@ -392,18 +436,49 @@ pub async fn get_metrics(
     }))
 }
 
-#[get("/api/metrics/stream")]
+/// SSE 推送间隔的下限（毫秒）
+const MIN_STREAM_INTERVAL_MS: u64 = 1000;
+/// SSE 推送间隔的上限（毫秒），内存压力降速后也不超过该值
+const MAX_STREAM_INTERVAL_MS: u64 = 300_000;
+
+/// 按内存压力放慢推送：High 为 2 倍、Critical 为 4 倍
+fn adaptive_interval(base: Duration, pressure: &MemoryPressure) -> Duration {
+    let factor = match pressure {
+        MemoryPressure::Low | MemoryPressure::Medium => 1,
+        MemoryPressure::High => 2,
+        MemoryPressure::Critical => 4,
+    };
+    (base * factor).min(Duration::from_millis(MAX_STREAM_INTERVAL_MS))
+}
+
+// `interval` 为推送间隔（毫秒，不小于 1000）；`refresh`（秒）为旧参数，未指定 interval 时使用
+#[get("/api/metrics/stream?<refresh>&<interval>")]
 pub fn metrics_stream(
+    refresh: Option<u64>,
+    interval: Option<u64>,
     metrics: &State<MetricsHistory>,
     sys_state: &State<SystemState>,
     memory_manager: &State<Arc<MemoryManager>>,
-) -> EventStream![] {
+) -> Result<EventStream![]> {
+    let base_ms = match interval {
+        Some(ms) if ms < MIN_STREAM_INTERVAL_MS => {
+            return Err(Error::BadRequest(format!(
+                "Invalid interval: must be at least {}ms",
+                MIN_STREAM_INTERVAL_MS
+            )));
+        }
+        Some(ms) => ms.min(MAX_STREAM_INTERVAL_MS),
+        None => refresh.unwrap_or(5).clamp(1, 300) * 1000,
+    };
+    let base = Duration::from_millis(base_ms);
     let metrics = metrics.inner().clone();
     let sys_state = sys_state.inner().clone();
     let memory_manager = memory_manager.inner().clone();
 
-    EventStream! {
-        let mut timer = interval(Duration::from_secs(5)); // Push every 5 seconds (reduced frequency)
+    Ok(EventStream! {
+        // 默认每 5 秒推送一次，可由客户端指定；内存压力高时自动放慢，压力恢复后还原
+        let mut period = base;
+        let mut timer = tokio_interval(period);
 
         loop {
             let _ = timer.tick().await;
@@ -485,8 +560,10 @@ pub fn metrics_stream(
             };
             
             // 获取内存监控状态和性能统计
+            let mut pressure = MemoryPressure::Low;
             let memory_monitor_status = match memory_manager.get_memory_status().await {
                 Ok(status) => {
+                    pressure = status.pressure.clone();
                     // 获取性能统计
                     let perf_stats = memory_manager.get_performance_stats().await;
                     let avg_memory = memory_manager.calculate_average_memory_usage().await;
@@ -538,11 +615,19 @@ pub fn metrics_stream(
                 "system_memory_history": system_memory_history,
                 "timestamps": timestamps,
                 "memory_monitor": memory_monitor_status,
+                "interval_ms": period.as_millis() as u64,
             });
 
             yield Event::json(&payload);
+
+            let next = adaptive_interval(base, &pressure);
+            if next != period {
+                log::debug!("Metrics stream interval {:?} -> {:?} (memory pressure {:?})", period, next, pressure);
+                period = next;
+                timer = interval_at(Instant::now() + period, period);
+            }
         }
-    }
+    })
 }
 
 // API 端点用于获取详细的内存性能报告
@@ -583,15 +668,36 @@ pub async fn get_memory_trend(
     }))
 }
 
+// 启动报告：配置来源、功能开关、路由数、数据库、缓存目录和内存限制，用于部署后核对
+#[get("/api/boot-report")]
+pub fn get_boot_report(_admin: AdminGuard) -> Result<rocket::serde::json::Json<ApiResponse<BootReport>>> {
+    let report = boot_report::get().ok_or_else(|| Error::Unavailable("Boot report is not ready".into(), 5))?;
+    Ok(ApiResponse::success(report.clone(), "Boot report"))
+}
+
 pub fn routes() -> Vec<rocket::Route> {
-    rocket::routes![index, get_metrics, metrics_stream, get_memory_report, get_memory_trend]
+    rocket::routes![index, get_metrics, metrics_stream, get_memory_report, get_memory_trend, get_boot_report]
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::services::memory_service::MemoryManager;
     use crate::config::settings::MemoryConfig;
 
+    #[test]
+    fn test_adaptive_interval() {
+        let base = Duration::from_secs(5);
+        assert_eq!(adaptive_interval(base, &MemoryPressure::Medium), base);
+        assert_eq!(adaptive_interval(base, &MemoryPressure::High), Duration::from_secs(10));
+        assert_eq!(adaptive_interval(base, &MemoryPressure::Critical), Duration::from_secs(20));
+        // 降速后不超过上限
+        assert_eq!(
+            adaptive_interval(Duration::from_secs(120), &MemoryPressure::Critical),
+            Duration::from_millis(MAX_STREAM_INTERVAL_MS)
+        );
+    }
+
     #[tokio::test]
     async fn test_memory_status_serialization() {
         let config = MemoryConfig {
@@ -0,0 +1,75 @@
+use crate::services::link_service::{ClickSource, LinkService, LinkStats};
+use crate::utils::compression::AcceptEncoding;
+use crate::utils::etag::Conditional;
+use crate::utils::response::ApiResponse;
+use crate::Result;
+use rocket::http::ContentType;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route};
+
+/// 从请求头提取点击来源：Referer 只保留主机名，国家代码来自 CDN（Cloudflare 等）请求头
+struct ClickContext(ClickSource);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClickContext {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let referrer = req
+            .headers()
+            .get_one("Referer")
+            .and_then(|r| url::Url::parse(r).ok())
+            .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()));
+        let country = ["CF-IPCountry", "X-Country-Code"]
+            .iter()
+            .find_map(|h| req.headers().get_one(h))
+            .filter(|c| c.len() == 2 && c.chars().all(|ch| ch.is_ascii_alphanumeric()))
+            .map(|c| c.to_ascii_uppercase());
+        Outcome::Success(ClickContext(ClickSource { referrer, country }))
+    }
+}
+
+// 公开友链列表（服务端缓存序列化结果，CDN 按 s-maxage 缓存，友链变更时两级缓存都会失效）
+#[get("/")]
+async fn list(accept: AcceptEncoding) -> Result<Conditional> {
+    let (list, hit) = LinkService::public_list().await?;
+    let mut response = Conditional::bytes(ContentType::JSON, list.body.to_vec())
+        .with_header("Cache-Control", "public, max-age=60, s-maxage=600")
+        .with_header("X-Links-Version", list.version)
+        .with_header("server-cache", if hit { "HIT" } else { "MISS" });
+    if let Some((encoding, body)) = list.compressed.iter().find(|(e, _)| accept.accepts(e)) {
+        response = response.precompressed(encoding, body.to_vec());
+    }
+    Ok(response)
+}
+
+// 友链跳转：记录点击后 302 到友链站点（只跳转已公开的友链，避免未审核的地址借用本站域名）
+#[get("/go/<id>")]
+async fn go(id: &str, ctx: ClickContext) -> Result<Redirect> {
+    let link = LinkService::find_public_link(id).await?;
+    let target = LinkService::target_url(&link)?;
+
+    // 点击记录失败不影响跳转
+    let link_id = id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = LinkService::record_click(&link_id, ctx.0).await {
+            log::warn!("Failed to record click for link {}: {}", link_id, e);
+        }
+    });
+
+    Ok(Redirect::found(target))
+}
+
+// 友链点击统计（最近 days 天，默认 30 天）
+#[get("/<id>/stats?<days>")]
+async fn stats(id: &str, days: Option<u32>) -> Result<Json<ApiResponse<LinkStats>>> {
+    LinkService::find_public_link(id).await?;
+    let days = days.unwrap_or(30).clamp(1, 365);
+    Ok(ApiResponse::success(LinkService::stats(id, days).await?, "Link stats"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![list, go, stats]
+}
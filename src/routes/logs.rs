@@ -0,0 +1,54 @@
+use crate::utils::auth::AdminGuard;
+use crate::utils::log_buffer::LOG_BUFFER;
+use crate::{Error, Result};
+use log::Level;
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{get, routes, Route};
+
+/// 实时日志流
+///
+/// 先回放缓冲区中符合条件的日志，再持续推送新日志；level 为最低级别（默认 warn），module 为模块前缀
+#[get("/stream?<level>&<module>")]
+fn log_stream(
+    _admin: AdminGuard,
+    level: Option<&str>,
+    module: Option<String>,
+) -> Result<EventStream![]> {
+    let min_level = match level {
+        Some(l) => l
+            .parse::<Level>()
+            .map_err(|_| Error::BadRequest(format!("Invalid log level: {}", l)))?,
+        None => Level::Warn,
+    };
+
+    // 订阅要早于读取快照，避免两者之间产生的日志丢失（客户端按 seq 去重）
+    let mut receiver = LOG_BUFFER.subscribe();
+    let backlog = LOG_BUFFER.snapshot();
+
+    Ok(EventStream! {
+        let mut last_seq = 0;
+        for entry in backlog.into_iter().filter(|e| e.matches(min_level, module.as_deref())) {
+            last_seq = entry.seq;
+            yield Event::json(&entry).event("log");
+        }
+
+        loop {
+            match receiver.recv().await {
+                Ok(entry) => {
+                    if entry.seq > last_seq && entry.matches(min_level, module.as_deref()) {
+                        yield Event::json(&entry).event("log");
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    yield Event::json(&serde_json::json!({ "skipped": skipped })).event("lagged");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![log_stream]
+}
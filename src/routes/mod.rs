@@ -1,9 +1,30 @@
+pub mod admin;
+pub mod admin_ui;
 pub mod avatar;
+pub mod badge;
+pub mod bench;
+pub mod cache;
+pub mod calendar;
+pub mod dashboard;
+pub mod diagnostics;
 pub mod email;
+pub mod errors;
+pub mod examples;
 pub mod friend_avatar;
+pub mod graphql;
+pub mod hooks;
 pub mod images;
 pub mod index;
+pub mod links;
+pub mod logs;
 pub mod oauth;
+pub mod render;
+pub mod robots;
+pub mod rpc;
+pub mod search;
+pub mod static_files;
+pub mod stats;
 pub mod status;
 pub mod sw;
 pub mod user;
+pub mod widgets;
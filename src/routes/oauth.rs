@@ -2,14 +2,16 @@ use rocket::{Route, get, State, routes, Either};
 use rocket::serde::json::Json;
 use crate::config::settings::Config;
 use crate::services::oauth_service::OAuthService;
+use crate::services::upstream_service::HttpClientService;
 use crate::utils::response::ApiResponse;
 use crate::Result;
 use mongodb::bson::doc;
 use crate::services::db_service;
+use crate::services::event_bus::Event;
+use crate::services::outbox_service;
 use rocket::response::Redirect;
 use rocket::serde::json::serde_json;
-use rand::Rng;
-use hex::ToHex;
+use crate::utils::{crypto, rng};
 use chrono::{Utc, Duration};
 use url::Url;
 
@@ -20,8 +22,9 @@ fn qq_authorize(
     return_url: Option<&str>,
     redirect: Option<&str>,
     config: &State<Config>,
+    http: &State<HttpClientService>,
 ) -> Result<Either<Redirect, Json<ApiResponse<serde_json::Value>>>> {
-    let oauth_service = OAuthService::new(config.oauth.clone());
+    let oauth_service = OAuthService::new(config.oauth.clone(), http);
     // 将 return_url 放入 state JSON
     let state_json = serde_json::json!({
         "original_state": state.unwrap_or(""),
@@ -46,8 +49,9 @@ async fn qq_callback(
     code: &str,
     state: Option<&str>,
     config: &State<Config>,
+    http: &State<HttpClientService>,
 ) -> Result<Redirect> {
-    let oauth_service = OAuthService::new(config.oauth.clone());
+    let oauth_service = OAuthService::new(config.oauth.clone(), http);
 
     // 解析 state，提取 return_url 与 original_state
     let default_return_url = std::env::var("DEFAULT_RETURN_URL")
@@ -124,6 +128,7 @@ async fn qq_callback(
                     "nickname": &nickname,
                     "avatar": &avatar,
                     "gender": user_info.gender.clone().unwrap_or_default(),
+                    "qq_access_token": &access_token,
                     "updated_at": now.to_rfc3339(),
                     "last_login": now.to_rfc3339(),
                 }
@@ -135,20 +140,27 @@ async fn qq_callback(
                 "nickname": &nickname,
                 "avatar": &avatar,
                 "gender": user_info.gender.clone().unwrap_or_default(),
+                "qq_access_token": &access_token,
                 "created_at": now.to_rfc3339(),
                 "updated_at": now.to_rfc3339(),
             };
             let _ = db_service::insert_one("users", user_doc).await?;
         }
 
+        // 通知写入失败不影响登录，emit 已记录日志
+        let _ = outbox_service::emit(Event::UserLoggedIn {
+            openid: openid.clone(),
+            new_user: existing_user.is_none(),
+        })
+        .await;
+
         // 生成一次性临时代码，保存 temp_codes
-        let mut buf = [0u8; 32];
-        rand::rng().fill_bytes(&mut buf);
-        let temp_code = buf.encode_hex::<String>();
+        let temp_code = rng::secure_hex(32);
         let expires_at = (now + Duration::minutes(10)).to_rfc3339();
 
         let temp_doc = doc! {
-            "code": &temp_code,
+            // 只保存哈希，/user/get 中以 crypto::hash_code(code) 查询
+            "code_hash": crypto::hash_code(&temp_code),
             "qq_openid": &openid,
             "created_at": now.to_rfc3339(),
             "expires_at": &expires_at,
@@ -0,0 +1,37 @@
+use crate::utils::markdown;
+use crate::utils::response::ApiResponse;
+use crate::utils::validation::{Valid, Validate, Validator};
+use crate::Result;
+use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::{post, routes, Route};
+
+#[derive(Debug, Deserialize)]
+pub struct MarkdownRequest {
+    markdown: String,
+}
+
+impl Validate for MarkdownRequest {
+    fn check(&self, v: &mut Validator) {
+        v.check(
+            "markdown",
+            self.markdown.len() <= markdown::MAX_MARKDOWN_LEN,
+            format!("must be at most {} bytes", markdown::MAX_MARKDOWN_LEN),
+        );
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkdownResponse {
+    pub html: String,
+}
+
+// Markdown 预览：渲染为安全的 HTML（与留言、友链描述的展示结果一致）
+#[post("/markdown", data = "<data>")]
+async fn render_markdown(data: Valid<MarkdownRequest>) -> Result<Json<ApiResponse<MarkdownResponse>>> {
+    let html = markdown::render_cached(&data.markdown).await;
+    Ok(ApiResponse::success(MarkdownResponse { html }, "Rendered"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![render_markdown]
+}
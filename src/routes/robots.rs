@@ -0,0 +1,34 @@
+use crate::config::settings::{Config, RobotsConfig};
+use crate::utils::custom_response::CustomResponse;
+use rocket::http::{ContentType, Status};
+use rocket::{get, routes, Route, State};
+
+// 根据配置生成 robots.txt 内容
+fn render_robots(config: &RobotsConfig) -> String {
+    let mut out = String::new();
+    for rule in &config.rules {
+        out.push_str(&format!("User-agent: {}\n", rule.user_agent));
+        for path in &rule.allow {
+            out.push_str(&format!("Allow: {}\n", path));
+        }
+        for path in &rule.disallow {
+            out.push_str(&format!("Disallow: {}\n", path));
+        }
+        out.push('\n');
+    }
+    if let Some(sitemap) = &config.sitemap {
+        out.push_str(&format!("Sitemap: {}\n", sitemap));
+    }
+    out
+}
+
+#[get("/robots.txt")]
+fn robots_txt(config: &State<Config>) -> CustomResponse {
+    let body = render_robots(&config.robots);
+    CustomResponse::new(ContentType::Plain, body.into_bytes(), Status::Ok)
+        .with_header("Cache-Control", "public, max-age=86400")
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![robots_txt]
+}
@@ -0,0 +1,242 @@
+use crate::config::settings::Config;
+use crate::services::link_service::LinkService;
+use crate::services::ncm_service;
+use crate::services::search_service::SearchService;
+use crate::services::stats_service::StatsService;
+use crate::services::user_service::UserService;
+use crate::utils::auth::{secure_eq, AdminAuth};
+use crate::Error;
+use mongodb::bson::Document;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::serde::json::Json;
+use rocket::{post, routes, Either, Route};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// 单次批量请求的最大调用数
+const MAX_BATCH: usize = 50;
+
+// JSON-RPC 2.0 标准错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+// 应用自定义错误码
+const NOT_FOUND: i64 = -32004;
+
+/// RPC 访问守卫：未启用时返回 404，配置了令牌时校验 Authorization: Bearer
+pub struct RpcGuard;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RpcGuard {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(config) = req.rocket().state::<Config>().map(|c| &c.rpc) else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        if !config.enabled {
+            return Outcome::Error((Status::NotFound, ()));
+        }
+        let Some(expected) = config.token.as_deref().filter(|t| !t.is_empty()) else {
+            return Outcome::Success(RpcGuard);
+        };
+
+        let provided = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match provided {
+            Some(token) if secure_eq(token.trim(), expected) => Outcome::Success(RpcGuard),
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// 缺省时为通知（不返回响应）
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct LinkStatsParams {
+    id: String,
+    #[serde(default = "default_days")]
+    days: u32,
+}
+
+fn default_days() -> u32 {
+    30
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    #[serde(default)]
+    page: Option<u64>,
+    #[serde(default)]
+    per_page: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct UserParams {
+    openid: String,
+}
+
+#[derive(Deserialize)]
+struct NowPlayingParams {
+    user_id: u64,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        let code = match e {
+            Error::BadRequest(_) => INVALID_PARAMS,
+            Error::NotFound(_) => NOT_FOUND,
+            _ => INTERNAL_ERROR,
+        };
+        RpcError::new(code, e.to_string())
+    }
+}
+
+fn params<T: DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+// 按 ID 查找友链，待审核和已拒绝的友链只对管理员可见（对其他调用方按不存在处理）
+async fn find_visible_link(id: &str, admin: bool) -> Result<Document, RpcError> {
+    let link = LinkService::find_link(id).await?;
+    if !admin && !LinkService::is_public(&link) {
+        return Err(RpcError::new(NOT_FOUND, "Link not found"));
+    }
+    Ok(link)
+}
+
+// 方法表：与 HTTP 接口共用服务层；`admin` 为调用方是否携带管理员凭据
+async fn dispatch(method: &str, raw: Value, admin: bool) -> Result<Value, RpcError> {
+    match method {
+        "links.get" => {
+            let p: IdParams = params(raw)?;
+            let mut link = find_visible_link(&p.id, admin).await?;
+            // 友链申请人邮箱不对外返回
+            link.remove("email");
+            to_value(link)
+        }
+        "links.stats" => {
+            let p: LinkStatsParams = params(raw)?;
+            find_visible_link(&p.id, admin).await?;
+            to_value(LinkService::stats(&p.id, p.days).await?)
+        }
+        "links.search" => {
+            let p: SearchParams = params(raw)?;
+            to_value(SearchService::search(&p.q, p.page.unwrap_or(1), p.per_page.unwrap_or(20)).await?)
+        }
+        "site.stats" => to_value(StatsService::site_stats().await?),
+        "status.now_playing" => {
+            let p: NowPlayingParams = params(raw)?;
+            ncm_service::get_ncm_now_play(p.user_id)
+                .await
+                .map_err(|e| RpcError::new(INTERNAL_ERROR, format!("ncm request failed: {}", e)))
+        }
+        "user.get" => {
+            let p: UserParams = params(raw)?;
+            let user = UserService::profile(&p.openid)
+                .await?
+                .ok_or_else(|| RpcError::new(NOT_FOUND, "User not found"))?;
+            to_value(user)
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": error.code, "message": error.message },
+        "id": id,
+    })
+}
+
+/// 处理单个调用，通知（无 id）返回 None
+async fn handle_call(call: Value, admin: bool) -> Option<Value> {
+    let request: RpcRequest = match serde_json::from_value(call) {
+        Ok(r) => r,
+        Err(e) => return Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, e.to_string()))),
+    };
+    if request.jsonrpc != "2.0" {
+        let id = request.id.unwrap_or(Value::Null);
+        return Some(error_response(id, RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"")));
+    }
+
+    let result = dispatch(&request.method, request.params, admin).await;
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+// JSON-RPC 2.0 接口，支持批量调用；全部为通知时返回 204
+#[post("/", data = "<body>")]
+async fn rpc(_guard: RpcGuard, admin: Option<AdminAuth>, body: &str) -> Either<Json<Value>, Status> {
+    let admin = admin.is_some();
+    let payload: Value = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(e) => return Either::Left(Json(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))),
+    };
+
+    let response = match payload {
+        Value::Array(calls) => {
+            if calls.is_empty() || calls.len() > MAX_BATCH {
+                let message = format!("Batch must contain 1-{} calls", MAX_BATCH);
+                return Either::Left(Json(error_response(Value::Null, RpcError::new(INVALID_REQUEST, message))));
+            }
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in calls {
+                responses.extend(handle_call(call, admin).await);
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        call => handle_call(call, admin).await,
+    };
+
+    match response {
+        Some(response) => Either::Left(Json(response)),
+        None => Either::Right(Status::NoContent),
+    }
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![rpc]
+}
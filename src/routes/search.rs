@@ -0,0 +1,16 @@
+use crate::services::search_service::{SearchResults, SearchService};
+use crate::utils::response::ApiResponse;
+use crate::Result;
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route};
+
+// 全文搜索（当前覆盖友链名称、描述和标签），结果按集合分组并分页
+#[get("/search?<q>&<page>&<per_page>")]
+async fn search(q: &str, page: Option<u64>, per_page: Option<u64>) -> Result<Json<ApiResponse<SearchResults>>> {
+    let results = SearchService::search(q, page.unwrap_or(1), per_page.unwrap_or(20)).await?;
+    Ok(ApiResponse::success(results, "Search results"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![search]
+}
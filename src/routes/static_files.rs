@@ -0,0 +1,283 @@
+use crate::config::settings::{Config, StaticFilesConfig};
+use crate::utils::custom_response::CustomResponse;
+use crate::{Error, Result};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder};
+use rocket::{get, routes, Route, State};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// 文件名中内容哈希的长度（十六进制字符）
+const HASH_LEN: usize = 8;
+/// 带哈希文件名的缓存时长：一年
+const IMMUTABLE_MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// 文件摘要缓存：路径 -> (修改时间, 文件大小, sha256 十六进制)
+static DIGEST_CACHE: Lazy<Cache<PathBuf, (SystemTime, u64, String)>> =
+    Lazy::new(|| Cache::builder().max_capacity(1_000).build());
+
+/// If-None-Match 请求头
+pub struct IfNoneMatch(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfNoneMatch(
+            req.headers().get_one("If-None-Match").map(|s| s.to_string()),
+        ))
+    }
+}
+
+impl IfNoneMatch {
+    fn matches(&self, etag: &str) -> bool {
+        self.0
+            .as_deref()
+            .map(|v| v.split(',').any(|t| {
+                let t = t.trim();
+                t == "*" || t.trim_start_matches("W/") == etag
+            }))
+            .unwrap_or(false)
+    }
+}
+
+/// 拆分带哈希的文件名：`dashboard.1a2b3c4d.css` -> (`dashboard.css`, `1a2b3c4d`)
+fn split_hashed_name(name: &str) -> Option<(String, &str)> {
+    let (rest, ext) = name.rsplit_once('.')?;
+    let (stem, hash) = rest.rsplit_once('.')?;
+    if stem.is_empty() || hash.len() != HASH_LEN || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((format!("{}.{}", stem, ext), hash))
+}
+
+/// 将请求路径解析为静态目录内的文件，拒绝任何越出目录的路径
+fn resolve(root: &Path, rel: &Path) -> Option<PathBuf> {
+    // 只允许普通路径段（排除 `..`、绝对路径、盘符等）
+    if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let root = root.canonicalize().ok()?;
+    let full = root.join(rel).canonicalize().ok()?;
+    // 再次确认符号链接解析后仍在目录内
+    if full.starts_with(&root) && full.is_file() {
+        Some(full)
+    } else {
+        None
+    }
+}
+
+/// 计算文件内容的 sha256（按修改时间和大小缓存）
+async fn file_digest(path: &Path) -> Result<(String, Vec<u8>)> {
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|_| Error::NotFound("File not found".to_string()))?;
+    let modified = tokio::fs::metadata(path)
+        .await
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let key = path.to_path_buf();
+    if let Some((m, len, digest)) = DIGEST_CACHE.get(&key).await {
+        if m == modified && len == data.len() as u64 {
+            return Ok((digest, data));
+        }
+    }
+
+    let digest = format!("{:x}", Sha256::digest(&data));
+    DIGEST_CACHE
+        .insert(key, (modified, data.len() as u64, digest.clone()))
+        .await;
+    Ok((digest, data))
+}
+
+/// 生成带内容哈希的静态资源 URL，用于模板中引用（内容变化后 URL 随之变化）
+///
+/// 文件不存在时退回不带哈希的路径
+pub async fn asset_url(config: &StaticFilesConfig, name: &str) -> String {
+    let hashed = match resolve(Path::new(&config.dir), Path::new(name)) {
+        Some(path) => file_digest(&path).await.ok().and_then(|(digest, _)| {
+            let (stem, ext) = name.rsplit_once('.')?;
+            Some(format!("{}.{}.{}", stem, &digest[..HASH_LEN], ext))
+        }),
+        None => None,
+    };
+    format!("/static/{}", hashed.as_deref().unwrap_or(name))
+}
+
+/// 模板配置的预加载资源，转换为 Link 响应头的值；`vars` 替换路径中的 `{name}` 占位符
+pub async fn preload_links(config: &StaticFilesConfig, template: &str, vars: &[(&str, String)]) -> Vec<String> {
+    let mut links = Vec::new();
+    for entry in config.preload.get(template).into_iter().flatten() {
+        let link = if entry.starts_with("https://") || entry.starts_with("http://") {
+            match url::Url::parse(entry) {
+                Ok(url) => format!("<{}>; rel=preconnect; crossorigin", url.origin().ascii_serialization()),
+                Err(_) => continue,
+            }
+        } else if entry.starts_with('/') {
+            let path = vars
+                .iter()
+                .fold(entry.clone(), |path, (name, value)| path.replace(&format!("{{{}}}", name), value));
+            preload_link(&path)
+        } else {
+            preload_link(&asset_url(config, entry).await)
+        };
+        links.push(link);
+    }
+    links
+}
+
+// 按扩展名决定 as 属性，其余（接口、SSE）按 fetch 预加载
+fn preload_link(path: &str) -> String {
+    let ext = path
+        .split(['?', '#'])
+        .next()
+        .and_then(|p| p.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    let kind = match ext.as_str() {
+        "css" => "style",
+        "js" | "mjs" => "script",
+        "png" | "jpg" | "jpeg" | "webp" | "avif" | "gif" | "svg" => "image",
+        "woff" | "woff2" => "font; crossorigin",
+        _ => "fetch; crossorigin",
+    };
+    format!("<{}>; rel=preload; as={}", path, kind)
+}
+
+/// 带 Link 预加载响应头的响应
+pub struct Preloaded<R> {
+    inner: R,
+    links: Vec<String>,
+}
+
+impl<R> Preloaded<R> {
+    pub fn new(inner: R, links: Vec<String>) -> Self {
+        Self { inner, links }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Preloaded<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        for link in self.links {
+            response.adjoin_header(Header::new("Link", link));
+        }
+        Ok(response)
+    }
+}
+
+#[get("/static/<path..>")]
+async fn static_file(
+    path: PathBuf,
+    if_none_match: IfNoneMatch,
+    config: &State<Config>,
+) -> Result<CustomResponse> {
+    let root = Path::new(&config.static_files.dir);
+    let not_found = || Error::NotFound("File not found".to_string());
+
+    // 优先按原路径查找；找不到时再尝试去掉文件名中的哈希
+    let (file, expected_hash) = match resolve(root, &path) {
+        Some(file) => (file, None),
+        None => {
+            let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(not_found)?;
+            let (plain, hash) = split_hashed_name(name).ok_or_else(not_found)?;
+            let file = resolve(root, &path.with_file_name(plain)).ok_or_else(not_found)?;
+            (file, Some(hash.to_ascii_lowercase()))
+        }
+    };
+
+    let (digest, data) = file_digest(&file).await?;
+
+    // 哈希与当前内容不一致说明引用已过期，不能以 immutable 方式返回旧 URL
+    if let Some(hash) = &expected_hash {
+        if !digest.starts_with(hash.as_str()) {
+            return Err(not_found());
+        }
+    }
+
+    let etag = format!("\"{}\"", &digest[..16]);
+    let cache_control = if expected_hash.is_some() {
+        format!("public, max-age={}, immutable", IMMUTABLE_MAX_AGE)
+    } else {
+        format!("public, max-age={}, must-revalidate", config.static_files.max_age_secs)
+    };
+
+    let content_type = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(ContentType::from_extension)
+        .unwrap_or(ContentType::Binary);
+
+    if if_none_match.matches(&etag) {
+        return Ok(CustomResponse::new(content_type, Vec::new(), Status::NotModified)
+            .with_header("ETag", etag)
+            .with_header("Cache-Control", cache_control));
+    }
+
+    Ok(CustomResponse::new(content_type, data, Status::Ok)
+        .with_header("ETag", etag)
+        .with_header("Cache-Control", cache_control))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![static_file]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_hashed_name() {
+        assert_eq!(
+            split_hashed_name("dashboard.1a2b3c4d.css"),
+            Some(("dashboard.css".to_string(), "1a2b3c4d"))
+        );
+        assert_eq!(split_hashed_name("dashboard.css"), None);
+        assert_eq!(split_hashed_name("jquery.min.js"), None);
+        assert_eq!(split_hashed_name(".1a2b3c4d.css"), None);
+    }
+
+    #[rocket::async_test]
+    async fn test_preload_links() {
+        let config = StaticFilesConfig {
+            dir: std::env::temp_dir().join("space-api-missing").to_string_lossy().into_owned(),
+            ..StaticFilesConfig::default()
+        };
+        let links = preload_links(&config, "index", &[("refresh", "5".to_string())]).await;
+        assert_eq!(
+            links,
+            vec![
+                "</static/dashboard.css>; rel=preload; as=style",
+                "</static/dashboard.js>; rel=preload; as=script",
+            ]
+        );
+        let config = StaticFilesConfig {
+            preload: std::collections::HashMap::from([("index".to_string(), vec!["/api/stats?refresh={refresh}".to_string()])]),
+            ..config
+        };
+        assert_eq!(
+            preload_links(&config, "index", &[("refresh", "5".to_string())]).await,
+            vec!["</api/stats?refresh=5>; rel=preload; as=fetch; crossorigin"]
+        );
+        assert!(preload_links(&config, "admin/login", &[]).await.is_empty());
+        assert_eq!(
+            preload_link("/fonts/a.woff2?v=1"),
+            "</fonts/a.woff2?v=1>; rel=preload; as=font; crossorigin"
+        );
+    }
+
+    #[test]
+    fn test_resolve_rejects_traversal() {
+        let root = std::env::temp_dir();
+        assert!(resolve(&root, Path::new("../etc/passwd")).is_none());
+        assert!(resolve(&root, Path::new("/etc/passwd")).is_none());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::services::stats_service::StatsService;
+use crate::utils::etag::Conditional;
+use crate::utils::response::ApiResponse;
+use crate::Result;
+use rocket::{get, routes, Route};
+
+// 站点统计（监控面板和首页页脚使用），计数部分缓存 5 分钟
+//
+// 强 ETag 按完整响应体生成，响应内容完全相同时返回 304
+#[get("/")]
+async fn stats() -> Result<Conditional> {
+    let stats = StatsService::site_stats().await?;
+    Conditional::json(ApiResponse::success(stats, "Site statistics"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![stats]
+}
@@ -1,24 +1,68 @@
-use rocket::http::Status;
+use rocket::http::{ContentType, Status};
 use rocket::response::stream::{Event, EventStream};
 use rocket::serde::json::Json;
 use rocket::tokio::{
     select,
     time::{interval as tokio_interval, Duration as TokioDuration},
 };
-use rocket::{get, routes, Either, Route};
+use rocket::{get, routes, Either, Route, State};
+use rocket_dyn_templates::{context, Template};
 
+use crate::services::feature_service::NcmStatus;
+use crate::services::image_service::ImageService;
+use crate::services::mock_upstream;
 use crate::services::ncm_service;
-use crate::utils::cache::{self, CACHE_BUCKET};
+use crate::services::now_playing_service::{
+    build_base_result, build_song_obj, extract_song_id, handle_cache, now_playing, now_playing_detail,
+    playback_progress, DEFAULT_NCM_USER_ID,
+};
+use crate::services::upstream_fixtures;
+use crate::services::upstream_service::{self, HttpClientService};
+use crate::utils::cache::{self, Namespace};
+use crate::utils::custom_response::CustomResponse;
+use crate::utils::etag::Conditional;
+use crate::utils::feature_gate::FeatureGate;
+use crate::utils::music_card::{self, MusicCard};
+use crate::utils::negotiate::{AcceptsHtml, Negotiated};
+use crate::utils::request_context::RequestContext;
 use crate::utils::response::ApiResponse;
+use crate::utils::transcode_limiter;
 use crate::{Error, Result};
+use log::warn;
+use serde::Serialize;
 use serde_json::Value;
 use std::env;
 
-// 占位型结构已不需要，移除
+/// codetime 统计的缓存有效期
+const CODETIME_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
 
-// 获取代码时间统计（从 codetime.dev 代理返回原始 JSON）
+// 获取代码时间统计（从 codetime.dev 代理返回原始 JSON，浏览器访问时返回统计摘要页面）
 #[get("/codetime")]
-async fn codetime() -> Result<Json<ApiResponse<Value>>> {
+async fn codetime(
+    http: &State<HttpClientService>,
+    accepts: AcceptsHtml,
+) -> Result<Negotiated<Json<ApiResponse<Value>>>> {
+    let json = codetime_stats(http).await?;
+    if !accepts.0 {
+        return Ok(Negotiated::Data(codetime_response(json)));
+    }
+    if has_error(&json) {
+        return Err(Error::Internal("codetime service error".to_string()));
+    }
+    Ok(Negotiated::html(Template::render(
+        "status/codetime",
+        context! { summary: codetime_summary(&json) },
+    )))
+}
+
+async fn codetime_stats(http: &HttpClientService) -> Result<Value> {
+    if mock_upstream::is_enabled() {
+        return Ok(mock_upstream::codetime_stats().await);
+    }
+    if let Some(fixture) = upstream_fixtures::replay("codetime_stats").await {
+        return fixture;
+    }
+
     let session = env::var("CODETIME_SESSION").unwrap_or_default();
     if session.is_empty() {
         return Err(Error::Internal(
@@ -26,14 +70,21 @@ async fn codetime() -> Result<Json<ApiResponse<Value>>> {
         ));
     }
 
-    let client = reqwest::Client::new();
-    let resp = client
+    // 统计数据变化很慢，过期后先返回旧数据再后台刷新
+    let client = http.client().clone();
+    let key = Namespace::Snippets.key("codetime");
+    let (bytes, _) = cache::swr_get(&key, CODETIME_TTL, move || fetch_codetime(client, session)).await?;
+    serde_json::from_slice(&bytes).map_err(|e| Error::Internal(format!("parse codetime json failed: {}", e)))
+}
+
+async fn fetch_codetime(client: reqwest::Client, session: String) -> Result<Vec<u8>> {
+    let request = client
         .get("https://api.codetime.dev/stats/latest")
         .header(
             reqwest::header::COOKIE,
             format!("CODETIME_SESSION={}", session),
-        )
-        .send()
+        );
+    let resp = upstream_service::send(request)
         .await
         .map_err(|e| Error::Internal(format!("codetime request failed: {}", e)))?;
 
@@ -48,23 +99,90 @@ async fn codetime() -> Result<Json<ApiResponse<Value>>> {
         .json()
         .await
         .map_err(|e| Error::Internal(format!("parse codetime json failed: {}", e)))?;
+    upstream_fixtures::record("codetime_stats", &json).await;
+    // 不缓存服务错误，保留之前的统计数据
+    if has_error(&json) {
+        return Err(Error::Internal("codetime service error".to_string()));
+    }
+
+    Ok(json.to_string().into_bytes())
+}
+
+// codetime 响应中 error 不为空时视为服务错误
+fn has_error(json: &Value) -> bool {
+    json.get("error").is_some_and(|v| !v.is_null())
+}
 
-    if json.get("error").and_then(|v| if v.is_null() { None } else { Some(v) }).is_some() {
-        return Ok(ApiResponse::error("500", "codetime service error"));
+fn codetime_response(json: Value) -> Json<ApiResponse<Value>> {
+    if has_error(&json) {
+        return ApiResponse::error("500", "codetime service error");
     }
+    ApiResponse::success(json, "codetime")
+}
+
+/// codetime 统计摘要（HTML 页面使用）
+#[derive(Debug, Serialize)]
+struct CodetimeSummary {
+    total: String,
+    today: String,
+    languages: Vec<LanguageShare>,
+}
 
-    Ok(ApiResponse::success(json, "codetime"))
+#[derive(Debug, Serialize)]
+struct LanguageShare {
+    name: String,
+    duration: String,
+    /// 占今日各语言合计时长的百分比
+    percent: u64,
+}
+
+// 分钟数格式化为“x 小时 y 分钟”
+fn format_minutes(minutes: u64) -> String {
+    if minutes < 60 {
+        format!("{} 分钟", minutes)
+    } else {
+        format!("{} 小时 {} 分钟", minutes / 60, minutes % 60)
+    }
+}
+
+fn codetime_summary(json: &Value) -> CodetimeSummary {
+    let data = &json["data"];
+    let minutes = |v: &Value| v.as_u64().unwrap_or_default();
+    let languages: Vec<(String, u64)> = data["languages"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .map(|l| (l["name"].as_str().unwrap_or_default().to_string(), minutes(&l["minutes"])))
+                .collect()
+        })
+        .unwrap_or_default();
+    let sum: u64 = languages.iter().map(|(_, m)| m).sum();
+
+    CodetimeSummary {
+        total: format_minutes(minutes(&data["total_minutes"])),
+        today: format_minutes(minutes(&data["today_minutes"])),
+        languages: languages
+            .into_iter()
+            .map(|(name, m)| LanguageShare {
+                name,
+                duration: format_minutes(m),
+                percent: (m * 100).checked_div(sum).unwrap_or_default(),
+            })
+            .collect(),
+    }
 }
 
 #[get("/ncm?<q>&<query>&<sse>&<interval>&<i>")]
 async fn ncm(
+    _feature: FeatureGate<NcmStatus>,
     q: Option<u64>,
     query: Option<u64>,
     sse: Option<&str>,
     interval: Option<u64>,
     i: Option<u64>,
-) -> Result<Either<EventStream![], (Status, Json<ApiResponse<Value>>)>> {
-    let user_id = q.or(query).unwrap_or(515522946);
+    accepts: AcceptsHtml,
+) -> Result<Either<EventStream![], Negotiated<(Status, Json<ApiResponse<Value>>)>>> {
+    let user_id = q.or(query).unwrap_or(DEFAULT_NCM_USER_ID);
     let use_sse = matches!(sse, Some(v) if v.eq_ignore_ascii_case("true"));
     if use_sse {
         let ival = interval.or(i).unwrap_or(5000);
@@ -76,7 +194,7 @@ async fn ncm(
                 message: "Invalid interval: must be at least 1000ms".into(),
                 data: None,
             });
-            return Ok(Either::Right((Status::BadRequest, resp)));
+            return Ok(Either::Right(Negotiated::Data((Status::BadRequest, resp))));
         }
 
         let user_id_copy = user_id; // move into async block
@@ -140,214 +258,200 @@ async fn ncm(
         return Ok(Either::Left(stream));
     }
 
-    // 原 JSON 路径
-    let now = chrono::Utc::now().to_rfc3339();
-    let raw = ncm_service::get_ncm_now_play(user_id)
-        .await
-        .map_err(|e| Error::Internal(format!("ncm request failed: {}", e)))?;
-
-    let data = match raw.get("data") {
-        Some(v) if !v.is_null() => v,
-        _ => {
+    // 原 JSON 路径（浏览器访问时返回正在播放卡片）
+    match now_playing(user_id).await? {
+        Some(result) if accepts.0 => Ok(Either::Right(Negotiated::html(now_playing_page(&result)))),
+        Some(result) => Ok(Either::Right(Negotiated::Data((
+            Status::Ok,
+            ApiResponse::success(result, "Netease Music Now Playing Status"),
+        )))),
+        None => {
             let resp = Json(ApiResponse::<Value> {
                 code: "404".into(),
                 status: "failed".into(),
                 message: "User not found".into(),
                 data: None,
             });
-            return Ok(Either::Right((Status::NotFound, resp)));
-        }
-    };
-
-    // 提取当前 songId 用于活跃度判断
-    let current_song_id = extract_song_id(data);
-
-    let is_inactive = handle_cache(user_id as i64, current_song_id, &now).await?;
-
-    // 组装返回结构
-    let mut result = build_base_result(data, user_id as i64, !is_inactive, &now);
-
-    if !is_inactive {
-        // song 细节
-        if let Some(song) = data.get("song") {
-            let song_obj = build_song_obj(song);
-            if let Some(obj) = result.as_object_mut() {
-                obj.insert("song".to_string(), song_obj);
-            }
+            Ok(Either::Right(Negotiated::Data((Status::NotFound, resp))))
         }
     }
-
-    Ok(Either::Right((
-        Status::Ok,
-        ApiResponse::success(result, "Netease Music Now Playing Status"),
-    )))
 }
 
-// 处理简单缓存以判断活跃状态（5 分钟内同一首歌视为不活跃）
-async fn handle_cache(user_id: i64, song_id: i64, now_iso: &str) -> Result<bool> {
-    // 使用内置缓存（moka）替代数据库：键为 ncm_status:{user_id}，值为 JSON bytes
-    let key = format!("ncm_status:{}", user_id);
-
-    let mut is_inactive = false;
-
-    if let Some(bytes) = cache::get(&*CACHE_BUCKET, &key).await {
-        // 解析缓存内容
-        if let Ok(text) = String::from_utf8(bytes.clone()) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
-                let last_song_id = json
-                    .get("songId")
-                    .and_then(|v| v.as_i64())
-                    .unwrap_or_default();
-                let last_ts = json
-                    .get("timestamp")
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-                    .map(|dt| dt.with_timezone(&chrono::Utc));
-
-                if let Some(last) = last_ts {
-                    let diff = chrono::Utc::now() - last;
-                    if diff.num_milliseconds() > 5 * 60 * 1000 && last_song_id == song_id {
-                        is_inactive = true;
-                    }
-                }
-
-                // 歌曲变更则更新缓存
-                if last_song_id != song_id {
-                    let new_json = serde_json::json!({
-                        "userId": user_id,
-                        "songId": song_id,
-                        "timestamp": now_iso,
-                    });
-                    cache::put(&*CACHE_BUCKET, key, new_json.to_string().into_bytes()).await;
-                }
-            } else {
-                // 解析失败则写入当前状态
-                let new_json = serde_json::json!({
-                    "userId": user_id,
-                    "songId": song_id,
-                    "timestamp": now_iso,
-                });
-                cache::put(&*CACHE_BUCKET, key, new_json.to_string().into_bytes()).await;
-            }
-        }
-    } else {
-        // 无缓存，写入当前状态
-        let new_json = serde_json::json!({
-            "userId": user_id,
-            "songId": song_id,
-            "timestamp": now_iso,
-        });
-        cache::put(&*CACHE_BUCKET, key, new_json.to_string().into_bytes()).await;
+// 当前播放状态（供前端定时刷新），歌曲和活跃状态未变化时返回 304；浏览器访问时返回正在播放卡片
+#[get("/now?<q>")]
+async fn now(q: Option<u64>, accepts: AcceptsHtml) -> Result<Negotiated<Conditional>> {
+    let user_id = q.unwrap_or(DEFAULT_NCM_USER_ID);
+    let result = now_playing(user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound("User not found".into()))?;
+    if accepts.0 {
+        return Ok(Negotiated::html(now_playing_page(&result)));
     }
-
-    Ok(is_inactive)
+    // lastUpdate 每次请求都会变化，ETag 只按用户、歌曲和活跃状态生成
+    let key = format!("{}:{}:{}", user_id, result["song"]["id"], result["user"]["active"]);
+    Ok(Negotiated::Data(
+        Conditional::json(ApiResponse::success(result, "Netease Music Now Playing Status"))?
+            .weak(key)
+            .with_header("Cache-Control", "no-cache"),
+    ))
 }
 
-// 提取当前播放的歌曲 ID
-fn extract_song_id(data: &Value) -> i64 {
-    data.get("song")
-        .and_then(|s| s.get("id"))
-        .and_then(|v| v.as_i64())
-        .unwrap_or_default()
-}
-
-// 将毫秒时间戳转换为 RFC3339 字符串
-fn ms_to_rfc3339(ms: i64) -> String {
-    chrono::DateTime::<chrono::Utc>::from_timestamp_millis(ms)
-        .map(|d| d.to_rfc3339())
-        .unwrap_or_default()
-}
-
-// 构建基础返回结构（不含 song）
-fn build_base_result(
-    data: &Value,
-    user_id_fallback: i64,
-    active: bool,
-    last_update_iso: &str,
-) -> Value {
-    serde_json::json!({
-        "id": data.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
-        "user": {
-            "id": data.get("userId").and_then(|v| v.as_i64()).unwrap_or(user_id_fallback),
-            "avatar": data.get("avatar").and_then(|v| v.as_str()).unwrap_or_default(),
-            "name": data.get("userName").and_then(|v| v.as_str()).unwrap_or_default(),
-            "active": active,
+/// 正在播放卡片（页面每 30 秒刷新，可通过 iframe 嵌入）
+fn now_playing_page(result: &Value) -> Template {
+    Template::render(
+        "status/now_playing",
+        context! {
+            status: result,
+            artists: join_names(&result["song"]["artists"]),
         },
-        "lastUpdate": last_update_iso,
-    })
+    )
 }
 
-// 根据 TS 结构组装歌曲对象
-fn build_song_obj(song: &Value) -> Value {
-    let trans_names = song
-        .get("transNames")
-        .or_else(|| {
-            song.get("extProperties")
-                .and_then(|ep| ep.get("transNames"))
-        })
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-
-    let alias = song
-        .get("alias")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-
-    let artists = song
-        .get("artists")
-        .and_then(|v| v.as_array())
+// 歌手名以 " / " 连接
+fn join_names(artists: &Value) -> String {
+    artists
+        .as_array()
         .map(|arr| {
             arr.iter()
-                .map(|a| {
-                    serde_json::json!({
-                        "id": a.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
-                        "name": a.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
-                    })
-                })
+                .filter_map(|a| a["name"].as_str())
                 .collect::<Vec<_>>()
+                .join(" / ")
         })
-        .unwrap_or_default();
+        .unwrap_or_default()
+}
 
-    let album = song.get("album").cloned().unwrap_or(Value::Null);
-    let album_artists = album
-        .get("artists")
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .map(|a| {
-                    serde_json::json!({
-                        "id": a.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
-                        "name": a.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
-                    })
-                })
-                .collect::<Vec<_>>()
+/// 正在播放卡片 SVG（封面、歌名、歌手、进度），可嵌入 GitHub 主页和静态页面，无需 JavaScript
+///
+/// 查询参数：
+/// - q: 网易云音乐用户 ID（默认站长）
+///
+/// 示例：<img src="https://api.tnxg.top/status/ncm/widget.svg">
+#[get("/ncm/widget.svg?<q>")]
+async fn ncm_widget(
+    _feature: FeatureGate<NcmStatus>,
+    q: Option<u64>,
+    ctx: RequestContext,
+    images: &State<ImageService>,
+) -> Result<CustomResponse> {
+    let user_id = q.unwrap_or(DEFAULT_NCM_USER_ID);
+    let (result, duration_ms) = now_playing_detail(user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound("User not found".into()))?;
+
+    let song = &result["song"];
+    let svg = if song.is_null() {
+        music_card::render(&MusicCard {
+            title: "当前没有在听歌",
+            artist: result["user"]["name"].as_str().unwrap_or_default(),
+            cover: None,
+            progress: None,
         })
-        .unwrap_or_default();
+    } else {
+        let cover = match song["album"]["image"].as_str() {
+            Some(url) if !url.is_empty() => cover_data_uri(&ctx, images, url).await,
+            _ => None,
+        };
+        let progress = playback_progress(user_id, song["id"].as_i64().unwrap_or_default(), duration_ms).await;
+        music_card::render(&MusicCard {
+            title: song["name"].as_str().unwrap_or_default(),
+            artist: &join_names(&song["artists"]),
+            cover: cover.as_deref(),
+            progress,
+        })
+    };
 
-    let publish_time_iso = album
-        .get("publishTime")
-        .and_then(|v| v.as_i64())
-        .map(ms_to_rfc3339)
-        .unwrap_or_default();
+    // 进度随时间变化，只短暂缓存
+    Ok(CustomResponse::new(ContentType::SVG, svg.into_bytes(), Status::Ok)
+        .with_header("Cache-Control", "public, max-age=30, s-maxage=30"))
+}
 
-    serde_json::json!({
-        "name": song.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
-        "transNames": trans_names,
-        "alias": alias,
-        "id": song.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
-        "artists": artists,
-        "album": {
-            "name": album.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
-            "id": album.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
-            "image": album.get("picUrl").and_then(|v| v.as_str()).unwrap_or_default(),
-            "publishTime": publish_time_iso,
-            "artists": album_artists,
+// 封面缩略图的 data URI（snippets 命名空间缓存），获取失败时不显示封面
+async fn cover_data_uri(ctx: &RequestContext, images: &ImageService, url: &str) -> Option<String> {
+    let key = Namespace::Snippets.derived_key(format_args!("ncm-cover:{}", url));
+    if let Some(cached) = cache::backend().get(&key).await {
+        return String::from_utf8(cached).ok();
+    }
+
+    let result = async {
+        let (bytes, _) = images.fetch_avatar(ctx, url).await?;
+        transcode_limiter::run(move || music_card::thumbnail_data_uri(&bytes)).await?
+    }
+    .await;
+    match result {
+        Ok(uri) => {
+            cache::backend().put(&key, uri.clone().into_bytes(), None).await;
+            Some(uri)
         }
-    })
+        Err(e) => {
+            warn!("Failed to load album cover {}: {}", url, e);
+            None
+        }
+    }
 }
 
 pub fn routes() -> Vec<Route> {
-    routes![codetime, ncm]
+    routes![codetime, ncm, ncm_widget, now]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::upstream_fixtures::test_fixture;
+
+    #[test]
+    fn test_parse_fixtures() {
+        let raw = test_fixture("ncm_now_play");
+        let data = &raw["data"];
+        assert_eq!(extract_song_id(data), 1901371647);
+
+        let base = build_base_result(data, 42, true, "2025-01-01T00:00:00+00:00");
+        assert_eq!(base["user"]["name"], "redacted");
+        assert_eq!(base["user"]["active"], true);
+
+        let song = build_song_obj(&data["song"]);
+        assert_eq!(song["name"], "孤勇者");
+        // transNames 只在 extProperties 中
+        assert_eq!(song["transNames"][0], "Lonely Warrior");
+        assert_eq!(song["artists"][0]["name"], "陈奕迅");
+        assert_eq!(song["album"]["id"], 135450364);
+        assert!(song["album"]["image"].as_str().unwrap().starts_with("https://"));
+        assert!(song["album"]["publishTime"].as_str().unwrap().starts_with("2021-11-11"));
+
+        let codetime = codetime_response(test_fixture("codetime_stats")).into_inner();
+        assert_eq!(codetime.status, "success");
+        let codetime = codetime_response(serde_json::json!({ "error": "unauthorized" })).into_inner();
+        assert_eq!(codetime.code, "500");
+
+        let summary = codetime_summary(&test_fixture("codetime_stats"));
+        assert_eq!(summary.total, "1646 小时 5 分钟");
+        assert_eq!(summary.today, "3 小时 3 分钟");
+        assert_eq!(summary.languages[0].name, "Rust");
+        assert_eq!(summary.languages[0].percent, 65);
+        assert_eq!(summary.languages[2].duration, "15 分钟");
+        assert_eq!(join_names(&song["artists"]), "陈奕迅");
+    }
+
+    #[rocket::async_test]
+    async fn test_render_pages() {
+        let figment = rocket::Config::figment().merge(("template_dir", "src/templates"));
+        let client = rocket::local::asynchronous::Client::untracked(rocket::custom(figment).attach(Template::fairing()))
+            .await
+            .unwrap();
+        let rocket = client.rocket();
+
+        let raw = test_fixture("ncm_now_play");
+        let mut result = build_base_result(&raw["data"], 42, true, "2025-01-01T00:00:00+00:00");
+        result["song"] = build_song_obj(&raw["data"]["song"]);
+        let page = Template::show(rocket, "status/now_playing", context! { status: &result, artists: "陈奕迅" }).unwrap();
+        assert!(page.contains("孤勇者"));
+
+        // 不活跃时没有 song 字段
+        let idle = build_base_result(&raw["data"], 42, false, "2025-01-01T00:00:00+00:00");
+        let page = Template::show(rocket, "status/now_playing", context! { status: &idle, artists: "" }).unwrap();
+        assert!(page.contains("当前没有在听歌"));
+
+        let summary = codetime_summary(&test_fixture("codetime_stats"));
+        let page = Template::show(rocket, "status/codetime", context! { summary }).unwrap();
+        assert!(page.contains("width: 65%"));
+    }
 }
@@ -1,60 +1,110 @@
-use rocket::{Route, get, routes};
+use rocket::{Route, get, routes, Orbit, Rocket, State};
 use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket_dyn_templates::{context, Template};
+use sha2::{Digest, Sha256};
+use crate::config::settings::{Config, ServiceWorkerConfig};
+use crate::services::cdn_service;
 use crate::utils::custom_response::CustomResponse;
-use crate::utils::cache::CACHE_BUCKET;
+use crate::utils::cache::{self, Namespace};
+use crate::utils::compression::AcceptEncoding;
+
+const SUPPORTED_STRATEGIES: &[&str] = &["cache-first", "network-first", "stale-while-revalidate", "network-only"];
+
+/// 运行中的 Rocket 实例（Template::show 渲染模板需要）
+struct OrbitRocket<'r>(&'r Rocket<Orbit>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for OrbitRocket<'r> {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(OrbitRocket(req.rocket()))
+    }
+}
+
+// 缓存版本：构建版本（可通过 BUILD_HASH 环境变量在编译期注入 git 提交号）+ 配置内容的哈希
+fn cache_version(config: &ServiceWorkerConfig) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(option_env!("BUILD_HASH").unwrap_or_default().as_bytes());
+    hasher.update(serde_json::to_vec(config).unwrap_or_default());
+    let hash = format!("{:x}", hasher.finalize());
+    hash[..12].to_string()
+}
+
+fn script_response(body: Vec<u8>, encoding: Option<&str>, cached: bool) -> CustomResponse {
+    let response = CustomResponse::new(ContentType::JavaScript, body, Status::Ok)
+        .with_header("Cache-Control", "no-cache")
+        .with_header("Vary", "Accept-Encoding")
+        .with_cache(cached);
+    match encoding {
+        Some(encoding) => response.with_header("Content-Encoding", encoding),
+        None => response,
+    }
+}
 
 #[get("/sw.js")]
-async fn sw_js() -> CustomResponse {
-    // 缓存键
-    let cache_key = "sw_js".to_string();
+async fn sw_js(rocket: OrbitRocket<'_>, accept: AcceptEncoding, config: &State<Config>) -> CustomResponse {
+    let sw_config = &config.service_worker;
+    let version = cache_version(sw_config);
 
-    // 先尝试从全局缓存读取
-    if let Some(cached) = crate::utils::cache::get(&CACHE_BUCKET, &cache_key).await {
-        return CustomResponse::new(ContentType::JavaScript, cached, Status::Ok).with_cache(true);
+    // 缓存键（包含版本，配置变化后自动失效）
+    let cache_key = Namespace::ServiceWorker.derived_key(&version);
+
+    // 先尝试从缓存读取（客户端支持时直接返回预压缩的版本）
+    if let Some((cached, encoding)) = cache::get_variant(&cache_key, &accept).await {
+        return script_response(cached, encoding, true);
     }
 
-    // 远程 URL
-    let url = "https://mx.tnxg.top/api/v2/snippets/js/sw";
+    // 过滤不支持的缓存策略，避免生成的脚本行为不确定
+    let rules: Vec<_> = sw_config
+        .runtime_rules
+        .iter()
+        .filter(|r| {
+            let ok = SUPPORTED_STRATEGIES.contains(&r.strategy.as_str());
+            if !ok {
+                log::warn!("忽略不支持的 Service Worker 缓存策略: {} ({})", r.strategy, r.pattern);
+            }
+            ok
+        })
+        .collect();
 
-    let client = reqwest::Client::new();
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36 Edg/114.0.1823.82"),
-    );
-    headers.insert(
-        reqwest::header::CONTENT_TYPE,
-        reqwest::header::HeaderValue::from_static("application/javascript; charset=utf-8"),
+    let rendered = Template::show(
+        rocket.0,
+        "sw.js",
+        context! {
+            app_version: concat!("v", env!("CARGO_PKG_VERSION")),
+            cache_version: &version,
+            precache_json: serde_json::to_string(&sw_config.precache).unwrap_or_else(|_| "[]".to_string()),
+            rules_json: serde_json::to_string(&rules).unwrap_or_else(|_| "[]".to_string()),
+        },
     );
 
-    match client.get(url).headers(headers).send().await {
-        Ok(resp) => {
-            let status = resp.status();
-            match resp.text().await {
-                Ok(text) => {
-                    if status.is_success() {
-                        let bytes = text.into_bytes();
-                        // 写入缓存，忽略返回值
-                        let _ = crate::utils::cache::put(&CACHE_BUCKET, cache_key.clone(), bytes.clone()).await;
-                        CustomResponse::new(ContentType::JavaScript, bytes, Status::Ok).with_cache(false)
-                    } else {
-                        let msg = format!("// Failed to load service worker script: HTTP status {}", status.as_u16());
-                        CustomResponse::new(ContentType::JavaScript, msg.into_bytes(), Status::InternalServerError)
-                    }
-                }
-                Err(e) => {
-                    let msg = format!("// Failed to load service worker script: {}", e);
-                    CustomResponse::new(ContentType::JavaScript, msg.into_bytes(), Status::InternalServerError)
-                }
+    match rendered {
+        Some(script) => {
+            let bytes = script.into_bytes();
+            // 写入缓存并生成预压缩版本，之后的请求不再渲染和压缩
+            cache::put_with_variants(cache_key.clone(), bytes.clone()).await;
+            match cache::get_variant(&cache_key, &accept).await {
+                Some((body, encoding)) => script_response(body, encoding, false),
+                None => script_response(bytes, None, false),
             }
         }
-        Err(e) => {
-            let msg = format!("// Failed to load service worker script: {}", e);
-            CustomResponse::new(ContentType::JavaScript, msg.into_bytes(), Status::InternalServerError)
+        None => {
+            let msg = "// Failed to render service worker script";
+            CustomResponse::new(ContentType::JavaScript, msg.as_bytes().to_vec(), Status::InternalServerError)
         }
     }
 }
 
+/// 清除 sw.js 的本地缓存并刷新 CDN 上的副本（部署或外部事件触发）
+pub(crate) async fn invalidate(config: &ServiceWorkerConfig) {
+    let cache_key = Namespace::ServiceWorker.derived_key(cache_version(config));
+    cache::remove_with_variants(&cache_key).await;
+    cdn_service::purge_detached(vec!["/sw.js".to_string()]);
+}
+
 pub fn routes() -> Vec<Route> {
     routes![sw_js]
-}
\ No newline at end of file
+}
@@ -1,7 +1,13 @@
-use rocket::{Route, get, routes};
+use rocket::{Route, delete, get, routes};
+use rocket::http::{ContentType, Status};
 use rocket::serde::json::Json;
 use mongodb::bson::{doc, Bson};
 use crate::services::db_service;
+use crate::services::user_service::UserService;
+use crate::utils::auth::UserGuard;
+use crate::utils::crypto;
+use crate::utils::idempotency::Idempotency;
+use crate::utils::custom_response::CustomResponse;
 use crate::utils::response::ApiResponse;
 use crate::{Result, Error};
 
@@ -17,11 +23,8 @@ async fn user_info(
         Error::BadRequest("id is required".to_string())
     })?;
     
-    // 查询数据库
-    let user = db_service::find_one(
-        "users", 
-        doc! { "qq_openid": qqopenid }
-    ).await?;
+    // 查询数据库（敏感字段如访问令牌不对外返回）
+    let user = UserService::profile(qqopenid).await?;
     
     // 检查用户是否存在
     match user {
@@ -42,8 +45,9 @@ async fn user_info(
 async fn user_get(code: Option<&str>) -> Result<Json<ApiResponse<serde_json::Value>>> {
     let code = code.ok_or_else(|| Error::BadRequest("Temporary code is required".into()))?;
 
-    // 查找未使用的临时代码
-    let temp_opt = db_service::find_one("temp_codes", doc! { "code": code, "used": false }).await?;
+    // 查找未使用的临时代码（库中只保存 HMAC，按哈希查询；泄露的哈希无法反推出可用的代码）
+    let code_hash = crypto::hash_code(code);
+    let temp_opt = db_service::find_one("temp_codes", doc! { "code_hash": &code_hash, "used": false }).await?;
     let temp = temp_opt.ok_or_else(|| Error::NotFound("Invalid or expired temporary code".into()))?;
 
     // 过期校验
@@ -66,7 +70,7 @@ async fn user_get(code: Option<&str>) -> Result<Json<ApiResponse<serde_json::Val
     let user_doc = user_doc_opt.ok_or_else(|| Error::NotFound("User not found".into()))?;
 
     // 删除临时代码（一次性）
-    let _ = db_service::delete_one("temp_codes", doc! { "code": code }).await?;
+    let _ = db_service::delete_one("temp_codes", doc! { "code_hash": &code_hash }).await?;
 
     // 构造返回
     let user_id = match user_doc.get("_id") {
@@ -79,6 +83,9 @@ async fn user_get(code: Option<&str>) -> Result<Json<ApiResponse<serde_json::Val
     let created_at = user_doc.get_str("created_at").unwrap_or("").to_string();
     let updated_at = user_doc.get_str("updated_at").unwrap_or("").to_string();
 
+    // 签发会话令牌，用于 /user/export 和 /user/me 等需要登录的接口
+    let (session_token, session_expires_at) = UserService::create_session(&openid).await?;
+
     let data = serde_json::json!({
         "user_id": user_id,
         "qq_openid": openid,
@@ -87,11 +94,32 @@ async fn user_get(code: Option<&str>) -> Result<Json<ApiResponse<serde_json::Val
         "gender": gender,
         "created_at": created_at,
         "updated_at": updated_at,
+        "session_token": session_token,
+        "session_expires_at": session_expires_at,
     });
 
     Ok(ApiResponse::success(data, "User information retrieved successfully"))
 }
 
+// 导出当前用户的个人数据（JSON 文件下载）
+#[get("/export")]
+async fn user_export(guard: UserGuard) -> Result<CustomResponse> {
+    let data = UserService::export(&guard.user).await?;
+    let body = serde_json::to_vec_pretty(&data)
+        .map_err(|e| Error::Internal(format!("Failed to serialize export: {}", e)))?;
+
+    Ok(CustomResponse::new(ContentType::JSON, body, Status::Ok)
+        .with_header("Content-Disposition", "attachment; filename=\"space-api-export.json\"")
+        .with_cache(false))
+}
+
+// 注销当前用户：软删除并匿名化个人数据
+#[delete("/me")]
+async fn user_delete(guard: UserGuard, _idempotency: Idempotency) -> Result<Json<ApiResponse<()>>> {
+    UserService::delete_account(&guard.user).await?;
+    Ok(ApiResponse::success((), "Account deleted"))
+}
+
 pub fn routes() -> Vec<Route> {
-    routes![user_info, user_get]
+    routes![user_info, user_get, user_export, user_delete]
 }
\ No newline at end of file
@@ -0,0 +1,218 @@
+use crate::services::now_playing_service::{self, DEFAULT_NCM_USER_ID};
+use crate::services::stats_service::StatsService;
+use crate::services::weather_service;
+use crate::utils::request_counter;
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use rocket::serde::json::Json;
+use rocket::{get, routes, Route};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// 可用的首页组件
+const WIDGETS: &[&str] = &["ncm", "weather", "links_count", "uptime"];
+
+/// 一次最多请求的组件数
+const MAX_WIDGETS: usize = 8;
+
+/// 正在播放的缓存时长（秒）
+const NCM_MAX_AGE: u64 = 15;
+/// 友链计数复用站点统计的 5 分钟缓存
+const LINKS_COUNT_MAX_AGE: u64 = 5 * 60;
+
+/// 正在播放（用户不存在时为 None）和获取时间
+type NcmEntry = (Option<Value>, DateTime<Utc>);
+
+// 用户 ID -> 正在播放
+static NCM_CACHE: Lazy<Cache<u64, NcmEntry>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(100)
+        .time_to_live(Duration::from_secs(NCM_MAX_AGE))
+        .build()
+});
+
+/// 单个组件的数据和新鲜度
+#[derive(Debug, Serialize)]
+pub struct Widget {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 数据生成时间（RFC 3339），客户端据此判断是否需要刷新
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    /// 数据最长缓存时间（秒），0 表示实时数据
+    pub max_age_secs: u64,
+}
+
+impl Widget {
+    fn fresh(data: Value, generated_at: DateTime<Utc>, max_age_secs: u64) -> Self {
+        Self {
+            ok: true,
+            data: Some(data),
+            error: None,
+            generated_at: Some(generated_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            max_age_secs,
+        }
+    }
+
+    fn failed(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            data: None,
+            error: Some(error.into()),
+            generated_at: None,
+            max_age_secs: 0,
+        }
+    }
+}
+
+impl From<Result<Widget>> for Widget {
+    fn from(result: Result<Widget>) -> Self {
+        result.unwrap_or_else(|e| {
+            log::warn!("Widget failed: {}", e);
+            Widget::failed(match e {
+                Error::Database(_) | Error::Internal(_) => "Upstream unavailable".to_string(),
+                other => other.to_string(),
+            })
+        })
+    }
+}
+
+async fn ncm_widget(user_id: u64) -> Result<Widget> {
+    let (data, fetched_at) = match NCM_CACHE.get(&user_id).await {
+        Some(cached) => cached,
+        None => {
+            let entry = (now_playing_service::now_playing(user_id).await?, Utc::now());
+            NCM_CACHE.insert(user_id, entry.clone()).await;
+            entry
+        }
+    };
+    match data {
+        Some(data) => Ok(Widget::fresh(data, fetched_at, NCM_MAX_AGE)),
+        None => Err(Error::NotFound("NCM user not found".into())),
+    }
+}
+
+async fn weather_widget() -> Result<Widget> {
+    let (data, fetched_at) = weather_service::current().await?;
+    Ok(Widget::fresh(data, fetched_at, weather_service::max_age_secs()))
+}
+
+async fn links_count_widget() -> Result<Widget> {
+    let stats = StatsService::site_stats().await?;
+    let generated_at = DateTime::parse_from_rfc3339(&stats.counts.generated_at)
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+    let links = &stats.counts.links;
+    let approved = links.by_state.get("approved").copied().unwrap_or(0);
+    Ok(Widget::fresh(
+        json!({ "total": links.total, "approved": approved }),
+        generated_at,
+        LINKS_COUNT_MAX_AGE,
+    ))
+}
+
+fn uptime_widget() -> Widget {
+    let uptime_secs = request_counter::uptime_secs();
+    Widget::fresh(
+        json!({
+            "uptime_secs": uptime_secs,
+            "started_at": (Utc::now() - chrono::Duration::seconds(uptime_secs as i64))
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        }),
+        Utc::now(),
+        0,
+    )
+}
+
+// 首页组件合集：一次请求返回 include 中列出的组件（逗号分隔），单个组件失败不影响其他组件
+//
+// 例：/api/widgets?include=ncm,weather,links_count,uptime
+#[get("/?<include>&<ncm_user>")]
+async fn widgets(include: &str, ncm_user: Option<u64>) -> Result<Json<ApiResponse<BTreeMap<String, Widget>>>> {
+    let mut names: Vec<&str> = include.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+    names.sort_unstable();
+    names.dedup();
+    if names.is_empty() {
+        return Err(Error::BadRequest(format!("include must list widgets: {}", WIDGETS.join(", "))));
+    }
+    if names.len() > MAX_WIDGETS {
+        return Err(Error::BadRequest(format!("include accepts at most {} widgets", MAX_WIDGETS)));
+    }
+
+    let wants = |name: &str| names.contains(&name);
+    let (ncm, weather, links_count) = tokio::join!(
+        async {
+            if wants("ncm") {
+                Some(ncm_widget(ncm_user.unwrap_or(DEFAULT_NCM_USER_ID)).await)
+            } else {
+                None
+            }
+        },
+        async {
+            if wants("weather") {
+                Some(weather_widget().await)
+            } else {
+                None
+            }
+        },
+        async {
+            if wants("links_count") {
+                Some(links_count_widget().await)
+            } else {
+                None
+            }
+        },
+    );
+
+    let mut bundle = BTreeMap::new();
+    if let Some(widget) = ncm {
+        bundle.insert("ncm".to_string(), widget.into());
+    }
+    if let Some(widget) = weather {
+        bundle.insert("weather".to_string(), widget.into());
+    }
+    if let Some(widget) = links_count {
+        bundle.insert("links_count".to_string(), widget.into());
+    }
+    if wants("uptime") {
+        bundle.insert("uptime".to_string(), uptime_widget());
+    }
+    // 未知组件单独返回错误，不影响其他组件
+    for name in names.iter().filter(|n| !WIDGETS.contains(n)) {
+        bundle.insert(name.to_string(), Widget::failed("Unknown widget"));
+    }
+
+    Ok(ApiResponse::success(bundle, "Widgets"))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![widgets]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+
+    #[rocket::async_test]
+    async fn test_bundle() {
+        let client = Client::untracked(rocket::build().mount("/api/widgets", routes())).await.unwrap();
+        let response = client.get("/api/widgets?include=uptime,weather,clock,uptime").dispatch().await;
+        let body: Value = response.into_json().await.unwrap();
+        let data = body["data"].as_object().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data["uptime"]["ok"], true);
+        assert_eq!(data["uptime"]["max_age_secs"], 0);
+        // 测试中未配置天气位置
+        assert_eq!(data["weather"]["ok"], false);
+        assert_eq!(data["clock"]["error"], "Unknown widget");
+    }
+}
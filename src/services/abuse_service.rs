@@ -0,0 +1,179 @@
+use crate::config::settings::AbuseConfig;
+use crate::services::audit_service::AuditService;
+use crate::services::ip_filter_service::{IpBlock, IpFilterService};
+use crate::Result;
+use log::{error, warn};
+use moka::future::Cache;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 自动封禁的原因前缀（用于区分手动封禁）
+pub const AUTO_BAN_PREFIX: &str = "auto:";
+
+/// 滥用信号类型
+#[derive(Debug, Clone, Copy)]
+pub enum AbuseSignal {
+    /// 图片类接口缓存未命中（触发上游下载和转码）
+    ImageCacheMiss,
+    /// 邮箱验证码校验失败
+    VerificationFailure,
+}
+
+impl AbuseSignal {
+    fn name(&self) -> &'static str {
+        match self {
+            AbuseSignal::ImageCacheMiss => "image_cache_miss",
+            AbuseSignal::VerificationFailure => "verification_failure",
+        }
+    }
+}
+
+/// 单个信号的固定窗口计数器（窗口从首次事件开始计算）
+struct SignalCounter {
+    threshold: u32,
+    window_secs: u64,
+    counts: Cache<IpAddr, Arc<AtomicU32>>,
+}
+
+impl SignalCounter {
+    fn new(threshold: u32, window_secs: u64) -> Self {
+        Self {
+            threshold,
+            window_secs,
+            counts: Cache::builder()
+                .time_to_live(Duration::from_secs(window_secs.max(1)))
+                .max_capacity(100_000)
+                .build(),
+        }
+    }
+}
+
+/// 滥用检测服务：按 IP 统计可疑行为，超过阈值后自动添加临时封禁，封禁时长随违规次数指数增长
+pub struct AbuseService {
+    config: AbuseConfig,
+    ip_filter: Arc<IpFilterService>,
+    image_misses: SignalCounter,
+    verify_failures: SignalCounter,
+    /// IP -> 违规次数
+    offenses: Cache<IpAddr, u32>,
+}
+
+impl AbuseService {
+    pub fn new(config: AbuseConfig, ip_filter: Arc<IpFilterService>) -> Self {
+        Self {
+            image_misses: SignalCounter::new(config.image_miss_threshold, config.image_miss_window_secs),
+            verify_failures: SignalCounter::new(
+                config.verify_failure_threshold,
+                config.verify_failure_window_secs,
+            ),
+            offenses: Cache::builder()
+                .time_to_live(Duration::from_secs(config.offense_memory_secs.max(1)))
+                .max_capacity(100_000)
+                .build(),
+            config,
+            ip_filter,
+        }
+    }
+
+    /// 记录一次可疑行为，达到阈值时在后台执行封禁
+    pub async fn record(self: &Arc<Self>, ip: Option<IpAddr>, signal: AbuseSignal) {
+        let ip = match ip {
+            Some(ip) if self.config.enabled => ip,
+            _ => return,
+        };
+
+        let counter = match signal {
+            AbuseSignal::ImageCacheMiss => &self.image_misses,
+            AbuseSignal::VerificationFailure => &self.verify_failures,
+        };
+
+        let count = counter
+            .counts
+            .get_with(ip, async { Arc::new(AtomicU32::new(0)) })
+            .await
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+
+        // 只在刚好达到阈值时触发一次，避免并发请求重复封禁
+        if count != counter.threshold {
+            return;
+        }
+        counter.counts.invalidate(&ip).await;
+
+        let service = Arc::clone(self);
+        let window_secs = counter.window_secs;
+        tokio::spawn(async move {
+            service.auto_ban(ip, signal, count, window_secs).await;
+        });
+    }
+
+    /// 计算第 n 次违规的封禁时长：base * 2^(n-1)，不超过 max
+    pub fn ban_duration(&self, offense: u32) -> Duration {
+        let factor = 1u64 << offense.saturating_sub(1).min(32);
+        let secs = self
+            .config
+            .base_ban_secs
+            .saturating_mul(factor)
+            .min(self.config.max_ban_secs);
+        Duration::from_secs(secs)
+    }
+
+    async fn auto_ban(&self, ip: IpAddr, signal: AbuseSignal, count: u32, window_secs: u64) {
+        let offense = self.offenses.get(&ip).await.unwrap_or(0) + 1;
+        self.offenses.insert(ip, offense).await;
+
+        let duration = self.ban_duration(offense);
+        let reason = format!(
+            "{} {} x{} in {}s (offense #{})",
+            AUTO_BAN_PREFIX,
+            signal.name(),
+            count,
+            window_secs,
+            offense
+        );
+
+        warn!("自动封禁 {} {} 秒: {}", ip, duration.as_secs(), reason);
+        match self.ip_filter.block(&ip.to_string(), &reason, Some(duration)).await {
+            Ok(block) => {
+                AuditService::record(
+                    "abuse.auto_ban",
+                    "system",
+                    &block.cidr,
+                    serde_json::json!({
+                        "signal": signal.name(),
+                        "count": count,
+                        "window_secs": window_secs,
+                        "offense": offense,
+                        "duration_secs": duration.as_secs(),
+                    }),
+                )
+                .await;
+            }
+            Err(e) => error!("自动封禁 {} 失败: {}", ip, e),
+        }
+    }
+
+    /// 当前生效的自动封禁
+    pub async fn list_bans(&self) -> Vec<IpBlock> {
+        self.ip_filter
+            .list_blocks()
+            .await
+            .into_iter()
+            .filter(|b| b.reason.starts_with(AUTO_BAN_PREFIX))
+            .collect()
+    }
+
+    /// 解除自动封禁（同一地址的手动封禁不受影响），返回是否存在该自动封禁
+    pub async fn lift_ban(&self, ip: &str) -> Result<bool> {
+        self.ip_filter
+            .unblock_if(ip, |b| b.reason.starts_with(AUTO_BAN_PREFIX))
+            .await
+    }
+
+    /// 获取 IP 的违规次数
+    pub async fn offense_count(&self, ip: &IpAddr) -> u32 {
+        self.offenses.get(ip).await.unwrap_or(0)
+    }
+}
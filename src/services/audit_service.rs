@@ -0,0 +1,38 @@
+use crate::services::db_service;
+use chrono::Utc;
+use log::{error, info};
+use mongodb::bson::{doc, Bson};
+
+const AUDIT_COLLECTION: &str = "audit_logs";
+
+/// 审计日志服务：记录管理操作与安全事件到 MongoDB audit_logs 集合
+pub struct AuditService;
+
+impl AuditService {
+    /// 记录一条审计日志
+    ///
+    /// 写入失败只记录错误日志，不影响调用方的主流程
+    pub async fn record(action: &str, actor: &str, target: &str, detail: serde_json::Value) {
+        let detail = mongodb::bson::to_bson(&detail).unwrap_or(Bson::Null);
+        let entry = doc! {
+            "action": action,
+            "actor": actor,
+            "target": target,
+            "detail": detail,
+            "created_at": Utc::now().to_rfc3339(),
+        };
+
+        info!("[audit] {} by {} -> {}", action, actor, target);
+        if let Err(e) = db_service::insert_one(AUDIT_COLLECTION, entry).await {
+            error!("Failed to write audit log ({}): {}", action, e);
+        }
+    }
+
+    /// 在后台记录审计日志（用于请求热路径，如 fairing 中）
+    pub fn record_detached(action: &str, actor: &str, target: &str, detail: serde_json::Value) {
+        let (action, actor, target) = (action.to_string(), actor.to_string(), target.to_string());
+        tokio::spawn(async move {
+            Self::record(&action, &actor, &target, detail).await;
+        });
+    }
+}
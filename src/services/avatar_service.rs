@@ -0,0 +1,695 @@
+use crate::config::settings::{AvatarConfig, AvatarSourceConfig};
+use crate::services::image_service::{ImageService, ImageTransform};
+use crate::utils::cache::{self, Namespace};
+use crate::utils::client_hints::AVATAR_SIZES;
+use crate::utils::request_context::RequestContext;
+use crate::utils::rng;
+use crate::utils::transcode_limiter;
+use crate::{Error, Result};
+use chrono::Utc;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+/// 地址模板中的 id 占位符
+const ID_PLACEHOLDER: &str = "{id}";
+const MAX_ID_LEN: usize = 64;
+
+/// 一个头像来源
+#[derive(Debug, Clone)]
+pub struct AvatarSource {
+    pub name: String,
+    template: String,
+    default_id: Option<String>,
+    pub ttl: Duration,
+    pub fallback: Option<String>,
+    pub warm: bool,
+    /// 接受 email 参数（id 为邮箱的 SHA-256）
+    pub email: bool,
+    /// 按邮箱域名的 SRV 记录联邦查找 Libravatar 服务器
+    pub federated: bool,
+}
+
+impl AvatarSource {
+    fn new(name: &str, config: &AvatarSourceConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            template: config.url.clone(),
+            default_id: config.default_id.clone(),
+            ttl: Duration::from_secs(config.ttl_secs),
+            fallback: config.fallback.clone(),
+            warm: config.warm,
+            email: config.email,
+            federated: config.federated,
+        }
+    }
+
+    /// 地址模板是否包含 {id}
+    pub fn takes_id(&self) -> bool {
+        self.template.contains(ID_PLACEHOLDER)
+    }
+
+    /// 按 id 生成原图地址，返回 (地址, 实际使用的 id)；模板不含 {id} 时忽略 id
+    pub fn resolve<'a>(&'a self, id: Option<&'a str>) -> Result<(String, Option<&'a str>)> {
+        if !self.takes_id() {
+            return Ok((self.template.clone(), None));
+        }
+        let id = id
+            .filter(|id| !id.is_empty())
+            .or(self.default_id.as_deref())
+            .ok_or_else(|| Error::BadRequest(format!("Avatar source {} requires an id", self.name)))?;
+        if !valid_id(id) {
+            return Err(Error::BadRequest("id must be 1-64 letters, digits, '.', '-' or '_'".into()));
+        }
+        Ok((self.template.replace(ID_PLACEHOLDER, id), Some(id)))
+    }
+
+    /// 预热的地址（模板含 {id} 且没有 default_id 时无法预热）
+    pub fn warm_url(&self) -> Option<String> {
+        if !self.warm {
+            return None;
+        }
+        self.resolve(None).ok().map(|(url, _)| url)
+    }
+}
+
+/// 配置中的头像来源（按名称和别名查找）
+#[derive(Debug)]
+pub struct AvatarRegistry {
+    sources: HashMap<String, AvatarSource>,
+    aliases: HashMap<String, String>,
+    default_source: String,
+}
+
+impl AvatarRegistry {
+    /// 校验配置：名称格式、地址模板、别名不重复、fallback 存在且不成环
+    pub fn from_config(config: &AvatarConfig) -> Result<Self> {
+        let invalid = |msg: String| Error::Internal(format!("Invalid avatar config: {}", msg));
+        let mut sources = HashMap::new();
+        let mut aliases = HashMap::new();
+
+        for (name, source) in &config.sources {
+            if !valid_name(name) {
+                return Err(invalid(format!("source name {:?} must be lowercase letters, digits, '-' or '_'", name)));
+            }
+            validate_template(&source.url).map_err(|e| invalid(format!("source {}: {}", name, e)))?;
+            if let Some(id) = source.default_id.as_deref().filter(|id| !valid_id(id)) {
+                return Err(invalid(format!("source {}: invalid default_id {:?}", name, id)));
+            }
+            if (source.email || source.federated) && !source.url.contains(ID_PLACEHOLDER) {
+                return Err(invalid(format!("source {}: email sources need {{id}} in url", name)));
+            }
+            if source.federated && !source.email {
+                return Err(invalid(format!("source {}: federated requires email", name)));
+            }
+            if source.ttl_secs == 0 {
+                return Err(invalid(format!("source {}: ttl_secs must be positive", name)));
+            }
+            for alias in &source.aliases {
+                if !valid_name(alias) || config.sources.contains_key(alias) {
+                    return Err(invalid(format!("source {}: invalid alias {:?}", name, alias)));
+                }
+                if aliases.insert(alias.clone(), name.clone()).is_some() {
+                    return Err(invalid(format!("alias {} is used by more than one source", alias)));
+                }
+            }
+            sources.insert(name.clone(), AvatarSource::new(name, source));
+        }
+
+        if !sources.contains_key(&config.default_source) {
+            return Err(invalid(format!("default_source {} is not a configured source", config.default_source)));
+        }
+        for source in sources.values() {
+            // 沿 fallback 链走，超过来源数仍未结束说明成环
+            let mut next = source.fallback.as_deref();
+            let mut steps = 0;
+            while let Some(name) = next {
+                let target = sources
+                    .get(name)
+                    .ok_or_else(|| invalid(format!("source {}: unknown fallback {}", source.name, name)))?;
+                steps += 1;
+                if steps > sources.len() {
+                    return Err(invalid(format!("source {}: fallback chain loops", source.name)));
+                }
+                next = target.fallback.as_deref();
+            }
+        }
+
+        Ok(Self {
+            sources,
+            aliases,
+            default_source: config.default_source.clone(),
+        })
+    }
+
+    /// 按名称或别名查找来源（不区分大小写），未指定或不存在时使用默认来源
+    pub fn get(&self, name: Option<&str>) -> &AvatarSource {
+        let name = name.map(str::to_ascii_lowercase).unwrap_or_default();
+        let name = self.aliases.get(&name).unwrap_or(&name);
+        self.sources
+            .get(name)
+            .unwrap_or_else(|| &self.sources[&self.default_source])
+    }
+
+    /// 是否为默认来源（有上传的头像时使用上传的头像）
+    pub fn is_default(&self, source: &AvatarSource) -> bool {
+        source.name == self.default_source
+    }
+
+    /// 来源及其 fallback 链（按尝试顺序）
+    pub fn chain<'a>(&'a self, source: &'a AvatarSource) -> Vec<&'a AvatarSource> {
+        let mut chain = vec![source];
+        let mut next = source.fallback.as_deref();
+        while let Some(source) = next.and_then(|name| self.sources.get(name)) {
+            chain.push(source);
+            next = source.fallback.as_deref();
+        }
+        chain
+    }
+
+    /// 需要预热的来源（按名称排序）
+    pub fn warm_sources(&self) -> Vec<&AvatarSource> {
+        let mut sources: Vec<&AvatarSource> = self.sources.values().filter(|s| s.warm_url().is_some()).collect();
+        sources.sort_by(|a, b| a.name.cmp(&b.name));
+        sources
+    }
+}
+
+/// email 参数对应的头像 id
+pub struct AvatarEmail {
+    /// SHA-256(去除首尾空白并转为小写的邮箱)，Gravatar 和 Libravatar 都支持；缓存 key 和原图地址只使用哈希
+    pub hash: String,
+    /// 邮箱域名（Libravatar 联邦查找用）
+    pub domain: String,
+}
+
+impl AvatarEmail {
+    pub fn parse(email: &str) -> Result<Self> {
+        let email = email.trim().to_lowercase();
+        let valid = email.len() <= 254
+            && email
+                .split_once('@')
+                .is_some_and(|(local, domain)| !local.is_empty() && valid_domain(domain));
+        if !valid {
+            return Err(Error::BadRequest("Invalid email address".into()));
+        }
+        let domain = email.rsplit('@').next().unwrap_or_default().to_string();
+        Ok(Self {
+            hash: hex::encode(Sha256::digest(email.as_bytes())),
+            domain,
+        })
+    }
+}
+
+fn valid_domain(domain: &str) -> bool {
+    matches!(url::Host::parse(domain), Ok(url::Host::Domain(_))) && domain.contains('.')
+}
+
+// ==========================================
+// Libravatar 联邦：邮箱域名的 _avatars-sec._tcp SRV 记录指向该域名自建的头像服务器
+// ==========================================
+
+const FEDERATION_SERVICE: &str = "_avatars-sec._tcp";
+
+// 域名 -> 联邦服务器（host, port），没有 SRV 记录或查询失败时为 None，同样缓存，避免每次请求都查询 DNS
+static FEDERATION: Lazy<moka::future::Cache<String, Option<(String, u16)>>> = Lazy::new(|| {
+    moka::future::Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(3600))
+        .build()
+});
+
+static RESOLVER: Lazy<Option<hickory_resolver::TokioResolver>> = Lazy::new(|| {
+    match hickory_resolver::TokioResolver::builder_tokio() {
+        Ok(builder) => Some(builder.build()),
+        Err(e) => {
+            warn!("Failed to load system DNS config, Libravatar federation disabled: {}", e);
+            None
+        }
+    }
+});
+
+/// 按 SRV 规则选择服务器：优先级最小的记录中按权重随机选择（权重全为 0 时取第一个）
+fn pick_server(records: &[(u16, u16, String, u16)]) -> Option<(String, u16)> {
+    let priority = records.iter().map(|r| r.0).min()?;
+    let candidates: Vec<_> = records.iter().filter(|r| r.0 == priority).collect();
+    let weights: Vec<u64> = candidates.iter().map(|r| u64::from(r.1)).collect();
+    let chosen = candidates[rng::weighted_index(&weights).unwrap_or(0)];
+    // SRV 目标须为域名（不接受 IP 和根域 "."）
+    let host = chosen.2.trim_end_matches('.').to_ascii_lowercase();
+    valid_domain(&host).then_some((host, chosen.3))
+}
+
+/// 将来源地址的主机替换为联邦服务器（路径和查询参数不变）
+fn federate(url: &str, host: &str, port: u16) -> Option<String> {
+    let mut url = url::Url::parse(url).ok()?;
+    url.set_host(Some(host)).ok()?;
+    url.set_port((port != 443).then_some(port)).ok()?;
+    Some(url.to_string())
+}
+
+async fn lookup_server(domain: &str) -> Option<(String, u16)> {
+    let resolver = RESOLVER.as_ref()?;
+    let name = format!("{}.{}.", FEDERATION_SERVICE, domain);
+    match resolver.srv_lookup(name).await {
+        Ok(lookup) => {
+            let records: Vec<_> = lookup
+                .iter()
+                .map(|srv| (srv.priority(), srv.weight(), srv.target().to_ascii(), srv.port()))
+                .collect();
+            pick_server(&records)
+        }
+        // 没有 SRV 记录是常态（使用中心服务器），不记录日志
+        Err(e) if e.is_no_records_found() => None,
+        Err(e) => {
+            warn!("Libravatar SRV lookup for {} failed: {}", domain, e);
+            None
+        }
+    }
+}
+
+/// 联邦来源的原图地址：邮箱域名有 SRV 记录时改用其服务器，否则使用来源的中心服务器地址
+pub async fn federated_url(url: &str, email: &AvatarEmail) -> String {
+    let server = FEDERATION
+        .get_with_by_ref(email.domain.as_str(), lookup_server(&email.domain))
+        .await;
+    server
+        .and_then(|(host, port)| federate(url, &host, port))
+        .unwrap_or_else(|| url.to_string())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+/// 地址模板替换 {id} 后须为 http(s) 地址，且不含其他占位符
+fn validate_template(template: &str) -> std::result::Result<(), String> {
+    let sample = template.replace(ID_PLACEHOLDER, "0");
+    if sample.contains('{') || sample.contains('}') {
+        return Err(format!("unsupported placeholder in {}", template));
+    }
+    let url = url::Url::parse(&sample).map_err(|e| format!("invalid url {}: {}", template, e))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(format!("url must be http(s): {}", template));
+    }
+    Ok(())
+}
+
+static REGISTRY: OnceCell<AvatarRegistry> = OnceCell::new();
+
+/// 校验并加载头像来源（启动时调用一次，配置无效时返回错误）
+pub fn init(config: &AvatarConfig) -> Result<()> {
+    let registry = AvatarRegistry::from_config(config)?;
+    let _ = REGISTRY.set(registry);
+    let _ = UPLOAD_DIR.set(PathBuf::from(&config.upload_dir));
+    Ok(())
+}
+
+pub fn registry() -> &'static AvatarRegistry {
+    REGISTRY.get_or_init(|| {
+        AvatarRegistry::from_config(&AvatarConfig::default()).expect("default avatar sources are valid")
+    })
+}
+
+/// 转码结果的缓存 key（/avatar 和预热共用）：来源[:id][:尺寸][:meta][:形状][:self-<哈希前 12 位>]:格式
+pub fn variant_key(
+    source: &AvatarSource,
+    id: Option<&str>,
+    transform: &ImageTransform,
+    uploaded: Option<&SelfAvatar>,
+    fmt_key: &str,
+) -> String {
+    let mut key = source.name.clone();
+    if let Some(id) = id {
+        key = format!("{}:{}", key, id);
+    }
+    if let Some(size) = transform.width {
+        key = format!("{}:{}", key, size);
+    }
+    if transform.keeps_metadata() {
+        key = format!("{}:meta", key);
+    }
+    if let Some(shape) = transform.shape {
+        key = format!("{}:{}", key, shape.name());
+    }
+    if let Some(avatar) = uploaded {
+        key = format!("{}:self-{}", key, &avatar.sha256[..12]);
+    }
+    Namespace::Avatars.derived_key(format_args!("{}:{}", key, fmt_key))
+}
+
+/// 阻塞式：按尺寸和形状处理头像并编码为目标格式
+pub fn process(raw_bytes: Vec<u8>, transform: ImageTransform, img_format: ImageFormat) -> Result<Vec<u8>> {
+    // 动图按 transcode.animated 原样返回或逐帧缩放为 GIF，响应类型按实际内容确定
+    if let Some(source_format) = ImageService::animated_format(&raw_bytes) {
+        return ImageService::process_animated(raw_bytes, source_format, &transform).map(|(out, _)| out);
+    }
+
+    // 默认去除 EXIF / ICC 等元数据（像素按 EXIF 方向旋转）
+    let (img, metadata) = ImageService::decode(&raw_bytes, img_format, transform.keeps_metadata())
+        .map_err(|e| Error::Internal(format!("Failed to decode avatar: {}", e)))?;
+    let img = transform.apply(img);
+
+    ImageService::encode(&img, img_format, None, metadata)
+        .map_err(|e| Error::Internal(format!("Failed to encode {:?}: {}", img_format, e)))
+}
+
+/// 预热 warm = true 的来源在各尺寸和格式下的转码结果（与 /avatar 使用相同的缓存 key），返回 (成功, 失败) 数
+///
+/// 已缓存的变体跳过；默认来源有上传的头像时跳过（上传时已转码全部变体）
+pub async fn warm_variants(image_service: &ImageService, formats: &[ImageFormat]) -> (u32, u32) {
+    let registry = registry();
+    let uploaded = self_avatar().await.is_some();
+    let ctx = RequestContext::background();
+    let (mut warmed, mut failed) = (0, 0);
+
+    for source in registry.warm_sources() {
+        if uploaded && registry.is_default(source) {
+            continue;
+        }
+        let Ok((url, id)) = source.resolve(None) else {
+            continue;
+        };
+        for size in std::iter::once(None).chain(AVATAR_SIZES.iter().copied().map(Some)) {
+            let transform = ImageTransform {
+                width: size,
+                height: size,
+                ..ImageTransform::default()
+            };
+            for &format in formats {
+                let key = variant_key(source, id, &transform, None, ImageService::format_extension(format));
+                if cache::backend().get(&key).await.is_some() {
+                    continue;
+                }
+                // 原图只在第一次下载，之后命中 fetch_avatar 的缓存
+                let result = async {
+                    let (raw_bytes, _) = image_service.fetch_avatar(&ctx, &url).await?;
+                    transcode_limiter::run_background(move || process(raw_bytes, transform, format)).await?
+                }
+                .await;
+                match result {
+                    Ok(out) => {
+                        cache::backend().put(&key, out, Some(source.ttl)).await;
+                        warmed += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to warm up avatar {} ({:?}, {:?}): {}", source.name, size, format, e);
+                        failed += 1;
+                    }
+                }
+            }
+        }
+    }
+    (warmed, failed)
+}
+
+// ==========================================
+// 上传的头像（PUT /avatar/self），替代默认来源的原图地址
+// ==========================================
+
+/// 上传头像预先转码的格式（与 /avatar 按 Accept 协商出的格式对应）
+const VARIANT_FORMATS: &[ImageFormat] = &[ImageFormat::Avif, ImageFormat::WebP, ImageFormat::Png, ImageFormat::Jpeg];
+/// 原尺寸变体的最大边长
+const MAX_FULL_SIZE: u32 = 1024;
+const SELF_DIR: &str = "self";
+const MANIFEST: &str = "manifest.json";
+
+/// 上传的头像
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfAvatar {
+    pub sha256: String,
+    pub width: u32,
+    pub height: u32,
+    /// 原图大小（字节）
+    pub size: u64,
+    pub format: String,
+    /// 预先转码的变体数（尺寸 × 格式）
+    pub variants: usize,
+    pub updated_at: String,
+}
+
+static UPLOAD_DIR: OnceCell<PathBuf> = OnceCell::new();
+// 当前上传的头像，首次使用时从上传目录的 manifest.json 读取
+static SELF_AVATAR: Lazy<RwLock<Option<SelfAvatar>>> = Lazy::new(|| RwLock::new(load_manifest()));
+// 上传和删除串行进行
+static UPLOADS: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn upload_dir() -> &'static PathBuf {
+    UPLOAD_DIR.get_or_init(|| PathBuf::from(AvatarConfig::default().upload_dir))
+}
+
+fn self_dir() -> PathBuf {
+    upload_dir().join(SELF_DIR)
+}
+
+fn load_manifest() -> Option<SelfAvatar> {
+    let path = self_dir().join(MANIFEST);
+    let data = std::fs::read(&path).ok()?;
+    match serde_json::from_slice(&data) {
+        Ok(avatar) => Some(avatar),
+        Err(e) => {
+            warn!("Ignoring invalid avatar manifest {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 变体文件名：<边长或 full>.<扩展名>
+fn variant_name(size: Option<u32>, format: ImageFormat) -> String {
+    let size = size.map_or_else(|| "full".to_string(), |s| s.to_string());
+    format!("{}.{}", size, ImageService::format_extension(format))
+}
+
+/// 当前上传的头像
+pub async fn self_avatar() -> Option<SelfAvatar> {
+    SELF_AVATAR.read().await.clone()
+}
+
+/// 读取上传头像的变体（size 为 None 时为原尺寸）
+pub async fn read_variant(size: Option<u32>, format: ImageFormat) -> Result<Vec<u8>> {
+    let name = variant_name(size, format);
+    tokio::fs::read(self_dir().join(&name))
+        .await
+        .map_err(|e| Error::NotFound(format!("Avatar variant {}: {}", name, e)))
+}
+
+/// 转码结果
+struct EncodedAvatar {
+    width: u32,
+    height: u32,
+    /// (文件名, 数据)
+    files: Vec<(String, Vec<u8>)>,
+}
+
+/// 阻塞式：解码上传的头像，按全部尺寸 × 格式生成变体（不含元数据，像素按 EXIF 方向旋转）
+fn encode_variants(bytes: &[u8], formats: &[ImageFormat]) -> Result<EncodedAvatar> {
+    let (img, _) = ImageService::decode(bytes, ImageFormat::Png, false)
+        .map_err(|e| Error::BadRequest(format!("Failed to decode avatar: {}", e)))?;
+    let (width, height) = (img.width(), img.height());
+
+    let mut files = Vec::new();
+    for size in AVATAR_SIZES.iter().copied().map(Some).chain([None]) {
+        let target = size.unwrap_or(MAX_FULL_SIZE);
+        let resized = if target < width.max(height) {
+            img.resize(target, target, FilterType::Lanczos3)
+        } else {
+            img.clone()
+        };
+        // JPEG 不支持透明通道
+        let opaque = DynamicImage::ImageRgb8(resized.to_rgb8());
+        for &format in formats {
+            let source = if format == ImageFormat::Jpeg { &opaque } else { &resized };
+            let mut out = Vec::new();
+            source
+                .write_to(&mut Cursor::new(&mut out), format)
+                .map_err(|e| Error::Internal(format!("Failed to encode {:?}: {}", format, e)))?;
+            files.push((variant_name(size, format), out));
+        }
+    }
+    Ok(EncodedAvatar { width, height, files })
+}
+
+async fn write_dir(dir: &Path, files: &[(String, Vec<u8>)]) -> std::io::Result<()> {
+    if tokio::fs::try_exists(dir).await? {
+        tokio::fs::remove_dir_all(dir).await?;
+    }
+    tokio::fs::create_dir_all(dir).await?;
+    for (name, data) in files {
+        tokio::fs::write(dir.join(name), data).await?;
+    }
+    Ok(())
+}
+
+/// 保存上传的头像：生成全部变体后整体替换上传目录中的旧头像
+pub async fn upload_self(bytes: Vec<u8>) -> Result<SelfAvatar> {
+    let format = ImageService::detect_format(&bytes)
+        .filter(|f| matches!(f, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP))
+        .ok_or_else(|| Error::BadRequest("Avatar must be a JPEG, PNG or WebP image".into()))?;
+    let sha256 = hex::encode(Sha256::digest(&bytes));
+
+    let _guard = UPLOADS.lock().await;
+    // 管理操作，等待空闲的处理许可而不是返回 503
+    let (encoded, bytes) = transcode_limiter::run_background(move || {
+        encode_variants(&bytes, VARIANT_FORMATS).map(|encoded| (encoded, bytes))
+    })
+    .await??;
+    let EncodedAvatar { width, height, mut files } = encoded;
+
+    let avatar = SelfAvatar {
+        sha256,
+        width,
+        height,
+        size: bytes.len() as u64,
+        format: ImageService::format_extension(format).to_string(),
+        variants: files.len(),
+        updated_at: Utc::now().to_rfc3339(),
+    };
+    let manifest = serde_json::to_vec_pretty(&avatar).map_err(|e| Error::Internal(e.to_string()))?;
+    files.push((format!("original.{}", avatar.format), bytes));
+    files.push((MANIFEST.to_string(), manifest));
+
+    // 先写入临时目录，再替换旧目录，读取方不会看到只写了一半的变体
+    let dir = self_dir();
+    let staging = upload_dir().join(format!("{}.new", SELF_DIR));
+    let retired = upload_dir().join(format!("{}.old", SELF_DIR));
+    let io_error = |e: std::io::Error| Error::Internal(format!("Failed to save avatar: {}", e));
+    write_dir(&staging, &files).await.map_err(io_error)?;
+    let _ = tokio::fs::remove_dir_all(&retired).await;
+    if tokio::fs::try_exists(&dir).await.map_err(io_error)? {
+        tokio::fs::rename(&dir, &retired).await.map_err(io_error)?;
+    }
+    tokio::fs::rename(&staging, &dir).await.map_err(io_error)?;
+    let _ = tokio::fs::remove_dir_all(&retired).await;
+
+    *SELF_AVATAR.write().await = Some(avatar.clone());
+    info!("头像已更新（{}x{}，{} 个变体）", width, height, avatar.variants);
+    Ok(avatar)
+}
+
+/// 删除上传的头像，默认来源恢复使用配置的原图地址
+pub async fn delete_self() -> Result<Option<SelfAvatar>> {
+    let _guard = UPLOADS.lock().await;
+    let Some(avatar) = SELF_AVATAR.write().await.take() else {
+        return Ok(None);
+    };
+    if let Err(e) = tokio::fs::remove_dir_all(self_dir()).await {
+        warn!("Failed to remove uploaded avatar files: {}", e);
+    }
+    info!("上传的头像已删除");
+    Ok(Some(avatar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = AvatarRegistry::from_config(&AvatarConfig::default()).unwrap();
+        assert_eq!(registry.get(None).name, "default");
+        assert_eq!(registry.get(Some("GH")).name, "github");
+        assert_eq!(registry.get(Some("unknown")).name, "default");
+
+        let qq = registry.get(Some("qq"));
+        assert_eq!(qq.resolve(None).unwrap().0, "https://q1.qlogo.cn/g?b=qq&nk=2271225249&s=640");
+        assert_eq!(qq.resolve(Some("10001")).unwrap(), ("https://q1.qlogo.cn/g?b=qq&nk=10001&s=640".to_string(), Some("10001")));
+        assert!(qq.resolve(Some("1&x=2")).is_err());
+        assert_eq!(registry.get(None).resolve(Some("ignored")).unwrap().1, None);
+
+        let chain: Vec<&str> = registry.chain(qq).iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(chain, ["qq", "default"]);
+        assert_eq!(registry.warm_sources().len(), 3);
+
+        // 修改默认配置中的一个来源后应校验失败
+        let rejects = |name: &str, edit: fn(&mut AvatarSourceConfig)| {
+            let mut sources = AvatarConfig::default().sources;
+            edit(sources.get_mut(name).unwrap());
+            AvatarRegistry::from_config(&AvatarConfig { sources, ..Default::default() }).is_err()
+        };
+        assert!(rejects("default", |s| s.fallback = Some("qq".into())));
+        assert!(rejects("qq", |s| s.url = "ftp://example.com/{id}".into()));
+        assert!(rejects("qq", |s| s.url = "https://example.com/{name}".into()));
+        assert!(rejects("qq", |s| s.aliases = vec!["gh".into()]));
+        assert!(rejects("qq", |s| s.federated = true));
+
+        let config = AvatarConfig { default_source: "missing".into(), ..Default::default() };
+        assert!(AvatarRegistry::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_avatar_email() {
+        let email = AvatarEmail::parse("  Test@Example.com ").unwrap();
+        assert_eq!(email.hash, "973dfe463ec85785f5f95af5ba3906eedb2d931c24e69824a89ea65dba4e813b");
+        assert_eq!(email.domain, "example.com");
+        for invalid in ["", "test", "@example.com", "test@", "test@localhost", "test@127.0.0.1", "a@b@c"] {
+            assert!(AvatarEmail::parse(invalid).is_err(), "{}", invalid);
+        }
+
+        let registry = AvatarRegistry::from_config(&AvatarConfig::default()).unwrap();
+        let gravatar = registry.get(Some("gravatar"));
+        let chain: Vec<&str> = registry.chain(gravatar).iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(chain, ["gravatar", "libravatar", "default"]);
+        assert_eq!(
+            gravatar.resolve(Some(&email.hash)).unwrap().0,
+            format!("https://gravatar.com/avatar/{}?s=640&d=404", email.hash)
+        );
+        assert!(gravatar.resolve(None).is_err());
+
+        // 联邦服务器
+        let records = vec![
+            (10, 0, "backup.example.com.".to_string(), 443),
+            (5, 0, "Avatars.Example.com.".to_string(), 8443),
+        ];
+        assert_eq!(pick_server(&records), Some(("avatars.example.com".to_string(), 8443)));
+        assert_eq!(pick_server(&[(0, 0, ".".to_string(), 443)]), None);
+        assert_eq!(pick_server(&[(0, 0, "10.0.0.1".to_string(), 443)]), None);
+        assert_eq!(pick_server(&[]), None);
+        let url = "https://seccdn.libravatar.org/avatar/abc?s=640&d=404";
+        assert_eq!(federate(url, "avatars.example.com", 443).unwrap(), "https://avatars.example.com/avatar/abc?s=640&d=404");
+        assert_eq!(federate(url, "avatars.example.com", 8443).unwrap(), "https://avatars.example.com:8443/avatar/abc?s=640&d=404");
+    }
+
+    #[test]
+    fn test_encode_variants() {
+        let img = image::RgbaImage::from_fn(300, 200, |x, _| image::Rgba([(x % 256) as u8, 0, 0, 128]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let formats = [ImageFormat::Png, ImageFormat::Jpeg];
+        let encoded = encode_variants(&png, &formats).unwrap();
+        assert_eq!((encoded.width, encoded.height), (300, 200));
+        assert_eq!(encoded.files.len(), (AVATAR_SIZES.len() + 1) * formats.len());
+
+        let find = |name: &str| &encoded.files.iter().find(|(n, _)| n == name).unwrap().1;
+        let small = image::load_from_memory(find("64.png")).unwrap();
+        assert_eq!((small.width(), small.height()), (64, 43));
+        // 大于原图的尺寸不放大
+        let large = image::load_from_memory(find("512.jpeg")).unwrap();
+        assert_eq!((large.width(), large.height()), (300, 200));
+        assert!(find("full.png").starts_with(&[0x89, b'P', b'N', b'G']));
+
+        assert!(encode_variants(b"not an image", &formats).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::utils::cache;
+use crate::utils::rng;
+use crate::{Error, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use serde::Serialize;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+/// 基准测试覆盖的图片格式（AVIF 只能编码，解码测试跳过不支持读取的格式）
+pub const BENCH_FORMATS: &[ImageFormat] = &[ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP, ImageFormat::Avif];
+/// 基准测试覆盖的图片尺寸（正方形边长）
+pub const BENCH_SIZES: &[u32] = &[256, 1024];
+/// resize 的目标边长（与头像常用尺寸一致）
+pub const RESIZE_TARGET: u32 = 128;
+
+/// 单项测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: u32,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+/// 生成测试图片（渐变 + 噪点，避免纯色图被编码器过度压缩）
+pub fn sample_image(size: u32) -> DynamicImage {
+    let img = RgbImage::from_fn(size, size, |x, y| {
+        let noise = (x.wrapping_mul(31) ^ y.wrapping_mul(17)) as u8;
+        image::Rgb([
+            (x * 255 / size) as u8,
+            (y * 255 / size) as u8,
+            noise,
+        ])
+    });
+    DynamicImage::ImageRgb8(img)
+}
+
+pub fn encode(img: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    img.write_to(&mut Cursor::new(&mut output), format)
+        .map_err(|e| Error::Internal(format!("Failed to encode image: {}", e)))?;
+    Ok(output)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<DynamicImage> {
+    image::load_from_memory(bytes).map_err(|e| Error::Internal(format!("Failed to decode image: {}", e)))
+}
+
+pub fn resize(img: &DynamicImage) -> DynamicImage {
+    img.resize(RESIZE_TARGET, RESIZE_TARGET, FilterType::Lanczos3)
+}
+
+/// 基准测试使用的临时键前缀（每次运行随机生成，测试结束后按前缀删除，不与线上缓存键冲突）
+pub fn bench_prefix() -> String {
+    format!("bench:{}:", rng::secure_hex(4))
+}
+
+fn summarize(name: String, samples: &[Duration]) -> BenchResult {
+    let to_ms = |d: &Duration| d.as_secs_f64() * 1000.0;
+    let total: Duration = samples.iter().sum();
+    BenchResult {
+        name,
+        iterations: samples.len() as u32,
+        mean_ms: to_ms(&total) / samples.len().max(1) as f64,
+        min_ms: samples.iter().min().map(to_ms).unwrap_or(0.0),
+        max_ms: samples.iter().max().map(to_ms).unwrap_or(0.0),
+    }
+}
+
+fn measure<F: FnMut() -> Result<()>>(name: String, iterations: u32, mut f: F) -> Result<BenchResult> {
+    let mut samples = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        f()?;
+        samples.push(start.elapsed());
+    }
+    Ok(summarize(name, &samples))
+}
+
+/// 运行图片管线基准测试（解码 / 缩放 / 编码，CPU 密集，需在 spawn_blocking 中调用）
+pub fn run_image_benchmarks(iterations: u32) -> Result<Vec<BenchResult>> {
+    let mut results = Vec::new();
+    for &size in BENCH_SIZES {
+        let img = sample_image(size);
+        for &format in BENCH_FORMATS {
+            let ext = format.extensions_str().first().copied().unwrap_or("bin");
+            let encoded = encode(&img, format)?;
+
+            results.push(measure(format!("encode/{}/{}", ext, size), iterations, || {
+                encode(&img, format).map(|_| ())
+            })?);
+            if format.reading_enabled() {
+                results.push(measure(format!("decode/{}/{}", ext, size), iterations, || {
+                    decode(&encoded).map(|_| ())
+                })?);
+            }
+        }
+        results.push(measure(format!("resize/{}", size), iterations, || {
+            resize(&img);
+            Ok(())
+        })?);
+    }
+    Ok(results)
+}
+
+/// 运行缓存读写吞吐测试（每次迭代写入并读取 1000 个 4KB 条目）
+///
+/// 通过线上使用的缓存后端读写（含命名空间路由、统计和转存），键位于临时前缀下，结束后删除
+pub async fn run_cache_benchmarks(iterations: u32) -> Vec<BenchResult> {
+    const ENTRIES: usize = 1000;
+    let backend = cache::backend();
+    let prefix = bench_prefix();
+    let value = vec![0u8; 4096];
+    let keys: Vec<String> = (0..ENTRIES).map(|i| format!("{}{}", prefix, i)).collect();
+
+    let mut put_samples = Vec::with_capacity(iterations as usize);
+    let mut get_samples = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        for key in &keys {
+            backend.put(key, value.clone(), None).await;
+        }
+        put_samples.push(start.elapsed());
+
+        let start = Instant::now();
+        for key in &keys {
+            let _ = backend.get(key).await;
+        }
+        get_samples.push(start.elapsed());
+    }
+    backend.remove_prefix(&prefix).await;
+
+    vec![
+        summarize("cache/put_1000".to_string(), &put_samples),
+        summarize("cache/get_1000".to_string(), &get_samples),
+    ]
+}
@@ -0,0 +1,173 @@
+use crate::services::db_service;
+use crate::services::friend_avatar_service::FriendAvatarService;
+use crate::services::mock_upstream;
+use crate::services::upstream_service::{self, HttpClientService};
+use crate::utils::cache::{self, Namespace};
+use crate::utils::request_context::RequestContext;
+use crate::utils::single_flight::SingleFlight;
+use crate::utils::transcode_limiter;
+use crate::{Error, Result};
+use chrono::Utc;
+use image::imageops::FilterType;
+use log::{debug, warn};
+use mongodb::bson::{self, doc};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const COLLECTION: &str = "blurhashes";
+/// blurhash 的横向 / 纵向分量数
+const COMPONENTS_X: u32 = 4;
+const COMPONENTS_Y: u32 = 3;
+/// 计算前将图片缩小到的最大边长（blurhash 只保留低频信息，缩小后结果几乎不变，计算量小得多）
+const SAMPLE_SIZE: u32 = 64;
+/// 下载的图片大小上限
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 进行中的计算（按 URL），同一图片的并发请求只下载和计算一次
+static COMPUTATIONS: Lazy<SingleFlight<Blurhash>> = Lazy::new(SingleFlight::new);
+
+/// 图片的 blurhash 及原图尺寸
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Blurhash {
+    pub url: String,
+    pub blurhash: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 按图片内容计算 blurhash，结果保存在 MongoDB，并在内存缓存（metadata 命名空间）中缓存
+pub struct BlurhashService {
+    client: Client,
+}
+
+impl BlurhashService {
+    pub fn new(http: &HttpClientService) -> Self {
+        Self {
+            client: http.client().clone(),
+        }
+    }
+
+    /// 获取图片的 blurhash：缓存 -> MongoDB -> 下载并计算
+    ///
+    /// MongoDB 查询受请求时限约束，超过时限后不再开始计算，返回 504
+    ///
+    /// 返回 (结果, 是否无需重新计算)
+    pub async fn get(&self, ctx: &RequestContext, url: &str) -> Result<(Blurhash, bool)> {
+        let cache_key = Namespace::Metadata.key(format_args!("blurhash:{}", url));
+        if let Some(cached) = cache::get_json::<Blurhash>(&cache_key).await {
+            return Ok((cached, true));
+        }
+
+        if let Some(stored) = ctx.within("blurhash lookup", async { Ok(Self::load(url).await) }).await? {
+            cache::put_json(&cache_key, &stored).await;
+            return Ok((stored, true));
+        }
+
+        ctx.check("blurhash computation")?;
+        let computed = COMPUTATIONS.run(url, || self.compute(url)).await?;
+        cache::put_json(&cache_key, &computed).await;
+        Ok((computed, false))
+    }
+
+    async fn load(url: &str) -> Option<Blurhash> {
+        match db_service::find_one(COLLECTION, doc! { "url": url }).await {
+            Ok(doc) => doc.and_then(|d| bson::from_document(d).ok()),
+            Err(e) => {
+                debug!("Blurhash lookup skipped for {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    async fn compute(&self, url: &str) -> Result<Blurhash> {
+        let bytes = self.download(url).await?;
+        let (blurhash, width, height) = transcode_limiter::run(move || Self::encode(&bytes)).await??;
+        let result = Blurhash {
+            url: url.to_string(),
+            blurhash,
+            width,
+            height,
+        };
+
+        // 保存失败（如数据库不可用）不影响本次结果，下次请求重新计算
+        let doc = doc! {
+            "url": url,
+            "blurhash": &result.blurhash,
+            "width": width as i64,
+            "height": height as i64,
+            "created_at": Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = db_service::insert_one(COLLECTION, doc).await {
+            warn!("Failed to store blurhash for {}: {}", url, e);
+        }
+        Ok(result)
+    }
+
+    /// 下载图片（与友链头像相同的 SSRF 防护）
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        if mock_upstream::is_enabled() {
+            return Ok(mock_upstream::image(url).await);
+        }
+        FriendAvatarService::validate_url(url)?;
+
+        let response = upstream_service::send(self.client.get(url).timeout(DOWNLOAD_TIMEOUT))
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to fetch image: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(Error::NotFound(format!("Image not found: HTTP {}", response.status())));
+        }
+        if response.content_length().is_some_and(|len| len > MAX_IMAGE_BYTES as u64) {
+            return Err(Error::BadRequest("Image is too large".into()));
+        }
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to read image bytes: {}", e)))?;
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(Error::BadRequest("Image is too large".into()));
+        }
+        Ok(bytes.to_vec())
+    }
+
+    /// 阻塞式计算（在 spawn_blocking 中调用），返回 (blurhash, 原图宽, 原图高)
+    pub fn encode(bytes: &[u8]) -> Result<(String, u32, u32)> {
+        let img = image::load_from_memory(bytes)
+            .map_err(|e| Error::BadRequest(format!("Failed to decode image: {}", e)))?;
+        let (width, height) = (img.width(), img.height());
+        let sample = img.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle).to_rgba8();
+        let blurhash = blurhash::encode(COMPONENTS_X, COMPONENTS_Y, sample.width(), sample.height(), sample.as_raw())
+            .map_err(|e| Error::Internal(format!("Failed to encode blurhash: {}", e)))?;
+        Ok((blurhash, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encode() {
+        let img = RgbImage::from_fn(320, 180, |x, _| image::Rgb([(x * 255 / 320) as u8, 64, 192]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(img)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        let (hash, width, height) = BlurhashService::encode(&png).unwrap();
+        assert_eq!((width, height), (320, 180));
+        // 1 位分量数 + 1 位最大值 + 4 位 DC + 每个 AC 分量 2 位
+        assert_eq!(hash.len(), 1 + 1 + 4 + 2 * (COMPONENTS_X * COMPONENTS_Y - 1) as usize);
+        let decoded = blurhash::decode(&hash, 32, 18, 1.0).unwrap();
+        assert_eq!(decoded.len(), 32 * 18 * 4);
+        // 左暗右亮
+        assert!(decoded[0] < decoded[(32 - 1) * 4]);
+
+        assert!(BlurhashService::encode(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config::settings::{self, Config, ConfigSources};
+use crate::services::feature_service;
+use crate::services::maintenance_service;
+use crate::utils::cache::{self, DiskLimits};
+use chrono::Utc;
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+static REPORT: OnceCell<BootReport> = OnceCell::new();
+
+/// 启动报告：本次启动使用的配置和各组件状态，供部署后核对
+#[derive(Debug, Clone, Serialize)]
+pub struct BootReport {
+    pub version: &'static str,
+    pub started_at: String,
+    pub pid: u32,
+    pub config: ConfigSources,
+    pub features: FeatureSummary,
+    pub maintenance: bool,
+    /// 挂载的路由数
+    pub routes: usize,
+    pub database: DatabaseStatus,
+    pub cache: CacheSummary,
+    pub memory: MemorySummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureSummary {
+    pub enabled: Vec<&'static str>,
+    pub disabled: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStatus {
+    /// 启动时连接成功（失败时不会启动）
+    pub connected: bool,
+    pub host: String,
+    pub database: String,
+    pub indexes_ensured: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheSummary {
+    pub backend: &'static str,
+    pub disk_dirs: Vec<String>,
+    pub disk_limits: DiskLimits,
+    pub wallpaper_upload_dir: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemorySummary {
+    pub allocator: &'static str,
+    pub threshold_mb: u64,
+    pub check_interval_secs: u64,
+    pub gc_cooldown_secs: u64,
+}
+
+/// 汇总启动报告（在所有组件初始化完成、启动 Rocket 前调用）
+pub fn build(config: &Config, routes: usize, indexes_ensured: bool) -> BootReport {
+    let (enabled, disabled): (Vec<_>, Vec<_>) = feature_service::list().into_iter().partition(|f| f.enabled);
+    BootReport {
+        version: concat!("v", env!("CARGO_PKG_VERSION")),
+        started_at: Utc::now().to_rfc3339(),
+        pid: std::process::id(),
+        config: settings::config_sources(),
+        features: FeatureSummary {
+            enabled: enabled.into_iter().map(|f| f.name).collect(),
+            disabled: disabled.into_iter().map(|f| f.name).collect(),
+        },
+        maintenance: maintenance_service::current().enabled,
+        routes,
+        database: DatabaseStatus {
+            connected: true,
+            host: format!("{}:{}", config.mongo.host, config.mongo.port),
+            database: config.mongo.database.clone(),
+            indexes_ensured,
+        },
+        cache: CacheSummary {
+            backend: cache::backend().name(),
+            disk_dirs: cache::disk_dirs(),
+            disk_limits: cache::disk_limits(),
+            wallpaper_upload_dir: config.wallpapers.upload_dir.clone(),
+        },
+        memory: MemorySummary {
+            allocator: if cfg!(target_os = "windows") { "system" } else { "jemalloc" },
+            threshold_mb: config.memory.threshold_mb,
+            check_interval_secs: config.memory.check_interval_secs,
+            gc_cooldown_secs: config.memory.gc_cooldown_secs,
+        },
+    }
+}
+
+/// 以 JSON 记录启动报告并保存，供 GET /api/boot-report 查询（只保存第一次）
+pub fn record(report: BootReport) {
+    match serde_json::to_string(&report) {
+        Ok(json) => info!("启动报告: {}", json),
+        Err(e) => warn!("Failed to serialize boot report: {}", e),
+    }
+    let _ = REPORT.set(report);
+}
+
+/// 本次启动的报告（启动完成前为 None）
+pub fn get() -> Option<&'static BootReport> {
+    REPORT.get()
+}
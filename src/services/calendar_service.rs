@@ -0,0 +1,254 @@
+pub use crate::config::settings::CalendarEvent;
+use crate::config::settings::CalendarConfig;
+use crate::services::db_service;
+use crate::utils::validation::{Validate, Validator};
+use crate::{Error, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::warn;
+use moka::future::Cache;
+use mongodb::bson::{self, doc};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const EVENTS_COLLECTION: &str = "calendar_events";
+const ICS_CACHE_KEY: &str = "calendar.ics";
+
+// 生成好的日历（数据库中的事件变化时主动失效）
+static ICS_CACHE: Lazy<Cache<String, String>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1)
+        .time_to_live(Duration::from_secs(5 * 60))
+        .build()
+});
+
+enum EventStart {
+    AllDay(NaiveDate),
+    Timed(DateTime<Utc>),
+}
+
+impl Validate for CalendarEvent {
+    fn check(&self, v: &mut Validator) {
+        v.length("summary", self.summary.trim(), 1, 200)
+            .check("start", self.parse_start().is_ok(), "must be YYYY-MM-DD or an RFC 3339 time")
+            .check(
+                "recurrence",
+                rrule(&self.recurrence).is_some(),
+                "must be one of none, daily, weekly, monthly, yearly",
+            );
+        if let Some(description) = &self.description {
+            v.length("description", description, 0, 2000);
+        }
+    }
+}
+
+impl CalendarEvent {
+    /// 校验事件并补全 uid
+    pub fn normalize(mut self) -> Result<Self> {
+        self.validate()?;
+        if self.uid.trim().is_empty() {
+            let digest = Sha256::digest(format!("{}\n{}", self.summary, self.start).as_bytes());
+            self.uid = format!("{}@space-api", hex::encode(&digest[..12]));
+        }
+        Ok(self)
+    }
+
+    fn parse_start(&self) -> Result<EventStart> {
+        if let Ok(date) = NaiveDate::parse_from_str(&self.start, "%Y-%m-%d") {
+            return Ok(EventStart::AllDay(date));
+        }
+        DateTime::parse_from_rfc3339(&self.start)
+            .map(|dt| EventStart::Timed(dt.with_timezone(&Utc)))
+            .map_err(|_| Error::BadRequest(format!("Invalid start: {}", self.start)))
+    }
+}
+
+fn rrule(recurrence: &str) -> Option<Option<&'static str>> {
+    match recurrence {
+        "none" | "" => Some(None),
+        "daily" => Some(Some("FREQ=DAILY")),
+        "weekly" => Some(Some("FREQ=WEEKLY")),
+        "monthly" => Some(Some("FREQ=MONTHLY")),
+        "yearly" => Some(Some("FREQ=YEARLY")),
+        _ => None,
+    }
+}
+
+pub struct CalendarService;
+
+impl CalendarService {
+    /// 获取 iCalendar 内容（配置中的事件 + 数据库中的事件）
+    pub async fn ics(config: &CalendarConfig) -> Result<String> {
+        if let Some(cached) = ICS_CACHE.get(ICS_CACHE_KEY).await {
+            return Ok(cached);
+        }
+
+        let mut events = Vec::new();
+        for event in config.events.iter().cloned().chain(Self::stored_events().await?) {
+            match event.normalize() {
+                Ok(event) => events.push(event),
+                Err(e) => warn!("Skipping invalid calendar event: {}", e),
+            }
+        }
+
+        let ics = render_ics(&config.name, &events, Utc::now());
+        ICS_CACHE.insert(ICS_CACHE_KEY.to_string(), ics.clone()).await;
+        Ok(ics)
+    }
+
+    /// 清除生成好的日历缓存
+    pub fn invalidate_cache() {
+        ICS_CACHE.invalidate_all();
+    }
+
+    /// 数据库中的事件
+    pub async fn stored_events() -> Result<Vec<CalendarEvent>> {
+        let docs = db_service::find_many(EVENTS_COLLECTION, doc! {}).await?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| match bson::from_document::<CalendarEvent>(d) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    warn!("Malformed calendar event document: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// 新增或替换事件（按 uid）
+    pub async fn save_event(event: CalendarEvent) -> Result<CalendarEvent> {
+        let event = event.normalize()?;
+        let document = bson::to_document(&event).map_err(|e| Error::Internal(e.to_string()))?;
+        db_service::delete_one(EVENTS_COLLECTION, doc! { "uid": &event.uid }).await?;
+        db_service::insert_one(EVENTS_COLLECTION, document).await?;
+        ICS_CACHE.invalidate_all();
+        Ok(event)
+    }
+
+    /// 删除事件，返回是否存在
+    pub async fn delete_event(uid: &str) -> Result<bool> {
+        let deleted = db_service::delete_one(EVENTS_COLLECTION, doc! { "uid": uid }).await?;
+        ICS_CACHE.invalidate_all();
+        Ok(deleted > 0)
+    }
+}
+
+/// 生成 iCalendar（RFC 5545）文本
+pub fn render_ics(name: &str, events: &[CalendarEvent], now: DateTime<Utc>) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//TNXG//space-api//CN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    for event in events {
+        let Ok(start) = event.parse_start() else {
+            continue;
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape_text(&event.uid)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        match start {
+            EventStart::AllDay(date) => {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+                if let Some(next) = date.succ_opt() {
+                    lines.push(format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")));
+                }
+            }
+            EventStart::Timed(start) => {
+                let end = start + chrono::Duration::minutes(event.duration_minutes as i64);
+                lines.push(format!("DTSTART:{}", start.format("%Y%m%dT%H%M%SZ")));
+                lines.push(format!("DTEND:{}", end.format("%Y%m%dT%H%M%SZ")));
+            }
+        }
+        if let Some(Some(rule)) = rrule(&event.recurrence) {
+            lines.push(format!("RRULE:{}", rule));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+        if let Some(description) = event.description.as_deref().filter(|d| !d.is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold_line(&line, &mut out);
+    }
+    out
+}
+
+/// 转义 TEXT 类型的值
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// 按 75 字节折行（续行以空格开头），不拆分多字节字符
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(summary: &str, start: &str, recurrence: &str) -> CalendarEvent {
+        CalendarEvent {
+            uid: String::new(),
+            summary: summary.to_string(),
+            description: None,
+            start: start.to_string(),
+            duration_minutes: 90,
+            recurrence: recurrence.to_string(),
+        }
+        .normalize()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_ics() {
+        let events = vec![
+            event("Site anniversary, 2020", "2020-05-01", "yearly"),
+            event("Maintenance", "2026-01-01T02:00:00+08:00", "none"),
+        ];
+        let ics = render_ics("Space", &events, Utc::now());
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20200501\r\n"));
+        assert!(ics.contains("RRULE:FREQ=YEARLY\r\n"));
+        assert!(ics.contains("SUMMARY:Site anniversary\\, 2020\r\n"));
+        assert!(ics.contains("DTSTART:20251231T180000Z\r\nDTEND:20251231T193000Z\r\n"));
+        assert!(ics.lines().all(|l| l.trim_end_matches('\r').len() <= 75));
+    }
+
+    #[test]
+    fn test_invalid_event() {
+        let mut e = event("x", "2020-05-01", "none");
+        e.recurrence = "hourly".to_string();
+        assert!(e.normalize().is_err());
+        assert!(CalendarEvent { start: "tomorrow".into(), ..event("x", "2020-05-01", "none") }
+            .normalize()
+            .is_err());
+    }
+}
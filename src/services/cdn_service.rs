@@ -0,0 +1,290 @@
+use crate::config::settings::CdnConfig;
+use crate::services::upstream_service;
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4";
+const EDGEONE_HOST: &str = "teo.tencentcloudapi.com";
+const EDGEONE_VERSION: &str = "2022-09-01";
+/// Cloudflare 单次刷新最多 30 个 URL
+const CLOUDFLARE_BATCH: usize = 30;
+/// 刷新请求的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// 单次请求允许刷新的 URL 数量上限
+pub const MAX_PURGE_URLS: usize = 500;
+
+static CDN: OnceCell<CdnService> = OnceCell::new();
+
+/// 初始化 CDN 缓存刷新（启动时调用一次）
+pub fn init(config: &CdnConfig) {
+    let service = CdnService::new(config.clone());
+    if service.enabled() {
+        info!("已启用 CDN 缓存刷新（{}）", service.config.provider);
+    }
+    let _ = CDN.set(service);
+}
+
+/// 全局 CDN 服务，未初始化时返回 None
+pub fn get() -> Option<&'static CdnService> {
+    CDN.get().filter(|s| s.enabled())
+}
+
+/// 刷新范围
+#[derive(Debug, Clone)]
+pub enum PurgeTarget {
+    /// 站内路径或完整 URL
+    Urls(Vec<String>),
+    /// 整个站点
+    Everything,
+}
+
+/// 刷新结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeOutcome {
+    pub provider: String,
+    /// 提交刷新的 URL（刷新全站时为空）
+    pub urls: Vec<String>,
+    pub everything: bool,
+    /// 服务商返回的任务 ID（EdgeOne）
+    pub job_id: Option<String>,
+}
+
+pub struct CdnService {
+    config: CdnConfig,
+    client: reqwest::Client,
+}
+
+impl CdnService {
+    fn new(config: CdnConfig) -> Self {
+        Self {
+            config,
+            client: upstream_service::client(),
+        }
+    }
+
+    /// 是否配置了可用的服务商
+    pub fn enabled(&self) -> bool {
+        match self.config.provider.as_str() {
+            "cloudflare" => !self.config.zone_id.is_empty() && self.config.api_token.is_some(),
+            "edgeone" => {
+                !self.config.zone_id.is_empty() && self.config.secret_id.is_some() && self.config.secret_key.is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// 刷新边缘节点缓存
+    pub async fn purge(&self, target: PurgeTarget) -> Result<PurgeOutcome> {
+        if !self.enabled() {
+            return Err(Error::BadRequest("CDN purge is not configured".into()));
+        }
+        let (urls, everything) = match target {
+            PurgeTarget::Everything => (Vec::new(), true),
+            PurgeTarget::Urls(urls) => {
+                let urls = urls
+                    .iter()
+                    .map(|u| resolve_url(&self.config.public_base_url, u))
+                    .collect::<Result<Vec<_>>>()?;
+                if urls.is_empty() {
+                    return Err(Error::BadRequest("No URLs to purge".into()));
+                }
+                (urls, false)
+            }
+        };
+
+        let job_id = match self.config.provider.as_str() {
+            "cloudflare" => {
+                self.purge_cloudflare(&urls, everything).await?;
+                None
+            }
+            _ => self.purge_edgeone(&urls, everything).await?,
+        };
+        info!(
+            "CDN 缓存已刷新（{}）：{}",
+            self.config.provider,
+            if everything { "全站".to_string() } else { urls.join(", ") }
+        );
+        Ok(PurgeOutcome {
+            provider: self.config.provider.clone(),
+            urls,
+            everything,
+            job_id,
+        })
+    }
+
+    async fn purge_cloudflare(&self, urls: &[String], everything: bool) -> Result<()> {
+        let endpoint = format!("{}/zones/{}/purge_cache", CLOUDFLARE_API, self.config.zone_id);
+        let token = self.config.api_token.as_deref().unwrap_or_default();
+        let bodies: Vec<Value> = if everything {
+            vec![json!({ "purge_everything": true })]
+        } else {
+            urls.chunks(CLOUDFLARE_BATCH).map(|chunk| json!({ "files": chunk })).collect()
+        };
+
+        for body in bodies {
+            let request = self
+                .client
+                .post(&endpoint)
+                .timeout(REQUEST_TIMEOUT)
+                .bearer_auth(token)
+                .json(&body);
+            let response = upstream_service::send(request)
+                .await
+                .map_err(|e| Error::Internal(format!("Cloudflare purge request failed: {}", e)))?;
+            let status = response.status();
+            let result: Value = response.json().await.unwrap_or_default();
+            if !status.is_success() || result["success"] != json!(true) {
+                return Err(Error::Internal(format!(
+                    "Cloudflare purge failed ({}): {}",
+                    status, result["errors"]
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn purge_edgeone(&self, urls: &[String], everything: bool) -> Result<Option<String>> {
+        let body = if everything {
+            json!({ "ZoneId": self.config.zone_id, "Type": "purge_all" })
+        } else {
+            json!({ "ZoneId": self.config.zone_id, "Type": "purge_url", "Targets": urls })
+        }
+        .to_string();
+
+        let secret_id = self.config.secret_id.as_deref().unwrap_or_default();
+        let secret_key = self.config.secret_key.as_deref().unwrap_or_default();
+        let now = Utc::now();
+        let authorization = tc3_authorization(secret_id, secret_key, &body, now);
+
+        let request = self
+            .client
+            .post(format!("https://{}", EDGEONE_HOST))
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .header("X-TC-Action", "CreatePurgeTask")
+            .header("X-TC-Timestamp", now.timestamp().to_string())
+            .header("X-TC-Version", EDGEONE_VERSION)
+            .body(body);
+        let response = upstream_service::send(request)
+            .await
+            .map_err(|e| Error::Internal(format!("EdgeOne purge request failed: {}", e)))?;
+        let result: Value = response
+            .json()
+            .await
+            .map_err(|e| Error::Internal(format!("Invalid EdgeOne response: {}", e)))?;
+        let result = &result["Response"];
+        if let Some(error) = result.get("Error") {
+            return Err(Error::Internal(format!(
+                "EdgeOne purge failed: {} {}",
+                error["Code"].as_str().unwrap_or_default(),
+                error["Message"].as_str().unwrap_or_default()
+            )));
+        }
+        Ok(result["JobId"].as_str().map(str::to_string))
+    }
+}
+
+/// 在后台刷新指定路径（上传、强制刷新等流程调用），未配置 CDN 时不做任何事
+pub fn purge_detached(paths: Vec<String>) {
+    let Some(service) = get() else {
+        return;
+    };
+    if paths.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = service.purge(PurgeTarget::Urls(paths)).await {
+            warn!("CDN purge failed: {}", e);
+        }
+    });
+}
+
+/// 启动时刷新配置的路径（sw.js 等随部署变化的文件）
+pub fn purge_on_startup() {
+    if let Some(service) = get() {
+        purge_detached(service.config.purge_on_startup.clone());
+    }
+}
+
+/// 站内路径按 public_base_url 拼接为完整 URL，完整 URL 原样使用
+fn resolve_url(base: &str, input: &str) -> Result<String> {
+    let input = input.trim();
+    if input.starts_with("http://") || input.starts_with("https://") {
+        return Ok(input.to_string());
+    }
+    if !input.starts_with('/') {
+        return Err(Error::BadRequest(format!("Invalid purge target: {}", input)));
+    }
+    if base.is_empty() {
+        return Err(Error::BadRequest("cdn.public_base_url is required to purge paths".into()));
+    }
+    Ok(format!("{}{}", base.trim_end_matches('/'), input))
+}
+
+// 腾讯云 API 3.0 签名（TC3-HMAC-SHA256）
+fn tc3_authorization(secret_id: &str, secret_key: &str, body: &str, now: DateTime<Utc>) -> String {
+    let date = now.format("%Y-%m-%d").to_string();
+    let service = EDGEONE_HOST.split('.').next().unwrap_or_default();
+    let canonical_request = format!(
+        "POST\n/\n\ncontent-type:application/json; charset=utf-8\nhost:{}\n\ncontent-type;host\n{}",
+        EDGEONE_HOST,
+        hex::encode(Sha256::digest(body.as_bytes()))
+    );
+    let scope = format!("{}/{}/tc3_request", date, service);
+    let string_to_sign = format!(
+        "TC3-HMAC-SHA256\n{}\n{}\n{}",
+        now.timestamp(),
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret_date = hmac(format!("TC3{}", secret_key).as_bytes(), &date);
+    let secret_service = hmac(&secret_date, service);
+    let secret_signing = hmac(&secret_service, "tc3_request");
+    let signature = hex::encode(hmac(&secret_signing, &string_to_sign));
+    format!(
+        "TC3-HMAC-SHA256 Credential={}/{}, SignedHeaders=content-type;host, Signature={}",
+        secret_id, scope, signature
+    )
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_url() {
+        assert_eq!(resolve_url("https://api.example.com/", "/sw.js").unwrap(), "https://api.example.com/sw.js");
+        assert_eq!(resolve_url("", "https://cdn.example.com/a").unwrap(), "https://cdn.example.com/a");
+        assert!(resolve_url("", "/sw.js").is_err());
+        assert!(resolve_url("https://api.example.com", "sw.js").is_err());
+    }
+
+    #[test]
+    fn test_tc3_authorization() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let auth = tc3_authorization("AKID", "secret", r#"{"ZoneId":"zone-1"}"#, now);
+        assert!(auth.starts_with("TC3-HMAC-SHA256 Credential=AKID/2023-11-14/teo/tc3_request, "));
+        assert!(auth.contains("SignedHeaders=content-type;host, Signature="));
+        let signature = auth.rsplit('=').next().unwrap();
+        assert_eq!(signature.len(), 64);
+        assert_eq!(auth, tc3_authorization("AKID", "secret", r#"{"ZoneId":"zone-1"}"#, now));
+        assert_ne!(auth, tc3_authorization("AKID", "other", r#"{"ZoneId":"zone-1"}"#, now));
+    }
+}
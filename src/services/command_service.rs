@@ -0,0 +1,341 @@
+use crate::services::audit_service::AuditService;
+use crate::services::calendar_service::CalendarService;
+use crate::services::cdn_service::{self, PurgeTarget};
+use crate::services::event_bus::Event;
+use crate::services::link_service::LinkService;
+use crate::services::memory_service::MemoryManager;
+use crate::services::outbox_service;
+use crate::services::stats_service::StatsService;
+use crate::utils::cache;
+use crate::{Error, Result};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// 命令参数说明
+#[derive(Debug, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+}
+
+/// 命令说明（注册表条目）
+#[derive(Debug, Serialize)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamSpec],
+}
+
+/// 可清除的缓存命名空间
+pub const CACHE_NAMESPACES: &[&str] = &["memory", "disk", "calendar", "stats", "cdn"];
+
+/// 可手动触发的后台任务
+pub const JOBS: &[&str] = &["cache_cleanup"];
+
+/// 命令注册表：新增命令时在这里声明参数，并在 run 中实现
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "cache.purge",
+        description: "清除指定命名空间的缓存（memory / disk / calendar / stats / cdn）",
+        params: &[ParamSpec {
+            name: "namespace",
+            description: "缓存命名空间",
+            required: true,
+        }],
+    },
+    CommandSpec {
+        name: "cache.bump_epoch",
+        description: "递增缓存纪元，使所有派生缓存（转码、缩放、渲染结果）失效，原始下载保留",
+        params: &[],
+    },
+    CommandSpec {
+        name: "memory.gc",
+        description: "立即执行一次全局内存释放",
+        params: &[],
+    },
+    CommandSpec {
+        name: "job.run",
+        description: "立即执行一次后台任务（cache_cleanup）",
+        params: &[ParamSpec {
+            name: "job",
+            description: "任务名称",
+            required: true,
+        }],
+    },
+    CommandSpec {
+        name: "link.approve",
+        description: "审核通过友链",
+        params: &[ParamSpec {
+            name: "id",
+            description: "友链 ID",
+            required: true,
+        }],
+    },
+    CommandSpec {
+        name: "link.reject",
+        description: "拒绝友链",
+        params: &[ParamSpec {
+            name: "id",
+            description: "友链 ID",
+            required: true,
+        }],
+    },
+];
+
+/// 命令执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutcome {
+    pub command: String,
+    pub output: Value,
+    /// 是否为相同幂等键的重放结果（命令未再次执行）
+    pub replayed: bool,
+}
+
+#[derive(Clone)]
+struct StoredOutcome {
+    /// 命令与参数的摘要，用于拒绝以相同幂等键提交不同命令
+    fingerprint: String,
+    command: String,
+    output: Value,
+}
+
+// 幂等键对应的执行结果（失败的命令不缓存，可以使用相同的键重试）
+static IDEMPOTENCY_CACHE: Lazy<Cache<String, StoredOutcome>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+pub struct CommandService;
+
+impl CommandService {
+    /// 校验并执行命令，`idempotency_key` 相同的请求只执行一次
+    pub async fn execute(
+        name: &str,
+        args: Value,
+        idempotency_key: Option<&str>,
+        actor: &str,
+        memory_manager: &MemoryManager,
+    ) -> Result<CommandOutcome> {
+        let args = validate(name, args)?;
+        let Some(key) = idempotency_key.map(str::trim).filter(|k| !k.is_empty()) else {
+            let output = Self::run_audited(name, &args, actor, memory_manager).await?;
+            return Ok(CommandOutcome {
+                command: name.to_string(),
+                output,
+                replayed: false,
+            });
+        };
+        if key.len() > 128 {
+            return Err(Error::BadRequest("idempotency_key must be at most 128 characters".into()));
+        }
+
+        let fingerprint = fingerprint(name, &args);
+        let executed = AtomicBool::new(false);
+        // 并发的相同键请求会等待同一次执行
+        let stored = IDEMPOTENCY_CACHE
+            .try_get_with(key.to_string(), async {
+                executed.store(true, Ordering::Relaxed);
+                let output = Self::run_audited(name, &args, actor, memory_manager).await?;
+                Ok::<_, Error>(StoredOutcome {
+                    fingerprint: fingerprint.clone(),
+                    command: name.to_string(),
+                    output,
+                })
+            })
+            .await
+            .map_err(|e| clone_error(&e))?;
+
+        if stored.fingerprint != fingerprint {
+            return Err(Error::Conflict(format!(
+                "idempotency_key was already used for a different {} command",
+                stored.command
+            )));
+        }
+        Ok(CommandOutcome {
+            command: stored.command,
+            output: stored.output,
+            replayed: !executed.load(Ordering::Relaxed),
+        })
+    }
+
+    async fn run_audited(name: &str, args: &Map<String, Value>, actor: &str, memory_manager: &MemoryManager) -> Result<Value> {
+        let result = run(name, args, memory_manager).await;
+        let target = args
+            .values()
+            .next()
+            .and_then(Value::as_str)
+            .unwrap_or(name)
+            .to_string();
+        let detail = match &result {
+            Ok(output) => json!({ "args": args, "ok": true, "output": output }),
+            Err(e) => json!({ "args": args, "ok": false, "error": e.to_string() }),
+        };
+        AuditService::record(&format!("command.{}", name), actor, &target, detail).await;
+        result
+    }
+}
+
+/// 按注册表校验命令名和参数
+fn validate(name: &str, args: Value) -> Result<Map<String, Value>> {
+    let spec = COMMANDS
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| Error::BadRequest(format!("Unknown command: {}", name)))?;
+    let args = match args {
+        Value::Null => Map::new(),
+        Value::Object(map) => map,
+        _ => return Err(Error::BadRequest("args must be an object".into())),
+    };
+
+    if let Some(unknown) = args.keys().find(|k| !spec.params.iter().any(|p| p.name == k.as_str())) {
+        return Err(Error::BadRequest(format!("Unknown argument for {}: {}", name, unknown)));
+    }
+    for param in spec.params.iter().filter(|p| p.required) {
+        if args.get(param.name).and_then(Value::as_str).is_none_or(str::is_empty) {
+            return Err(Error::BadRequest(format!("{} requires argument {}", name, param.name)));
+        }
+    }
+    Ok(args)
+}
+
+fn arg<'a>(args: &'a Map<String, Value>, name: &str) -> &'a str {
+    args.get(name).and_then(Value::as_str).unwrap_or_default()
+}
+
+async fn run(name: &str, args: &Map<String, Value>, memory_manager: &MemoryManager) -> Result<Value> {
+    match name {
+        "cache.purge" => match arg(args, "namespace") {
+            "memory" => {
+                let backend = cache::backend();
+                let entries = backend.clear().await;
+                Ok(json!({ "entries": entries, "backend": backend.name() }))
+            }
+            "disk" => {
+                let files = tokio::task::spawn_blocking(cache::clear_disk)
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                Ok(json!({ "files": files }))
+            }
+            "calendar" => {
+                CalendarService::invalidate_cache();
+                Ok(json!({}))
+            }
+            "stats" => {
+                StatsService::invalidate_cache();
+                Ok(json!({}))
+            }
+            "cdn" => {
+                let cdn = cdn_service::get().ok_or_else(|| Error::BadRequest("CDN purge is not configured".into()))?;
+                let outcome = cdn.purge(PurgeTarget::Everything).await?;
+                serde_json::to_value(outcome).map_err(|e| Error::Internal(e.to_string()))
+            }
+            other => Err(Error::BadRequest(format!(
+                "Unknown cache namespace: {} (expected one of {})",
+                other,
+                CACHE_NAMESPACES.join(", ")
+            ))),
+        },
+        "cache.bump_epoch" => {
+            let epoch = tokio::task::spawn_blocking(cache::bump_epoch)
+                .await
+                .map_err(|e| Error::Internal(e.to_string()))?
+                .map_err(|e| Error::Internal(format!("Failed to persist cache epoch: {}", e)))?;
+            Ok(json!({ "version": cache::SCHEMA_VERSION, "epoch": epoch }))
+        }
+        "memory.gc" => {
+            let result = memory_manager
+                .trigger_global_release()
+                .await
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            serde_json::to_value(result).map_err(|e| Error::Internal(e.to_string()))
+        }
+        "job.run" => match arg(args, "job") {
+            "cache_cleanup" => {
+                tokio::task::spawn_blocking(cache::cleanup_expired_cache)
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                Ok(json!({}))
+            }
+            other => Err(Error::BadRequest(format!(
+                "Unknown job: {} (expected one of {})",
+                other,
+                JOBS.join(", ")
+            ))),
+        },
+        "link.approve" => {
+            let id = arg(args, "id");
+            let changed = LinkService::approve(id).await?;
+            if changed {
+                // 审核结果已写入，通知写入失败时 emit 已记录日志
+                let _ = outbox_service::emit(Event::LinkApproved { link_id: id.to_string() }).await;
+            }
+            Ok(json!({ "changed": changed }))
+        }
+        "link.reject" => {
+            let id = arg(args, "id");
+            let changed = LinkService::reject(id).await?;
+            if changed {
+                let _ = outbox_service::emit(Event::LinkRejected { link_id: id.to_string() }).await;
+            }
+            Ok(json!({ "changed": changed }))
+        }
+        _ => Err(Error::BadRequest(format!("Unknown command: {}", name))),
+    }
+}
+
+fn fingerprint(name: &str, args: &Map<String, Value>) -> String {
+    // 按键排序后序列化，与参数顺序无关
+    let sorted: BTreeMap<&String, &Value> = args.iter().collect();
+    let canonical = format!("{}\n{}", name, serde_json::to_string(&sorted).unwrap_or_default());
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+// moka 以 Arc 共享初始化错误，这里按原类型复制一份返回给调用方
+fn clone_error(e: &Error) -> Error {
+    match e {
+        Error::Database(m) => Error::Database(m.clone()),
+        Error::NotFound(m) => Error::NotFound(m.clone()),
+        Error::BadRequest(m) => Error::BadRequest(m.clone()),
+        Error::Unauthorized(m) => Error::Unauthorized(m.clone()),
+        Error::Forbidden(m) => Error::Forbidden(m.clone()),
+        Error::Conflict(m) => Error::Conflict(m.clone()),
+        Error::Gone(m) => Error::Gone(m.clone()),
+        Error::Internal(m) => Error::Internal(m.clone()),
+        Error::Unavailable(m, retry_after) => Error::Unavailable(m.clone(), *retry_after),
+        Error::Timeout(m) => Error::Timeout(m.clone()),
+        Error::Validation(errors) => Error::Validation(errors.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("cache.purge", json!({ "namespace": "memory" })).is_ok());
+        assert!(validate("memory.gc", Value::Null).is_ok());
+        assert!(validate("cache.purge", json!({})).is_err());
+        assert!(validate("cache.purge", json!({ "namespace": "memory", "x": 1 })).is_err());
+        assert!(validate("link.delete", json!({ "id": "1" })).is_err());
+        assert!(validate("memory.gc", json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = validate("link.approve", json!({ "id": "abc" })).unwrap();
+        let b = validate("link.approve", json!({ "id": "abd" })).unwrap();
+        assert_eq!(fingerprint("link.approve", &a), fingerprint("link.approve", &a.clone()));
+        assert_ne!(fingerprint("link.approve", &a), fingerprint("link.approve", &b));
+        assert_ne!(fingerprint("link.approve", &a), fingerprint("cache.purge", &a));
+    }
+}
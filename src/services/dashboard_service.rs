@@ -0,0 +1,126 @@
+use crate::services::db_service;
+use crate::utils::validation::Validator;
+use crate::Result;
+use chrono::Utc;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+
+const PREFERENCES_COLLECTION: &str = "dashboard_preferences";
+
+/// 仪表盘可选的组件
+pub const DASHBOARD_WIDGETS: &[&str] = &["connection", "client", "monitor", "logs"];
+
+/// 默认显示的组件（日志面板需要管理员令牌，默认不显示）
+const DEFAULT_WIDGETS: &[&str] = &["connection", "client", "monitor"];
+
+/// 刷新间隔范围（秒）
+const MIN_REFRESH_INTERVAL: u64 = 1;
+const MAX_REFRESH_INTERVAL: u64 = 300;
+
+/// 仪表盘个人设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardPreferences {
+    /// 主题：system / light / dark
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// 显示的组件
+    #[serde(default = "default_widgets")]
+    pub widgets: Vec<String>,
+    /// 实时数据刷新间隔（秒）
+    #[serde(default = "default_refresh_interval")]
+    pub refresh_interval_secs: u64,
+}
+
+impl Default for DashboardPreferences {
+    fn default() -> Self {
+        Self {
+            theme: default_theme(),
+            widgets: default_widgets(),
+            refresh_interval_secs: default_refresh_interval(),
+        }
+    }
+}
+
+fn default_theme() -> String {
+    "system".to_string()
+}
+
+fn default_widgets() -> Vec<String> {
+    DEFAULT_WIDGETS.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_refresh_interval() -> u64 {
+    5
+}
+
+impl DashboardPreferences {
+    /// 校验设置，组件列表会去重
+    pub fn validate(mut self) -> Result<Self> {
+        let mut v = Validator::new();
+        v.one_of("theme", &self.theme, &["system", "light", "dark"])
+            .count("widgets", &self.widgets, DASHBOARD_WIDGETS.len() * 2)
+            .range(
+                "refresh_interval_secs",
+                self.refresh_interval_secs,
+                MIN_REFRESH_INTERVAL,
+                MAX_REFRESH_INTERVAL,
+            );
+        for (i, w) in self.widgets.iter().enumerate() {
+            v.one_of(&format!("widgets[{}]", i), w, DASHBOARD_WIDGETS);
+        }
+        v.finish()?;
+        let mut seen = Vec::new();
+        self.widgets.retain(|w| {
+            if seen.contains(w) {
+                false
+            } else {
+                seen.push(w.clone());
+                true
+            }
+        });
+        Ok(self)
+    }
+}
+
+/// 仪表盘设置服务（按管理员用户存储在 MongoDB）
+pub struct DashboardService;
+
+impl DashboardService {
+    /// 读取管理员的仪表盘设置，未保存过时返回默认值
+    pub async fn get_preferences(admin_id: &str) -> Result<DashboardPreferences> {
+        let doc = db_service::find_one(PREFERENCES_COLLECTION, doc! { "admin_id": admin_id }).await?;
+        Ok(match doc {
+            Some(d) => mongodb::bson::from_document(d).unwrap_or_default(),
+            None => DashboardPreferences::default(),
+        })
+    }
+
+    /// 保存管理员的仪表盘设置
+    pub async fn save_preferences(
+        admin_id: &str,
+        prefs: DashboardPreferences,
+    ) -> Result<DashboardPreferences> {
+        let prefs = prefs.validate()?;
+        let widgets = prefs.widgets.clone();
+        let fields = doc! {
+            "theme": &prefs.theme,
+            "widgets": widgets,
+            "refresh_interval_secs": prefs.refresh_interval_secs as i64,
+            "updated_at": Utc::now().to_rfc3339(),
+        };
+
+        let matched = db_service::update_one(
+            PREFERENCES_COLLECTION,
+            doc! { "admin_id": admin_id },
+            doc! { "$set": fields.clone() },
+        )
+        .await?;
+        if matched == 0 {
+            let mut doc = fields;
+            doc.insert("admin_id", admin_id);
+            db_service::insert_one(PREFERENCES_COLLECTION, doc).await?;
+        }
+
+        Ok(prefs)
+    }
+}
@@ -1,11 +1,13 @@
 use crate::config::settings::MongoConfig;
+use crate::utils::crypto::{self, FieldCipher};
 use crate::{Error, Result};
 use chrono::Utc;
-use log::info;
+use log::{error, info, warn};
 use mongodb::{
     bson::{doc, Bson, Document},
-    options::{ClientOptions, ServerApi, ServerApiVersion},
-    Client, Database,
+    error::{ErrorKind, WriteFailure},
+    options::{ClientOptions, IndexOptions, ReturnDocument, ServerApi, ServerApiVersion},
+    Client, Database, IndexModel,
 };
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
@@ -13,6 +15,13 @@ use tokio::sync::Mutex;
 
 static DB_INSTANCE: OnceCell<Arc<Mutex<Database>>> = OnceCell::new();
 
+/// MongoDB 重复键错误码
+const DUPLICATE_KEY: i32 = 11000;
+/// 文档未通过集合校验（$jsonSchema）
+const DOCUMENT_VALIDATION_FAILURE: i32 = 121;
+/// 并发写入冲突
+const WRITE_CONFLICT: i32 = 112;
+
 pub async fn initialize_db(config: &MongoConfig) -> Result<Client> {
     if DB_INSTANCE.get().is_some() {
         return Err(Error::Database("Database already initialized".to_string()));
@@ -67,6 +76,311 @@ pub async fn initialize_db(config: &MongoConfig) -> Result<Client> {
     Ok(client)
 }
 
+/// 启动时创建所需索引（已存在时 MongoDB 会直接跳过）
+pub async fn ensure_indexes() -> Result<()> {
+    // (集合, 索引名, 键, 是否唯一)
+    let indexes = [
+        // 全文搜索：友链名称、描述、标签
+        (
+            "links",
+            "links_text",
+            doc! { "name": "text", "description": "text", "tags": "text" },
+            false,
+        ),
+        // 同一地址只保存一条友链（并发提交时由数据库去重）
+        ("links", "links_url", doc! { "url": 1 }, true),
+        ("link_clicks", "link_clicks_link_time", doc! { "link_id": 1, "timestamp": 1 }, false),
+        ("calendar_events", "calendar_events_uid", doc! { "uid": 1 }, false),
+        ("sessions", "sessions_token_hash", doc! { "token_hash": 1 }, false),
+        ("outbox", "outbox_state_next", doc! { "state": 1, "next_attempt_at": 1 }, false),
+        ("blurhashes", "blurhashes_url", doc! { "url": 1 }, false),
+        ("wallpapers", "wallpapers_id", doc! { "wallpaper_id": 1 }, false),
+        ("wallpaper_serves", "wallpaper_serves_id", doc! { "wallpaper_id": 1 }, false),
+        ("features", "features_name", doc! { "name": 1 }, false),
+        ("github_issues", "github_issues_key", doc! { "issue": 1 }, true),
+    ];
+
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+    // 单个索引失败（如已有重复数据无法建立唯一索引）不影响其他索引
+    let mut failed = Vec::new();
+    for (collection_name, name, keys, unique) in indexes {
+        let model = IndexModel::builder()
+            .keys(keys)
+            .options(IndexOptions::builder().name(name.to_string()).unique(unique).build())
+            .build();
+        if let Err(e) = db_lock.collection::<Document>(collection_name).create_index(model).await {
+            failed.push(format!("{}: {}", name, e));
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Database(failed.join("; ")))
+    }
+}
+
+/// 写入错误：违反唯一索引或写冲突时返回 Conflict，未通过集合校验时返回 BadRequest，其他错误返回 Database
+fn write_error(e: mongodb::error::Error) -> Error {
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(w)) if w.code == DUPLICATE_KEY => {
+            Error::Conflict("Duplicate key".to_string())
+        }
+        ErrorKind::Write(WriteFailure::WriteError(w)) if w.code == DOCUMENT_VALIDATION_FAILURE => {
+            Error::BadRequest(format!("Document failed validation: {}", w.message))
+        }
+        ErrorKind::Command(c) if c.code == WRITE_CONFLICT => Error::Conflict("Write conflict".to_string()),
+        _ => Error::Database(e.to_string()),
+    }
+}
+
+/// 字段类型（软校验：只检查声明过的字段，其余字段不限制）
+#[derive(Debug, Clone, Copy)]
+enum FieldKind {
+    String,
+    /// RFC 3339 时间字符串
+    Timestamp,
+    Int,
+    Bool,
+    Array,
+    /// 取值限定在列表中的字符串
+    OneOf(&'static [&'static str]),
+    /// 只要求存在（必填字段）
+    Any,
+}
+
+struct FieldRule {
+    name: &'static str,
+    kind: FieldKind,
+    required: bool,
+}
+
+const fn required(name: &'static str, kind: FieldKind) -> FieldRule {
+    FieldRule { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: FieldKind) -> FieldRule {
+    FieldRule { name, kind, required: false }
+}
+
+// 各集合的字段规则
+const LINKS_SCHEMA: &[FieldRule] = &[
+    required("name", FieldKind::String),
+    required("url", FieldKind::String),
+    optional("moderation_state", FieldKind::OneOf(&["pending", "approved", "rejected"])),
+    optional("needs_review", FieldKind::Bool),
+    optional("reviewed_at", FieldKind::Timestamp),
+    optional("avatar_repaired_at", FieldKind::Timestamp),
+    optional("source", FieldKind::String),
+    optional("created_at", FieldKind::Timestamp),
+];
+
+const LINK_CLICKS_SCHEMA: &[FieldRule] = &[
+    required("link_id", FieldKind::String),
+    required("timestamp", FieldKind::Timestamp),
+    optional("referrer", FieldKind::String),
+    optional("country", FieldKind::String),
+];
+
+const USERS_SCHEMA: &[FieldRule] = &[
+    required("qq_openid", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+    optional("updated_at", FieldKind::Timestamp),
+    optional("last_login", FieldKind::Timestamp),
+    optional("deleted_at", FieldKind::Timestamp),
+];
+
+const SESSIONS_SCHEMA: &[FieldRule] = &[
+    required("token_hash", FieldKind::String),
+    required("qq_openid", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+    required("expires_at", FieldKind::Timestamp),
+];
+
+const TEMP_CODES_SCHEMA: &[FieldRule] = &[
+    required("code_hash", FieldKind::String),
+    required("qq_openid", FieldKind::String),
+    required("expires_at", FieldKind::Timestamp),
+    required("used", FieldKind::Bool),
+];
+
+const CALENDAR_EVENTS_SCHEMA: &[FieldRule] = &[
+    required("uid", FieldKind::String),
+    required("summary", FieldKind::String),
+    required("start", FieldKind::String),
+    optional("duration_minutes", FieldKind::Int),
+    optional("recurrence", FieldKind::OneOf(&["none", "", "daily", "weekly", "monthly", "yearly"])),
+];
+
+const OUTBOX_SCHEMA: &[FieldRule] = &[
+    required("kind", FieldKind::OneOf(&["webhook", "email"])),
+    required("target", FieldKind::String),
+    required("event", FieldKind::String),
+    required("payload", FieldKind::Any),
+    required("state", FieldKind::OneOf(&["pending", "delivered", "failed"])),
+    required("attempts", FieldKind::Int),
+    required("next_attempt_at", FieldKind::Timestamp),
+    required("created_at", FieldKind::Timestamp),
+    optional("delivered_at", FieldKind::Timestamp),
+    optional("last_error", FieldKind::String),
+];
+
+const IP_BLOCKS_SCHEMA: &[FieldRule] = &[
+    required("cidr", FieldKind::String),
+    optional("reason", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+    optional("expires_at", FieldKind::Timestamp),
+];
+
+const AUDIT_LOGS_SCHEMA: &[FieldRule] = &[
+    required("action", FieldKind::String),
+    required("actor", FieldKind::String),
+    required("target", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+];
+
+const BLURHASHES_SCHEMA: &[FieldRule] = &[
+    required("url", FieldKind::String),
+    required("blurhash", FieldKind::String),
+    required("width", FieldKind::Int),
+    required("height", FieldKind::Int),
+    required("created_at", FieldKind::Timestamp),
+];
+
+const WALLPAPERS_SCHEMA: &[FieldRule] = &[
+    required("wallpaper_id", FieldKind::Int),
+    required("orientation", FieldKind::OneOf(&["landscape", "portrait"])),
+    required("file", FieldKind::String),
+    required("width", FieldKind::Int),
+    required("height", FieldKind::Int),
+    required("blurhash", FieldKind::String),
+    optional("tags", FieldKind::Array),
+    required("size", FieldKind::Int),
+    required("sha256", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+];
+
+const WALLPAPER_SERVES_SCHEMA: &[FieldRule] = &[
+    required("wallpaper_id", FieldKind::Int),
+    required("count", FieldKind::Int),
+    required("last_served_at", FieldKind::Timestamp),
+];
+
+const FEATURES_SCHEMA: &[FieldRule] = &[
+    required("name", FieldKind::String),
+    required("enabled", FieldKind::Bool),
+    optional("reason", FieldKind::String),
+    required("updated_by", FieldKind::String),
+    required("updated_at", FieldKind::Timestamp),
+];
+
+const GITHUB_ISSUES_SCHEMA: &[FieldRule] = &[
+    required("issue", FieldKind::String),
+    required("link_id", FieldKind::String),
+    required("created_at", FieldKind::Timestamp),
+];
+
+const DASHBOARD_PREFERENCES_SCHEMA: &[FieldRule] = &[
+    required("admin_id", FieldKind::String),
+    optional("theme", FieldKind::OneOf(&["system", "light", "dark"])),
+    optional("widgets", FieldKind::Array),
+    optional("refresh_interval_secs", FieldKind::Int),
+    optional("updated_at", FieldKind::Timestamp),
+];
+
+/// 各集合的写入校验规则（与索引一起维护），在 insert / update 前检查
+fn collection_schema(collection_name: &str) -> &'static [FieldRule] {
+    match collection_name {
+        "links" => LINKS_SCHEMA,
+        "link_clicks" => LINK_CLICKS_SCHEMA,
+        "users" => USERS_SCHEMA,
+        "sessions" => SESSIONS_SCHEMA,
+        "temp_codes" => TEMP_CODES_SCHEMA,
+        "calendar_events" => CALENDAR_EVENTS_SCHEMA,
+        "outbox" => OUTBOX_SCHEMA,
+        "ip_blocks" => IP_BLOCKS_SCHEMA,
+        "audit_logs" => AUDIT_LOGS_SCHEMA,
+        "dashboard_preferences" => DASHBOARD_PREFERENCES_SCHEMA,
+        "blurhashes" => BLURHASHES_SCHEMA,
+        "wallpapers" => WALLPAPERS_SCHEMA,
+        "wallpaper_serves" => WALLPAPER_SERVES_SCHEMA,
+        "features" => FEATURES_SCHEMA,
+        "github_issues" => GITHUB_ISSUES_SCHEMA,
+        _ => &[],
+    }
+}
+
+fn matches_kind(kind: FieldKind, value: &Bson) -> bool {
+    match (kind, value) {
+        (FieldKind::Any, _) => true,
+        (FieldKind::String, Bson::String(_)) => true,
+        (FieldKind::Timestamp, Bson::String(s)) => chrono::DateTime::parse_from_rfc3339(s).is_ok(),
+        (FieldKind::Timestamp, Bson::DateTime(_)) => true,
+        (FieldKind::Int, Bson::Int32(_) | Bson::Int64(_)) => true,
+        (FieldKind::Bool, Bson::Boolean(_)) => true,
+        (FieldKind::Array, Bson::Array(_)) => true,
+        (FieldKind::OneOf(allowed), Bson::String(s)) => allowed.contains(&s.as_str()),
+        _ => false,
+    }
+}
+
+// 检查文档字段；complete 为 true 时（插入、整体替换）同时检查必填字段
+fn check_fields(collection_name: &str, doc: &Document, complete: bool, problems: &mut Vec<String>) {
+    for rule in collection_schema(collection_name) {
+        match doc.get(rule.name) {
+            None if complete && rule.required => problems.push(format!("missing {}", rule.name)),
+            None => {}
+            // 可选字段允许显式写入 null
+            Some(Bson::Null) if !rule.required => {}
+            Some(value) if !matches_kind(rule.kind, value) => {
+                problems.push(format!("{} should be {:?}, got {}", rule.name, rule.kind, value));
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+fn schema_error(collection_name: &str, problems: Vec<String>) -> Result<()> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    error!("Rejected write to {}: {}", collection_name, problems.join("; "));
+    Err(Error::Internal(format!(
+        "Document for {} failed schema validation: {}",
+        collection_name,
+        problems.join("; ")
+    )))
+}
+
+/// 校验待插入的文档
+fn validate_insert(collection_name: &str, doc: &Document) -> Result<()> {
+    let mut problems = Vec::new();
+    check_fields(collection_name, doc, true, &mut problems);
+    schema_error(collection_name, problems)
+}
+
+/// 校验更新文档（$set / $setOnInsert 的字段类型，$unset 不能移除必填字段，整体替换按插入校验）
+fn validate_update(collection_name: &str, update: &Document) -> Result<()> {
+    if !update.keys().any(|k| k.starts_with('$')) {
+        return validate_insert(collection_name, update);
+    }
+
+    let mut problems = Vec::new();
+    for op in ["$set", "$setOnInsert"] {
+        if let Ok(fields) = update.get_document(op) {
+            check_fields(collection_name, fields, false, &mut problems);
+        }
+    }
+    if let Ok(fields) = update.get_document("$unset") {
+        for rule in collection_schema(collection_name).iter().filter(|r| r.required) {
+            if fields.contains_key(rule.name) {
+                problems.push(format!("cannot unset required field {}", rule.name));
+            }
+        }
+    }
+    schema_error(collection_name, problems)
+}
+
 pub async fn get_db() -> Result<Arc<Mutex<Database>>> {
     DB_INSTANCE
         .get()
@@ -84,8 +398,8 @@ pub async fn find_one(collection_name: &str, filter: Document) -> Result<Option<
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
-    // 规范化返回中的日期字段为 ISO 字符串
-    let normalized = opt.map(|d| normalize_document_dates(d));
+    // 规范化返回中的日期字段为 ISO 字符串，并解密敏感字段
+    let normalized = opt.map(|d| open_sensitive_fields(collection_name, normalize_document_dates(d)));
     Ok(normalized)
 }
 
@@ -110,23 +424,50 @@ pub async fn find_many(collection_name: &str, filter: Document) -> Result<Vec<Do
         let doc = cursor
             .deserialize_current()
             .map_err(|e| Error::Database(e.to_string()))?;
-        results.push(normalize_document_dates(doc));
+        results.push(open_sensitive_fields(collection_name, normalize_document_dates(doc)));
     }
 
     Ok(results)
 }
 
-pub async fn insert_one(collection_name: &str, document: Document) -> Result<String> {
+/// 执行聚合管道（结果不做日期规范化和解密，管道中应避免输出敏感字段）
+pub async fn aggregate(collection_name: &str, pipeline: Vec<Document>) -> Result<Vec<Document>> {
     let db = get_db().await?;
     let db_lock = db.lock().await;
 
     let collection = db_lock.collection::<Document>(collection_name);
 
-    let result = collection
-        .insert_one(document)
+    let mut cursor = collection
+        .aggregate(pipeline)
         .await
         .map_err(|e| Error::Database(e.to_string()))?;
 
+    let mut results = Vec::new();
+    while cursor
+        .advance()
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?
+    {
+        results.push(
+            cursor
+                .deserialize_current()
+                .map_err(|e| Error::Database(e.to_string()))?,
+        );
+    }
+
+    Ok(results)
+}
+
+pub async fn insert_one(collection_name: &str, document: Document) -> Result<String> {
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+
+    let collection = db_lock.collection::<Document>(collection_name);
+    validate_insert(collection_name, &document)?;
+    let document = seal_sensitive_fields(collection_name, document)?;
+
+    let result = collection.insert_one(document).await.map_err(write_error)?;
+
     Ok(result
         .inserted_id
         .as_object_id()
@@ -139,13 +480,60 @@ pub async fn update_one(collection_name: &str, filter: Document, update: Documen
     let db_lock = db.lock().await;
 
     let collection = db_lock.collection::<Document>(collection_name);
+    validate_update(collection_name, &update)?;
+    let update = seal_sensitive_update(collection_name, update)?;
 
-    let result = collection
+    let result = collection.update_one(filter, update).await.map_err(write_error)?;
+
+    Ok(result.modified_count)
+}
+
+/// 更新匹配的文档，不存在时按 filter 和 update 插入
+pub async fn upsert_one(collection_name: &str, filter: Document, update: Document) -> Result<()> {
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+
+    let collection = db_lock.collection::<Document>(collection_name);
+    validate_update(collection_name, &update)?;
+    let update = seal_sensitive_update(collection_name, update)?;
+
+    collection
         .update_one(filter, update)
+        .upsert(true)
         .await
-        .map_err(|e| Error::Database(e.to_string()))?;
+        .map_err(write_error)?;
+    Ok(())
+}
 
-    Ok(result.modified_count)
+/// 计数器集合（`{ _id: <名称>, seq: <当前值> }`）
+const COUNTERS_COLLECTION: &str = "counters";
+
+/// 递增名为 `name` 的计数器并返回新值，结果大于 `floor`
+///
+/// 计数器只增不减，已分配的值（包括对应数据已删除的）不会再次返回；多个实例并发调用时各自得到不同的值
+pub async fn next_sequence(name: &str, floor: u64) -> Result<u64> {
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+
+    let collection = db_lock.collection::<Document>(COUNTERS_COLLECTION);
+    // $max 与 $inc 不能作用于同一字段，先抬高下限再递增（两步各自原子，组合后仍不会重复）
+    collection
+        .update_one(doc! { "_id": name }, doc! { "$max": { "seq": floor as i64 } })
+        .upsert(true)
+        .await
+        .map_err(write_error)?;
+    let counter = collection
+        .find_one_and_update(doc! { "_id": name }, doc! { "$inc": { "seq": 1_i64 } })
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .await
+        .map_err(write_error)?
+        .ok_or_else(|| Error::Database(format!("Counter {} was not created", name)))?;
+
+    counter
+        .get_i64("seq")
+        .map(|seq| seq as u64)
+        .map_err(|e| Error::Database(format!("Invalid counter {}: {}", name, e)))
 }
 
 pub async fn delete_one(collection_name: &str, filter: Document) -> Result<u64> {
@@ -162,6 +550,211 @@ pub async fn delete_one(collection_name: &str, filter: Document) -> Result<u64>
     Ok(result.deleted_count)
 }
 
+pub async fn update_many(collection_name: &str, filter: Document, update: Document) -> Result<u64> {
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+
+    let collection = db_lock.collection::<Document>(collection_name);
+    validate_update(collection_name, &update)?;
+    let update = seal_sensitive_update(collection_name, update)?;
+
+    let result = collection.update_many(filter, update).await.map_err(write_error)?;
+
+    Ok(result.modified_count)
+}
+
+pub async fn delete_many(collection_name: &str, filter: Document) -> Result<u64> {
+    let db = get_db().await?;
+    let db_lock = db.lock().await;
+
+    let collection = db_lock.collection::<Document>(collection_name);
+
+    let result = collection
+        .delete_many(filter)
+        .await
+        .map_err(|e| Error::Database(e.to_string()))?;
+
+    Ok(result.deleted_count)
+}
+
+/// 重新加密时每批处理的文档数（每批之间释放数据库锁，避免长时间阻塞其他请求）
+const RESEAL_BATCH_SIZE: i64 = 200;
+
+/// 一次重新加密的结果（按文档计数）
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResealReport {
+    pub updated: u64,
+    /// 解密或写回失败而跳过的文档（保持原样，下次启动重试）
+    pub failed: u64,
+}
+
+/// 使用当前密钥重新加密集合中由旧密钥加密的敏感字段
+///
+/// 按 _id 分批扫描，每批单独获取数据库锁；单个文档失败时记录并跳过，只有扫描本身出错才中止
+pub async fn reseal_collection(collection_name: &str) -> Result<ResealReport> {
+    let fields = sensitive_fields(collection_name);
+    let mut report = ResealReport::default();
+    let cipher = match crypto::cipher() {
+        Some(c) if c.is_enabled() && !fields.is_empty() => c,
+        _ => return Ok(report),
+    };
+
+    let db = get_db().await?;
+    let mut last_id: Option<Bson> = None;
+    loop {
+        let db_lock = db.lock().await;
+        let collection = db_lock.collection::<Document>(collection_name);
+        let filter = match &last_id {
+            Some(id) => doc! { "_id": { "$gt": id.clone() } },
+            None => doc! {},
+        };
+        let mut cursor = collection
+            .find(filter)
+            .sort(doc! { "_id": 1 })
+            .limit(RESEAL_BATCH_SIZE)
+            .await
+            .map_err(|e| Error::Database(e.to_string()))?;
+
+        let mut scanned = 0;
+        while cursor
+            .advance()
+            .await
+            .map_err(|e| Error::Database(e.to_string()))?
+        {
+            scanned += 1;
+            let doc = match cursor.deserialize_current() {
+                Ok(doc) => doc,
+                Err(e) => {
+                    warn!("重新加密 {} 时跳过无法解析的文档: {}", collection_name, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            let Some(id) = doc.get("_id").cloned() else {
+                continue;
+            };
+            last_id = Some(id.clone());
+
+            let set = match reseal_fields(&doc, fields, cipher) {
+                Ok(set) if set.is_empty() => continue,
+                Ok(set) => set,
+                Err(e) => {
+                    warn!("重新加密 {} 中的文档 {} 失败: {}", collection_name, id, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            match collection.update_one(doc! { "_id": id.clone() }, doc! { "$set": set }).await {
+                Ok(_) => report.updated += 1,
+                Err(e) => {
+                    warn!("写回 {} 中的文档 {} 失败: {}", collection_name, id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+        drop(db_lock);
+
+        if scanned < RESEAL_BATCH_SIZE {
+            break;
+        }
+        tokio::task::yield_now().await;
+    }
+
+    Ok(report)
+}
+
+// 需要用当前密钥重新加密的字段（$set 内容），没有时为空
+fn reseal_fields(doc: &Document, fields: &[&str], cipher: &FieldCipher) -> Result<Document> {
+    let mut set = Document::new();
+    for field in fields {
+        if let Ok(value) = doc.get_str(field) {
+            if cipher.needs_rotation(value) {
+                let plaintext = cipher.decrypt(value)?;
+                set.insert(*field, cipher.encrypt(&plaintext)?);
+            }
+        }
+    }
+    Ok(set)
+}
+
+/// 各集合中需要加密存储的敏感字段
+///
+/// sessions 只保存令牌的 SHA-256（token_hash，用作查询条件），没有需要加密的字段
+fn sensitive_fields(collection_name: &str) -> &'static [&'static str] {
+    match collection_name {
+        "users" => &["qq_access_token", "qq_refresh_token"],
+        _ => &[],
+    }
+}
+
+/// 从文档中移除敏感字段（用于对外返回）
+pub fn strip_sensitive_fields(collection_name: &str, mut doc: Document) -> Document {
+    for field in sensitive_fields(collection_name) {
+        doc.remove(*field);
+    }
+    doc
+}
+
+// 加密文档中的敏感字段（未配置密钥时保持明文，已加密的字段跳过）
+fn seal_sensitive_fields(collection_name: &str, mut doc: Document) -> Result<Document> {
+    let cipher = match crypto::cipher() {
+        Some(c) if c.is_enabled() => c,
+        _ => return Ok(doc),
+    };
+
+    for field in sensitive_fields(collection_name) {
+        let sealed = match doc.get(*field) {
+            Some(Bson::String(value)) if !FieldCipher::is_sealed(value) => cipher.encrypt(value)?,
+            _ => continue,
+        };
+        doc.insert(*field, sealed);
+    }
+    Ok(doc)
+}
+
+// 加密更新文档中的敏感字段（支持 $set / $setOnInsert 与整体替换）
+fn seal_sensitive_update(collection_name: &str, mut update: Document) -> Result<Document> {
+    let is_operator_update = update.keys().any(|k| k.starts_with('$'));
+    if !is_operator_update {
+        return seal_sensitive_fields(collection_name, update);
+    }
+
+    for op in ["$set", "$setOnInsert"] {
+        if let Ok(inner) = update.get_document(op) {
+            let sealed = seal_sensitive_fields(collection_name, inner.clone())?;
+            update.insert(op, sealed);
+        }
+    }
+    Ok(update)
+}
+
+// 解密文档中的敏感字段，解密失败时移除该字段，避免密文外泄
+fn open_sensitive_fields(collection_name: &str, mut doc: Document) -> Document {
+    for field in sensitive_fields(collection_name) {
+        let opened = match doc.get(*field) {
+            Some(Bson::String(value)) if FieldCipher::is_sealed(value) => {
+                crypto::cipher().map(|c| c.decrypt(value))
+            }
+            _ => continue,
+        };
+
+        match opened {
+            Some(Ok(plaintext)) => {
+                doc.insert(*field, plaintext);
+            }
+            Some(Err(e)) => {
+                error!("Failed to decrypt field {}.{}: {}", collection_name, field, e);
+                doc.remove(*field);
+            }
+            None => {
+                error!("Encrypted field {}.{} found but cipher not initialized", collection_name, field);
+                doc.remove(*field);
+            }
+        }
+    }
+    doc
+}
+
 // 将 Document 中的 BSON 日期或扩展 JSON 日期转换为 ISO 字符串（递归）
 fn normalize_document_dates(doc: Document) -> Document {
     fn normalize_bson(value: Bson) -> Bson {
@@ -233,3 +826,61 @@ fn normalize_document_dates(doc: Document) -> Document {
     }
     new
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_validation() {
+        let now = Utc::now().to_rfc3339();
+        let click = doc! { "link_id": "abc", "timestamp": &now };
+        assert!(validate_insert("link_clicks", &click).is_ok());
+        assert!(validate_insert("link_clicks", &doc! { "link_id": "abc" }).is_err());
+        assert!(validate_insert("link_clicks", &doc! { "link_id": "abc", "timestamp": "yesterday" }).is_err());
+        // 未声明规则的集合不做限制
+        assert!(validate_insert("misc", &doc! { "x": 1 }).is_ok());
+
+        assert!(validate_update("links", &doc! { "$set": { "moderation_state": "approved" } }).is_ok());
+        assert!(validate_update("links", &doc! { "$set": { "moderation_state": "maybe" } }).is_err());
+        assert!(validate_update("links", &doc! { "$unset": { "email": "" } }).is_ok());
+        assert!(validate_update("links", &doc! { "$unset": { "url": "" } }).is_err());
+        assert!(validate_update("ip_blocks", &doc! { "cidr": "1.2.3.4/32", "created_at": &now, "expires_at": Bson::Null }).is_ok());
+    }
+
+    #[test]
+    fn test_reseal_fields() {
+        use crate::config::settings::{EncryptionKeyConfig, SecurityConfig};
+        use base64::Engine;
+
+        let config = |ids: &[&str]| SecurityConfig {
+            encryption_keys: ids
+                .iter()
+                .map(|id| EncryptionKeyConfig {
+                    id: id.to_string(),
+                    key: base64::engine::general_purpose::STANDARD.encode([id.len() as u8; 32]),
+                })
+                .collect(),
+            code_pepper: None,
+        };
+        let old = FieldCipher::from_config(&config(&["k1"])).unwrap();
+        let rotated = FieldCipher::from_config(&config(&["key2", "k1"])).unwrap();
+
+        let doc = doc! {
+            "qq_access_token": old.encrypt("secret").unwrap(),
+            "qq_refresh_token": rotated.encrypt("current").unwrap(),
+            "other": "plain",
+        };
+        let fields = sensitive_fields("users");
+        let set = reseal_fields(&doc, fields, &rotated).unwrap();
+        assert_eq!(set.len(), 1);
+        let resealed = set.get_str("qq_access_token").unwrap();
+        assert!(!rotated.needs_rotation(resealed));
+        assert_eq!(rotated.decrypt(resealed).unwrap(), "secret");
+
+        // 已使用当前密钥的文档不需要更新
+        assert!(reseal_fields(&doc! { "qq_access_token": resealed }, fields, &rotated).unwrap().is_empty());
+        // 会话只保存令牌哈希，没有需要加密的字段
+        assert!(sensitive_fields("sessions").is_empty());
+    }
+}
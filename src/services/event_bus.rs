@@ -0,0 +1,108 @@
+use crate::services::memory_service::MemoryPressure;
+use log::{error, warn};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::future::Future;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// 事件通道容量（订阅者处理过慢时丢弃最旧的事件）
+const CHANNEL_CAPACITY: usize = 1024;
+
+static SENDER: Lazy<broadcast::Sender<Event>> = Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// 内部事件
+///
+/// 可选子系统（webhook、统计、通知等）通过 subscribe 订阅，业务代码只负责 publish
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event")]
+pub enum Event {
+    /// 新的友链申请
+    #[serde(rename = "link.submitted")]
+    LinkSubmitted { link_id: String, url: String },
+    /// 用户完成 OAuth 登录
+    #[serde(rename = "user.logged_in")]
+    UserLoggedIn { openid: String, new_user: bool },
+    /// 内存压力等级变化
+    #[serde(rename = "memory.pressure_changed")]
+    MemoryPressureChanged {
+        from: MemoryPressure,
+        to: MemoryPressure,
+        usage_mb: u64,
+    },
+    /// 友链头像无缓存，需要同步下载
+    #[serde(rename = "avatar.cache_miss")]
+    AvatarCacheMiss { url: String },
+    /// 友链头像失效后自动替换为备用头像
+    #[serde(rename = "link.avatar_repaired")]
+    LinkAvatarRepaired { link_id: String, avatar: String },
+    /// 友链审核通过
+    #[serde(rename = "link.approved")]
+    LinkApproved { link_id: String },
+    /// 友链被拒绝
+    #[serde(rename = "link.rejected")]
+    LinkRejected { link_id: String },
+    /// 用户注销账号
+    #[serde(rename = "user.deleted")]
+    UserDeleted { user_id: String },
+    /// 收到外部来源的事件（GitHub 推送、博客文章发布等）
+    #[serde(rename = "hook.received")]
+    HookReceived {
+        source: String,
+        /// 来源的事件类型（如 push、POST_CREATE）
+        kind: String,
+        summary: String,
+    },
+}
+
+impl Event {
+    /// 事件名称（与序列化后的 event 字段一致）
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::LinkSubmitted { .. } => "link.submitted",
+            Event::UserLoggedIn { .. } => "user.logged_in",
+            Event::MemoryPressureChanged { .. } => "memory.pressure_changed",
+            Event::AvatarCacheMiss { .. } => "avatar.cache_miss",
+            Event::LinkAvatarRepaired { .. } => "link.avatar_repaired",
+            Event::LinkApproved { .. } => "link.approved",
+            Event::LinkRejected { .. } => "link.rejected",
+            Event::UserDeleted { .. } => "user.deleted",
+            Event::HookReceived { .. } => "hook.received",
+        }
+    }
+}
+
+/// 发布事件（没有订阅者时直接丢弃，不阻塞调用方）
+pub fn publish(event: Event) {
+    let _ = SENDER.send(event);
+}
+
+/// 订阅事件
+///
+/// `names` 为空时接收全部事件；每个订阅者在独立任务中按顺序处理事件，
+/// 处理失败或变慢不会影响发布方和其他订阅者。需在 tokio 运行时中调用
+pub fn subscribe<F, Fut>(subscriber: &'static str, names: &'static [&'static str], handler: F) -> JoinHandle<()>
+where
+    F: Fn(Event) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut receiver = SENDER.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if names.is_empty() || names.contains(&event.name()) {
+                        handler(event).await;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Event subscriber {} lagged, skipped {} events", subscriber, skipped);
+                }
+                Err(RecvError::Closed) => {
+                    error!("Event bus closed, subscriber {} stopped", subscriber);
+                    break;
+                }
+            }
+        }
+    })
+}
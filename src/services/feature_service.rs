@@ -0,0 +1,301 @@
+use crate::config::settings::FeaturesConfig;
+use crate::services::db_service;
+use crate::{Error, Result};
+use chrono::Utc;
+use log::{info, warn};
+use mongodb::bson::doc;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+const COLLECTION: &str = "features";
+/// 功能关闭时 503 响应的 Retry-After（秒）
+const RETRY_AFTER_SECS: u64 = 300;
+
+/// 功能说明（注册表条目）
+#[derive(Debug, Serialize)]
+pub struct FeatureSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// 功能注册表：新增开关时在这里声明，并为路由添加对应的 FeatureGate 守卫（非路由入口调用 ensure）
+pub const FEATURES: &[FeatureSpec] = &[
+    FeatureSpec {
+        name: "ncm_status",
+        description: "网易云音乐播放状态（/status/ncm），更换 Cookie 期间可关闭",
+    },
+    FeatureSpec {
+        name: "link_submissions",
+        description: "友链申请（GitHub issue 和邮件），垃圾申请较多时可关闭",
+    },
+    FeatureSpec {
+        name: "friend_avatar",
+        description: "友链头像代理（/friend-avatar）",
+    },
+    FeatureSpec {
+        name: "blurhash",
+        description: "任意图片的 blurhash 计算（/images/blurhash）",
+    },
+    FeatureSpec {
+        name: "palette",
+        description: "任意图片的主色和调色板计算（/images/palette）",
+    },
+    FeatureSpec {
+        name: "og_image",
+        description: "分享卡片生成（/images/og）",
+    },
+    FeatureSpec {
+        name: "image_proxy",
+        description: "通用图片代理（/images/proxy）",
+    },
+    FeatureSpec {
+        name: "email_verification",
+        description: "发送邮箱验证码（/email/send）",
+    },
+];
+
+/// 路由守卫使用的功能标识
+pub trait Feature: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+pub struct NcmStatus;
+pub struct LinkSubmissions;
+pub struct FriendAvatar;
+pub struct BlurhashApi;
+pub struct PaletteApi;
+pub struct OgImage;
+pub struct ImageProxy;
+pub struct EmailVerification;
+
+impl Feature for NcmStatus {
+    const NAME: &'static str = "ncm_status";
+}
+
+impl Feature for LinkSubmissions {
+    const NAME: &'static str = "link_submissions";
+}
+
+impl Feature for FriendAvatar {
+    const NAME: &'static str = "friend_avatar";
+}
+
+impl Feature for BlurhashApi {
+    const NAME: &'static str = "blurhash";
+}
+
+impl Feature for PaletteApi {
+    const NAME: &'static str = "palette";
+}
+
+impl Feature for OgImage {
+    const NAME: &'static str = "og_image";
+}
+
+impl Feature for ImageProxy {
+    const NAME: &'static str = "image_proxy";
+}
+
+impl Feature for EmailVerification {
+    const NAME: &'static str = "email_verification";
+}
+
+/// 管理接口设置的开关（覆盖配置中的默认值）
+#[derive(Debug, Clone)]
+struct Override {
+    enabled: bool,
+    reason: Option<String>,
+    updated_by: String,
+    updated_at: String,
+}
+
+/// 功能的当前状态
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureState {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+    /// 配置中的默认值
+    pub default_enabled: bool,
+    pub reason: Option<String>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// 配置中默认关闭的功能
+static DISABLED_BY_CONFIG: OnceCell<HashSet<&'static str>> = OnceCell::new();
+// 管理接口设置的开关（按功能名称）
+static OVERRIDES: Lazy<RwLock<HashMap<&'static str, Override>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn spec(name: &str) -> Result<&'static FeatureSpec> {
+    FEATURES
+        .iter()
+        .find(|f| f.name == name)
+        .ok_or_else(|| Error::NotFound(format!("Unknown feature: {}", name)))
+}
+
+/// 初始化配置中的默认开关（启动时调用一次，未知名称忽略）
+pub fn init(config: &FeaturesConfig) {
+    let mut disabled = HashSet::new();
+    for name in &config.disabled {
+        match spec(name) {
+            Ok(spec) => {
+                disabled.insert(spec.name);
+            }
+            Err(_) => warn!("Ignoring unknown feature in features.disabled: {}", name),
+        }
+    }
+    let _ = DISABLED_BY_CONFIG.set(disabled);
+}
+
+fn default_enabled(name: &str) -> bool {
+    DISABLED_BY_CONFIG.get().is_none_or(|disabled| !disabled.contains(name))
+}
+
+/// 功能是否开启：管理接口的设置优先，其次为配置
+pub fn is_enabled(name: &str) -> bool {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    match overrides.get(name) {
+        Some(o) => o.enabled,
+        None => default_enabled(name),
+    }
+}
+
+/// 功能关闭时返回 503（非路由入口使用，路由使用 FeatureGate 守卫）
+pub fn ensure(name: &str) -> Result<()> {
+    if is_enabled(name) {
+        return Ok(());
+    }
+    Err(Error::Unavailable(
+        format!("Feature {} is temporarily disabled", name),
+        RETRY_AFTER_SECS,
+    ))
+}
+
+fn state(spec: &'static FeatureSpec, overrides: &HashMap<&'static str, Override>) -> FeatureState {
+    let default_enabled = default_enabled(spec.name);
+    let o = overrides.get(spec.name);
+    FeatureState {
+        name: spec.name,
+        description: spec.description,
+        enabled: o.map_or(default_enabled, |o| o.enabled),
+        default_enabled,
+        reason: o.and_then(|o| o.reason.clone()),
+        updated_by: o.map(|o| o.updated_by.clone()),
+        updated_at: o.map(|o| o.updated_at.clone()),
+    }
+}
+
+/// 全部功能的当前状态
+pub fn list() -> Vec<FeatureState> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    FEATURES.iter().map(|spec| state(spec, &overrides)).collect()
+}
+
+/// 开启或关闭功能（持久化到 MongoDB，立即生效）
+pub async fn set(name: &str, enabled: bool, reason: Option<String>, actor: &str) -> Result<FeatureState> {
+    let spec = spec(name)?;
+    let o = Override {
+        enabled,
+        reason: reason.filter(|r| !r.trim().is_empty()),
+        updated_by: actor.to_string(),
+        updated_at: Utc::now().to_rfc3339(),
+    };
+
+    // 同一功能只保留一条，单次 upsert 写入，并发修改或写入失败时不会丢失已有设置
+    let mut fields = doc! {
+        "enabled": enabled,
+        "updated_by": &o.updated_by,
+        "updated_at": &o.updated_at,
+    };
+    let update = match &o.reason {
+        Some(reason) => {
+            fields.insert("reason", reason);
+            doc! { "$set": fields }
+        }
+        None => doc! { "$set": fields, "$unset": { "reason": "" } },
+    };
+    db_service::upsert_one(COLLECTION, doc! { "name": spec.name }, update).await?;
+
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    overrides.insert(spec.name, o);
+    info!("功能 {} 已{}（{}）", spec.name, if enabled { "开启" } else { "关闭" }, actor);
+    Ok(state(spec, &overrides))
+}
+
+/// 删除管理接口的设置，恢复配置中的默认值；返回是否存在该设置
+pub async fn reset(name: &str) -> Result<(FeatureState, bool)> {
+    let spec = spec(name)?;
+    db_service::delete_one(COLLECTION, doc! { "name": spec.name }).await?;
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    let existed = overrides.remove(spec.name).is_some();
+    Ok((state(spec, &overrides), existed))
+}
+
+/// 启动时从 MongoDB 加载持久化的开关（未知名称忽略）
+pub async fn load_persisted() -> Result<usize> {
+    let docs = db_service::find_many(COLLECTION, doc! {}).await?;
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    for d in docs {
+        let (Ok(spec), Ok(enabled)) = (spec(d.get_str("name").unwrap_or_default()), d.get_bool("enabled")) else {
+            continue;
+        };
+        overrides.insert(
+            spec.name,
+            Override {
+                enabled,
+                reason: d.get_str("reason").ok().map(str::to_string),
+                updated_by: d.get_str("updated_by").unwrap_or("").to_string(),
+                updated_at: d.get_str("updated_at").unwrap_or("").to_string(),
+            },
+        );
+    }
+    Ok(overrides.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features() {
+        // 守卫使用的名称都已注册
+        for name in [
+            NcmStatus::NAME,
+            LinkSubmissions::NAME,
+            FriendAvatar::NAME,
+            BlurhashApi::NAME,
+            PaletteApi::NAME,
+            OgImage::NAME,
+            ImageProxy::NAME,
+            EmailVerification::NAME,
+        ] {
+            assert!(spec(name).is_ok(), "{}", name);
+        }
+        assert!(matches!(spec("nope"), Err(Error::NotFound(_))));
+
+        init(&FeaturesConfig {
+            disabled: vec!["og_image".to_string(), "nope".to_string()],
+        });
+        assert!(!is_enabled("og_image"));
+        assert!(is_enabled("blurhash"));
+        assert!(matches!(ensure("og_image"), Err(Error::Unavailable(_, RETRY_AFTER_SECS))));
+
+        // 管理接口的设置优先于配置
+        OVERRIDES.write().unwrap().insert(
+            "og_image",
+            Override {
+                enabled: true,
+                reason: None,
+                updated_by: "test".to_string(),
+                updated_at: String::new(),
+            },
+        );
+        assert!(ensure("og_image").is_ok());
+        let og = list().into_iter().find(|f| f.name == "og_image").unwrap();
+        assert!(og.enabled && !og.default_enabled);
+        assert_eq!(og.updated_by.as_deref(), Some("test"));
+    }
+}
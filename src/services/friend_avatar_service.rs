@@ -1,15 +1,28 @@
+use crate::services::audit_service::AuditService;
+use crate::services::event_bus::{self, Event};
 use crate::services::image_service::ImageService;
+use crate::services::link_service::LinkService;
+use crate::services::mock_upstream;
+use crate::services::outbox_service;
+use crate::services::upstream_service::{self, HttpClientService};
+use crate::utils::cache::{self, Namespace};
+use crate::utils::request_context::RequestContext;
+use crate::utils::transcode_limiter;
+use crate::utils::url::{canonical_host, canonicalize};
 use crate::{Error, Result};
 use image::ImageFormat;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::fs;
-use tokio::sync::RwLock;
+
+/// 共享缓存后端中元数据的保留时间
+const METADATA_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// 下载头像的超时
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// 友链头像缓存元数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +41,10 @@ struct AvatarMetadata {
     format: String,
 }
 
+fn metadata_key(cache_key: &str) -> String {
+    Namespace::Metadata.key(format_args!("friend-avatar:{}", cache_key))
+}
+
 /// 获取当前时间戳（秒），系统时钟异常时回退到 0
 fn now_secs() -> u64 {
     SystemTime::now()
@@ -71,35 +88,31 @@ impl AvatarMetadata {
         self.legacy_mode = false;
     }
 
-    /// 标记为失败
-    fn mark_failure(&mut self) {
+    /// 标记为失败，返回是否刚进入 legacy 模式
+    fn mark_failure(&mut self) -> bool {
         let now = now_secs();
         self.last_check_time = now;
         self.fail_count += 1;
 
         // 连续失败3次进入 legacy 模式
+        let entered = self.fail_count >= 3 && !self.legacy_mode;
         if self.fail_count >= 3 {
             self.legacy_mode = true;
         }
+        entered
     }
 }
 
 pub struct FriendAvatarService {
     client: Client,
     cache_dir: PathBuf,
-    /// 正在更新的 URL 集合（防止并发重复请求）
-    updating: Arc<RwLock<std::collections::HashSet<String>>>,
 }
 
 impl FriendAvatarService {
-    pub fn new() -> Self {
+    pub fn new(http: &HttpClientService) -> Self {
         Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .expect("Failed to create HTTP client for FriendAvatarService"),
+            client: http.client().clone(),
             cache_dir: PathBuf::from("cache/friend_avatars"),
-            updating: Arc::new(RwLock::new(std::collections::HashSet::new())),
         }
     }
 
@@ -109,8 +122,11 @@ impl FriendAvatarService {
     /// 1. 有缓存 -> 立即返回，根据新鲜度决定是否后台更新
     /// 2. 无缓存 -> 同步下载
     /// 3. 强制刷新 -> 同步下载
+    ///
+    /// 同步下载在超过请求时限时不再开始，返回 504
     pub async fn fetch_friend_avatar(
         &self,
+        ctx: &RequestContext,
         url: &str,
         accept_header: &str,
         force_refresh: bool,
@@ -118,8 +134,8 @@ impl FriendAvatarService {
         let target_format = self.get_preferred_format(accept_header);
         let target_format_ext = ImageService::format_extension(target_format);
         
-        // 尝试多种格式的缓存（优先目标格式，其次 avif/webp/jpeg）
-        let formats_to_try = [target_format_ext, "avif", "webp", "jpeg"];
+        // 尝试多种格式的缓存（优先目标格式，其次 avif/webp/jpeg，动图可能为 gif）
+        let formats_to_try = [target_format_ext, "avif", "webp", "jpeg", "gif"];
         
         info!("[友链头像] 请求: {} (目标格式: {})", url, target_format_ext);
         
@@ -127,7 +143,7 @@ impl FriendAvatarService {
         if force_refresh {
             info!("[友链头像] 强制刷新: {}", url);
             let cache_key = self.get_cache_key(url, target_format_ext);
-            return self.download_and_cache(url, target_format, &cache_key).await;
+            return self.download_and_cache(ctx, url, target_format, &cache_key).await;
         }
 
         // 尝试读取缓存（按格式优先级）
@@ -188,27 +204,28 @@ impl FriendAvatarService {
 
         // 无缓存：同步下载
         info!("[友链头像] 无缓存，开始下载: {}", url);
+        event_bus::publish(Event::AvatarCacheMiss { url: url.to_string() });
         let cache_key = self.get_cache_key(url, target_format_ext);
-        self.download_and_cache(url, target_format, &cache_key).await
+        self.download_and_cache(ctx, url, target_format, &cache_key).await
     }
 
     /// 同步下载并缓存
     async fn download_and_cache(
         &self,
+        ctx: &RequestContext,
         url: &str,
         format: ImageFormat,
         cache_key: &str,
     ) -> Result<(Vec<u8>, String, String)> {
         // 下载原图
+        ctx.check("friend avatar download")?;
         let raw_bytes = self.download_image(url).await?;
         info!("[友链头像] 下载完成: {} ({} 字节)", url, raw_bytes.len());
+        ctx.check("friend avatar transcode")?;
 
         // 智能转码（AVIF 等无法解码的格式会透传）
-        let (final_bytes, final_format) = tokio::task::spawn_blocking(move || {
-            ImageService::smart_transcode(raw_bytes, format)
-        })
-        .await
-        .map_err(|e| Error::Internal(format!("Task join error: {}", e)))??;
+        let (final_bytes, final_format) =
+            transcode_limiter::run(move || ImageService::smart_transcode(raw_bytes, format)).await??;
 
         let format_ext = ImageService::format_extension(final_format);
         
@@ -234,15 +251,11 @@ impl FriendAvatarService {
         format: ImageFormat,
         cache_key: &str,
     ) -> Result<()> {
-        // 防止并发重复更新
-        {
-            let mut updating = self.updating.write().await;
-            if updating.contains(url) {
-                debug!("[友链头像] 已在更新中，跳过: {}", url);
-                return Ok(());
-            }
-            updating.insert(url.to_string());
-        }
+        // 防止并发重复更新（守卫在更新结束时释放）
+        let Some(_guard) = cache::try_revalidate(&format!("friend-avatar:{}", url)) else {
+            debug!("[友链头像] 已在更新中，跳过: {}", url);
+            return Ok(());
+        };
 
         info!("[友链头像] 后台更新开始: {}", url);
 
@@ -252,11 +265,8 @@ impl FriendAvatarService {
             info!("[友链头像] 后台下载完成: {} ({} 字节)", url, raw_bytes.len());
             
             // 智能转码
-            let (final_bytes, final_format) = tokio::task::spawn_blocking(move || {
-                ImageService::smart_transcode(raw_bytes, format)
-            })
-            .await
-            .map_err(|e| Error::Internal(format!("Task join error: {}", e)))??;
+            let (final_bytes, final_format) =
+                transcode_limiter::run_background(move || ImageService::smart_transcode(raw_bytes, format)).await??;
 
             let final_format_ext = ImageService::format_extension(final_format);
             
@@ -278,13 +288,10 @@ impl FriendAvatarService {
         // 处理失败情况
         if let Err(e) = result {
             error!("[友链头像] 后台更新失败: {} - {}", url, e);
-            self.mark_update_failure(cache_key).await;
-        }
-
-        // 移除更新标记
-        {
-            let mut updating = self.updating.write().await;
-            updating.remove(url);
+            // 刚进入 legacy 模式（连续失败）时尝试自动修复友链头像
+            if self.mark_update_failure(cache_key).await {
+                self.repair_link_avatar(url).await;
+            }
         }
 
         Ok(())
@@ -292,16 +299,22 @@ impl FriendAvatarService {
 
     /// 下载原始图片（包含 SSRF 防护）
     async fn download_image(&self, url: &str) -> Result<Vec<u8>> {
+        // 模拟上游模式下不发出任何外部请求
+        if mock_upstream::is_enabled() {
+            return Ok(mock_upstream::image(url).await);
+        }
+
         // SSRF 防护：校验 URL 安全性
         Self::validate_url(url)?;
 
         debug!("[友链头像] 正在请求: {}", url);
         
-        let response = self
+        let request = self
             .client
             .get(url)
-            .header("User-Agent", "Mozilla/5.0 (compatible; MaigoStarlightChecker/1.0; +mailto:tnxg@outlook.jp; ) AppleWebKit/99 (KHTML, like Gecko) Chrome/99 MyGO/5 (KiraKira/DokiDoki; Bananice/Protected) Giraffe/4.11 (Wakarimasu/; Haruhikage/Stop)")
-            .send()
+            .timeout(DOWNLOAD_TIMEOUT)
+            .header("User-Agent", "Mozilla/5.0 (compatible; MaigoStarlightChecker/1.0; +mailto:tnxg@outlook.jp; ) AppleWebKit/99 (KHTML, like Gecko) Chrome/99 MyGO/5 (KiraKira/DokiDoki; Bananice/Protected) Giraffe/4.11 (Wakarimasu/; Haruhikage/Stop)");
+        let response = upstream_service::send(request)
             .await
             .map_err(|e| Error::Internal(format!("请求失败: {}", e)))?;
 
@@ -356,11 +369,18 @@ impl FriendAvatarService {
         fs::read(&data_path).await.ok()
     }
 
-    /// 保存元数据
+    /// 保存元数据（配置了共享缓存后端时保存到共享缓存，多个实例的新鲜度和失败计数保持一致）
     async fn save_metadata(&self, cache_key: &str, metadata: &AvatarMetadata) -> Result<()> {
-        let meta_path = self.cache_dir.join(format!("{}.meta", cache_key));
         let json = serde_json::to_string(metadata)
             .map_err(|e| Error::Internal(format!("Failed to serialize metadata: {}", e)))?;
+        let backend = cache::backend();
+        if backend.shared() {
+            backend
+                .put(&metadata_key(cache_key), json.into_bytes(), Some(METADATA_TTL))
+                .await;
+            return Ok(());
+        }
+        let meta_path = self.cache_dir.join(format!("{}.meta", cache_key));
         fs::write(&meta_path, json)
             .await
             .map_err(|e| Error::Internal(format!("Failed to write metadata: {}", e)))?;
@@ -369,41 +389,93 @@ impl FriendAvatarService {
 
     /// 加载元数据
     async fn load_metadata(&self, cache_key: &str) -> Option<AvatarMetadata> {
-        let meta_path = self.cache_dir.join(format!("{}.meta", cache_key));
-        let json = fs::read_to_string(&meta_path).await.ok()?;
-        serde_json::from_str(&json).ok()
+        let backend = cache::backend();
+        let json = if backend.shared() {
+            backend.get(&metadata_key(cache_key)).await?
+        } else {
+            let meta_path = self.cache_dir.join(format!("{}.meta", cache_key));
+            fs::read(&meta_path).await.ok()?
+        };
+        serde_json::from_slice(&json).ok()
     }
 
-    /// 标记更新失败
-    async fn mark_update_failure(&self, cache_key: &str) {
-        if let Some(mut metadata) = self.load_metadata(cache_key).await {
-            metadata.mark_failure();
-            let _ = self.save_metadata(cache_key, &metadata).await;
+    /// 标记更新失败，返回是否刚进入 legacy 模式
+    async fn mark_update_failure(&self, cache_key: &str) -> bool {
+        match self.load_metadata(cache_key).await {
+            Some(mut metadata) => {
+                let entered = metadata.mark_failure();
+                let _ = self.save_metadata(cache_key, &metadata).await;
+                entered
+            }
+            None => false,
         }
     }
 
+    /// 头像持续失效时自动修复：依次尝试站点图标和 Gravatar，
+    /// 找到可用图片后更新友链记录并标记为待审核
+    async fn repair_link_avatar(&self, avatar_url: &str) {
+        let link = match LinkService::find_by_avatar(avatar_url).await {
+            Ok(Some(link)) => link,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("[友链头像] 查询友链失败: {} - {}", avatar_url, e);
+                return;
+            }
+        };
+
+        for candidate in LinkService::avatar_candidates(&link) {
+            let Ok(bytes) = self.download_image(&candidate).await else {
+                continue;
+            };
+            // 只接受能正常解码的图片（排除返回 HTML 的错误页等）
+            let decodable = transcode_limiter::run_background(move || image::load_from_memory(&bytes).is_ok())
+                .await
+                .unwrap_or(false);
+            if !decodable {
+                continue;
+            }
+
+            if let Err(e) = LinkService::replace_avatar(&link, &candidate).await {
+                warn!("[友链头像] 更新友链头像失败: {} - {}", avatar_url, e);
+                return;
+            }
+            info!("[友链头像] 已自动修复: {} -> {}", avatar_url, candidate);
+            AuditService::record(
+                "link.avatar_repaired",
+                "system",
+                avatar_url,
+                serde_json::json!({ "new_avatar": candidate }),
+            )
+            .await;
+            // 头像已经替换，通知写入失败时 emit 已记录日志
+            let _ = outbox_service::emit(Event::LinkAvatarRepaired {
+                link_id: link.get_object_id("_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+                avatar: candidate,
+            })
+            .await;
+            return;
+        }
+        warn!("[友链头像] 未找到可用的备用头像: {}", avatar_url);
+    }
+
     /// 获取缓存 key（URL hash + format）
     fn get_cache_key(&self, url: &str, format: &str) -> String {
         use sha2::{Digest, Sha256};
+        // 同一头像的不同写法（大小写、默认端口、跟踪参数等）共用缓存
+        let canonical = canonicalize(url).unwrap_or_else(|_| url.to_string());
         let mut hasher = Sha256::new();
-        hasher.update(url.as_bytes());
+        hasher.update(canonical.as_bytes());
         let hash = format!("{:x}", hasher.finalize());
         format!("{}_{}", &hash[..16], format)
     }
 
-    /// 根据 Accept 头确定最佳格式
+    /// 根据 Accept 头确定最佳格式（与壁纸、头像一致）
     fn get_preferred_format(&self, accept_header: &str) -> ImageFormat {
-        if accept_header.contains("image/avif") {
-            ImageFormat::Avif
-        } else if accept_header.contains("image/webp") {
-            ImageFormat::WebP
-        } else {
-            ImageFormat::Jpeg
-        }
+        ImageService::preferred_format(accept_header)
     }
 
     /// SSRF 防护：校验 URL 是否安全
-    fn validate_url(url: &str) -> Result<()> {
+    pub(crate) fn validate_url(url: &str) -> Result<()> {
         let parsed = url::Url::parse(url)
             .map_err(|_| Error::BadRequest(format!("Invalid URL: {}", url)))?;
 
@@ -422,8 +494,8 @@ impl FriendAvatarService {
             .host_str()
             .ok_or_else(|| Error::BadRequest("URL missing host".to_string()))?;
 
-        // 拒绝 localhost 和常见本地别名
-        let lower_host = host.to_ascii_lowercase();
+        // 拒绝 localhost 和常见本地别名（按规范化主机名比较，避免 LOCALHOST.、IDN 等写法绕过）
+        let lower_host = canonical_host(url).unwrap_or_else(|| host.to_ascii_lowercase());
         if lower_host == "localhost"
             || lower_host == "127.0.0.1"
             || lower_host == "[::1]"
@@ -461,12 +533,11 @@ impl FriendAvatarService {
         Ok(())
     }
 
-    /// 克隆用于后台任务（共享 updating 集合）
+    /// 克隆用于后台任务
     fn clone_for_background(&self) -> Self {
         Self {
             client: self.client.clone(),
             cache_dir: self.cache_dir.clone(),
-            updating: Arc::clone(&self.updating),
         }
     }
 }
@@ -0,0 +1,186 @@
+use crate::config::settings::GithubLinksConfig;
+use crate::services::db_service;
+use crate::services::link_service::LinkService;
+use crate::services::upstream_service;
+use crate::{Error, Result};
+use chrono::{SecondsFormat, Utc};
+use log::{info, warn};
+use mongodb::bson::doc;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const GITHUB_API: &str = "https://api.github.com";
+/// 已处理的 issue（唯一索引 github_issues_key），重启后也不会重复回复
+const ISSUES_COLLECTION: &str = "github_issues";
+/// 每次轮询读取的 issue 数量
+const PAGE_SIZE: u32 = 50;
+
+/// GitHub API 请求的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// 解析失败的 issue（按正文区分），正文被修改前不再重复解析和回复
+static REJECTED: Lazy<Cache<String, ()>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1_000)
+        .time_to_live(Duration::from_secs(7 * 24 * 60 * 60))
+        .build()
+});
+
+/// 一个 issue 的导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestOutcome {
+    pub issue: u64,
+    pub link_id: String,
+    /// 是否新建了友链（相同地址已存在时为 false）
+    pub created: bool,
+}
+
+/// 启动定时导入任务（未配置仓库或轮询间隔为 0 时不启动）
+pub fn start_poller(config: GithubLinksConfig) -> Option<JoinHandle<()>> {
+    if config.repo.is_empty() || config.poll_interval_secs == 0 {
+        return None;
+    }
+    info!("已启用 GitHub 友链申请导入：{}（标签 {}）", config.repo, config.label);
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = poll(&config).await {
+                warn!("GitHub link ingestion failed: {}", e);
+            }
+        }
+    }))
+}
+
+/// 读取仓库中带标签的打开 issue 并逐个导入
+pub async fn poll(config: &GithubLinksConfig) -> Result<Vec<IngestOutcome>> {
+    let url = format!(
+        "{}/repos/{}/issues?state=open&labels={}&per_page={}",
+        GITHUB_API,
+        config.repo,
+        urlencoding::encode(&config.label),
+        PAGE_SIZE
+    );
+    let issues: Vec<Value> = request(config, upstream_service::http().client().get(&url))
+        .send()
+        .await
+        .map_err(|e| Error::Internal(format!("GitHub request failed: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::Internal(format!("GitHub request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::Internal(format!("Invalid GitHub response: {}", e)))?;
+
+    let mut outcomes = Vec::new();
+    for issue in &issues {
+        match ingest_issue(config, issue).await {
+            Ok(Some(outcome)) => outcomes.push(outcome),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to ingest issue #{}: {}", issue["number"], e),
+        }
+    }
+    Ok(outcomes)
+}
+
+/// 导入单个 issue / PR（来自轮询或 webhook），没有对应标签时返回 None
+pub async fn ingest_issue(config: &GithubLinksConfig, issue: &Value) -> Result<Option<IngestOutcome>> {
+    let labelled = issue["labels"]
+        .as_array()
+        .is_some_and(|labels| labels.iter().any(|l| l["name"].as_str() == Some(config.label.as_str())));
+    if !labelled || issue["state"].as_str() == Some("closed") {
+        return Ok(None);
+    }
+    let number = issue["number"]
+        .as_u64()
+        .ok_or_else(|| Error::BadRequest("Issue number missing".into()))?;
+    // 不关闭 issue 时每次轮询都会再次读到它，已处理过的直接跳过
+    let issue_key = format!("{}#{}", config.repo, number);
+    if db_service::find_one(ISSUES_COLLECTION, doc! { "issue": &issue_key }).await?.is_some() {
+        return Ok(None);
+    }
+
+    // 回复评论也会更新 updated_at，这里按正文内容区分
+    let body = issue["body"].as_str().unwrap_or_default();
+    let revision = format!("{}#{}:{:x}", config.repo, number, md5::compute(body.as_bytes()));
+    if REJECTED.contains_key(&revision) {
+        return Ok(None);
+    }
+
+    let mut submission = match LinkService::parse_submission(body) {
+        Ok(submission) => submission,
+        Err(e) => {
+            REJECTED.insert(revision, ()).await;
+            let message = format!("无法解析友链信息：{}\n\n请在正文中使用 YAML 或 JSON 代码块填写 name、url 等字段。", e);
+            reply(config, number, &message, false).await;
+            return Err(e);
+        }
+    };
+    submission.source = format!("github:{}#{}", config.repo, number);
+    let (link_id, created) = LinkService::submit(submission).await?;
+
+    // 轮询和 webhook 可能同时处理同一 issue，只有写入记录成功的一方回复
+    let record = doc! {
+        "issue": &issue_key,
+        "link_id": &link_id,
+        "created_at": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    match db_service::insert_one(ISSUES_COLLECTION, record).await {
+        Ok(_) => {}
+        Err(Error::Conflict(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let message = if created {
+        "已收到友链申请，审核通过后会显示在友链页面。"
+    } else {
+        "该站点已在友链列表中（或已有待审核的申请）。"
+    };
+    reply(config, number, message, config.close_issues).await;
+    Ok(Some(IngestOutcome {
+        issue: number,
+        link_id,
+        created,
+    }))
+}
+
+// 回复并按需关闭 issue（未配置 token 时跳过，失败只记录日志）
+async fn reply(config: &GithubLinksConfig, number: u64, message: &str, close: bool) {
+    if config.token.as_deref().is_none_or(str::is_empty) {
+        return;
+    }
+    let issue_url = format!("{}/repos/{}/issues/{}", GITHUB_API, config.repo, number);
+    let comment = request(config, upstream_service::http().client().post(format!("{}/comments", issue_url)))
+        .json(&json!({ "body": message }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    if let Err(e) = comment {
+        warn!("Failed to comment on issue #{}: {}", number, e);
+        return;
+    }
+    if close {
+        let closed = request(config, upstream_service::http().client().patch(&issue_url))
+            .json(&json!({ "state": "closed" }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(e) = closed {
+            warn!("Failed to close issue #{}: {}", number, e);
+        }
+    }
+}
+
+fn request(config: &GithubLinksConfig, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    let builder = builder
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28");
+    match config.token.as_deref().filter(|t| !t.is_empty()) {
+        Some(token) => builder.bearer_auth(token),
+        None => builder,
+    }
+}
@@ -0,0 +1,333 @@
+use crate::services::link_service::{CountEntry, LinkService};
+use crate::services::ncm_service;
+use crate::services::search_service::SearchService;
+use crate::services::stats_service::StatsService;
+use crate::services::user_service::UserService;
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Json, Object, Result, Schema, SimpleObject, ID};
+use mongodb::bson::{Bson, Document};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 查询最大嵌套深度
+const MAX_DEPTH: usize = 8;
+/// 查询最大复杂度（每个字段计 1，开销较大的字段单独加权）
+const MAX_COMPLEXITY: usize = 500;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn build_schema() -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// 请求者身份：未携带管理员令牌或会话时只能看到已公开的友链，且不能查询提交者
+#[derive(Debug, Clone, Copy)]
+pub struct Viewer {
+    pub admin: bool,
+}
+
+/// 为单个请求附加 DataLoader（批量加载结果只在本次请求内缓存）和请求者身份
+pub fn with_loaders(request: async_graphql::Request, viewer: Viewer) -> async_graphql::Request {
+    request
+        .data(DataLoader::new(LinkLoader, tokio::spawn))
+        .data(DataLoader::new(UserLoader, tokio::spawn))
+        .data(viewer)
+}
+
+fn is_admin(ctx: &Context<'_>) -> bool {
+    ctx.data_opt::<Viewer>().is_some_and(|v| v.admin)
+}
+
+/// 对请求者可见的友链
+fn visible(ctx: &Context<'_>, link: Document) -> Option<Link> {
+    (is_admin(ctx) || LinkService::is_public(&link)).then_some(Link(link))
+}
+
+/// 按 ID 批量加载友链
+pub struct LinkLoader;
+
+impl Loader<String> for LinkLoader {
+    type Value = Document;
+    type Error = Arc<crate::Error>;
+
+    async fn load(&self, keys: &[String]) -> std::result::Result<HashMap<String, Document>, Self::Error> {
+        let links = LinkService::find_links(keys).await.map_err(Arc::new)?;
+        Ok(links.into_iter().map(|d| (object_id(&d), d)).collect())
+    }
+}
+
+/// 按 QQ OpenID 批量加载用户公开资料
+pub struct UserLoader;
+
+impl Loader<String> for UserLoader {
+    type Value = Document;
+    type Error = Arc<crate::Error>;
+
+    async fn load(&self, keys: &[String]) -> std::result::Result<HashMap<String, Document>, Self::Error> {
+        let users = UserService::profiles(keys).await.map_err(Arc::new)?;
+        Ok(users
+            .into_iter()
+            .map(|d| (d.get_str("qq_openid").unwrap_or_default().to_string(), d))
+            .collect())
+    }
+}
+
+fn object_id(d: &Document) -> String {
+    match d.get("_id") {
+        Some(Bson::ObjectId(oid)) => oid.to_hex(),
+        _ => String::new(),
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// 按 ID 查询友链
+    async fn link(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Link>> {
+        let loader = ctx.data_unchecked::<DataLoader<LinkLoader>>();
+        Ok(loader.load_one(id.to_string()).await?.and_then(|d| visible(ctx, d)))
+    }
+
+    /// 按 ID 批量查询友链（不存在或不可见的 ID 会被忽略）
+    #[graphql(complexity = "ids.len().saturating_mul(child_complexity)")]
+    async fn links(&self, ctx: &Context<'_>, ids: Vec<ID>) -> Result<Vec<Link>> {
+        let keys: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        let mut found = ctx.data_unchecked::<DataLoader<LinkLoader>>().load_many(keys.clone()).await?;
+        Ok(keys
+            .iter()
+            .filter_map(|k| found.remove(k))
+            .filter_map(|d| visible(ctx, d))
+            .collect())
+    }
+
+    /// 全文搜索友链
+    // 复杂度按实际生效的每页条数（1-50）计算，避免客户端传入超大值时溢出
+    #[graphql(complexity = "(per_page.unwrap_or(20).clamp(1, 50) as usize).saturating_mul(child_complexity).saturating_add(5)")]
+    async fn search(&self, q: String, page: Option<u64>, per_page: Option<u64>) -> Result<LinkSearch> {
+        let results = SearchService::search(&q, page.unwrap_or(1), per_page.unwrap_or(20)).await?;
+        Ok(LinkSearch {
+            total: results.links.total,
+            hits: results
+                .links
+                .items
+                .into_iter()
+                .map(|hit| LinkHit {
+                    id: hit.id,
+                    score: hit.score,
+                    snippet: hit.snippet,
+                })
+                .collect(),
+        })
+    }
+
+    /// 站点统计
+    #[graphql(complexity = 10)]
+    async fn stats(&self) -> Result<SiteStats> {
+        let stats = StatsService::site_stats().await?;
+        Ok(SiteStats {
+            links_total: stats.counts.links.total,
+            links_by_state: stats
+                .counts
+                .links
+                .by_state
+                .into_iter()
+                .map(|(state, count)| StateCount { state, count })
+                .collect(),
+            users: stats.counts.users,
+            cache_bytes: stats.counts.cache.total_bytes,
+            uptime_secs: stats.uptime_secs,
+            total_requests: stats.total_requests,
+        })
+    }
+
+    /// 用户公开资料
+    async fn user(&self, ctx: &Context<'_>, openid: String) -> Result<Option<User>> {
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        Ok(loader.load_one(openid).await?.map(User))
+    }
+
+    /// 网易云音乐正在播放（与 /status/ncm 相同的数据）
+    #[graphql(complexity = 10)]
+    async fn now_playing(&self, user_id: u64) -> Result<Json<serde_json::Value>> {
+        ncm_service::get_ncm_now_play(user_id)
+            .await
+            .map(Json)
+            .map_err(|e| async_graphql::Error::new(format!("ncm request failed: {}", e)))
+    }
+}
+
+pub struct Link(Document);
+
+#[Object]
+impl Link {
+    async fn id(&self) -> ID {
+        ID(object_id(&self.0))
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.get_str("name").ok()
+    }
+
+    async fn url(&self) -> Option<&str> {
+        self.0.get_str("url").ok()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.get_str("description").ok()
+    }
+
+    async fn avatar(&self) -> Option<&str> {
+        self.0.get_str("avatar").ok()
+    }
+
+    async fn tags(&self) -> Vec<&str> {
+        self.0
+            .get_array("tags")
+            .map(|tags| tags.iter().filter_map(Bson::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// 提交该友链的用户（未记录或已注销时为 null，仅管理员可查询）
+    async fn submitted_by(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        if !is_admin(ctx) {
+            return Err("submittedBy requires admin authentication".into());
+        }
+        let Ok(openid) = self.0.get_str("submitted_by") else {
+            return Ok(None);
+        };
+        let loader = ctx.data_unchecked::<DataLoader<UserLoader>>();
+        Ok(loader.load_one(openid.to_string()).await?.map(User))
+    }
+
+    /// 最近 `days` 天的点击统计
+    #[graphql(complexity = "10 + child_complexity")]
+    async fn click_stats(&self, #[graphql(default = 30)] days: u32) -> Result<ClickStats> {
+        let stats = LinkService::stats(&object_id(&self.0), days.min(365)).await?;
+        Ok(ClickStats {
+            total: stats.total,
+            daily: stats.daily.into_iter().map(ClickCount::from).collect(),
+            referrers: stats.referrers.into_iter().map(ClickCount::from).collect(),
+            countries: stats.countries.into_iter().map(ClickCount::from).collect(),
+        })
+    }
+}
+
+pub struct User(Document);
+
+#[Object]
+impl User {
+    async fn id(&self) -> ID {
+        ID(object_id(&self.0))
+    }
+
+    async fn nickname(&self) -> Option<&str> {
+        self.0.get_str("nickname").ok()
+    }
+
+    async fn avatar(&self) -> Option<&str> {
+        self.0.get_str("avatar").ok()
+    }
+
+    async fn gender(&self) -> Option<&str> {
+        self.0.get_str("gender").ok()
+    }
+
+    async fn created_at(&self) -> Option<&str> {
+        self.0.get_str("created_at").ok()
+    }
+}
+
+pub struct LinkHit {
+    id: String,
+    score: f64,
+    snippet: Option<String>,
+}
+
+#[Object]
+impl LinkHit {
+    /// 文本相关度
+    async fn score(&self) -> f64 {
+        self.score
+    }
+
+    async fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+
+    async fn link(&self, ctx: &Context<'_>) -> Result<Option<Link>> {
+        let loader = ctx.data_unchecked::<DataLoader<LinkLoader>>();
+        Ok(loader.load_one(self.id.clone()).await?.and_then(|d| visible(ctx, d)))
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct LinkSearch {
+    total: i64,
+    hits: Vec<LinkHit>,
+}
+
+#[derive(SimpleObject)]
+pub struct ClickStats {
+    total: i64,
+    daily: Vec<ClickCount>,
+    referrers: Vec<ClickCount>,
+    countries: Vec<ClickCount>,
+}
+
+#[derive(SimpleObject)]
+pub struct ClickCount {
+    key: String,
+    clicks: i64,
+}
+
+impl From<CountEntry> for ClickCount {
+    fn from(e: CountEntry) -> Self {
+        Self {
+            key: e.key,
+            clicks: e.clicks,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct StateCount {
+    state: String,
+    count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct SiteStats {
+    links_total: i64,
+    links_by_state: Vec<StateCount>,
+    users: i64,
+    cache_bytes: u64,
+    uptime_secs: u64,
+    total_requests: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_limits() {
+        let schema = build_schema();
+        let deep = "{ search(q: \"rust\") { hits { link { submittedBy { id } } } } }";
+        let response = schema.execute(with_loaders(deep.into(), Viewer { admin: true })).await;
+        // 深度未超限，只会因没有数据库连接而失败
+        assert!(response.errors.iter().all(|e| !e.message.contains("depth")));
+
+        let costly = "{ search(q: \"rust\", perPage: 50) { hits { link { clickStats { total } } } } }";
+        let response = schema.execute(with_loaders(costly.into(), Viewer { admin: true })).await;
+        assert!(response.errors.iter().any(|e| e.message.contains("complex")));
+
+        // 超大 perPage 不应在计算复杂度时溢出
+        let huge = "{ search(q: \"rust\", perPage: 18446744073709551615) { total } }";
+        let response = schema.execute(with_loaders(huge.into(), Viewer { admin: false })).await;
+        assert!(response.errors.iter().all(|e| !e.message.contains("complex")));
+    }
+}
@@ -1,55 +1,360 @@
-use crate::utils::cache;
+use crate::config::settings::{CacheWarmupConfig, TranscodeConfig, WallpaperPregenConfig};
+use crate::services::avatar_service;
+use crate::services::mock_upstream;
+use crate::services::upstream_service::{self, HttpClientService};
+use crate::services::wallpaper_service::{self, WallpaperService};
+use crate::utils::cache::{self, Namespace};
+use crate::utils::negotiate;
+use crate::utils::request_context::RequestContext;
+use crate::utils::single_flight::SingleFlight;
+use crate::utils::transcode_limiter;
+use crate::utils::url::canonical_host;
 use crate::{Error, Result};
-use image::ImageFormat;
-use log::{debug, info};
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::{WebPDecoder, WebPEncoder};
+use image::imageops::{self, FilterType};
+use image::metadata::Orientation as ExifOrientation;
+use image::{
+    AnimationDecoder, DynamicImage, Frame, Frames, ImageDecoder, ImageEncoder, ImageFormat, ImageReader, ImageResult,
+    RgbImage,
+};
+use log::{debug, info, warn};
+use once_cell::sync::{Lazy, OnceCell};
 use reqwest::Client;
+use sha2::{Digest, Sha256};
 use std::io::Cursor;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 拼图时每批并发下载的壁纸数量
+const SPRITE_BATCH: usize = 8;
+/// 壁纸处理后的最大宽高（像素）
+pub const MAX_DIMENSION: u32 = 4096;
+
+/// 重新编码的动图最多帧数和总像素数（所有帧合计），超过时原样返回
+const MAX_ANIMATION_FRAMES: usize = 500;
+const MAX_ANIMATION_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// 动图（GIF / 动态 WebP）的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnimatedMode {
+    /// 原样返回，不缩放也不转换格式
+    #[default]
+    Passthrough,
+    /// 逐帧缩放后重新编码为 GIF（保留全部帧和帧间隔）
+    Reencode,
+}
+
+impl AnimatedMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "passthrough" => Some(Self::Passthrough),
+            "reencode" => Some(Self::Reencode),
+            _ => None,
+        }
+    }
+}
+
+static ANIMATED_MODE: OnceCell<AnimatedMode> = OnceCell::new();
+static STRIP_METADATA: OnceCell<bool> = OnceCell::new();
+// 壁纸预生成的格式（预生成启用时设置，新上传的壁纸按同样的格式生成）
+static PREGEN_FORMATS: OnceCell<Vec<ImageFormat>> = OnceCell::new();
+
+/// 初始化动图处理方式和元数据去除（启动时调用一次）
+pub fn init(config: &TranscodeConfig) {
+    let mode = AnimatedMode::parse(&config.animated).unwrap_or_else(|| {
+        warn!("Unknown transcode.animated mode {}, using passthrough", config.animated);
+        AnimatedMode::default()
+    });
+    let _ = ANIMATED_MODE.set(mode);
+    let _ = STRIP_METADATA.set(config.strip_metadata);
+}
+
+fn animated_mode() -> AnimatedMode {
+    ANIMATED_MODE.get().copied().unwrap_or_default()
+}
+
+/// 转码时是否默认去除 EXIF / XMP / ICC 元数据（未初始化时去除）
+fn strip_metadata() -> bool {
+    STRIP_METADATA.get().copied().unwrap_or(true)
+}
+
+/// 转码输出中保留的原图元数据，默认为空（全部去除）
+///
+/// XMP 没有编码器支持写入，始终去除；AVIF 输出不支持写入任何元数据
+#[derive(Debug, Clone, Default)]
+pub struct ImageMetadata {
+    pub icc_profile: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    fn is_empty(&self) -> bool {
+        self.icc_profile.is_none() && self.exif.is_none()
+    }
+
+    /// 写入编码器（格式不支持时忽略）
+    fn attach(self, encoder: &mut impl ImageEncoder) {
+        if let Some(icc_profile) = self.icc_profile {
+            let _ = encoder.set_icc_profile(icc_profile);
+        }
+        if let Some(exif) = self.exif {
+            let _ = encoder.set_exif_metadata(exif);
+        }
+    }
+}
+
+// 进行中的头像下载（按 URL）和壁纸编码（按缓存 key），冷缓存下的并发请求只访问一次上游
+static AVATAR_DOWNLOADS: Lazy<SingleFlight<Vec<u8>>> = Lazy::new(SingleFlight::new);
+static WALLPAPER_FLIGHTS: Lazy<SingleFlight<Vec<u8>>> = Lazy::new(SingleFlight::new);
+
+/// 壁纸拼图（contact sheet）布局
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteLayout {
+    pub cols: u32,
+    pub rows: u32,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub count: u32,
+    /// 下载或解码失败、留空的格子数（不为 0 时拼图不写入缓存）
+    pub missing: u32,
+}
+
+/// 一轮壁纸预生成的结果（按变体计数）
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PregenReport {
+    pub generated: u32,
+    /// 已有未过期缓存，无需生成
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+/// 同时指定宽高时的缩放方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fit {
+    /// 等比缩放到不超过指定宽高（默认）
+    #[default]
+    Contain,
+    /// 等比缩放并居中裁剪，填满指定宽高
+    Cover,
+}
+
+impl Fit {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "contain" => Some(Fit::Contain),
+            "cover" => Some(Fit::Cover),
+            _ => None,
+        }
+    }
+}
+
+/// 头像形状：居中裁剪为正方形，形状以外透明
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Circle,
+    /// 圆角正方形（圆角半径为边长的 ROUNDED_RADIUS）
+    Rounded,
+}
+
+/// 圆角正方形的圆角半径与边长之比
+const ROUNDED_RADIUS: f32 = 0.2;
+
+impl Shape {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "circle" => Some(Shape::Circle),
+            "rounded" => Some(Shape::Rounded),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Circle => "circle",
+            Shape::Rounded => "rounded",
+        }
+    }
+}
+
+/// 壁纸处理参数：缩放尺寸、缩放方式和编码质量，处理结果按参数分别缓存
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageTransform {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 编码质量（1-100，仅 JPEG 使用；WebP 为无损编码，AVIF 使用编码器默认值）
+    pub quality: Option<u8>,
+    pub fit: Fit,
+    /// 保留原图的 ICC 色彩配置和 EXIF（默认按 transcode.strip_metadata 去除）
+    pub keep_metadata: bool,
+    /// 缩放后按形状裁剪（需输出支持透明的格式）
+    pub shape: Option<Shape>,
+}
+
+impl ImageTransform {
+    /// 只限制宽度（按比例缩小）
+    pub fn width(width: Option<u32>) -> Self {
+        Self {
+            width,
+            ..Self::default()
+        }
+    }
+
+    /// 宽高限制在 1..=MAX_DIMENSION，质量限制在 1..=100
+    pub fn clamped(self) -> Self {
+        let dimension = |v: Option<u32>| v.filter(|&v| v > 0).map(|v| v.min(MAX_DIMENSION));
+        Self {
+            width: dimension(self.width),
+            height: dimension(self.height),
+            quality: self.quality.map(|q| q.clamp(1, 100)),
+            fit: self.fit,
+            keep_metadata: self.keep_metadata,
+            shape: self.shape,
+        }
+    }
+
+    /// 输出是否保留原图元数据
+    pub fn keeps_metadata(&self) -> bool {
+        self.keep_metadata || !strip_metadata()
+    }
+
+    /// 缓存 key 中的参数部分（未指定的参数不出现，只有宽度时与之前的 key 相同；质量只对 JPEG 生效，其他格式不区分）
+    fn cache_suffix(&self, format: ImageFormat) -> String {
+        let mut suffix = String::new();
+        if let Some(width) = self.width {
+            suffix.push_str(&format!(":w{}", width));
+        }
+        if let Some(height) = self.height {
+            suffix.push_str(&format!(":h{}", height));
+        }
+        if self.fit == Fit::Cover && self.width.is_some() && self.height.is_some() {
+            suffix.push_str(":cover");
+        }
+        if let Some(quality) = self.quality.filter(|_| format == ImageFormat::Jpeg) {
+            suffix.push_str(&format!(":q{}", quality));
+        }
+        if self.keeps_metadata() {
+            suffix.push_str(":meta");
+        }
+        if let Some(shape) = self.shape {
+            suffix.push_str(&format!(":{}", shape.name()));
+        }
+        suffix
+    }
+
+    /// 按参数缩放图片（不放大），指定形状时再裁剪
+    pub fn apply(&self, img: DynamicImage) -> DynamicImage {
+        let img = self.resize(img);
+        match self.shape {
+            Some(shape) => ImageService::mask(img, shape),
+            None => img,
+        }
+    }
+
+    fn resize(&self, img: DynamicImage) -> DynamicImage {
+        let (img_width, img_height) = (img.width(), img.height());
+        match (self.width, self.height) {
+            (None, None) => img,
+            (Some(width), Some(height)) if self.fit == Fit::Cover => {
+                // 目标尺寸超出原图时等比缩小，保持裁剪比例
+                let scale = (img_width as f64 / width as f64)
+                    .min(img_height as f64 / height as f64)
+                    .min(1.0);
+                let width = ((width as f64 * scale).round() as u32).max(1);
+                let height = ((height as f64 * scale).round() as u32).max(1);
+                if (width, height) == (img_width, img_height) {
+                    img
+                } else {
+                    img.resize_to_fill(width, height, FilterType::Lanczos3)
+                }
+            }
+            (width, height) => {
+                let width = width.unwrap_or(u32::MAX);
+                let height = height.unwrap_or(u32::MAX);
+                if img_width <= width && img_height <= height {
+                    img
+                } else {
+                    img.resize(width, height, FilterType::Lanczos3)
+                }
+            }
+        }
+    }
+}
 
 pub struct ImageService {
     client: Client,
 }
 
 impl ImageService {
-    pub fn new() -> Self {
+    pub fn new(http: &HttpClientService) -> Self {
         Self {
-            client: Client::new(),
+            client: http.client().clone(),
         }
     }
 
     /// 壁纸服务：按格式缓存编码后的图片
     /// 
     /// 缓存策略：
-    /// - 缓存 key = url + format (如 avif/webp/jpeg)
+    /// - 缓存 key = wallpaper: + url + 处理参数 + format (如 avif/webp/jpeg)，过期时间和容量见 cache.wallpapers
     /// - 有缓存：直接返回编码后的数据，无需任何处理
-    /// - 无缓存：下载原图 -> 编码为目标格式 -> 缓存编码结果 -> 返回
+    /// - 无缓存：下载原图 -> 按参数缩放并编码为目标格式 -> 缓存编码结果 -> 返回
     /// 
     /// 这样避免了重复的图片解码/编码操作，大幅降低内存占用
-    pub async fn fetch_wallpaper(&self, url: &str, accept_header: &str) -> Result<(Vec<u8>, ImageFormat)> {
+    ///
+    /// 已超过请求时限时不再开始下载或编码，返回 504（缓存命中仍直接返回）
+    ///
+    /// 返回 (编码后的数据, 格式, 是否命中缓存)
+    pub async fn fetch_wallpaper(
+        &self,
+        ctx: &RequestContext,
+        url: &str,
+        accept_header: &str,
+        transform: ImageTransform,
+    ) -> Result<(Vec<u8>, ImageFormat, bool)> {
         // 1. 确定目标格式：avif > webp > jpeg
         let format = self.get_preferred_format(accept_header);
         let format_ext = Self::format_extension(format);
         
-        // 2. 缓存 key = url + format
-        let cache_key = format!("{}:{}", url, format_ext);
+        // 2. 缓存 key = url + 处理参数 + format
+        let transform = transform.clamped();
+        let cache_key = Self::wallpaper_cache_key(url, &transform, format);
         
         // 3. 检查硬盘缓存（编码后的数据）
         if let Some(cached_data) = cache::get_disk(&cache_key) {
             debug!("Wallpaper cache hit: {} ({} bytes)", format_ext, cached_data.len());
-            return Ok((cached_data, format));
+            return Ok((cached_data, format, true));
         }
         
-        // 4. 无缓存：下载原图
+        // 4. 无缓存：下载原图并编码（相同缓存 key 的并发请求共享一次下载和编码）
+        ctx.check("wallpaper download")?;
+        let encoded_bytes = WALLPAPER_FLIGHTS
+            .run(&cache_key, || self.encode_wallpaper(ctx, url, format, transform, &cache_key))
+            .await?;
+        Ok((encoded_bytes, format, false))
+    }
+
+    async fn encode_wallpaper(
+        &self,
+        ctx: &RequestContext,
+        url: &str,
+        format: ImageFormat,
+        transform: ImageTransform,
+        cache_key: &str,
+    ) -> Result<Vec<u8>> {
+        let format_ext = Self::format_extension(format);
         info!("Wallpaper cache miss, downloading: {}", url);
-        let raw_bytes = self.download_image(url).await?;
+        let raw_bytes = self.download_wallpaper(url).await?;
         let raw_len = raw_bytes.len();
+        ctx.check("wallpaper transcode")?;
         
-        // 5. 在阻塞线程中处理图片（解码+编码），避免阻塞 async runtime
-        let encoded_bytes = tokio::task::spawn_blocking(move || {
-            Self::encode_image_blocking(&raw_bytes, format)
+        // 5. 在阻塞线程中处理图片（解码+编码），避免阻塞 async runtime；同时进行的处理数受限，繁忙时返回 503
+        let encoded_bytes = transcode_limiter::run(move || {
+            Self::process_image(&raw_bytes, format, &transform)
             // raw_bytes 在这里被消费并释放
         })
-        .await
-        .map_err(|e| Error::Internal(format!("Task join error: {}", e)))??;
+        .await??;
         
         let encoded_len = encoded_bytes.len();
         debug!("Wallpaper encoded: {} -> {} bytes ({})", raw_len, encoded_len, format_ext);
@@ -57,7 +362,7 @@ impl ImageService {
         // 6. 异步写入硬盘缓存（编码后的数据，使用 Arc 避免深拷贝）
         let bytes_arc = std::sync::Arc::new(encoded_bytes);
         {
-            let cache_key_clone = cache_key;
+            let cache_key_clone = cache_key.to_string();
             let bytes_for_cache = std::sync::Arc::clone(&bytes_arc);
             tokio::task::spawn_blocking(move || {
                 cache::put_disk(&cache_key_clone, &bytes_for_cache);
@@ -68,15 +373,235 @@ impl ImageService {
         // 7. 返回编码后的数据（通过 Arc::try_unwrap 避免额外 clone）
         let encoded_bytes = std::sync::Arc::try_unwrap(bytes_arc)
             .unwrap_or_else(|arc| (*arc).clone());
-        Ok((encoded_bytes, format))
+        Ok(encoded_bytes)
+    }
+
+    fn digest_cache_key(url: &str) -> String {
+        format!("wallpaper:digest:{}", url)
+    }
+
+    /// 已记录的原图 SHA-256（十六进制）和字节数（原图下载过才有，不会为此下载原图）
+    pub fn cached_digest(url: &str) -> Option<(String, u64)> {
+        let cached = cache::get_disk(&Self::digest_cache_key(url))?;
+        let (hash, size) = std::str::from_utf8(&cached).ok()?.split_once(':')?;
+        Some((hash.to_string(), size.parse().ok()?))
+    }
+
+    /// 下载壁纸原图，同时记录其摘要（供 ?type=json 使用）
+    async fn download_wallpaper(&self, url: &str) -> Result<Vec<u8>> {
+        let bytes = self.download_image(url).await?;
+        let digest = format!("{}:{}", hex::encode(Sha256::digest(&bytes)), bytes.len());
+        cache::put_disk(&Self::digest_cache_key(url), digest.as_bytes());
+        Ok(bytes)
+    }
+
+    fn wallpaper_cache_key(url: &str, transform: &ImageTransform, format: ImageFormat) -> String {
+        Namespace::Wallpapers.derived_key(format_args!(
+            "{}{}:{}",
+            url,
+            transform.cache_suffix(format),
+            Self::format_extension(format)
+        ))
+    }
+
+    /// 为壁纸预先生成各格式的编码结果并写入硬盘缓存（与 fetch_wallpaper 使用相同的缓存 key）
+    ///
+    /// 已有未过期缓存的变体跳过；每张原图只下载和解码一次，逐张处理以限制 CPU 和内存占用
+    pub async fn pregenerate_wallpapers(&self, urls: &[String], formats: &[ImageFormat]) -> PregenReport {
+        let mut report = PregenReport::default();
+        for url in urls {
+            let missing: Vec<ImageFormat> = formats
+                .iter()
+                .copied()
+                .filter(|&format| !cache::has_disk(&Self::wallpaper_cache_key(url, &ImageTransform::default(), format)))
+                .collect();
+            report.skipped += (formats.len() - missing.len()) as u32;
+            if missing.is_empty() {
+                continue;
+            }
+            let pending = missing.len() as u32;
+
+            let raw_bytes = match self.download_wallpaper(url).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!("Failed to download wallpaper {} for pre-generation: {}", url, e);
+                    report.failed += pending;
+                    continue;
+                }
+            };
+
+            let url_owned = url.clone();
+            let encoded = transcode_limiter::run_background(move || {
+                let img = image::load_from_memory(&raw_bytes)
+                    .map_err(|e| Error::Internal(format!("Failed to decode image: {}", e)))?;
+                drop(raw_bytes);
+                let mut generated = 0;
+                for format in missing {
+                    let mut output = Vec::new();
+                    match img.write_to(&mut Cursor::new(&mut output), format) {
+                        Ok(_) => {
+                            cache::put_disk(&Self::wallpaper_cache_key(&url_owned, &ImageTransform::default(), format), &output);
+                            generated += 1;
+                        }
+                        Err(e) => warn!("Failed to encode wallpaper {} as {:?}: {}", url_owned, format, e),
+                    }
+                }
+                Ok::<u32, Error>(generated)
+            })
+            .await;
+
+            match encoded {
+                Ok(Ok(generated)) => {
+                    report.generated += generated;
+                    report.failed += pending - generated;
+                }
+                Ok(Err(e)) | Err(e) => {
+                    warn!("Failed to pre-generate wallpaper {}: {}", url, e);
+                    report.failed += pending;
+                }
+            }
+        }
+        report
+    }
+
+    /// 壁纸拼图：将所有壁纸缩略图按行列拼接为一张图片（供前端图库选择器使用）
+    ///
+    /// 第 i 张壁纸位于第 i / cols 行、第 i % cols 列；单张下载或解码失败时对应格子留空。
+    /// 结果按 壁纸列表 + 布局 + 格式 缓存到硬盘
+    ///
+    /// 返回 (编码后的数据, 格式, 布局, 是否命中缓存)
+    pub async fn fetch_wallpaper_sprite(
+        &self,
+        urls: &[String],
+        cols: u32,
+        cell_width: u32,
+        accept_header: &str,
+    ) -> Result<(Vec<u8>, ImageFormat, SpriteLayout, bool)> {
+        if urls.is_empty() {
+            return Err(Error::NotFound("No wallpapers available".to_string()));
+        }
+
+        let format = self.get_preferred_format(accept_header);
+        let count = urls.len() as u32;
+        let cols = cols.clamp(1, count);
+        let layout = SpriteLayout {
+            cols,
+            rows: count.div_ceil(cols),
+            cell_width,
+            // 壁纸为 16:9 横屏图
+            cell_height: (cell_width * 9 / 16).max(1),
+            count,
+            missing: 0,
+        };
+
+        let mut hasher = Sha256::new();
+        for url in urls {
+            hasher.update(url.as_bytes());
+            hasher.update(b"\n");
+        }
+        let cache_key = Namespace::Wallpapers.derived_key(format_args!(
+            "sprite:{}:{}x{}:{}",
+            hex::encode(&hasher.finalize()[..8]),
+            cols,
+            cell_width,
+            Self::format_extension(format)
+        ));
+        if let Some(cached) = cache::get_disk(&cache_key) {
+            debug!("Wallpaper sprite cache hit: {} bytes", cached.len());
+            return Ok((cached, format, layout, true));
+        }
+
+        info!("Building wallpaper sprite: {} images, {} columns", count, cols);
+        let mut canvas = RgbImage::new(layout.cols * layout.cell_width, layout.rows * layout.cell_height);
+        let mut drawn = 0;
+
+        // 分批下载并在阻塞线程中缩放拼接，避免同时持有所有原图
+        for (batch_index, batch) in urls.chunks(SPRITE_BATCH).enumerate() {
+            let mut tasks = tokio::task::JoinSet::new();
+            for (offset, url) in batch.iter().enumerate() {
+                let client = self.client.clone();
+                let url = url.clone();
+                let index = batch_index * SPRITE_BATCH + offset;
+                tasks.spawn(async move { (index, Self::fetch_raw(&client, &url).await, url) });
+            }
+
+            let mut downloaded = Vec::with_capacity(batch.len());
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok((index, Ok(bytes), _)) => downloaded.push((index as u32, bytes)),
+                    Ok((_, Err(e), url)) => warn!("Skipping wallpaper {} in sprite: {}", url, e),
+                    Err(e) => warn!("Sprite download task failed: {}", e),
+                }
+            }
+
+            let batch_drawn;
+            (canvas, batch_drawn) = transcode_limiter::run(move || {
+                let drawn = downloaded
+                    .into_iter()
+                    .filter(|(index, bytes)| Self::draw_sprite_cell(&mut canvas, &layout, *index, bytes))
+                    .count() as u32;
+                (canvas, drawn)
+            })
+            .await?;
+            drawn += batch_drawn;
+        }
+        let layout = SpriteLayout {
+            missing: count - drawn,
+            ..layout
+        };
+
+        let encoded = transcode_limiter::run(move || {
+            let mut output = Vec::new();
+            image::DynamicImage::ImageRgb8(canvas)
+                .write_to(&mut Cursor::new(&mut output), format)
+                .map(|_| output)
+                .map_err(|e| Error::Internal(format!("Failed to encode sprite: {}", e)))
+        })
+        .await??;
+
+        // 缺少格子的拼图不缓存，下次请求重新下载失败的壁纸
+        if layout.missing == 0 {
+            let encoded = encoded.clone();
+            tokio::task::spawn_blocking(move || cache::put_disk(&cache_key, &encoded));
+        } else {
+            warn!("Wallpaper sprite is missing {} of {} images, not caching", layout.missing, count);
+        }
+        Ok((encoded, format, layout, false))
+    }
+
+    /// 将一张壁纸缩放裁剪后绘制到拼图对应的格子中，无法解码时返回 false
+    fn draw_sprite_cell(canvas: &mut RgbImage, layout: &SpriteLayout, index: u32, bytes: &[u8]) -> bool {
+        let img = match image::load_from_memory(bytes) {
+            Ok(img) => img,
+            Err(e) => {
+                warn!("Skipping undecodable wallpaper #{} in sprite: {}", index, e);
+                return false;
+            }
+        };
+        let thumb = img
+            .resize_to_fill(layout.cell_width, layout.cell_height, FilterType::Triangle)
+            .to_rgb8();
+        let x = (index % layout.cols) * layout.cell_width;
+        let y = (index / layout.cols) * layout.cell_height;
+        imageops::overlay(canvas, &thumb, x as i64, y as i64);
+        true
     }
 
     /// 下载原始图片
-    async fn download_image(&self, url: &str) -> Result<Vec<u8>> {
-        let response = self
-            .client
-            .get(url)
-            .send()
+    pub async fn download_image(&self, url: &str) -> Result<Vec<u8>> {
+        Self::fetch_raw(&self.client, url).await
+    }
+
+    async fn fetch_raw(client: &Client, url: &str) -> Result<Vec<u8>> {
+        // 管理接口上传的壁纸保存在本地
+        if let Some(file) = url.strip_prefix(wallpaper_service::UPLOAD_SCHEME) {
+            return wallpaper_service::read_upload(file).await;
+        }
+        if mock_upstream::is_enabled() {
+            return Ok(mock_upstream::image(url).await);
+        }
+
+        let response = upstream_service::send(client.get(url))
             .await
             .map_err(|e| Error::Internal(format!("Failed to fetch image: {}", e)))?;
 
@@ -95,38 +620,145 @@ impl ImageService {
         Ok(bytes.to_vec())
     }
 
-    /// 阻塞式图片编码（在 spawn_blocking 中调用）
-    fn encode_image_blocking(raw_bytes: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+    /// 阻塞式图片处理（在 spawn_blocking 中调用）：按参数缩放（不放大）后编码为目标格式
+    pub fn process_image(raw_bytes: &[u8], format: ImageFormat, transform: &ImageTransform) -> Result<Vec<u8>> {
         // 解码原图
-        let img = image::load_from_memory(raw_bytes)
+        let (img, metadata) = Self::decode(raw_bytes, format, transform.keeps_metadata())
             .map_err(|e| Error::Internal(format!("Failed to decode image: {}", e)))?;
+        let img = transform.apply(img);
 
         // 编码为目标格式
-        let mut output = Vec::new();
-        img.write_to(&mut Cursor::new(&mut output), format)
+        let output = Self::encode(&img, format, transform.quality, metadata)
             .map_err(|e| Error::Internal(format!("Failed to encode image: {}", e)))?;
 
         // img 在这里被 drop，释放解码后的内存
         Ok(output)
     }
 
-    /// 根据 Accept 头确定最佳格式：avif > webp > jpeg
-    pub fn get_preferred_format(&self, accept_header: &str) -> ImageFormat {
-        if accept_header.contains("image/avif") {
-            ImageFormat::Avif
-        } else if accept_header.contains("image/webp") {
-            ImageFormat::WebP
+    /// 按形状裁剪：居中裁剪为正方形，形状以外透明（边缘按像素覆盖率抗锯齿）
+    pub fn mask(img: DynamicImage, shape: Shape) -> DynamicImage {
+        let side = img.width().min(img.height());
+        let mut square = img
+            .crop_imm((img.width() - side) / 2, (img.height() - side) / 2, side, side)
+            .to_rgba8();
+        let size = side as f32;
+        let radius = match shape {
+            Shape::Circle => size / 2.0,
+            Shape::Rounded => size * ROUNDED_RADIUS,
+        };
+        for (x, y, pixel) in square.enumerate_pixels_mut() {
+            // 像素中心到最近圆角圆心的距离（圆角以外的区域为 0）
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let dx = px - px.clamp(radius, size - radius);
+            let dy = py - py.clamp(radius, size - radius);
+            let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            pixel[3] = (pixel[3] as f32 * coverage).round() as u8;
+        }
+        DynamicImage::ImageRgba8(square)
+    }
+
+    /// 阻塞式解码，返回图片和输出需要保留的元数据
+    ///
+    /// 像素按 EXIF 方向旋转（去除 EXIF 后方向仍然正确），保留的 EXIF 中方向重置为不旋转
+    pub fn decode(raw_bytes: &[u8], format: ImageFormat, keep_metadata: bool) -> ImageResult<(DynamicImage, ImageMetadata)> {
+        let mut decoder = ImageReader::new(Cursor::new(raw_bytes))
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation().unwrap_or(ExifOrientation::NoTransforms);
+        let mut metadata = if keep_metadata && matches!(format, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP) {
+            ImageMetadata {
+                icc_profile: decoder.icc_profile().ok().flatten(),
+                exif: decoder.exif_metadata().ok().flatten(),
+            }
         } else {
-            ImageFormat::Jpeg
+            ImageMetadata::default()
+        };
+
+        if let Some(exif) = metadata.exif.as_mut() {
+            let _ = ExifOrientation::remove_from_exif_chunk(exif);
+        }
+        let mut img = DynamicImage::from_decoder(decoder)?;
+        img.apply_orientation(orientation);
+        Ok((img, metadata))
+    }
+
+    /// 阻塞式编码为目标格式，`metadata` 写入支持的格式（JPEG / PNG / WebP）
+    pub fn encode(
+        img: &DynamicImage,
+        format: ImageFormat,
+        quality: Option<u8>,
+        metadata: ImageMetadata,
+    ) -> ImageResult<Vec<u8>> {
+        let mut output = Vec::new();
+        match format {
+            ImageFormat::Jpeg if quality.is_some() || !metadata.is_empty() => {
+                let mut encoder = match quality {
+                    Some(quality) => JpegEncoder::new_with_quality(&mut output, quality),
+                    None => JpegEncoder::new(&mut output),
+                };
+                metadata.attach(&mut encoder);
+                img.write_with_encoder(encoder)?;
+            }
+            ImageFormat::Png if !metadata.is_empty() => {
+                let mut encoder = PngEncoder::new(&mut output);
+                metadata.attach(&mut encoder);
+                img.write_with_encoder(encoder)?;
+            }
+            ImageFormat::WebP if !metadata.is_empty() => {
+                let mut encoder = WebPEncoder::new_lossless(&mut output);
+                metadata.attach(&mut encoder);
+                img.write_with_encoder(encoder)?;
+            }
+            _ => img.write_to(&mut Cursor::new(&mut output), format)?,
+        }
+        Ok(output)
+    }
+
+    /// 根据 Accept 头确定最佳格式：按质量值选择 avif / webp（相同时 avif 优先），都不接受时为 jpeg
+    pub fn get_preferred_format(&self, accept_header: &str) -> ImageFormat {
+        Self::preferred_format(accept_header)
+    }
+
+    /// 同 get_preferred_format（不需要服务实例的调用方使用）
+    pub fn preferred_format(accept_header: &str) -> ImageFormat {
+        match negotiate::preferred(accept_header, &["image/avif", "image/webp"]) {
+            Some("image/avif") => ImageFormat::Avif,
+            Some(_) => ImageFormat::WebP,
+            None => ImageFormat::Jpeg,
         }
     }
 
+    /// 按扩展名解析格式（get_preferred_format 可能选出的格式）
+    pub fn parse_format(name: &str) -> Option<ImageFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "avif" => Some(ImageFormat::Avif),
+            "webp" => Some(ImageFormat::WebP),
+            "jpeg" | "jpg" => Some(ImageFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// 解析配置中的格式列表，跳过不支持的格式（purpose 用于日志）
+    pub fn parse_formats(names: &[String], purpose: &str) -> Vec<ImageFormat> {
+        names
+            .iter()
+            .filter_map(|name| {
+                let format = Self::parse_format(name);
+                if format.is_none() {
+                    warn!("Ignoring unsupported {} format {}", purpose, name);
+                }
+                format
+            })
+            .collect()
+    }
+
     /// 格式扩展名
     pub fn format_extension(format: ImageFormat) -> &'static str {
         match format {
             ImageFormat::Avif => "avif",
             ImageFormat::WebP => "webp",
             ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
             _ => "jpeg",
         }
     }
@@ -168,10 +800,98 @@ impl ImageService {
         None
     }
 
+    /// 动图的格式（GIF / 动态 WebP），静态图片或无法识别时为 None
+    pub fn animated_format(bytes: &[u8]) -> Option<ImageFormat> {
+        match Self::detect_format(bytes)? {
+            ImageFormat::Gif => {
+                let frames = GifDecoder::new(Cursor::new(bytes)).ok()?.into_frames();
+                (frames.take(2).filter(|f| f.is_ok()).count() == 2).then_some(ImageFormat::Gif)
+            }
+            ImageFormat::WebP => WebPDecoder::new(Cursor::new(bytes))
+                .ok()?
+                .has_animation()
+                .then_some(ImageFormat::WebP),
+            _ => None,
+        }
+    }
+
+    /// 阻塞式动图处理（在 spawn_blocking 中调用）：按 transcode.animated 原样返回，
+    /// 或逐帧缩放后重新编码为 GIF；帧数或像素数超过上限时原样返回
+    ///
+    /// 返回 (图片数据, 实际格式)
+    pub fn process_animated(
+        raw_bytes: Vec<u8>,
+        source_format: ImageFormat,
+        transform: &ImageTransform,
+    ) -> Result<(Vec<u8>, ImageFormat)> {
+        Self::process_animated_with(raw_bytes, source_format, transform, animated_mode())
+    }
+
+    fn process_animated_with(
+        raw_bytes: Vec<u8>,
+        source_format: ImageFormat,
+        transform: &ImageTransform,
+        mode: AnimatedMode,
+    ) -> Result<(Vec<u8>, ImageFormat)> {
+        if mode == AnimatedMode::Passthrough {
+            return Ok((raw_bytes, source_format));
+        }
+
+        match Self::reencode_animation(&raw_bytes, source_format, transform)? {
+            Some(output) => Ok((output, ImageFormat::Gif)),
+            None => {
+                debug!("Animation too large to re-encode, passing through");
+                Ok((raw_bytes, source_format))
+            }
+        }
+    }
+
+    /// 逐帧缩放并编码为 GIF，帧数或像素数超过上限时返回 None
+    fn reencode_animation(
+        raw_bytes: &[u8],
+        source_format: ImageFormat,
+        transform: &ImageTransform,
+    ) -> Result<Option<Vec<u8>>> {
+        let decode_error = |e: image::ImageError| Error::Internal(format!("Failed to decode animation: {}", e));
+        let frames: Frames = match source_format {
+            ImageFormat::Gif => GifDecoder::new(Cursor::new(raw_bytes)).map_err(decode_error)?.into_frames(),
+            ImageFormat::WebP => WebPDecoder::new(Cursor::new(raw_bytes)).map_err(decode_error)?.into_frames(),
+            _ => return Err(Error::Internal("Unsupported animation format".into())),
+        };
+
+        let (mut resized, mut pixels) = (Vec::new(), 0u64);
+        for frame in frames {
+            let frame = frame.map_err(decode_error)?;
+            let (width, height) = frame.buffer().dimensions();
+            pixels += width as u64 * height as u64;
+            if resized.len() == MAX_ANIMATION_FRAMES || pixels > MAX_ANIMATION_PIXELS {
+                return Ok(None);
+            }
+            // 解码出的每一帧都是完整画布，缩放后放在 (0, 0)
+            let delay = frame.delay();
+            let buffer = transform.apply(DynamicImage::ImageRgba8(frame.into_buffer())).to_rgba8();
+            resized.push(Frame::from_parts(buffer, 0, 0, delay));
+        }
+
+        let encode_error = |e: image::ImageError| Error::Internal(format!("Failed to encode animation: {}", e));
+        let mut output = Vec::new();
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut output, 10);
+            encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?;
+            encoder.encode_frames(resized).map_err(encode_error)?;
+        }
+        Ok(Some(output))
+    }
+
     /// 智能转码：如果源格式无法解码或已是目标格式则透传
     /// 
     /// 返回 (图片数据, 实际格式)
     pub fn smart_transcode(raw_bytes: Vec<u8>, target_format: ImageFormat) -> Result<(Vec<u8>, ImageFormat)> {
+        // 动图按 transcode.animated 处理，避免只保留第一帧
+        if let Some(source_format) = Self::animated_format(&raw_bytes) {
+            return Self::process_animated(raw_bytes, source_format, &ImageTransform::default());
+        }
+
         // 检测源格式
         if let Some(source_format) = Self::detect_format(&raw_bytes) {
             // 已经是目标格式，直接返回
@@ -188,57 +908,416 @@ impl ImageService {
         }
         
         // 尝试转码
-        let encoded = Self::encode_image_blocking(&raw_bytes, target_format)?;
+        let encoded = Self::process_image(&raw_bytes, target_format, &ImageTransform::default())?;
         Ok((encoded, target_format))
     }
 
-    /// 头像获取：内存缓存优先（头像通常较小）
-    pub async fn fetch_avatar(&self, url: &str) -> Result<(Vec<u8>, bool)> {
+    /// 图片代理是否允许该地址：主机名为白名单中的域名或其子域名
+    pub fn proxy_allowed(url: &str, allowed_domains: &[String]) -> bool {
+        let Some(host) = canonical_host(url) else {
+            return false;
+        };
+        allowed_domains.iter().any(|domain| {
+            let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+            !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+        })
+    }
+
+    /// 头像获取：内存缓存优先（头像通常较小）；已超过请求时限时不再下载
+    pub async fn fetch_avatar(&self, ctx: &RequestContext, url: &str) -> Result<(Vec<u8>, bool)> {
         let memory_cache_key = format!("avatar:{}", url);
 
         // 1. 内存缓存优先
-        if let Some(cached) = cache::get(&cache::CACHE_BUCKET, &memory_cache_key).await {
+        if let Some(cached) = cache::backend().get(&memory_cache_key).await {
             debug!("Avatar memory cache hit: {} bytes", cached.len());
             return Ok((cached, true));
         }
 
         // 2. 硬盘缓存
-        if let Some(cached) = cache::get_disk(url) {
+        if let Some(cached) = cache::get_disk(&memory_cache_key) {
             let len = cached.len();
             // 小于 512KB 提升到内存（直接 move 进 spawn，避免 clone）
             if len < 512 * 1024 {
                 let key = memory_cache_key.clone();
                 let data = cached.clone(); // 需要 clone 一份给内存缓存
                 tokio::spawn(async move {
-                    cache::put(&cache::CACHE_BUCKET, key, data).await;
+                    cache::backend().put(&key, data, None).await;
                 });
             }
             debug!("Avatar disk cache hit: {} bytes", len);
             return Ok((cached, true));
         }
 
-        // 3. 下载
+        // 3. 下载（相同 URL 的并发请求共享一次下载）
+        ctx.check("avatar download")?;
+        let bytes = AVATAR_DOWNLOADS
+            .run(url, || self.download_avatar(url, &memory_cache_key))
+            .await?;
+        Ok((bytes, false))
+    }
+
+    async fn download_avatar(&self, url: &str, memory_cache_key: &str) -> Result<Vec<u8>> {
         let bytes = self.download_image(url).await?;
         let len = bytes.len();
 
         // 4. 写入缓存（使用 Arc 共享数据避免多次深拷贝）
         let bytes_arc = std::sync::Arc::new(bytes);
         {
-            let url_clone = url.to_string();
+            let key = memory_cache_key.to_string();
             let bytes_for_disk = std::sync::Arc::clone(&bytes_arc);
             tokio::task::spawn_blocking(move || {
-                cache::put_disk(&url_clone, &bytes_for_disk);
+                cache::put_disk(&key, &bytes_for_disk);
             });
         }
 
         if len < 512 * 1024 {
-            cache::put(&cache::CACHE_BUCKET, memory_cache_key, (*bytes_arc).clone()).await;
+            cache::backend().put(memory_cache_key, (*bytes_arc).clone(), None).await;
         }
 
         let bytes = std::sync::Arc::try_unwrap(bytes_arc)
             .unwrap_or_else(|arc| (*arc).clone());
 
         info!("Avatar downloaded: {} bytes", len);
-        Ok((bytes, false))
+        Ok(bytes)
+    }
+}
+
+/// 启动壁纸预生成任务（未开启或没有可用格式时不启动）
+pub fn start_wallpaper_pregen(config: WallpaperPregenConfig, urls: Vec<String>) -> Option<JoinHandle<()>> {
+    if !config.enabled {
+        return None;
+    }
+    let formats = ImageService::parse_formats(&config.formats, "wallpaper pre-generation");
+    if formats.is_empty() {
+        return None;
+    }
+    let _ = PREGEN_FORMATS.set(formats.clone());
+
+    Some(tokio::spawn(async move {
+        let service = ImageService::new(upstream_service::http());
+        loop {
+            let started = Instant::now();
+            // 每轮重新读取上传的壁纸，包含上一轮之后上传的
+            let mut urls = urls.clone();
+            match WallpaperService::list().await {
+                Ok(uploaded) => urls.extend(uploaded.iter().map(|w| w.source_url())),
+                Err(e) => warn!("Failed to list uploaded wallpapers for pre-generation: {}", e),
+            }
+            let report = service.pregenerate_wallpapers(&urls, &formats).await;
+            info!(
+                "壁纸预生成完成：生成 {} 个，已有 {} 个，失败 {} 个（{} 张壁纸，耗时 {:?}）",
+                report.generated,
+                report.skipped,
+                report.failed,
+                urls.len(),
+                started.elapsed()
+            );
+            if config.interval_secs == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(config.interval_secs.max(60))).await;
+        }
+    }))
+}
+
+/// 为新上传的壁纸立即生成各格式的变体（未启用预生成时忽略）
+pub fn queue_wallpaper_pregen(url: String) {
+    let Some(formats) = PREGEN_FORMATS.get().cloned() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let service = ImageService::new(upstream_service::http());
+        let report = service.pregenerate_wallpapers(std::slice::from_ref(&url), &formats).await;
+        info!("壁纸预生成完成：{}（生成 {} 个，失败 {} 个）", url, report.generated, report.failed);
+    });
+}
+
+/// 启动缓存预热任务（没有需要预热的内容时不启动）：依次下载头像原图、按各格式编码壁纸，写入与请求时相同的缓存
+///
+/// 头像来源中 warm = true 的来源按各尺寸和格式预先转码（与 /avatar 协商出的变体相同）
+pub fn start_cache_warmup(config: CacheWarmupConfig) -> Option<JoinHandle<()>> {
+    let formats = ImageService::parse_formats(&config.formats, "cache warm-up");
+    let warm_sources = !formats.is_empty() && !avatar_service::registry().warm_sources().is_empty();
+    if config.avatars.is_empty() && config.wallpapers.is_empty() && !warm_sources {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let service = ImageService::new(upstream_service::http());
+        let ctx = RequestContext::background();
+        let started = Instant::now();
+        let (mut warmed, mut failed) = (0, 0);
+
+        if warm_sources {
+            let (ok, err) = avatar_service::warm_variants(&service, &formats).await;
+            warmed += ok;
+            failed += err;
+        }
+        for url in &config.avatars {
+            match service.fetch_avatar(&ctx, url).await {
+                Ok(_) => warmed += 1,
+                Err(e) => {
+                    warn!("Failed to warm up avatar {}: {}", url, e);
+                    failed += 1;
+                }
+            }
+        }
+        for url in &config.wallpapers {
+            for &format in &formats {
+                let accept = format!("image/{}", ImageService::format_extension(format));
+                match service.fetch_wallpaper(&ctx, url, &accept, ImageTransform::default()).await {
+                    Ok(_) => warmed += 1,
+                    Err(e) => {
+                        warn!("Failed to warm up wallpaper {} ({}): {}", url, accept, e);
+                        failed += 1;
+                    }
+                }
+            }
+        }
+        info!("缓存预热完成：成功 {} 项，失败 {} 项，耗时 {:?}", warmed, failed, started.elapsed());
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_allowed() {
+        let domains = vec!["cdn.tnxg.top".to_string(), "Example.com.".to_string()];
+        assert!(ImageService::proxy_allowed("https://cdn.tnxg.top/a.png", &domains));
+        assert!(ImageService::proxy_allowed("https://img.example.com/a.png", &domains));
+        assert!(ImageService::proxy_allowed("https://EXAMPLE.com/a.png", &domains));
+        assert!(!ImageService::proxy_allowed("https://notexample.com/a.png", &domains));
+        assert!(!ImageService::proxy_allowed("https://example.com.evil.test/a.png", &domains));
+        assert!(!ImageService::proxy_allowed("not a url", &domains));
+        assert!(!ImageService::proxy_allowed("https://cdn.tnxg.top/a.png", &[]));
+    }
+
+    #[test]
+    fn test_image_transform() {
+        // 只有宽度时参数部分与之前相同
+        let url = "https://cdn.tnxg.top/images/wallpaper/1.jpg";
+        let tag = cache::derived_tag();
+        assert_eq!(
+            ImageService::wallpaper_cache_key(url, &ImageTransform::width(Some(1280)), ImageFormat::WebP),
+            format!("wallpaper:{}:{}:w1280:webp", tag, url)
+        );
+        assert_eq!(
+            ImageService::wallpaper_cache_key(url, &ImageTransform::default(), ImageFormat::Jpeg),
+            format!("wallpaper:{}:{}:jpeg", tag, url)
+        );
+
+        // 超出范围的参数被限制，不同参数使用不同的 key
+        let transform = ImageTransform {
+            width: Some(10_000),
+            height: Some(0),
+            quality: Some(0),
+            fit: Fit::Cover,
+            keep_metadata: false,
+            shape: None,
+        }
+        .clamped();
+        assert_eq!(transform.width, Some(MAX_DIMENSION));
+        assert_eq!(transform.height, None);
+        assert_eq!(transform.quality, Some(1));
+        assert_eq!(transform.cache_suffix(ImageFormat::Jpeg), ":w4096:q1");
+        assert_eq!(transform.cache_suffix(ImageFormat::WebP), ":w4096");
+        let cover = ImageTransform {
+            width: Some(400),
+            height: Some(400),
+            quality: None,
+            fit: Fit::Cover,
+            keep_metadata: false,
+            shape: None,
+        };
+        assert_eq!(cover.cache_suffix(ImageFormat::WebP), ":w400:h400:cover");
+        let keep = ImageTransform {
+            keep_metadata: true,
+            ..cover
+        };
+        assert_eq!(keep.cache_suffix(ImageFormat::WebP), ":w400:h400:cover:meta");
+
+        let img = DynamicImage::ImageRgb8(RgbImage::new(1600, 900));
+        // contain：等比缩放到宽高之内
+        let contain = ImageTransform { fit: Fit::Contain, ..cover };
+        let out = contain.apply(img.clone());
+        assert_eq!((out.width(), out.height()), (400, 225));
+        // cover：裁剪填满
+        let out = cover.apply(img.clone());
+        assert_eq!((out.width(), out.height()), (400, 400));
+        // 不放大：cover 的目标尺寸等比缩小到原图之内
+        let large = ImageTransform {
+            width: Some(3200),
+            height: Some(1200),
+            ..cover
+        };
+        let out = large.apply(img.clone());
+        assert_eq!((out.width(), out.height()), (1600, 600));
+        let out = ImageTransform::width(Some(2560)).apply(img.clone());
+        assert_eq!((out.width(), out.height()), (1600, 900));
+
+        // 形状：缩放后居中裁剪为正方形，角落透明、中心不透明
+        let circle = ImageTransform {
+            width: Some(128),
+            height: Some(128),
+            shape: Shape::parse("Circle"),
+            ..ImageTransform::default()
+        };
+        assert_eq!(circle.cache_suffix(ImageFormat::Png), ":w128:h128:circle");
+        let out = circle.apply(img.clone()).to_rgba8();
+        assert_eq!(out.dimensions(), (72, 72));
+        assert_eq!(out.get_pixel(0, 0)[3], 0);
+        assert_eq!(out.get_pixel(36, 36)[3], 255);
+        assert_eq!(out.get_pixel(36, 1)[3], 255);
+        let rounded = ImageService::mask(img, Shape::Rounded).to_rgba8();
+        assert_eq!(rounded.dimensions(), (900, 900));
+        assert_eq!(rounded.get_pixel(0, 0)[3], 0);
+        // 圆角只影响角落，边的中段不透明
+        assert_eq!(rounded.get_pixel(0, 450)[3], 255);
+        assert_eq!(rounded.get_pixel(30, 30)[3], 0);
+        assert_eq!(rounded.get_pixel(200, 200)[3], 255);
+        assert_eq!(Shape::parse("square"), None);
+
+        // 质量只影响 JPEG 编码
+        let mut source = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 128])))
+            .write_to(&mut Cursor::new(&mut source), ImageFormat::Png)
+            .unwrap();
+        let low = ImageTransform {
+            quality: Some(10),
+            ..ImageTransform::default()
+        };
+        let high = ImageTransform {
+            quality: Some(95),
+            ..ImageTransform::default()
+        };
+        let low = ImageService::process_image(&source, ImageFormat::Jpeg, &low).unwrap();
+        let high = ImageService::process_image(&source, ImageFormat::Jpeg, &high).unwrap();
+        assert!(low.len() < high.len());
+        assert_eq!(ImageService::detect_format(&low), Some(ImageFormat::Jpeg));
+    }
+
+    #[test]
+    fn test_metadata() {
+        // 方向为顺时针旋转 90° 的 EXIF（小端 TIFF，一个 Orientation = 6 条目）
+        let exif = vec![
+            0x49, 0x49, 42, 0, 8, 0, 0, 0, 1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut source = Vec::new();
+        let mut encoder = JpegEncoder::new(&mut source);
+        encoder.set_icc_profile(b"test icc profile".to_vec()).unwrap();
+        encoder.set_exif_metadata(exif).unwrap();
+        DynamicImage::ImageRgb8(RgbImage::new(40, 20)).write_with_encoder(encoder).unwrap();
+
+        let read = |bytes: &[u8]| {
+            let mut decoder = ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .unwrap()
+                .into_decoder()
+                .unwrap();
+            let metadata = (decoder.icc_profile().unwrap(), decoder.exif_metadata().unwrap());
+            let orientation = decoder.orientation().unwrap();
+            (decoder.dimensions(), metadata, orientation)
+        };
+
+        // 默认去除元数据，像素按 EXIF 方向旋转
+        let out = ImageService::process_image(&source, ImageFormat::Jpeg, &ImageTransform::default()).unwrap();
+        let (dimensions, (icc, exif), _) = read(&out);
+        assert_eq!(dimensions, (20, 40));
+        assert_eq!((icc, exif), (None, None));
+
+        // 保留 ICC 和 EXIF，EXIF 中的方向已重置
+        let keep = ImageTransform {
+            keep_metadata: true,
+            ..ImageTransform::default()
+        };
+        for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+            let out = ImageService::process_image(&source, format, &keep).unwrap();
+            let (dimensions, (icc, exif), orientation) = read(&out);
+            assert_eq!(dimensions, (20, 40), "{:?}", format);
+            assert_eq!(icc.as_deref(), Some(&b"test icc profile"[..]), "{:?}", format);
+            assert!(exif.is_some(), "{:?}", format);
+            assert_eq!(orientation, ExifOrientation::NoTransforms, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_animation() {
+        let frames: Vec<Frame> = (0..3u8)
+            .map(|i| {
+                let buffer = image::RgbaImage::from_pixel(100, 80, image::Rgba([i * 80, 0, 0, 255]));
+                Frame::from_parts(buffer, 0, 0, image::Delay::from_numer_denom_ms(100, 1))
+            })
+            .collect();
+        let mut gif = Vec::new();
+        GifEncoder::new(&mut gif).encode_frames(frames).unwrap();
+        assert_eq!(ImageService::animated_format(&gif), Some(ImageFormat::Gif));
+
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(10, 10))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(ImageService::animated_format(&png), None);
+
+        // 原样返回
+        let transform = ImageTransform::width(Some(50));
+        let (out, format) =
+            ImageService::process_animated_with(gif.clone(), ImageFormat::Gif, &transform, AnimatedMode::Passthrough)
+                .unwrap();
+        assert_eq!((out == gif, format), (true, ImageFormat::Gif));
+
+        // 逐帧缩放，保留全部帧
+        let (out, format) =
+            ImageService::process_animated_with(gif, ImageFormat::Gif, &transform, AnimatedMode::Reencode).unwrap();
+        assert_eq!(format, ImageFormat::Gif);
+        let frames = GifDecoder::new(Cursor::new(&out)).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].buffer().dimensions(), (50, 40));
+    }
+
+    #[tokio::test]
+    async fn test_wallpaper_pregen() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut png = Vec::new();
+        DynamicImage::new_rgb8(8, 8).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        let png_len = png.len() as u64;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/pregen-{}.png", listener.local_addr().unwrap(), rand::random::<u64>());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    png.len()
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(&png).await;
+            }
+        });
+
+        // 未开启或没有可用格式时不启动（内置壁纸为空时仍启动，每轮会读取上传的壁纸）
+        let config = |enabled: bool, formats: &[&str]| WallpaperPregenConfig {
+            enabled,
+            formats: formats.iter().map(|f| f.to_string()).collect(),
+            interval_secs: 0,
+        };
+        assert!(start_wallpaper_pregen(config(false, &["webp"]), vec![url.clone()]).is_none());
+        assert!(start_wallpaper_pregen(config(true, &["bmp"]), vec![url.clone()]).is_none());
+
+        let key = ImageService::wallpaper_cache_key(&url, &ImageTransform::default(), ImageFormat::WebP);
+        assert!(!cache::has_disk(&key));
+        start_wallpaper_pregen(config(true, &["webp", "bmp"]), vec![url.clone()])
+            .unwrap()
+            .await
+            .unwrap();
+        let cached = cache::get_disk(&key).unwrap();
+        assert_eq!(ImageService::detect_format(&cached), Some(ImageFormat::WebP));
+        // 下载原图时记录摘要
+        let (sha256, size) = ImageService::cached_digest(&url).unwrap();
+        assert_eq!((sha256.len(), size), (64, png_len));
+        assert!(!cache::has_disk(&ImageService::wallpaper_cache_key(&url, &ImageTransform::default(), ImageFormat::Avif)));
     }
 }
@@ -0,0 +1,346 @@
+use crate::config::settings::{EmailConfig, InboundEmailConfig};
+use crate::services::email_service::EmailService;
+use crate::services::link_service::{LinkService, LinkSubmission};
+use crate::utils::mail::{self, ParsedMail};
+use crate::utils::{crypto, rng};
+use crate::{Error, Result};
+use log::{info, warn};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// 单轮收信（连接、读取、处理）的超时时间
+const POLL_TIMEOUT_SECS: u64 = 120;
+/// 每轮最多处理的邮件数
+const MAX_MESSAGES: usize = 20;
+/// 单封邮件大小上限
+const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+/// 确认码最多尝试次数，超过后作废，需要重新提交
+const MAX_CODE_ATTEMPTS: u32 = 5;
+/// 每个发件人每小时最多处理的邮件数
+const MAX_MESSAGES_PER_SENDER: u32 = 10;
+/// 每个收件人每小时最多发送的回复数
+const MAX_REPLIES_PER_RECIPIENT: u32 = 5;
+
+// 等待发件人确认的申请（发件人 -> (确认码哈希, 申请)），24 小时内有效；进程重启后需重新提交
+static PENDING: Lazy<Cache<String, (String, LinkSubmission)>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+// 发件人 -> 确认码错误次数，与 PENDING 同时失效
+static ATTEMPTS: Lazy<Cache<String, u32>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+// 发件人 -> 最近一小时处理的邮件数
+static SENDER_COUNTS: Lazy<Cache<String, u32>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(60 * 60))
+        .build()
+});
+
+// 收件人 -> 最近一小时发出的回复数
+static RECIPIENT_COUNTS: Lazy<Cache<String, u32>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(60 * 60))
+        .build()
+});
+
+/// 启动收信任务（未配置服务器时不启动）
+pub fn start_poller(config: InboundEmailConfig, email: EmailConfig) -> Option<JoinHandle<()>> {
+    if config.server.is_empty() {
+        return None;
+    }
+    info!("已启用邮件友链申请：{}/{}", config.server, config.mailbox);
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(config.poll_interval_secs.max(30)));
+        loop {
+            interval.tick().await;
+            match tokio::time::timeout(Duration::from_secs(POLL_TIMEOUT_SECS), poll(&config, &email)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("Inbound email poll failed: {}", e),
+                Err(_) => warn!("Inbound email poll timed out"),
+            }
+        }
+    }))
+}
+
+/// 读取未读邮件并逐封处理，返回处理的邮件数
+pub async fn poll(config: &InboundEmailConfig, email: &EmailConfig) -> Result<usize> {
+    let mut session = ImapSession::connect(&config.server, config.port).await?;
+    session
+        .command(&format!("LOGIN {} {}", quote(&config.username), quote(&config.password)))
+        .await?;
+    session.command(&format!("SELECT {}", quote(&config.mailbox))).await?;
+    let search = session.command("UID SEARCH UNSEEN").await?;
+    let uids: Vec<u64> = search
+        .lines
+        .iter()
+        .filter_map(|l| l.strip_prefix("* SEARCH"))
+        .flat_map(|l| l.split_whitespace().filter_map(|n| n.parse().ok()).collect::<Vec<_>>())
+        .take(MAX_MESSAGES)
+        .collect();
+
+    let sender = EmailService::new(email.clone())?;
+    for uid in &uids {
+        // 读取 BODY[] 会同时标记为已读，处理失败的邮件不会被反复处理
+        let fetched = session.command(&format!("UID FETCH {} BODY[]", uid)).await?;
+        let Some(raw) = fetched.literals.into_iter().next() else {
+            continue;
+        };
+        let parsed = mail::parse(&raw);
+        if let Err(e) = handle(config, email, &sender, &parsed).await {
+            warn!("Failed to process inbound email from {:?}: {}", parsed.from, e);
+        }
+    }
+    let _ = session.command("LOGOUT").await;
+    Ok(uids.len())
+}
+
+/// 处理一封邮件：带确认码的回复创建友链，带主题前缀的新申请发送确认码，其余忽略
+async fn handle(config: &InboundEmailConfig, email: &EmailConfig, sender: &EmailService, mail: &ParsedMail) -> Result<()> {
+    let Some(from) = mail.from.as_deref().map(normalize_address) else {
+        return Ok(());
+    };
+    let from = from.as_str();
+    // 忽略自动回复、退信和自己发出的邮件，避免邮件循环
+    if mail.auto_submitted || from == normalize_address(&email.from_address) {
+        return Ok(());
+    }
+    if !within_limit(&SENDER_COUNTS, from, MAX_MESSAGES_PER_SENDER).await {
+        warn!("Inbound email rate limit exceeded for {}", from);
+        return Ok(());
+    }
+
+    if let Some(code) = confirmation_code(&mail.subject) {
+        if let Some((hash, mut submission)) = PENDING.get(from).await {
+            if !crypto::verify_code(&code, &hash) {
+                let attempts = ATTEMPTS.get(from).await.unwrap_or(0) + 1;
+                if attempts >= MAX_CODE_ATTEMPTS {
+                    PENDING.invalidate(from).await;
+                    ATTEMPTS.invalidate(from).await;
+                } else {
+                    ATTEMPTS.insert(from.to_string(), attempts).await;
+                }
+                return Err(Error::Unauthorized("Confirmation code mismatch".into()));
+            }
+            PENDING.invalidate(from).await;
+            ATTEMPTS.invalidate(from).await;
+            if submission.email.as_deref().is_none_or(str::is_empty) {
+                submission.email = Some(from.to_string());
+            }
+            let (_, created) = LinkService::submit(submission).await?;
+            let message = if created {
+                "已收到友链申请，审核通过后会显示在友链页面。"
+            } else {
+                "该站点已在友链列表中（或已有待审核的申请）。"
+            };
+            return reply(sender, from, &format!("{} 申请已提交", config.subject_prefix), message).await;
+        }
+    }
+
+    if !mail.subject.starts_with(&config.subject_prefix) {
+        return Ok(());
+    }
+    let mut submission = match LinkService::parse_submission(&mail.text) {
+        Ok(submission) => submission,
+        Err(e) => {
+            let message = format!(
+                "无法解析友链信息：{}\n\n请在正文中按 YAML 格式填写，例如：\n\nname: 站点名称\nurl: https://example.com\navatar: https://example.com/avatar.png\ndescription: 一句话介绍",
+                e
+            );
+            return reply(sender, from, &format!("{} 申请格式错误", config.subject_prefix), &message).await;
+        }
+    };
+    submission.source = format!("email:{}", from);
+
+    let code = rng::secure_digits(6);
+    PENDING.insert(from.to_string(), (crypto::hash_code(&code), submission)).await;
+    ATTEMPTS.invalidate(from).await;
+    let message = format!(
+        "您好，\n\n我们收到了以此邮箱提交的友链申请。请直接回复本邮件（保留主题中的确认码 {}）完成确认，24 小时内有效。\n\n如果不是您本人提交，请忽略本邮件。",
+        code
+    );
+    reply(sender, from, &format!("{} 确认码 {}", config.subject_prefix, code), &message).await
+}
+
+/// 发送回复（超过收件人每小时上限时不发送）
+async fn reply(sender: &EmailService, to: &str, subject: &str, message: &str) -> Result<()> {
+    if !within_limit(&RECIPIENT_COUNTS, to, MAX_REPLIES_PER_RECIPIENT).await {
+        warn!("Inbound email reply limit exceeded for {}", to);
+        return Ok(());
+    }
+    sender.send_email(to, subject, message, None).await
+}
+
+/// 计数加一，返回是否仍在上限内
+async fn within_limit(counts: &Cache<String, u32>, key: &str, max: u32) -> bool {
+    let count = counts.get(key).await.unwrap_or(0) + 1;
+    counts.insert(key.to_string(), count).await;
+    count <= max
+}
+
+/// 规范化邮箱地址（去掉显示名和空白，转为小写）
+fn normalize_address(address: &str) -> String {
+    let address = match (address.rfind('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &address[start + 1..end],
+        _ => address,
+    };
+    address.trim().to_ascii_lowercase()
+}
+
+// 主题中的 6 位确认码
+fn confirmation_code(subject: &str) -> Option<String> {
+    subject
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|s| s.len() == 6)
+        .map(str::to_string)
+}
+
+/// IMAP 字符串（带引号并转义）
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// 一条命令的响应：非标记行和其中的字面量（{n} 后的原始字节）
+#[derive(Debug, Default)]
+struct ImapResponse {
+    lines: Vec<String>,
+    literals: Vec<Vec<u8>>,
+}
+
+/// 最小的 IMAP 客户端（IMAPS，按顺序执行命令）
+struct ImapSession {
+    stream: BufReader<TlsStream<TcpStream>>,
+    tag: u32,
+}
+
+impl ImapSession {
+    async fn connect(server: &str, port: u16) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::Internal(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let name = ServerName::try_from(server.to_string()).map_err(|e| Error::Internal(e.to_string()))?;
+        let tcp = TcpStream::connect((server, port))
+            .await
+            .map_err(|e| Error::Internal(format!("IMAP connect failed: {}", e)))?;
+        let stream = TlsConnector::from(Arc::new(tls))
+            .connect(name, tcp)
+            .await
+            .map_err(|e| Error::Internal(format!("IMAP TLS handshake failed: {}", e)))?;
+
+        let mut session = Self {
+            stream: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = session.read_line().await?;
+        if !greeting.starts_with("* OK") {
+            return Err(Error::Internal(format!("Unexpected IMAP greeting: {}", greeting.trim())));
+        }
+        Ok(session)
+    }
+
+    /// 发送命令并读取到对应的标记响应
+    async fn command(&mut self, command: &str) -> Result<ImapResponse> {
+        self.tag += 1;
+        let tag = format!("A{:04}", self.tag);
+        self.stream
+            .get_mut()
+            .write_all(format!("{} {}\r\n", tag, command).as_bytes())
+            .await
+            .map_err(|e| Error::Internal(format!("IMAP write failed: {}", e)))?;
+
+        let mut response = ImapResponse::default();
+        loop {
+            let line = self.read_line().await?;
+            if let Some(status) = line.strip_prefix(&tag) {
+                let status = status.trim();
+                if status.starts_with("OK") {
+                    return Ok(response);
+                }
+                // 不在错误中回显命令（LOGIN 带密码）
+                let verb = command.split_whitespace().next().unwrap_or_default();
+                return Err(Error::Internal(format!("IMAP {} failed: {}", verb, status)));
+            }
+            if let Some(size) = literal_size(&line) {
+                if size > MAX_MESSAGE_BYTES {
+                    return Err(Error::Internal(format!("IMAP literal too large: {} bytes", size)));
+                }
+                let mut literal = vec![0; size];
+                self.stream
+                    .read_exact(&mut literal)
+                    .await
+                    .map_err(|e| Error::Internal(format!("IMAP read failed: {}", e)))?;
+                response.literals.push(literal);
+            }
+            response.lines.push(line.trim_end().to_string());
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut buf = Vec::new();
+        let n = self
+            .stream
+            .read_until(b'\n', &mut buf)
+            .await
+            .map_err(|e| Error::Internal(format!("IMAP read failed: {}", e)))?;
+        if n == 0 {
+            return Err(Error::Internal("IMAP connection closed".into()));
+        }
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+// 行尾的字面量长度标记，如 "* 1 FETCH (UID 7 BODY[] {1234}"
+fn literal_size(line: &str) -> Option<usize> {
+    let line = line.trim_end();
+    let inner = line.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    inner[start + 1..].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_helpers() {
+        assert_eq!(literal_size("* 1 FETCH (UID 7 BODY[] {1234}\r\n"), Some(1234));
+        assert_eq!(literal_size("* 1 FETCH (FLAGS (\\Seen))\r\n"), None);
+        assert_eq!(quote(r#"pa"ss\word"#), r#""pa\"ss\\word""#);
+        assert_eq!(confirmation_code("Re: [友链] 确认码 042917").as_deref(), Some("042917"));
+        assert_eq!(confirmation_code("[友链] 2024 申请"), None);
+        assert_eq!(normalize_address(" Space API <Bot@Example.com> "), "bot@example.com");
+        assert_ne!(normalize_address("a@example.com"), normalize_address("bot@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_within_limit() {
+        let counts = Cache::new(10);
+        for _ in 0..3 {
+            assert!(within_limit(&counts, "a@example.com", 3).await);
+        }
+        assert!(!within_limit(&counts, "a@example.com", 3).await);
+        assert!(within_limit(&counts, "b@example.com", 3).await);
+    }
+}
@@ -0,0 +1,359 @@
+use crate::config::settings::IpFilterConfig;
+use crate::services::db_service;
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use log::{info, warn};
+use mongodb::bson::{doc, Bson};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const BLOCK_COLLECTION: &str = "ip_blocks";
+
+/// 解析 CIDR 或单个 IP（单个 IP 视为 /32 或 /128）
+pub fn parse_net(s: &str) -> Result<IpNet> {
+    let s = s.trim();
+    if let Ok(net) = s.parse::<IpNet>() {
+        return Ok(net.trunc());
+    }
+    s.parse::<IpAddr>()
+        .map(IpNet::from)
+        .map_err(|_| Error::BadRequest(format!("Invalid IP or CIDR: {}", s)))
+}
+
+fn parse_list(items: &[String], context: &str) -> Vec<IpNet> {
+    items
+        .iter()
+        .filter_map(|s| match parse_net(s) {
+            Ok(net) => Some(net),
+            Err(_) => {
+                warn!("忽略无效的 IP 规则 [{}]: {}", context, s);
+                None
+            }
+        })
+        .collect()
+}
+
+fn matches_any(nets: &[IpNet], ip: &IpAddr) -> bool {
+    nets.iter().any(|n| n.contains(ip))
+}
+
+/// 路由分组规则
+struct RuleGroup {
+    name: String,
+    path_prefixes: Vec<String>,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl RuleGroup {
+    fn matches_path(&self, path: &str) -> bool {
+        self.path_prefixes.iter().any(|p| {
+            let p = p.trim_end_matches('/');
+            p.is_empty() || path == p || path.starts_with(&format!("{}/", p))
+        })
+    }
+}
+
+/// 动态封禁记录
+#[derive(Debug, Clone, Serialize)]
+pub struct IpBlock {
+    pub cidr: String,
+    pub reason: String,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+}
+
+impl IpBlock {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|exp| Utc::now() > exp.with_timezone(&Utc))
+            .unwrap_or(false)
+    }
+}
+
+/// 过滤结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpDecision {
+    Allow,
+    Deny(String),
+}
+
+/// IP 白名单/黑名单服务
+///
+/// 静态规则来自配置（全局 + 按路由分组），动态封禁通过管理接口维护并持久化到 MongoDB
+pub struct IpFilterService {
+    enabled: bool,
+    trust_proxy_headers: bool,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    groups: Vec<RuleGroup>,
+    blocks: RwLock<HashMap<IpNet, IpBlock>>,
+}
+
+impl IpFilterService {
+    pub fn new(config: &IpFilterConfig) -> Self {
+        let groups = config
+            .groups
+            .iter()
+            .map(|g| RuleGroup {
+                name: g.name.clone(),
+                path_prefixes: g.path_prefixes.clone(),
+                allow: parse_list(&g.allow, &g.name),
+                deny: parse_list(&g.deny, &g.name),
+            })
+            .collect();
+
+        Self {
+            enabled: config.enabled,
+            trust_proxy_headers: config.trust_proxy_headers,
+            allow: parse_list(&config.allow, "global"),
+            deny: parse_list(&config.deny, "global"),
+            groups,
+            blocks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn trust_proxy_headers(&self) -> bool {
+        self.trust_proxy_headers
+    }
+
+    /// 判断请求是否放行
+    ///
+    /// 顺序：动态封禁 -> 全局黑名单 -> 全局白名单 -> 分组黑名单 -> 分组白名单
+    pub async fn check(&self, ip: Option<IpAddr>, path: &str) -> IpDecision {
+        let ip = match ip {
+            Some(ip) => ip,
+            None => {
+                // 无法识别来源 IP 时，只要存在适用的白名单就拒绝
+                let restricted = self.enabled
+                    && (!self.allow.is_empty()
+                        || self
+                            .groups
+                            .iter()
+                            .any(|g| !g.allow.is_empty() && g.matches_path(path)));
+                return if restricted {
+                    IpDecision::Deny("unknown client ip".to_string())
+                } else {
+                    IpDecision::Allow
+                };
+            }
+        };
+
+        {
+            let blocks = self.blocks.read().await;
+            if let Some(block) = blocks
+                .iter()
+                .find(|(net, b)| net.contains(&ip) && !b.is_expired())
+                .map(|(_, b)| b)
+            {
+                return IpDecision::Deny(format!("blocked: {}", block.reason));
+            }
+        }
+
+        if !self.enabled {
+            return IpDecision::Allow;
+        }
+
+        if matches_any(&self.deny, &ip) {
+            return IpDecision::Deny("global denylist".to_string());
+        }
+        if !self.allow.is_empty() && !matches_any(&self.allow, &ip) {
+            return IpDecision::Deny("not in global allowlist".to_string());
+        }
+
+        for group in self.groups.iter().filter(|g| g.matches_path(path)) {
+            if matches_any(&group.deny, &ip) {
+                return IpDecision::Deny(format!("denylist of group {}", group.name));
+            }
+            if !group.allow.is_empty() && !matches_any(&group.allow, &ip) {
+                return IpDecision::Deny(format!("not in allowlist of group {}", group.name));
+            }
+        }
+
+        IpDecision::Allow
+    }
+
+    /// 添加动态封禁（ttl 为空表示永久）
+    pub async fn block(&self, cidr: &str, reason: &str, ttl: Option<Duration>) -> Result<IpBlock> {
+        let net = parse_net(cidr)?;
+        let now = Utc::now();
+        let expires_at = ttl
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+            .map(|d| (now + d).to_rfc3339());
+
+        let block = IpBlock {
+            cidr: net.to_string(),
+            reason: reason.to_string(),
+            created_at: now.to_rfc3339(),
+            expires_at,
+        };
+
+        // 持久化（同一网段只保留最新一条）
+        db_service::delete_one(BLOCK_COLLECTION, doc! { "cidr": &block.cidr }).await?;
+        let expires_bson = match &block.expires_at {
+            Some(s) => Bson::String(s.clone()),
+            None => Bson::Null,
+        };
+        db_service::insert_one(
+            BLOCK_COLLECTION,
+            doc! {
+                "cidr": &block.cidr,
+                "reason": &block.reason,
+                "created_at": &block.created_at,
+                "expires_at": expires_bson,
+            },
+        )
+        .await?;
+
+        self.blocks.write().await.insert(net, block.clone());
+        info!("已封禁 {} ({})", block.cidr, block.reason);
+        Ok(block)
+    }
+
+    /// 解除动态封禁，返回是否存在该封禁
+    pub async fn unblock(&self, cidr: &str) -> Result<bool> {
+        let net = parse_net(cidr)?;
+        db_service::delete_one(BLOCK_COLLECTION, doc! { "cidr": net.to_string() }).await?;
+        let removed = self.blocks.write().await.remove(&net).is_some();
+        if removed {
+            info!("已解除封禁 {}", net);
+        }
+        Ok(removed)
+    }
+
+    /// 仅当封禁满足条件时解除（如只解除自动封禁，保留手动封禁），返回是否解除
+    pub async fn unblock_if(&self, cidr: &str, pred: impl Fn(&IpBlock) -> bool) -> Result<bool> {
+        let net = parse_net(cidr)?;
+        let mut blocks = self.blocks.write().await;
+        if !blocks.get(&net).is_some_and(|b| !b.is_expired() && pred(b)) {
+            return Ok(false);
+        }
+        db_service::delete_one(BLOCK_COLLECTION, doc! { "cidr": net.to_string() }).await?;
+        blocks.remove(&net);
+        info!("已解除封禁 {}", net);
+        Ok(true)
+    }
+
+    /// 列出当前有效的动态封禁（顺带清理已过期的记录）
+    pub async fn list_blocks(&self) -> Vec<IpBlock> {
+        let mut blocks = self.blocks.write().await;
+        blocks.retain(|_, b| !b.is_expired());
+        let mut list: Vec<IpBlock> = blocks.values().cloned().collect();
+        list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        list
+    }
+
+    /// 启动时从 MongoDB 加载持久化的封禁记录
+    pub async fn load_persisted(&self) -> Result<usize> {
+        let docs = db_service::find_many(BLOCK_COLLECTION, doc! {}).await?;
+        let mut blocks = self.blocks.write().await;
+        for d in docs {
+            let cidr = match d.get_str("cidr") {
+                Ok(s) => s.to_string(),
+                Err(_) => continue,
+            };
+            let net = match parse_net(&cidr) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            let block = IpBlock {
+                cidr,
+                reason: d.get_str("reason").unwrap_or("").to_string(),
+                created_at: d.get_str("created_at").unwrap_or("").to_string(),
+                expires_at: d.get_str("expires_at").ok().map(|s| s.to_string()),
+            };
+            if !block.is_expired() {
+                blocks.insert(net, block);
+            }
+        }
+        Ok(blocks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::IpFilterGroupConfig;
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let config = IpFilterConfig {
+            enabled: true,
+            trust_proxy_headers: false,
+            allow: Vec::new(),
+            deny: strings(&["203.0.113.0/24", "2001:db8::1", "not-an-ip"]),
+            groups: vec![IpFilterGroupConfig {
+                name: "admin".into(),
+                path_prefixes: strings(&["/api/admin"]),
+                allow: strings(&["10.0.0.0/8"]),
+                deny: strings(&["10.9.9.9"]),
+            }],
+        };
+        let filter = IpFilterService::new(&config);
+
+        // 全局黑名单（CIDR 和单个 IP，无效规则被忽略）
+        assert_eq!(filter.check(ip("198.51.100.7"), "/status").await, IpDecision::Allow);
+        assert!(matches!(filter.check(ip("203.0.113.200"), "/status").await, IpDecision::Deny(_)));
+        assert!(matches!(filter.check(ip("2001:db8::1"), "/").await, IpDecision::Deny(_)));
+        assert_eq!(filter.check(ip("2001:db8::2"), "/").await, IpDecision::Allow);
+
+        // 分组规则只作用于匹配的路径
+        assert_eq!(filter.check(ip("10.1.2.3"), "/api/admin/features").await, IpDecision::Allow);
+        assert_eq!(filter.check(ip("198.51.100.7"), "/api/administrator").await, IpDecision::Allow);
+        assert_eq!(
+            filter.check(ip("198.51.100.7"), "/api/admin").await,
+            IpDecision::Deny("not in allowlist of group admin".into())
+        );
+        assert_eq!(
+            filter.check(ip("10.9.9.9"), "/api/admin").await,
+            IpDecision::Deny("denylist of group admin".into())
+        );
+        // 未知来源：存在适用的白名单时拒绝
+        assert!(matches!(filter.check(None, "/api/admin").await, IpDecision::Deny(_)));
+        assert_eq!(filter.check(None, "/status").await, IpDecision::Allow);
+
+        // 全局白名单
+        let filter = IpFilterService::new(&IpFilterConfig {
+            allow: strings(&["192.0.2.0/28"]),
+            ..config.clone()
+        });
+        assert_eq!(filter.check(ip("192.0.2.15"), "/").await, IpDecision::Allow);
+        assert!(matches!(filter.check(ip("192.0.2.16"), "/").await, IpDecision::Deny(_)));
+
+        // 动态封禁在过滤关闭时仍然生效，过期后失效
+        let filter = IpFilterService::new(&IpFilterConfig {
+            enabled: false,
+            ..config
+        });
+        let block = |expires_at: Option<String>| IpBlock {
+            cidr: String::new(),
+            reason: "spam".into(),
+            created_at: Utc::now().to_rfc3339(),
+            expires_at,
+        };
+        filter.blocks.write().await.insert(parse_net("198.51.100.0/24").unwrap(), block(None));
+        let expired = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+        filter.blocks.write().await.insert(parse_net("192.0.2.1").unwrap(), block(Some(expired)));
+        assert_eq!(filter.check(ip("198.51.100.7"), "/").await, IpDecision::Deny("blocked: spam".into()));
+        assert_eq!(filter.check(ip("192.0.2.1"), "/").await, IpDecision::Allow);
+        assert_eq!(filter.check(ip("203.0.113.1"), "/").await, IpDecision::Allow);
+
+        assert_eq!(parse_net(" 10.1.2.3/8 ").unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!(parse_net("::1").unwrap().to_string(), "::1/128");
+        assert!(parse_net("10.0.0.0/33").is_err());
+    }
+}
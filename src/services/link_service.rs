@@ -0,0 +1,545 @@
+use crate::services::cdn_service;
+use crate::services::db_service;
+use crate::services::event_bus::{self, Event};
+use crate::services::feature_service::{self, Feature, LinkSubmissions};
+use crate::services::outbox_service;
+use crate::services::spam_service::{SpamInput, SpamService};
+use crate::utils::compression;
+use crate::utils::response::ApiResponse;
+use crate::utils::url::canonicalize;
+use crate::utils::validation::{Validate, Validator};
+use crate::{Error, Result};
+use chrono::{Duration, SecondsFormat, Utc};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use config::{Config as ConfigLoader, File, FileFormat};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+const LINKS_COLLECTION: &str = "links";
+const CLICKS_COLLECTION: &str = "link_clicks";
+
+/// 统计中返回的来源 / 国家数量上限
+const TOP_N: i64 = 10;
+
+/// 会改变公开友链列表的事件（新申请等待审核，不会出现在公开列表中）
+const LIST_CHANGE_EVENTS: &[&str] = &["link.approved", "link.rejected", "link.avatar_repaired"];
+
+// 公开友链列表的序列化响应（写入时通过事件失效，TTL 只是兜底）
+static LIST_CACHE: Lazy<Cache<&'static str, PublicList>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1)
+        .time_to_live(std::time::Duration::from_secs(30 * 60))
+        .build()
+});
+
+/// 不公开的审核状态（没有 moderation_state 的旧数据视为已通过）
+pub const HIDDEN_STATES: [&str; 2] = ["pending", "rejected"];
+
+// 列表缓存的代数：查询期间发生失效时，不写入已过时的结果
+static LIST_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 序列化后的公开友链列表
+#[derive(Debug, Clone)]
+pub struct PublicList {
+    /// 内容哈希（前 12 位），便于排查客户端拿到的是哪个版本
+    pub version: String,
+    pub body: Arc<Vec<u8>>,
+    /// 预压缩的版本（编码, 内容），按编码优先级排列，列表过小时为空
+    pub compressed: Vec<(&'static str, Arc<Vec<u8>>)>,
+}
+
+/// 一次点击的来源信息
+#[derive(Debug, Clone, Default)]
+pub struct ClickSource {
+    /// 来源站点（仅保存主机名）
+    pub referrer: Option<String>,
+    /// 两位国家代码（来自 CDN 传入的请求头）
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CountEntry {
+    pub key: String,
+    pub clicks: i64,
+}
+
+/// 友链点击统计
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStats {
+    pub link_id: String,
+    pub days: u32,
+    pub total: i64,
+    /// 按天（UTC）统计，只包含有点击的日期
+    pub daily: Vec<CountEntry>,
+    pub referrers: Vec<CountEntry>,
+    pub countries: Vec<CountEntry>,
+}
+
+/// 友链申请
+#[derive(Debug, Clone, Default)]
+pub struct LinkSubmission {
+    pub name: String,
+    pub url: String,
+    pub avatar: Option<String>,
+    pub description: Option<String>,
+    pub email: Option<String>,
+    /// 申请来源（如 github:owner/repo#12）
+    pub source: String,
+}
+
+// 文本中的友链申请字段（GitHub issue、邮件正文）
+#[derive(Debug, Deserialize)]
+struct SubmissionFields {
+    name: String,
+    #[serde(alias = "link")]
+    url: String,
+    #[serde(default)]
+    avatar: Option<String>,
+    #[serde(default, alias = "desc")]
+    description: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+impl Validate for SubmissionFields {
+    fn check(&self, v: &mut Validator) {
+        v.length("name", self.name.trim(), 1, 50).url("url", &self.url);
+        if let Some(avatar) = self.avatar.as_deref().filter(|a| !a.is_empty()) {
+            v.url("avatar", avatar);
+        }
+        if let Some(description) = &self.description {
+            v.length("description", description, 0, 200);
+        }
+        if let Some(email) = self.email.as_deref().filter(|e| !e.is_empty()) {
+            v.email("email", email);
+        }
+    }
+}
+
+pub struct LinkService;
+
+impl LinkService {
+    /// 按 ID 查找友链（ID 为 ObjectId 十六进制字符串）
+    pub async fn find_link(id: &str) -> Result<Document> {
+        let oid = ObjectId::parse_str(id).map_err(|_| Error::BadRequest("Invalid link id".into()))?;
+        db_service::find_one(LINKS_COLLECTION, doc! { "_id": oid })
+            .await?
+            .ok_or_else(|| Error::NotFound("Link not found".into()))
+    }
+
+    /// 按 ID 查找公开可见的友链，待审核和已拒绝的友链按不存在处理（跳转、点击统计等匿名接口使用）
+    pub async fn find_public_link(id: &str) -> Result<Document> {
+        Self::public_only(Self::find_link(id).await?)
+    }
+
+    fn public_only(link: Document) -> Result<Document> {
+        if Self::is_public(&link) {
+            Ok(link)
+        } else {
+            Err(Error::NotFound("Link not found".into()))
+        }
+    }
+
+    /// 批量查找友链（忽略无效 ID 和不存在的友链）
+    pub async fn find_links(ids: &[String]) -> Result<Vec<Document>> {
+        let oids: Vec<ObjectId> = ids.iter().filter_map(|id| ObjectId::parse_str(id).ok()).collect();
+        if oids.is_empty() {
+            return Ok(Vec::new());
+        }
+        db_service::find_many(LINKS_COLLECTION, doc! { "_id": { "$in": oids } }).await
+    }
+
+    /// 友链是否公开可见（待审核和已拒绝的友链只对管理员可见）
+    pub fn is_public(link: &Document) -> bool {
+        !link
+            .get_str("moderation_state")
+            .is_ok_and(|state| HIDDEN_STATES.contains(&state))
+    }
+
+    /// 友链的跳转地址（仅允许 http / https，返回规范化后的地址）
+    pub fn target_url(link: &Document) -> Result<String> {
+        let url = link
+            .get_str("url")
+            .map_err(|_| Error::Internal("Malformed link record".into()))?;
+        canonicalize(url).map_err(|_| Error::Internal(format!("Link has invalid url: {}", url)))
+    }
+
+    /// 记录一次点击（不保存访客 IP）
+    pub async fn record_click(link_id: &str, source: ClickSource) -> Result<()> {
+        let mut click = doc! {
+            "link_id": link_id,
+            // 统一使用 UTC + 秒精度，字符串可直接按字典序做范围查询
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        };
+        if let Some(referrer) = source.referrer {
+            click.insert("referrer", referrer);
+        }
+        if let Some(country) = source.country {
+            click.insert("country", country);
+        }
+        db_service::insert_one(CLICKS_COLLECTION, click).await?;
+        Ok(())
+    }
+
+    /// 公开友链列表（已审核，不含待审核和被拒绝的友链），返回 (列表, 是否命中缓存)
+    pub async fn public_list() -> Result<(PublicList, bool)> {
+        if let Some(list) = LIST_CACHE.get("links").await {
+            return Ok((list, true));
+        }
+
+        let generation = LIST_GENERATION.load(Ordering::Acquire);
+        let links: Vec<serde_json::Value> = db_service::aggregate(
+            LINKS_COLLECTION,
+            vec![
+                doc! { "$match": { "moderation_state": { "$nin": HIDDEN_STATES.to_vec() } } },
+                doc! { "$sort": { "_id": 1 } },
+            ],
+        )
+        .await?
+        .iter()
+        .filter_map(|link| {
+            Some(serde_json::json!({
+                "id": link.get_object_id("_id").ok()?.to_hex(),
+                "name": link.get_str("name").ok()?,
+                "url": link.get_str("url").ok()?,
+                "avatar": link.get_str("avatar").unwrap_or_default(),
+                "description": link.get_str("description").unwrap_or_default(),
+            }))
+        })
+        .collect();
+
+        let body = serde_json::to_vec(&ApiResponse::success(links, "Links").into_inner())
+            .map_err(|e| Error::Internal(e.to_string()))?;
+        let compressed = compression::PRECOMPRESSED_ENCODINGS
+            .iter()
+            .filter_map(|e| compression::compress(e, &body).map(|c| (*e, Arc::new(c))))
+            .collect();
+        let list = PublicList {
+            version: hex::encode(Sha256::digest(&body))[..12].to_string(),
+            body: Arc::new(body),
+            compressed,
+        };
+        if LIST_GENERATION.load(Ordering::Acquire) == generation {
+            LIST_CACHE.insert("links", list.clone()).await;
+        }
+        Ok((list, false))
+    }
+
+    /// 清除公开友链列表的本地缓存，并刷新 CDN 上的副本
+    pub fn invalidate_list_cache() {
+        LIST_GENERATION.fetch_add(1, Ordering::AcqRel);
+        LIST_CACHE.invalidate_all();
+        cdn_service::purge_detached(vec!["/links".to_string()]);
+    }
+
+    /// 订阅友链变更事件，自动清除列表缓存
+    pub fn watch_changes() -> JoinHandle<()> {
+        event_bus::subscribe("links_cache", LIST_CHANGE_EVENTS, |event| async move {
+            log::debug!("Links list cache invalidated by {}", event.name());
+            Self::invalidate_list_cache();
+        })
+    }
+
+    /// 按头像地址查找友链
+    pub async fn find_by_avatar(avatar_url: &str) -> Result<Option<Document>> {
+        db_service::find_one(LINKS_COLLECTION, doc! { "avatar": avatar_url }).await
+    }
+
+    /// 头像失效时可尝试的备用地址：站点图标、提交者邮箱对应的 Gravatar
+    pub fn avatar_candidates(link: &Document) -> Vec<String> {
+        let mut candidates = Vec::new();
+        if let Some(origin) = link
+            .get_str("url")
+            .ok()
+            .and_then(|u| url::Url::parse(u).ok())
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+            .map(|u| u.origin().ascii_serialization())
+        {
+            for path in ["apple-touch-icon.png", "apple-touch-icon-precomposed.png", "favicon.png"] {
+                candidates.push(format!("{}/{}", origin, path));
+            }
+        }
+        if let Ok(email) = link.get_str("email") {
+            let email = email.trim().to_ascii_lowercase();
+            if !email.is_empty() {
+                // d=404：邮箱未注册 Gravatar 时返回 404，而不是默认占位图
+                let hash = format!("{:x}", md5::compute(email.as_bytes()));
+                candidates.push(format!("https://www.gravatar.com/avatar/{}?s=256&d=404", hash));
+            }
+        }
+        candidates
+    }
+
+    /// 解析文本中的友链申请：有代码块时取第一个代码块，否则取全文，按 JSON 或 YAML 解析并校验
+    pub fn parse_submission(text: &str) -> Result<LinkSubmission> {
+        let block = if text.lines().any(|l| l.trim_start().starts_with("```")) {
+            let mut lines = text.lines().skip_while(|l| !l.trim_start().starts_with("```"));
+            let lang = lines
+                .next()
+                .map(|l| l.trim().trim_start_matches('`').trim().to_ascii_lowercase())
+                .unwrap_or_default();
+            let block = lines
+                .take_while(|l| !l.trim_start().starts_with("```"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if lang == "json" && !block.trim_start().starts_with('{') {
+                return Err(Error::BadRequest("Expected a JSON object".into()));
+            }
+            block
+        } else {
+            text.to_string()
+        };
+
+        let fields: SubmissionFields = if block.trim_start().starts_with('{') {
+            serde_json::from_str(&block).map_err(|e| Error::BadRequest(e.to_string()))?
+        } else {
+            ConfigLoader::builder()
+                .add_source(File::from_str(&block, FileFormat::Yaml))
+                .build()
+                .and_then(|c| c.try_deserialize())
+                .map_err(|e| Error::BadRequest(e.to_string()))?
+        };
+        fields.validate()?;
+        Ok(LinkSubmission {
+            name: fields.name,
+            url: fields.url,
+            avatar: fields.avatar,
+            description: fields.description,
+            email: fields.email,
+            source: String::new(),
+        })
+    }
+
+    /// 创建待审核的友链，返回 (友链 ID, 是否新建)；相同地址的友链已存在时直接返回已有的 ID
+    ///
+    /// 友链申请被关闭（功能开关 link_submissions）时返回 503
+    pub async fn submit(submission: LinkSubmission) -> Result<(String, bool)> {
+        feature_service::ensure(LinkSubmissions::NAME)?;
+        let url = canonicalize(&submission.url)?;
+        if let Some(id) = Self::id_by_url(&url).await? {
+            return Ok((id, false));
+        }
+
+        let mut link = doc! {
+            "name": submission.name.trim(),
+            "url": &url,
+            "source": &submission.source,
+            "created_at": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        };
+        for (field, value) in [
+            ("avatar", &submission.avatar),
+            ("description", &submission.description),
+            ("email", &submission.email),
+        ] {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                link.insert(field, value);
+            }
+        }
+        let verdict = SpamService::score(&SpamInput {
+            kind: "link",
+            content: &format!("{}\n{}", submission.name, submission.description.as_deref().unwrap_or_default()),
+            author: Some(&submission.name),
+            email: submission.email.as_deref(),
+            ..Default::default()
+        })
+        .await;
+        verdict.apply_to(&mut link);
+        // 新申请一律等待人工审核
+        link.insert("moderation_state", "pending");
+
+        // 并发提交同一地址时由唯一索引 links_url 去重
+        let id = match db_service::insert_one(LINKS_COLLECTION, link).await {
+            Ok(id) => id,
+            Err(Error::Conflict(_)) => {
+                let id = Self::id_by_url(&url)
+                    .await?
+                    .ok_or_else(|| Error::Internal("Conflicting link disappeared".into()))?;
+                return Ok((id, false));
+            }
+            Err(e) => return Err(e),
+        };
+        // 友链已写入，通知写入失败时 emit 已记录日志
+        let _ = outbox_service::emit(Event::LinkSubmitted {
+            link_id: id.clone(),
+            url,
+        })
+        .await;
+        Ok((id, true))
+    }
+
+    /// 按规范化后的地址查找友链 ID
+    async fn id_by_url(url: &str) -> Result<Option<String>> {
+        let Some(existing) = db_service::find_one(LINKS_COLLECTION, doc! { "url": url }).await? else {
+            return Ok(None);
+        };
+        let id = existing
+            .get_object_id("_id")
+            .map_err(|_| Error::Internal("Malformed link record".into()))?;
+        Ok(Some(id.to_hex()))
+    }
+
+    /// 待审核的友链（审核状态为 pending，或头像被自动替换后需要确认）
+    pub async fn pending_links() -> Result<Vec<Document>> {
+        db_service::find_many(
+            LINKS_COLLECTION,
+            doc! { "$or": [{ "moderation_state": "pending" }, { "needs_review": true }] },
+        )
+        .await
+    }
+
+    /// 审核通过友链，返回是否有变更（友链不存在时返回 NotFound）
+    pub async fn approve(id: &str) -> Result<bool> {
+        Self::set_moderation_state(id, "approved").await
+    }
+
+    /// 拒绝友链，返回是否有变更（友链不存在时返回 NotFound）
+    pub async fn reject(id: &str) -> Result<bool> {
+        Self::set_moderation_state(id, "rejected").await
+    }
+
+    async fn set_moderation_state(id: &str, state: &str) -> Result<bool> {
+        let link = Self::find_link(id).await?;
+        let modified = db_service::update_one(
+            LINKS_COLLECTION,
+            doc! { "_id": link.get("_id").cloned().unwrap_or(Bson::Null) },
+            doc! { "$set": {
+                "moderation_state": state,
+                "needs_review": false,
+                "reviewed_at": Utc::now().to_rfc3339(),
+            } },
+        )
+        .await?;
+        Ok(modified > 0)
+    }
+
+    /// 替换友链头像，并标记为待管理员审核
+    pub async fn replace_avatar(link: &Document, new_avatar: &str) -> Result<()> {
+        let id = link
+            .get_object_id("_id")
+            .map_err(|_| Error::Internal("Malformed link record".into()))?;
+        let previous = link.get_str("avatar").unwrap_or_default();
+        db_service::update_one(
+            LINKS_COLLECTION,
+            doc! { "_id": id },
+            doc! { "$set": {
+                "avatar": new_avatar,
+                "avatar_previous": previous,
+                "avatar_repaired_at": Utc::now().to_rfc3339(),
+                "needs_review": true,
+            } },
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 最近 `days` 天的点击统计
+    pub async fn stats(link_id: &str, days: u32) -> Result<LinkStats> {
+        let since = (Utc::now() - Duration::days(days as i64)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let top = |field: &str| {
+            vec![
+                doc! { "$match": { field: { "$exists": true } } },
+                doc! { "$group": { "_id": format!("${}", field), "clicks": { "$sum": 1 } } },
+                doc! { "$sort": { "clicks": -1, "_id": 1 } },
+                doc! { "$limit": TOP_N },
+            ]
+        };
+        let pipeline = vec![
+            doc! { "$match": { "link_id": link_id, "timestamp": { "$gte": &since } } },
+            doc! { "$facet": {
+                "total": [ { "$count": "clicks" } ],
+                "daily": [
+                    { "$group": { "_id": { "$substrBytes": ["$timestamp", 0, 10] }, "clicks": { "$sum": 1 } } },
+                    { "$sort": { "_id": 1 } },
+                ],
+                "referrers": top("referrer"),
+                "countries": top("country"),
+            } },
+        ];
+
+        let result = db_service::aggregate(CLICKS_COLLECTION, pipeline)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        Ok(LinkStats {
+            link_id: link_id.to_string(),
+            days,
+            total: count_entries(&result, "total").first().map(|e| e.clicks).unwrap_or(0),
+            daily: count_entries(&result, "daily"),
+            referrers: count_entries(&result, "referrers"),
+            countries: count_entries(&result, "countries"),
+        })
+    }
+}
+
+/// 解析 $facet 中的 { _id, clicks } 列表
+fn count_entries(result: &Document, facet: &str) -> Vec<CountEntry> {
+    let Ok(items) = result.get_array(facet) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| item.as_document())
+        .map(|d| CountEntry {
+            key: match d.get("_id") {
+                Some(Bson::String(s)) => s.clone(),
+                _ => String::new(),
+            },
+            clicks: match d.get("clicks") {
+                Some(Bson::Int32(n)) => *n as i64,
+                Some(Bson::Int64(n)) => *n,
+                _ => 0,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_submission() {
+        let issue = "请添加友链\n\n```yaml\nname: 示例站点\nurl: https://example.com\navatar: https://example.com/a.png\ndesc: 一个博客\n```\n";
+        let link = LinkService::parse_submission(issue).unwrap();
+        assert_eq!(link.name, "示例站点");
+        assert_eq!(link.url, "https://example.com");
+        assert_eq!(link.description.as_deref(), Some("一个博客"));
+
+        let json = "```json\n{\"name\": \"Site\", \"link\": \"https://site.dev\"}\n```";
+        let link = LinkService::parse_submission(json).unwrap();
+        assert_eq!(link.url, "https://site.dev");
+        assert!(link.avatar.is_none());
+
+        // 邮件正文可以直接是 YAML
+        let link = LinkService::parse_submission("name: Mail\nurl: https://mail.example\n").unwrap();
+        assert_eq!(link.name, "Mail");
+
+        assert!(LinkService::parse_submission("no fields here").is_err());
+        assert!(matches!(
+            LinkService::parse_submission("```\nname: x\nurl: ftp://x\n```"),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_public() {
+        assert!(LinkService::is_public(&doc! { "name": "legacy" }));
+        assert!(LinkService::is_public(&doc! { "moderation_state": "approved" }));
+        assert!(!LinkService::is_public(&doc! { "moderation_state": "pending" }));
+        assert!(!LinkService::is_public(&doc! { "moderation_state": "rejected" }));
+
+        // 跳转和统计接口对未公开的友链返回 404
+        assert!(LinkService::public_only(doc! { "moderation_state": "approved" }).is_ok());
+        for state in ["pending", "rejected"] {
+            let result = LinkService::public_only(doc! { "moderation_state": state });
+            assert!(matches!(result, Err(Error::NotFound(_))), "{}", state);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::config::settings::MaintenanceConfig;
+use chrono::Utc;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::RwLock;
+
+/// 维护模式的当前状态
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    pub message: String,
+    pub retry_after_secs: u64,
+    /// 维护期间仍可访问的路径前缀
+    pub allow_paths: Vec<String>,
+    /// 最近一次通过管理接口切换的管理员和时间（来自配置时为空）
+    pub updated_by: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// 当前状态（只保存在内存中：维护通常伴随 MongoDB 迁移，不依赖数据库）
+static STATE: Lazy<RwLock<MaintenanceState>> = Lazy::new(|| RwLock::new(from_config(&MaintenanceConfig::default())));
+
+fn from_config(config: &MaintenanceConfig) -> MaintenanceState {
+    MaintenanceState {
+        enabled: config.enabled,
+        message: config.message.clone(),
+        retry_after_secs: config.retry_after_secs,
+        allow_paths: config.allow_paths.clone(),
+        updated_by: None,
+        updated_at: None,
+    }
+}
+
+/// 初始化配置中的维护状态（启动时调用一次）
+pub fn init(config: &MaintenanceConfig) {
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = from_config(config);
+    if config.enabled {
+        warn!("Maintenance mode is enabled by configuration");
+    }
+}
+
+/// 当前状态
+pub fn current() -> MaintenanceState {
+    STATE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn is_allowed(allow_paths: &[String], path: &str) -> bool {
+    // 前缀按路径段匹配，"/" 只匹配首页
+    allow_paths
+        .iter()
+        .any(|p| path == p || (p != "/" && path.starts_with(&format!("{}/", p))))
+}
+
+/// 请求是否应被维护模式拦截
+pub fn blocks(path: &str) -> bool {
+    let state = STATE.read().unwrap_or_else(|e| e.into_inner());
+    state.enabled && !is_allowed(&state.allow_paths, path)
+}
+
+/// 开启或关闭维护模式（立即生效，重启后恢复为配置）；未指定的提示信息和重试间隔保持不变
+pub fn set(enabled: bool, message: Option<String>, retry_after_secs: Option<u64>, actor: &str) -> MaintenanceState {
+    let mut state = STATE.write().unwrap_or_else(|e| e.into_inner());
+    state.enabled = enabled;
+    if let Some(message) = message.filter(|m| !m.trim().is_empty()) {
+        state.message = message;
+    }
+    if let Some(retry_after_secs) = retry_after_secs {
+        state.retry_after_secs = retry_after_secs;
+    }
+    state.updated_by = Some(actor.to_string());
+    state.updated_at = Some(Utc::now().to_rfc3339());
+    info!("维护模式已{}（{}）", if enabled { "开启" } else { "关闭" }, actor);
+    state.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance() {
+        init(&MaintenanceConfig::default());
+        assert!(!blocks("/links"));
+
+        let allow_paths = MaintenanceConfig::default().allow_paths;
+        assert!(is_allowed(&allow_paths, "/"));
+        assert!(is_allowed(&allow_paths, "/api/admin/maintenance"));
+        assert!(is_allowed(&allow_paths, "/static/dashboard.css"));
+        assert!(!is_allowed(&allow_paths, "/links"));
+        assert!(!is_allowed(&allow_paths, "/api/adminx"));
+
+        let state = set(true, Some("迁移中".to_string()), None, "test");
+        assert_eq!(state.message, "迁移中");
+        assert_eq!(state.retry_after_secs, MaintenanceConfig::default().retry_after_secs);
+        assert!(blocks("/links"));
+        assert!(!blocks("/api/metrics"));
+
+        // 空白的提示信息不覆盖原有信息
+        let state = set(false, Some(" ".to_string()), Some(60), "test");
+        assert_eq!(state.message, "迁移中");
+        assert_eq!(state.retry_after_secs, 60);
+        assert!(!blocks("/links"));
+    }
+}
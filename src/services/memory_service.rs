@@ -1,4 +1,5 @@
 use crate::config::settings::MemoryConfig;
+use crate::services::event_bus::{self, Event};
 use crate::utils::jemalloc_interface::{JemallocError, JemallocInterface};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -584,18 +585,18 @@ impl MemoryManager {
 
     /// 清理缓存条目
     async fn cleanup_cache(&self) -> Result<usize, MemoryError> {
-        use crate::utils::cache::{cleanup_expired_cache, CACHE_BUCKET};
+        use crate::utils::cache::{self, cleanup_expired_cache};
 
         log::debug!("Starting cache cleanup operation");
 
         // 获取清理前的缓存条目数量
-        let before_count = CACHE_BUCKET.entry_count();
+        let (before_count, _) = cache::memory_usage();
         log::debug!("Cache entries before cleanup: {}", before_count);
 
         // 清理内存缓存中的过期条目
         match tokio::time::timeout(
             tokio::time::Duration::from_secs(30), // 30秒超时
-            CACHE_BUCKET.run_pending_tasks(),
+            cache::run_pending_tasks(),
         )
         .await
         {
@@ -635,7 +636,7 @@ impl MemoryManager {
         }
 
         // 获取清理后的缓存条目数量
-        let after_count = CACHE_BUCKET.entry_count();
+        let (after_count, _) = cache::memory_usage();
         let cleaned_count = before_count.saturating_sub(after_count);
 
         log::info!(
@@ -830,6 +831,11 @@ impl MemoryManager {
                     current_mb,
                     self.config.threshold_mb
                 );
+                event_bus::publish(Event::MemoryPressureChanged {
+                    from: old_pressure,
+                    to: new_pressure.clone(),
+                    usage_mb: current_mb,
+                });
             }
         }
 
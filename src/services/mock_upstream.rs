@@ -0,0 +1,141 @@
+use crate::config::settings::MockUpstreamsConfig;
+use crate::utils::rng;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use log::warn;
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::time::Duration;
+
+static MOCK_CONFIG: OnceCell<MockUpstreamsConfig> = OnceCell::new();
+
+/// 初始化模拟上游（启动时调用一次）
+///
+/// 开启后 NCM、codetime、CDN 图片、QQ OAuth 请求都由内置的假响应返回，用于压测和 CI 集成测试
+pub fn init(config: &MockUpstreamsConfig) {
+    if config.enabled {
+        warn!(
+            "模拟上游模式已开启，所有外部请求将返回假数据 (延迟 {}ms ± {}ms)",
+            config.latency_ms, config.jitter_ms
+        );
+    }
+    let _ = MOCK_CONFIG.set(config.clone());
+}
+
+/// 是否开启模拟上游
+pub fn is_enabled() -> bool {
+    MOCK_CONFIG.get().map(|c| c.enabled).unwrap_or(false)
+}
+
+/// 模拟网络延迟
+async fn simulate_latency() {
+    let Some(config) = MOCK_CONFIG.get() else {
+        return;
+    };
+    let jitter = if config.jitter_ms > 0 {
+        rng::random_range(0..=config.jitter_ms * 2) as i64 - config.jitter_ms as i64
+    } else {
+        0
+    };
+    let ms = (config.latency_ms as i64 + jitter).max(0) as u64;
+    if ms > 0 {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+}
+
+/// 由字符串派生稳定的数值（同一输入返回相同的假数据）
+fn seed_of(s: &str) -> u64 {
+    let digest = Sha256::digest(s.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap_or_default())
+}
+
+/// 网易云音乐 “正在播放” 接口的假响应（与真实接口的 data 结构一致）
+pub async fn ncm_now_play(user_id: u64) -> Value {
+    simulate_latency().await;
+    let song_id = 1_000_000 + (seed_of(&user_id.to_string()) % 1000) as i64;
+    json!({
+        "code": 200,
+        "data": {
+            "id": song_id,
+            "userId": user_id,
+            "avatar": "https://example.com/mock/avatar.png",
+            "userName": "mock-user",
+            "song": {
+                "id": song_id,
+                "name": "Mock Song",
+                "transNames": [],
+                "alias": [],
+                "artists": [{ "id": 1, "name": "Mock Artist" }],
+                "album": {
+                    "id": 1,
+                    "name": "Mock Album",
+                    "picUrl": "https://example.com/mock/album.png",
+                    "publishTime": 1_700_000_000_000i64,
+                    "artists": [{ "id": 1, "name": "Mock Artist" }]
+                }
+            }
+        }
+    })
+}
+
+/// codetime 统计的假响应
+pub async fn codetime_stats() -> Value {
+    simulate_latency().await;
+    json!({
+        "error": null,
+        "data": {
+            "total_minutes": 1234,
+            "today_minutes": 56,
+            "languages": [
+                { "name": "Rust", "minutes": 40 },
+                { "name": "TypeScript", "minutes": 16 }
+            ]
+        }
+    })
+}
+
+/// 图片下载的假响应：按 URL 生成固定颜色的 PNG 图片
+pub async fn image(url: &str) -> Vec<u8> {
+    simulate_latency().await;
+    let seed = seed_of(url).to_be_bytes();
+    let img = RgbImage::from_fn(256, 256, |x, y| {
+        image::Rgb([
+            seed[0].wrapping_add((x / 2) as u8),
+            seed[1].wrapping_add((y / 2) as u8),
+            seed[2],
+        ])
+    });
+    let mut output = Vec::new();
+    let _ = DynamicImage::ImageRgb8(img).write_to(&mut Cursor::new(&mut output), ImageFormat::Png);
+    output
+}
+
+/// QQ OAuth：授权码换取 access_token
+pub async fn qq_access_token(code: &str) -> String {
+    simulate_latency().await;
+    format!("mock_token_{}", code)
+}
+
+/// QQ OAuth：access_token 换取 openid（同一令牌返回相同的 openid）
+pub async fn qq_openid(access_token: &str) -> String {
+    simulate_latency().await;
+    format!("MOCKOPENID{:016X}", seed_of(access_token))
+}
+
+/// QQ OAuth：用户信息
+pub async fn qq_user_info(openid: &str) -> Value {
+    simulate_latency().await;
+    let suffix = &openid[openid.len().saturating_sub(6)..];
+    json!({
+        "ret": 0,
+        "msg": "",
+        "nickname": format!("mock-{}", suffix),
+        "figureurl": "https://example.com/mock/qq_30.png",
+        "figureurl_1": "https://example.com/mock/qq_50.png",
+        "figureurl_2": "https://example.com/mock/qq_100.png",
+        "figureurl_qq_1": "https://example.com/mock/qq_40.png",
+        "figureurl_qq_2": "https://example.com/mock/qq_100.png",
+        "gender": "男"
+    })
+}
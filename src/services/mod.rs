@@ -1,8 +1,39 @@
+pub mod abuse_service;
+pub mod audit_service;
+pub mod avatar_service;
+pub mod bench_service;
+pub mod blurhash_service;
+pub mod boot_report;
+pub mod calendar_service;
+pub mod cdn_service;
+pub mod command_service;
+pub mod dashboard_service;
 pub mod db_service;
 pub mod email_service;
+pub mod event_bus;
+pub mod feature_service;
 pub mod friend_avatar_service;
+pub mod github_link_service;
+pub mod graphql_service;
 pub mod image_service;
+pub mod inbound_email_service;
+pub mod ip_filter_service;
+pub mod link_service;
+pub mod maintenance_service;
 pub mod memory_service;
+pub mod mock_upstream;
 pub mod ncm_service;
+pub mod now_playing_service;
 pub mod oauth_service;
-pub mod verify_service;
\ No newline at end of file
+pub mod og_service;
+pub mod outbox_service;
+pub mod palette_service;
+pub mod search_service;
+pub mod spam_service;
+pub mod stats_service;
+pub mod upstream_fixtures;
+pub mod upstream_service;
+pub mod user_service;
+pub mod verify_service;
+pub mod weather_service;
+pub mod wallpaper_service;
\ No newline at end of file
@@ -1,9 +1,12 @@
 use aes::cipher::block_padding::Pkcs7;
 use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyInit};
 use aes::Aes128;
+use crate::services::mock_upstream;
+use crate::services::upstream_fixtures;
+use crate::services::upstream_service;
+use crate::utils::rng;
 use ecb::{Decryptor, Encryptor};
 use md5;
-use rand::RngExt;
 use reqwest::header::{HeaderMap, ACCEPT, ACCEPT_ENCODING, CONTENT_TYPE, COOKIE, USER_AGENT};
 use serde::Serialize;
 use serde_json::Value;
@@ -34,6 +37,13 @@ struct UserStatusDetailReqJson {
 }
 
 pub async fn get_ncm_now_play(user_id: u64) -> Result<Value, Box<dyn Error>> {
+    if mock_upstream::is_enabled() {
+        return Ok(mock_upstream::ncm_now_play(user_id).await);
+    }
+    if let Some(fixture) = upstream_fixtures::replay("ncm_now_play").await {
+        return Ok(fixture?);
+    }
+
     let req_json = create_user_status_detail_req_json(user_id);
     let encrypted_params = eapi_encrypt(USER_STATUS_DETAIL_API, &req_json);
 
@@ -53,19 +63,24 @@ pub async fn get_ncm_now_play(user_id: u64) -> Result<Value, Box<dyn Error>> {
     let cookie_string = format!("appver=9.3.35; buildver={}; MUSIC_U={}", buildver, music_u);
     headers.insert(COOKIE, cookie_string.parse()?);
 
-    let client = reqwest::Client::new();
-    let response = client
+    let client = upstream_service::client();
+    let request = client
         .post("https://interface3.music.163.com/eapi/social/user/status/detail")
         .headers(headers)
-        .body(encrypted_params)
-        .send()
-        .await?;
+        .body(encrypted_params);
+    let response = upstream_service::send(request).await?;
 
     // Body bytes
     let body_bytes = response.bytes().await?;
+    let json = decode_response(&body_bytes)?;
+    upstream_fixtures::record("ncm_now_play", &json).await;
+    Ok(json)
+}
 
+// 解析接口响应（明文 JSON 或 eapi 加密的 JSON）
+fn decode_response(body_bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
     // 1) 优先尝试直接按 JSON 解析（部分情况下接口会直接返回明文 JSON 错误信息）
-    if let Ok(text) = std::str::from_utf8(&body_bytes) {
+    if let Ok(text) = std::str::from_utf8(body_bytes) {
         if text.trim_start().starts_with('{') || text.trim_start().starts_with('[') {
             if let Ok(json) = serde_json::from_str::<Value>(text) {
                 return Ok(json);
@@ -131,7 +146,5 @@ fn create_user_status_detail_req_json(visitor_id: u64) -> String {
 }
 
 fn choose_user_agent() -> &'static str {
-    let mut rng = rand::rng();
-    let index = rng.random_range(0..USER_AGENT_LIST.len());
-    USER_AGENT_LIST[index]
+    rng::choose(USER_AGENT_LIST).copied().unwrap_or(USER_AGENT_LIST[0])
 }
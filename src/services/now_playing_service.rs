@@ -0,0 +1,198 @@
+use crate::services::ncm_service;
+use crate::utils::cache::{self, Namespace};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 未指定用户时查询的网易云音乐用户
+pub const DEFAULT_NCM_USER_ID: u64 = 515522946;
+
+/// 查询网易云音乐当前播放状态（用户不存在时返回 None）
+pub async fn now_playing(user_id: u64) -> Result<Option<Value>> {
+    Ok(now_playing_detail(user_id).await?.map(|(result, _)| result))
+}
+
+/// 当前播放状态及歌曲时长（毫秒，未知时为 0）
+pub async fn now_playing_detail(user_id: u64) -> Result<Option<(Value, i64)>> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let raw = ncm_service::get_ncm_now_play(user_id)
+        .await
+        .map_err(|e| Error::Internal(format!("ncm request failed: {}", e)))?;
+
+    let data = match raw.get("data") {
+        Some(v) if !v.is_null() => v,
+        _ => return Ok(None),
+    };
+
+    // 提取当前 songId 用于活跃度判断
+    let current_song_id = extract_song_id(data);
+
+    let is_inactive = handle_cache(user_id as i64, current_song_id, &now).await?;
+
+    // 组装返回结构
+    let mut result = build_base_result(data, user_id as i64, !is_inactive, &now);
+
+    if !is_inactive {
+        // song 细节
+        if let Some(song) = data.get("song") {
+            let song_obj = build_song_obj(song);
+            if let Some(obj) = result.as_object_mut() {
+                obj.insert("song".to_string(), song_obj);
+            }
+        }
+    }
+
+    let duration_ms = data["song"]["duration"].as_i64().unwrap_or_default();
+    Ok(Some((result, duration_ms)))
+}
+
+/// 播放进度：按 handle_cache 记录的开始时间估算（歌曲不一致或时长未知时为 None）
+pub async fn playback_progress(user_id: u64, song_id: i64, duration_ms: i64) -> Option<f32> {
+    let entry = cache::get_json::<NcmStatusEntry>(&Namespace::NcmStatus.key(user_id as i64)).await?;
+    if entry.song_id != song_id || duration_ms <= 0 {
+        return None;
+    }
+    let started = chrono::DateTime::parse_from_rfc3339(&entry.timestamp).ok()?;
+    let elapsed = (chrono::Utc::now() - started.with_timezone(&chrono::Utc)).num_milliseconds();
+    Some((elapsed as f32 / duration_ms as f32).clamp(0.0, 1.0))
+}
+
+/// 网易云音乐用户最近一次播放状态（ncm_status 命名空间）
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NcmStatusEntry {
+    user_id: i64,
+    song_id: i64,
+    /// 开始播放该歌曲的时间（RFC3339）
+    timestamp: String,
+}
+
+/// 处理简单缓存以判断活跃状态（5 分钟以上仍是同一首歌视为不活跃）
+pub async fn handle_cache(user_id: i64, song_id: i64, now_iso: &str) -> Result<bool> {
+    let key = Namespace::NcmStatus.key(user_id);
+    let last = cache::get_json::<NcmStatusEntry>(&key).await;
+
+    let is_inactive = last.as_ref().is_some_and(|last| {
+        let started = chrono::DateTime::parse_from_rfc3339(&last.timestamp).map(|dt| dt.with_timezone(&chrono::Utc));
+        last.song_id == song_id
+            && started.is_ok_and(|started| (chrono::Utc::now() - started).num_milliseconds() > 5 * 60 * 1000)
+    });
+
+    // 无缓存、无法解析或歌曲变更时写入当前状态
+    if last.is_none_or(|last| last.song_id != song_id) {
+        let entry = NcmStatusEntry {
+            user_id,
+            song_id,
+            timestamp: now_iso.to_string(),
+        };
+        cache::put_json(&key, &entry).await;
+    }
+
+    Ok(is_inactive)
+}
+
+/// 提取当前播放的歌曲 ID
+pub fn extract_song_id(data: &Value) -> i64 {
+    data.get("song")
+        .and_then(|s| s.get("id"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default()
+}
+
+// 将毫秒时间戳转换为 RFC3339 字符串
+fn ms_to_rfc3339(ms: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp_millis(ms)
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// 构建基础返回结构（不含 song）
+pub fn build_base_result(
+    data: &Value,
+    user_id_fallback: i64,
+    active: bool,
+    last_update_iso: &str,
+) -> Value {
+    serde_json::json!({
+        "id": data.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
+        "user": {
+            "id": data.get("userId").and_then(|v| v.as_i64()).unwrap_or(user_id_fallback),
+            "avatar": data.get("avatar").and_then(|v| v.as_str()).unwrap_or_default(),
+            "name": data.get("userName").and_then(|v| v.as_str()).unwrap_or_default(),
+            "active": active,
+        },
+        "lastUpdate": last_update_iso,
+    })
+}
+
+/// 根据 TS 结构组装歌曲对象
+pub fn build_song_obj(song: &Value) -> Value {
+    let trans_names = song
+        .get("transNames")
+        .or_else(|| {
+            song.get("extProperties")
+                .and_then(|ep| ep.get("transNames"))
+        })
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let alias = song
+        .get("alias")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let artists = song
+        .get("artists")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .map(|a| {
+                    serde_json::json!({
+                        "id": a.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
+                        "name": a.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let album = song.get("album").cloned().unwrap_or(Value::Null);
+    let album_artists = album
+        .get("artists")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .map(|a| {
+                    serde_json::json!({
+                        "id": a.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
+                        "name": a.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let publish_time_iso = album
+        .get("publishTime")
+        .and_then(|v| v.as_i64())
+        .map(ms_to_rfc3339)
+        .unwrap_or_default();
+
+    serde_json::json!({
+        "name": song.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+        "transNames": trans_names,
+        "alias": alias,
+        "id": song.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
+        "artists": artists,
+        "album": {
+            "name": album.get("name").and_then(|v| v.as_str()).unwrap_or_default(),
+            "id": album.get("id").and_then(|v| v.as_i64()).unwrap_or_default(),
+            "image": album.get("picUrl").and_then(|v| v.as_str()).unwrap_or_default(),
+            "publishTime": publish_time_iso,
+            "artists": album_artists,
+        }
+    })
+}
+
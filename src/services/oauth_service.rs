@@ -1,5 +1,8 @@
 use crate::{Result, Error};
 use crate::config::settings::OAuthConfig;
+use crate::services::mock_upstream;
+use crate::services::upstream_fixtures;
+use crate::services::upstream_service::{self, HttpClientService};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -16,16 +19,32 @@ pub struct QQUserInfo {
     pub gender: Option<String>,
 }
 
+impl QQUserInfo {
+    // 从 get_user_info 接口的响应中提取用户信息
+    fn from_api(openid: &str, data: &Value) -> Self {
+        Self {
+            openid: openid.to_string(),
+            nickname: data["nickname"].as_str().map(|s| s.to_string()),
+            figureurl: data["figureurl"].as_str().map(|s| s.to_string()),
+            figureurl_1: data["figureurl_1"].as_str().map(|s| s.to_string()),
+            figureurl_2: data["figureurl_2"].as_str().map(|s| s.to_string()),
+            figureurl_qq_1: data["figureurl_qq_1"].as_str().map(|s| s.to_string()),
+            figureurl_qq_2: data["figureurl_qq_2"].as_str().map(|s| s.to_string()),
+            gender: data["gender"].as_str().map(|s| s.to_string()),
+        }
+    }
+}
+
 pub struct OAuthService {
     config: OAuthConfig,
     client: Client,
 }
 
 impl OAuthService {
-    pub fn new(config: OAuthConfig) -> Self {
+    pub fn new(config: OAuthConfig, http: &HttpClientService) -> Self {
         Self {
             config,
-            client: Client::new(),
+            client: http.client().clone(),
         }
     }
     
@@ -44,6 +63,10 @@ impl OAuthService {
     
     // 使用授权码获取QQ访问令牌
     pub async fn get_qq_access_token(&self, code: &str) -> Result<String> {
+        if mock_upstream::is_enabled() {
+            return Ok(mock_upstream::qq_access_token(code).await);
+        }
+
         let url = format!(
             "https://graph.qq.com/oauth2.0/token?grant_type=authorization_code&client_id={}&client_secret={}&code={}&redirect_uri={}",
             self.config.qq_app_id,
@@ -52,9 +75,7 @@ impl OAuthService {
             urlencoding::encode(&self.config.redirect_uri)
         );
         
-        let response = self.client
-            .get(&url)
-            .send()
+        let response = upstream_service::send(self.client.get(&url))
             .await
             .map_err(|e| Error::Internal(format!("Failed to get access token: {}", e)))?;
             
@@ -77,14 +98,16 @@ impl OAuthService {
     
     // 使用访问令牌获取OpenID
     pub async fn get_qq_openid(&self, access_token: &str) -> Result<String> {
+        if mock_upstream::is_enabled() {
+            return Ok(mock_upstream::qq_openid(access_token).await);
+        }
+
         let url = format!(
             "https://graph.qq.com/oauth2.0/me?access_token={}&fmt=json",
             access_token
         );
         
-        let response = self.client
-            .get(&url)
-            .send()
+        let response = upstream_service::send(self.client.get(&url))
             .await
             .map_err(|e| Error::Internal(format!("Failed to get OpenID: {}", e)))?;
             
@@ -102,6 +125,14 @@ impl OAuthService {
     
     // 获取QQ用户信息
     pub async fn get_qq_user_info(&self, access_token: &str, openid: &str) -> Result<QQUserInfo> {
+        if mock_upstream::is_enabled() {
+            let data = mock_upstream::qq_user_info(openid).await;
+            return Ok(QQUserInfo::from_api(openid, &data));
+        }
+        if let Some(fixture) = upstream_fixtures::replay("qq_user_info").await {
+            return Ok(QQUserInfo::from_api(openid, &fixture?));
+        }
+
         let url = format!(
             "https://graph.qq.com/user/get_user_info?access_token={}&oauth_consumer_key={}&openid={}&fmt=json",
             access_token,
@@ -109,9 +140,7 @@ impl OAuthService {
             openid
         );
         
-        let response = self.client
-            .get(&url)
-            .send()
+        let response = upstream_service::send(self.client.get(&url))
             .await
             .map_err(|e| Error::Internal(format!("Failed to get user info: {}", e)))?;
             
@@ -119,20 +148,28 @@ impl OAuthService {
             .json()
             .await
             .map_err(|e| Error::Internal(format!("Failed to parse response: {}", e)))?;
+        upstream_fixtures::record("qq_user_info", &data).await;
             
         if data["ret"].as_i64().unwrap_or(-1) != 0 {
             return Err(Error::Internal(format!("QQ API error: {}", data["msg"].as_str().unwrap_or("Unknown error"))));
         }
         
-        Ok(QQUserInfo {
-            openid: openid.to_string(),
-            nickname: data["nickname"].as_str().map(|s| s.to_string()),
-            figureurl: data["figureurl"].as_str().map(|s| s.to_string()),
-            figureurl_1: data["figureurl_1"].as_str().map(|s| s.to_string()),
-            figureurl_2: data["figureurl_2"].as_str().map(|s| s.to_string()),
-            figureurl_qq_1: data["figureurl_qq_1"].as_str().map(|s| s.to_string()),
-            figureurl_qq_2: data["figureurl_qq_2"].as_str().map(|s| s.to_string()),
-            gender: data["gender"].as_str().map(|s| s.to_string()),
-        })
+        Ok(QQUserInfo::from_api(openid, &data))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::upstream_fixtures::test_fixture;
+
+    #[test]
+    fn test_parse_user_info_fixture() {
+        let data = test_fixture("qq_user_info");
+        assert_eq!(data["ret"], 0);
+        let info = QQUserInfo::from_api("OPENID", &data);
+        assert_eq!(info.openid, "OPENID");
+        assert_eq!(info.nickname.as_deref(), Some("redacted"));
+        assert_eq!(info.gender.as_deref(), Some("男"));
+        assert!(info.figureurl_qq_2.is_some_and(|url| url.starts_with("https://")));
+    }
+}
@@ -0,0 +1,437 @@
+use crate::config::settings::OgImageConfig;
+use crate::utils::cache;
+use crate::utils::request_context::RequestContext;
+use crate::utils::transcode_limiter;
+use crate::{Error, Result};
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+use log::{debug, info, warn};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::sync::Arc;
+
+pub const OG_WIDTH: u32 = 1200;
+pub const OG_HEIGHT: u32 = 630;
+
+const PADDING: f32 = 80.0;
+const TITLE_SIZE: f32 = 68.0;
+const SUBTITLE_SIZE: f32 = 34.0;
+const SITE_SIZE: f32 = 28.0;
+const TITLE_MAX_LINES: usize = 3;
+const SUBTITLE_MAX_LINES: usize = 2;
+/// 头像直径（显示在右侧，垂直居中）
+const AVATAR_SIZE: u32 = 200;
+/// 头像外圈（强调色）宽度
+const AVATAR_RING: f32 = 6.0;
+/// 头像与文字的间距
+const AVATAR_GAP: f32 = 48.0;
+
+/// 卡片配色
+#[derive(Debug, Clone, Copy)]
+struct Theme {
+    /// 覆盖在背景图上的遮罩色
+    overlay: [u8; 3],
+    /// 遮罩不透明度
+    overlay_alpha: f32,
+    title: [u8; 3],
+    subtitle: [u8; 3],
+    accent: [u8; 3],
+}
+
+impl Theme {
+    fn from_config(config: &OgImageConfig) -> Self {
+        let accent = parse_hex_color(&config.accent_color).unwrap_or_else(|| {
+            warn!("Invalid og_image.accent_color {}, using default", config.accent_color);
+            [124, 92, 255]
+        });
+        match config.theme.as_str() {
+            "light" => Self {
+                overlay: [250, 250, 252],
+                overlay_alpha: 0.72,
+                title: [20, 20, 30],
+                subtitle: [80, 80, 96],
+                accent,
+            },
+            other => {
+                if other != "dark" {
+                    warn!("Unknown og_image.theme {}, using dark", other);
+                }
+                Self {
+                    overlay: [14, 16, 24],
+                    overlay_alpha: 0.62,
+                    title: [255, 255, 255],
+                    subtitle: [200, 202, 214],
+                    accent,
+                }
+            }
+        }
+    }
+}
+
+/// 卡片内容
+pub struct OgCard {
+    pub title: String,
+    pub subtitle: String,
+    /// 背景原图数据，为空时使用渐变背景
+    pub background: Option<Vec<u8>>,
+    /// 头像原图数据，为空时不显示头像
+    pub avatar: Option<Vec<u8>>,
+}
+
+/// Open Graph 分享卡片生成服务
+///
+/// 字体在启动时加载一次；渲染在阻塞线程池中进行，结果按参数缓存到硬盘
+pub struct OgService {
+    fonts: Arc<Vec<FontVec>>,
+    theme: Theme,
+    site_name: String,
+    /// 渲染配置（主题、强调色、站点名称、字体列表）的摘要，配置变化后旧卡片不再命中缓存
+    config_tag: String,
+}
+
+impl OgService {
+    pub fn new(config: &OgImageConfig) -> Self {
+        let mut fonts = Vec::new();
+        for path in &config.fonts {
+            match load_font(path) {
+                Ok(font) => fonts.push(font),
+                Err(e) => debug!("Skipping OG font {}: {}", path, e),
+            }
+        }
+        if fonts.is_empty() {
+            warn!("未找到可用的 OG 字体，/images/og 将不可用（请检查 og_image.fonts）");
+        } else {
+            info!("OG 卡片已加载 {} 个字体", fonts.len());
+        }
+
+        Self {
+            fonts: Arc::new(fonts),
+            theme: Theme::from_config(config),
+            site_name: config.site_name.clone(),
+            config_tag: config_tag(config),
+        }
+    }
+
+    /// 缓存 key（参数 + 渲染配置 + 格式）
+    pub fn cache_key(&self, title: &str, subtitle: &str, background: &str, avatar: &str, format: ImageFormat) -> String {
+        let mut hasher = Sha256::new();
+        for part in [title, subtitle, background, avatar, &self.config_tag] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+        format!(
+            "og:{}:{}:{}",
+            cache::derived_tag(),
+            hex::encode(&hasher.finalize()[..12]),
+            if format == ImageFormat::WebP { "webp" } else { "png" }
+        )
+    }
+
+    /// 渲染分享卡片并写入硬盘缓存（调用方先用 cache_key 查询缓存）；已超过请求时限时不再渲染
+    pub async fn render_and_cache(
+        &self,
+        ctx: &RequestContext,
+        cache_key: String,
+        card: OgCard,
+        format: ImageFormat,
+    ) -> Result<Vec<u8>> {
+        if self.fonts.is_empty() {
+            return Err(Error::Internal("No OG font available".to_string()));
+        }
+        ctx.check("OG render")?;
+
+        let fonts = Arc::clone(&self.fonts);
+        let theme = self.theme;
+        let site_name = self.site_name.clone();
+        let encoded = transcode_limiter::run(move || {
+            let canvas = render(&fonts, theme, &card, &site_name);
+            let mut output = Vec::new();
+            image::DynamicImage::ImageRgba8(canvas)
+                .to_rgb8()
+                .write_to(&mut Cursor::new(&mut output), format)
+                .map(|_| output)
+                .map_err(|e| Error::Internal(format!("Failed to encode OG image: {}", e)))
+        })
+        .await??;
+
+        {
+            let encoded = encoded.clone();
+            tokio::task::spawn_blocking(move || cache::put_disk(&cache_key, &encoded));
+        }
+        Ok(encoded)
+    }
+}
+
+fn load_font(path: &str) -> std::result::Result<FontVec, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    // .ttc 字体集合取第一个字体
+    FontVec::try_from_vec_and_index(data, 0).map_err(|e| e.to_string())
+}
+
+fn parse_hex_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.trim().strip_prefix('#').unwrap_or(s.trim());
+    if hex.len() != 6 {
+        return None;
+    }
+    let bytes = hex::decode(hex).ok()?;
+    Some([bytes[0], bytes[1], bytes[2]])
+}
+
+/// 渲染卡片（阻塞）
+// 渲染配置摘要：影响卡片外观的配置项依次哈希
+fn config_tag(config: &OgImageConfig) -> String {
+    let mut hasher = Sha256::new();
+    let parts = [&config.theme, &config.accent_color, &config.site_name].into_iter().chain(&config.fonts);
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+fn render(fonts: &[FontVec], theme: Theme, card: &OgCard, site_name: &str) -> RgbaImage {
+    let mut canvas = card
+        .background
+        .as_deref()
+        .and_then(|bytes| match image::load_from_memory(bytes) {
+            Ok(img) => Some(img),
+            Err(e) => {
+                warn!("Failed to decode OG background: {}", e);
+                None
+            }
+        })
+        .map(|img| {
+            let filled = img.resize_to_fill(OG_WIDTH, OG_HEIGHT, FilterType::Triangle).to_rgba8();
+            imageops::fast_blur(&filled, 6.0)
+        })
+        .unwrap_or_else(|| gradient(theme));
+
+    // 遮罩，保证文字可读
+    for pixel in canvas.pixels_mut() {
+        blend(pixel, theme.overlay, theme.overlay_alpha);
+    }
+
+    // 左侧强调色条
+    for y in (PADDING as u32)..(OG_HEIGHT - PADDING as u32) {
+        for x in 0..12 {
+            canvas.put_pixel(x, y, Rgba([theme.accent[0], theme.accent[1], theme.accent[2], 255]));
+        }
+    }
+
+    // 头像解码失败时按没有头像排版
+    let avatar = card.avatar.as_deref().and_then(|bytes| match image::load_from_memory(bytes) {
+        Ok(img) => Some(img.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3).to_rgba8()),
+        Err(e) => {
+            warn!("Failed to decode OG avatar: {}", e);
+            None
+        }
+    });
+    let mut max_width = OG_WIDTH as f32 - PADDING * 2.0;
+    if let Some(avatar) = &avatar {
+        let left = OG_WIDTH - PADDING as u32 - AVATAR_SIZE;
+        draw_avatar(&mut canvas, avatar, left, (OG_HEIGHT - AVATAR_SIZE) / 2, theme.accent);
+        max_width -= AVATAR_SIZE as f32 + AVATAR_GAP;
+    }
+
+    let title_lines = wrap(fonts, &card.title, TITLE_SIZE, max_width, TITLE_MAX_LINES);
+    let subtitle_lines = wrap(fonts, &card.subtitle, SUBTITLE_SIZE, max_width, SUBTITLE_MAX_LINES);
+
+    let title_height = title_lines.len() as f32 * TITLE_SIZE * 1.2;
+    let subtitle_height = if subtitle_lines.is_empty() {
+        0.0
+    } else {
+        24.0 + subtitle_lines.len() as f32 * SUBTITLE_SIZE * 1.35
+    };
+    // 标题 + 副标题整体垂直居中（略偏上，为底部站点名留出空间）
+    let mut y = ((OG_HEIGHT as f32 - title_height - subtitle_height) / 2.0 - 20.0).max(PADDING);
+
+    for line in &title_lines {
+        draw_text(&mut canvas, fonts, line, TITLE_SIZE, PADDING, y, theme.title);
+        y += TITLE_SIZE * 1.2;
+    }
+    y += 24.0;
+    for line in &subtitle_lines {
+        draw_text(&mut canvas, fonts, line, SUBTITLE_SIZE, PADDING, y, theme.subtitle);
+        y += SUBTITLE_SIZE * 1.35;
+    }
+
+    if !site_name.is_empty() {
+        let y = OG_HEIGHT as f32 - PADDING - SITE_SIZE;
+        draw_text(&mut canvas, fonts, site_name, SITE_SIZE, PADDING, y, theme.accent);
+    }
+    canvas
+}
+
+/// 将头像裁剪为圆形绘制在 (left, top)，外圈为强调色（边缘抗锯齿）
+fn draw_avatar(canvas: &mut RgbaImage, avatar: &RgbaImage, left: u32, top: u32, accent: [u8; 3]) {
+    let radius = AVATAR_SIZE as f32 / 2.0;
+    for (x, y, pixel) in avatar.enumerate_pixels() {
+        let dx = x as f32 + 0.5 - radius;
+        let dy = y as f32 + 0.5 - radius;
+        let distance = (dx * dx + dy * dy).sqrt();
+        // 超出圆形的部分不绘制；外圈和头像之间、外圈外缘各有 1 像素过渡
+        let coverage = (radius - distance).clamp(0.0, 1.0);
+        if coverage == 0.0 {
+            continue;
+        }
+        let target = canvas.get_pixel_mut(left + x, top + y);
+        let inner = (radius - AVATAR_RING - distance).clamp(0.0, 1.0);
+        if inner < 1.0 {
+            blend(target, accent, coverage * (1.0 - inner));
+        }
+        if inner > 0.0 {
+            let alpha = inner * pixel[3] as f32 / 255.0;
+            blend(target, [pixel[0], pixel[1], pixel[2]], alpha);
+        }
+    }
+}
+
+fn gradient(theme: Theme) -> RgbaImage {
+    RgbaImage::from_fn(OG_WIDTH, OG_HEIGHT, |x, y| {
+        let t = (x + y) as f32 / (OG_WIDTH + OG_HEIGHT) as f32;
+        let mix = |a: u8, b: u8| (a as f32 * (1.0 - t * 0.35) + b as f32 * t * 0.35) as u8;
+        Rgba([
+            mix(theme.overlay[0], theme.accent[0]),
+            mix(theme.overlay[1], theme.accent[1]),
+            mix(theme.overlay[2], theme.accent[2]),
+            255,
+        ])
+    })
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: [u8; 3], alpha: f32) {
+    for (channel, target) in pixel.0.iter_mut().zip(color) {
+        *channel = (*channel as f32 * (1.0 - alpha) + target as f32 * alpha).round() as u8;
+    }
+}
+
+/// 选择包含该字符的第一个字体（均不包含时使用第一个字体）
+fn font_for(fonts: &[FontVec], c: char) -> &FontVec {
+    fonts
+        .iter()
+        .find(|f| f.glyph_id(c).0 != 0)
+        .unwrap_or(&fonts[0])
+}
+
+fn char_advance(fonts: &[FontVec], c: char, size: f32) -> f32 {
+    let font = font_for(fonts, c);
+    font.as_scaled(PxScale::from(size)).h_advance(font.glyph_id(c))
+}
+
+fn text_width(fonts: &[FontVec], text: &str, size: f32) -> f32 {
+    text.chars().map(|c| char_advance(fonts, c, size)).sum()
+}
+
+/// 按宽度折行：拉丁文本优先在空格处断行，CJK 文本按字符断行；超出行数时末行以省略号结尾
+fn wrap(fonts: &[FontVec], text: &str, size: f32, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut width = 0.0;
+
+    for c in text.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+        let advance = char_advance(fonts, c, size);
+        if width + advance > max_width && !line.is_empty() {
+            let mut next = String::new();
+            if c.is_alphanumeric() && c.is_ascii() {
+                if let Some(pos) = line.rfind(' ') {
+                    next = line[pos + 1..].to_string();
+                    line.truncate(pos);
+                }
+            }
+            lines.push(std::mem::take(&mut line));
+            line = next;
+            width = text_width(fonts, &line, size);
+            if c == ' ' && line.is_empty() {
+                continue;
+            }
+        }
+        line.push(c);
+        width += advance;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = &mut lines[max_lines - 1];
+        let ellipsis = char_advance(fonts, '…', size);
+        while !last.is_empty() && text_width(fonts, last, size) + ellipsis > max_width {
+            last.pop();
+        }
+        last.push('…');
+    }
+    lines
+}
+
+/// 绘制一行文本，`top` 为行顶部坐标
+fn draw_text(canvas: &mut RgbaImage, fonts: &[FontVec], text: &str, size: f32, left: f32, top: f32, color: [u8; 3]) {
+    let scale = PxScale::from(size);
+    let mut x = left;
+    let mut prev: Option<(usize, ab_glyph::GlyphId)> = None;
+
+    for c in text.chars() {
+        let font = font_for(fonts, c);
+        let font_index = fonts.iter().position(|f| std::ptr::eq(f, font)).unwrap_or(0);
+        let scaled = font.as_scaled(scale);
+        let id = font.glyph_id(c);
+        if let Some((prev_index, prev_id)) = prev {
+            if prev_index == font_index {
+                x += scaled.kern(prev_id, id);
+            }
+        }
+
+        let glyph = id.with_scale_and_position(scale, point(x, top + scaled.ascent()));
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i32 + gx as i32;
+                let py = bounds.min.y as i32 + gy as i32;
+                if px >= 0 && py >= 0 && (px as u32) < canvas.width() && (py as u32) < canvas.height() {
+                    blend(canvas.get_pixel_mut(px as u32, py as u32), color, coverage.min(1.0));
+                }
+            });
+        }
+        x += scaled.h_advance(id);
+        prev = Some((font_index, id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#7c5cff"), Some([124, 92, 255]));
+        assert_eq!(parse_hex_color("ffffff"), Some([255, 255, 255]));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#zzzzzz"), None);
+    }
+
+    #[test]
+    fn test_config_tag() {
+        let base = OgImageConfig::default();
+        let tag = config_tag(&base);
+        assert_eq!(tag, config_tag(&OgImageConfig::default()));
+        // 强调色或字体变化后缓存 key 随之变化
+        let accent = OgImageConfig { accent_color: "#ff0000".into(), ..OgImageConfig::default() };
+        assert_ne!(config_tag(&accent), tag);
+        let fonts = OgImageConfig { fonts: vec!["/tmp/other.ttf".into()], ..OgImageConfig::default() };
+        assert_ne!(config_tag(&fonts), tag);
+    }
+
+    #[test]
+    fn test_draw_avatar() {
+        let mut canvas = RgbaImage::from_pixel(OG_WIDTH, OG_HEIGHT, Rgba([0, 0, 0, 255]));
+        let avatar = RgbaImage::from_pixel(AVATAR_SIZE, AVATAR_SIZE, Rgba([255, 0, 0, 255]));
+        draw_avatar(&mut canvas, &avatar, 100, 100, [0, 0, 255]);
+
+        let center = 100 + AVATAR_SIZE / 2;
+        // 圆心为头像，边缘为外圈，圆形外的角落不绘制
+        assert_eq!(canvas.get_pixel(center, center), &Rgba([255, 0, 0, 255]));
+        assert_eq!(canvas.get_pixel(center, 102), &Rgba([0, 0, 255, 255]));
+        assert_eq!(canvas.get_pixel(101, 101), &Rgba([0, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,351 @@
+use crate::config::settings::{EmailConfig, OutboxConfig};
+use crate::services::db_service;
+use crate::services::email_service::EmailService;
+use crate::services::event_bus::{self, Event};
+use crate::services::upstream_service;
+use crate::{Error, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use mongodb::bson::{self, doc, Bson, Document};
+use once_cell::sync::OnceCell;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+
+const OUTBOX_COLLECTION: &str = "outbox";
+/// 每轮最多投递的条数
+const BATCH_SIZE: i64 = 50;
+/// 投递中的条目在该时间内不会被再次领取（进程在投递中退出时，超时后重新投递）
+const LEASE_SECS: i64 = 5 * 60;
+/// 重试退避的上限（秒）
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+/// 单次 webhook 投递的超时
+const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+static OUTBOX_CONFIG: OnceCell<OutboxConfig> = OnceCell::new();
+
+/// 初始化投递配置（启动时调用一次）
+pub fn init(config: &OutboxConfig) {
+    if !config.webhooks.is_empty() {
+        info!("已配置 {} 个 webhook", config.webhooks.len());
+    }
+    let _ = OUTBOX_CONFIG.set(config.clone());
+}
+
+fn timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// 发出事件：先写入 outbox 等待持久投递，再通知进程内订阅者
+///
+/// 在业务数据写入成功后立即调用；进程内订阅者总会收到通知，
+/// 写入 outbox 失败时记录日志并返回错误。业务数据已提交时调用方应忽略该错误，
+/// 否则重试会因为数据已经变更而不再发出事件
+pub async fn emit(event: Event) -> Result<()> {
+    let queued = enqueue(&event).await;
+    if let Err(e) = &queued {
+        error!("Failed to write outbox entry for {}: {}", event.name(), e);
+    }
+    event_bus::publish(event);
+    queued.map(|_| ())
+}
+
+/// 为订阅该事件的每个投递目标写入一条待投递记录
+async fn enqueue(event: &Event) -> Result<usize> {
+    let Some(config) = OUTBOX_CONFIG.get() else {
+        return Ok(0);
+    };
+    let entries = outbox_entries(config, event, Utc::now())?;
+    for entry in &entries {
+        db_service::insert_one(OUTBOX_COLLECTION, entry.clone()).await?;
+    }
+    Ok(entries.len())
+}
+
+/// 订阅该事件的投递目标：(类型, 地址)
+fn targets<'a>(config: &'a OutboxConfig, name: &str) -> Vec<(&'static str, &'a str)> {
+    let mut targets: Vec<(&str, &str)> = config
+        .webhooks
+        .iter()
+        .filter(|w| w.events.is_empty() || w.events.iter().any(|e| e == name))
+        .map(|w| ("webhook", w.url.as_str()))
+        .collect();
+    if let Some(email) = config.notify_email.as_deref().filter(|e| !e.is_empty()) {
+        if config.notify_events.iter().any(|e| e == name) {
+            targets.push(("email", email));
+        }
+    }
+    targets
+}
+
+/// 是否配置了任何投递目标
+fn has_targets(config: &OutboxConfig) -> bool {
+    !config.webhooks.is_empty()
+        || (config.notify_email.as_deref().is_some_and(|e| !e.is_empty()) && !config.notify_events.is_empty())
+}
+
+/// 为每个投递目标生成一条待投递记录
+fn outbox_entries(config: &OutboxConfig, event: &Event, now: DateTime<Utc>) -> Result<Vec<Document>> {
+    let name = event.name();
+    let targets = targets(config, name);
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let payload = bson::to_bson(event).map_err(|e| Error::Internal(e.to_string()))?;
+    let now = timestamp(now);
+    Ok(targets
+        .into_iter()
+        .map(|(kind, target)| {
+            doc! {
+                "kind": kind,
+                "target": target,
+                "event": name,
+                "payload": payload.clone(),
+                "state": "pending",
+                "attempts": 0,
+                "next_attempt_at": &now,
+                "created_at": &now,
+            }
+        })
+        .collect())
+}
+
+/// 根据本次投递结果生成记录的更新：成功标记 delivered，
+/// 失败按指数退避安排重试，超过最大次数标记 failed
+fn delivery_update(result: Result<()>, attempts: u32, max_attempts: u32, now: DateTime<Utc>) -> Document {
+    match result {
+        Ok(()) => doc! { "$set": { "state": "delivered", "delivered_at": timestamp(now) } },
+        Err(e) if attempts >= max_attempts => doc! { "$set": { "state": "failed", "last_error": e.to_string() } },
+        Err(e) => {
+            let backoff = (30i64 << (attempts.max(1) - 1).min(16)).min(MAX_BACKOFF_SECS);
+            doc! { "$set": {
+                "next_attempt_at": timestamp(now + Duration::seconds(backoff)),
+                "last_error": e.to_string(),
+            } }
+        }
+    }
+}
+
+/// 启动后台投递任务（未配置任何投递目标时不启动，避免空轮询数据库）
+pub fn start_dispatcher(email: EmailConfig) -> Option<JoinHandle<()>> {
+    let config = OUTBOX_CONFIG.get().cloned().unwrap_or_default();
+    if !has_targets(&config) {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let client = upstream_service::client();
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(config.poll_interval_secs.max(1)));
+        loop {
+            interval.tick().await;
+            if let Err(e) = dispatch_pending(&config, &email, &client).await {
+                warn!("Outbox dispatch failed: {}", e);
+            }
+        }
+    }))
+}
+
+async fn dispatch_pending(config: &OutboxConfig, email: &EmailConfig, client: &reqwest::Client) -> Result<()> {
+    let now = Utc::now();
+    let due = db_service::aggregate(
+        OUTBOX_COLLECTION,
+        vec![
+            doc! { "$match": { "state": "pending", "next_attempt_at": { "$lte": timestamp(now) } } },
+            doc! { "$sort": { "next_attempt_at": 1 } },
+            doc! { "$limit": BATCH_SIZE },
+        ],
+    )
+    .await?;
+
+    for entry in due {
+        let Some(id) = entry.get("_id").cloned() else {
+            continue;
+        };
+        // 领取：推迟下次投递时间并计数，其他实例或下一轮在租期内不会重复领取
+        let claimed = db_service::update_one(
+            OUTBOX_COLLECTION,
+            doc! { "_id": &id, "state": "pending", "next_attempt_at": entry.get("next_attempt_at").cloned().unwrap_or(Bson::Null) },
+            doc! {
+                "$set": { "next_attempt_at": timestamp(now + Duration::seconds(LEASE_SECS)) },
+                "$inc": { "attempts": 1 },
+            },
+        )
+        .await?;
+        if claimed == 0 {
+            continue;
+        }
+
+        let attempts = entry.get_i32("attempts").unwrap_or(0) as u32 + 1;
+        let result = deliver(&entry, config, email, client).await;
+        if let Err(e) = &result {
+            if attempts >= config.max_attempts {
+                error!(
+                    "Outbox delivery gave up after {} attempts ({} -> {}): {}",
+                    attempts,
+                    entry.get_str("event").unwrap_or_default(),
+                    entry.get_str("target").unwrap_or_default(),
+                    e
+                );
+            }
+        }
+        let update = delivery_update(result, attempts, config.max_attempts, Utc::now());
+        db_service::update_one(OUTBOX_COLLECTION, doc! { "_id": &id }, update).await?;
+    }
+    Ok(())
+}
+
+async fn deliver(entry: &Document, config: &OutboxConfig, email: &EmailConfig, client: &reqwest::Client) -> Result<()> {
+    let event = entry.get_str("event").unwrap_or_default();
+    let target = entry.get_str("target").unwrap_or_default();
+    let payload = entry.get("payload").cloned().unwrap_or(Bson::Null).into_relaxed_extjson();
+
+    match entry.get_str("kind").unwrap_or_default() {
+        "webhook" => {
+            let body = serde_json::json!({
+                "id": entry.get_object_id("_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+                "event": event,
+                "created_at": entry.get_str("created_at").unwrap_or_default(),
+                "data": payload,
+            })
+            .to_string();
+            let mut request = client
+                .post(target)
+                .timeout(DELIVERY_TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Space-Event", event);
+            let secret = config
+                .webhooks
+                .iter()
+                .find(|w| w.url == target)
+                .and_then(|w| w.secret.as_deref());
+            if let Some(secret) = secret {
+                request = request.header("X-Space-Signature", format!("sha256={}", sign(secret, &body)));
+            }
+            let response = request
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| Error::Internal(format!("webhook request failed: {}", e)))?;
+            if !response.status().is_success() {
+                return Err(Error::Internal(format!("webhook returned {}", response.status())));
+            }
+            Ok(())
+        }
+        "email" => {
+            let text = format!(
+                "事件：{}\n时间：{}\n\n{}",
+                event,
+                entry.get_str("created_at").unwrap_or_default(),
+                serde_json::to_string_pretty(&payload).unwrap_or_default()
+            );
+            EmailService::new(email.clone())?
+                .send_email(target, &format!("[space-api] {}", event), &text, None)
+                .await
+        }
+        other => Err(Error::Internal(format!("Unknown outbox entry kind: {}", other))),
+    }
+}
+
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::WebhookConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn config(url: &str) -> OutboxConfig {
+        OutboxConfig {
+            notify_email: Some("admin@example.com".into()),
+            notify_events: vec!["link.submitted".into()],
+            webhooks: vec![
+                WebhookConfig {
+                    url: url.into(),
+                    events: Vec::new(),
+                    secret: Some("secret".into()),
+                },
+                WebhookConfig {
+                    url: "https://example.com/approved".into(),
+                    events: vec!["link.approved".into()],
+                    secret: None,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_enqueue_entries() {
+        let config = config("https://example.com/all");
+        let now = Utc::now();
+        let event = Event::LinkSubmitted {
+            link_id: "1".into(),
+            url: "https://a.example".into(),
+        };
+        let entries = outbox_entries(&config, &event, now).unwrap();
+        let targets: Vec<_> = entries.iter().map(|e| e.get_str("target").unwrap()).collect();
+        assert_eq!(targets, ["https://example.com/all", "admin@example.com"]);
+        assert!(entries.iter().all(|e| e.get_str("state") == Ok("pending") && e.get_i32("attempts") == Ok(0)));
+        assert_eq!(entries[0].get_str("next_attempt_at").unwrap(), timestamp(now));
+
+        let event = Event::LinkApproved { link_id: "1".into() };
+        assert_eq!(outbox_entries(&config, &event, now).unwrap().len(), 2);
+
+        assert!(has_targets(&config));
+        assert!(!has_targets(&OutboxConfig::default()));
+        assert!(outbox_entries(&OutboxConfig::default(), &event, now).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_delivery() {
+        // 始终返回 500 的 webhook 接收端
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+
+        let config = config(&url);
+        let now = Utc::now();
+        let event = Event::LinkApproved { link_id: "1".into() };
+        let mut entry = outbox_entries(&config, &event, now).unwrap().remove(0);
+        entry.insert("_id", bson::oid::ObjectId::new());
+
+        let email = EmailConfig {
+            smtp_server: String::new(),
+            smtp_port: 465,
+            username: String::new(),
+            password: String::new(),
+            from_address: String::new(),
+            from_name: String::new(),
+        };
+        let result = deliver(&entry, &config, &email, &reqwest::Client::new()).await;
+        assert!(result.is_err());
+
+        let retry = delivery_update(result, 1, config.max_attempts, now);
+        let set = retry.get_document("$set").unwrap();
+        assert_eq!(set.get_str("next_attempt_at").unwrap(), timestamp(now + Duration::seconds(30)));
+        assert!(set.get_str("last_error").unwrap().contains("500"));
+        assert!(set.get("state").is_none());
+
+        let later = delivery_update(Err(Error::Internal("boom".into())), 3, config.max_attempts.max(4), now);
+        let set = later.get_document("$set").unwrap();
+        assert_eq!(set.get_str("next_attempt_at").unwrap(), timestamp(now + Duration::seconds(120)));
+
+        let failed = delivery_update(Err(Error::Internal("boom".into())), config.max_attempts, config.max_attempts, now);
+        assert_eq!(failed.get_document("$set").unwrap().get_str("state"), Ok("failed"));
+
+        let delivered = delivery_update(Ok(()), 2, config.max_attempts, now);
+        assert_eq!(delivered.get_document("$set").unwrap().get_str("state"), Ok("delivered"));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::services::friend_avatar_service::FriendAvatarService;
+use crate::services::image_service::ImageService;
+use crate::utils::cache::{self, Namespace};
+use crate::utils::request_context::RequestContext;
+use crate::utils::transcode_limiter;
+use crate::{Error, Result};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 计算前将图片缩小到的最大边长
+const SAMPLE_SIZE: u32 = 64;
+/// 调色板的颜色数
+const PALETTE_SIZE: usize = 5;
+/// 每个通道保留的位数（颜色按 2^15 个区间统计）
+const CHANNEL_BITS: u8 = 5;
+/// 调色板中两种颜色的最小距离（RGB 欧氏距离），更接近的颜色视为同一种
+const MIN_DISTANCE: f32 = 48.0;
+/// 透明度低于此值的像素不参与统计
+const MIN_ALPHA: u8 = 128;
+
+/// 图片的主色和调色板（十六进制颜色，如 #1e90ff）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Palette {
+    pub url: String,
+    pub dominant: String,
+    /// 按占比从高到低排列，第一个即主色
+    pub palette: Vec<String>,
+}
+
+/// 获取图片的调色板：缓存（metadata 命名空间）-> 通过 ImageService 获取原图（复用头像的原图缓存）并计算
+///
+/// 返回 (结果, 是否命中缓存)
+pub async fn get(ctx: &RequestContext, images: &ImageService, url: &str) -> Result<(Palette, bool)> {
+    FriendAvatarService::validate_url(url)?;
+    let cache_key = Namespace::Metadata.key(format_args!("palette:{}", url));
+    if let Some(cached) = cache::get_json::<Palette>(&cache_key).await {
+        return Ok((cached, true));
+    }
+
+    let (bytes, _) = images.fetch_avatar(ctx, url).await?;
+    ctx.check("palette extraction")?;
+    let colors = transcode_limiter::run(move || extract(&bytes)).await??;
+    let palette = Palette {
+        url: url.to_string(),
+        dominant: colors[0].clone(),
+        palette: colors,
+    };
+    cache::put_json(&cache_key, &palette).await;
+    Ok((palette, false))
+}
+
+/// 阻塞式计算（在 spawn_blocking 中调用）：返回按占比排列的颜色，至少一种
+///
+/// 缩小后按量化的颜色区间统计像素数，依次选取占比最高且与已选颜色差异足够大的区间（取区间内的平均色）
+pub fn extract(bytes: &[u8]) -> Result<Vec<String>> {
+    let img = image::load_from_memory(bytes)
+        .map_err(|e| Error::BadRequest(format!("Failed to decode image: {}", e)))?;
+    let sample = img.resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle).to_rgba8();
+
+    // 区间 -> (像素数, 各通道之和)
+    let mut bins: HashMap<u16, (u32, [u32; 3])> = HashMap::new();
+    let shift = 8 - CHANNEL_BITS;
+    let opaque = sample.pixels().any(|p| p[3] >= MIN_ALPHA);
+    for pixel in sample.pixels() {
+        // 全透明的图片统计全部像素
+        if opaque && pixel[3] < MIN_ALPHA {
+            continue;
+        }
+        let [r, g, b, _] = pixel.0;
+        let bin = ((r >> shift) as u16) << (2 * CHANNEL_BITS) | ((g >> shift) as u16) << CHANNEL_BITS | (b >> shift) as u16;
+        let entry = bins.entry(bin).or_insert((0, [0; 3]));
+        entry.0 += 1;
+        entry.1[0] += r as u32;
+        entry.1[1] += g as u32;
+        entry.1[2] += b as u32;
+    }
+
+    let mut bins: Vec<(u32, [f32; 3])> = bins
+        .into_values()
+        .map(|(count, sum)| (count, sum.map(|s| s as f32 / count as f32)))
+        .collect();
+    // 像素数相同时按颜色排序，保证结果稳定
+    bins.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal)));
+
+    let mut picked: Vec<[f32; 3]> = Vec::with_capacity(PALETTE_SIZE);
+    for (_, color) in bins {
+        if picked.len() == PALETTE_SIZE {
+            break;
+        }
+        if picked.iter().all(|p| distance(p, &color) >= MIN_DISTANCE) {
+            picked.push(color);
+        }
+    }
+    if picked.is_empty() {
+        return Err(Error::BadRequest("Image has no pixels".into()));
+    }
+    Ok(picked.iter().map(hex).collect())
+}
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt()
+}
+
+fn hex(color: &[f32; 3]) -> String {
+    let [r, g, b] = color.map(|c| c.round().clamp(0.0, 255.0) as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png(img: RgbaImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_extract() {
+        // 左 3/4 红色、右 1/4 蓝色，透明的底部不参与统计
+        let img = RgbaImage::from_fn(128, 128, |x, y| match (x, y) {
+            (_, 96..) => Rgba([0, 255, 0, 0]),
+            (0..96, _) => Rgba([255, 0, 0, 255]),
+            _ => Rgba([0, 0, 255, 255]),
+        });
+        let colors = extract(&png(img)).unwrap();
+        assert_eq!(colors[0], "#ff0000");
+        assert!(colors.contains(&"#0000ff".to_string()));
+        assert!(!colors.contains(&"#00ff00".to_string()));
+        assert!(colors.len() <= PALETTE_SIZE);
+
+        // 相近的颜色只保留一种
+        let img = RgbaImage::from_fn(64, 64, |x, _| if x < 32 { Rgba([200, 200, 200, 255]) } else { Rgba([205, 205, 205, 255]) });
+        assert_eq!(extract(&png(img)).unwrap().len(), 1);
+
+        assert!(extract(b"not an image").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::services::db_service;
+use crate::{Error, Result};
+use mongodb::bson::{doc, Bson, Document};
+use serde::Serialize;
+
+/// 单条搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub snippet: Option<String>,
+    /// MongoDB 文本相关度
+    pub score: f64,
+}
+
+/// 某个集合的搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchGroup {
+    pub total: i64,
+    pub items: Vec<SearchHit>,
+}
+
+/// 按集合分组的搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub page: u64,
+    pub per_page: u64,
+    pub links: SearchGroup,
+}
+
+/// 最大页码，避免 `$skip` 溢出或扫描过深
+const MAX_PAGE: u64 = 1000;
+
+pub struct SearchService;
+
+impl SearchService {
+    /// 全文搜索（依赖 db_service::ensure_indexes 创建的文本索引）
+    pub async fn search(query: &str, page: u64, per_page: u64) -> Result<SearchResults> {
+        let query = query.trim();
+        if query.chars().count() < 2 || query.chars().count() > 100 {
+            return Err(Error::BadRequest("q must be 2-100 characters".into()));
+        }
+        let page = page.clamp(1, MAX_PAGE);
+        let per_page = per_page.clamp(1, 50);
+
+        let links = search_collection(
+            "links",
+            query,
+            page,
+            per_page,
+            // 与公开列表一致：不返回待审核与已拒绝的友链
+            doc! { "moderation_state": { "$nin": ["pending", "rejected"] } },
+            doc! { "name": 1, "url": 1, "description": 1 },
+            |d| SearchHit {
+                id: object_id(d),
+                title: d.get_str("name").unwrap_or_default().to_string(),
+                url: d.get_str("url").ok().map(str::to_string),
+                snippet: d.get_str("description").ok().map(snippet),
+                score: d.get_f64("score").unwrap_or_default(),
+            },
+        )
+        .await?;
+
+        Ok(SearchResults {
+            query: query.to_string(),
+            page,
+            per_page,
+            links,
+        })
+    }
+}
+
+async fn search_collection(
+    collection: &str,
+    query: &str,
+    page: u64,
+    per_page: u64,
+    filter: Document,
+    mut projection: Document,
+    to_hit: impl Fn(&Document) -> SearchHit,
+) -> Result<SearchGroup> {
+    projection.insert("score", doc! { "$meta": "textScore" });
+    let mut matcher = doc! { "$text": { "$search": query } };
+    matcher.extend(filter);
+    let skip = (page - 1).saturating_mul(per_page).min(i64::MAX as u64) as i64;
+    let pipeline = vec![
+        doc! { "$match": matcher },
+        doc! { "$project": projection },
+        doc! { "$sort": { "score": { "$meta": "textScore" } } },
+        doc! { "$facet": {
+            "total": [ { "$count": "count" } ],
+            "items": [ { "$skip": skip }, { "$limit": per_page as i64 } ],
+        } },
+    ];
+
+    let result = db_service::aggregate(collection, pipeline)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    let total = result
+        .get_array("total")
+        .ok()
+        .and_then(|a| a.first())
+        .and_then(Bson::as_document)
+        .and_then(|d| match d.get("count") {
+            Some(Bson::Int32(n)) => Some(*n as i64),
+            Some(Bson::Int64(n)) => Some(*n),
+            _ => None,
+        })
+        .unwrap_or(0);
+    let items = result
+        .get_array("items")
+        .map(|a| a.iter().filter_map(Bson::as_document).map(&to_hit).collect())
+        .unwrap_or_default();
+
+    Ok(SearchGroup { total, items })
+}
+
+fn object_id(d: &Document) -> String {
+    d.get_object_id("_id").map(|oid| oid.to_hex()).unwrap_or_default()
+}
+
+/// 截取摘要（最多 160 个字符）
+fn snippet(text: &str) -> String {
+    let mut s: String = text.chars().take(160).collect();
+    if text.chars().count() > 160 {
+        s.push('…');
+    }
+    s
+}
@@ -0,0 +1,191 @@
+use crate::config::settings::SpamConfig;
+use crate::services::mock_upstream;
+use crate::services::upstream_service;
+use log::{info, warn};
+use mongodb::bson::{doc, Document};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::env;
+use std::time::Duration;
+
+static SPAM_CONFIG: OnceCell<SpamConfig> = OnceCell::new();
+
+/// 评分请求的超时（超时按未知处理，不阻塞评论提交）
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 待评分的内容
+#[derive(Debug, Clone, Default)]
+pub struct SpamInput<'a> {
+    /// 内容类型（Akismet comment_type），如 comment、contact-form
+    pub kind: &'a str,
+    pub content: &'a str,
+    pub author: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub ip: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+/// 评分结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SpamVerdict {
+    pub score: f32,
+    /// 得分达到阈值，需要人工审核
+    pub flagged: bool,
+    /// 命中的规则（便于审核时查看）
+    pub reasons: Vec<String>,
+}
+
+impl SpamVerdict {
+    /// 将评分写入文档：记录 spam_score / spam_reasons，被标记时设置 moderation_state = "pending"
+    pub fn apply_to(&self, document: &mut Document) {
+        document.insert("spam_score", self.score as f64);
+        document.insert("spam_reasons", self.reasons.clone());
+        if self.flagged {
+            document.insert("moderation_state", "pending");
+        }
+    }
+}
+
+/// 初始化垃圾内容评分配置（启动时调用一次）
+pub fn init(config: &SpamConfig) {
+    let mut config = config.clone();
+    if let Ok(key) = env::var("SPACE_API_AKISMET_KEY") {
+        if !key.is_empty() {
+            config.akismet_key = Some(key);
+        }
+    }
+    if config.akismet_key.is_some() {
+        info!("垃圾内容评分已启用 Akismet ({} 条关键词规则)", config.rules.len());
+    }
+    let _ = SPAM_CONFIG.set(config);
+}
+
+pub struct SpamService;
+
+impl SpamService {
+    /// 对内容评分：关键词规则 + 链接数量 + 可选的 Akismet
+    pub async fn score(input: &SpamInput<'_>) -> SpamVerdict {
+        let default_config;
+        let config = match SPAM_CONFIG.get() {
+            Some(c) => c,
+            None => {
+                default_config = SpamConfig::default();
+                &default_config
+            }
+        };
+
+        let (mut score, mut reasons) = score_rules(config, input.content);
+
+        if let Some(key) = config.akismet_key.as_deref().filter(|k| !k.is_empty()) {
+            match akismet_check(key, &config.akismet_site, input).await {
+                Ok(true) => {
+                    score += config.akismet_weight;
+                    reasons.push("akismet".to_string());
+                }
+                Ok(false) => {}
+                // Akismet 不可用时只使用本地规则
+                Err(e) => warn!("Akismet check failed: {}", e),
+            }
+        }
+
+        SpamVerdict {
+            score,
+            flagged: score >= config.threshold,
+            reasons,
+        }
+    }
+}
+
+/// 本地规则评分
+fn score_rules(config: &SpamConfig, content: &str) -> (f32, Vec<String>) {
+    let lower = content.to_lowercase();
+    let mut score = 0.0;
+    let mut reasons = Vec::new();
+
+    for rule in &config.rules {
+        let keyword = rule.keyword.trim().to_lowercase();
+        if !keyword.is_empty() && lower.contains(&keyword) {
+            score += rule.weight;
+            reasons.push(format!("keyword:{}", rule.keyword));
+        }
+    }
+
+    let links = lower.matches("http://").count() + lower.matches("https://").count();
+    if links > config.max_links {
+        score += (links - config.max_links) as f32 * config.link_weight;
+        reasons.push(format!("links:{}", links));
+    }
+    (score, reasons)
+}
+
+/// 调用 Akismet comment-check，返回是否为垃圾内容
+async fn akismet_check(key: &str, site: &str, input: &SpamInput<'_>) -> Result<bool, String> {
+    if mock_upstream::is_enabled() {
+        return Ok(false);
+    }
+
+    // Serializer 不是 Send，先生成请求体再发送
+    let body = {
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("blog", site)
+            .append_pair("comment_type", input.kind)
+            .append_pair("comment_content", input.content)
+            .append_pair("user_ip", input.ip.unwrap_or(""))
+            .append_pair("user_agent", input.user_agent.unwrap_or(""));
+        if let Some(author) = input.author {
+            form.append_pair("comment_author", author);
+        }
+        if let Some(email) = input.email {
+            form.append_pair("comment_author_email", email);
+        }
+        form.finish()
+    };
+
+    let request = upstream_service::http()
+        .client()
+        .post(format!("https://{}.rest.akismet.com/1.1/comment-check", key))
+        .timeout(REQUEST_TIMEOUT)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body);
+    let response = upstream_service::send(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    match body.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(format!("unexpected response: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::SpamRuleConfig;
+
+    #[test]
+    fn test_score_rules() {
+        let config = SpamConfig {
+            rules: vec![SpamRuleConfig {
+                keyword: "Casino".to_string(),
+                weight: 0.8,
+            }],
+            ..Default::default()
+        };
+
+        let (score, reasons) = score_rules(&config, "hello, nice blog");
+        assert_eq!(score, 0.0);
+        assert!(reasons.is_empty());
+
+        let (score, reasons) = score_rules(
+            &config,
+            "best CASINO https://a.example https://b.example http://c.example https://d.example",
+        );
+        assert!((score - 1.4).abs() < 1e-6);
+        assert_eq!(reasons, vec!["keyword:Casino", "links:4"]);
+
+        let mut document = doc! { "message": "x" };
+        SpamVerdict { score, flagged: true, reasons }.apply_to(&mut document);
+        assert_eq!(document.get_str("moderation_state").unwrap(), "pending");
+    }
+}
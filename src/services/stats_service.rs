@@ -0,0 +1,141 @@
+use crate::services::db_service;
+use crate::utils::{cache, request_counter};
+use crate::Result;
+use mongodb::bson::{doc, Bson, Document};
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const COUNTS_CACHE_KEY: &str = "site_stats";
+
+// 数据库计数和硬盘缓存占用的统计开销较大，缓存 5 分钟
+static COUNTS_CACHE: Lazy<Cache<String, SiteCounts>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1)
+        .time_to_live(Duration::from_secs(5 * 60))
+        .build()
+});
+
+/// 友链计数（按审核状态分组，未设置状态的视为 approved）
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCounts {
+    pub total: i64,
+    pub by_state: BTreeMap<String, i64>,
+}
+
+/// 缓存占用
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    pub memory_entries: u64,
+    pub memory_bytes: u64,
+    pub disk_files: u64,
+    pub disk_bytes: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteCounts {
+    pub links: LinkCounts,
+    pub users: i64,
+    pub cache: CacheUsage,
+    /// 统计时间（RFC 3339）
+    pub generated_at: String,
+}
+
+/// 站点统计
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteStats {
+    #[serde(flatten)]
+    pub counts: SiteCounts,
+    pub uptime_secs: u64,
+    pub total_requests: u64,
+    /// 缓存命中率（内存和硬盘合计，进程启动以来；尚无读取时为空）
+    pub cache_hit_rate: Option<f64>,
+}
+
+pub struct StatsService;
+
+impl StatsService {
+    /// 站点统计：计数部分有 5 分钟缓存，运行时长和请求数实时读取
+    pub async fn site_stats() -> Result<SiteStats> {
+        let counts = match COUNTS_CACHE.get(COUNTS_CACHE_KEY).await {
+            Some(counts) => counts,
+            None => {
+                let counts = Self::collect_counts().await?;
+                COUNTS_CACHE.insert(COUNTS_CACHE_KEY.to_string(), counts.clone()).await;
+                counts
+            }
+        };
+
+        Ok(SiteStats {
+            counts,
+            uptime_secs: request_counter::uptime_secs(),
+            total_requests: request_counter::total_requests(),
+            cache_hit_rate: cache::hit_rate(),
+        })
+    }
+
+    /// 清除统计缓存，下次请求时重新计算
+    pub fn invalidate_cache() {
+        COUNTS_CACHE.invalidate_all();
+    }
+
+    async fn collect_counts() -> Result<SiteCounts> {
+        let (links, users) = tokio::try_join!(Self::link_counts(), Self::user_count())?;
+
+        cache::run_pending_tasks().await;
+        let (disk_files, disk_bytes) = tokio::task::spawn_blocking(cache::disk_usage)
+            .await
+            .unwrap_or_default();
+        let (memory_entries, memory_bytes) = cache::memory_usage();
+
+        Ok(SiteCounts {
+            links,
+            users,
+            cache: CacheUsage {
+                memory_entries,
+                memory_bytes,
+                disk_files,
+                disk_bytes,
+                total_bytes: memory_bytes + disk_bytes,
+            },
+            generated_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        })
+    }
+
+    async fn link_counts() -> Result<LinkCounts> {
+        let pipeline = vec![doc! { "$group": {
+            "_id": { "$ifNull": ["$moderation_state", "approved"] },
+            "count": { "$sum": 1 },
+        } }];
+        let by_state: BTreeMap<String, i64> = db_service::aggregate("links", pipeline)
+            .await?
+            .iter()
+            .map(|d| (d.get_str("_id").unwrap_or("unknown").to_string(), count(d)))
+            .collect();
+
+        Ok(LinkCounts {
+            total: by_state.values().sum(),
+            by_state,
+        })
+    }
+
+    async fn user_count() -> Result<i64> {
+        let pipeline = vec![doc! { "$count": "count" }];
+        Ok(db_service::aggregate("users", pipeline)
+            .await?
+            .first()
+            .map(count)
+            .unwrap_or(0))
+    }
+}
+
+fn count(d: &Document) -> i64 {
+    match d.get("count") {
+        Some(Bson::Int32(n)) => *n as i64,
+        Some(Bson::Int64(n)) => *n,
+        _ => 0,
+    }
+}
@@ -0,0 +1,166 @@
+use crate::config::settings::UpstreamFixturesConfig;
+use crate::{Error, Result};
+use log::{debug, warn};
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// 上游响应快照的录制 / 回放模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Off,
+    /// 将上游响应脱敏后写入快照目录（覆盖同名快照）
+    Record,
+    /// 从快照目录读取响应，不访问上游
+    Replay,
+}
+
+static FIXTURES: OnceCell<(Mode, PathBuf)> = OnceCell::new();
+
+/// 敏感字段：键名包含以下片段（不区分大小写）的值会被替换
+const SECRET_KEY_PARTS: &[&str] = &["token", "session", "cookie", "secret", "password", "openid"];
+/// 可识别个人身份的字段（精确匹配键名，figureurl 开头的头像地址同样处理）
+const PERSONAL_KEYS: &[&str] = &[
+    "userId", "userName", "nickname", "avatar", "avatarUrl", "email", "phone", "ip", "province", "city", "year",
+];
+
+/// 初始化快照录制 / 回放（启动时调用一次）
+pub fn init(config: &UpstreamFixturesConfig) {
+    let mode = match config.mode.as_str() {
+        "record" => Mode::Record,
+        "replay" => Mode::Replay,
+        "off" | "" => Mode::Off,
+        other => {
+            warn!("Unknown fixtures mode {}, disabling", other);
+            Mode::Off
+        }
+    };
+    if mode != Mode::Off {
+        warn!("上游响应快照模式：{:?}（目录 {}），仅用于开发和测试", mode, config.dir);
+    }
+    let _ = FIXTURES.set((mode, PathBuf::from(&config.dir)));
+}
+
+fn current() -> Option<&'static (Mode, PathBuf)> {
+    FIXTURES.get().filter(|(mode, _)| *mode != Mode::Off)
+}
+
+/// 回放模式下读取快照（未开启回放时为 None，快照不存在时返回错误）
+pub async fn replay(name: &str) -> Option<Result<Value>> {
+    let (mode, dir) = current()?;
+    if *mode != Mode::Replay {
+        return None;
+    }
+    let dir = dir.clone();
+    let name = name.to_string();
+    Some(
+        tokio::task::spawn_blocking(move || load(&dir, &name))
+            .await
+            .unwrap_or_else(|e| Err(Error::Internal(e.to_string()))),
+    )
+}
+
+/// 录制模式下将响应脱敏后写入快照（失败只记录日志，不影响请求）
+pub async fn record(name: &str, value: &Value) {
+    let Some((Mode::Record, dir)) = current() else {
+        return;
+    };
+    let mut value = value.clone();
+    sanitize(&mut value);
+    let path = dir.join(format!("{}.json", name));
+    let body = serde_json::to_vec_pretty(&value).unwrap_or_default();
+    let written = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(&path, body).await
+    };
+    match written.await {
+        Ok(_) => debug!("Recorded upstream fixture {:?}", path),
+        Err(e) => warn!("Failed to record upstream fixture {:?}: {}", path, e),
+    }
+}
+
+/// 读取快照文件 `<dir>/<name>.json`
+pub fn load(dir: &Path, name: &str) -> Result<Value> {
+    let path = dir.join(format!("{}.json", name));
+    let data = std::fs::read(&path).map_err(|e| Error::NotFound(format!("Fixture {:?}: {}", path, e)))?;
+    serde_json::from_slice(&data).map_err(|e| Error::Internal(format!("Invalid fixture {:?}: {}", path, e)))
+}
+
+/// 替换令牌、会话和可识别个人身份的字段，保留值的类型（字符串、数字）和整体结构
+pub fn sanitize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let lower = key.to_ascii_lowercase();
+                let sensitive = SECRET_KEY_PARTS.iter().any(|part| lower.contains(part))
+                    || PERSONAL_KEYS.contains(&key.as_str())
+                    || lower.starts_with("figureurl");
+                if sensitive {
+                    redact(value);
+                } else {
+                    sanitize(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize),
+        _ => {}
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        // 保持 URL 形式，解析代码中对地址的处理仍可覆盖到
+        Value::String(s) if s.starts_with("http://") || s.starts_with("https://") => {
+            *s = "https://example.com/redacted.png".to_string();
+        }
+        Value::String(s) if !s.is_empty() => *s = "redacted".to_string(),
+        Value::Number(_) => *value = Value::from(1),
+        Value::Object(_) | Value::Array(_) => sanitize(value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_fixture(name: &str) -> Value {
+    load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"), name).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize() {
+        let mut value = json!({
+            "code": 200,
+            "access_token": "abc",
+            "data": {
+                "userId": 515522946,
+                "userName": "someone",
+                "avatar": "https://p1.music.126.net/a.jpg",
+                "song": { "id": 1, "name": "Song", "artists": [{ "id": 2, "name": "Artist" }] }
+            },
+            "figureurl_qq_2": "http://thirdqq.qlogo.cn/x/100",
+            "nickname": ""
+        });
+        sanitize(&mut value);
+        assert_eq!(value["access_token"], "redacted");
+        assert_eq!(value["data"]["userId"], 1);
+        assert_eq!(value["data"]["userName"], "redacted");
+        assert_eq!(value["data"]["avatar"], "https://example.com/redacted.png");
+        assert_eq!(value["figureurl_qq_2"], "https://example.com/redacted.png");
+        assert_eq!(value["nickname"], "");
+        // 非敏感字段保持原样
+        assert_eq!(value["data"]["song"]["artists"][0]["name"], "Artist");
+        assert_eq!(value["code"], 200);
+
+        // 快照本身已脱敏
+        for name in ["ncm_now_play", "codetime_stats", "qq_user_info"] {
+            let fixture = test_fixture(name);
+            let mut sanitized = fixture.clone();
+            sanitize(&mut sanitized);
+            assert_eq!(fixture, sanitized, "{} is not sanitized", name);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::services::audit_service::AuditService;
+use crate::services::db_service;
+use crate::services::event_bus::Event;
+use crate::services::outbox_service;
+use crate::utils::{crypto, rng};
+use crate::{Error, Result};
+use chrono::{Duration, Utc};
+use mongodb::bson::{doc, Bson, Document};
+
+const USERS_COLLECTION: &str = "users";
+const SESSIONS_COLLECTION: &str = "sessions";
+const LINKS_COLLECTION: &str = "links";
+/// 会话令牌有效期（天）
+const SESSION_TTL_DAYS: i64 = 30;
+/// 注销后替换的昵称
+const DELETED_NICKNAME: &str = "已注销用户";
+
+pub struct UserService;
+
+impl UserService {
+    /// 为用户签发会话令牌，返回（令牌，过期时间），库中只保存令牌的 SHA-256
+    pub async fn create_session(openid: &str) -> Result<(String, String)> {
+        Self::issue_session(openid, Duration::days(SESSION_TTL_DAYS)).await
+    }
+
+    /// 签发指定有效期的会话令牌（管理后台登录使用较短的有效期）
+    pub async fn issue_session(openid: &str, ttl: Duration) -> Result<(String, String)> {
+        let token = rng::secure_hex(32);
+        let now = Utc::now();
+        let expires_at = (now + ttl).to_rfc3339();
+        let session = doc! {
+            "token_hash": crypto::hash_token(&token),
+            "qq_openid": openid,
+            "created_at": now.to_rfc3339(),
+            "expires_at": &expires_at,
+        };
+        db_service::insert_one(SESSIONS_COLLECTION, session).await?;
+        Ok((token, expires_at))
+    }
+
+    /// 按会话令牌查找会话所属的 openid（会话不存在或已过期时返回 None，过期会话顺便删除）
+    pub async fn session_owner(token: &str) -> Result<Option<String>> {
+        let token_hash = crypto::hash_token(token);
+        let Some(session) = db_service::find_one(SESSIONS_COLLECTION, doc! { "token_hash": &token_hash }).await?
+        else {
+            return Ok(None);
+        };
+
+        let expired = session
+            .get_str("expires_at")
+            .ok()
+            .and_then(|e| chrono::DateTime::parse_from_rfc3339(e).ok())
+            .is_none_or(|exp| Utc::now() > exp.with_timezone(&Utc));
+        if expired {
+            db_service::delete_one(SESSIONS_COLLECTION, doc! { "token_hash": &token_hash }).await?;
+            return Ok(None);
+        }
+        Ok(session.get_str("qq_openid").ok().map(str::to_string))
+    }
+
+    /// 吊销会话令牌
+    pub async fn revoke_session(token: &str) -> Result<()> {
+        db_service::delete_one(SESSIONS_COLLECTION, doc! { "token_hash": crypto::hash_token(token) }).await?;
+        Ok(())
+    }
+
+    /// 按会话令牌查找用户（会话过期或账号已注销时返回 None）
+    pub async fn find_by_session(token: &str) -> Result<Option<Document>> {
+        let Some(openid) = Self::session_owner(token).await? else {
+            return Ok(None);
+        };
+        db_service::find_one(
+            USERS_COLLECTION,
+            doc! { "qq_openid": &openid, "deleted_at": { "$exists": false } },
+        )
+        .await
+    }
+
+    /// 按 QQ OpenID 查询对外公开的用户资料（已移除敏感字段）
+    pub async fn profile(openid: &str) -> Result<Option<Document>> {
+        let user = db_service::find_one(USERS_COLLECTION, doc! { "qq_openid": openid }).await?;
+        Ok(user.map(|u| db_service::strip_sensitive_fields(USERS_COLLECTION, u)))
+    }
+
+    /// 批量查询公开资料（不包含已注销的账号）
+    pub async fn profiles(openids: &[String]) -> Result<Vec<Document>> {
+        let users = db_service::find_many(
+            USERS_COLLECTION,
+            doc! { "qq_openid": { "$in": openids }, "deleted_at": { "$exists": false } },
+        )
+        .await?;
+        Ok(users
+            .into_iter()
+            .map(|u| db_service::strip_sensitive_fields(USERS_COLLECTION, u))
+            .collect())
+    }
+
+    /// 导出用户的全部个人数据
+    pub async fn export(user: &Document) -> Result<serde_json::Value> {
+        let openid = user.get_str("qq_openid").unwrap_or_default();
+        let profile = db_service::strip_sensitive_fields(USERS_COLLECTION, user.clone());
+        let links = db_service::find_many(LINKS_COLLECTION, doc! { "submitted_by": openid }).await?;
+
+        Ok(serde_json::json!({
+            "exported_at": Utc::now().to_rfc3339(),
+            "profile": to_json(profile)?,
+            "identities": [ { "provider": "qq", "openid": openid } ],
+            "links": links.into_iter().map(to_json).collect::<Result<Vec<_>>>()?,
+        }))
+    }
+
+    /// 注销账号：软删除用户文档并匿名化，解除其提交内容与身份的关联，吊销所有会话
+    pub async fn delete_account(user: &Document) -> Result<()> {
+        let openid = user.get_str("qq_openid").unwrap_or_default().to_string();
+        let user_id = match user.get("_id") {
+            Some(Bson::ObjectId(oid)) => oid.to_hex(),
+            _ => return Err(Error::Internal("Malformed user record".into())),
+        };
+        let now = Utc::now().to_rfc3339();
+
+        // 替换 openid 后再次登录会创建新账号，而不是恢复已注销的账号
+        db_service::update_one(
+            USERS_COLLECTION,
+            doc! { "_id": user.get("_id").cloned().unwrap_or(Bson::Null) },
+            doc! {
+                "$set": {
+                    "qq_openid": format!("deleted:{}", crypto::hash_code(&openid)),
+                    "nickname": DELETED_NICKNAME,
+                    "avatar": "",
+                    "gender": "",
+                    "deleted_at": &now,
+                    "updated_at": &now,
+                },
+                "$unset": { "qq_access_token": "", "qq_refresh_token": "", "last_login": "" },
+            },
+        )
+        .await?;
+
+        let links = db_service::update_many(
+            LINKS_COLLECTION,
+            doc! { "submitted_by": &openid },
+            doc! { "$unset": { "submitted_by": "", "email": "" } },
+        )
+        .await?;
+        let sessions = db_service::delete_many(SESSIONS_COLLECTION, doc! { "qq_openid": &openid }).await?;
+        db_service::delete_many("temp_codes", doc! { "qq_openid": &openid }).await?;
+
+        AuditService::record(
+            "user.deleted",
+            &format!("user:{}", user_id),
+            &user_id,
+            serde_json::json!({ "anonymized_links": links, "revoked_sessions": sessions }),
+        )
+        .await;
+        // 账号已经匿名化，通知写入失败时 emit 已记录日志
+        let _ = outbox_service::emit(Event::UserDeleted { user_id }).await;
+        Ok(())
+    }
+}
+
+fn to_json(document: Document) -> Result<serde_json::Value> {
+    serde_json::to_value(document).map_err(|e| Error::Internal(format!("Failed to serialize document: {}", e)))
+}
@@ -1,59 +1,93 @@
 use crate::{Error, Result};
 use moka::future::Cache;
 use once_cell::sync::Lazy;
-use rand::RngExt;
-// 暂时移除，我们使用其他方式生成验证码
+use crate::utils::{crypto, rng};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-// 验证码缓存（邮箱 -> (验证码，过期时间戳)）
-pub static VERIFICATION_CACHE: Lazy<Cache<String, (String, u64)>> = Lazy::new(|| {
+/// 验证码有效期（秒）
+const CODE_TTL_SECS: u64 = 600;
+
+// 待验证的验证码（邮箱 -> 验证码），不保存明文验证码
+pub static VERIFICATION_CACHE: Lazy<Cache<String, PendingCode>> = Lazy::new(|| {
     Cache::builder()
-        .time_to_live(Duration::from_secs(600)) // 10分钟
+        .time_to_live(Duration::from_secs(CODE_TTL_SECS)) // 10分钟
         .build()
 });
 
+/// 缓存中的验证码：验证用的哈希和派生验证码的随机种子（有效期内重发同一验证码）
+#[derive(Clone)]
+pub struct PendingCode {
+    hash: String,
+    seed: String,
+    expires_at: u64,
+}
+
+/// 本次发送的验证码
+pub struct IssuedCode {
+    pub code: String,
+    /// 剩余有效秒数
+    pub expires_in_secs: u64,
+    /// 是否为有效期内的重发（验证码和过期时间不变）
+    pub resent: bool,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_secs()
+}
+
+// 验证码由邮箱和随机种子派生（CSPRNG 种子 + HMAC），重发时无需保存明文
+fn derive_code(email: &str, seed: &str) -> String {
+    crypto::derive_digits(&format!("{}:{}", email, seed), 6)
+}
+
 pub struct VerificationService;
 
 impl VerificationService {
-    // 生成验证码
-    pub fn generate_verification_code() -> String {
-        let mut rng = rand::rng();
-        let code: String = (0..6)
-            .map(|_| rng.random_range(0..10).to_string())
-            .collect();
-        code
-    }
+    // 签发验证码：有效期内重复请求返回同一验证码，不延长有效期（避免连点时旧验证码被覆盖）
+    pub async fn issue_code(email: &str) -> Result<IssuedCode> {
+        loop {
+            let entry = VERIFICATION_CACHE
+                .entry_by_ref(email)
+                .or_insert_with(async {
+                    let seed = rng::secure_hex(16);
+                    PendingCode {
+                        hash: crypto::hash_code(&derive_code(email, &seed)),
+                        seed,
+                        expires_at: now_secs() + CODE_TTL_SECS,
+                    }
+                })
+                .await;
+            let resent = !entry.is_fresh();
+            let pending = entry.into_value();
+            let expires_in_secs = pending.expires_at.saturating_sub(now_secs());
 
-    // 存储验证码
-    pub async fn store_verification_code(email: &str, code: &str) -> Result<()> {
-        let expiry = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_else(|_| Duration::from_secs(0))
-            .as_secs()
-            + 600; // 10分钟后过期
-
-        VERIFICATION_CACHE
-            .insert(email.to_string(), (code.to_string(), expiry))
-            .await;
-        Ok(())
+            // 缓存淘汰与过期时间戳之间的临界情况：丢弃后重新签发
+            if expires_in_secs == 0 {
+                VERIFICATION_CACHE.remove(email).await;
+                continue;
+            }
+            return Ok(IssuedCode {
+                code: derive_code(email, &pending.seed),
+                expires_in_secs,
+                resent,
+            });
+        }
     }
 
     // 验证验证码
     pub async fn verify_code(email: &str, code: &str) -> Result<bool> {
-        if let Some((stored_code, expiry)) = VERIFICATION_CACHE.get(email).await {
-            let current_time = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_else(|_| Duration::from_secs(0))
-                .as_secs();
-
+        if let Some(pending) = VERIFICATION_CACHE.get(email).await {
             // 如果验证码已过期
-            if current_time > expiry {
+            if now_secs() > pending.expires_at {
                 VERIFICATION_CACHE.remove(email).await;
                 return Ok(false);
             }
 
-            // 验证码匹配
-            if stored_code == code {
+            // 验证码匹配（常量时间比较）
+            if crypto::verify_code(code, &pending.hash) {
                 VERIFICATION_CACHE.remove(email).await;
                 return Ok(true);
             }
@@ -68,3 +102,27 @@ impl VerificationService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_issue_code_idempotent() {
+        let email = "issue-test@example.com";
+        let first = VerificationService::issue_code(email).await.unwrap();
+        assert!(!first.resent);
+        assert_eq!(first.code.len(), 6);
+        assert!(first.expires_in_secs <= CODE_TTL_SECS);
+
+        // 有效期内重复请求：同一验证码，不延长有效期
+        let second = VerificationService::issue_code(email).await.unwrap();
+        assert!(second.resent);
+        assert_eq!(second.code, first.code);
+        assert!(second.expires_in_secs <= first.expires_in_secs);
+
+        assert!(VerificationService::verify_code(email, &second.code).await.unwrap());
+        // 验证通过后重新签发新的验证码
+        assert!(!VerificationService::issue_code(email).await.unwrap().resent);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::config::settings::WallpapersConfig;
+use crate::services::blurhash_service::BlurhashService;
+use crate::services::db_service;
+use crate::services::image_service::ImageService;
+use crate::utils::transcode_limiter;
+use crate::{Error, Result};
+use chrono::Utc;
+use image::ImageFormat;
+use log::{info, warn};
+use moka::future::Cache;
+use mongodb::bson::{self, doc, Bson, Document};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const COLLECTION: &str = "wallpapers";
+/// 随机壁纸的返回次数和最近返回时间（每张壁纸一条）
+const SERVES_COLLECTION: &str = "wallpaper_serves";
+/// 上传壁纸编号的计数器名
+const ID_COUNTER: &str = "wallpaper_id";
+/// 加权随机中距上次返回的时间最多按 7 天计算，从未返回过的壁纸同样按上限计算
+const WEIGHT_CAP_SECS: i64 = 7 * 24 * 3600;
+/// 上传的壁纸在图片管线中使用的地址前缀（`upload://<文件名>`），读取时直接访问上传目录
+pub const UPLOAD_SCHEME: &str = "upload://";
+/// 每张壁纸最多的标签数和单个标签的长度
+const MAX_TAGS: usize = 16;
+const MAX_TAG_LEN: usize = 32;
+
+/// 壁纸方向（横屏用于 /wallpaper，竖屏用于 /wallpaper_height）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+impl Orientation {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "landscape" => Some(Self::Landscape),
+            "portrait" => Some(Self::Portrait),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Landscape => "landscape",
+            Self::Portrait => "portrait",
+        }
+    }
+
+    fn of(width: u32, height: u32) -> Self {
+        if height > width {
+            Self::Portrait
+        } else {
+            Self::Landscape
+        }
+    }
+}
+
+/// 通过管理接口上传的壁纸
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wallpaper {
+    /// 壁纸编号（接在内置壁纸之后）
+    pub wallpaper_id: u32,
+    pub orientation: Orientation,
+    /// 上传目录中的文件名
+    pub file: String,
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub size: u64,
+    pub sha256: String,
+    pub created_at: String,
+}
+
+impl Wallpaper {
+    /// 在图片管线中使用的地址
+    pub fn source_url(&self) -> String {
+        format!("{}{}", UPLOAD_SCHEME, self.file)
+    }
+}
+
+/// 壁纸的返回次数
+#[derive(Debug, Clone, Serialize)]
+pub struct ServeCount {
+    pub wallpaper_id: u32,
+    pub count: i64,
+    pub last_served_at: String,
+}
+
+/// 随机壁纸的返回统计
+#[derive(Debug, Clone, Serialize)]
+pub struct ServeStats {
+    pub total: i64,
+    /// 按返回次数从多到少排列，未返回过的壁纸不列出
+    pub wallpapers: Vec<ServeCount>,
+}
+
+/// 新上传的壁纸
+pub struct NewWallpaper {
+    pub bytes: Vec<u8>,
+    /// 未指定时按宽高判断
+    pub orientation: Option<Orientation>,
+    pub tags: Vec<String>,
+}
+
+static UPLOAD_DIR: OnceCell<PathBuf> = OnceCell::new();
+// 上传壁纸列表（每次请求随机选择时使用），增删后立即失效
+static POOL: Lazy<Cache<(), Arc<Vec<Wallpaper>>>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(1)
+        .time_to_live(Duration::from_secs(5 * 60))
+        .build()
+});
+// 各壁纸最近一次返回的时间（Unix 秒）：首次使用时从 MongoDB 加载，之后随返回记录更新
+static LAST_SERVED: Lazy<RwLock<HashMap<u32, i64>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+static LAST_SERVED_LOADED: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+// 分配编号和写入数据库串行进行，避免并发上传得到相同的编号
+static UPLOADS: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// 初始化上传目录（启动时调用一次）
+pub fn init(config: &WallpapersConfig) {
+    let _ = UPLOAD_DIR.set(PathBuf::from(&config.upload_dir));
+}
+
+fn upload_dir() -> &'static PathBuf {
+    UPLOAD_DIR.get_or_init(|| PathBuf::from(WallpapersConfig::default().upload_dir))
+}
+
+/// 上传文件的路径（只接受上传时生成的文件名，拒绝路径分隔符）
+fn upload_path(file: &str) -> Result<PathBuf> {
+    let valid = !file.is_empty()
+        && file
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.')
+        && !file.starts_with('.');
+    if !valid {
+        return Err(Error::BadRequest(format!("Invalid wallpaper file: {}", file)));
+    }
+    Ok(upload_dir().join(file))
+}
+
+/// 读取上传的壁纸原图
+pub async fn read_upload(file: &str) -> Result<Vec<u8>> {
+    let path = upload_path(file)?;
+    tokio::fs::read(&path)
+        .await
+        .map_err(|e| Error::NotFound(format!("Wallpaper file {}: {}", file, e)))
+}
+
+/// 标签：去掉首尾空白并转为小写，逗号分隔的值拆开，去重
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().flat_map(|t| t.split(',')) {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LEN {
+            return Err(Error::BadRequest(format!("Tag must be at most {} characters", MAX_TAG_LEN)));
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(Error::BadRequest(format!("At most {} tags are allowed", MAX_TAGS)));
+    }
+    Ok(normalized)
+}
+
+fn select_page(
+    all: &[Wallpaper],
+    tag: Option<&str>,
+    orientation: Option<Orientation>,
+    page: u64,
+    limit: u64,
+) -> (u64, Vec<Wallpaper>) {
+    let tag = tag.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty());
+    let matched: Vec<&Wallpaper> = all
+        .iter()
+        .filter(|w| orientation.is_none_or(|o| w.orientation == o))
+        .filter(|w| tag.as_ref().is_none_or(|t| w.tags.contains(t)))
+        .collect();
+    let items = matched
+        .iter()
+        .skip((page.max(1) - 1).saturating_mul(limit) as usize)
+        .take(limit as usize)
+        .map(|w| (*w).clone())
+        .collect();
+    (matched.len() as u64, items)
+}
+
+/// 加权随机的权重：距上次返回的秒数（不超过 WEIGHT_CAP_SECS），从未返回过的按上限计算；至少为 1
+pub fn selection_weight(last_served: Option<i64>, now: i64) -> u64 {
+    let age = last_served.map_or(WEIGHT_CAP_SECS, |t| (now - t).clamp(0, WEIGHT_CAP_SECS));
+    age as u64 + 1
+}
+
+fn int_field(d: &Document, key: &str) -> i64 {
+    match d.get(key) {
+        Some(Bson::Int32(n)) => *n as i64,
+        Some(Bson::Int64(n)) => *n,
+        _ => 0,
+    }
+}
+
+/// 上传壁纸的管理（原图保存在上传目录，元数据保存在 MongoDB）
+pub struct WallpaperService;
+
+impl WallpaperService {
+    /// 全部上传的壁纸（按编号排序）
+    pub async fn list() -> Result<Arc<Vec<Wallpaper>>> {
+        POOL.try_get_with((), async {
+            let docs = db_service::find_many(COLLECTION, doc! {}).await?;
+            let mut wallpapers: Vec<Wallpaper> = docs
+                .into_iter()
+                .filter_map(|d| match bson::from_document::<Wallpaper>(d) {
+                    Ok(w) => Some(w),
+                    Err(e) => {
+                        warn!("Skipping invalid wallpaper document: {}", e);
+                        None
+                    }
+                })
+                .collect();
+            wallpapers.sort_by_key(|w| w.wallpaper_id);
+            Ok::<_, Error>(Arc::new(wallpapers))
+        })
+        .await
+        .map_err(|e| (*e).clone())
+    }
+
+    /// 指定方向的上传壁纸（数据库不可用时为空，只使用内置壁纸）
+    pub async fn pool(orientation: Orientation) -> Vec<Wallpaper> {
+        match Self::list().await {
+            Ok(all) => all.iter().filter(|w| w.orientation == orientation).cloned().collect(),
+            Err(e) => {
+                warn!("Failed to load uploaded wallpapers: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// 分页列出上传的壁纸（按编号排序），可按标签和方向筛选，返回 (筛选后的总数, 当前页)
+    pub async fn page(
+        tag: Option<&str>,
+        orientation: Option<Orientation>,
+        page: u64,
+        limit: u64,
+    ) -> Result<(u64, Vec<Wallpaper>)> {
+        Ok(select_page(&Self::list().await?, tag, orientation, page, limit))
+    }
+
+    pub async fn get(wallpaper_id: u32) -> Result<Option<Wallpaper>> {
+        Ok(Self::list().await?.iter().find(|w| w.wallpaper_id == wallpaper_id).cloned())
+    }
+
+    /// 保存上传的壁纸：校验格式，计算尺寸和 blurhash，编号接在 `first_id`、已上传和已删除的壁纸之后
+    pub async fn upload(upload: NewWallpaper, first_id: u32) -> Result<Wallpaper> {
+        let tags = normalize_tags(&upload.tags)?;
+        let format = ImageService::detect_format(&upload.bytes)
+            .filter(|f| matches!(f, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP))
+            .ok_or_else(|| Error::BadRequest("Wallpaper must be a JPEG, PNG or WebP image".into()))?;
+
+        let bytes = Arc::new(upload.bytes);
+        let (blurhash, width, height) = {
+            let bytes = Arc::clone(&bytes);
+            transcode_limiter::run(move || BlurhashService::encode(&bytes)).await??
+        };
+        let sha256 = hex::encode(Sha256::digest(bytes.as_slice()));
+        let file = format!("{}.{}", &sha256[..32], ImageService::format_extension(format));
+
+        let _guard = UPLOADS.lock().await;
+        let existing = Self::list().await?;
+        if let Some(duplicate) = existing.iter().find(|w| w.sha256 == sha256) {
+            return Err(Error::Conflict(format!(
+                "Wallpaper already uploaded as #{}",
+                duplicate.wallpaper_id
+            )));
+        }
+        // 编号取自只增不减的计数器，删除的编号不会分配给新壁纸（原图地址按编号长期缓存）
+        let floor = existing
+            .iter()
+            .map(|w| w.wallpaper_id)
+            .max()
+            .unwrap_or(0)
+            .max(first_id.saturating_sub(1));
+        let wallpaper_id = db_service::next_sequence(ID_COUNTER, floor as u64)
+            .await?
+            .try_into()
+            .map_err(|_| Error::Internal("Wallpaper ID overflow".into()))?;
+
+        let path = upload_path(&file)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| Error::Internal(format!("Failed to create upload dir: {}", e)))?;
+        }
+        tokio::fs::write(&path, bytes.as_slice())
+            .await
+            .map_err(|e| Error::Internal(format!("Failed to save wallpaper: {}", e)))?;
+
+        let wallpaper = Wallpaper {
+            wallpaper_id,
+            orientation: upload.orientation.unwrap_or_else(|| Orientation::of(width, height)),
+            file,
+            width,
+            height,
+            blurhash,
+            tags,
+            size: bytes.len() as u64,
+            sha256,
+            created_at: Utc::now().to_rfc3339(),
+        };
+        let doc = doc! {
+            "wallpaper_id": wallpaper.wallpaper_id as i64,
+            "orientation": wallpaper.orientation.as_str(),
+            "file": &wallpaper.file,
+            "width": wallpaper.width as i64,
+            "height": wallpaper.height as i64,
+            "blurhash": &wallpaper.blurhash,
+            "tags": &wallpaper.tags,
+            "size": wallpaper.size as i64,
+            "sha256": &wallpaper.sha256,
+            "created_at": &wallpaper.created_at,
+        };
+        if let Err(e) = db_service::insert_one(COLLECTION, doc).await {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+        POOL.invalidate_all();
+        info!("壁纸 #{} 已上传（{}x{}，{} 字节）", wallpaper.wallpaper_id, width, height, wallpaper.size);
+        Ok(wallpaper)
+    }
+
+    /// 记录一次随机壁纸的返回：更新内存中的最近返回时间，并累加 MongoDB 中的计数
+    pub async fn record_serve(wallpaper_id: u32) -> Result<()> {
+        let now = Utc::now();
+        LAST_SERVED
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(wallpaper_id, now.timestamp());
+        db_service::upsert_one(
+            SERVES_COLLECTION,
+            doc! { "wallpaper_id": wallpaper_id as i64 },
+            doc! {
+                "$inc": { "count": 1 },
+                "$set": { "last_served_at": now.to_rfc3339() },
+            },
+        )
+        .await
+    }
+
+    /// 各壁纸最近一次返回的时间（Unix 秒）；数据库不可用时只包含本进程记录的返回
+    pub async fn last_served() -> HashMap<u32, i64> {
+        let loaded = LAST_SERVED_LOADED
+            .get_or_try_init(|| async {
+                let stats = Self::serve_stats().await?;
+                let mut last_served = LAST_SERVED.write().unwrap_or_else(|e| e.into_inner());
+                for entry in stats.wallpapers {
+                    let Ok(served_at) = chrono::DateTime::parse_from_rfc3339(&entry.last_served_at) else {
+                        continue;
+                    };
+                    // 加载前本进程已记录过返回时保留较新的时间
+                    let served_at = served_at.timestamp();
+                    let current = last_served.entry(entry.wallpaper_id).or_insert(served_at);
+                    *current = (*current).max(served_at);
+                }
+                Ok::<_, Error>(())
+            })
+            .await;
+        if let Err(e) = loaded {
+            warn!("Failed to load wallpaper serve history: {}", e);
+        }
+        LAST_SERVED.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 随机壁纸的返回统计
+    pub async fn serve_stats() -> Result<ServeStats> {
+        let docs = db_service::find_many(SERVES_COLLECTION, doc! {}).await?;
+        let mut wallpapers: Vec<ServeCount> = docs
+            .iter()
+            .map(|d| ServeCount {
+                wallpaper_id: int_field(d, "wallpaper_id") as u32,
+                count: int_field(d, "count"),
+                last_served_at: d.get_str("last_served_at").unwrap_or_default().to_string(),
+            })
+            .collect();
+        wallpapers.sort_by(|a, b| b.count.cmp(&a.count).then(a.wallpaper_id.cmp(&b.wallpaper_id)));
+        Ok(ServeStats {
+            total: wallpapers.iter().map(|w| w.count).sum(),
+            wallpapers,
+        })
+    }
+
+    /// 删除上传的壁纸（数据库记录和原图），不存在时返回 None
+    pub async fn delete(wallpaper_id: u32) -> Result<Option<Wallpaper>> {
+        let _guard = UPLOADS.lock().await;
+        let Some(wallpaper) = Self::get(wallpaper_id).await? else {
+            return Ok(None);
+        };
+        db_service::delete_one(COLLECTION, doc! { "wallpaper_id": wallpaper_id as i64 }).await?;
+        POOL.invalidate_all();
+
+        // 相同内容只保存一份文件，删除记录后文件不再被引用
+        if let Err(e) = tokio::fs::remove_file(upload_path(&wallpaper.file)?).await {
+            warn!("Failed to remove wallpaper file {}: {}", wallpaper.file, e);
+        }
+        info!("壁纸 #{} 已删除", wallpaper_id);
+        Ok(Some(wallpaper))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_weight() {
+        let now = 1_700_000_000;
+        assert_eq!(selection_weight(None, now), WEIGHT_CAP_SECS as u64 + 1);
+        assert_eq!(selection_weight(Some(now), now), 1);
+        assert_eq!(selection_weight(Some(now - 60), now), 61);
+        assert_eq!(selection_weight(Some(now - WEIGHT_CAP_SECS * 2), now), WEIGHT_CAP_SECS as u64 + 1);
+        // 时钟回拨
+        assert_eq!(selection_weight(Some(now + 60), now), 1);
+    }
+
+    #[test]
+    fn test_upload_input() {
+        let tags = vec![" Anime, sky ".to_string(), "SKY".to_string(), "".to_string()];
+        assert_eq!(normalize_tags(&tags).unwrap(), ["anime", "sky"]);
+        assert!(normalize_tags(&["x".repeat(MAX_TAG_LEN + 1)]).is_err());
+        let many: Vec<String> = (0..=MAX_TAGS).map(|i| i.to_string()).collect();
+        assert!(normalize_tags(&many).is_err());
+
+        assert!(upload_path("0123abcd.webp").is_ok());
+        assert!(upload_path("../config.toml").is_err());
+        assert!(upload_path(".hidden").is_err());
+        assert!(upload_path("a/b.jpg").is_err());
+
+        assert_eq!(Orientation::of(1920, 1080), Orientation::Landscape);
+        assert_eq!(Orientation::of(1080, 1920), Orientation::Portrait);
+        assert_eq!(Orientation::parse("Portrait"), Some(Orientation::Portrait));
+    }
+
+    #[test]
+    fn test_select_page() {
+        let wallpaper = |id: u32, orientation: Orientation, tags: &[&str]| Wallpaper {
+            wallpaper_id: id,
+            orientation,
+            file: format!("{}.jpeg", id),
+            width: 1920,
+            height: 1080,
+            blurhash: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            size: 0,
+            sha256: String::new(),
+            created_at: String::new(),
+        };
+        let all = vec![
+            wallpaper(101, Orientation::Landscape, &["sky"]),
+            wallpaper(102, Orientation::Portrait, &["sky", "anime"]),
+            wallpaper(103, Orientation::Landscape, &[]),
+        ];
+        let ids = |(total, items): (u64, Vec<Wallpaper>)| (total, items.iter().map(|w| w.wallpaper_id).collect::<Vec<_>>());
+
+        assert_eq!(ids(select_page(&all, None, None, 1, 2)), (3, vec![101, 102]));
+        assert_eq!(ids(select_page(&all, None, None, 2, 2)), (3, vec![103]));
+        assert_eq!(ids(select_page(&all, Some(" SKY "), None, 1, 10)), (2, vec![101, 102]));
+        assert_eq!(ids(select_page(&all, Some("sky"), Some(Orientation::Landscape), 1, 10)), (1, vec![101]));
+        assert_eq!(ids(select_page(&all, None, None, 5, 10)), (3, vec![]));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::settings::WeatherConfig;
+use crate::services::upstream_service;
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use moka::future::Cache;
+use once_cell::sync::{Lazy, OnceCell};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// 单次天气请求的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static WEATHER_CONFIG: OnceCell<WeatherConfig> = OnceCell::new();
+
+/// 当前天气和获取时间
+type WeatherEntry = (Value, DateTime<Utc>);
+
+// 只缓存配置地点的当前天气
+static WEATHER_CACHE: Lazy<Cache<(), WeatherEntry>> = Lazy::new(|| {
+    let ttl = WEATHER_CONFIG.get().map_or(600, |c| c.max_age_secs.max(1));
+    Cache::builder().max_capacity(1).time_to_live(Duration::from_secs(ttl)).build()
+});
+
+/// 初始化天气配置（启动时调用一次）
+pub fn init(config: &WeatherConfig) {
+    let _ = WEATHER_CONFIG.set(config.clone());
+}
+
+/// 天气数据的缓存时间（秒）
+pub fn max_age_secs() -> u64 {
+    WEATHER_CONFIG.get().map_or(0, |c| c.max_age_secs)
+}
+
+/// 配置地点的当前天气（带缓存），未配置经纬度时返回 Unavailable
+pub async fn current() -> Result<WeatherEntry> {
+    let Some((config, latitude, longitude)) = WEATHER_CONFIG.get().and_then(|c| Some((c, c.latitude?, c.longitude?)))
+    else {
+        return Err(Error::Unavailable("Weather is not configured".into(), 0));
+    };
+
+    if let Some(cached) = WEATHER_CACHE.get(&()).await {
+        return Ok(cached);
+    }
+    let raw = fetch(config, latitude, longitude).await?;
+    let entry = (summarize(&raw, &config.location)?, Utc::now());
+    WEATHER_CACHE.insert((), entry.clone()).await;
+    Ok(entry)
+}
+
+async fn fetch(config: &WeatherConfig, latitude: f64, longitude: f64) -> Result<Value> {
+    let url = format!(
+        "{}?latitude={}&longitude={}&current=temperature_2m,relative_humidity_2m,weather_code,wind_speed_10m&timezone=auto",
+        config.endpoint, latitude, longitude
+    );
+    upstream_service::http()
+        .client()
+        .get(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| Error::Internal(format!("Weather request failed: {}", e)))?
+        .error_for_status()
+        .map_err(|e| Error::Internal(format!("Weather request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::Internal(format!("Invalid weather response: {}", e)))
+}
+
+/// 从 Open-Meteo 响应中提取组件需要的字段
+fn summarize(raw: &Value, location: &str) -> Result<Value> {
+    let current = raw
+        .get("current")
+        .filter(|c| c.is_object())
+        .ok_or_else(|| Error::Internal("Weather response has no current data".into()))?;
+    Ok(json!({
+        "location": location,
+        "temperature_c": current["temperature_2m"],
+        "humidity": current["relative_humidity_2m"],
+        "wind_speed_kmh": current["wind_speed_10m"],
+        "weather_code": current["weather_code"],
+        "observed_at": current["time"],
+        "timezone": raw["timezone"],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let raw = json!({
+            "timezone": "Asia/Shanghai",
+            "current": {
+                "time": "2025-01-01T12:00",
+                "temperature_2m": 8.5,
+                "relative_humidity_2m": 60,
+                "weather_code": 3,
+                "wind_speed_10m": 11.2,
+            },
+        });
+        let summary = summarize(&raw, "上海").unwrap();
+        assert_eq!(summary["location"], "上海");
+        assert_eq!(summary["temperature_c"], 8.5);
+        assert_eq!(summary["weather_code"], 3);
+        assert_eq!(summary["timezone"], "Asia/Shanghai");
+        assert!(summarize(&json!({ "error": true }), "").is_err());
+    }
+}
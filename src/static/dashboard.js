@@ -0,0 +1,345 @@
+const { createApp, ref, reactive, computed, onMounted, onUnmounted } = Vue;
+
+createApp({
+    setup() {
+        const server = JSON.parse(document.getElementById('server-data').textContent);
+
+        const realtime = reactive({ cpu: 0, mem_rss_mb: 0, mem_virtual_mb: 0 });
+        const monitor = reactive({ current_memory_mb: 0, threshold_mb: 500, memory_usage_percentage: 0, memory_pressure: 'low' });
+        const sseConnected = ref(false);
+        const sseStatusText = ref('Connecting');
+        const sseStatusClass = ref('connecting'); // 新增：状态 class
+        const mainChart = ref(null);
+        const ua = reactive({ browser: '', os: '', device: '', cpu: '' });
+
+        let chartInstance = null;
+        let eventSource = null;
+
+        const prefs = reactive(server.preferences || { theme: 'system', widgets: ['connection', 'client', 'monitor'], refresh_interval_secs: 5 });
+        const prefsStatus = ref('');
+        const showWidget = (name) => prefs.widgets.includes(name);
+        const isDarkTheme = () => prefs.theme === 'dark'
+            || (prefs.theme === 'system' && window.matchMedia('(prefers-color-scheme: dark)').matches);
+
+        // 日志面板：EventSource 无法携带请求头，使用 fetch 读取 SSE 流
+        const logs = reactive({
+            token: sessionStorage.getItem('adminToken') || '',
+            tokenInput: '',
+            level: 'warn',
+            module: '',
+            entries: [],
+            error: '',
+        });
+        let logController = null;
+
+        const connectLogs = async () => {
+            if (logController) logController.abort();
+            if (!logs.token || !showWidget('logs')) return;
+
+            logController = new AbortController();
+            logs.entries = [];
+            logs.error = '';
+            const params = new URLSearchParams({ level: logs.level });
+            if (logs.module) params.set('module', logs.module);
+
+            try {
+                const res = await fetch(`/api/logs/stream?${params}`, {
+                    headers: { Authorization: `Bearer ${logs.token}` },
+                    signal: logController.signal,
+                });
+                if (res.status === 401 || res.status === 403) {
+                    sessionStorage.removeItem('adminToken');
+                    logs.token = '';
+                    return;
+                }
+                const reader = res.body.pipeThrough(new TextDecoderStream()).getReader();
+                let buffer = '';
+                for (;;) {
+                    const { value, done } = await reader.read();
+                    if (done) break;
+                    buffer += value;
+                    const events = buffer.split('\n\n');
+                    buffer = events.pop();
+                    for (const ev of events) {
+                        const data = ev.split('\n').filter(l => l.startsWith('data:')).map(l => l.slice(5)).join('\n');
+                        if (!ev.includes('event:log') || !data) continue;
+                        logs.entries.unshift(JSON.parse(data));
+                        if (logs.entries.length > 200) logs.entries.pop();
+                    }
+                }
+            } catch (err) {
+                if (err.name !== 'AbortError') logs.error = `Log stream disconnected: ${err.message}`;
+            }
+        };
+
+        // 站点统计（服务端缓存 5 分钟）
+        const siteStats = ref(null);
+        const loadSiteStats = async () => {
+            try {
+                const res = await fetch('/api/stats');
+                if (res.ok) siteStats.value = (await res.json()).data;
+            } catch (_) { /* 统计加载失败不影响面板 */ }
+        };
+        const formatBytes = (bytes) => {
+            const units = ['B', 'KB', 'MB', 'GB'];
+            let i = 0;
+            while (bytes >= 1024 && i < units.length - 1) { bytes /= 1024; i++; }
+            return `${bytes.toFixed(i ? 1 : 0)} ${units[i]}`;
+        };
+
+        const saveLogToken = () => {
+            logs.token = logs.tokenInput.trim();
+            logs.tokenInput = '';
+            if (logs.token) sessionStorage.setItem('adminToken', logs.token);
+            connectLogs();
+            loadPreferences();
+        };
+
+        // 仪表盘设置：页面由浏览器直接访问，服务端只能按后台登录会话（Cookie）应用；
+        // 只输入了管理员令牌时在这里读取，保存时两种方式都可用
+        const canEditPrefs = computed(() => server.personalized || !!logs.token);
+        const adminHeaders = () => (logs.token ? { Authorization: `Bearer ${logs.token}` } : {});
+
+        const applyPreferences = (next) => {
+            const refreshChanged = next.refresh_interval_secs !== prefs.refresh_interval_secs;
+            Object.assign(prefs, next);
+            if (prefs.theme === 'system') delete document.documentElement.dataset.theme;
+            else document.documentElement.dataset.theme = prefs.theme;
+            if (chartInstance) { chartInstance.destroy(); initChart(); }
+            if (refreshChanged && eventSource) connectSSE();
+        };
+
+        const loadPreferences = async () => {
+            if (server.personalized || !logs.token) return;
+            try {
+                const res = await fetch('/api/dashboard/preferences', { headers: adminHeaders() });
+                if (res.ok) applyPreferences((await res.json()).data);
+            } catch (err) { console.error(err); }
+        };
+
+        const savePreferences = async () => {
+            prefsStatus.value = 'Saving…';
+            try {
+                const res = await fetch('/api/dashboard/preferences', {
+                    method: 'PUT',
+                    headers: { 'Content-Type': 'application/json', ...adminHeaders() },
+                    body: JSON.stringify(prefs),
+                });
+                const body = await res.json();
+                if (!res.ok) {
+                    prefsStatus.value = body.message || `HTTP ${res.status}`;
+                    return;
+                }
+                applyPreferences(body.data);
+                prefsStatus.value = 'Saved';
+            } catch (err) {
+                prefsStatus.value = `Save failed: ${err.message}`;
+            }
+        };
+
+        const mongoConnected = computed(() => server.mongoStatus === 'Connected');
+        const displayLocation = computed(() => server.clientLocation === "Unknown Region" ? "Direct Connection" : server.clientLocation);
+
+        const formatLargeMem = (mb) => {
+            if (mb > 1024) return (mb / 1024).toFixed(1) + ' GB';
+            return mb.toFixed(0) + ' MB';
+        };
+
+        // 更新 SSE 状态的辅助函数
+        const updateSseStatus = (status) => {
+            switch (status) {
+                case 'live':
+                    sseConnected.value = true;
+                    sseStatusText.value = 'Live';
+                    sseStatusClass.value = 'live';
+                    break;
+                case 'reconnecting':
+                    sseConnected.value = false;
+                    sseStatusText.value = 'Reconnecting';
+                    sseStatusClass.value = 'reconnecting';
+                    break;
+                case 'paused':
+                    sseConnected.value = false;
+                    sseStatusText.value = 'Paused';
+                    sseStatusClass.value = 'paused';
+                    break;
+                case 'connecting':
+                default:
+                    sseConnected.value = false;
+                    sseStatusText.value = 'Connecting';
+                    sseStatusClass.value = 'connecting';
+                    break;
+            }
+        };
+
+        const parseUA = async () => {
+            // 优先使用 ClientHints，没有时才回退到 UA 解析
+            let useClientHints = false;
+            
+            if (navigator.userAgentData) {
+                try {
+                    const basicHints = navigator.userAgentData;
+                    const highEntropyHints = await navigator.userAgentData.getHighEntropyValues([
+                        "architecture", "model", "platformVersion", "fullVersionList", "bitness"
+                    ]);
+                    
+                    // 使用 ClientHints 数据
+                    useClientHints = true;
+                    
+                    // Browser 信息
+                    const primaryBrand = basicHints.brands.find(b => !b.brand.includes('Not') && !b.brand.includes('Chromium')) 
+                        || basicHints.brands[0];
+                    const fullVersionBrand = highEntropyHints.fullVersionList?.find(b => b.brand === primaryBrand?.brand);
+                    ua.browser = `${primaryBrand?.brand || 'Unknown'} ${fullVersionBrand?.version || primaryBrand?.version || ''}`;
+                    
+                    // OS 信息
+                    if (basicHints.platform === 'Windows') {
+                        const majorVersion = parseInt(highEntropyHints.platformVersion?.split('.')[0] || '0');
+                        ua.os = majorVersion >= 13 ? 'Windows 11' : 'Windows 10';
+                        if (highEntropyHints.platformVersion) {
+                            ua.os += ` (${highEntropyHints.platformVersion})`;
+                        }
+                    } else {
+                        ua.os = `${basicHints.platform || 'Unknown'} ${highEntropyHints.platformVersion || ''}`;
+                    }
+                    
+                    // Device 信息
+                    if (highEntropyHints.model) {
+                        ua.device = highEntropyHints.model;
+                    } else if (basicHints.mobile) {
+                        ua.device = 'Mobile Device';
+                    } else {
+                        ua.device = 'Desktop / Generic';
+                    }
+                    
+                    // Architecture 信息
+                    ua.cpu = highEntropyHints.architecture || 'Unknown Arch';
+                    if (highEntropyHints.bitness) {
+                        ua.cpu += ` (${highEntropyHints.bitness}-bit)`;
+                    }
+                    
+                } catch (error) {
+                    console.warn('ClientHints failed, falling back to UA parsing:', error);
+                    useClientHints = false;
+                }
+            }
+            
+            // 回退到 UA 解析（当 ClientHints 不可用或失败时）
+            if (!useClientHints) {
+                const p = new UAParser(server.rawUa);
+                const r = p.getResult();
+                ua.browser = `${r.browser.name || 'Unknown'} ${r.browser.version || ''}`;
+                ua.os = `${r.os.name || 'Unknown'} ${r.os.version || ''}`;
+                ua.device = r.device.model ? `${r.device.vendor || ''} ${r.device.model}` : 'Desktop / Generic';
+                ua.cpu = r.cpu.architecture || 'Unknown Arch';
+            }
+        };
+
+        const initChart = () => {
+            if (!mainChart.value) return;
+            const isDark = isDarkTheme();
+            const ctx = mainChart.value.getContext('2d');
+
+            const colors = {
+                cpu: '#E74C3C', cpuBg: 'rgba(231, 76, 60, 0.1)',
+                mem: isDark ? '#5DADE2' : '#3498DB', memBg: isDark ? 'rgba(93, 173, 226, 0.1)' : 'rgba(52, 152, 219, 0.1)',
+                sysMem: isDark ? '#F1C40F' : '#F39C12', sysMemBg: isDark ? 'rgba(241, 196, 15, 0.1)' : 'rgba(243, 156, 18, 0.1)',
+                grid: isDark ? 'rgba(255, 255, 255, 0.06)' : 'rgba(0, 0, 0, 0.06)',
+                text: isDark ? '#95A5A6' : '#7F8C8D'
+            };
+
+            // 使用服务器提供的初始历史数据
+            const initialLabels = server.timestamps || [];
+            // CPU数据现在直接是百分比值，不需要转换
+            const initialCpuData = server.cpuHistory || [];
+            const initialMemData = server.memHistory || [];
+            const initialSysMemData = server.systemMemoryHistory || [];
+
+            chartInstance = new Chart(ctx, {
+                type: 'line',
+                data: {
+                    labels: initialLabels, datasets: [
+                        { label: 'CPU %', data: initialCpuData, borderColor: colors.cpu, backgroundColor: colors.cpuBg, fill: true, borderWidth: 2, tension: 0.4, pointRadius: 0, yAxisID: 'y' },
+                        { label: 'RSS (MB)', data: initialMemData, borderColor: colors.mem, backgroundColor: colors.memBg, fill: true, borderWidth: 2, tension: 0.4, pointRadius: 0, yAxisID: 'y1' },
+                        { label: 'System Memory (MB)', data: initialSysMemData, borderColor: colors.sysMem, backgroundColor: colors.sysMemBg, fill: false, borderWidth: 2, tension: 0.4, pointRadius: 0, yAxisID: 'y1' }
+                    ]
+                },
+                options: {
+                    responsive: true, maintainAspectRatio: false,
+                    interaction: { mode: 'index', intersect: false },
+                    plugins: { legend: { display: false } },
+                    scales: {
+                        x: { grid: { color: colors.grid, drawBorder: false }, ticks: { display: false } },
+                        y: { position: 'left', min: 0, suggestedMax: 10, grid: { color: colors.grid, drawBorder: false }, ticks: { color: colors.text, font: { size: 10 }, callback: v => v + '%' } },
+                        y1: { position: 'right', min: 0, grid: { drawOnChartArea: false }, ticks: { color: colors.text, font: { size: 10 } } }
+                    },
+                    animation: { duration: 0 }
+                }
+            });
+        };
+
+        const connectSSE = () => {
+            if (eventSource) eventSource.close();
+
+            updateSseStatus('connecting');
+            eventSource = new EventSource(`/api/metrics/stream?refresh=${prefs.refresh_interval_secs}`);
+
+            eventSource.onopen = () => {
+                updateSseStatus('live');
+            };
+
+            eventSource.onmessage = (e) => {
+                try {
+                    const d = JSON.parse(e.data);
+                    realtime.cpu = d.cpu;
+                    realtime.mem_rss_mb = d.mem_rss_mb;
+                    realtime.mem_virtual_mb = d.mem_virtual_mb;
+                    if (d.memory_monitor) Object.assign(monitor, d.memory_monitor);
+
+                    if (chartInstance) {
+                        chartInstance.data.labels = d.timestamps || [];
+                        // CPU数据现在直接是百分比值，不需要转换
+                        chartInstance.data.datasets[0].data = d.cpu_history || [];
+                        chartInstance.data.datasets[1].data = d.mem_history || [];
+                        chartInstance.data.datasets[2].data = d.system_memory_history || [];
+                        chartInstance.update('none');
+                    }
+                } catch (err) { console.error(err); }
+            };
+
+            eventSource.onerror = () => {
+                updateSseStatus('reconnecting');
+            };
+        };
+
+        onMounted(() => {
+            initChart();
+            connectSSE();
+            connectLogs();
+            loadPreferences();
+            parseUA();
+            loadSiteStats();
+            setInterval(loadSiteStats, 60 * 1000);
+
+            window.matchMedia('(prefers-color-scheme: dark)').addEventListener('change', () => {
+                if (chartInstance) { chartInstance.destroy(); initChart(); }
+            });
+
+            document.addEventListener('visibilitychange', () => {
+                if (document.hidden) {
+                    if (eventSource) { eventSource.close(); eventSource = null; }
+                    updateSseStatus('paused');
+                } else {
+                    connectSSE();
+                }
+            });
+        });
+
+        return {
+            server, realtime, monitor, sseConnected, sseStatusText, sseStatusClass,
+            mongoConnected, displayLocation, mainChart, ua, formatLargeMem, showWidget,
+            logs, connectLogs, saveLogToken, siteStats, formatBytes,
+            prefs, prefsStatus, canEditPrefs, savePreferences
+        };
+    }
+}).mount('#app');
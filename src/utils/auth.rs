@@ -0,0 +1,173 @@
+use crate::config::settings::Config;
+use crate::services::user_service::UserService;
+use mongodb::bson::Document;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use sha2::{Digest, Sha256};
+
+/// 默认管理员用户标识
+pub const DEFAULT_ADMIN_ID: &str = "admin";
+
+/// 管理后台登录会话的 Cookie 名称
+pub const ADMIN_SESSION_COOKIE: &str = "admin_session";
+
+/// 管理后台会话在 sessions 集合中的所有者标识（不会与 QQ OpenID 冲突）
+pub fn admin_session_owner(admin_id: &str) -> String {
+    format!("admin:{}", admin_id)
+}
+
+fn client_actor(req: &Request<'_>) -> String {
+    let ip = req
+        .client_ip()
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("admin@{}", ip)
+}
+
+/// 管理员身份守卫
+///
+/// 从 `Authorization: Bearer <token>` 或 `X-Admin-Token` 头读取令牌，与配置中的 admin.token 比对；
+/// 未配置令牌时所有管理接口返回 403
+pub struct AdminGuard {
+    /// 管理员用户标识（用于关联个人设置，目前只有单一令牌对应的 admin 用户）
+    pub id: String,
+    /// 操作者标识（用于审计日志）
+    pub actor: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminGuard {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let expected = match req
+            .rocket()
+            .state::<Config>()
+            .and_then(|c| c.admin.token.as_deref())
+        {
+            Some(token) if !token.is_empty() => token,
+            _ => return Outcome::Error((Status::Forbidden, ())),
+        };
+
+        let provided = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| req.headers().get_one("X-Admin-Token"));
+
+        match provided {
+            Some(token) if secure_eq(token.trim(), expected) => Outcome::Success(AdminGuard {
+                id: DEFAULT_ADMIN_ID.to_string(),
+                actor: client_actor(req),
+            }),
+            None => Outcome::Error((Status::Unauthorized, ())),
+            _ => {
+                log::warn!("Rejected admin request to {}", req.uri());
+                Outcome::Error((Status::Unauthorized, ()))
+            }
+        }
+    }
+}
+
+/// 管理后台页面的身份守卫
+///
+/// 从 `admin_session` Cookie 读取 /admin/login 签发的会话令牌；
+/// 未登录时转发（Forward），由页面路由跳转到登录页
+pub struct AdminSession {
+    pub admin: AdminGuard,
+    /// 会话令牌（退出登录时吊销）
+    pub token: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminSession {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = req
+            .cookies()
+            .get(ADMIN_SESSION_COOKIE)
+            .map(|c| c.value().to_string())
+            .filter(|t| !t.is_empty())
+        else {
+            return Outcome::Forward(Status::Unauthorized);
+        };
+
+        match UserService::session_owner(&token).await {
+            Ok(Some(owner)) if owner == admin_session_owner(DEFAULT_ADMIN_ID) => Outcome::Success(AdminSession {
+                admin: AdminGuard {
+                    id: DEFAULT_ADMIN_ID.to_string(),
+                    actor: client_actor(req),
+                },
+                token,
+            }),
+            Ok(_) => Outcome::Forward(Status::Unauthorized),
+            Err(e) => {
+                log::error!("Failed to look up admin session: {}", e);
+                Outcome::Error((Status::ServiceUnavailable, ()))
+            }
+        }
+    }
+}
+
+/// 管理员令牌或管理后台登录会话（仪表盘页面由浏览器直接访问，只能携带 Cookie）
+pub struct AdminAuth(pub AdminGuard);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminAuth {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let has_token = req.headers().contains("Authorization") || req.headers().contains("X-Admin-Token");
+        if has_token {
+            return AdminGuard::from_request(req).await.map(AdminAuth);
+        }
+        match AdminSession::from_request(req).await {
+            Outcome::Success(session) => Outcome::Success(AdminAuth(session.admin)),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(_) => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// 用户身份守卫
+///
+/// 从 `Authorization: Bearer <token>` 头读取 /user/get 签发的会话令牌；
+/// 令牌无效、过期或账号已注销时返回 401
+pub struct UserGuard {
+    /// 用户文档（未移除敏感字段，对外返回前需调用 strip_sensitive_fields）
+    pub user: Document,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserGuard {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        match UserService::find_by_session(token).await {
+            Ok(Some(user)) => Outcome::Success(UserGuard { user }),
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(e) => {
+                log::error!("Failed to look up user session: {}", e);
+                Outcome::Error((Status::ServiceUnavailable, ()))
+            }
+        }
+    }
+}
+
+/// 常量时间字符串比较（先做 SHA-256 消除长度差异，避免计时侧信道）
+pub fn secure_eq(a: &str, b: &str) -> bool {
+    let ha = Sha256::digest(a.as_bytes());
+    let hb = Sha256::digest(b.as_bytes());
+    ha.iter().zip(hb.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,117 @@
+// shields.io 风格的 SVG 徽章
+
+/// 徽章高度
+const HEIGHT: u32 = 20;
+/// 文字左右留白（合计）
+const TEXT_PADDING: f32 = 10.0;
+
+/// Verdana 11px 下 ASCII 可见字符（0x20..=0x7E）的宽度（像素）
+const VERDANA_11_WIDTHS: [f32; 95] = [
+    3.87, 4.33, 5.05, 9.0, 7.0, 11.84, 7.99, 2.95, 4.99, 4.99, 7.0, 9.0, 4.0, 4.99, 4.0, 4.99, // ' '..'/'
+    7.0, 7.0, 7.0, 7.0, 7.0, 7.0, 7.0, 7.0, 7.0, 7.0, // '0'..'9'
+    4.99, 4.99, 9.0, 9.0, 9.0, 6.0, 11.0, // ':'..'@'
+    7.52, 7.54, 7.68, 8.48, 6.96, 6.32, 8.53, 8.27, 4.61, 5.0, 7.62, 6.12, 9.27, // 'A'..'M'
+    8.23, 8.66, 6.63, 8.66, 7.65, 7.52, 6.78, 8.05, 7.52, 10.88, 7.54, 6.77, 7.54, // 'N'..'Z'
+    4.99, 4.99, 4.99, 9.0, 7.0, 7.0, // '['..'`'
+    6.61, 6.85, 5.73, 6.85, 6.55, 3.87, 6.85, 6.96, 3.02, 3.79, 6.51, 3.02, 10.7, // 'a'..'m'
+    6.96, 6.68, 6.85, 6.85, 4.69, 5.73, 4.33, 6.96, 6.51, 8.98, 6.51, 6.51, 5.78, // 'n'..'z'
+    6.98, 4.99, 6.98, 9.0, // '{'..'~'
+];
+
+/// 预设颜色名称（与 shields.io 一致）
+const NAMED_COLORS: [(&str, &str); 10] = [
+    ("brightgreen", "#4c1"),
+    ("green", "#97ca00"),
+    ("yellowgreen", "#a4a61d"),
+    ("yellow", "#dfb317"),
+    ("orange", "#fe7d37"),
+    ("red", "#e05d44"),
+    ("blue", "#007ec6"),
+    ("lightgrey", "#9f9f9f"),
+    ("grey", "#555"),
+    ("informational", "#007ec6"),
+];
+
+/// 估算文本在 Verdana 11px 下的宽度
+pub fn text_width(text: &str) -> f32 {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7E => VERDANA_11_WIDTHS[(c as u32 - 0x20) as usize],
+            // CJK、全角字符、emoji 等按全宽计算
+            0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1FAFF | 0x20000..=0x3FFFD => 11.0,
+            _ => 7.0,
+        })
+        .sum()
+}
+
+/// 解析颜色：预设名称或 3/6 位十六进制（可省略 #），无效时返回 None
+pub fn parse_color(color: &str) -> Option<String> {
+    let color = color.trim();
+    if let Some((_, hex)) = NAMED_COLORS.iter().find(|(name, _)| name.eq_ignore_ascii_case(color)) {
+        return Some(hex.to_string());
+    }
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(format!("#{}", hex.to_ascii_lowercase()))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 生成徽章 SVG，颜色需已通过 parse_color 校验
+pub fn render(label: &str, value: &str, label_color: &str, color: &str) -> String {
+    let label_width = (text_width(label) + TEXT_PADDING).round() as u32;
+    let value_width = (text_width(value) + TEXT_PADDING).round() as u32;
+    let width = label_width + value_width;
+    // 文字居中位置（乘以 10 后配合 scale(.1) 使用，获得亚像素精度）
+    let label_x = label_width * 5;
+    let value_x = label_width * 10 + value_width * 5;
+    let label_len = (text_width(label) * 10.0).round() as u32;
+    let value_len = (text_width(value) * 10.0).round() as u32;
+    let label = escape_xml(label);
+    let value = escape_xml(value);
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{HEIGHT}" role="img" aria-label="{label}: {value}"><title>{label}: {value}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="{HEIGHT}" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="{HEIGHT}" fill="{label_color}"/><rect x="{label_width}" width="{value_width}" height="{HEIGHT}" fill="{color}"/><rect width="{width}" height="{HEIGHT}" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" text-rendering="geometricPrecision" font-size="110"><text aria-hidden="true" x="{label_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)" textLength="{label_len}">{label}</text><text x="{label_x}" y="140" transform="scale(.1)" fill="#fff" textLength="{label_len}">{label}</text><text aria-hidden="true" x="{value_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)" textLength="{value_len}">{value}</text><text x="{value_x}" y="140" transform="scale(.1)" fill="#fff" textLength="{value_len}">{value}</text></g></svg>"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("brightgreen").as_deref(), Some("#4c1"));
+        assert_eq!(parse_color("FF8800").as_deref(), Some("#ff8800"));
+        assert_eq!(parse_color("#abc").as_deref(), Some("#abc"));
+        assert_eq!(parse_color("red\" onload=\"x"), None);
+        assert_eq!(parse_color("#12345"), None);
+    }
+
+    #[test]
+    fn test_render_escapes_text() {
+        let svg = render("a<b", "\"x\" & y", "#555", "#4c1");
+        assert!(svg.contains("a&lt;b"));
+        assert!(svg.contains("&quot;x&quot; &amp; y"));
+        assert!(!svg.contains("a<b"));
+        assert!(text_width("中文") > text_width("ab"));
+    }
+}
@@ -1,24 +1,48 @@
-use log::{debug, error, info};
+use crate::config::settings::{CacheConfig, CacheNamespaceConfig};
+use crate::utils::redis::{RedisClient, Reply};
+use crate::utils::slow_requests;
+use log::{debug, error, info, warn};
 use moka::future::Cache;
-use once_cell::sync::Lazy;
+use moka::notification::RemovalCause;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
 use std::time::Duration;
 
-// 创建一个全局的轻量级缓存实例（只缓存小数据，如元数据、配置等）
-pub static CACHE_BUCKET: Lazy<Cache<String, Vec<u8>>> = Lazy::new(|| {
-    Cache::builder()
-        .time_to_live(Duration::from_secs(12 * 60 * 60)) // 12小时刷新全部缓存
-        .time_to_idle(Duration::from_secs(2 * 60 * 60)) // 2小时不访问则失效
-        .weigher(|_key, value: &Vec<u8>| -> u32 {
-            // 限制单个缓存项最大1MB，超过则不缓存到内存
-            if value.len() > 1024 * 1024 {
+/// 单个缓存项（键 + 值）的上限，超过时 MemoryBackend 将值转存到硬盘，内存中只保留占位标记
+const MAX_MEMORY_ENTRY_BYTES: usize = 1024 * 1024;
+/// 转存到硬盘的值在内存中的占位标记（过期、淘汰和删除仍由内存缓存控制）
+const SPILL_MARKER: &[u8] = b"\0space-spill\0";
+/// 可转存的值的上限，更大的值不缓存
+const MAX_SPILL_BYTES: usize = 64 * 1024 * 1024;
+/// 进程内缓存的总容量，按比例分给各命名空间和默认缓存（命名空间配置了 max_size_mb 时按配置）
+const MEMORY_BUDGET_BYTES: u64 = 50 * 1024 * 1024;
+/// 不属于任何命名空间的键使用的默认缓存：占总容量的百分比和空闲过期时间
+const DEFAULT_BUCKET_SHARE: u64 = 22;
+const DEFAULT_BUCKET_IDLE: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// 创建进程内缓存（按键和值的字节数计算容量）
+fn build_bucket(ttl: Duration, idle: Option<Duration>, max_bytes: u64) -> Cache<String, Vec<u8>> {
+    let mut builder = Cache::builder()
+        .time_to_live(ttl)
+        .weigher(|key: &String, value: &Vec<u8>| -> u32 {
+            let size = key.len() + value.len();
+            if size > MAX_MEMORY_ENTRY_BYTES {
                 u32::MAX // 拒绝缓存大文件
             } else {
-                value.len() as u32
+                size as u32
             }
         })
-        .max_capacity(50 * 1024 * 1024) // 最大50MB内存缓存（按 weigher 权重计算）
-        .build()
-});
+        .max_capacity(max_bytes)
+        .eviction_listener(on_removal);
+    if let Some(idle) = idle {
+        builder = builder.time_to_idle(idle);
+    }
+    builder.build()
+}
 
 // 缓存项目，返回是否是新插入的项目
 pub async fn put<K, V>(cache: &Cache<K, V>, key: K, value: V) -> bool
@@ -58,21 +82,1036 @@ where
     cache.remove(key).await;
 }
 
+// ==========================================
+// Cache Namespaces
+// ==========================================
+
+/// 可单独配置过期时间和容量的缓存命名空间（按键前缀区分）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Wallpapers,
+    Avatars,
+    ServiceWorker,
+    NcmStatus,
+    /// 渲染结果和上游数据片段（徽章 SVG、codetime 统计等）
+    Snippets,
+    /// 其他缓存的元数据（友链头像的新鲜度和失败计数等）
+    Metadata,
+}
+
+impl Namespace {
+    pub const ALL: [Namespace; 6] = [
+        Self::Wallpapers,
+        Self::Avatars,
+        Self::ServiceWorker,
+        Self::NcmStatus,
+        Self::Snippets,
+        Self::Metadata,
+    ];
+
+    /// 配置名，同时作为硬盘缓存的子目录名
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wallpapers => "wallpapers",
+            Self::Avatars => "avatars",
+            Self::ServiceWorker => "sw_js",
+            Self::NcmStatus => "ncm_status",
+            Self::Snippets => "snippets",
+            Self::Metadata => "metadata",
+        }
+    }
+
+    /// 属于该命名空间的缓存键前缀
+    pub fn key_prefix(self) -> &'static str {
+        match self {
+            Self::Wallpapers => "wallpaper:",
+            Self::Avatars => "avatar:",
+            Self::ServiceWorker => "sw_js:",
+            Self::NcmStatus => "ncm_status:",
+            Self::Snippets => "snippet:",
+            Self::Metadata => "meta:",
+        }
+    }
+
+    /// 命名空间内的缓存键（`<前缀><id>`）
+    pub fn key(self, id: impl std::fmt::Display) -> String {
+        format!("{}{}", self.key_prefix(), id)
+    }
+
+    /// 派生数据（转码、缩放、渲染结果）的缓存键（`<前缀><版本标记>:<id>`），
+    /// 版本或纪元改变后旧的结果不再被读取，原始下载使用 `key` 不受影响
+    pub fn derived_key(self, id: impl std::fmt::Display) -> String {
+        format!("{}{}:{}", self.key_prefix(), derived_tag(), id)
+    }
+
+    /// 键所属的命名空间
+    pub fn of(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ns| key.starts_with(ns.key_prefix()))
+    }
+
+    fn config(self, config: &CacheConfig) -> &CacheNamespaceConfig {
+        match self {
+            Self::Wallpapers => &config.wallpapers,
+            Self::Avatars => &config.avatars,
+            Self::ServiceWorker => &config.sw_js,
+            Self::NcmStatus => &config.ncm_status,
+            Self::Snippets => &config.snippets,
+            Self::Metadata => &config.metadata,
+        }
+    }
+
+    /// 未配置容量时分到的内存缓存百分比（与 DEFAULT_BUCKET_SHARE 合计 100；壁纸以硬盘缓存为主，内存只保留小图和转码结果）
+    fn memory_share(self) -> u64 {
+        match self {
+            Self::Wallpapers => 20,
+            Self::Avatars => 40,
+            Self::ServiceWorker => 4,
+            Self::NcmStatus => 2,
+            Self::Snippets => 8,
+            Self::Metadata => 4,
+        }
+    }
+}
+
+struct NamespaceSettings {
+    namespace: Namespace,
+    /// 为空时使用全局默认值
+    ttl: Option<Duration>,
+    max_bytes: Option<u64>,
+    /// 该命名空间独立的内存缓存，不与其他数据争用容量
+    bucket: Cache<String, Vec<u8>>,
+}
+
+struct CacheSettings {
+    disk_ttl: Duration,
+    disk_limits: DiskLimits,
+    disk_compression: Option<DiskCompression>,
+    namespaces: Vec<NamespaceSettings>,
+    /// 不属于任何命名空间的键
+    default_bucket: Cache<String, Vec<u8>>,
+    /// 内存缓存中最长的过期时间，转存文件超过该时间后一定已无占位标记引用
+    spill_ttl: Duration,
+}
+
+impl CacheSettings {
+    fn new(config: &CacheConfig) -> Self {
+        let default_ttl = Duration::from_secs(config.ttl_secs.max(1));
+        let namespaces: Vec<NamespaceSettings> = Namespace::ALL
+            .into_iter()
+            .map(|namespace| {
+                let ns = namespace.config(config);
+                let ttl = ns.ttl_secs.map(|secs| Duration::from_secs(secs.max(1)));
+                let max_bytes = ns.max_size_mb.map(|mb| mb * 1024 * 1024);
+                let bucket = build_bucket(
+                    ttl.unwrap_or(default_ttl),
+                    None,
+                    max_bytes.unwrap_or(MEMORY_BUDGET_BYTES * namespace.memory_share() / 100),
+                );
+                NamespaceSettings {
+                    namespace,
+                    ttl,
+                    max_bytes,
+                    bucket,
+                }
+            })
+            .collect();
+        Self {
+            disk_ttl: Duration::from_secs(config.disk_ttl_secs),
+            disk_limits: DiskLimits {
+                max_bytes: config.disk_max_size_mb.map(|mb| mb * 1024 * 1024),
+                max_files: config.disk_max_files,
+            },
+            disk_compression: config.disk_compress_min_kb.map(|kb| DiskCompression {
+                min_bytes: kb * 1024,
+                level: config.disk_compress_level.clamp(1, 19),
+            }),
+            spill_ttl: namespaces
+                .iter()
+                .filter_map(|ns| ns.ttl)
+                .fold(default_ttl, Duration::max),
+            namespaces,
+            default_bucket: build_bucket(
+                default_ttl,
+                Some(DEFAULT_BUCKET_IDLE),
+                MEMORY_BUDGET_BYTES * DEFAULT_BUCKET_SHARE / 100,
+            ),
+        }
+    }
+}
+
+static SETTINGS: OnceCell<CacheSettings> = OnceCell::new();
+
+fn settings() -> &'static CacheSettings {
+    SETTINGS.get_or_init(|| CacheSettings::new(&CacheConfig::default()))
+}
+
+fn namespace_settings(key: &str) -> Option<&'static NamespaceSettings> {
+    let namespace = Namespace::of(key)?;
+    settings().namespaces.iter().find(|s| s.namespace == namespace)
+}
+
+/// 键所属命名空间配置的过期时间
+fn namespace_ttl(key: &str) -> Option<Duration> {
+    namespace_settings(key)?.ttl
+}
+
+/// 键所在的进程内缓存
+fn bucket(key: &str) -> &'static Cache<String, Vec<u8>> {
+    match namespace_settings(key) {
+        Some(ns) => &ns.bucket,
+        None => &settings().default_bucket,
+    }
+}
+
+/// 所有进程内缓存（默认缓存和各命名空间的缓存）
+fn buckets() -> impl Iterator<Item = &'static Cache<String, Vec<u8>>> {
+    let settings = settings();
+    std::iter::once(&settings.default_bucket).chain(settings.namespaces.iter().map(|s| &s.bucket))
+}
+
+/// 单个进程内缓存（默认缓存或某个命名空间的缓存）的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketUsage {
+    /// 命名空间名，default 为不属于任何命名空间的键
+    pub name: &'static str,
+    pub entries: u64,
+    /// 占用字节数（按值大小计）
+    pub bytes: u64,
+    /// 容量上限（字节）
+    pub max_bytes: Option<u64>,
+    pub ttl_secs: Option<u64>,
+}
+
+/// 各进程内缓存的条目数、占用和容量上限（顺序与 `stats` 相同）
+pub fn bucket_usage() -> Vec<BucketUsage> {
+    let settings = settings();
+    let named = settings.namespaces.iter().map(|s| (s.namespace.name(), &s.bucket));
+    std::iter::once(("default", &settings.default_bucket))
+        .chain(named)
+        .map(|(name, bucket)| BucketUsage {
+            name,
+            entries: bucket.entry_count(),
+            bytes: bucket.weighted_size(),
+            max_bytes: bucket.policy().max_capacity(),
+            ttl_secs: bucket.policy().time_to_live().map(|ttl| ttl.as_secs()),
+        })
+        .collect()
+}
+
+/// 清理所有进程内缓存中的过期条目
+pub async fn run_pending_tasks() {
+    for bucket in buckets() {
+        bucket.run_pending_tasks().await;
+    }
+}
+
+/// 所有进程内缓存的条目数和占用字节数
+pub fn memory_usage() -> (u64, u64) {
+    buckets().fold((0, 0), |(entries, bytes), bucket| {
+        (entries + bucket.entry_count(), bytes + bucket.weighted_size())
+    })
+}
+
+// ==========================================
+// Statistics
+// ==========================================
+
+/// 缓存层：memory 为字节值缓存后端（进程内或 Redis），disk 为硬盘缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Memory,
+    Disk,
+}
+
+impl Layer {
+    fn name(self) -> &'static str {
+        match self {
+            Layer::Memory => "memory",
+            Layer::Disk => "disk",
+        }
+    }
+}
+
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    puts: AtomicU64,
+    evictions: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            puts: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> LayerStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        LayerStats {
+            hits,
+            misses,
+            puts: self.puts.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            hit_rate: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+        }
+    }
+}
+
+// 按命名空间分组的计数：下标 0 为不属于任何命名空间的键，其余与 Namespace::ALL 对应
+static STATS: [[Counters; 2]; Namespace::ALL.len() + 1] =
+    [const { [Counters::new(), Counters::new()] }; Namespace::ALL.len() + 1];
+
+fn group_index(key: &str) -> usize {
+    Namespace::of(key)
+        .and_then(|ns| Namespace::ALL.iter().position(|&n| n == ns))
+        .map_or(0, |i| i + 1)
+}
+
+fn counters(layer: Layer, group: usize) -> &'static Counters {
+    &STATS[group][layer as usize]
+}
+
+fn record_lookup(layer: Layer, key: &str, hit: bool) {
+    let namespace = Namespace::of(key).map_or("default", Namespace::name);
+    slow_requests::record_cache(namespace, layer.name(), hit);
+    let counters = counters(layer, group_index(key));
+    if hit {
+        counters.hits.fetch_add(1, Ordering::Relaxed);
+    } else {
+        counters.misses.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn record_put(layer: Layer, key: &str, bytes: usize) {
+    let counters = counters(layer, group_index(key));
+    counters.puts.fetch_add(1, Ordering::Relaxed);
+    counters.bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+fn record_evictions(layer: Layer, group: usize, count: u64) {
+    counters(layer, group).evictions.fetch_add(count, Ordering::Relaxed);
+}
+
+/// 进程内缓存因过期或容量不足淘汰条目时计数（主动删除不计入）；
+/// 占位标记被淘汰或删除时一并删除转存的文件（被替换时文件已由新值覆盖）
+fn on_removal(key: Arc<String>, value: Vec<u8>, cause: RemovalCause) {
+    if cause.was_evicted() {
+        record_evictions(Layer::Memory, group_index(&key), 1);
+    }
+    if value == SPILL_MARKER && cause != RemovalCause::Replaced {
+        let _ = fs::remove_file(spill_path(&key));
+    }
+}
+
+/// 单个缓存层的计数（进程启动以来）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LayerStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub puts: u64,
+    /// 过期或超出容量被淘汰的条目
+    pub evictions: u64,
+    pub bytes_written: u64,
+    /// 命中率（尚无读取时为空）
+    pub hit_rate: Option<f64>,
+}
+
+impl LayerStats {
+    fn add(&mut self, other: &LayerStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.puts += other.puts;
+        self.evictions += other.evictions;
+        self.bytes_written += other.bytes_written;
+        let lookups = self.hits + self.misses;
+        self.hit_rate = (lookups > 0).then(|| self.hits as f64 / lookups as f64);
+    }
+}
+
+/// 单个命名空间的计数
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespaceStats {
+    /// 命名空间名，default 为不属于任何命名空间的键
+    pub name: &'static str,
+    pub memory: LayerStats,
+    pub disk: LayerStats,
+}
+
+/// 各命名空间的命中、写入和淘汰计数
+pub fn stats() -> Vec<NamespaceStats> {
+    let names = std::iter::once("default").chain(Namespace::ALL.iter().map(|ns| ns.name()));
+    names
+        .enumerate()
+        .map(|(group, name)| NamespaceStats {
+            name,
+            memory: counters(Layer::Memory, group).snapshot(),
+            disk: counters(Layer::Disk, group).snapshot(),
+        })
+        .collect()
+}
+
+/// 所有命名空间合计的 (内存层, 硬盘层) 计数
+pub fn total_stats() -> (LayerStats, LayerStats) {
+    stats()
+        .iter()
+        .fold(Default::default(), |(mut memory, mut disk): (LayerStats, LayerStats), ns| {
+            memory.add(&ns.memory);
+            disk.add(&ns.disk);
+            (memory, disk)
+        })
+}
+
+/// 内存和硬盘合计的命中率（尚无读取时为空）
+pub fn hit_rate() -> Option<f64> {
+    let (mut total, disk) = total_stats();
+    total.add(&disk);
+    total.hit_rate
+}
+
+// ==========================================
+// Shared Cache Backend
+// ==========================================
+
+/// 字节值缓存后端：默认为进程内缓存，多实例部署时可配置为 Redis 共享
+#[rocket::async_trait]
+pub trait CacheBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// 是否在多个实例间共享
+    fn shared(&self) -> bool;
+    async fn get(&self, key: &str) -> Option<Vec<u8>>;
+    /// 写入缓存，`ttl` 为空时使用后端默认的过期时间
+    async fn put(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>);
+    async fn remove(&self, key: &str);
+    /// 按前缀列出键（最多 `limit` 个）
+    async fn keys(&self, prefix: &str, limit: usize) -> Vec<String>;
+    /// 按前缀删除，返回删除的条目数
+    async fn remove_prefix(&self, prefix: &str) -> u64;
+    /// 清空缓存，返回删除的条目数
+    async fn clear(&self) -> u64;
+    /// 条目数和占用字节数（后端无法统计时为空）
+    async fn usage(&self) -> Option<(u64, u64)>;
+}
+
+/// 进程内缓存（默认缓存及各命名空间的独立缓存，过期时间由缓存本身控制）
+pub struct MemoryBackend;
+
+#[rocket::async_trait]
+impl CacheBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn shared(&self) -> bool {
+        false
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let value = bucket(key).get(key).await;
+        record_lookup(Layer::Memory, key, value.is_some());
+        if value.as_deref() != Some(SPILL_MARKER) {
+            return value;
+        }
+
+        let owned = key.to_string();
+        let spilled = tokio::task::spawn_blocking(move || read_spill(&owned)).await.ok().flatten();
+        record_lookup(Layer::Disk, key, spilled.is_some());
+        if spilled.is_none() {
+            // 文件已被硬盘容量清理删除或已损坏
+            bucket(key).remove(key).await;
+        }
+        spilled
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>, _ttl: Option<Duration>) {
+        if key.len() + value.len() <= MAX_MEMORY_ENTRY_BYTES {
+            record_put(Layer::Memory, key, value.len());
+            bucket(key).insert(key.to_string(), value).await;
+            return;
+        }
+        if value.len() > MAX_SPILL_BYTES {
+            debug!("Not caching {}: {} bytes exceeds the spill limit", key, value.len());
+            return;
+        }
+
+        let owned = key.to_string();
+        let written = tokio::task::spawn_blocking(move || write_spill(&owned, &value))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        match written {
+            Ok(stored) => {
+                record_put(Layer::Disk, key, stored);
+                bucket(key).insert(key.to_string(), SPILL_MARKER.to_vec()).await;
+            }
+            Err(e) => error!("Failed to spill cache value {} to disk: {}", key, e),
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        bucket(key).remove(key).await;
+    }
+
+    async fn keys(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let mut keys: Vec<String> = buckets()
+            .flat_map(|bucket| bucket.iter())
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        keys.sort();
+        keys.truncate(limit);
+        keys
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> u64 {
+        let mut removed = 0;
+        for bucket in buckets() {
+            let keys: Vec<String> = bucket
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .map(|(key, _)| key.to_string())
+                .collect();
+            for key in keys {
+                if bucket.remove(&key).await.is_some() {
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
+
+    async fn clear(&self) -> u64 {
+        let (entries, _) = memory_usage();
+        for bucket in buckets() {
+            bucket.invalidate_all();
+        }
+        entries
+    }
+
+    async fn usage(&self) -> Option<(u64, u64)> {
+        run_pending_tasks().await;
+        Some(memory_usage())
+    }
+}
+
+/// Redis 缓存，键统一加上前缀；Redis 不可用时按未命中处理，不影响请求
+pub struct RedisBackend {
+    client: RedisClient,
+    prefix: String,
+    ttl: Duration,
+}
+
+impl RedisBackend {
+    pub fn new(config: &CacheConfig) -> crate::Result<Self> {
+        Ok(Self {
+            client: RedisClient::from_url(&config.redis_url)?,
+            prefix: config.key_prefix.clone(),
+            ttl: Duration::from_secs(config.ttl_secs.max(1)),
+        })
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// 用 SCAN 遍历匹配前缀的键（带键前缀的完整键名），`limit` 为空表示全部
+    async fn scan(&self, prefix: &str, limit: Option<usize>) -> Vec<Vec<u8>> {
+        // 转义通配符，前缀按字面匹配
+        let mut pattern = String::new();
+        for c in self.key(prefix).chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+
+        let mut cursor = b"0".to_vec();
+        let mut found = Vec::new();
+        loop {
+            let reply = self
+                .client
+                .command(&[b"SCAN", &cursor, b"MATCH", pattern.as_bytes(), b"COUNT", b"500"])
+                .await;
+            let (next, keys) = match reply {
+                Ok(Reply::Array(mut parts)) if parts.len() == 2 => match (parts.remove(0), parts.remove(0)) {
+                    (Reply::Bulk(next), Reply::Array(keys)) => (next, keys),
+                    _ => break,
+                },
+                Ok(_) => break,
+                Err(e) => {
+                    warn!("Redis cache scan failed: {}", e);
+                    break;
+                }
+            };
+            found.extend(keys.into_iter().filter_map(Reply::into_bytes));
+            if next == b"0" || limit.is_some_and(|limit| found.len() >= limit) {
+                break;
+            }
+            cursor = next;
+        }
+        if let Some(limit) = limit {
+            found.truncate(limit);
+        }
+        found
+    }
+}
+
+#[rocket::async_trait]
+impl CacheBackend for RedisBackend {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn shared(&self) -> bool {
+        true
+    }
+
+    async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let value = match self.client.command(&[b"GET", self.key(key).as_bytes()]).await {
+            Ok(reply) => reply.into_bytes(),
+            Err(e) => {
+                warn!("Redis cache read failed: {}", e);
+                None
+            }
+        };
+        record_lookup(Layer::Memory, key, value.is_some());
+        value
+    }
+
+    async fn put(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) {
+        record_put(Layer::Memory, key, value.len());
+        let ttl = ttl.or_else(|| namespace_ttl(key)).unwrap_or(self.ttl);
+        let ttl = ttl.as_secs().max(1).to_string();
+        let key = self.key(key);
+        let args: [&[u8]; 5] = [b"SET", key.as_bytes(), &value, b"EX", ttl.as_bytes()];
+        if let Err(e) = self.client.command(&args).await {
+            warn!("Redis cache write failed: {}", e);
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        if let Err(e) = self.client.command(&[b"DEL", self.key(key).as_bytes()]).await {
+            warn!("Redis cache delete failed: {}", e);
+        }
+    }
+
+    async fn keys(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .scan(prefix, Some(limit))
+            .await
+            .into_iter()
+            .map(|key| String::from_utf8_lossy(&key[self.prefix.len().min(key.len())..]).into_owned())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    async fn remove_prefix(&self, prefix: &str) -> u64 {
+        let mut removed = 0;
+        for batch in self.scan(prefix, None).await.chunks(500) {
+            let mut args: Vec<&[u8]> = vec![b"DEL"];
+            args.extend(batch.iter().map(Vec::as_slice));
+            match self.client.command(&args).await {
+                Ok(Reply::Integer(n)) => removed += n.max(0) as u64,
+                Ok(_) => {}
+                Err(e) => warn!("Redis cache delete failed: {}", e),
+            }
+        }
+        removed
+    }
+
+    async fn clear(&self) -> u64 {
+        self.remove_prefix("").await
+    }
+
+    async fn usage(&self) -> Option<(u64, u64)> {
+        None
+    }
+}
+
+static BACKEND: OnceCell<Box<dyn CacheBackend>> = OnceCell::new();
+
+/// 按配置选择缓存后端并初始化各命名空间（启动时调用一次）；配置无效时回退到进程内缓存
+pub fn init_backend(config: &CacheConfig) {
+    let _ = SETTINGS.set(CacheSettings::new(config));
+    load_epoch();
+    for ns in &settings().namespaces {
+        if ns.ttl.is_some() || ns.max_bytes.is_some() {
+            info!(
+                "缓存命名空间 {}：过期时间 {:?}，容量上限 {:?} 字节",
+                ns.namespace.name(),
+                ns.ttl,
+                ns.max_bytes
+            );
+        }
+    }
+
+    let backend: Box<dyn CacheBackend> = match config.backend.as_str() {
+        "redis" => match RedisBackend::new(config) {
+            Ok(backend) => {
+                info!("缓存后端：Redis（键前缀 {}）", config.key_prefix);
+                Box::new(backend)
+            }
+            Err(e) => {
+                error!("Invalid Redis cache configuration, falling back to memory: {}", e);
+                Box::new(MemoryBackend)
+            }
+        },
+        "memory" => Box::new(MemoryBackend),
+        other => {
+            warn!("Unknown cache backend {}, using memory", other);
+            Box::new(MemoryBackend)
+        }
+    };
+    let _ = BACKEND.set(backend);
+}
+
+/// 派生缓存的格式版本：处理逻辑改变、已缓存的结果不再正确时递增
+pub const SCHEMA_VERSION: u32 = 1;
+/// 持久化纪元的文件（位于 CACHE_DIR/STATE_DIR 下）
+const EPOCH_FILE: &str = "epoch";
+
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// 派生缓存键中的版本标记（`v<版本>.<纪元>`）
+pub fn derived_tag() -> String {
+    format!("v{}.{}", SCHEMA_VERSION, epoch())
+}
+
+/// 当前的缓存纪元（通过管理接口递增，使所有派生缓存失效）
+pub fn epoch() -> u64 {
+    EPOCH.load(Ordering::Relaxed)
+}
+
+fn epoch_path() -> PathBuf {
+    PathBuf::from(CACHE_DIR).join(STATE_DIR).join(EPOCH_FILE)
+}
+
+/// 读取持久化的纪元（启动时调用）
+fn load_epoch() {
+    let Ok(content) = fs::read_to_string(epoch_path()) else {
+        return;
+    };
+    match content.trim().parse::<u64>() {
+        Ok(epoch) => {
+            EPOCH.store(epoch, Ordering::Relaxed);
+            info!("缓存纪元：{}（派生缓存版本 {}）", epoch, SCHEMA_VERSION);
+        }
+        Err(e) => warn!("Invalid cache epoch file {:?}: {}", epoch_path(), e),
+    }
+}
+
+/// 递增纪元并持久化，返回新纪元；旧纪元的派生缓存不再被读取，随过期和清理任务删除
+pub fn bump_epoch() -> std::io::Result<u64> {
+    let path = epoch_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let epoch = epoch() + 1;
+    write_atomic(&path, epoch.to_string().as_bytes())?;
+    EPOCH.store(epoch, Ordering::Relaxed);
+    info!("缓存纪元已更新为 {}", epoch);
+    Ok(epoch)
+}
+
+/// 当前的字节值缓存后端
+pub fn backend() -> &'static dyn CacheBackend {
+    BACKEND.get_or_init(|| Box::new(MemoryBackend)).as_ref()
+}
+
+/// 以 JSON 写入缓存后端；过期时间按键所属命名空间的配置（未配置时为后端默认值）
+pub async fn put_json<T: Serialize + ?Sized>(key: &str, value: &T) {
+    match serde_json::to_vec(value) {
+        Ok(bytes) => backend().put(key, bytes, None).await,
+        Err(e) => warn!("Failed to serialize cache value {}: {}", key, e),
+    }
+}
+
+/// 读取 JSON 缓存值；不存在或无法解析（如结构已变化）时为 None
+pub async fn get_json<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let bytes = backend().get(key).await?;
+    match serde_json::from_slice(&bytes) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            debug!("Ignoring undecodable cache value {}: {}", key, e);
+            None
+        }
+    }
+}
+
+// ==========================================
+// Stale-While-Revalidate
+// ==========================================
+
+/// swr_get 返回的缓存状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwrStatus {
+    /// 缓存在有效期内
+    Hit,
+    /// 缓存已过期，先返回旧值，后台重新获取
+    Stale,
+    /// 没有缓存，同步获取
+    Miss,
+}
+
+impl SwrStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Stale => "stale",
+            Self::Miss => "miss",
+        }
+    }
+}
+
+/// SWR 条目头部：获取时间（Unix 秒，u64 小端）
+const SWR_HEADER_LEN: usize = 8;
+
+// 各键的重新获取锁（只保存弱引用，没有进行中的获取时自动释放）
+static REVALIDATIONS: Lazy<Mutex<HashMap<String, Weak<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+fn revalidation_lock(key: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = REVALIDATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(lock) = locks.get(key).and_then(Weak::upgrade) {
+        return lock;
+    }
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(tokio::sync::Mutex::new(()));
+    locks.insert(key.to_string(), Arc::downgrade(&lock));
+    lock
+}
+
+/// 尝试开始对 `key` 的重新获取；同一个键已有获取在进行时返回 None
+///
+/// 持有返回的守卫直到获取结束，用于合并并发的后台更新
+pub fn try_revalidate(key: &str) -> Option<tokio::sync::OwnedMutexGuard<()>> {
+    revalidation_lock(key).try_lock_owned().ok()
+}
+
+fn encode_swr(value: &[u8], fetched_at: u64) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(SWR_HEADER_LEN + value.len());
+    entry.extend_from_slice(&fetched_at.to_le_bytes());
+    entry.extend_from_slice(value);
+    entry
+}
+
+/// 解析 SWR 条目，返回值和获取时间
+fn decode_swr(mut entry: Vec<u8>) -> Option<(Vec<u8>, u64)> {
+    let header: [u8; SWR_HEADER_LEN] = entry.get(..SWR_HEADER_LEN)?.try_into().ok()?;
+    entry.drain(..SWR_HEADER_LEN);
+    Some((entry, u64::from_le_bytes(header)))
+}
+
+async fn swr_lookup(key: &str, ttl: Duration) -> Option<(Vec<u8>, SwrStatus)> {
+    let (value, fetched_at) = decode_swr(backend().get(key).await?)?;
+    let status = if unix_secs().saturating_sub(fetched_at) < ttl.as_secs() {
+        SwrStatus::Hit
+    } else {
+        SwrStatus::Stale
+    };
+    Some((value, status))
+}
+
+async fn swr_store(key: &str, value: &[u8]) {
+    backend().put(key, encode_swr(value, unix_secs()), None).await;
+}
+
+/// 按 stale-while-revalidate 读取缓存后端中的值
+///
+/// - 获取时间在 `ttl` 内：直接返回
+/// - 已过期：返回旧值，并在后台调用 `revalidate` 更新（同一个键同时只有一个后台更新，失败时保留旧值）
+/// - 没有缓存：调用 `revalidate` 获取并写入；并发的请求等待同一次获取的结果
+///
+/// 旧值的保留时间由缓存后端（或所属命名空间）的过期时间决定
+pub async fn swr_get<F, Fut>(key: &str, ttl: Duration, revalidate: F) -> crate::Result<(Vec<u8>, SwrStatus)>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = crate::Result<Vec<u8>>> + Send + 'static,
+{
+    if let Some((value, status)) = swr_lookup(key, ttl).await {
+        if status == SwrStatus::Stale {
+            if let Some(guard) = try_revalidate(key) {
+                let key = key.to_string();
+                tokio::spawn(async move {
+                    let _guard = guard;
+                    match revalidate().await {
+                        Ok(value) => swr_store(&key, &value).await,
+                        Err(e) => warn!("Background revalidation of {} failed, keeping stale value: {}", key, e),
+                    }
+                });
+            }
+        }
+        return Ok((value, status));
+    }
+
+    let lock = revalidation_lock(key);
+    let _guard = lock.lock().await;
+    // 等待期间其他请求可能已经写入
+    if let Some(found) = swr_lookup(key, ttl).await {
+        return Ok(found);
+    }
+    let value = revalidate().await?;
+    swr_store(key, &value).await;
+    Ok((value, SwrStatus::Miss))
+}
+
 // ==========================================
 // Disk Cache Implementation
 // ==========================================
 
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 use std::time::SystemTime;
 use sha2::{Sha256, Digest};
+use crate::utils::compression::{self, AcceptEncoding};
 
 const CACHE_DIR: &str = "cache";
-const IMAGE_CACHE_TTL: u64 = 30; // 30 seconds
+
+/// 硬盘缓存的过期时间：命名空间配置的值，否则使用全局默认值
+fn disk_ttl(key: &str) -> Duration {
+    namespace_ttl(key).unwrap_or(settings().disk_ttl)
+}
+
+/// 硬盘缓存的 zstd 压缩设置
+#[derive(Debug, Clone, Copy)]
+struct DiskCompression {
+    min_bytes: u64,
+    level: i32,
+}
+
+/// 硬盘缓存文件头：魔数 + 标志 + 内容长度（u64 小端），读取时校验长度以发现写入不完整的文件
+///
+/// 没有文件头的文件按原样读取（加入文件头之前写入的文件）
+const ENTRY_MAGIC: &[u8] = b"\0space-cache\0";
+/// 文件头：魔数、标志位、内容长度（u64 小端）和校验和
+const ENTRY_HEADER_LEN: usize = ENTRY_MAGIC.len() + 1 + 8 + CHECKSUM_LEN;
+/// 标志位：内容经 zstd 压缩
+const FLAG_ZSTD: u8 = 1;
+/// 标志位：长度之后带有内容的校验和（之前写入的文件没有）
+const FLAG_CHECKSUM: u8 = 2;
+/// 校验和为存储内容 SHA-256 的前 8 字节
+const CHECKSUM_LEN: usize = 8;
+/// 加入文件头之前的压缩文件（只有魔数，没有长度）
+const LEGACY_COMPRESSED_MAGIC: &[u8] = b"\0space-zstd\0";
+
+/// 压缩硬盘缓存内容；内容过小或压缩后没有变小时返回 None
+fn compress_disk(value: &[u8], compression: DiskCompression) -> Option<Vec<u8>> {
+    if (value.len() as u64) < compression.min_bytes {
+        return None;
+    }
+    let compressed = zstd::bulk::compress(value, compression.level).ok()?;
+    (compressed.len() < value.len()).then_some(compressed)
+}
+
+/// 生成硬盘缓存文件内容（文件头 + 按配置压缩后的内容）
+fn encode_entry(value: &[u8], compression: Option<DiskCompression>) -> Vec<u8> {
+    let compressed = compression.and_then(|c| compress_disk(value, c));
+    let (flags, body) = match &compressed {
+        Some(compressed) => (FLAG_ZSTD | FLAG_CHECKSUM, compressed.as_slice()),
+        None => (FLAG_CHECKSUM, value),
+    };
+    let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN + body.len());
+    entry.extend_from_slice(ENTRY_MAGIC);
+    entry.push(flags);
+    entry.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    entry.extend_from_slice(&entry_checksum(body));
+    entry.extend_from_slice(body);
+    entry
+}
+
+fn entry_checksum(body: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let digest = Sha256::digest(body);
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&digest[..CHECKSUM_LEN]);
+    checksum
+}
+
+/// 还原硬盘缓存文件内容；长度与文件头不符（写入不完整）、校验和不符（内容损坏）或解压失败时返回错误
+fn decode_entry(data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+    // 解压失败说明内容已损坏，与校验失败同样按 InvalidData 处理
+    let decompress = |body: &[u8]| {
+        zstd::stream::decode_all(body).map_err(|e| invalid(&format!("cache entry decompression failed: {}", e)))
+    };
+    if let Some(rest) = data.strip_prefix(ENTRY_MAGIC) {
+        let (&flags, rest) = rest.split_first().ok_or_else(|| invalid("truncated cache header"))?;
+        let (len, rest) = rest.split_first_chunk::<8>().ok_or_else(|| invalid("truncated cache header"))?;
+        let (checksum, body) = if flags & FLAG_CHECKSUM != 0 {
+            let (checksum, body) = rest
+                .split_first_chunk::<CHECKSUM_LEN>()
+                .ok_or_else(|| invalid("truncated cache header"))?;
+            (Some(checksum), body)
+        } else {
+            (None, rest)
+        };
+        if u64::from_le_bytes(*len) != body.len() as u64 {
+            return Err(invalid("cache entry length mismatch"));
+        }
+        if checksum.is_some_and(|checksum| *checksum != entry_checksum(body)) {
+            return Err(invalid("cache entry checksum mismatch"));
+        }
+        return if flags & FLAG_ZSTD != 0 {
+            decompress(body)
+        } else {
+            Ok(body.to_vec())
+        };
+    }
+    match data.strip_prefix(LEGACY_COMPRESSED_MAGIC) {
+        Some(compressed) => decompress(compressed),
+        None => Ok(data),
+    }
+}
+
+/// 写入临时文件并同步到磁盘后再重命名为目标文件，避免进程或系统崩溃时留下不完整的缓存文件
+fn write_atomic(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.{}.tmp", std::process::id(), SEQ.fetch_add(1, Ordering::Relaxed)));
+    let tmp = PathBuf::from(tmp);
+
+    let written = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_data()?;
+        fs::rename(&tmp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
 
 fn get_cache_path(key: &str) -> PathBuf {
     let mut path = PathBuf::from(CACHE_DIR);
-    
+    // 命名空间的缓存放在独立的子目录中，按各自的过期时间和容量清理
+    if let Some(namespace) = Namespace::of(key) {
+        path.push(namespace.name());
+    }
+    hashed_path(path, key)
+}
+
+/// 转存的大值所在路径（cache/spill/ 下，按内存缓存中最长的过期时间清理）
+fn spill_path(key: &str) -> PathBuf {
+    hashed_path(PathBuf::from(CACHE_DIR).join(SPILL_DIR), key)
+}
+
+fn hashed_path(mut path: PathBuf, key: &str) -> PathBuf {
     // 使用SHA256哈希，更安全且避免特殊字符
     let mut hasher = Sha256::new();
     hasher.update(key.as_bytes());
@@ -100,18 +1139,35 @@ pub fn put_disk(key: &str, value: &[u8]) {
         }
     }
 
-    // 直接写入，不限制缓存次数
-    if let Err(e) = fs::write(&path, value) {
+    let stored = encode_entry(value, settings().disk_compression);
+
+    // 不限制缓存次数
+    if let Err(e) = write_atomic(&path, &stored) {
         error!("Failed to write cache file {:?}: {}", path, e);
     } else {
-        debug!("Cached to disk: {} bytes -> {:?}", value.len(), path);
+        record_put(Layer::Disk, key, stored.len());
+        debug!("Cached to disk: {} bytes ({} stored) -> {:?}", value.len(), stored.len(), path);
     }
 }
 
+/// 硬盘缓存中是否有未过期的条目（不读取内容，不计入命中统计）
+pub fn has_disk(key: &str) -> bool {
+    fs::metadata(get_cache_path(key))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age <= disk_ttl(key))
+}
+
 /// 从硬盘缓存读取数据
-/// 
-/// 内存优化：预分配精确大小的缓冲区，避免多次扩容
 pub fn get_disk(key: &str) -> Option<Vec<u8>> {
+    let data = read_disk(key);
+    record_lookup(Layer::Disk, key, data.is_some());
+    data
+}
+
+/// 内存优化：预分配精确大小的缓冲区，避免多次扩容
+fn read_disk(key: &str) -> Option<Vec<u8>> {
     let path = get_cache_path(key);
     
     if !path.exists() {
@@ -127,42 +1183,248 @@ pub fn get_disk(key: &str) -> Option<Vec<u8>> {
     // 检查过期
     if let Ok(modified) = metadata.modified() {
         if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
-            if elapsed.as_secs() > IMAGE_CACHE_TTL {
-                let _ = fs::remove_file(&path);
+            if elapsed > disk_ttl(key) {
+                if fs::remove_file(&path).is_ok() {
+                    record_evictions(Layer::Disk, group_index(key), 1);
+                }
                 debug!("Expired cache removed: {:?}", path);
                 return None;
             }
         }
     }
 
-    match fs::read(&path) {
+    match fs::read(&path).and_then(decode_entry) {
         Ok(data) => {
             debug!("Disk cache hit: {} bytes from {:?}", data.len(), path);
             Some(data)
         },
+        // 不完整或损坏（长度、校验和不符或无法解压）的文件不会再被读取成功，移入隔离目录
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            error!("Corrupt cache entry {:?}: {}", path, e);
+            quarantine(&path);
+            None
+        }
+        // 其他错误（文件被并发删除、权限、IO 错误等）不代表内容损坏，保留文件
+        Err(e) => {
+            warn!("Cache read failed {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// 写入转存文件，返回写入的字节数
+fn write_spill(key: &str, value: &[u8]) -> std::io::Result<usize> {
+    let path = spill_path(key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let stored = encode_entry(value, settings().disk_compression);
+    write_atomic(&path, &stored)?;
+    Ok(stored.len())
+}
+
+/// 读取转存文件（是否过期由内存中的占位标记决定），损坏的文件移入隔离目录
+fn read_spill(key: &str) -> Option<Vec<u8>> {
+    let path = spill_path(key);
+    match fs::read(&path).and_then(decode_entry) {
+        Ok(data) => Some(data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        // 与硬盘缓存相同：只隔离内容损坏的文件，其他错误按未命中处理并保留文件
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            error!("Corrupt spilled cache entry {:?}: {}", path, e);
+            quarantine(&path);
+            None
+        }
         Err(e) => {
-            error!("Cache read failed {:?}: {}", path, e);
+            warn!("Spilled cache read failed {:?}: {}", path, e);
             None
         }
     }
 }
 
-/// 不由通用清理任务管理的目录（有独立缓存策略）
-const CACHE_EXCLUDED_DIRS: &[&str] = &["friend_avatars"];
+static CORRUPT_ENTRIES: AtomicU64 = AtomicU64::new(0);
 
-// 清理过期的缓存文件（统计在清理过程中直接收集，避免额外的目录扫描）
+/// 将损坏的缓存文件移入隔离目录（保留以便排查，按 QUARANTINE_TTL 清理），移动失败时直接删除
+fn quarantine(path: &std::path::Path) {
+    CORRUPT_ENTRIES.fetch_add(1, Ordering::Relaxed);
+    let dir = PathBuf::from(CACHE_DIR).join(QUARANTINE_DIR);
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("entry");
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let target = dir.join(format!("{}.{}", name, now));
+    let moved = fs::create_dir_all(&dir).and_then(|_| fs::rename(path, &target));
+    match moved {
+        Ok(_) => warn!("Corrupt cache entry quarantined: {:?} -> {:?}", path, target),
+        Err(e) => {
+            warn!("Failed to quarantine corrupt cache entry {:?}: {}", path, e);
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// 进程启动以来发现的损坏缓存文件数
+pub fn corrupt_entries() -> u64 {
+    CORRUPT_ENTRIES.load(Ordering::Relaxed)
+}
+
+/// 预压缩变体的缓存键
+fn variant_key(key: &str, encoding: &str) -> String {
+    format!("{}@{}", key, encoding)
+}
+
+/// 写入缓存后端，同时在缓存后端和硬盘缓存中保存预压缩变体（brotli、gzip），供 `get_variant` 按 Accept-Encoding 直接返回
+///
+/// 变体与原始内容一样经过 `backend()`，使用 Redis 时在实例间共享并随原始内容一起失效
+pub async fn put_with_variants(key: String, value: Vec<u8>) {
+    let variants: Vec<(String, Vec<u8>)> = {
+        let value = value.clone();
+        let key = key.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut variants = Vec::new();
+            for encoding in compression::PRECOMPRESSED_ENCODINGS {
+                if let Some(compressed) = compression::compress(encoding, &value) {
+                    let variant = variant_key(&key, encoding);
+                    put_disk(&variant, &compressed);
+                    variants.push((variant, compressed));
+                }
+            }
+            put_disk(&key, &value);
+            variants
+        })
+        .await
+        .unwrap_or_default()
+    };
+    let backend = backend();
+    for (variant, compressed) in variants {
+        backend.put(&variant, compressed, None).await;
+    }
+    backend.put(&key, value, None).await;
+}
+
+/// 删除缓存项及其预压缩变体（缓存后端和硬盘），返回删除的条目数
+pub async fn remove_with_variants(key: &str) -> u64 {
+    let backend = backend();
+    let mut keys = vec![key.to_string()];
+    keys.extend(compression::PRECOMPRESSED_ENCODINGS.iter().map(|e| variant_key(key, e)));
+    let mut removed = 0;
+    for key in &keys {
+        if backend.get(key).await.is_some() {
+            backend.remove(key).await;
+            removed += 1;
+        }
+        if fs::remove_file(get_cache_path(key)).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// 按键删除所有缓存层（缓存后端、进程内缓存、硬盘缓存及预压缩变体），返回删除的条目数
+pub async fn invalidate(key: &str) -> u64 {
+    let mut removed = remove_with_variants(key).await;
+    // 共享后端之外，进程内缓存中可能还有直接写入的副本
+    if backend().shared() && bucket(key).remove(key).await.is_some() {
+        removed += 1;
+    }
+    removed
+}
+
+/// 按客户端接受的编码读取缓存（先缓存后端后硬盘），返回 (内容, 内容编码)；没有可用的压缩变体时返回原始内容
+pub async fn get_variant(key: &str, accept: &AcceptEncoding) -> Option<(Vec<u8>, Option<&'static str>)> {
+    let backend = backend();
+    // 内容较小或压缩收益不足时没有该编码的变体，依次尝试客户端接受的下一个编码
+    for encoding in accept.acceptable() {
+        let variant = variant_key(key, encoding);
+        if let Some(data) = backend.get(&variant).await {
+            return Some((data, Some(encoding)));
+        }
+        if let Some(data) = get_disk(&variant) {
+            backend.put(&variant, data.clone(), None).await;
+            return Some((data, Some(encoding)));
+        }
+    }
+    if let Some(data) = backend.get(key).await {
+        return Some((data, None));
+    }
+    let data = get_disk(key)?;
+    backend.put(key, data.clone(), None).await;
+    Some((data, None))
+}
+
+/// 硬盘缓存使用的目录（根目录、各命名空间及独立管理的子目录），用于启动报告
+pub fn disk_dirs() -> Vec<String> {
+    let root = PathBuf::from(CACHE_DIR);
+    let mut dirs = vec![root.clone()];
+    dirs.extend(Namespace::ALL.iter().map(|ns| root.join(ns.name())));
+    dirs.extend([SPILL_DIR, QUARANTINE_DIR].iter().chain(CACHE_EXCLUDED_DIRS).map(|d| root.join(d)));
+    dirs.into_iter().map(|d| d.display().to_string()).collect()
+}
+
+/// 不由通用清理任务管理的目录（有独立缓存策略，或保存缓存自身的状态）
+const CACHE_EXCLUDED_DIRS: &[&str] = &["friend_avatars", STATE_DIR];
+/// 缓存状态（纪元）的目录（位于 CACHE_DIR 下）
+const STATE_DIR: &str = "state";
+/// 转存大值的目录（位于 CACHE_DIR 下）
+const SPILL_DIR: &str = "spill";
+/// 损坏的缓存文件移入的目录
+const QUARANTINE_DIR: &str = "quarantine";
+/// 隔离文件的保留时间
+const QUARANTINE_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 最近一次硬盘缓存清理的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiskCleanupReport {
+    pub finished_at: String,
+    /// 过期删除的文件
+    pub expired_files: u64,
+    pub expired_bytes: u64,
+    /// 超出容量或文件数限制被删除的文件（按修改时间从旧到新）
+    pub evicted_files: u64,
+    pub evicted_bytes: u64,
+    /// 清理后剩余（不含有独立缓存策略的目录）
+    pub remaining_files: u64,
+    pub remaining_bytes: u64,
+}
+
+static LAST_CLEANUP: Lazy<Mutex<Option<DiskCleanupReport>>> = Lazy::new(|| Mutex::new(None));
+
+/// 最近一次硬盘缓存清理的结果（尚未清理过时为空）
+pub fn last_cleanup() -> Option<DiskCleanupReport> {
+    LAST_CLEANUP.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 硬盘缓存的总容量和文件数上限（未配置时为空）
+pub fn disk_limits() -> DiskLimits {
+    settings().disk_limits
+}
+
+// 清理过期的缓存文件，再按容量和文件数限制淘汰最旧的文件（统计在清理过程中直接收集，避免额外的目录扫描）
 pub fn cleanup_expired_cache() {
     use std::fs;
     use std::path::Path;
 
+    #[derive(Default)]
     struct CleanupStats {
-        removed_count: usize,
+        removed_count: u64,
         removed_size: u64,
-        remaining_count: usize,
+        evicted_count: u64,
+        evicted_size: u64,
+        remaining_count: u64,
         remaining_size: u64,
     }
 
-    fn cleanup_dir(dir: &Path, stats: &mut CleanupStats) -> std::io::Result<()> {
+    impl CleanupStats {
+        fn evicted(&mut self, (count, size): (u64, u64)) {
+            self.evicted_count += count;
+            self.evicted_size += size;
+            self.remaining_count = self.remaining_count.saturating_sub(count);
+            self.remaining_size = self.remaining_size.saturating_sub(size);
+        }
+    }
+
+    fn cleanup_dir(dir: &Path, ttl: Duration, skip: &[&str], stats: &mut CleanupStats) -> std::io::Result<()> {
         if !dir.exists() {
             return Ok(());
         }
@@ -174,12 +1436,12 @@ pub fn cleanup_expired_cache() {
             if path.is_dir() {
                 // 跳过有独立缓存策略的目录
                 if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if CACHE_EXCLUDED_DIRS.contains(&name) {
+                    if skip.contains(&name) {
                         debug!("Skipping excluded cache dir: {:?}", path);
                         continue;
                     }
                 }
-                cleanup_dir(&path, stats)?;
+                cleanup_dir(&path, ttl, skip, stats)?;
                 // 尝试删除空目录
                 let _ = fs::remove_dir(&path);
             } else if path.is_file() {
@@ -188,7 +1450,7 @@ pub fn cleanup_expired_cache() {
                     let mut expired = false;
                     if let Ok(modified) = metadata.modified() {
                         if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
-                            if elapsed.as_secs() > IMAGE_CACHE_TTL {
+                            if elapsed > ttl {
                                 expired = true;
                             }
                         }
@@ -209,22 +1471,432 @@ pub fn cleanup_expired_cache() {
     }
 
     let cache_dir = Path::new(CACHE_DIR);
-    let mut stats = CleanupStats {
-        removed_count: 0,
-        removed_size: 0,
-        remaining_count: 0,
-        remaining_size: 0,
-    };
+    let mut stats = CleanupStats::default();
+
+    // 命名空间目录按各自的过期时间清理，超出容量时再删除最旧的文件
+    let settings = settings();
+    let mut skip: Vec<&str> = CACHE_EXCLUDED_DIRS.to_vec();
+    skip.push(SPILL_DIR);
+    skip.push(QUARANTINE_DIR);
+    skip.extend(Namespace::ALL.iter().map(|ns| ns.name()));
+    let mut result = cleanup_dir(cache_dir, settings.disk_ttl, &skip, &mut stats);
+    // 转存文件随内存中的占位标记删除，这里只清理残留的文件（如进程重启前写入的）
+    result = result.and(cleanup_dir(&cache_dir.join(SPILL_DIR), settings.spill_ttl, &[], &mut stats));
+    result = result.and(cleanup_dir(&cache_dir.join(QUARANTINE_DIR), QUARANTINE_TTL, &[], &mut stats));
+    record_evictions(Layer::Disk, 0, stats.removed_count);
+    for (i, ns) in settings.namespaces.iter().enumerate() {
+        let dir = cache_dir.join(ns.namespace.name());
+        let ttl = ns.ttl.unwrap_or(settings.disk_ttl);
+        let before = stats.removed_count + stats.evicted_count;
+        result = result.and(cleanup_dir(&dir, ttl, &[], &mut stats));
+        if ns.max_bytes.is_some() {
+            let limits = DiskLimits {
+                max_bytes: ns.max_bytes,
+                max_files: None,
+            };
+            stats.evicted(evict_oldest(&dir, &[], limits));
+        }
+        record_evictions(Layer::Disk, i + 1, stats.removed_count + stats.evicted_count - before);
+    }
+    // 整个硬盘缓存（含各命名空间）的总量限制（按文件名无法区分命名空间，计入 default）
+    if settings.disk_limits.is_set() {
+        let evicted = evict_oldest(cache_dir, CACHE_EXCLUDED_DIRS, settings.disk_limits);
+        record_evictions(Layer::Disk, 0, evicted.0);
+        stats.evicted(evicted);
+    }
 
-    if let Err(e) = cleanup_dir(cache_dir, &mut stats) {
+    if let Err(e) = result {
         error!("Failed to cleanup cache directory: {}", e);
     } else {
-        if stats.removed_count > 0 {
-            info!("Cache cleanup completed: removed {} files, freed {} bytes",
-                    stats.removed_count, stats.removed_size);
+        if stats.removed_count > 0 || stats.evicted_count > 0 {
+            info!("Cache cleanup completed: removed {} expired files ({} bytes), evicted {} files ({} bytes)",
+                    stats.removed_count, stats.removed_size, stats.evicted_count, stats.evicted_size);
         }
 
         debug!("Cache stats: {} files, {} bytes total",
                 stats.remaining_count, stats.remaining_size);
     }
-}
\ No newline at end of file
+
+    *LAST_CLEANUP.lock().unwrap_or_else(|e| e.into_inner()) = Some(DiskCleanupReport {
+        finished_at: chrono::Utc::now().to_rfc3339(),
+        expired_files: stats.removed_count,
+        expired_bytes: stats.removed_size,
+        evicted_files: stats.evicted_count,
+        evicted_bytes: stats.evicted_size,
+        remaining_files: stats.remaining_count,
+        remaining_bytes: stats.remaining_size,
+    });
+}
+
+/// 硬盘缓存的容量限制
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DiskLimits {
+    pub max_bytes: Option<u64>,
+    pub max_files: Option<u64>,
+}
+
+impl DiskLimits {
+    fn is_set(&self) -> bool {
+        self.max_bytes.is_some() || self.max_files.is_some()
+    }
+
+    fn exceeded(&self, files: u64, bytes: u64) -> bool {
+        self.max_bytes.is_some_and(|max| bytes > max) || self.max_files.is_some_and(|max| files > max)
+    }
+}
+
+/// 按修改时间从旧到新删除目录中的文件（跳过 `skip` 中的子目录），直到总大小和文件数都不超过限制，
+/// 返回 (删除的文件数, 释放的字节数)
+fn evict_oldest(dir: &std::path::Path, skip: &[&str], limits: DiskLimits) -> (u64, u64) {
+    fn collect(dir: &std::path::Path, skip: &[&str], files: &mut Vec<(SystemTime, u64, PathBuf)>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if !entry.file_name().to_str().is_some_and(|name| skip.contains(&name)) {
+                    collect(&entry.path(), skip, files);
+                }
+            } else if metadata.is_file() {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((modified, metadata.len(), entry.path()));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    collect(dir, skip, &mut files);
+    files.sort();
+    let mut total_files = files.len() as u64;
+    let mut total_bytes: u64 = files.iter().map(|(_, size, _)| size).sum();
+    let (mut count, mut freed) = (0, 0);
+    for (_, size, path) in files {
+        if !limits.exceeded(total_files, total_bytes) {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            count += 1;
+            freed += size;
+            total_files -= 1;
+            total_bytes -= size;
+        }
+    }
+    if count > 0 {
+        debug!("Evicted {} files ({} bytes) from {:?} over limit", count, freed, dir);
+    }
+    (count, freed)
+}
+
+fn disk_usage_of(dir: &std::path::Path, files: &mut u64, bytes: &mut u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            disk_usage_of(&entry.path(), files, bytes);
+        } else if metadata.is_file() {
+            *files += 1;
+            *bytes += metadata.len();
+        }
+    }
+}
+
+/// 硬盘缓存占用（文件数，字节数），包括有独立缓存策略的目录
+pub fn disk_usage() -> (u64, u64) {
+    let (mut files, mut bytes) = (0, 0);
+    disk_usage_of(std::path::Path::new(CACHE_DIR), &mut files, &mut bytes);
+    (files, bytes)
+}
+
+/// 清空通用硬盘缓存（跳过有独立缓存策略的目录），返回删除的文件数
+pub fn clear_disk() -> u64 {
+    let mut removed = 0;
+    let Ok(entries) = fs::read_dir(CACHE_DIR) else {
+        return 0;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let excluded = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| CACHE_EXCLUDED_DIRS.contains(&n));
+        if excluded {
+            continue;
+        }
+        if path.is_dir() {
+            let (mut files, mut bytes) = (0, 0);
+            disk_usage_of(&path, &mut files, &mut bytes);
+            if fs::remove_dir_all(&path).is_ok() {
+                removed += files;
+            }
+        } else if fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    info!("Disk cache cleared: removed {} files", removed);
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_routing() {
+        assert_eq!(Namespace::of("wallpaper:https://cdn/1.jpg:webp"), Some(Namespace::Wallpapers));
+        assert_eq!(Namespace::of("sw_js:abc@gzip"), Some(Namespace::ServiceWorker));
+        assert_eq!(Namespace::of("badge:views"), None);
+        assert!(get_cache_path("avatar:https://q1.qlogo.cn/x").starts_with("cache/avatars"));
+        assert!(!get_cache_path("og:title").starts_with("cache/avatars"));
+
+        // 派生缓存键带版本标记，仍属于同一命名空间
+        let derived = Namespace::Avatars.derived_key("qq:128:webp");
+        assert_eq!(derived, format!("avatar:v{}.{}:qq:128:webp", SCHEMA_VERSION, epoch()));
+        assert_eq!(Namespace::of(&derived), Some(Namespace::Avatars));
+
+        let mut config = CacheConfig::default();
+        config.avatars.ttl_secs = Some(600);
+        config.avatars.max_size_mb = Some(4);
+        let settings = CacheSettings::new(&config);
+        let avatars = settings
+            .namespaces
+            .iter()
+            .find(|s| s.namespace == Namespace::Avatars)
+            .unwrap();
+        assert_eq!(avatars.ttl, Some(Duration::from_secs(600)));
+        assert_eq!(avatars.max_bytes, Some(4 * 1024 * 1024));
+        assert_eq!(avatars.bucket.policy().max_capacity(), Some(4 * 1024 * 1024));
+        assert_eq!(avatars.bucket.policy().time_to_live(), Some(Duration::from_secs(600)));
+        // 未配置的命名空间按比例分配总容量，使用全局过期时间
+        let snippets = settings
+            .namespaces
+            .iter()
+            .find(|s| s.namespace == Namespace::Snippets)
+            .unwrap();
+        assert_eq!(snippets.bucket.policy().max_capacity(), Some(MEMORY_BUDGET_BYTES * 8 / 100));
+        assert_eq!(snippets.bucket.policy().time_to_live(), Some(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(settings.default_bucket.policy().time_to_idle(), Some(DEFAULT_BUCKET_IDLE));
+        let shares: u64 = Namespace::ALL.iter().map(|ns| ns.memory_share()).sum();
+        assert_eq!(shares + DEFAULT_BUCKET_SHARE, 100);
+        let defaults = CacheSettings::new(&CacheConfig::default());
+        let total: u64 = defaults
+            .namespaces
+            .iter()
+            .filter_map(|ns| ns.bucket.policy().max_capacity())
+            .sum();
+        assert!(total + defaults.default_bucket.policy().max_capacity().unwrap() <= MEMORY_BUDGET_BYTES);
+        assert_eq!(settings.disk_ttl, Duration::from_secs(30));
+
+        // 每个命名空间一个进程内缓存，顺序与计数一致
+        let buckets: Vec<&str> = bucket_usage().iter().map(|b| b.name).collect();
+        let stats: Vec<&str> = stats().iter().map(|s| s.name).collect();
+        assert_eq!(buckets, stats);
+    }
+
+    #[test]
+    fn test_disk_entry() {
+        let compression = DiskCompression {
+            min_bytes: 1024,
+            level: 3,
+        };
+        let value = "wallpaper ".repeat(1000).into_bytes();
+        let stored = encode_entry(&value, Some(compression));
+        assert!(stored.starts_with(ENTRY_MAGIC));
+        assert_eq!(stored[ENTRY_MAGIC.len()], FLAG_ZSTD | FLAG_CHECKSUM);
+        assert!(stored.len() < value.len());
+        assert_eq!(decode_entry(stored.clone()).unwrap(), value);
+
+        // 过小或无法压缩的内容按原样存储
+        assert!(compress_disk(&value[..512], compression).is_none());
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let raw = encode_entry(&noise, Some(compression));
+        assert_eq!(raw[ENTRY_MAGIC.len()], FLAG_CHECKSUM);
+        assert_eq!(decode_entry(raw).unwrap(), noise);
+
+        // 写入不完整的文件读取失败（InvalidData，读取时移入隔离目录）
+        let kind = |data: &[u8]| decode_entry(data.to_vec()).unwrap_err().kind();
+        assert_eq!(kind(&stored[..stored.len() - 1]), std::io::ErrorKind::InvalidData);
+        assert_eq!(kind(&stored[..ENTRY_MAGIC.len() + 3]), std::io::ErrorKind::InvalidData);
+
+        // 长度正确但内容损坏的文件校验失败
+        let mut flipped = encode_entry(&noise, None);
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        assert_eq!(kind(&flipped), std::io::ErrorKind::InvalidData);
+        let legacy = [LEGACY_COMPRESSED_MAGIC, b"not zstd"].concat();
+        assert_eq!(kind(&legacy), std::io::ErrorKind::InvalidData);
+
+        // 没有校验和的文件头（加入校验和之前写入）仍可读取
+        let unchecked = [ENTRY_MAGIC, &[0], &(noise.len() as u64).to_le_bytes(), &noise].concat();
+        assert_eq!(decode_entry(unchecked).unwrap(), noise);
+
+        // 加入文件头之前写入的文件仍可读取
+        assert_eq!(decode_entry(b"\x89PNG raw".to_vec()).unwrap(), b"\x89PNG raw");
+        let legacy = [LEGACY_COMPRESSED_MAGIC, &zstd::bulk::compress(&value, 3).unwrap()].concat();
+        assert_eq!(decode_entry(legacy).unwrap(), value);
+        assert!(decode_entry([LEGACY_COMPRESSED_MAGIC, b"garbage"].concat()).is_err());
+
+        // 原子写入：覆盖已有文件，不留下临时文件
+        let dir = std::env::temp_dir().join(format!("space-api-atomic-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("entry");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, &stored).unwrap();
+        assert_eq!(decode_entry(fs::read(&path).unwrap()).unwrap(), value);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_evict_oldest() {
+        let dir = std::env::temp_dir().join(format!("space-api-evict-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("ab")).unwrap();
+        fs::create_dir_all(dir.join("friend_avatars")).unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["ab/old", "ab/mid", "new", "friend_avatars/kept"].iter().enumerate() {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - Duration::from_secs(300 - i as u64 * 100)).unwrap();
+        }
+
+        let limits = DiskLimits {
+            max_bytes: Some(250),
+            max_files: None,
+        };
+        assert_eq!(evict_oldest(&dir, CACHE_EXCLUDED_DIRS, limits), (1, 100));
+        assert!(!dir.join("ab/old").exists());
+
+        let limits = DiskLimits {
+            max_bytes: None,
+            max_files: Some(1),
+        };
+        assert_eq!(evict_oldest(&dir, CACHE_EXCLUDED_DIRS, limits), (1, 100));
+        assert!(dir.join("new").exists());
+        assert!(dir.join("friend_avatars/kept").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_spill() {
+        let backend = MemoryBackend;
+        let key = "snippet:spill-test";
+        let value = b"spilled ".repeat(MAX_MEMORY_ENTRY_BYTES / 4);
+
+        // 超过内存上限的值转存到硬盘，内存中只有占位标记
+        backend.put(key, value.clone(), None).await;
+        assert_eq!(bucket(key).get(key).await.as_deref(), Some(SPILL_MARKER));
+        assert!(spill_path(key).exists());
+        assert_eq!(backend.get(key).await, Some(value.clone()));
+        assert!(backend.keys("snippet:spill-", 10).await.contains(&key.to_string()));
+
+        // 删除时一并删除文件
+        backend.remove(key).await;
+        bucket(key).run_pending_tasks().await;
+        assert!(!spill_path(key).exists());
+        assert_eq!(backend.get(key).await, None);
+
+        // 文件丢失时按未命中处理并移除占位标记
+        backend.put(key, value, None).await;
+        fs::remove_file(spill_path(key)).unwrap();
+        assert_eq!(backend.get(key).await, None);
+        assert_eq!(bucket(key).get(key).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_json_values() {
+        #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+        struct Status {
+            song_id: i64,
+        }
+
+        let key = Namespace::NcmStatus.key(42);
+        assert_eq!(key, "ncm_status:42");
+        assert_eq!(Namespace::of(&key), Some(Namespace::NcmStatus));
+
+        put_json(&key, &Status { song_id: 7 }).await;
+        assert_eq!(get_json::<Status>(&key).await, Some(Status { song_id: 7 }));
+        // 结构不匹配时按未命中处理
+        assert_eq!(get_json::<Vec<String>>(&key).await, None);
+        assert_eq!(get_json::<Status>("ncm_status:missing").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_swr_get() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let fetch = |value: &'static [u8]| {
+            let calls = calls.clone();
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(value.to_vec())
+            }
+        };
+
+        // 并发的未命中只获取一次
+        let key = "swr-test:miss";
+        let (a, b) = tokio::join!(
+            swr_get(key, Duration::from_secs(60), fetch(b"v1")),
+            swr_get(key, Duration::from_secs(60), fetch(b"v1")),
+        );
+        let mut statuses = [a.unwrap().1, b.unwrap().1];
+        statuses.sort_by_key(|s| s.as_str());
+        assert_eq!(statuses, [SwrStatus::Hit, SwrStatus::Miss]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 过期后返回旧值，后台只更新一次
+        let key = "swr-test:stale";
+        backend().put(key, encode_swr(b"old", unix_secs() - 120), None).await;
+        let (value, status) = swr_get(key, Duration::from_secs(60), fetch(b"new")).await.unwrap();
+        assert_eq!((value.as_slice(), status), (&b"old"[..], SwrStatus::Stale));
+        let (_, status) = swr_get(key, Duration::from_secs(60), fetch(b"new")).await.unwrap();
+        assert_eq!(status, SwrStatus::Stale);
+        let _guard = revalidation_lock(key).lock_owned().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let (value, status) = swr_lookup(key, Duration::from_secs(60)).await.unwrap();
+        assert_eq!((value.as_slice(), status), (&b"new"[..], SwrStatus::Hit));
+
+        // 更新失败时保留旧值
+        let key = "swr-test:error";
+        backend().put(key, encode_swr(b"old", 0), None).await;
+        let failing = || async { Err(crate::Error::Internal("upstream down".into())) };
+        let (value, _) = swr_get(key, Duration::from_secs(60), failing).await.unwrap();
+        assert_eq!(value, b"old");
+        assert!(swr_get("swr-test:missing", Duration::from_secs(60), failing).await.is_err());
+        assert!(decode_swr(b"short".to_vec()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let ncm = |stats: Vec<NamespaceStats>| stats.into_iter().find(|ns| ns.name == "ncm_status").unwrap();
+        let before = ncm(stats());
+
+        let backend = MemoryBackend;
+        backend.put("ncm_status:test-stats", b"{}".to_vec(), None).await;
+        assert!(backend.get("ncm_status:test-stats").await.is_some());
+        assert!(backend.get("ncm_status:test-stats-missing").await.is_none());
+
+        let after = ncm(stats());
+        assert!(after.memory.puts > before.memory.puts);
+        assert!(after.memory.bytes_written >= before.memory.bytes_written + 2);
+        assert!(after.memory.hits > before.memory.hits);
+        assert!(after.memory.misses > before.memory.misses);
+        assert!(after.memory.hit_rate.is_some());
+        assert!(hit_rate().is_some());
+    }
+}
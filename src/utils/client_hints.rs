@@ -0,0 +1,141 @@
+use rocket::http::HeaderMap;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::convert::Infallible;
+
+/// 响应中声明希望浏览器在后续请求中携带的客户端提示
+pub const ACCEPT_CH: &str = "Sec-CH-DPR, Sec-CH-Width, Sec-CH-Viewport-Width";
+/// 按客户端提示选择尺寸的响应需要按这些请求头区分缓存（含旧版不带 Sec-CH- 前缀的请求头）
+pub const VARY: &str = "Sec-CH-DPR, Sec-CH-Width, Sec-CH-Viewport-Width, DPR, Width, Viewport-Width";
+
+/// 壁纸可选的宽度（像素），超过最大值时返回原图
+pub const WALLPAPER_WIDTHS: &[u32] = &[640, 1280, 1920, 2560];
+/// 头像可选的边长（像素），超过最大值时返回原图
+pub const AVATAR_SIZES: &[u32] = &[64, 128, 256, 512];
+/// 图片代理可选的宽度（像素），超过最大值时返回原图
+pub const PROXY_WIDTHS: &[u32] = &[64, 128, 256, 512, 640, 1280, 1920, 2560];
+
+const MAX_DPR: f32 = 8.0;
+const MAX_WIDTH: u32 = 10_000;
+
+/// 请求携带的客户端提示（Sec-CH-DPR / Sec-CH-Width / Sec-CH-Viewport-Width，兼容旧版请求头）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClientHints {
+    /// 设备像素比
+    pub dpr: Option<f32>,
+    /// 图片的显示宽度（物理像素）
+    pub width: Option<u32>,
+    /// 视口宽度（CSS 像素）
+    pub viewport_width: Option<u32>,
+}
+
+impl ClientHints {
+    pub fn from_headers(headers: &HeaderMap<'_>) -> Self {
+        let get = |name: &str| {
+            headers
+                .get_one(&format!("Sec-CH-{}", name))
+                .or_else(|| headers.get_one(name))
+                .map(str::trim)
+        };
+        let width = |name: &str| get(name).and_then(|v| v.parse::<u32>().ok()).filter(|w| (1..=MAX_WIDTH).contains(w));
+        Self {
+            dpr: get("DPR")
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|dpr| *dpr > 0.0 && *dpr <= MAX_DPR),
+            width: width("Width"),
+            viewport_width: width("Viewport-Width"),
+        }
+    }
+
+    /// 需要的图片宽度（物理像素）：优先使用 Width 提示，其次为视口宽度，都没有时使用查询参数
+    ///
+    /// 视口宽度和查询参数为 CSS 像素，按 DPR（提示优先，其次为查询参数）换算
+    pub fn requested_width(&self, query_width: Option<u32>, query_dpr: Option<f32>) -> Option<u32> {
+        if let Some(width) = self.width {
+            return Some(width);
+        }
+        let dpr = self
+            .dpr
+            .or(query_dpr.filter(|dpr| *dpr > 0.0 && *dpr <= MAX_DPR))
+            .unwrap_or(1.0);
+        let css_width = self.viewport_width.or(query_width.filter(|w| (1..=MAX_WIDTH).contains(w)))?;
+        Some((css_width as f32 * dpr).ceil() as u32)
+    }
+}
+
+/// 将查询参数中的 CSS 像素按 DPR 换算为物理像素（无效值忽略）
+pub fn physical_pixels(css: Option<u32>, dpr: Option<f32>) -> Option<u32> {
+    let css = css.filter(|v| (1..=MAX_WIDTH).contains(v))?;
+    let dpr = dpr.filter(|dpr| *dpr > 0.0 && *dpr <= MAX_DPR).unwrap_or(1.0);
+    Some((css as f32 * dpr).ceil() as u32)
+}
+
+/// 精确尺寸（壁纸的 w × h）的取整步长（像素），任意参数组合按桶共享缓存
+pub const DIMENSION_STEP: u32 = 64;
+/// 编码质量的取整步长
+pub const QUALITY_STEP: u8 = 10;
+
+/// 向上取整到 step 的倍数（不小于 step）
+pub fn snap_up(value: u32, step: u32) -> u32 {
+    value.max(1).div_ceil(step).saturating_mul(step)
+}
+
+/// 选择不小于需要宽度的最小尺寸；超过最大尺寸或未指定时为 None（使用原图）
+pub fn pick_size(requested: Option<u32>, sizes: &[u32]) -> Option<u32> {
+    let requested = requested?;
+    sizes.iter().copied().find(|&size| size >= requested)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientHints {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientHints::from_headers(req.headers()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+
+    fn hints(headers: &[(&'static str, &'static str)]) -> ClientHints {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.add(Header::new(*name, *value));
+        }
+        ClientHints::from_headers(&map)
+    }
+
+    #[test]
+    fn test_client_hints() {
+        // Width 已是物理像素
+        let h = hints(&[("Sec-CH-Width", "700"), ("Sec-CH-DPR", "2")]);
+        assert_eq!(h.requested_width(Some(100), None), Some(700));
+        assert_eq!(pick_size(h.requested_width(None, None), WALLPAPER_WIDTHS), Some(1280));
+
+        // 视口宽度按 DPR 换算，兼容旧版请求头
+        let h = hints(&[("Viewport-Width", "1000"), ("DPR", "1.5")]);
+        assert_eq!(h.requested_width(None, None), Some(1500));
+        assert_eq!(pick_size(Some(1500), WALLPAPER_WIDTHS), Some(1920));
+
+        // 没有提示时使用查询参数
+        let h = hints(&[]);
+        assert_eq!(h, ClientHints::default());
+        assert_eq!(h.requested_width(Some(100), Some(2.0)), Some(200));
+        assert_eq!(pick_size(h.requested_width(Some(100), Some(2.0)), AVATAR_SIZES), Some(256));
+        assert_eq!(h.requested_width(None, Some(2.0)), None);
+        assert_eq!(physical_pixels(Some(720), Some(1.5)), Some(1080));
+        assert_eq!(physical_pixels(Some(720), Some(-1.0)), Some(720));
+        assert_eq!(physical_pixels(Some(0), None), None);
+        assert_eq!(snap_up(1, DIMENSION_STEP), 64);
+        assert_eq!(snap_up(1080, DIMENSION_STEP), 1088);
+        assert_eq!(snap_up(1088, DIMENSION_STEP), 1088);
+        assert_eq!(snap_up(73, QUALITY_STEP as u32), 80);
+
+        // 无效值忽略，超过最大尺寸时使用原图
+        let h = hints(&[("Sec-CH-DPR", "-1"), ("Sec-CH-Width", "abc")]);
+        assert_eq!(h, ClientHints::default());
+        assert_eq!(pick_size(Some(4000), WALLPAPER_WIDTHS), None);
+    }
+}
@@ -0,0 +1,120 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::io::Write;
+
+/// 小于该大小的内容不做预压缩（压缩收益抵不过额外的缓存项）
+pub const MIN_COMPRESS_BYTES: usize = 1024;
+
+/// 预压缩使用的编码（按优先级排列）
+pub const PRECOMPRESSED_ENCODINGS: &[&str] = &["br", "gzip"];
+/// brotli 压缩等级（0-11）和窗口大小（log2）：预压缩只做一次，使用最高等级
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_LGWIN: u32 = 22;
+
+/// 客户端接受的内容编码（来自 Accept-Encoding，q=0 视为不接受）
+#[derive(Debug, Clone, Default)]
+pub struct AcceptEncoding(Vec<String>);
+
+impl AcceptEncoding {
+    pub fn parse(header: &str) -> Self {
+        let accepted = header
+            .split(',')
+            .filter_map(|part| {
+                let mut params = part.split(';');
+                let coding = params.next()?.trim().to_ascii_lowercase();
+                let rejected = params.any(|p| {
+                    p.trim()
+                        .strip_prefix("q=")
+                        .and_then(|q| q.trim().parse::<f32>().ok())
+                        .is_some_and(|q| q <= 0.0)
+                });
+                (!coding.is_empty() && !rejected).then_some(coding)
+            })
+            .collect();
+        Self(accepted)
+    }
+
+    pub fn accepts(&self, encoding: &str) -> bool {
+        self.0.iter().any(|c| c == encoding || c == "*")
+    }
+
+    /// 客户端接受的预压缩编码（按优先级排列）
+    pub fn acceptable(&self) -> impl Iterator<Item = &'static str> + '_ {
+        PRECOMPRESSED_ENCODINGS.iter().copied().filter(|e| self.accepts(e))
+    }
+
+    /// 客户端接受的第一个预压缩编码
+    pub fn preferred(&self) -> Option<&'static str> {
+        self.acceptable().next()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptEncoding {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptEncoding::parse(
+            req.headers().get_one("Accept-Encoding").unwrap_or_default(),
+        ))
+    }
+}
+
+/// 按编码压缩内容；内容过小、编码不支持或压缩后没有变小时返回 None
+pub fn compress(encoding: &str, data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < MIN_COMPRESS_BYTES {
+        return None;
+    }
+    let compressed = match encoding {
+        "br" => {
+            let output = Vec::with_capacity(data.len() / 3);
+            let mut encoder = brotli::CompressorWriter::new(output, 4096, BROTLI_QUALITY, BROTLI_LGWIN);
+            encoder.write_all(data).ok()?;
+            encoder.flush().ok()?;
+            encoder.into_inner()
+        }
+        "gzip" => {
+            let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 3), Compression::best());
+            encoder.write_all(data).ok()?;
+            encoder.finish().ok()?
+        }
+        _ => return None,
+    };
+    (compressed.len() < data.len()).then_some(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_accept_encoding() {
+        assert_eq!(AcceptEncoding::parse("gzip, deflate, br").preferred(), Some("br"));
+        assert_eq!(AcceptEncoding::parse("gzip, deflate").preferred(), Some("gzip"));
+        assert_eq!(AcceptEncoding::parse("br;q=0, gzip;q=1.0").preferred(), Some("gzip"));
+        assert_eq!(AcceptEncoding::parse("br;q=0, gzip;q=0").preferred(), None);
+        assert_eq!(AcceptEncoding::parse("*").acceptable().collect::<Vec<_>>(), ["br", "gzip"]);
+        assert_eq!(AcceptEncoding::parse("").preferred(), None);
+    }
+
+    #[test]
+    fn test_compress() {
+        let data = "self.addEventListener('fetch', () => {});\n".repeat(100);
+        let gz = compress("gzip", data.as_bytes()).unwrap();
+        assert!(gz.len() < data.len());
+        let mut decoded = String::new();
+        GzDecoder::new(gz.as_slice()).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+        assert!(compress("gzip", b"tiny").is_none());
+
+        let br = compress("br", data.as_bytes()).unwrap();
+        assert!(br.len() < data.len());
+        let mut decoded = String::new();
+        brotli::Decompressor::new(br.as_slice(), 4096).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+        assert!(compress("deflate", data.as_bytes()).is_none());
+    }
+}
@@ -0,0 +1,314 @@
+use crate::config::settings::SecurityConfig;
+use crate::{Error, Result};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use crate::utils::rng;
+use std::env;
+
+/// 加密字段前缀，格式：enc:v1:<key_id>:<base64(nonce || ciphertext)>
+const SEALED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+static FIELD_CIPHER: OnceCell<FieldCipher> = OnceCell::new();
+static CODE_PEPPER: OnceCell<Vec<u8>> = OnceCell::new();
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 字段级 AES-256-GCM 加密器
+///
+/// 密钥列表中第一个为当前写入密钥，其余密钥仅用于解密旧数据（密钥轮换）
+pub struct FieldCipher {
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl FieldCipher {
+    /// 从配置和环境变量构建加密器
+    ///
+    /// 环境变量 SPACE_API_ENCRYPTION_KEY（可选 SPACE_API_ENCRYPTION_KEY_ID）优先作为当前写入密钥，
+    /// 便于从 KMS / Secret 注入而不落盘到配置文件
+    pub fn from_config(config: &SecurityConfig) -> Result<Self> {
+        let mut raw_keys: Vec<(String, String)> = Vec::new();
+
+        if let Ok(key) = env::var("SPACE_API_ENCRYPTION_KEY") {
+            if !key.is_empty() {
+                let id = env::var("SPACE_API_ENCRYPTION_KEY_ID").unwrap_or_else(|_| "env".to_string());
+                raw_keys.push((id, key));
+            }
+        }
+        for k in &config.encryption_keys {
+            raw_keys.push((k.id.clone(), k.key.clone()));
+        }
+
+        let mut keys = Vec::with_capacity(raw_keys.len());
+        for (id, key) in raw_keys {
+            if id.is_empty() || id.contains(':') {
+                return Err(Error::Internal(format!("Invalid encryption key id: {:?}", id)));
+            }
+            if keys.iter().any(|(existing, _)| existing == &id) {
+                return Err(Error::Internal(format!("Duplicate encryption key id: {}", id)));
+            }
+            let bytes = BASE64
+                .decode(key.trim())
+                .map_err(|e| Error::Internal(format!("Encryption key {} is not valid base64: {}", id, e)))?;
+            let cipher = Aes256Gcm::new_from_slice(&bytes)
+                .map_err(|_| Error::Internal(format!("Encryption key {} must be 32 bytes", id)))?;
+            keys.push((id, cipher));
+        }
+
+        Ok(Self { keys })
+    }
+
+    /// 是否配置了任何密钥
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// 是否存在仅用于解密的旧密钥（需要执行轮换）
+    pub fn has_retired_keys(&self) -> bool {
+        self.keys.len() > 1
+    }
+
+    /// 当前写入密钥 ID
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.keys.first().map(|(id, _)| id.as_str())
+    }
+
+    /// 使用当前密钥加密字符串
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let (key_id, cipher) = self
+            .keys
+            .first()
+            .ok_or_else(|| Error::Internal("No encryption key configured".to_string()))?;
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        rng::secure_fill(&mut nonce_bytes);
+
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+            .map_err(|_| Error::Internal("Field encryption failed".to_string()))?;
+
+        let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        payload.extend_from_slice(&nonce_bytes);
+        payload.extend_from_slice(&ciphertext);
+
+        Ok(format!("{}{}:{}", SEALED_PREFIX, key_id, BASE64.encode(payload)))
+    }
+
+    /// 解密字符串（根据密文中的 key_id 选择密钥）
+    pub fn decrypt(&self, sealed: &str) -> Result<String> {
+        let (key_id, encoded) = Self::split_sealed(sealed)
+            .ok_or_else(|| Error::Internal("Value is not an encrypted field".to_string()))?;
+
+        let cipher = self
+            .keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, c)| c)
+            .ok_or_else(|| Error::Internal(format!("Unknown encryption key id: {}", key_id)))?;
+
+        let payload = BASE64
+            .decode(encoded)
+            .map_err(|e| Error::Internal(format!("Malformed encrypted field: {}", e)))?;
+        if payload.len() <= NONCE_LEN {
+            return Err(Error::Internal("Malformed encrypted field: too short".to_string()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Internal(format!("Field decryption failed (key {})", key_id)))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|e| Error::Internal(format!("Decrypted field is not UTF-8: {}", e)))
+    }
+
+    /// 密文是否由非当前密钥加密（需要轮换）
+    pub fn needs_rotation(&self, sealed: &str) -> bool {
+        match (Self::split_sealed(sealed), self.active_key_id()) {
+            (Some((key_id, _)), Some(active)) => key_id != active,
+            _ => false,
+        }
+    }
+
+    /// 判断值是否为加密字段
+    pub fn is_sealed(value: &str) -> bool {
+        value.starts_with(SEALED_PREFIX)
+    }
+
+    fn split_sealed(sealed: &str) -> Option<(&str, &str)> {
+        sealed.strip_prefix(SEALED_PREFIX)?.split_once(':')
+    }
+}
+
+/// 初始化全局加密器和验证码 pepper（启动时调用一次）
+pub fn init(config: &SecurityConfig) -> Result<()> {
+    let pepper = env::var("SPACE_API_CODE_PEPPER")
+        .ok()
+        .filter(|p| !p.is_empty())
+        .or_else(|| config.code_pepper.clone().filter(|p| !p.is_empty()));
+    let pepper = match pepper {
+        Some(p) => p.into_bytes(),
+        None => {
+            warn!("未配置 code_pepper，已随机生成（重启后未使用的临时代码将失效）");
+            let mut buf = vec![0u8; 32];
+            rng::secure_fill(&mut buf);
+            buf
+        }
+    };
+    let _ = CODE_PEPPER.set(pepper);
+
+    let cipher = FieldCipher::from_config(config)?;
+    match cipher.active_key_id() {
+        Some(id) => info!("字段加密已启用 (当前密钥: {}, 密钥总数: {})", id, cipher.keys.len()),
+        None => warn!("未配置字段加密密钥，敏感字段将以明文存储"),
+    }
+    FIELD_CIPHER
+        .set(cipher)
+        .map_err(|_| Error::Internal("Field cipher already initialized".to_string()))
+}
+
+/// 获取全局加密器（未初始化时返回 None）
+pub fn cipher() -> Option<&'static FieldCipher> {
+    FIELD_CIPHER.get()
+}
+
+fn code_mac() -> HmacSha256 {
+    // 未初始化时（如单元测试）使用进程内随机 pepper
+    let pepper = CODE_PEPPER.get_or_init(|| {
+        let mut buf = vec![0u8; 32];
+        rng::secure_fill(&mut buf);
+        buf
+    });
+    <HmacSha256 as Mac>::new_from_slice(pepper).expect("HMAC accepts keys of any length")
+}
+
+/// 计算验证码 / 临时代码的存储哈希：hex(HMAC-SHA256(pepper, code))
+///
+/// 结果是确定性的，可直接作为数据库查询条件
+pub fn hash_code(code: &str) -> String {
+    let mut mac = code_mac();
+    mac.update(code.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 计算会话令牌的存储哈希：hex(SHA-256(token))
+///
+/// 会话令牌是 256 位随机数，无需 pepper；不依赖 pepper 也保证重启后已签发的会话仍然有效
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// 常量时间校验代码与存储的哈希是否匹配
+pub fn verify_code(code: &str, stored_hash: &str) -> bool {
+    let Ok(expected) = hex::decode(stored_hash) else {
+        return false;
+    };
+    let mut mac = code_mac();
+    mac.update(code.as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// 由种子派生固定位数的数字代码：HMAC-SHA256(pepper, "derive:" || seed) 的字节按拒绝采样映射为数字
+///
+/// 同一种子总是得到同一代码，调用方只需保存随机种子即可重发代码，无需保存明文
+pub fn derive_digits(seed: &str, len: usize) -> String {
+    let mut code = String::with_capacity(len);
+    let mut counter = 0u32;
+    while code.len() < len {
+        let mut mac = code_mac();
+        mac.update(b"derive:");
+        mac.update(&counter.to_be_bytes());
+        mac.update(seed.as_bytes());
+        for b in mac.finalize().into_bytes() {
+            // 250 = 25 * 10，丢弃 250..=255 以避免取模偏差
+            if b < 250 && code.len() < len {
+                code.push(char::from(b'0' + b % 10));
+            }
+        }
+        counter += 1;
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::EncryptionKeyConfig;
+
+    fn config_with(keys: &[(&str, [u8; 32])]) -> SecurityConfig {
+        SecurityConfig {
+            encryption_keys: keys
+                .iter()
+                .map(|(id, key)| EncryptionKeyConfig {
+                    id: id.to_string(),
+                    key: BASE64.encode(key),
+                })
+                .collect(),
+            code_pepper: None,
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let cipher = FieldCipher::from_config(&config_with(&[("k1", [7u8; 32])])).unwrap();
+        let sealed = cipher.encrypt("secret-token").unwrap();
+
+        assert!(FieldCipher::is_sealed(&sealed));
+        assert!(!sealed.contains("secret-token"));
+        assert_eq!(cipher.decrypt(&sealed).unwrap(), "secret-token");
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = FieldCipher::from_config(&config_with(&[("old", [1u8; 32])])).unwrap();
+        let sealed = old.encrypt("token").unwrap();
+
+        let rotated =
+            FieldCipher::from_config(&config_with(&[("new", [2u8; 32]), ("old", [1u8; 32])])).unwrap();
+        assert!(rotated.needs_rotation(&sealed));
+        assert_eq!(rotated.decrypt(&sealed).unwrap(), "token");
+
+        let resealed = rotated.encrypt("token").unwrap();
+        assert!(!rotated.needs_rotation(&resealed));
+    }
+
+    #[test]
+    fn test_invalid_key_length() {
+        let config = SecurityConfig {
+            encryption_keys: vec![EncryptionKeyConfig {
+                id: "short".to_string(),
+                key: BASE64.encode([0u8; 16]),
+            }],
+            code_pepper: None,
+        };
+        assert!(FieldCipher::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_code_hash() {
+        let hash = hash_code("123456");
+        assert_ne!(hash, "123456");
+        assert_eq!(hash, hash_code("123456"));
+        assert!(verify_code("123456", &hash));
+        assert!(!verify_code("654321", &hash));
+        assert!(!verify_code("123456", "not-hex"));
+
+        // 会话令牌哈希与 pepper 无关，重启后保持不变
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let code = derive_digits("user@example.com:seed", 6);
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(code, derive_digits("user@example.com:seed", 6));
+        assert_eq!(derive_digits("seed", 40).len(), 40);
+    }
+}
@@ -1,6 +1,10 @@
+use crate::utils::etag;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use rocket::http::{ContentType, Status};
 use rocket::request::Request;
 use rocket::response::{self, Responder, Response};
+use sha2::{Digest, Sha256};
 use std::io::Cursor;
 
 pub struct CustomResponse {
@@ -9,6 +13,8 @@ pub struct CustomResponse {
     status: Status,
     headers: Vec<(String, String)>,
     cache: bool,
+    etag: Option<String>,
+    sha256: Option<[u8; 32]>,
 }
 
 impl CustomResponse {
@@ -19,6 +25,8 @@ impl CustomResponse {
             status,
             headers: Vec::new(),
             cache: false,
+            etag: None,
+            sha256: None,
         }
     }
 
@@ -34,19 +42,48 @@ impl CustomResponse {
         self
     }
 
+    /// 追加 Vary 的值（多次调用合并为一个响应头）
+    pub fn with_vary(mut self, value: &str) -> Self {
+        match self.headers.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case("Vary")) {
+            Some((_, existing)) => {
+                existing.push_str(", ");
+                existing.push_str(value);
+            }
+            None => self.headers.push(("Vary".into(), value.into())),
+        }
+        self
+    }
+
     /// 设置是否命中服务器端缓存
     pub fn with_cache(mut self, cache: bool) -> Self {
         self.cache = cache;
         self
     }
+
+    /// 按响应体内容计算强 ETag；请求头 If-None-Match 与之相同时返回 304（无响应体）
+    pub fn with_etag(mut self) -> Self {
+        let digest = self.sha256();
+        self.etag = Some(format!("\"{}\"", etag::digest_hex(&digest)));
+        self
+    }
+
+    /// 附加响应体的 SHA-256 摘要（X-Content-Digest，格式同 RFC 9530 的 Content-Digest），供客户端校验缓存内容
+    pub fn with_digest(mut self) -> Self {
+        let digest = self.sha256();
+        self.with_header("X-Content-Digest", format!("sha-256=:{}:", BASE64.encode(digest)))
+    }
+
+    /// 响应体的 SHA-256（ETag 和摘要共用，只计算一次）
+    fn sha256(&mut self) -> [u8; 32] {
+        *self.sha256.get_or_insert_with(|| Sha256::digest(&self.data).into())
+    }
 }
 
 impl<'r> Responder<'r, 'static> for CustomResponse {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
         let mut builder = Response::build();
-        builder.header(self.content_type);
         builder.status(self.status);
-        
+
         let mut headers = self.headers;
         headers.push(if self.cache {
             ("server-cache".into(), "HIT".into())
@@ -54,10 +91,67 @@ impl<'r> Responder<'r, 'static> for CustomResponse {
             ("server-cache".into(), "MISS".into())
         });
 
+        let not_modified = match self.etag {
+            Some(tag) => {
+                let matched = self.status == Status::Ok
+                    && req
+                        .headers()
+                        .get_one("If-None-Match")
+                        .is_some_and(|value| etag::matches(value, &tag));
+                headers.push(("ETag".into(), tag));
+                matched
+            }
+            None => false,
+        };
+
         for (k, v) in headers {
             builder.raw_header(k, v);
         }
 
+        if not_modified {
+            return builder.status(Status::NotModified).ok();
+        }
+        builder.header(self.content_type);
         builder.sized_body(self.data.len(), Cursor::new(self.data)).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+    use rocket::local::asynchronous::Client;
+    use rocket::{get, routes};
+
+    #[get("/image")]
+    fn image() -> CustomResponse {
+        CustomResponse::new(ContentType::PNG, b"\x89PNG image".to_vec(), Status::Ok)
+            .with_header("Cache-Control", "public, max-age=30")
+            .with_vary("Accept")
+            .with_vary("Sec-CH-DPR")
+            .with_etag()
+            .with_digest()
+            .with_cache(true)
+    }
+
+    #[rocket::async_test]
+    async fn test_etag() {
+        let client = Client::untracked(rocket::build().mount("/", routes![image])).await.unwrap();
+
+        let first = client.get("/image").dispatch().await;
+        assert_eq!(first.status(), Status::Ok);
+        let etag = first.headers().get_one("ETag").unwrap().to_string();
+        let digest = BASE64.encode(Sha256::digest(b"\x89PNG image"));
+        assert_eq!(first.headers().get_one("X-Content-Digest"), Some(format!("sha-256=:{}:", digest).as_str()));
+        assert_eq!(first.headers().get_one("Vary"), Some("Accept, Sec-CH-DPR"));
+
+        let cached = client.get("/image").header(Header::new("If-None-Match", etag)).dispatch().await;
+        assert_eq!(cached.status(), Status::NotModified);
+        assert_eq!(cached.headers().get_one("Cache-Control"), Some("public, max-age=30"));
+        assert!(cached.into_bytes().await.unwrap_or_default().is_empty());
+
+        let changed = client.get("/image").header(Header::new("If-None-Match", "\"other\"")).dispatch().await;
+        assert_eq!(changed.status(), Status::Ok);
+        assert_eq!(changed.into_bytes().await.unwrap(), b"\x89PNG image");
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 最多保留的错误种类数（按 类型 + 路由 区分）
+const MAX_ENTRIES: usize = 200;
+/// 错误率窗口内最多保留的时间点数（防止错误洪泛时占用过多内存）
+const MAX_RECENT: usize = 100_000;
+/// 用于计算错误率的时间窗口
+const RATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// 一类错误的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRecord {
+    /// 错误类型（Error 枚举的变体名）
+    pub kind: String,
+    /// 路由模板（未匹配路由时为 "<unmatched>"）
+    pub route: String,
+    pub status: u16,
+    pub count: u64,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub last_message: String,
+    pub last_request_id: String,
+}
+
+/// 错误率摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorSummary {
+    pub last_minute: usize,
+    pub last_hour: usize,
+    /// 最近一小时内的服务端错误（5xx）数
+    pub server_errors_last_hour: usize,
+    pub total: u64,
+}
+
+struct TrackerState {
+    records: HashMap<(String, String), ErrorRecord>,
+    /// 最近一小时的错误时间点（时间, 是否为 5xx）
+    recent: VecDeque<(Instant, bool)>,
+    total: u64,
+}
+
+/// 有界的内存错误统计，由 Error 响应器写入
+pub struct ErrorTracker {
+    state: Mutex<TrackerState>,
+}
+
+impl ErrorTracker {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(TrackerState {
+                records: HashMap::new(),
+                recent: VecDeque::new(),
+                total: 0,
+            }),
+        }
+    }
+
+    /// 记录一次错误响应
+    pub fn record(&self, kind: &str, route: &str, status: u16, message: &str, request_id: &str) {
+        let now = Utc::now().to_rfc3339();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        state.total += 1;
+        state.recent.push_back((Instant::now(), status >= 500));
+        prune(&mut state.recent);
+
+        let key = (kind.to_string(), route.to_string());
+        if !state.records.contains_key(&key) && state.records.len() >= MAX_ENTRIES {
+            // 淘汰最久未出现的一类错误
+            if let Some(oldest) = state
+                .records
+                .iter()
+                .min_by(|a, b| a.1.last_seen.cmp(&b.1.last_seen))
+                .map(|(k, _)| k.clone())
+            {
+                state.records.remove(&oldest);
+            }
+        }
+
+        let record = state.records.entry(key).or_insert_with(|| ErrorRecord {
+            kind: kind.to_string(),
+            route: route.to_string(),
+            status,
+            count: 0,
+            first_seen: now.clone(),
+            last_seen: now.clone(),
+            last_message: String::new(),
+            last_request_id: String::new(),
+        });
+        record.count += 1;
+        record.status = status;
+        record.last_seen = now;
+        record.last_message = message.to_string();
+        record.last_request_id = request_id.to_string();
+    }
+
+    /// 最近出现的错误（按最后出现时间倒序）
+    pub fn recent(&self, limit: usize) -> Vec<ErrorRecord> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut list: Vec<ErrorRecord> = state.records.values().cloned().collect();
+        list.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        list.truncate(limit);
+        list
+    }
+
+    /// 错误率摘要
+    pub fn summary(&self) -> ErrorSummary {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        prune(&mut state.recent);
+        let minute_ago = Instant::now().checked_sub(Duration::from_secs(60));
+        ErrorSummary {
+            last_minute: state
+                .recent
+                .iter()
+                .filter(|(t, _)| minute_ago.map(|m| *t >= m).unwrap_or(true))
+                .count(),
+            last_hour: state.recent.len(),
+            server_errors_last_hour: state.recent.iter().filter(|(_, server)| *server).count(),
+            total: state.total,
+        }
+    }
+}
+
+fn prune(recent: &mut VecDeque<(Instant, bool)>) {
+    while recent.len() > MAX_RECENT {
+        recent.pop_front();
+    }
+    while let Some((t, _)) = recent.front() {
+        if t.elapsed() > RATE_WINDOW {
+            recent.pop_front();
+        } else {
+            break;
+        }
+    }
+}
+
+/// 全局错误统计
+pub static ERROR_TRACKER: Lazy<ErrorTracker> = Lazy::new(ErrorTracker::new);
@@ -5,8 +5,31 @@ use rocket::request::Request;
 use rocket::response::{self, Response, Responder};
 use serde_json::json;
 use std::io::Cursor;
+use crate::utils::error_tracker::ERROR_TRACKER;
+use crate::utils::feature_gate;
+use crate::utils::rng;
+use crate::utils::validation::{self, FieldError};
 
-#[derive(Debug)]
+/// 请求 ID（同一请求内相同）
+#[derive(Debug, Clone)]
+struct RequestId(String);
+
+/// 获取请求 ID：优先使用上游传入的 X-Request-Id，否则生成一个随机 ID
+pub fn request_id(req: &Request<'_>) -> String {
+    req.local_cache(|| {
+        RequestId(
+            req.headers()
+                .get_one("X-Request-Id")
+                .filter(|id| !id.is_empty() && id.len() <= 64)
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| rng::secure_hex(8)),
+        )
+    })
+    .0
+    .clone()
+}
+
+#[derive(Debug, Clone)]
 pub enum Error {
     Database(String),
     NotFound(String),
@@ -16,6 +39,12 @@ pub enum Error {
     Conflict(String),
     Gone(String),
     Internal(String),
+    /// 服务暂时繁忙（503），附带建议的重试间隔（秒，用于 Retry-After）
+    Unavailable(String, u64),
+    /// 请求超过截止时间，放弃后续处理（504）
+    Timeout(String),
+    /// 请求字段校验失败（422），包含全部字段错误
+    Validation(Vec<FieldError>),
 }
 
 impl Display for Error {
@@ -29,14 +58,46 @@ impl Display for Error {
             Error::Conflict(msg) => write!(f, "Conflict: {}", msg),
             Error::Gone(msg) => write!(f, "Gone: {}", msg),
             Error::Internal(msg) => write!(f, "Internal error: {}", msg),
+            Error::Unavailable(msg, _) => write!(f, "Service unavailable: {}", msg),
+            Error::Timeout(msg) => write!(f, "Timeout: {}", msg),
+            Error::Validation(errors) if errors.is_empty() => write!(f, "Unprocessable request"),
+            Error::Validation(errors) => {
+                write!(f, "Validation failed: ")?;
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{} {}", e.field, e.reason)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// 错误类型名称（用于错误统计）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Database(_) => "Database",
+            Error::NotFound(_) => "NotFound",
+            Error::BadRequest(_) => "BadRequest",
+            Error::Unauthorized(_) => "Unauthorized",
+            Error::Forbidden(_) => "Forbidden",
+            Error::Conflict(_) => "Conflict",
+            Error::Gone(_) => "Gone",
+            Error::Internal(_) => "Internal",
+            Error::Unavailable(..) => "Unavailable",
+            Error::Timeout(_) => "Timeout",
+            Error::Validation(_) => "Validation",
+        }
+    }
+}
+
 impl<'r> Responder<'r, 'static> for Error {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
         let status = match &self {
             Error::Database(_) => Status::InternalServerError,
             Error::NotFound(_) => Status::NotFound,
@@ -46,6 +107,9 @@ impl<'r> Responder<'r, 'static> for Error {
             Error::Conflict(_) => Status::Conflict,
             Error::Gone(_) => Status::Gone,
             Error::Internal(_) => Status::InternalServerError,
+            Error::Unavailable(..) => Status::ServiceUnavailable,
+            Error::Timeout(_) => Status::GatewayTimeout,
+            Error::Validation(_) => Status::UnprocessableEntity,
         };
 
         let code = match &self {
@@ -57,6 +121,9 @@ impl<'r> Responder<'r, 'static> for Error {
             Error::Conflict(_) => "409",
             Error::Gone(_) => "410",
             Error::Internal(_) => "500",
+            Error::Unavailable(..) => "503",
+            Error::Timeout(_) => "504",
+            Error::Validation(_) => "422",
         };
 
         // 仅对客户端错误返回详细信息，服务端错误返回通用消息（避免泄露内部实现细节）
@@ -73,17 +140,53 @@ impl<'r> Responder<'r, 'static> for Error {
         };
         let status_text = "failed";
 
+        // 记录到错误统计（详细信息仅供 /api/errors/recent 查看）
+        let request_id = request_id(req);
+        let route = req
+            .route()
+            .map(|r| r.uri.to_string())
+            .unwrap_or_else(|| "<unmatched>".to_string());
+        ERROR_TRACKER.record(self.kind(), &route, status.code, &self.to_string(), &request_id);
+
+        // 校验错误在 data 中列出每个字段的错误原因
+        let data = match &self {
+            Error::Validation(errors) => json!({ "errors": errors }),
+            _ => serde_json::Value::Null,
+        };
         let body = json!({
             "code": code,
             "message": message,
             "status": status_text,
-            "data": null
+            "data": data
         });
 
-        Response::build()
+        let mut response = Response::build();
+        response
             .status(status)
             .header(rocket::http::ContentType::JSON)
-            .sized_body(body.to_string().len(), Cursor::new(body.to_string()))
-            .ok()
+            .raw_header("X-Request-Id", request_id)
+            .sized_body(body.to_string().len(), Cursor::new(body.to_string()));
+        if let Error::Unavailable(_, retry_after) = &self {
+            response.raw_header("Retry-After", retry_after.to_string());
+        }
+        response.ok()
     }
-}
\ No newline at end of file
+}
+
+// 只接管本服务的守卫记录了原因的状态码；401 / 403 / 404 / 500 等仍使用 Rocket 默认的错误响应，保持原有格式
+
+// 请求体解析或校验失败（Valid 数据守卫会记录字段错误）
+#[rocket::catch(422)]
+fn unprocessable(req: &Request<'_>) -> Error {
+    Error::Validation(validation::rejected_fields(req))
+}
+
+// 功能被关闭（FeatureGate 守卫会记录原因）
+#[rocket::catch(503)]
+fn unavailable(req: &Request<'_>) -> Error {
+    feature_gate::rejection(req).unwrap_or_else(|| Error::Unavailable("Service temporarily unavailable".to_string(), 60))
+}
+
+pub fn catchers() -> Vec<rocket::Catcher> {
+    rocket::catchers![unprocessable, unavailable]
+}
@@ -0,0 +1,148 @@
+use crate::utils::response::ApiResponse;
+use crate::{Error, Result};
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Json;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+
+/// 带 ETag 的条件响应：请求头 If-None-Match 与当前 ETag 相同时返回 304（无响应体）
+///
+/// 默认按响应体内容计算强 ETag；响应中含有每次都会变化但不影响语义的字段（时间戳、运行时长）时，
+/// 用 `weak` 指定决定内容的部分，生成弱 ETag
+pub struct Conditional {
+    content_type: ContentType,
+    body: Vec<u8>,
+    etag: String,
+    headers: Vec<Header<'static>>,
+}
+
+impl Conditional {
+    /// 包装 ApiResponse JSON 响应（序列化失败时返回错误，不会生成空响应体）
+    pub fn json<T: Serialize>(response: Json<ApiResponse<T>>) -> Result<Self> {
+        let body = serde_json::to_vec(&response.into_inner())
+            .map_err(|e| Error::Internal(format!("Failed to serialize response: {}", e)))?;
+        Ok(Self::bytes(ContentType::JSON, body))
+    }
+
+    /// 包装已序列化的响应体
+    pub fn bytes(content_type: ContentType, body: Vec<u8>) -> Self {
+        let etag = format!("\"{}\"", digest(&body));
+        Self {
+            content_type,
+            body,
+            etag,
+            headers: Vec::new(),
+        }
+    }
+
+    /// 使用弱 ETag，由 `key` 决定（key 相同即视为内容未变化）
+    pub fn weak(mut self, key: impl AsRef<[u8]>) -> Self {
+        self.etag = format!("W/\"{}\"", digest(key.as_ref()));
+        self
+    }
+
+    /// 改为返回预压缩的内容（ETag 按编码区分，并带上 Content-Encoding 和 Vary）
+    pub fn precompressed(mut self, encoding: &'static str, body: Vec<u8>) -> Self {
+        self.body = body;
+        if let Some(tag) = self.etag.strip_suffix('"') {
+            self.etag = format!("{}-{}\"", tag, encoding);
+        }
+        self.headers.push(Header::new("Content-Encoding", encoding));
+        self.with_header("Vary", "Accept-Encoding")
+    }
+
+    /// 附加响应头（304 响应同样带上，如 Cache-Control）
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push(Header::new(name, value.into()));
+        self
+    }
+}
+
+fn digest(data: &[u8]) -> String {
+    digest_hex(&Sha256::digest(data))
+}
+
+/// 由完整的 SHA-256 生成 ETag 使用的摘要
+pub(crate) fn digest_hex(sha256: &[u8]) -> String {
+    hex::encode(&sha256[..12])
+}
+
+/// If-None-Match 是否命中（弱比较：忽略 W/ 前缀）
+pub(crate) fn matches(if_none_match: &str, etag: &str) -> bool {
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = strip(etag);
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || strip(tag) == current)
+}
+
+impl<'r> Responder<'r, 'static> for Conditional {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let not_modified = req
+            .headers()
+            .get_one("If-None-Match")
+            .is_some_and(|value| matches(value, &self.etag));
+
+        let mut builder = Response::build();
+        builder.raw_header("ETag", self.etag);
+        for header in self.headers {
+            builder.header_adjoin(header);
+        }
+        if not_modified {
+            builder.status(Status::NotModified);
+        } else {
+            builder
+                .header(self.content_type)
+                .sized_body(self.body.len(), Cursor::new(self.body));
+        }
+        builder.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+    use rocket::{get, routes};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    #[get("/strong")]
+    fn strong() -> Result<Conditional> {
+        Ok(Conditional::json(ApiResponse::success(vec![1, 2, 3], "list"))?.with_header("Cache-Control", "no-cache"))
+    }
+
+    #[get("/weak")]
+    fn weak() -> Result<Conditional> {
+        let tick = TICKS.fetch_add(1, Ordering::SeqCst);
+        Ok(Conditional::json(ApiResponse::success(tick, "tick"))?.weak("stable"))
+    }
+
+    #[rocket::async_test]
+    async fn test_not_modified() {
+        let client = Client::untracked(rocket::build().mount("/", routes![strong, weak])).await.unwrap();
+
+        let first = client.get("/strong").dispatch().await;
+        assert_eq!(first.status(), Status::Ok);
+        let etag = first.headers().get_one("ETag").unwrap().to_string();
+        assert!(etag.starts_with('"'));
+
+        let cached = client.get("/strong").header(Header::new("If-None-Match", etag.clone())).dispatch().await;
+        assert_eq!(cached.status(), Status::NotModified);
+        assert_eq!(cached.headers().get_one("Cache-Control"), Some("no-cache"));
+        assert!(cached.into_bytes().await.unwrap_or_default().is_empty());
+
+        let stale = client.get("/strong").header(Header::new("If-None-Match", "\"other\"")).dispatch().await;
+        assert_eq!(stale.status(), Status::Ok);
+
+        // 内容变化但弱 ETag 不变
+        let etag = client.get("/weak").dispatch().await.headers().get_one("ETag").unwrap().to_string();
+        assert!(etag.starts_with("W/"));
+        let cached = client.get("/weak").header(Header::new("If-None-Match", etag)).dispatch().await;
+        assert_eq!(cached.status(), Status::NotModified);
+    }
+}
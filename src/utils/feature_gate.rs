@@ -0,0 +1,31 @@
+use crate::services::feature_service::{self, Feature};
+use crate::Error;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::marker::PhantomData;
+
+/// 守卫拒绝的原因（供 503 错误处理读取）
+struct Disabled(Option<Error>);
+
+/// 功能开关守卫：功能被关闭时返回 503（开关见 feature_service::FEATURES，通过管理接口切换）
+pub struct FeatureGate<F: Feature>(PhantomData<F>);
+
+#[rocket::async_trait]
+impl<'r, F: Feature> FromRequest<'r> for FeatureGate<F> {
+    type Error = Error;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match feature_service::ensure(F::NAME) {
+            Ok(()) => Outcome::Success(FeatureGate(PhantomData)),
+            Err(e) => {
+                req.local_cache(|| Disabled(Some(e.clone())));
+                Outcome::Error((Status::ServiceUnavailable, e))
+            }
+        }
+    }
+}
+
+/// 503 错误处理使用：取出守卫记录的拒绝原因
+pub fn rejection(req: &Request<'_>) -> Option<Error> {
+    req.local_cache(|| Disabled(None)).0.clone()
+}
@@ -0,0 +1,377 @@
+use crate::utils::auth::ADMIN_SESSION_COOKIE;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 幂等键最大长度
+const MAX_KEY_LEN: usize = 128;
+/// 请求体能预读的最大字节数（Rocket 的 peek 上限），更长的请求体由 Valid 数据守卫读取后计算摘要
+const PEEK_LEN: usize = 512;
+/// 重放时不复制的响应头（由本次响应重新生成）
+const SKIPPED_HEADERS: &[&str] = &["Content-Length", "Transfer-Encoding", "Date", "X-Request-Id"];
+
+/// 已完成请求的响应
+#[derive(Clone)]
+struct StoredResponse {
+    status: u16,
+    /// 全部响应头（含 Content-Type、Location、Set-Cookie 等），同名头按顺序保存
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+#[derive(Clone)]
+enum Entry {
+    InFlight { fingerprint: String },
+    Done { fingerprint: String, response: StoredResponse },
+}
+
+// 幂等键 -> 请求摘要和响应，保留 24 小时
+static STORE: Lazy<Cache<String, Entry>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(24 * 60 * 60))
+        .build()
+});
+
+enum State {
+    /// 请求未带幂等键或接口未启用幂等守卫
+    None,
+    /// 请求体未能完整预读，等待 Valid 数据守卫读取完整请求体后检查
+    Deferred,
+    /// 首次请求（附请求摘要），响应完成后保存
+    Fresh(String),
+    /// 重复请求，返回保存的响应
+    Replay(StoredResponse),
+}
+
+struct Context {
+    /// 存储键（调用方身份 + 幂等键 + 路径）
+    store_key: String,
+    /// 请求方法和 URI
+    head: String,
+    /// 请求摘要（方法、URI 和完整请求体）；请求体超过预读上限时为 None
+    fingerprint: Option<String>,
+    state: Mutex<State>,
+}
+
+/// 请求摘要：方法、URI 和完整请求体
+fn fingerprint(head: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(head.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// 调用方身份摘要：Authorization / X-Admin-Token 头和管理会话 Cookie，匿名请求为空串的摘要
+fn caller_identity(req: &Request<'_>) -> String {
+    let mut hasher = Sha256::new();
+    for value in [
+        req.headers().get_one("Authorization"),
+        req.headers().get_one("X-Admin-Token"),
+        req.cookies().get(ADMIN_SESSION_COOKIE).map(|c| c.value()),
+    ] {
+        hasher.update(value.unwrap_or_default().as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// 为带 `Idempotency-Key` 头的写请求计算摘要，保存首次响应并在重试时重放
+///
+/// 只对使用了 `Idempotency` 守卫的接口生效
+pub struct IdempotencyFairing;
+
+#[rocket::async_trait]
+impl Fairing for IdempotencyFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Idempotency-Key replay",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, data: &mut Data<'_>) {
+        if !matches!(req.method(), Method::Post | Method::Put | Method::Patch | Method::Delete) {
+            return;
+        }
+        let Some(key) = req.headers().get_one("Idempotency-Key").map(str::trim) else {
+            return;
+        };
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return;
+        }
+
+        // 键按调用方隔离，不同身份使用相同的键不会互相重放响应
+        let store_key = hex::encode(Sha256::digest(
+            format!("{}\n{}\n{}", caller_identity(req), key, req.uri().path()).as_bytes(),
+        ));
+        let head = format!("{}\n{}", req.method(), req.uri());
+        // 请求体能完整预读时直接计算摘要，否则留给数据守卫
+        // （peek 最多返回 PEEK_LEN 字节，读满时无法确定是否还有剩余内容）
+        let body = data.peek(PEEK_LEN).await.to_vec();
+        let complete = data.peek_complete() && body.len() < PEEK_LEN;
+        let fingerprint = complete.then(|| fingerprint(&head, &body));
+
+        req.local_cache(|| {
+            Some(Context {
+                store_key,
+                head,
+                fingerprint,
+                state: Mutex::new(State::None),
+            })
+        });
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(ctx) = req.local_cache(|| None::<Context>) else {
+            return;
+        };
+        let state = std::mem::replace(&mut *ctx.state.lock().unwrap_or_else(|e| e.into_inner()), State::None);
+        match state {
+            State::None | State::Deferred => {}
+            State::Replay(stored) => {
+                res.set_status(Status::new(stored.status));
+                for (name, _) in &stored.headers {
+                    res.remove_header(name);
+                }
+                for (name, value) in stored.headers {
+                    res.adjoin_raw_header(name, value);
+                }
+                res.set_header(Header::new("Idempotent-Replayed", "true"));
+                res.set_sized_body(stored.body.len(), Cursor::new(stored.body));
+            }
+            State::Fresh(fingerprint) => {
+                // 服务端错误不保存，允许客户端使用相同的键重试
+                if res.status().code >= 500 {
+                    STORE.invalidate(&ctx.store_key).await;
+                    return;
+                }
+                let body = match res.body_mut().to_bytes().await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        log::warn!("Failed to buffer response for idempotency key: {}", e);
+                        STORE.invalidate(&ctx.store_key).await;
+                        return;
+                    }
+                };
+                let headers = res
+                    .headers()
+                    .iter()
+                    .filter(|h| !SKIPPED_HEADERS.iter().any(|s| h.name().as_str().eq_ignore_ascii_case(s)))
+                    .map(|h| (h.name().to_string(), h.value().to_string()))
+                    .collect();
+                let response = StoredResponse {
+                    status: res.status().code,
+                    headers,
+                    body: body.clone(),
+                };
+                res.set_sized_body(body.len(), Cursor::new(body));
+                STORE
+                    .insert(
+                        ctx.store_key.clone(),
+                        Entry::Done { fingerprint, response },
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+/// 请求守卫：为接口启用 Idempotency-Key 支持
+///
+/// 重复请求不会执行处理函数，而是由 IdempotencyFairing 返回首次的响应；
+/// 首次请求仍在处理中时返回 409，相同的键用于不同请求时返回 422。
+/// 请求体超过预读上限时由 Valid 数据守卫读取完整请求体后再检查
+pub struct Idempotency;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Idempotency {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(ctx) = req.local_cache(|| None::<Context>) else {
+            return Outcome::Success(Idempotency);
+        };
+        let Some(fingerprint) = ctx.fingerprint.clone() else {
+            *ctx.state.lock().unwrap_or_else(|e| e.into_inner()) = State::Deferred;
+            return Outcome::Success(Idempotency);
+        };
+        match begin(ctx, fingerprint).await {
+            Ok(()) => Outcome::Success(Idempotency),
+            Err(e) => Outcome::Error(e),
+        }
+    }
+}
+
+/// 数据守卫读取完整请求体后调用：Idempotency 守卫推迟了检查时，按完整请求体计算摘要并检查
+pub async fn check_body(req: &Request<'_>, body: &[u8]) -> std::result::Result<(), (Status, &'static str)> {
+    let Some(ctx) = req.local_cache(|| None::<Context>) else {
+        return Ok(());
+    };
+    if !matches!(*ctx.state.lock().unwrap_or_else(|e| e.into_inner()), State::Deferred) {
+        return Ok(());
+    }
+    begin(ctx, fingerprint(&ctx.head, body)).await
+}
+
+/// 登记首次请求，或根据已有记录决定重放、409 或 422
+async fn begin(ctx: &Context, fingerprint: String) -> std::result::Result<(), (Status, &'static str)> {
+    let entry = STORE
+        .entry(ctx.store_key.clone())
+        .or_insert_with(async {
+            Entry::InFlight {
+                fingerprint: fingerprint.clone(),
+            }
+        })
+        .await;
+    let mut state = ctx.state.lock().unwrap_or_else(|e| e.into_inner());
+    if entry.is_fresh() {
+        *state = State::Fresh(fingerprint);
+        return Ok(());
+    }
+
+    match entry.into_value() {
+        Entry::Done {
+            fingerprint: stored,
+            response,
+        } if stored == fingerprint => {
+            *state = State::Replay(response);
+            // 状态码会被替换为保存的响应
+            Err((Status::Conflict, "replayed"))
+        }
+        Entry::InFlight { fingerprint: stored } if stored == fingerprint => Err((
+            Status::Conflict,
+            "A request with this Idempotency-Key is still being processed",
+        )),
+        _ => Err((
+            Status::UnprocessableEntity,
+            "Idempotency-Key was already used for a different request",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::validation::{Valid, Validate, Validator};
+    use rocket::http::CookieJar;
+    use rocket::local::asynchronous::Client;
+    use rocket::response::status::Created;
+    use rocket::{post, routes};
+    use serde::Deserialize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[post("/send", data = "<body>")]
+    fn send(body: &str, _idempotency: Idempotency) -> String {
+        format!("{}:{}", body, CALLS.fetch_add(1, Ordering::SeqCst))
+    }
+
+    static SCOPED_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[post("/scoped", data = "<body>")]
+    fn scoped(body: &str, _idempotency: Idempotency) -> String {
+        format!("{}:{}", body, SCOPED_CALLS.fetch_add(1, Ordering::SeqCst))
+    }
+
+    static LONG_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Deserialize)]
+    struct Note {
+        text: String,
+    }
+
+    impl Validate for Note {
+        fn check(&self, v: &mut Validator) {
+            v.required("text", &self.text);
+        }
+    }
+
+    #[post("/notes", data = "<note>")]
+    fn create_note(note: Valid<Note>, cookies: &CookieJar<'_>, _idempotency: Idempotency) -> Created<String> {
+        let id = LONG_CALLS.fetch_add(1, Ordering::SeqCst);
+        cookies.add(("note", id.to_string()));
+        Created::new(format!("/notes/{}", id)).body(note.text[note.text.len() - 1..].to_string())
+    }
+
+    #[rocket::async_test]
+    async fn test_replay() {
+        let rocket = rocket::build().attach(IdempotencyFairing).mount("/", routes![send]);
+        let client = Client::untracked(rocket).await.unwrap();
+        let request = |body: &'static str| {
+            client
+                .post("/send")
+                .header(Header::new("Idempotency-Key", "test-key"))
+                .body(body)
+        };
+
+        let first = request("a").dispatch().await.into_string().await.unwrap();
+        let replay = request("a").dispatch().await;
+        assert_eq!(replay.headers().get_one("Idempotent-Replayed"), Some("true"));
+        assert_eq!(replay.into_string().await.unwrap(), first);
+        assert_eq!(request("b").dispatch().await.status(), Status::UnprocessableEntity);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    }
+
+    #[rocket::async_test]
+    async fn test_key_scoped_to_caller() {
+        let rocket = rocket::build().attach(IdempotencyFairing).mount("/", routes![scoped]);
+        let client = Client::untracked(rocket).await.unwrap();
+        let request = |token: &'static str| {
+            client
+                .post("/scoped")
+                .header(Header::new("Idempotency-Key", "shared-key"))
+                .header(Header::new("Authorization", token))
+                .body("c")
+        };
+
+        let alice = request("Bearer alice").dispatch().await;
+        assert!(alice.headers().get_one("Idempotent-Replayed").is_none());
+        let bob = request("Bearer bob").dispatch().await;
+        assert!(bob.headers().get_one("Idempotent-Replayed").is_none());
+        assert_ne!(alice.into_string().await, bob.into_string().await);
+
+        let retry = request("Bearer alice").dispatch().await;
+        assert_eq!(retry.headers().get_one("Idempotent-Replayed"), Some("true"));
+        assert_eq!(SCOPED_CALLS.load(Ordering::SeqCst), 2);
+    }
+
+    #[rocket::async_test]
+    async fn test_long_body_and_headers() {
+        let rocket = rocket::build().attach(IdempotencyFairing).mount("/", routes![create_note]);
+        let client = Client::untracked(rocket).await.unwrap();
+        // 两个请求体长度相同、开头超过预读上限的部分也相同，只有结尾不同
+        let prefix = "a".repeat(1024);
+        let request = |last: char| {
+            client
+                .post("/notes")
+                .header(Header::new("Idempotency-Key", "note-key"))
+                .body(format!(r#"{{"text":"{}{}"}}"#, prefix, last))
+        };
+
+        let first = request('b').dispatch().await;
+        assert_eq!(first.status(), Status::Created);
+        let location = first.headers().get_one("Location").map(str::to_string);
+        let cookie = first.headers().get_one("Set-Cookie").map(str::to_string);
+        assert_eq!(first.into_string().await.as_deref(), Some("b"));
+
+        let replay = request('b').dispatch().await;
+        assert_eq!(replay.status(), Status::Created);
+        assert_eq!(replay.headers().get_one("Idempotent-Replayed"), Some("true"));
+        assert_eq!(replay.headers().get_one("Location").map(str::to_string), location);
+        assert_eq!(replay.headers().get_one("Set-Cookie").map(str::to_string), cookie);
+        assert_eq!(replay.into_string().await.as_deref(), Some("b"));
+
+        assert_eq!(request('c').dispatch().await.status(), Status::UnprocessableEntity);
+        assert_eq!(LONG_CALLS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::services::abuse_service::{AbuseService, AbuseSignal};
+use crate::services::audit_service::AuditService;
+use crate::services::ip_filter_service::{IpDecision, IpFilterService};
+use crate::Error;
+use log::warn;
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Method;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{get, routes, Data, Request, Response, Route};
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 被拒绝的请求会被改写到此路径，由 ip_denied 路由返回 403
+const IP_DENIED_PATH: &str = "/__ip_denied";
+
+// 同一 IP 的拒绝事件每分钟最多写入一次审计日志，避免被攻击时刷爆数据库
+static DENY_AUDIT_THROTTLE: Lazy<Cache<String, ()>> = Lazy::new(|| {
+    Cache::builder()
+        .time_to_live(Duration::from_secs(60))
+        .max_capacity(10_000)
+        .build()
+});
+
+/// 解析客户端 IP
+///
+/// 仅在 trust_proxy_headers 开启时读取代理头，否则使用 TCP 连接的对端地址
+pub fn resolve_client_ip(req: &Request<'_>, trust_proxy_headers: bool) -> Option<IpAddr> {
+    if trust_proxy_headers {
+        let from_header = req
+            .headers()
+            .get_one("CF-Connecting-IP")
+            .or_else(|| req.headers().get_one("X-Forwarded-For").and_then(|s| s.split(',').next()))
+            .or_else(|| req.headers().get_one("X-Real-IP"))
+            .and_then(|s| s.trim().parse::<IpAddr>().ok());
+        if from_header.is_some() {
+            return from_header;
+        }
+    }
+    req.remote().map(|addr| addr.ip())
+}
+
+/// 客户端 IP 请求守卫（与 IP 过滤使用相同的解析规则）
+pub struct ClientAddr(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientAddr {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let trust = req
+            .rocket()
+            .state::<Arc<IpFilterService>>()
+            .map(|s| s.trust_proxy_headers())
+            .unwrap_or(false);
+        Outcome::Success(ClientAddr(resolve_client_ip(req, trust)))
+    }
+}
+
+/// IP 白名单/黑名单 fairing
+pub struct IpFilterFairing {
+    service: Arc<IpFilterService>,
+}
+
+impl IpFilterFairing {
+    pub fn new(service: Arc<IpFilterService>) -> Self {
+        Self { service }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for IpFilterFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "IP allowlist/denylist filter",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        let path = req.uri().path().as_str().to_string();
+        let ip = resolve_client_ip(req, self.service.trust_proxy_headers());
+
+        if let IpDecision::Deny(reason) = self.service.check(ip, &path).await {
+            let ip_str = ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+            warn!("IP 访问被拒绝: {} {} {} ({})", ip_str, req.method(), path, reason);
+
+            if !DENY_AUDIT_THROTTLE.contains_key(&ip_str) {
+                DENY_AUDIT_THROTTLE.insert(ip_str.clone(), ()).await;
+                AuditService::record_detached(
+                    "ip_filter.deny",
+                    &ip_str,
+                    &path,
+                    serde_json::json!({ "reason": reason, "method": req.method().as_str() }),
+                );
+            }
+
+            req.set_method(Method::Get);
+            req.set_uri(Origin::parse(IP_DENIED_PATH).expect("hardcoded URI is valid"));
+        }
+    }
+}
+
+/// 图片类接口路径前缀（缓存未命中会触发上游下载和转码）
+const IMAGE_PATH_PREFIXES: &[&str] = &["/avatar", "/images", "/friend-avatar"];
+
+/// 滥用检测 fairing：统计图片类接口的缓存未命中次数
+pub struct AbuseFairing {
+    service: Arc<AbuseService>,
+    trust_proxy_headers: bool,
+}
+
+impl AbuseFairing {
+    pub fn new(service: Arc<AbuseService>, trust_proxy_headers: bool) -> Self {
+        Self {
+            service,
+            trust_proxy_headers,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for AbuseFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Abuse detection",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let path = req.uri().path().as_str();
+        if !IMAGE_PATH_PREFIXES.iter().any(|p| path.starts_with(p)) {
+            return;
+        }
+        if res.headers().get_one("server-cache") == Some("MISS") {
+            let ip = resolve_client_ip(req, self.trust_proxy_headers);
+            self.service.record(ip, AbuseSignal::ImageCacheMiss).await;
+        }
+    }
+}
+
+#[get("/__ip_denied")]
+fn ip_denied() -> Error {
+    Error::Forbidden("Access denied".to_string())
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![ip_denied]
+}
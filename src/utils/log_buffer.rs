@@ -0,0 +1,134 @@
+use chrono::Local;
+use log::{Level, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once, RwLock};
+use tokio::sync::broadcast;
+
+/// 内存中保留的最近日志条数
+const BUFFER_CAPACITY: usize = 1000;
+
+/// 单条日志
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// 递增序号（用于客户端去重）
+    pub seq: u64,
+    pub timestamp: String,
+    pub level: String,
+    /// 日志来源模块（log target）
+    pub target: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// 按最低级别和模块前缀过滤
+    pub fn matches(&self, min_level: Level, module: Option<&str>) -> bool {
+        let level_ok = self
+            .level
+            .parse::<Level>()
+            .map(|l| l <= min_level)
+            .unwrap_or(true);
+        level_ok && module.map(|m| self.target.starts_with(m)).unwrap_or(true)
+    }
+}
+
+/// 最近日志的环形缓冲区，同时向实时订阅者广播新日志
+pub struct LogBuffer {
+    entries: Mutex<VecDeque<LogEntry>>,
+    sender: broadcast::Sender<LogEntry>,
+    next_seq: AtomicU64,
+}
+
+impl LogBuffer {
+    fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            sender,
+            next_seq: AtomicU64::new(1),
+        }
+    }
+
+    fn push(&self, record: &Record) {
+        let entry = LogEntry {
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if entries.len() >= BUFFER_CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.sender.send(entry);
+    }
+
+    /// 获取缓冲区中的日志快照（按时间顺序）
+    pub fn snapshot(&self) -> Vec<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+
+    /// 订阅新日志
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.sender.subscribe()
+    }
+}
+
+/// 全局日志缓冲区
+pub static LOG_BUFFER: Lazy<LogBuffer> = Lazy::new(|| LogBuffer::new(BUFFER_CAPACITY));
+
+/// 包装 env_logger：正常输出的同时写入内存缓冲区
+///
+/// 全局 logger 只能设置一次，内部的 env_logger 可以替换（启动时先用引导配置，读取配置后替换）
+struct BufferedLogger {
+    inner: RwLock<Option<env_logger::Logger>>,
+}
+
+impl Log for BufferedLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        inner.as_ref().is_some_and(|l| l.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(logger) = inner.as_ref().filter(|l| l.matches(record)) {
+            logger.log(record);
+            LOG_BUFFER.push(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = self.inner.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            logger.flush();
+        }
+    }
+}
+
+static LOGGER: BufferedLogger = BufferedLogger {
+    inner: RwLock::new(None),
+};
+
+/// 安装日志系统（替代 env_logger 的 init）；再次调用时替换之前的配置
+pub fn init(inner: env_logger::Logger) {
+    let max_level = inner.filter();
+    *LOGGER.inner.write().unwrap_or_else(|e| e.into_inner()) = Some(inner);
+
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        if let Err(e) = log::set_logger(&LOGGER) {
+            eprintln!("Failed to install logger: {}", e);
+        }
+    });
+    log::set_max_level(max_level);
+}
@@ -0,0 +1,199 @@
+use crate::config::settings::{LogConfig, LogFileConfig};
+use crate::utils::log_buffer;
+use chrono::Local;
+use log::LevelFilter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// 初始化日志系统
+///
+/// 级别优先级：RUST_LOG 环境变量 > [log.modules] > [log].level。
+/// 开启文件输出时返回后台写线程的 guard，需要在 main 中持有到退出，否则缓冲中的日志会丢失
+pub fn init(config: &LogConfig) -> Option<WorkerGuard> {
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(parse_level(&config.level).unwrap_or(LevelFilter::Info))
+        .format_timestamp_millis();
+
+    for (module, level) in &config.modules {
+        match parse_level(level) {
+            Some(level) => {
+                builder.filter_module(module, level);
+            }
+            None => eprintln!("Invalid log level for module {}: {}", module, level),
+        }
+    }
+    builder.parse_env(env_logger::Env::default());
+
+    let mut guard = None;
+    if config.file.enabled {
+        match open_file_writer(&config.file) {
+            Ok((writer, g)) => {
+                builder.target(env_logger::Target::Pipe(Box::new(TeeWriter { file: writer })));
+                guard = Some(g);
+            }
+            Err(e) => eprintln!("Failed to open log file in {}: {}", config.file.dir, e),
+        }
+    }
+
+    // 同时写入内存缓冲区，供 /api/logs/stream 查看
+    log_buffer::init(builder.build());
+    guard
+}
+
+/// 读取配置前的引导日志：info 级别（可由 RUST_LOG 覆盖）输出到标准错误，
+/// 保证加载配置期间的日志不丢失；`init` 按配置替换
+pub fn bootstrap() {
+    let logger = env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .format_timestamp_millis()
+        .target(env_logger::Target::Stderr)
+        .parse_env(env_logger::Env::default())
+        .build();
+    log_buffer::init(logger);
+}
+
+fn parse_level(s: &str) -> Option<LevelFilter> {
+    s.trim().parse().ok()
+}
+
+/// 打开日志文件写入器（通过后台线程写入，不阻塞请求处理）
+fn open_file_writer(
+    config: &LogFileConfig,
+) -> io::Result<(tracing_appender::non_blocking::NonBlocking, WorkerGuard)> {
+    fs::create_dir_all(&config.dir)?;
+
+    let rotation = match config.rotation.as_str() {
+        "size" => {
+            let writer = SizeRotatingWriter::new(config)?;
+            return Ok(tracing_appender::non_blocking(writer));
+        }
+        "hourly" => Rotation::HOURLY,
+        "never" => Rotation::NEVER,
+        "daily" => Rotation::DAILY,
+        other => {
+            eprintln!("Unknown log rotation {}, falling back to daily", other);
+            Rotation::DAILY
+        }
+    };
+
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&config.prefix)
+        .filename_suffix("log")
+        .max_log_files(config.max_files.max(1))
+        .build(&config.dir)
+        .map_err(io::Error::other)?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// 同时写入标准输出和日志文件
+struct TeeWriter<W: Write> {
+    file: W,
+}
+
+impl<W: Write> Write for TeeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 文件写入失败不影响标准输出
+        let _ = self.file.write_all(buf);
+        io::stdout().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = self.file.flush();
+        io::stdout().flush()
+    }
+}
+
+/// 按大小轮转的日志文件
+///
+/// 当前文件为 `<prefix>.log`，超过上限后重命名为 `<prefix>.<时间戳>.log`，并删除超出保留数量的旧文件
+struct SizeRotatingWriter {
+    dir: PathBuf,
+    prefix: String,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingWriter {
+    fn new(config: &LogFileConfig) -> io::Result<Self> {
+        let dir = PathBuf::from(&config.dir);
+        let path = dir.join(format!("{}.log", config.prefix));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            dir,
+            prefix: config.prefix.clone(),
+            max_bytes: config.max_size_mb.max(1) * 1024 * 1024,
+            max_files: config.max_files.max(1),
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let current = self.dir.join(format!("{}.log", self.prefix));
+        let archived = self.dir.join(format!(
+            "{}.{}.log",
+            self.prefix,
+            Local::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+        ));
+        fs::rename(&current, &archived)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&current)?;
+        self.written = 0;
+        self.cleanup();
+        Ok(())
+    }
+
+    /// 删除超出保留数量的归档文件（当前文件也计入数量）
+    fn cleanup(&self) {
+        let archive_prefix = format!("{}.", self.prefix);
+        let current = format!("{}.log", self.prefix);
+        let mut archives: Vec<PathBuf> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.starts_with(&archive_prefix) && n.ends_with(".log") && n != current)
+                        .unwrap_or(false)
+                })
+                .collect(),
+            Err(_) => return,
+        };
+
+        // 归档文件名中的时间戳可直接按字典序排序
+        archives.sort();
+        let keep = self.max_files.saturating_sub(1);
+        if archives.len() > keep {
+            for old in &archives[..archives.len() - keep] {
+                let _ = fs::remove_file(old);
+            }
+        }
+    }
+}
+
+impl Write for SizeRotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            if let Err(e) = self.rotate() {
+                eprintln!("Failed to rotate log file: {}", e);
+            }
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,219 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+/// 解析后的邮件（只保留收信处理需要的字段）
+#[derive(Debug, Clone, Default)]
+pub struct ParsedMail {
+    /// 发件人地址（小写）
+    pub from: Option<String>,
+    pub subject: String,
+    /// 第一个 text/plain 部分的正文
+    pub text: String,
+    /// 自动回复、退信等（Auto-Submitted 不为 no）
+    pub auto_submitted: bool,
+}
+
+/// 解析 RFC 5322 邮件：解码头部的 RFC 2047 编码字，取 multipart 中的第一个纯文本部分
+pub fn parse(raw: &[u8]) -> ParsedMail {
+    let raw = String::from_utf8_lossy(raw);
+    let (headers, body) = split_message(&raw);
+    let from = header(&headers, "from").and_then(|v| extract_address(&decode_words(v)));
+    let subject = header(&headers, "subject").map(decode_words).unwrap_or_default();
+    let auto_submitted = header(&headers, "auto-submitted").is_some_and(|v| !v.trim().eq_ignore_ascii_case("no"));
+    ParsedMail {
+        from,
+        subject: subject.trim().to_string(),
+        text: text_part(&headers, body).unwrap_or_default(),
+        auto_submitted,
+    }
+}
+
+// 拆分头部和正文，头部的折行合并为一行
+fn split_message(raw: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = raw
+        .split_once("\r\n\r\n")
+        .or_else(|| raw.split_once("\n\n"))
+        .unwrap_or((raw, ""));
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+// 头部参数，如 Content-Type 的 boundary、charset
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|part| {
+        let (key, val) = part.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| val.trim().trim_matches('"').to_string())
+    })
+}
+
+/// "名字 <a@b.c>" 或 "a@b.c" 中的地址
+fn extract_address(value: &str) -> Option<String> {
+    let address = match (value.rfind('<'), value.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    };
+    let address = address.trim().to_ascii_lowercase();
+    address.contains('@').then_some(address)
+}
+
+// 找到第一个 text/plain 部分并解码
+fn text_part(headers: &[(String, String)], body: &str) -> Option<String> {
+    let content_type = header(headers, "content-type").unwrap_or("text/plain");
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+
+    if mime.starts_with("multipart/") {
+        let boundary = header_param(content_type, "boundary")?;
+        let delimiter = format!("--{}", boundary);
+        return body
+            .split(delimiter.as_str())
+            .skip(1)
+            .take_while(|part| !part.starts_with("--"))
+            .find_map(|part| {
+                let (part_headers, part_body) = split_message(part.trim_start_matches(['\r', '\n']));
+                text_part(&part_headers, part_body)
+            });
+    }
+    if mime != "text/plain" {
+        return None;
+    }
+
+    let encoding = header(headers, "content-transfer-encoding").unwrap_or("7bit").trim().to_ascii_lowercase();
+    let bytes = match encoding.as_str() {
+        "base64" => BASE64
+            .decode(body.chars().filter(|c| !c.is_whitespace()).collect::<String>())
+            .unwrap_or_default(),
+        "quoted-printable" => decode_quoted_printable(body),
+        _ => body.as_bytes().to_vec(),
+    };
+    Some(String::from_utf8_lossy(&bytes).replace("\r\n", "\n").trim().to_string())
+}
+
+fn decode_quoted_printable(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'=' {
+            // 软换行
+            if bytes[i + 1..].starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            }
+            if bytes[i + 1..].starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            if let Some(b) = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// 解码 RFC 2047 编码字（=?utf-8?B?...?= / =?utf-8?Q?...?=），只支持 UTF-8 和 ASCII 兼容的字符集
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut previous_encoded = false;
+    while let Some(start) = rest.find("=?") {
+        let Some((charset_and_encoding, text_end)) = encoded_word(&rest[start + 2..]) else {
+            break;
+        };
+        let between = &rest[..start];
+        // 相邻编码字之间的空白不保留
+        if !(previous_encoded && between.trim().is_empty()) {
+            out.push_str(between);
+        }
+        let (encoding, text) = charset_and_encoding;
+        let bytes = if encoding.eq_ignore_ascii_case("b") {
+            BASE64.decode(text).unwrap_or_default()
+        } else {
+            decode_quoted_printable(&text.replace('_', " "))
+        };
+        out.push_str(&String::from_utf8_lossy(&bytes));
+        rest = &rest[start + 2 + text_end..];
+        previous_encoded = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+// 解析 "charset?E?text?=" ，返回 ((编码, 内容), 编码字结束位置)
+fn encoded_word(s: &str) -> Option<((&str, &str), usize)> {
+    let mut parts = s.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let remainder = parts.next()?;
+    let end = remainder.find("?=")?;
+    if charset.is_empty() || !matches!(encoding, "B" | "b" | "Q" | "q") {
+        return None;
+    }
+    let consumed = charset.len() + 1 + encoding.len() + 1 + end + 2;
+    Some(((encoding, &remainder[..end]), consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multipart() {
+        let raw = concat!(
+            "From: =?UTF-8?B?5byg5LiJ?= <Zhang@Example.com>\r\n",
+            "Subject: =?utf-8?Q?=E5=8F=8B=E9=93=BE?= apply\r\n",
+            "Content-Type: multipart/alternative;\r\n boundary=\"b1\"\r\n",
+            "\r\n",
+            "--b1\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "name: =E7=A4=BA=E4=BE=8B\r\n",
+            "url: https://example.com/very-long-=\r\npath\r\n",
+            "--b1\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>ignored</p>\r\n",
+            "--b1--\r\n",
+        );
+        let mail = parse(raw.as_bytes());
+        assert_eq!(mail.from.as_deref(), Some("zhang@example.com"));
+        assert_eq!(mail.subject, "友链 apply");
+        assert_eq!(mail.text, "name: 示例\nurl: https://example.com/very-long-path");
+        assert!(!mail.auto_submitted);
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        let raw = "From: a@b.c\nSubject: hi\nAuto-Submitted: auto-replied\nContent-Transfer-Encoding: base64\n\naGVs\nbG8=\n";
+        let mail = parse(raw.as_bytes());
+        assert_eq!(mail.from.as_deref(), Some("a@b.c"));
+        assert_eq!(mail.text, "hello");
+        assert!(mail.auto_submitted);
+        assert_eq!(decode_words("=?utf-8?B?5Y+L?= =?utf-8?B?6ZO+?="), "友链");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::services::maintenance_service::{self, MaintenanceState};
+use crate::utils::auth::AdminAuth;
+use crate::utils::errors;
+use crate::utils::negotiate;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Method, Status};
+use rocket::request::FromRequest;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::{get, routes, Data, Request, Route};
+use rocket_dyn_templates::{context, Template};
+use serde_json::json;
+
+/// 维护期间被拦截的请求会被改写到此路径，由 maintenance 路由返回 503
+const MAINTENANCE_PATH: &str = "/__maintenance";
+
+/// 维护模式 fairing：开启时将 allow_paths 以外的请求改写到维护页面（携带有效管理员令牌或登录会话的请求除外）
+pub struct MaintenanceFairing;
+
+#[rocket::async_trait]
+impl Fairing for MaintenanceFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Maintenance mode",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        if maintenance_service::blocks(req.uri().path().as_str()) && !is_admin(req).await {
+            req.set_method(Method::Get);
+            req.set_uri(Origin::parse(MAINTENANCE_PATH).expect("hardcoded URI is valid"));
+        }
+    }
+}
+
+/// 管理员在维护期间仍可访问所有接口（上传壁纸、缓存管理等）
+async fn is_admin(req: &Request<'_>) -> bool {
+    AdminAuth::from_request(req).await.is_success()
+}
+
+/// 维护页面：浏览器返回 HTML，其他客户端返回与错误响应格式一致的 JSON
+pub struct MaintenanceResponse(MaintenanceState);
+
+impl<'r> Responder<'r, 'static> for MaintenanceResponse {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let state = self.0;
+        let mut response = if negotiate::wants_html(req) {
+            let template = Template::render(
+                "maintenance",
+                context! {
+                    message: &state.message,
+                    retry_after_mins: state.retry_after_secs.div_ceil(60),
+                },
+            );
+            (Status::ServiceUnavailable, template).respond_to(req)?
+        } else {
+            let body = json!({
+                "code": "503",
+                "message": &state.message,
+                "status": "failed",
+                "data": { "maintenance": true, "retry_after_secs": state.retry_after_secs },
+            });
+            (Status::ServiceUnavailable, Json(body)).respond_to(req)?
+        };
+        response.set_raw_header("Retry-After", state.retry_after_secs.to_string());
+        response.set_raw_header("Cache-Control", "no-store");
+        response.set_raw_header("X-Request-Id", errors::request_id(req));
+        Ok(response)
+    }
+}
+
+#[get("/__maintenance")]
+fn maintenance() -> MaintenanceResponse {
+    MaintenanceResponse(maintenance_service::current())
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![maintenance]
+}
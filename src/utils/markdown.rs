@@ -0,0 +1,105 @@
+use moka::future::Cache;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// 允许渲染的最大 Markdown 长度（字节）
+pub const MAX_MARKDOWN_LEN: usize = 20 * 1024;
+
+// 渲染结果缓存（key 为原文 SHA-256）
+static RENDER_CACHE: Lazy<Cache<String, String>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(4 * 1024 * 1024)
+        .weigher(|_key, value: &String| value.len().min(u32::MAX as usize) as u32)
+        .time_to_idle(Duration::from_secs(60 * 60))
+        .build()
+});
+
+/// 允许的链接 / 图片协议（相对地址和锚点也允许）
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.trim().to_ascii_lowercase();
+    match lower.split_once(':') {
+        // 冒号出现在路径 / 查询 / 锚点中时不是协议
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            matches!(scheme, "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// 将 Markdown 渲染为安全的 HTML
+///
+/// 原始 HTML 一律按文本转义输出（不做白名单过滤），javascript: / data: 等协议的链接和图片会被移除地址
+pub fn render(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        other => other,
+    });
+
+    let mut output = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut output, events);
+    output
+}
+
+fn sanitize_url(url: CowStr<'_>) -> CowStr<'_> {
+    if is_safe_url(&url) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+/// 渲染并缓存结果
+pub async fn render_cached(markdown: &str) -> String {
+    let key = hex::encode(Sha256::digest(markdown.as_bytes()));
+    if let Some(html) = RENDER_CACHE.get(&key).await {
+        return html;
+    }
+    let html = render(markdown);
+    RENDER_CACHE.insert(key, html.clone()).await;
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_basic() {
+        let html = render("# Title\n\n**bold** and [link](https://example.com)");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains(r#"<a href="https://example.com">link</a>"#));
+    }
+
+    #[test]
+    fn test_render_sanitizes() {
+        let html = render("<script>alert(1)</script>\n\nhi <img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("&lt;script&gt;"));
+
+        let html = render("[x](javascript:alert(1)) ![y](data:image/png;base64,AAAA) [z](/about#a:b)");
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains(r#"href="/about#a:b""#));
+    }
+}
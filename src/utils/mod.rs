@@ -1,6 +1,35 @@
+pub mod auth;
+pub mod badge;
 pub mod cache;
 pub mod charset;
+pub mod client_hints;
+pub mod compression;
+pub mod crypto;
 pub mod custom_response;
+pub mod error_tracker;
 pub mod errors;
+pub mod etag;
+pub mod feature_gate;
+pub mod idempotency;
+pub mod ip_filter;
 pub mod jemalloc_interface;
+pub mod log_buffer;
+pub mod logging;
+pub mod mail;
+pub mod maintenance;
+pub mod markdown;
+pub mod music_card;
+pub mod negotiate;
+pub mod redis;
+pub mod request_context;
+pub mod request_counter;
 pub mod response;
+pub mod rng;
+pub mod robots_tag;
+pub mod signed_url;
+pub mod single_flight;
+pub mod slow_requests;
+pub mod transcode_limiter;
+pub mod upload;
+pub mod url;
+pub mod validation;
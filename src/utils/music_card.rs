@@ -0,0 +1,133 @@
+// 正在播放卡片 SVG（GitHub 主页等不执行脚本、不加载外部资源的场景使用，封面以 data URI 内嵌）
+
+use crate::utils::badge;
+use crate::{Error, Result};
+use base64::Engine;
+use image::imageops::FilterType;
+use image::ImageFormat;
+use std::io::Cursor;
+
+const WIDTH: u32 = 360;
+const HEIGHT: u32 = 96;
+/// 封面边长及与卡片边缘的距离
+const COVER_SIZE: u32 = 64;
+const PADDING: u32 = 16;
+/// 文字区域起点和宽度
+const TEXT_X: u32 = PADDING * 2 + COVER_SIZE;
+const TEXT_WIDTH: u32 = WIDTH - TEXT_X - PADDING;
+const TITLE_FONT_SIZE: f32 = 15.0;
+const ARTIST_FONT_SIZE: f32 = 12.0;
+/// 内嵌封面的边长（2 倍图）
+pub const THUMBNAIL_SIZE: u32 = COVER_SIZE * 2;
+
+/// 卡片内容
+pub struct MusicCard<'a> {
+    pub title: &'a str,
+    pub artist: &'a str,
+    /// 封面 data URI，为空时显示占位色块
+    pub cover: Option<&'a str>,
+    /// 播放进度（0.0-1.0），为空时不显示进度条
+    pub progress: Option<f32>,
+}
+
+/// 估算文本宽度（按 Verdana 11px 的字宽缩放，粗体加宽约 10%）
+fn text_width(text: &str, font_size: f32, bold: bool) -> f32 {
+    let width = badge::text_width(text) * font_size / 11.0;
+    if bold {
+        width * 1.1
+    } else {
+        width
+    }
+}
+
+/// 超出宽度的文本截断并加省略号
+fn truncate(text: &str, max_width: f32, font_size: f32, bold: bool) -> String {
+    if text_width(text, font_size, bold) <= max_width {
+        return text.to_string();
+    }
+    let ellipsis = text_width("…", font_size, bold);
+    let mut out = String::new();
+    for c in text.chars() {
+        out.push(c);
+        if text_width(&out, font_size, bold) + ellipsis > max_width {
+            out.pop();
+            break;
+        }
+    }
+    format!("{}…", out.trim_end())
+}
+
+/// 阻塞式处理（在 spawn_blocking 中调用）：封面缩小为 THUMBNAIL_SIZE 的正方形 JPEG，返回 data URI
+pub fn thumbnail_data_uri(bytes: &[u8]) -> Result<String> {
+    let img = image::load_from_memory(bytes)
+        .map_err(|e| Error::BadRequest(format!("Failed to decode image: {}", e)))?;
+    let thumbnail = img.resize_to_fill(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle).to_rgb8();
+    let mut output = Vec::new();
+    thumbnail
+        .write_to(&mut Cursor::new(&mut output), ImageFormat::Jpeg)
+        .map_err(|e| Error::Internal(format!("Failed to encode thumbnail: {}", e)))?;
+    Ok(format!(
+        "data:image/jpeg;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(output)
+    ))
+}
+
+/// 生成卡片 SVG
+pub fn render(card: &MusicCard) -> String {
+    let label = badge::escape_xml(&format!("{} - {}", card.title, card.artist));
+    let title = badge::escape_xml(&truncate(card.title, TEXT_WIDTH as f32, TITLE_FONT_SIZE, true));
+    let artist = badge::escape_xml(&truncate(card.artist, TEXT_WIDTH as f32, ARTIST_FONT_SIZE, false));
+
+    let cover = match card.cover {
+        // data URI 只含 base64 字符，无需转义
+        Some(uri) => format!(
+            r#"<image x="{PADDING}" y="{PADDING}" width="{COVER_SIZE}" height="{COVER_SIZE}" href="{uri}" clip-path="url(#c)" preserveAspectRatio="xMidYMid slice"/>"#
+        ),
+        None => format!(r#"<rect x="{PADDING}" y="{PADDING}" width="{COVER_SIZE}" height="{COVER_SIZE}" rx="8" class="p"/>"#),
+    };
+    let progress = match card.progress {
+        Some(progress) => {
+            let filled = (progress.clamp(0.0, 1.0) * TEXT_WIDTH as f32).round() as u32;
+            format!(
+                r##"<rect x="{TEXT_X}" y="70" width="{TEXT_WIDTH}" height="4" rx="2" class="p"/><rect x="{TEXT_X}" y="70" width="{filled}" height="4" rx="2" fill="#1e90ff"/>"##
+            )
+        }
+        None => String::new(),
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" role="img" aria-label="{label}"><title>{label}</title><style>text{{font-family:system-ui,-apple-system,"Segoe UI","PingFang SC","Microsoft YaHei",sans-serif}}.b{{fill:#f5f5f7}}.p{{fill:#d2d2d7}}.t{{fill:#1d1d1f}}.s{{fill:#6e6e73}}@media (prefers-color-scheme:dark){{.b{{fill:#2d2d2f}}.p{{fill:#48484a}}.t{{fill:#f5f5f7}}.s{{fill:#a1a1a6}}}}</style><clipPath id="c"><rect x="{PADDING}" y="{PADDING}" width="{COVER_SIZE}" height="{COVER_SIZE}" rx="8"/></clipPath><rect width="{WIDTH}" height="{HEIGHT}" rx="12" class="b"/>{cover}<text x="{TEXT_X}" y="38" font-size="{TITLE_FONT_SIZE}" font-weight="600" class="t">{title}</text><text x="{TEXT_X}" y="58" font-size="{ARTIST_FONT_SIZE}" class="s">{artist}</text>{progress}</svg>"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbImage};
+
+    #[test]
+    fn test_render() {
+        let svg = render(&MusicCard {
+            title: "孤勇者",
+            artist: "陈奕迅 & <b>",
+            cover: None,
+            progress: Some(0.5),
+        });
+        assert!(svg.contains(">孤勇者</text>"));
+        assert!(svg.contains("陈奕迅 &amp; &lt;b&gt;"));
+        assert!(svg.contains(&format!(r#"width="{}" height="4" rx="2" fill"#, TEXT_WIDTH / 2)));
+
+        let long = "很长的歌名".repeat(10);
+        let truncated = truncate(&long, TEXT_WIDTH as f32, TITLE_FONT_SIZE, true);
+        assert!(truncated.ends_with('…'));
+        assert!(text_width(&truncated, TITLE_FONT_SIZE, true) <= TEXT_WIDTH as f32);
+        assert_eq!(truncate("short", TEXT_WIDTH as f32, TITLE_FONT_SIZE, true), "short");
+
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(300, 200, image::Rgb([200, 10, 10])));
+        let mut png = Vec::new();
+        img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        let uri = thumbnail_data_uri(&png).unwrap();
+        assert!(uri.starts_with("data:image/jpeg;base64,"));
+        assert!(thumbnail_data_uri(b"not an image").is_err());
+    }
+}
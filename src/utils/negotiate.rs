@@ -0,0 +1,114 @@
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder};
+use rocket_dyn_templates::Template;
+use std::convert::Infallible;
+
+/// 按 Accept 协商内容的响应需要的 Vary 值
+pub const VARY_ACCEPT: &str = "Accept";
+
+/// Accept 中明确列出的媒体类型的质量值（q=，默认 1）；未列出时返回 None
+///
+/// 只按完整的媒体类型匹配：image/* 和 */* 不算接受 AVIF / WebP 等可选格式（旧客户端普遍发送 */*）
+pub fn quality(accept: &str, media_type: &str) -> Option<f32> {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let name = parts.next()?;
+            if !name.eq_ignore_ascii_case(media_type) {
+                return None;
+            }
+            let q = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+                .map_or(Some(1.0), |(_, value)| value.trim().parse::<f32>().ok())?;
+            Some(q.clamp(0.0, 1.0))
+        })
+        // 同一类型重复出现时取最高值
+        .reduce(f32::max)
+}
+
+/// 从候选媒体类型中选出质量值最高的一个，相同时按候选顺序（服务端偏好）；都未列出或 q=0 时返回 None
+pub fn preferred<'a>(accept: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .filter_map(|&candidate| Some((candidate, quality(accept, candidate)?)))
+        .filter(|(_, q)| *q > 0.0)
+        // 质量值相同时保留靠前的候选
+        .fold(None, |best: Option<(&str, f32)>, (candidate, q)| match best {
+            Some((_, best_q)) if best_q >= q => best,
+            _ => Some((candidate, q)),
+        })
+        .map(|(candidate, _)| candidate)
+}
+
+/// 请求是否接受 HTML（浏览器直接访问或 iframe 嵌入；curl 等默认的 */* 不算）
+pub fn wants_html(req: &Request<'_>) -> bool {
+    req.headers()
+        .get_one("Accept")
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// 请求守卫：Accept 中是否包含 text/html
+pub struct AcceptsHtml(pub bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptsHtml {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptsHtml(wants_html(req)))
+    }
+}
+
+/// 按 Accept 协商的响应：HTML 页面或原有的数据响应，两者都带 Vary: Accept，避免缓存混用
+pub enum Negotiated<T> {
+    Html(Box<Template>),
+    Data(T),
+}
+
+impl<T> Negotiated<T> {
+    pub fn html(template: Template) -> Self {
+        Negotiated::Html(Box::new(template))
+    }
+}
+
+impl<'r, T: Responder<'r, 'static>> Responder<'r, 'static> for Negotiated<T> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = match self {
+            Negotiated::Html(template) => {
+                let mut response = template.respond_to(req)?;
+                response.set_raw_header("Cache-Control", "no-cache");
+                response
+            }
+            Negotiated::Data(data) => data.respond_to(req)?,
+        };
+        response.adjoin_raw_header("Vary", VARY_ACCEPT);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred() {
+        let images = ["image/avif", "image/webp"];
+        // Chrome / Safari
+        assert_eq!(preferred("image/avif,image/webp,image/apng,*/*;q=0.8", &images), Some("image/avif"));
+        assert_eq!(preferred("image/webp,image/avif,image/jxl,*/*;q=0.8", &images), Some("image/avif"));
+        // 质量值：明确降低或拒绝 AVIF
+        assert_eq!(preferred("image/avif;q=0.5, image/webp", &images), Some("image/webp"));
+        assert_eq!(preferred("image/avif; q=0, image/webp;q=0.1", &images), Some("image/webp"));
+        assert_eq!(preferred("image/avif;q=0", &images), None);
+        // 通配符不选中可选格式
+        assert_eq!(preferred("*/*", &images), None);
+        assert_eq!(preferred("image/*", &images), None);
+        assert_eq!(preferred("", &images), None);
+
+        assert_eq!(quality("IMAGE/WEBP;Q=0.7", "image/webp"), Some(0.7));
+        assert_eq!(quality("image/webp;q=abc", "image/webp"), None);
+        assert_eq!(quality("image/webp;level=1", "image/webp"), Some(1.0));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{Error, Result};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+/// 单条命令（含建立连接）的超时时间
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+/// 保留的空闲连接数
+const MAX_IDLE: usize = 8;
+/// 单个回复的大小上限
+const MAX_BULK_BYTES: usize = 64 * 1024 * 1024;
+
+/// Redis 回复（RESP2）
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    Nil,
+    Status(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Reply::Bulk(data) => Some(data),
+            Reply::Status(s) => Some(s.into_bytes()),
+            _ => None,
+        }
+    }
+}
+
+/// 最小的 Redis 客户端（RESP2，按需建立连接并复用空闲连接）
+pub struct RedisClient {
+    addr: String,
+    username: Option<String>,
+    password: Option<String>,
+    db: u32,
+    idle: Mutex<Vec<BufReader<TcpStream>>>,
+}
+
+impl RedisClient {
+    /// 解析 `redis://[[用户名]:密码@]主机[:端口][/库编号]`
+    pub fn from_url(url: &str) -> Result<Self> {
+        let parsed = url::Url::parse(url).map_err(|e| Error::BadRequest(format!("Invalid Redis URL: {}", e)))?;
+        if parsed.scheme() != "redis" {
+            return Err(Error::BadRequest(format!("Unsupported Redis URL scheme: {}", parsed.scheme())));
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| Error::BadRequest("Redis URL has no host".into()))?;
+        let decode = |s: &str| urlencoding::decode(s).map(|s| s.into_owned()).unwrap_or_else(|_| s.to_string());
+        let db = match parsed.path().trim_start_matches('/') {
+            "" => 0,
+            db => db
+                .parse()
+                .map_err(|_| Error::BadRequest(format!("Invalid Redis database: {}", db)))?,
+        };
+        Ok(Self {
+            addr: format!("{}:{}", host, parsed.port().unwrap_or(6379)),
+            username: Some(parsed.username()).filter(|u| !u.is_empty()).map(decode),
+            password: parsed.password().map(decode),
+            db,
+            idle: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 执行一条命令；连接出错时丢弃该连接，下次重新建立
+    pub async fn command(&self, args: &[&[u8]]) -> Result<Reply> {
+        let pooled = self.idle.lock().await.pop();
+        let mut conn = match pooled {
+            Some(conn) => conn,
+            None => self.connect().await?,
+        };
+        let reply = tokio::time::timeout(COMMAND_TIMEOUT, execute(&mut conn, args))
+            .await
+            .map_err(|_| Error::Internal("Redis command timed out".into()))?;
+        match reply {
+            // 错误回复（如类型错误）不影响连接本身
+            Ok(_) | Err(Error::BadRequest(_)) => {
+                let mut idle = self.idle.lock().await;
+                if idle.len() < MAX_IDLE {
+                    idle.push(conn);
+                }
+            }
+            Err(_) => {}
+        }
+        reply
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>> {
+        let stream = tokio::time::timeout(COMMAND_TIMEOUT, TcpStream::connect(&self.addr))
+            .await
+            .map_err(|_| Error::Internal("Redis connect timed out".into()))?
+            .map_err(|e| Error::Internal(format!("Redis connect failed: {}", e)))?;
+        let mut conn = BufReader::new(stream);
+        let setup = async {
+            match (&self.username, &self.password) {
+                (Some(user), Some(password)) => {
+                    execute(&mut conn, &[b"AUTH", user.as_bytes(), password.as_bytes()]).await?;
+                }
+                (None, Some(password)) => {
+                    execute(&mut conn, &[b"AUTH", password.as_bytes()]).await?;
+                }
+                _ => {}
+            }
+            if self.db != 0 {
+                execute(&mut conn, &[b"SELECT", self.db.to_string().as_bytes()]).await?;
+            }
+            Ok::<_, Error>(())
+        };
+        tokio::time::timeout(COMMAND_TIMEOUT, setup)
+            .await
+            .map_err(|_| Error::Internal("Redis handshake timed out".into()))??;
+        Ok(conn)
+    }
+}
+
+async fn execute(conn: &mut BufReader<TcpStream>, args: &[&[u8]]) -> Result<Reply> {
+    conn.get_mut()
+        .write_all(&encode(args))
+        .await
+        .map_err(|e| Error::Internal(format!("Redis write failed: {}", e)))?;
+    read_reply(conn).await
+}
+
+/// 编码为 RESP 数组
+fn encode(args: &[&[u8]]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+/// 读取一个回复；Redis 返回的错误回复转换为 BadRequest
+fn read_reply<R>(reader: &mut R) -> Pin<Box<dyn Future<Output = Result<Reply>> + Send + '_>>
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    Box::pin(async move {
+        let io_error = |e: std::io::Error| Error::Internal(format!("Redis read failed: {}", e));
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.map_err(io_error)? == 0 {
+            return Err(Error::Internal("Redis connection closed".into()));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (kind, rest) = line.split_at(line.len().min(1));
+        let number = || {
+            rest.parse::<i64>()
+                .map_err(|_| Error::Internal(format!("Invalid Redis reply: {}", line)))
+        };
+        match kind {
+            "+" => Ok(Reply::Status(rest.to_string())),
+            "-" => Err(Error::BadRequest(format!("Redis error: {}", rest))),
+            ":" => Ok(Reply::Integer(number()?)),
+            "$" => {
+                let len = number()?;
+                if len < 0 {
+                    return Ok(Reply::Nil);
+                }
+                let len = len as usize;
+                if len > MAX_BULK_BYTES {
+                    return Err(Error::Internal(format!("Redis reply too large: {} bytes", len)));
+                }
+                let mut data = vec![0; len + 2];
+                reader.read_exact(&mut data).await.map_err(io_error)?;
+                data.truncate(len);
+                Ok(Reply::Bulk(data))
+            }
+            "*" => {
+                let len = number()?;
+                if len < 0 {
+                    return Ok(Reply::Nil);
+                }
+                let mut items = Vec::with_capacity(len.min(1024) as usize);
+                for _ in 0..len {
+                    items.push(read_reply(reader).await?);
+                }
+                Ok(Reply::Array(items))
+            }
+            _ => Err(Error::Internal(format!("Invalid Redis reply: {}", line))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resp() {
+        assert_eq!(encode(&[b"GET", b"k"]), b"*2\r\n$3\r\nGET\r\n$1\r\nk\r\n");
+
+        let mut input: &[u8] = b"*2\r\n$1\r\n0\r\n*2\r\n$4\r\na\r\nb\r\n$-1\r\n+OK\r\n:42\r\n-ERR wrong type\r\n";
+        assert_eq!(
+            read_reply(&mut input).await.unwrap(),
+            Reply::Array(vec![
+                Reply::Bulk(b"0".to_vec()),
+                Reply::Array(vec![Reply::Bulk(b"a\r\nb".to_vec()), Reply::Nil]),
+            ])
+        );
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Status("OK".into()));
+        assert_eq!(read_reply(&mut input).await.unwrap(), Reply::Integer(42));
+        assert!(matches!(read_reply(&mut input).await, Err(Error::BadRequest(_))));
+        assert!(read_reply(&mut input).await.is_err());
+
+        let client = RedisClient::from_url("redis://:p%40ss@cache.local/2").unwrap();
+        assert_eq!(client.addr, "cache.local:6379");
+        assert_eq!(client.password.as_deref(), Some("p@ss"));
+        assert_eq!(client.username, None);
+        assert_eq!(client.db, 2);
+        assert!(RedisClient::from_url("rediss://cache.local").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::config::settings::RequestsConfig;
+use crate::utils::errors;
+use crate::{Error, Result};
+use once_cell::sync::OnceCell;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+static DEADLINE: OnceCell<Option<Duration>> = OnceCell::new();
+
+/// 初始化请求截止时间（启动时调用一次；为 0 时不限制）
+pub fn init(config: &RequestsConfig) {
+    let _ = DEADLINE.set((config.deadline_ms > 0).then(|| Duration::from_millis(config.deadline_ms)));
+}
+
+fn deadline() -> Option<Duration> {
+    DEADLINE.get().copied().flatten()
+}
+
+/// 请求上下文：截止时间、请求 ID 和客户端信息
+///
+/// 路由将其传给服务，服务在下载、转码、查询数据库等耗时步骤前检查截止时间，
+/// 快要超时的请求不再开始新的处理（客户端或反向代理多半已经放弃等待）
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub request_id: String,
+    pub client_ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    deadline: Option<Instant>,
+}
+
+impl RequestContext {
+    /// 后台任务（预热、预生成等）使用的上下文，没有截止时间
+    pub fn background() -> Self {
+        Self {
+            request_id: "background".to_string(),
+            client_ip: None,
+            user_agent: None,
+            deadline: None,
+        }
+    }
+
+    /// 指定截止时间的上下文
+    pub fn with_deadline(request_id: impl Into<String>, deadline: Instant) -> Self {
+        Self {
+            request_id: request_id.into(),
+            client_ip: None,
+            user_agent: None,
+            deadline: Some(deadline),
+        }
+    }
+
+    /// 距截止时间的剩余时间（没有截止时间时为 None）
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// 已过截止时间时返回 504，`stage` 为将要开始的步骤（用于日志和错误信息）
+    pub fn check(&self, stage: &str) -> Result<()> {
+        if self.is_expired() {
+            log::warn!("Request {} passed its deadline, skipping {}", self.request_id, stage);
+            return Err(Error::Timeout(format!("Request deadline exceeded before {}", stage)));
+        }
+        Ok(())
+    }
+
+    /// 在截止时间内执行（如数据库查询），超时返回 504
+    pub async fn within<T, F>(&self, stage: &str, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.check(stage)?;
+        match self.remaining() {
+            Some(remaining) => tokio::time::timeout(remaining, fut).await.unwrap_or_else(|_| {
+                log::warn!("Request {} reached its deadline during {}", self.request_id, stage);
+                Err(Error::Timeout(format!("Request deadline exceeded during {}", stage)))
+            }),
+            None => fut.await,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestContext {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // 与慢请求采样共用请求开始时间
+        let started = *req.local_cache(Instant::now);
+        Outcome::Success(RequestContext {
+            request_id: errors::request_id(req),
+            client_ip: req.client_ip(),
+            user_agent: req.headers().get_one("User-Agent").map(str::to_string),
+            deadline: deadline().map(|d| started + d),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deadline() {
+        assert!(RequestContext::background().check("encode").is_ok());
+        assert_eq!(RequestContext::background().remaining(), None);
+
+        let expired = RequestContext::with_deadline("a", Instant::now());
+        assert!(matches!(expired.check("encode"), Err(Error::Timeout(_))));
+        assert!(matches!(expired.within("query", async { Ok(1) }).await, Err(Error::Timeout(_))));
+
+        let ctx = RequestContext::with_deadline("b", Instant::now() + Duration::from_millis(50));
+        assert_eq!(ctx.within("query", async { Ok(1) }).await.unwrap(), 1);
+        let slow = ctx.within("query", async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(2)
+        });
+        assert!(matches!(slow.await, Err(Error::Timeout(_))));
+    }
+}
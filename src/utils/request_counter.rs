@@ -0,0 +1,39 @@
+use once_cell::sync::Lazy;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Request, Response, Rocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+static TOTAL_REQUESTS: AtomicU64 = AtomicU64::new(0);
+static STARTED_AT: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// 已处理的请求总数（进程启动以来）
+pub fn total_requests() -> u64 {
+    TOTAL_REQUESTS.load(Ordering::Relaxed)
+}
+
+/// 服务运行时长（秒）
+pub fn uptime_secs() -> u64 {
+    STARTED_AT.elapsed().as_secs()
+}
+
+// 统计已处理的请求数，并在服务启动时记录启动时间
+pub struct RequestCounterFairing;
+
+#[rocket::async_trait]
+impl Fairing for RequestCounterFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request counter",
+            kind: Kind::Liftoff | Kind::Response,
+        }
+    }
+
+    async fn on_liftoff(&self, _rocket: &Rocket<Orbit>) {
+        Lazy::force(&STARTED_AT);
+    }
+
+    async fn on_response<'r>(&self, _req: &'r Request<'_>, _res: &mut Response<'r>) {
+        TOTAL_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,143 @@
+// 统一的随机数入口
+// - 普通随机（壁纸选择、UA 轮换、模拟延迟等）：默认使用线程本地 RNG，测试中可通过 seed() 固定种子
+// - 安全随机（验证码、临时代码、令牌、nonce 等）：始终使用 CSPRNG；仅在本 crate 的单元测试中遵循种子
+use rand::distr::uniform::{SampleRange, SampleUniform};
+use rand::rngs::StdRng;
+use rand::{Rng, RngExt, SeedableRng};
+use std::cell::RefCell;
+
+thread_local! {
+    static SEEDED: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// 为当前线程设置固定种子（仅用于测试，使随机结果可复现）
+pub fn seed(seed: u64) {
+    SEEDED.with(|s| *s.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+}
+
+/// 清除当前线程的固定种子
+pub fn clear_seed() {
+    SEEDED.with(|s| *s.borrow_mut() = None);
+}
+
+/// 在指定范围内生成随机数（非安全用途）
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    SEEDED.with(|s| match s.borrow_mut().as_mut() {
+        Some(rng) => rng.random_range(range),
+        None => rand::rng().random_range(range),
+    })
+}
+
+/// 从切片中随机选择一个元素（非安全用途）
+pub fn choose<T>(items: &[T]) -> Option<&T> {
+    if items.is_empty() {
+        None
+    } else {
+        items.get(random_range(0..items.len()))
+    }
+}
+
+/// 按权重随机选择下标（非安全用途），权重全为 0 或为空时返回 None
+pub fn weighted_index(weights: &[u64]) -> Option<usize> {
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let mut target = random_range(0..total);
+    weights.iter().position(|&w| {
+        if target < w {
+            true
+        } else {
+            target -= w;
+            false
+        }
+    })
+}
+
+/// 用 CSPRNG 填充缓冲区
+pub fn secure_fill(buf: &mut [u8]) {
+    #[cfg(test)]
+    {
+        let seeded = SEEDED.with(|s| match s.borrow_mut().as_mut() {
+            Some(rng) => {
+                rng.fill_bytes(buf);
+                true
+            }
+            None => false,
+        });
+        if seeded {
+            return;
+        }
+    }
+    // ThreadRng 为 ChaCha 系 CSPRNG，并定期从操作系统熵源重新播种
+    rand::rng().fill_bytes(buf);
+}
+
+/// 生成指定字节数的安全随机十六进制字符串
+pub fn secure_hex(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    secure_fill(&mut buf);
+    hex::encode(buf)
+}
+
+/// 生成指定位数的安全随机数字验证码（拒绝采样，保证每一位均匀分布）
+pub fn secure_digits(len: usize) -> String {
+    let mut code = String::with_capacity(len);
+    let mut buf = [0u8; 32];
+    while code.len() < len {
+        secure_fill(&mut buf);
+        for &b in buf.iter() {
+            // 250 = 25 * 10，丢弃 250..=255 以避免取模偏差
+            if b < 250 && code.len() < len {
+                code.push(char::from(b'0' + b % 10));
+            }
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_is_reproducible() {
+        seed(42);
+        let a: Vec<u32> = (0..8).map(|_| random_range(1..=100)).collect();
+        let code_a = secure_digits(6);
+        seed(42);
+        let b: Vec<u32> = (0..8).map(|_| random_range(1..=100)).collect();
+        let code_b = secure_digits(6);
+        clear_seed();
+
+        assert_eq!(a, b);
+        assert_eq!(code_a, code_b);
+    }
+
+    #[test]
+    fn test_weighted_index() {
+        assert_eq!(weighted_index(&[]), None);
+        assert_eq!(weighted_index(&[0, 0]), None);
+        assert_eq!(weighted_index(&[0, 5, 0]), Some(1));
+
+        seed(7);
+        let mut hits = [0u32; 2];
+        for _ in 0..1000 {
+            hits[weighted_index(&[1, 9]).unwrap()] += 1;
+        }
+        clear_seed();
+        assert!(hits[1] > hits[0] * 4, "{:?}", hits);
+    }
+
+    #[test]
+    fn test_secure_digits_format() {
+        let code = secure_digits(6);
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(secure_hex(16).len(), 32);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::config::settings::RobotsConfig;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Request, Response};
+
+// 为配置的路径添加 X-Robots-Tag 头，避免图片代理接口和监控面板被搜索引擎收录
+pub struct RobotsTagFairing {
+    value: String,
+    path_prefixes: Vec<String>,
+}
+
+impl RobotsTagFairing {
+    pub fn new(config: &RobotsConfig) -> Self {
+        Self {
+            value: config.x_robots_tag.clone(),
+            path_prefixes: config.noindex_paths.clone(),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RobotsTagFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "X-Robots-Tag for non-indexable routes",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if self.value.is_empty() {
+            return;
+        }
+        let path = req.uri().path().as_str();
+        // robots.txt 本身需要被爬虫读取
+        if path == "/robots.txt" {
+            return;
+        }
+        if self.path_prefixes.iter().any(|p| path.starts_with(p.as_str())) {
+            res.set_header(Header::new("X-Robots-Tag", self.value.clone()));
+        }
+    }
+}
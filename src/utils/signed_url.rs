@@ -0,0 +1,254 @@
+use crate::config::settings::SignedUrlConfig;
+use crate::utils::auth::AdminGuard;
+use crate::utils::rng;
+use crate::{Error, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use log::warn;
+use moka::future::Cache;
+use once_cell::sync::{Lazy, OnceCell};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use sha2::Sha256;
+use std::env;
+use std::time::Duration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+static SIGNER: OnceCell<UrlSigner> = OnceCell::new();
+
+// 已使用的一次性签名（保留到该签名的最长有效期结束）
+static USED_SIGNATURES: Lazy<Cache<String, ()>> = Lazy::new(|| {
+    Cache::builder()
+        .max_capacity(100_000)
+        .time_to_live(Duration::from_secs(signer().max_ttl_secs.max(1)))
+        .build()
+});
+
+/// 签名校验失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    Missing,
+    Expired,
+    TtlTooLong,
+    Invalid,
+}
+
+/// URL 签名器
+///
+/// 签名内容为 `路径?按字典序排序的查询参数（不含 sig）\n过期时间`，
+/// 因此签名只对该资源和这组参数有效，修改任何参数或过期时间都会导致校验失败
+pub struct UrlSigner {
+    key: Vec<u8>,
+    max_ttl_secs: u64,
+}
+
+impl UrlSigner {
+    pub fn new(key: &[u8], max_ttl_secs: u64) -> Self {
+        Self {
+            key: key.to_vec(),
+            max_ttl_secs,
+        }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        <HmacSha256 as Mac>::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+
+    /// 为 `path_and_query` 生成签名链接，`now` 为当前 unix 时间戳
+    pub fn sign(&self, path_and_query: &str, ttl_secs: u64, now: i64) -> Result<String> {
+        if !path_and_query.starts_with('/') {
+            return Err(Error::BadRequest("Path must start with '/'".to_string()));
+        }
+        if ttl_secs == 0 || ttl_secs > self.max_ttl_secs {
+            return Err(Error::BadRequest(format!(
+                "ttl_secs must be between 1 and {}",
+                self.max_ttl_secs
+            )));
+        }
+
+        let (path, query) = split_path(path_and_query);
+        let params: Vec<&str> = query_params(query)
+            .filter(|p| !matches!(param_name(p), "sig" | "exp"))
+            .collect();
+        let exp = now + ttl_secs as i64;
+        let sig = self.signature(path, &params, exp);
+
+        let mut url = path.to_string();
+        url.push('?');
+        for p in &params {
+            url.push_str(p);
+            url.push('&');
+        }
+        url.push_str(&format!("exp={}&sig={}", exp, sig));
+        Ok(url)
+    }
+
+    /// 校验签名链接，成功时返回签名（用于一次性链接去重）
+    pub fn verify(&self, path: &str, query: Option<&str>, now: i64) -> std::result::Result<String, SignatureError> {
+        let mut sig = None;
+        let mut exp = None;
+        let mut params = Vec::new();
+        for p in query_params(query.unwrap_or("")) {
+            match param_name(p) {
+                "sig" => sig = p.split_once('=').map(|(_, v)| v),
+                "exp" => exp = p.split_once('=').and_then(|(_, v)| v.parse::<i64>().ok()),
+                _ => params.push(p),
+            }
+        }
+        let (Some(sig), Some(exp)) = (sig, exp) else {
+            return Err(SignatureError::Missing);
+        };
+
+        let expected = URL_SAFE_NO_PAD.decode(sig).map_err(|_| SignatureError::Invalid)?;
+        let mut mac = self.mac();
+        mac.update(canonical(path, &params, exp).as_bytes());
+        // 先校验签名再判断时间，避免对伪造请求泄露过期信息
+        mac.verify_slice(&expected).map_err(|_| SignatureError::Invalid)?;
+
+        if exp <= now {
+            return Err(SignatureError::Expired);
+        }
+        if exp - now > self.max_ttl_secs as i64 {
+            return Err(SignatureError::TtlTooLong);
+        }
+        Ok(sig.to_string())
+    }
+
+    fn signature(&self, path: &str, params: &[&str], exp: i64) -> String {
+        let mut mac = self.mac();
+        mac.update(canonical(path, params, exp).as_bytes());
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+}
+
+fn split_path(path_and_query: &str) -> (&str, &str) {
+    path_and_query.split_once('?').unwrap_or((path_and_query, ""))
+}
+
+fn query_params(query: &str) -> impl Iterator<Item = &str> {
+    query.split('&').filter(|p| !p.is_empty())
+}
+
+fn param_name(param: &str) -> &str {
+    param.split_once('=').map(|(k, _)| k).unwrap_or(param)
+}
+
+fn canonical(path: &str, params: &[&str], exp: i64) -> String {
+    let mut sorted = params.to_vec();
+    sorted.sort_unstable();
+    format!("{}?{}\n{}", path, sorted.join("&"), exp)
+}
+
+/// 初始化全局签名器（启动时调用一次）
+pub fn init(config: &SignedUrlConfig) {
+    let key = env::var("SPACE_API_URL_SIGNING_KEY")
+        .ok()
+        .filter(|k| !k.is_empty())
+        .or_else(|| config.key.clone().filter(|k| !k.is_empty()));
+    let key = match key {
+        Some(k) => k.into_bytes(),
+        None => {
+            warn!("未配置 signed_urls.key，已随机生成（重启后已签发的链接将失效）");
+            random_key()
+        }
+    };
+    let _ = SIGNER.set(UrlSigner::new(&key, config.max_ttl_secs));
+}
+
+fn random_key() -> Vec<u8> {
+    let mut buf = vec![0u8; 32];
+    rng::secure_fill(&mut buf);
+    buf
+}
+
+/// 获取全局签名器（未初始化时使用进程内随机密钥和默认有效期）
+pub fn signer() -> &'static UrlSigner {
+    SIGNER.get_or_init(|| UrlSigner::new(&random_key(), SignedUrlConfig::default().max_ttl_secs))
+}
+
+/// 为站内路径生成签名链接
+pub fn sign(path_and_query: &str, ttl_secs: u64) -> Result<String> {
+    signer().sign(path_and_query, ttl_secs, chrono::Utc::now().timestamp())
+}
+
+/// 请求守卫：要求请求 URL 携带有效签名
+///
+/// 查询参数中带 `once=1` 的签名只能使用一次
+pub struct SignedRequest;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SignedRequest {
+    type Error = SignatureError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let uri = req.uri();
+        let query = uri.query().map(|q| q.as_str());
+        let sig = match signer().verify(uri.path().as_str(), query, chrono::Utc::now().timestamp()) {
+            Ok(sig) => sig,
+            Err(e) => return Outcome::Error((Status::Forbidden, e)),
+        };
+
+        let once = query_params(query.unwrap_or("")).any(|p| p == "once=1");
+        if once {
+            let entry = USED_SIGNATURES.entry(sig).or_insert(()).await;
+            if !entry.is_fresh() {
+                log::warn!("Rejected replayed one-time signed URL {}", uri.path());
+                return Outcome::Error((Status::Forbidden, SignatureError::Invalid));
+            }
+        }
+        Outcome::Success(SignedRequest)
+    }
+}
+
+/// 请求守卫：有效签名或管理员令牌，满足其一即可
+///
+/// 用于允许管理员直接调用、其他人需要签名链接的操作（如强制回源、任意来源 URL）；
+/// 两者都不满足时返回 403，通常以 `Option<SignedOrAdmin>` 使用
+pub struct SignedOrAdmin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SignedOrAdmin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if req.guard::<AdminGuard>().await.is_success() || req.guard::<SignedRequest>().await.is_success() {
+            Outcome::Success(SignedOrAdmin)
+        } else {
+            Outcome::Error((Status::Forbidden, ()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify() {
+        let signer = UrlSigner::new(b"test-key", 3600);
+        let url = signer.sign("/friend-avatar?url=https://a.example/x.png", 60, 1_000).unwrap();
+        let (path, query) = split_path(&url);
+
+        assert!(signer.verify(path, Some(query), 1_030).is_ok());
+        assert_eq!(signer.verify(path, Some(query), 1_060), Err(SignatureError::Expired));
+
+        let tampered = query.replace("x.png", "y.png");
+        assert_eq!(signer.verify(path, Some(&tampered), 1_030), Err(SignatureError::Invalid));
+        assert_eq!(signer.verify("/other", Some(query), 1_030), Err(SignatureError::Invalid));
+        assert_eq!(signer.verify(path, None, 1_030), Err(SignatureError::Missing));
+    }
+
+    #[test]
+    fn test_param_order_and_ttl() {
+        let signer = UrlSigner::new(b"test-key", 3600);
+        let url = signer.sign("/p?b=2&a=1", 60, 0).unwrap();
+        let (path, query) = split_path(&url);
+        let reordered: Vec<&str> = query.split('&').rev().collect();
+        assert!(signer.verify(path, Some(&reordered.join("&")), 10).is_ok());
+
+        assert!(signer.sign("/p", 7200, 0).is_err());
+        assert!(signer.sign("p", 60, 0).is_err());
+    }
+}
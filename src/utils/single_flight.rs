@@ -0,0 +1,113 @@
+use crate::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// 合并相同键的并发调用：同一时间每个键只执行一次，等待中的调用共享结果（包括错误）
+///
+/// 结果不会保留，调用结束后的新请求会重新执行
+pub struct SingleFlight<T> {
+    calls: Mutex<HashMap<String, Arc<OnceCell<Result<T>>>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 执行 `f`，已有相同键的调用在进行时等待其结果
+    ///
+    /// 执行中的调用被取消时（如客户端断开），由下一个等待者重新执行
+    pub async fn run<F, Fut>(&self, key: &str, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let call = {
+            let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            calls.entry(key.to_string()).or_default().clone()
+        };
+
+        let mut leader = false;
+        let result = call
+            .get_or_init(|| {
+                leader = true;
+                f()
+            })
+            .await
+            .clone();
+
+        if leader {
+            let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            if calls.get(key).is_some_and(|current| Arc::ptr_eq(current, &call)) {
+                calls.remove(key);
+            }
+        }
+        result
+    }
+
+    /// 进行中的调用数
+    pub fn in_flight(&self) -> usize {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        let flights = SingleFlight::<Vec<u8>>::new();
+        let calls = AtomicUsize::new(0);
+        let fetch = |value: &'static [u8]| {
+            let calls = &calls;
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(value.to_vec())
+            }
+        };
+
+        // 相同键的并发调用只执行一次，不同键各自执行
+        let (a, b, c) = tokio::join!(
+            flights.run("avatar", fetch(b"one")),
+            flights.run("avatar", fetch(b"two")),
+            flights.run("wallpaper", fetch(b"three")),
+        );
+        assert_eq!(a.unwrap(), b"one");
+        assert_eq!(b.unwrap(), b"one");
+        assert_eq!(c.unwrap(), b"three");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(flights.in_flight(), 0);
+
+        // 结束后重新执行
+        assert_eq!(flights.run("avatar", fetch(b"four")).await.unwrap(), b"four");
+
+        // 错误同样共享
+        let failing = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Err(Error::Internal("upstream down".into()))
+        };
+        let (a, b) = tokio::join!(flights.run("down", failing), flights.run("down", fetch(b"late")));
+        assert!(a.is_err() && b.is_err());
+
+        // 执行中的调用被取消后由等待者重新执行
+        let cancelled = tokio::time::timeout(Duration::from_millis(5), flights.run("cancel", fetch(b"first")));
+        assert!(cancelled.await.is_err());
+        assert_eq!(flights.run("cancel", fetch(b"retry")).await.unwrap(), b"retry");
+        assert_eq!(flights.in_flight(), 0);
+    }
+}
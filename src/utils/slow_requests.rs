@@ -0,0 +1,343 @@
+use crate::config::settings::DiagnosticsConfig;
+use crate::services::memory_service::{MemoryManager, MemoryPressure};
+use chrono::Utc;
+use once_cell::sync::{Lazy, OnceCell};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task;
+
+/// 单个请求最多记录的上游调用 / 缓存判定数（超出部分只计数）
+const MAX_EVENTS: usize = 64;
+/// 同时追踪的请求数上限（连接中断时请求任务可能被取消，不会走到 on_response）
+const MAX_ACTIVE: usize = 4096;
+/// 超过该时长仍未结束的追踪视为已丢失，追踪数达到上限时清理
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// 一次上游 HTTP 调用（耗时为收到响应头的时间）
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamTiming {
+    pub method: String,
+    pub host: String,
+    /// 请求路径（不含查询参数，避免记录令牌）
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// 请求失败时的错误类型（timeout / connect / request）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+    /// 相对请求开始的时间（毫秒）
+    pub started_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// 一次缓存查找
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheDecision {
+    /// 缓存命名空间（不属于任何命名空间时为 default）
+    pub namespace: &'static str,
+    /// memory 或 disk
+    pub layer: &'static str,
+    pub hit: bool,
+    pub at_ms: u64,
+}
+
+/// 请求结束时的内存状态（来自内存监控最近一次采样）
+#[derive(Debug, Clone, Serialize)]
+pub struct MemorySnapshot {
+    pub pressure: MemoryPressure,
+    pub current_mb: u64,
+    pub peak_mb: u64,
+}
+
+/// 一条慢请求记录
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowRequest {
+    pub method: String,
+    /// 请求路径（不含查询参数）
+    pub path: String,
+    /// 路由模板（未匹配路由时为 "<unmatched>"）
+    pub route: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    pub finished_at: String,
+    pub upstream: Vec<UpstreamTiming>,
+    pub cache: Vec<CacheDecision>,
+    /// 超出 MAX_EVENTS 未记录的事件数
+    pub dropped_events: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemorySnapshot>,
+}
+
+struct Trace {
+    started: Instant,
+    upstream: Vec<UpstreamTiming>,
+    cache: Vec<CacheDecision>,
+    dropped: u64,
+}
+
+impl Trace {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            upstream: Vec::new(),
+            cache: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+}
+
+struct Settings {
+    threshold: Duration,
+    max_records: usize,
+}
+
+static SETTINGS: OnceCell<Settings> = OnceCell::new();
+// 进行中的请求追踪：Rocket 为每个请求单独启动一个任务，按任务 ID 关联同一请求内的事件
+static ACTIVE: Lazy<Mutex<HashMap<task::Id, Trace>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static RECORDS: Lazy<Mutex<VecDeque<SlowRequest>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// 初始化慢请求采样（启动时调用一次；阈值为 0 时不追踪）
+pub fn init(config: &DiagnosticsConfig) {
+    if config.slow_request_ms > 0 && config.max_slow_requests > 0 {
+        let _ = SETTINGS.set(Settings {
+            threshold: Duration::from_millis(config.slow_request_ms),
+            max_records: config.max_slow_requests,
+        });
+    }
+}
+
+fn enabled() -> bool {
+    SETTINGS.get().is_some()
+}
+
+/// 在当前请求的追踪中记录事件（当前任务没有追踪时忽略，如后台任务）
+fn with_trace(f: impl FnOnce(&mut Trace)) {
+    if !enabled() {
+        return;
+    }
+    let Some(id) = task::try_id() else {
+        return;
+    };
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(trace) = active.get_mut(&id) {
+        f(trace);
+    }
+}
+
+/// 记录一次上游调用
+pub fn record_upstream(method: &str, url: &reqwest::Url, outcome: Result<u16, &'static str>, started: Instant) {
+    let duration = started.elapsed();
+    with_trace(|trace| {
+        if trace.upstream.len() >= MAX_EVENTS {
+            trace.dropped += 1;
+            return;
+        }
+        let (status, error) = match outcome {
+            Ok(status) => (Some(status), None),
+            Err(e) => (None, Some(e)),
+        };
+        trace.upstream.push(UpstreamTiming {
+            method: method.to_string(),
+            host: url.host_str().unwrap_or_default().to_string(),
+            path: url.path().to_string(),
+            status,
+            error,
+            started_ms: started.saturating_duration_since(trace.started).as_millis() as u64,
+            duration_ms: duration.as_millis() as u64,
+        });
+    });
+}
+
+/// 记录一次缓存查找
+pub fn record_cache(namespace: &'static str, layer: &'static str, hit: bool) {
+    with_trace(|trace| {
+        if trace.cache.len() >= MAX_EVENTS {
+            trace.dropped += 1;
+            return;
+        }
+        let at_ms = trace.elapsed_ms();
+        trace.cache.push(CacheDecision {
+            namespace,
+            layer,
+            hit,
+            at_ms,
+        });
+    });
+}
+
+fn begin(started: Instant) {
+    let Some(id) = task::try_id() else {
+        return;
+    };
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    if active.len() >= MAX_ACTIVE {
+        active.retain(|_, trace| trace.started.elapsed() < STALE_AFTER);
+        if active.len() >= MAX_ACTIVE {
+            return;
+        }
+    }
+    active.insert(id, Trace::new(started));
+}
+
+fn finish() -> Option<Trace> {
+    let id = task::try_id()?;
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).remove(&id)
+}
+
+fn push(record: SlowRequest) {
+    let Some(settings) = SETTINGS.get() else {
+        return;
+    };
+    let mut records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+    while records.len() >= settings.max_records {
+        records.pop_front();
+    }
+    records.push_back(record);
+}
+
+/// 最近的慢请求（新的在前）
+pub fn recent(limit: usize) -> Vec<SlowRequest> {
+    let records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+    records.iter().rev().take(limit).cloned().collect()
+}
+
+/// 慢请求阈值（未开启时为 None）
+pub fn threshold() -> Option<Duration> {
+    SETTINGS.get().map(|s| s.threshold)
+}
+
+// 为每个请求建立追踪，耗时超过阈值时保存诊断记录
+pub struct SlowRequestFairing;
+
+#[rocket::async_trait]
+impl Fairing for SlowRequestFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Slow request sampler",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut rocket::Data<'_>) {
+        if !enabled() {
+            return;
+        }
+        let started = *req.local_cache(Instant::now);
+        begin(started);
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let Some(threshold) = threshold() else {
+            return;
+        };
+        let trace = finish();
+        let started = *req.local_cache(Instant::now);
+        let elapsed = started.elapsed();
+        if elapsed < threshold {
+            return;
+        }
+
+        let memory = match req.rocket().state::<Arc<MemoryManager>>() {
+            Some(manager) => {
+                let state = manager.get_monitor_state().await;
+                Some(MemorySnapshot {
+                    pressure: state.pressure_level,
+                    current_mb: state.current_usage_mb,
+                    peak_mb: state.peak_usage_mb,
+                })
+            }
+            None => None,
+        };
+        let trace = trace.unwrap_or_else(|| Trace::new(started));
+        push(SlowRequest {
+            method: req.method().as_str().to_string(),
+            path: req.uri().path().to_string(),
+            route: req
+                .route()
+                .map(|r| r.uri.to_string())
+                .unwrap_or_else(|| "<unmatched>".to_string()),
+            status: res.status().code,
+            duration_ms: elapsed.as_millis() as u64,
+            finished_at: Utc::now().to_rfc3339(),
+            upstream: trace.upstream,
+            cache: trace.cache,
+            dropped_events: trace.dropped,
+            memory,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trace() {
+        init(&DiagnosticsConfig {
+            slow_request_ms: 1,
+            max_slow_requests: 2,
+        });
+
+        // 事件按任务 ID 归属到各自的请求
+        let traced = tokio::spawn(async {
+            begin(Instant::now());
+            record_cache("wallpapers", "memory", false);
+            let url = reqwest::Url::parse("https://api.example.com/v1/stats?token=secret").unwrap();
+            record_upstream("GET", &url, Ok(200), Instant::now());
+            finish().unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(traced.cache.len(), 1);
+        assert!(!traced.cache[0].hit);
+        assert_eq!(traced.upstream[0].host, "api.example.com");
+        assert_eq!(traced.upstream[0].path, "/v1/stats");
+
+        // 没有追踪的任务（后台任务）不记录
+        let untraced = tokio::spawn(async {
+            record_cache("avatars", "disk", true);
+            finish()
+        })
+        .await
+        .unwrap();
+        assert!(untraced.is_none());
+
+        let overflow = tokio::spawn(async {
+            begin(Instant::now());
+            for _ in 0..MAX_EVENTS + 3 {
+                record_cache("avatars", "memory", true);
+            }
+            finish().unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(overflow.cache.len(), MAX_EVENTS);
+        assert_eq!(overflow.dropped, 3);
+
+        // 记录数有上限，保留最新的
+        for path in ["/a", "/b", "/c"] {
+            push(SlowRequest {
+                method: "GET".into(),
+                path: path.into(),
+                route: path.into(),
+                status: 200,
+                duration_ms: 5,
+                finished_at: String::new(),
+                upstream: Vec::new(),
+                cache: Vec::new(),
+                dropped_events: 0,
+                memory: None,
+            });
+        }
+        let paths: Vec<_> = recent(10).into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/c", "/b"]);
+    }
+}
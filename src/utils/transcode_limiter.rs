@@ -0,0 +1,177 @@
+use crate::config::settings::TranscodeConfig;
+use crate::{Error, Result};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 图片解码 / 编码的并发限制
+struct Limiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    queue_timeout: Duration,
+    retry_after_secs: u64,
+    /// 因排队超时被拒绝（503）的请求数
+    rejected: AtomicU64,
+}
+
+impl Limiter {
+    fn new(config: &TranscodeConfig) -> Self {
+        let max_concurrent = config.max_concurrent.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            queue_timeout: Duration::from_millis(config.queue_timeout_ms),
+            retry_after_secs: config.retry_after_secs.max(1),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let acquire = self.semaphore.clone().acquire_owned();
+        let permit = match tokio::time::timeout(self.queue_timeout, acquire).await {
+            Ok(permit) => permit.map_err(|e| Error::Internal(e.to_string()))?,
+            Err(_) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(Error::Unavailable(
+                    "Image processing is busy, please retry later".into(),
+                    self.retry_after_secs,
+                ));
+            }
+        };
+        spawn(permit, f).await
+    }
+
+    async fn run_background<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+        spawn(permit, f).await
+    }
+
+    fn status(&self) -> LimiterStatus {
+        LimiterStatus {
+            max_concurrent: self.max_concurrent,
+            in_use: self.max_concurrent - self.semaphore.available_permits(),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 当前的并发占用
+#[derive(Debug, Clone, Serialize)]
+pub struct LimiterStatus {
+    pub max_concurrent: usize,
+    pub in_use: usize,
+    /// 进程启动以来因排队超时被拒绝（503）的请求数
+    pub rejected: u64,
+}
+
+static LIMITER: OnceCell<Limiter> = OnceCell::new();
+
+/// 初始化并发限制（启动时调用一次）
+pub fn init(config: &TranscodeConfig) {
+    let _ = LIMITER.set(Limiter::new(config));
+}
+
+fn limiter() -> &'static Limiter {
+    LIMITER.get_or_init(|| Limiter::new(&TranscodeConfig::default()))
+}
+
+/// 在阻塞线程中执行图片处理（请求路径使用）：排队超过 queue_timeout 时返回 503，并附带 Retry-After
+pub async fn run<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    limiter().run(f).await
+}
+
+/// 在阻塞线程中执行图片处理（后台任务使用）：一直等待到有空闲的许可
+pub async fn run_background<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    limiter().run_background(f).await
+}
+
+async fn spawn<T, F>(permit: OwnedSemaphorePermit, f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    // 许可随任务一起移入阻塞线程：请求被取消时处理仍会继续，许可在处理结束后才释放
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    })
+    .await
+    .map_err(|e| Error::Internal(format!("Task join error: {}", e)))
+}
+
+pub fn status() -> LimiterStatus {
+    limiter().status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_limiter() {
+        // 使用独立的实例：全局实例可能已被其他测试按默认配置初始化
+        let limiter = Arc::new(Limiter::new(&TranscodeConfig {
+            max_concurrent: 1,
+            queue_timeout_ms: 50,
+            retry_after_secs: 3,
+            ..TranscodeConfig::default()
+        }));
+        assert_eq!(limiter.run(|| 1 + 1).await.unwrap(), 2);
+
+        // 占满许可时，请求路径排队超时后返回 503，后台任务等待
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let busy = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter
+                    .run(move || {
+                        started_tx.send(()).unwrap();
+                        release_rx.recv().unwrap();
+                    })
+                    .await
+            }
+        });
+        tokio::task::spawn_blocking(move || started_rx.recv().unwrap()).await.unwrap();
+        assert_eq!(limiter.status().in_use, 1);
+
+        match limiter.run(|| ()).await {
+            Err(Error::Unavailable(_, retry_after)) => assert_eq!(retry_after, 3),
+            other => panic!("expected 503, got {:?}", other),
+        }
+        assert_eq!(limiter.status().rejected, 1);
+
+        let background = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.run_background(|| "done").await }
+        });
+        release_tx.send(()).unwrap();
+        busy.await.unwrap().unwrap();
+        assert_eq!(background.await.unwrap().unwrap(), "done");
+        assert_eq!(limiter.status().in_use, 0);
+    }
+}
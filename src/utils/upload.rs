@@ -0,0 +1,19 @@
+use crate::{Error, Result};
+use rocket::fs::TempFile;
+use tokio::io::AsyncReadExt;
+
+/// 读取 multipart 表单中上传的文件，超过 `max_mb` 时返回 400
+pub async fn read_temp_file(file: &TempFile<'_>, max_mb: u64) -> Result<Vec<u8>> {
+    if file.len() > max_mb * 1024 * 1024 {
+        return Err(Error::BadRequest(format!("File must be at most {} MB", max_mb)));
+    }
+
+    let mut bytes = Vec::with_capacity(file.len() as usize);
+    file.open()
+        .await
+        .map_err(|e| Error::BadRequest(format!("Failed to read upload: {}", e)))?
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|e| Error::BadRequest(format!("Failed to read upload: {}", e)))?;
+    Ok(bytes)
+}
@@ -0,0 +1,108 @@
+use crate::{Error, Result};
+use ::url::Url;
+
+/// 跟踪参数（utm_* 另按前缀匹配）
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+];
+
+/// 规范化 http / https 链接，用于去重、缓存键和主机名比对
+///
+/// - 协议和主机名小写，国际化域名转为 punycode
+/// - 去掉默认端口（http:80、https:443）和片段（#...）
+/// - 百分号编码统一：非保留字符解码，其余转义使用大写十六进制
+/// - 去掉 utm_*、fbclid 等跟踪参数，其余参数保持原顺序
+pub fn canonicalize(input: &str) -> Result<String> {
+    // 主机名、端口和 IDN 由 url 解析器按 WHATWG 规则处理
+    let mut url = Url::parse(input.trim()).map_err(|_| Error::BadRequest(format!("Invalid URL: {}", input)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::BadRequest(format!("Unsupported URL scheme: {}", url.scheme())));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(Error::BadRequest("URL missing host".to_string()));
+    }
+
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+
+    let path = normalize_percent(url.path());
+    url.set_path(&path);
+
+    let query = url.query().map(|q| {
+        q.split('&')
+            .filter(|p| !p.is_empty() && !is_tracking_param(p.split('=').next().unwrap_or_default()))
+            .map(normalize_percent)
+            .collect::<Vec<_>>()
+            .join("&")
+    });
+    url.set_query(query.as_deref().filter(|q| !q.is_empty()));
+
+    Ok(url.to_string())
+}
+
+/// 规范化后的主机名（小写 punycode，去掉末尾的点），用于主机名比对，无法解析时返回 None
+pub fn canonical_host(input: &str) -> Option<String> {
+    Url::parse(input.trim())
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.trim_end_matches('.').to_string()))
+        .filter(|h| !h.is_empty())
+}
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+// 解码非保留字符（RFC 3986 2.3），其余百分号转义统一为大写
+fn normalize_percent(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+                    out.push(b as char);
+                } else {
+                    out.push('%');
+                    out.push_str(&hex.to_ascii_uppercase());
+                }
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i] as char);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(
+            canonicalize("HTTPS://Example.COM:443/a/%7euser/?utm_source=x&b=1&fbclid=y#top").unwrap(),
+            "https://example.com/a/~user/?b=1"
+        );
+        assert_eq!(canonicalize("http://example.com:8080").unwrap(), "http://example.com:8080/");
+        assert_eq!(
+            canonicalize("https://Bücher.example/%e4%b8%ad").unwrap(),
+            "https://xn--bcher-kva.example/%E4%B8%AD"
+        );
+        assert_eq!(canonicalize("https://a.com/?utm_medium=x").unwrap(), "https://a.com/");
+        assert!(canonicalize("javascript:alert(1)").is_err());
+        assert!(canonicalize("not a url").is_err());
+    }
+
+    #[test]
+    fn test_canonical_host() {
+        assert_eq!(canonical_host("https://BÜCHER.example/x").as_deref(), Some("xn--bcher-kva.example"));
+        assert_eq!(canonical_host("http://LocalHost./").as_deref(), Some("localhost"));
+        assert_eq!(canonical_host("mailto:a@b.c"), None);
+    }
+}
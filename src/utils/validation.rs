@@ -0,0 +1,294 @@
+use crate::utils::idempotency;
+use crate::{Error, Result};
+use rocket::data::{self, Data, FromData, Limits};
+use rocket::http::Status;
+use rocket::request::{local_cache, Request};
+use rocket::serde::json;
+use rocket::serde::Deserialize;
+use serde::Serialize;
+use std::ops::Deref;
+
+/// 单个字段的校验错误
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub reason: String,
+}
+
+/// 字段校验器：依次检查各字段并收集全部错误，而不是遇到第一个错误就返回
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一个字段错误
+    pub fn error(&mut self, field: &str, reason: impl Into<String>) -> &mut Self {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            reason: reason.into(),
+        });
+        self
+    }
+
+    /// 条件不成立时记录错误
+    pub fn check(&mut self, field: &str, ok: bool, reason: impl Into<String>) -> &mut Self {
+        if !ok {
+            self.error(field, reason);
+        }
+        self
+    }
+
+    /// 必填（去掉首尾空白后不能为空）
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(field, !value.trim().is_empty(), "is required")
+    }
+
+    /// 字符数在 [min, max] 之间
+    pub fn length(&mut self, field: &str, value: &str, min: usize, max: usize) -> &mut Self {
+        let len = value.chars().count();
+        if len == 0 && min > 0 {
+            self.error(field, "is required")
+        } else {
+            self.check(
+                field,
+                (min..=max).contains(&len),
+                format!("must be between {} and {} characters", min, max),
+            )
+        }
+    }
+
+    /// 数值在 [min, max] 之间
+    pub fn range(&mut self, field: &str, value: u64, min: u64, max: u64) -> &mut Self {
+        self.check(
+            field,
+            (min..=max).contains(&value),
+            format!("must be between {} and {}", min, max),
+        )
+    }
+
+    /// 列表最多 max 项
+    pub fn count<T>(&mut self, field: &str, items: &[T], max: usize) -> &mut Self {
+        self.check(field, items.len() <= max, format!("must contain at most {} items", max))
+    }
+
+    /// 取值在允许列表中
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) -> &mut Self {
+        self.check(
+            field,
+            allowed.contains(&value),
+            format!("must be one of {}", allowed.join(", ")),
+        )
+    }
+
+    /// 邮箱格式
+    pub fn email(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(field, is_email(value.trim()), "must be a valid email address")
+    }
+
+    /// http / https 链接
+    pub fn url(&mut self, field: &str, value: &str) -> &mut Self {
+        self.check(field, is_http_url(value.trim()), "must be a valid http(s) URL")
+    }
+
+    /// 有错误时返回 422 与全部字段错误
+    pub fn finish(self) -> Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(self.errors))
+        }
+    }
+}
+
+/// 请求结构体的字段校验规则
+pub trait Validate {
+    /// 检查各字段，错误记录到校验器中
+    fn check(&self, v: &mut Validator);
+
+    /// 校验全部字段
+    fn validate(&self) -> Result<()> {
+        let mut v = Validator::new();
+        self.check(&mut v);
+        v.finish()
+    }
+}
+
+/// 基础邮箱格式检查（RFC 5321 长度限制，不含空白，域名带点）
+pub fn is_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local.len() <= 64
+        && !domain.is_empty()
+        && domain.len() <= 255
+        && domain.contains('.')
+        && !domain.contains('@')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.chars().any(char::is_whitespace)
+}
+
+/// 带主机名的 http / https 链接
+pub fn is_http_url(value: &str) -> bool {
+    url::Url::parse(value)
+        .map(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some_and(|h| !h.is_empty()))
+        .unwrap_or(false)
+}
+
+// 数据守卫失败时的字段错误，供 422 错误处理读取
+struct RejectedFields(Vec<FieldError>);
+
+/// 请求体守卫：解析 JSON 并按 Validate 规则校验
+///
+/// JSON 格式错误或字段校验失败时返回 422，响应中列出所有字段错误
+#[derive(Debug)]
+pub struct Valid<T>(pub T);
+
+impl<T> Valid<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Valid<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: Deserialize<'r> + Validate> FromData<'r> for Valid<T> {
+    type Error = Error;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let limit = req.limits().get("json").unwrap_or(Limits::JSON);
+        let body = match data.open(limit).into_string().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return data::Outcome::Error((Status::PayloadTooLarge, Error::BadRequest("data limit exceeded".into())));
+            }
+            Err(e) => return data::Outcome::Error((Status::BadRequest, Error::BadRequest(e.to_string()))),
+        };
+        // 请求体较大时 Idempotency 守卫无法预读完整内容，在这里按完整请求体检查幂等键
+        if let Err((status, reason)) = idempotency::check_body(req, body.as_bytes()).await {
+            return data::Outcome::Error((status, Error::BadRequest(reason.into())));
+        }
+
+        let errors = match json::from_str::<T>(local_cache!(req, body)) {
+            Ok(value) => match value.validate() {
+                Ok(()) => return data::Outcome::Success(Valid(value)),
+                Err(Error::Validation(errors)) => errors,
+                Err(e) => return data::Outcome::Error((Status::BadRequest, e)),
+            },
+            Err(e) => vec![parse_error(&e)],
+        };
+        req.local_cache(|| RejectedFields(errors.clone()));
+        data::Outcome::Error((Status::UnprocessableEntity, Error::Validation(errors)))
+    }
+}
+
+// 把 serde 的解析错误转换为字段错误（缺少字段、类型不符等）
+fn parse_error(e: &serde_json::Error) -> FieldError {
+    let message = e.to_string();
+    let field = message
+        .split('`')
+        .nth(1)
+        .filter(|_| message.starts_with("missing field") || message.starts_with("unknown field"))
+        .unwrap_or("body");
+    let reason = match message.find(" at line ") {
+        Some(i) => &message[..i],
+        None => &message,
+    };
+    FieldError {
+        field: field.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// 422 错误处理使用：取出数据守卫记录的字段错误
+pub fn rejected_fields(req: &Request<'_>) -> Vec<FieldError> {
+    req.local_cache(|| RejectedFields(Vec::new())).0.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::local::asynchronous::Client;
+    use rocket::{post, routes};
+
+    #[derive(Deserialize)]
+    struct Submission {
+        name: String,
+        email: String,
+        url: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    impl Validate for Submission {
+        fn check(&self, v: &mut Validator) {
+            v.length("name", &self.name, 1, 20)
+                .email("email", &self.email)
+                .url("url", &self.url)
+                .count("tags", &self.tags, 2);
+        }
+    }
+
+    #[post("/submit", data = "<data>")]
+    fn submit(data: Valid<Submission>) -> String {
+        data.name.clone()
+    }
+
+    #[test]
+    fn test_formats() {
+        assert!(is_email("a@example.com"));
+        assert!(!is_email("a@b"));
+        assert!(!is_email("a b@example.com"));
+        assert!(!is_email("@example.com"));
+        assert!(is_http_url("https://example.com/path"));
+        assert!(!is_http_url("javascript:alert(1)"));
+        assert!(!is_http_url("example.com"));
+    }
+
+    #[rocket::async_test]
+    async fn test_field_errors() {
+        let rocket = rocket::build()
+            .mount("/", routes![submit])
+            .register("/", crate::utils::errors::catchers());
+        let client = Client::untracked(rocket).await.unwrap();
+
+        let ok = client
+            .post("/submit")
+            .body(r#"{"name":"a","email":"a@example.com","url":"https://example.com"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(ok.status(), Status::Ok);
+
+        let invalid = client
+            .post("/submit")
+            .body(r#"{"name":"","email":"x","url":"ftp://x","tags":["a","b","c"]}"#)
+            .dispatch()
+            .await;
+        assert_eq!(invalid.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value = invalid.into_json().await.unwrap();
+        let fields: Vec<&str> = body["data"]["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|e| e["field"].as_str())
+            .collect();
+        assert_eq!(fields, ["name", "email", "url", "tags"]);
+
+        let missing = client.post("/submit").body(r#"{"name":"a"}"#).dispatch().await;
+        let body: serde_json::Value = missing.into_json().await.unwrap();
+        assert_eq!(body["data"]["errors"][0]["field"], "email");
+    }
+}